@@ -11,6 +11,10 @@ use std::sync::Arc;
 use std::thread;
 
 use anyhow::Context;
+use database::sqlite::archive_cursor::ArchiveCursor;
+use database::sqlite::cold_storage::ColdSegment;
+use database::sqlite::cold_storage::ColdStorageConfig;
+use database::sqlite::sharded_helper::ShardedSqliteHelper;
 use database::sqlite::sqlite_helper;
 use database::sqlite::sqlite_helper::SqliteHelper;
 use database::sqlite::sqlite_helper::SqliteHelperConfig;
@@ -18,9 +22,11 @@ use node::bls::envelope::BLSSignedEnvelope;
 use node::bls::envelope::Envelope;
 use node::bls::GoshBLS;
 use node::types::AckiNackiBlock;
+use node::types::ThreadIdentifier;
 use parking_lot::Mutex;
 use rusqlite::Connection;
 use transport_layer::msquic::MsQuicTransport;
+use transport_layer::server::SubscriptionFilter;
 use transport_layer::NetConnection;
 use transport_layer::NetCredential;
 use transport_layer::NetTransport;
@@ -32,12 +38,15 @@ use crate::metrics::Metrics;
 pub enum WorkerCommand {
     Data(Vec<u8>),
     RotateDb,
+    MoveToColdStorage(ColdStorageConfig),
     Shutdown,
 }
 
 pub struct BlockSubscriber {
     db_file: PathBuf,
+    shard_by_thread: bool,
     socket_addr: SocketAddr,
+    filter: SubscriptionFilter,
     event_pub: Sender<Event>,
     bp_data_tx: Sender<(String, Vec<String>)>,
     // archive: Arc<dyn DocumentsDb>,
@@ -50,12 +59,14 @@ impl BlockSubscriber {
     /// Panics if the database file cannot be opened or created.
     pub fn new(
         db_file: PathBuf,
+        shard_by_thread: bool,
         socket_addr: SocketAddr,
+        filter: SubscriptionFilter,
         event_pub: Sender<Event>,
         bp_data_tx: Sender<(String, Vec<String>)>,
         // archive: Arc<dyn DocumentsDb>,
     ) -> Self {
-        Self { db_file, socket_addr, event_pub, bp_data_tx /* , archive */ }
+        Self { db_file, shard_by_thread, socket_addr, filter, event_pub, bp_data_tx }
     }
 
     pub async fn run(
@@ -64,15 +75,18 @@ impl BlockSubscriber {
         cmd_tx: mpsc::Sender<WorkerCommand>,
         cmd_rx: mpsc::Receiver<WorkerCommand>,
     ) -> anyhow::Result<()> {
-        let listener_handle = listener(self.socket_addr, cmd_tx);
+        let listener_handle = listener(self.socket_addr, self.filter.clone(), cmd_tx);
 
         let db_file = self.db_file.clone();
+        let shard_by_thread = self.shard_by_thread;
         let events_pub = self.event_pub.clone();
         let bp_data_tx = self.bp_data_tx.clone();
         let block_sub_handle = tokio::task::spawn_blocking(move || {
             match thread::Builder::new()
                 .name("block-subscriber".to_string())
-                .spawn(|| worker(db_file, cmd_rx, events_pub, bp_data_tx, metrics))
+                .spawn(move || {
+                    worker(db_file, shard_by_thread, cmd_rx, events_pub, bp_data_tx, metrics)
+                })
                 .expect("spawn block-subscriber worker")
                 .join()
             {
@@ -93,8 +107,65 @@ impl BlockSubscriber {
     }
 }
 
+// Archive written by the worker: a single database or per-thread shards
+enum Archive {
+    Single(Arc<Mutex<SqliteHelper>>),
+    Sharded(ShardedSqliteHelper),
+}
+
+impl Archive {
+    fn for_thread(
+        &mut self,
+        thread_id: &ThreadIdentifier,
+    ) -> anyhow::Result<Arc<Mutex<SqliteHelper>>> {
+        match self {
+            Archive::Single(sqlite_helper) => Ok(sqlite_helper.clone()),
+            Archive::Sharded(sharded) => sharded.shard(&format!("{thread_id:x}")),
+        }
+    }
+
+    fn rotate(&mut self) -> anyhow::Result<()> {
+        match self {
+            Archive::Single(sqlite_helper) => sqlite_helper.lock().rotate_db_file().map(|_| ()),
+            Archive::Sharded(sharded) => sharded.rotate_db_files(),
+        }
+    }
+
+    fn move_to_cold_storage(&self, config: &ColdStorageConfig) -> anyhow::Result<Vec<ColdSegment>> {
+        match self {
+            Archive::Single(sqlite_helper) => {
+                Ok(sqlite_helper.lock().move_to_cold_storage(config)?.into_iter().collect())
+            }
+            Archive::Sharded(sharded) => sharded.move_to_cold_storage(config),
+        }
+    }
+
+    // Last archived block of every thread, the archive resumes after them
+    fn cursors(&self) -> anyhow::Result<HashMap<String, ArchiveCursor>> {
+        let helpers = match self {
+            Archive::Single(sqlite_helper) => vec![sqlite_helper.clone()],
+            Archive::Sharded(sharded) => sharded.shards(),
+        };
+        let mut cursors = HashMap::new();
+        for helper in helpers {
+            for cursor in helper.lock().archive_cursors()? {
+                cursors.insert(cursor.thread_id.clone(), cursor);
+            }
+        }
+        Ok(cursors)
+    }
+
+    fn shutdown(&mut self) -> anyhow::Result<()> {
+        match self {
+            Archive::Single(sqlite_helper) => sqlite_helper.lock().shutdown(),
+            Archive::Sharded(sharded) => sharded.shutdown(),
+        }
+    }
+}
+
 fn worker(
     _db_file: impl AsRef<Path>,
+    shard_by_thread: bool,
     rx: mpsc::Receiver<WorkerCommand>,
     event_pub: mpsc::Sender<Event>,
     bp_data_tx: mpsc::Sender<(String, Vec<String>)>,
@@ -102,11 +173,26 @@ fn worker(
 ) -> anyhow::Result<()> {
     let data_dir =
         std::env::var("SQLITE_PATH").unwrap_or(sqlite_helper::SQLITE_DATA_DIR.to_string());
-    let sqlite_helper_config =
-        SqliteHelperConfig::new(data_dir.into(), Some("bm-archive.db".into()));
+    let mut archive = if shard_by_thread {
+        tracing::info!("Archive is sharded by thread");
+        Archive::Sharded(ShardedSqliteHelper::new(data_dir.into())?)
+    } else {
+        let sqlite_helper_config =
+            SqliteHelperConfig::new(data_dir.into(), Some("bm-archive.db".into()));
+        let (sqlite_helper, _writer_join_handle) = SqliteHelper::from_config(sqlite_helper_config)?;
+        Archive::Single(Arc::new(Mutex::new(sqlite_helper)))
+    };
 
-    let (sqlite_helper, _writer_join_handle) = SqliteHelper::from_config(sqlite_helper_config)?;
-    let sqlite_helper = Arc::new(Mutex::new(sqlite_helper));
+    // Checked against the first block of each thread after the restart
+    let mut resume_cursors = archive.cursors()?;
+    for cursor in resume_cursors.values() {
+        tracing::info!(
+            "Archive of thread {} resumes after block {} (seq_no {})",
+            cursor.thread_id,
+            cursor.block_id,
+            cursor.seq_no
+        );
+    }
 
     let mut transaction_traces = HashMap::new();
     let shard_state = Arc::new(ShardStateUnsplit::default());
@@ -137,13 +223,21 @@ fn worker(
                     }
                 }
 
-                let result = node::database::serialize_block::reflect_block_in_db(
-                    sqlite_helper.clone(),
-                    envelope,
-                    Some(raw_block),
-                    shard_state.clone(),
-                    &mut transaction_traces,
-                );
+                if let Some(cursor) = resume_cursors.remove(&format!("{thread_id:x}")) {
+                    if let Ok(block_info) = envelope.data().tvm_block().read_info() {
+                        check_resumed_block(&cursor, block_info.seq_no());
+                    }
+                }
+
+                let result = archive.for_thread(&thread_id).and_then(|sqlite_helper| {
+                    node::database::serialize_block::reflect_block_in_db(
+                        sqlite_helper,
+                        envelope,
+                        Some(raw_block),
+                        shard_state.clone(),
+                        &mut transaction_traces,
+                    )
+                });
 
                 match result {
                     Ok(_) => tracing::debug!("block stored"),
@@ -155,16 +249,27 @@ fn worker(
             Ok(WorkerCommand::RotateDb) => {
                 tracing::info!("Rotating SQLite DB...");
 
-                let mut guarded = sqlite_helper.lock();
-                match guarded.rotate_db_file() {
+                match archive.rotate() {
                     Ok(_) => tracing::info!("Database rotated successfully."),
                     Err(e) => tracing::error!("Failed to rotate database: {e}"),
                 }
             }
+            Ok(WorkerCommand::MoveToColdStorage(config)) => {
+                match archive.move_to_cold_storage(&config) {
+                    Ok(segments) if segments.is_empty() => {
+                        tracing::debug!("No archive data to move to cold storage")
+                    }
+                    Ok(segments) => {
+                        for segment in segments {
+                            tracing::info!("Cold storage segment stored: {}", segment.uri)
+                        }
+                    }
+                    Err(e) => tracing::error!("Failed to move archive data to cold storage: {e}"),
+                }
+            }
             Ok(WorkerCommand::Shutdown) => {
                 tracing::info!("Shutdown by SIGTERM...");
-                let mut guarded = sqlite_helper.lock();
-                match guarded.shutdown() {
+                match archive.shutdown() {
                     Ok(_) => tracing::info!("Database is ready to shutdown."),
                     Err(e) => tracing::error!("Failed to create checkpoint: {e}"),
                 }
@@ -174,7 +279,30 @@ fn worker(
     }
 }
 
-async fn listener(socket_addr: SocketAddr, tx: mpsc::Sender<WorkerCommand>) -> anyhow::Result<()> {
+// Blocks at or before the cursor are written again without duplicates, blocks
+// between the cursor and the first received one are missing
+fn check_resumed_block(cursor: &ArchiveCursor, seq_no: u32) {
+    if seq_no > cursor.seq_no.saturating_add(1) {
+        tracing::warn!(
+            "Archive of thread {} misses blocks with seq_no {}..{}",
+            cursor.thread_id,
+            cursor.seq_no + 1,
+            seq_no
+        );
+    } else if seq_no <= cursor.seq_no {
+        tracing::info!(
+            "Block {seq_no} of thread {} is already archived and is rewritten idempotently",
+            cursor.thread_id
+        );
+    }
+}
+
+async fn listener(
+    socket_addr: SocketAddr,
+    filter: SubscriptionFilter,
+    tx: mpsc::Sender<WorkerCommand>,
+) -> anyhow::Result<()> {
+    let filter_message = bincode::serialize(&filter)?;
     loop {
         let transport = MsQuicTransport::new();
         match transport
@@ -185,23 +313,34 @@ async fn listener(socket_addr: SocketAddr, tx: mpsc::Sender<WorkerCommand>) -> a
             )
             .await
         {
-            Ok(conn) => loop {
-                tracing::info!("Wait for incoming stream...");
-                match conn.recv().await {
-                    Ok((message, duration)) => {
-                        tracing::info!(
-                            duration = duration.as_millis(),
-                            "Received: {} bytes",
-                            message.len()
-                        );
-                        tx.send(WorkerCommand::Data(message)).expect("Receiver always exists");
+            Ok(conn) => {
+                // The server keeps no filter between connections
+                if !filter.accounts.is_empty() {
+                    if let Err(error) = conn.send(&filter_message).await {
+                        tracing::error!("Can't send subscription filter: {error}");
+                        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                        continue;
                     }
-                    Err(error) => {
-                        tracing::error!("Error receiving a message: {error}");
-                        break;
+                    tracing::info!("Subscribed to {} accounts", filter.accounts.len());
+                }
+                loop {
+                    tracing::info!("Wait for incoming stream...");
+                    match conn.recv().await {
+                        Ok((message, duration)) => {
+                            tracing::info!(
+                                duration = duration.as_millis(),
+                                "Received: {} bytes",
+                                message.len()
+                            );
+                            tx.send(WorkerCommand::Data(message)).expect("Receiver always exists");
+                        }
+                        Err(error) => {
+                            tracing::error!("Error receiving a message: {error}");
+                            break;
+                        }
                     }
                 }
-            },
+            }
             Err(error) => {
                 tracing::error!("Can't connect to  {socket_addr}: {error}");
                 tokio::time::sleep(std::time::Duration::from_secs(1)).await;
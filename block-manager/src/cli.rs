@@ -44,4 +44,40 @@ pub struct Args {
     /// File path for sqlite
     #[arg(long, env)]
     pub sqlite_path: PathBuf,
+
+    /// Store the archive in per-thread database files
+    /// (`bm-archive-thread-<thread_id>.db`) written in parallel. GraphQL
+    /// server must be started with `--shards-dir` to read them
+    #[arg(long, env)]
+    pub sqlite_shard_by_thread: bool,
+
+    /// Directory synchronized with the cold storage bucket. Archive blocks
+    /// older than the retention period are moved there with their
+    /// transactions and messages. Cold storage is disabled if not set
+    #[arg(long, env)]
+    pub cold_storage_dir: Option<PathBuf>,
+
+    /// URI of the cold storage bucket returned to GraphQL clients as a
+    /// retrieval hint (default: cold storage dir)
+    #[arg(long, env)]
+    pub cold_storage_uri: Option<String>,
+
+    /// Age of archive blocks in seconds after which they are moved to cold
+    /// storage
+    #[arg(long, env, default_value_t = 30 * 24 * 60 * 60)]
+    pub cold_storage_retention_secs: u64,
+
+    /// Interval between cold storage segments in seconds
+    #[arg(long, env, default_value_t = 60)]
+    pub cold_storage_interval_secs: u64,
+
+    /// YAML file with OpenTelemetry exporters config. Unset fields are taken
+    /// from the `OTEL_*` environment variables
+    #[arg(long, env)]
+    pub telemetry_config: Option<PathBuf>,
+
+    /// Comma separated account addresses. If set, the node sends only the
+    /// blocks with transactions of these accounts
+    #[arg(long, env, value_delimiter = ',')]
+    pub accounts_filter: Vec<String>,
 }
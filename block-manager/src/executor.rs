@@ -6,7 +6,9 @@ use std::net::ToSocketAddrs;
 use std::sync::mpsc;
 use std::sync::mpsc::channel;
 use std::sync::Arc;
+use std::time::Duration;
 
+use database::sqlite::cold_storage::ColdStorageConfig;
 use message_router::message_router::MessageRouter;
 use message_router::message_router::MessageRouterConfig;
 use message_router::read_keys_from_file;
@@ -15,8 +17,8 @@ use parking_lot::Mutex;
 use salvo::conn::TcpListener;
 use salvo::Listener;
 use salvo::Server;
-use telemetry_utils::get_metrics_endpoint;
-use telemetry_utils::init_meter_provider;
+use telemetry_config::TelemetryConfig;
+use transport_layer::server::SubscriptionFilter;
 
 use crate::block_subscriber;
 use crate::block_subscriber::WorkerCommand;
@@ -26,20 +28,33 @@ use crate::events;
 use crate::metrics::Metrics;
 use crate::rest_api_routes::rest_api_router;
 
+const COLD_STORAGE_MAX_BLOCKS_PER_SEGMENT: usize = 10_000;
+
 pub async fn execute(
     args: Args,
     cmd_tx: mpsc::Sender<WorkerCommand>,
     cmd_rx: mpsc::Receiver<WorkerCommand>,
 ) -> anyhow::Result<()> {
     // Init metrics
-    let metrics = if let Some(endpoint) = get_metrics_endpoint() {
-        tracing::info!("Using OTLP metrics endpoint: {endpoint}");
-        opentelemetry::global::set_meter_provider(init_meter_provider());
-        Some(Metrics::new(&opentelemetry::global::meter("bm")))
-    } else {
-        tracing::info!("No OTEL exporter endpoint found, metrics not collected.");
-        None
-    };
+    let telemetry = args
+        .telemetry_config
+        .as_ref()
+        .map(TelemetryConfig::from_file)
+        .transpose()?
+        .unwrap_or_default()
+        .with_env_defaults();
+    let metrics =
+        if let Some((meter_provider, _)) = telemetry.meter_provider("acki-nacki-block-manager")? {
+            tracing::info!(
+                "Using OTLP metrics endpoint: {}",
+                telemetry.metrics_endpoint().unwrap_or_default()
+            );
+            opentelemetry::global::set_meter_provider(meter_provider);
+            Some(Metrics::new(&opentelemetry::global::meter("bm")))
+        } else {
+            tracing::info!("No OTEL exporter endpoint found, metrics not collected.");
+            None
+        };
 
     // event bus
     let (event_pub, _event_sub) = channel::<events::Event>();
@@ -85,10 +100,34 @@ pub async fn execute(
         Server::new(acceptor).serve(rest_api_router(message_router, default_bp)).await;
     });
 
+    // cold storage
+    if let Some(target_dir) = args.cold_storage_dir {
+        let config = ColdStorageConfig {
+            uri_prefix: args
+                .cold_storage_uri
+                .unwrap_or_else(|| target_dir.to_string_lossy().to_string()),
+            target_dir,
+            retention: Duration::from_secs(args.cold_storage_retention_secs),
+            max_blocks_per_segment: COLD_STORAGE_MAX_BLOCKS_PER_SEGMENT,
+        };
+        let cmd_tx = cmd_tx.clone();
+        let interval = Duration::from_secs(args.cold_storage_interval_secs);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                if cmd_tx.send(WorkerCommand::MoveToColdStorage(config.clone())).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
     // block subscriber
     let block_subscriber = block_subscriber::BlockSubscriber::new(
         args.sqlite_path,
+        args.sqlite_shard_by_thread,
         socket_addr,
+        SubscriptionFilter::new(args.accounts_filter),
         event_pub.clone(),
         bp_data_tx,
     );
@@ -19,6 +19,14 @@ pub struct ChitchatConfig {
     pub chitchat_id: ChitchatId,
     pub cluster_id: String,
     pub gossip_interval: Duration,
+    /// Number of live nodes picked for gossip each round.
+    pub gossip_fanout: usize,
+    /// Max payload size of the gossip datagrams. None or values above the
+    /// transport limit fall back to the transport limit.
+    pub max_gossip_payload_size: Option<usize>,
+    /// Scaling of the gossip settings with the cluster size. None keeps them
+    /// fixed.
+    pub adaptive_gossip: Option<AdaptiveGossipConfig>,
     pub listen_addr: SocketAddr,
     pub seed_nodes: Vec<String>,
     pub failure_detector_config: FailureDetectorConfig,
@@ -40,7 +48,56 @@ pub struct ChitchatConfig {
     pub extra_liveness_predicate: Option<ExtraLivenessPredicate>,
 }
 
+/// Above `reference_cluster_size` live nodes the gossip interval and the
+/// payload size grow and the fanout shrinks proportionally to the cluster
+/// size, so big clusters exchange fewer but larger messages.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AdaptiveGossipConfig {
+    pub reference_cluster_size: usize,
+    pub max_gossip_interval: Duration,
+}
+
+/// Gossip settings applied for the current cluster size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GossipParams {
+    pub interval: Duration,
+    pub fanout: usize,
+    pub max_payload_size: usize,
+}
+
 impl ChitchatConfig {
+    pub fn gossip_params(
+        &self,
+        cluster_size: usize,
+        transport_payload_size: usize,
+    ) -> GossipParams {
+        let max_payload_size = self
+            .max_gossip_payload_size
+            .map_or(transport_payload_size, |size| size.min(transport_payload_size));
+        let params = GossipParams {
+            interval: self.gossip_interval,
+            fanout: self.gossip_fanout.max(1),
+            max_payload_size,
+        };
+        let Some(adaptive) = &self.adaptive_gossip else {
+            return params;
+        };
+        let reference = adaptive.reference_cluster_size.max(1);
+        if cluster_size <= reference {
+            return params;
+        }
+        let scale = cluster_size as f64 / reference as f64;
+        GossipParams {
+            interval: params
+                .interval
+                .mul_f64(scale)
+                .min(adaptive.max_gossip_interval.max(params.interval)),
+            fanout: (params.fanout * reference).div_ceil(cluster_size).max(1),
+            max_payload_size: (params.max_payload_size.saturating_mul(cluster_size) / reference)
+                .min(transport_payload_size),
+        }
+    }
+
     #[cfg(test)]
     pub fn for_test(port: u16) -> Self {
         let chitchat_id = ChitchatId::for_local_test(port);
@@ -49,6 +106,9 @@ impl ChitchatConfig {
             chitchat_id,
             cluster_id: "default-cluster".to_string(),
             gossip_interval: Duration::from_millis(50),
+            gossip_fanout: 3,
+            max_gossip_payload_size: None,
+            adaptive_gossip: None,
             listen_addr,
             seed_nodes: Vec::new(),
             failure_detector_config: Default::default(),
@@ -68,6 +128,9 @@ impl Default for ChitchatConfig {
             chitchat_id,
             cluster_id: "default-cluster".to_string(),
             gossip_interval: Duration::from_millis(1_000),
+            gossip_fanout: 3,
+            max_gossip_payload_size: None,
+            adaptive_gossip: None,
             listen_addr,
             seed_nodes: Vec::new(),
             failure_detector_config: Default::default(),
@@ -77,3 +140,40 @@ impl Default for ChitchatConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adaptive_gossip_params() {
+        let mut config = ChitchatConfig::for_test(10_001);
+        config.gossip_interval = Duration::from_millis(500);
+        config.max_gossip_payload_size = Some(10_000);
+        let fixed = GossipParams {
+            interval: Duration::from_millis(500),
+            fanout: 3,
+            max_payload_size: 10_000,
+        };
+        assert_eq!(config.gossip_params(200, 65_507), fixed);
+        assert_eq!(config.gossip_params(200, 1_400).max_payload_size, 1_400);
+
+        config.adaptive_gossip = Some(AdaptiveGossipConfig {
+            reference_cluster_size: 25,
+            max_gossip_interval: Duration::from_secs(2),
+        });
+        assert_eq!(config.gossip_params(20, 65_507), fixed);
+        assert_eq!(
+            config.gossip_params(50, 65_507),
+            GossipParams {
+                interval: Duration::from_millis(1_000),
+                fanout: 2,
+                max_payload_size: 20_000,
+            }
+        );
+        assert_eq!(
+            config.gossip_params(200, 65_507),
+            GossipParams { interval: Duration::from_secs(2), fanout: 1, max_payload_size: 65_507 }
+        );
+    }
+}
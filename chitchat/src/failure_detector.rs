@@ -52,6 +52,13 @@ impl FailureDetector {
         self.get_or_create_sampling_window(chitchat_id).report_heartbeat();
     }
 
+    /// Reports a node restored from a saved cluster state. The node is live
+    /// until it misses heartbeats for about `phi_threshold` initial intervals.
+    pub(crate) fn report_restored(&mut self, chitchat_id: &ChitchatId) {
+        let initial_interval = self.config.initial_interval;
+        self.get_or_create_sampling_window(chitchat_id).report_restored(initial_interval);
+    }
+
     /// Marks the node as dead or alive based on the current phi value.
     pub fn update_node_liveness(&mut self, chitchat_id: &ChitchatId) {
         let phi_opt = self.phi(chitchat_id);
@@ -225,6 +232,12 @@ impl SamplingWindow {
         self.last_heartbeat = Some(now);
     }
 
+    /// Reports a heartbeat of a restored node, which has no real intervals yet.
+    pub fn report_restored(&mut self, interval: Duration) {
+        self.intervals.append(interval.as_secs_f64());
+        self.last_heartbeat = Some(Instant::now());
+    }
+
     /// Forget about all previous intervals.
     pub fn reset(&mut self) {
         self.intervals.clear();
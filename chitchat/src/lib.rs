@@ -31,7 +31,9 @@ use tracing::error;
 use tracing::info;
 use tracing::warn;
 
+pub use self::configuration::AdaptiveGossipConfig;
 pub use self::configuration::ChitchatConfig;
+pub use self::configuration::GossipParams;
 pub use self::state::ClusterStateSnapshot;
 pub use self::state::NodeState;
 use crate::digest::Digest;
@@ -150,7 +152,11 @@ impl Chitchat {
                     self.scheduled_for_deletion_nodes().collect();
                 let self_digest = self.compute_digest(&scheduled_for_deletion);
 
-                let delta_mtu = self.max_datagram_payload_size - 1 - self_digest.serialized_len();
+                // The digest of a big cluster may not fit into a small payload
+                let delta_mtu = self
+                    .gossip_params()
+                    .max_payload_size
+                    .saturating_sub(1 + self_digest.serialized_len());
                 let delta = self.cluster_state.compute_partial_delta_respecting_mtu(
                     &digest,
                     delta_mtu,
@@ -165,7 +171,7 @@ impl Chitchat {
                     self.scheduled_for_deletion_nodes().collect::<HashSet<_>>();
                 let delta = self.cluster_state.compute_partial_delta_respecting_mtu(
                     &digest,
-                    self.max_datagram_payload_size - 1,
+                    self.gossip_params().max_payload_size.saturating_sub(1),
                     &scheduled_for_deletion,
                 );
                 Some(ChitchatMessage::Ack { delta })
@@ -252,6 +258,11 @@ impl Chitchat {
         self.cluster_state.node_state(chitchat_id)
     }
 
+    /// Gossip settings for the current number of live nodes.
+    pub fn gossip_params(&self) -> GossipParams {
+        self.config.gossip_params(self.live_nodes().count(), self.max_datagram_payload_size)
+    }
+
     pub fn self_node_state(&mut self) -> &mut NodeState {
         self.cluster_state.node_state_mut(&self.config.chitchat_id)
     }
@@ -348,6 +359,42 @@ impl Chitchat {
         node_state.set_last_gc_version(last_gc_version);
     }
 
+    /// Restores the states of other nodes saved before a restart, so the
+    /// peers are known before the first gossip rounds complete.
+    ///
+    /// Restored nodes are live until they miss heartbeats for the initial
+    /// interval of the failure detector. States of this node's previous
+    /// incarnation (same gossip address) are skipped.
+    ///
+    /// Returns the number of restored nodes.
+    pub fn restore_node_states(&mut self, node_states: Vec<NodeState>) -> usize {
+        let self_addr = self.self_chitchat_id().gossip_advertise_addr;
+        let mut restored = 0;
+        for node_state in node_states {
+            let chitchat_id = node_state.chitchat_id().clone();
+            if chitchat_id.gossip_advertise_addr == self_addr
+                || self.cluster_state.node_state(&chitchat_id).is_some()
+            {
+                continue;
+            }
+            self.reset_node_state(
+                &chitchat_id,
+                node_state
+                    .key_values_including_deleted()
+                    .map(|(key, value)| (key.to_string(), value.clone())),
+                node_state.max_version(),
+                node_state.last_gc_version(),
+            );
+            self.cluster_state
+                .node_state_mut(&chitchat_id)
+                .try_set_heartbeat(node_state.heartbeat());
+            self.failure_detector.report_restored(&chitchat_id);
+            restored += 1;
+        }
+        self.update_nodes_liveness();
+        restored
+    }
+
     pub(crate) fn update_self_heartbeat(&mut self) {
         self.self_node_state().inc_heartbeat();
     }
@@ -471,6 +518,9 @@ mod tests {
             chitchat_id: chitchat_id.clone(),
             cluster_id: "default-cluster".to_string(),
             gossip_interval: Duration::from_millis(100),
+            gossip_fanout: 3,
+            max_gossip_payload_size: None,
+            adaptive_gossip: None,
             listen_addr: chitchat_id.gossip_advertise_addr,
             seed_nodes: seeds.to_vec(),
             failure_detector_config: FailureDetectorConfig {
@@ -651,6 +701,9 @@ mod tests {
             chitchat_id: chitchat_id.clone(),
             cluster_id: "default-cluster".to_string(),
             gossip_interval: Duration::from_millis(100),
+            gossip_fanout: 3,
+            max_gossip_payload_size: None,
+            adaptive_gossip: None,
             listen_addr: chitchat_id.gossip_advertise_addr,
             seed_nodes: vec![chitchat_ids[0].gossip_advertise_addr.to_string()],
             failure_detector_config: FailureDetectorConfig {
@@ -1149,4 +1202,46 @@ mod tests {
         assert_eq!(node_state.get("toto"), Some("titi"));
         assert_eq!(node_state.max_version(), 3);
     }
+
+    #[test]
+    fn test_restore_node_states() {
+        let empty_seeds = watch::channel(Default::default()).1;
+        let mut node1 = Chitchat::with_chitchat_id_and_seeds(
+            ChitchatConfig::for_test(10_001),
+            empty_seeds.clone(),
+            vec![("key1".to_string(), "1".to_string())],
+            MAX_UDP_DATAGRAM_PAYLOAD_SIZE,
+        );
+        let mut node2 = Chitchat::with_chitchat_id_and_seeds(
+            ChitchatConfig::for_test(10_002),
+            empty_seeds.clone(),
+            vec![("key2".to_string(), "2".to_string())],
+            MAX_UDP_DATAGRAM_PAYLOAD_SIZE,
+        );
+        run_chitchat_handshake(&mut node1, &mut node2);
+        let saved = node2.node_states().values().cloned().collect::<Vec<_>>();
+        assert_eq!(saved.len(), 2);
+
+        // The restarted node1 has a new generation
+        let mut config = ChitchatConfig::for_test(10_001);
+        config.chitchat_id.generation_id += 1;
+        let mut restarted = Chitchat::with_chitchat_id_and_seeds(
+            config,
+            empty_seeds,
+            Vec::new(),
+            MAX_UDP_DATAGRAM_PAYLOAD_SIZE,
+        );
+        assert_eq!(restarted.restore_node_states(saved), 1);
+
+        let node2_id = node2.self_chitchat_id().clone();
+        let live_nodes = restarted.live_nodes().cloned().collect::<HashSet<_>>();
+        assert_eq!(
+            live_nodes,
+            HashSet::from([restarted.self_chitchat_id().clone(), node2_id.clone()])
+        );
+        assert!(restarted.live_nodes_watcher().borrow().contains_key(&node2_id));
+        let node_state = restarted.node_state(&node2_id).unwrap();
+        assert_eq!(node_state.get("key2"), Some("2"));
+        assert_eq!(node_state.heartbeat(), node2.self_node_state().heartbeat());
+    }
 }
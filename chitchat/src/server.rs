@@ -23,9 +23,6 @@ use crate::Chitchat;
 use crate::ChitchatConfig;
 use crate::ChitchatId;
 
-/// Number of nodes picked for random gossip.
-const GOSSIP_COUNT: usize = 3;
-
 /// UDP Chitchat server handler.
 ///
 /// It is necessary to hold (and not drop) the handler
@@ -234,8 +231,8 @@ impl Server {
 
     /// Listen for new Chitchat messages.
     async fn run(&mut self) -> anyhow::Result<()> {
-        let gossip_interval = { self.chitchat.lock().config.gossip_interval };
-        let mut gossip_interval = time::interval(gossip_interval);
+        let mut interval = { self.chitchat.lock().gossip_params().interval };
+        let mut gossip_interval = time::interval(interval);
         loop {
             tokio::select! {
                 result = self.socket.recv() => match result {
@@ -260,7 +257,15 @@ impl Server {
                 },
                 _ = gossip_interval.tick() => {
                     info!("gossip");
-                    self.gossip_multiple().await
+                    self.gossip_multiple().await;
+                    // The interval is adapted to the cluster size
+                    let new_interval = { self.chitchat.lock().gossip_params().interval };
+                    if new_interval != interval {
+                        info!(?new_interval, "gossip interval changed");
+                        interval = new_interval;
+                        let start = time::Instant::now() + interval;
+                        gossip_interval = time::interval_at(start, interval);
+                    }
                 },
                 command = self.command_rx.recv() => match command {
                     Some(Command::Gossip(addr)) => {
@@ -316,6 +321,7 @@ impl Server {
                 .dead_nodes()
                 .map(|chitchat_id| chitchat_id.gossip_advertise_addr)
                 .collect::<HashSet<_>>();
+            let fanout = chitchat_guard.gossip_params().fanout;
             let seed_nodes: HashSet<SocketAddr> = chitchat_guard
                 .seed_nodes()
                 .into_iter()
@@ -323,6 +329,7 @@ impl Server {
                 .collect();
             let result = select_nodes_for_gossip(
                 &mut self.rng,
+                fanout,
                 peer_nodes,
                 live_nodes,
                 dead_nodes,
@@ -380,6 +387,7 @@ enum Command {
 
 fn select_nodes_for_gossip<R>(
     rng: &mut R,
+    fanout: usize,
     peer_nodes: HashSet<SocketAddr>,
     live_nodes: HashSet<SocketAddr>,
     dead_nodes: HashSet<SocketAddr>,
@@ -391,12 +399,12 @@ where
     let live_nodes_count = live_nodes.len();
     let dead_nodes_count = dead_nodes.len();
 
-    // Select `GOSSIP_COUNT` number of live nodes.
+    // Select `fanout` number of live nodes.
     // On startup, select from cluster nodes since we don't know any live node yet.
     let nodes = if live_nodes_count == 0 { peer_nodes } else { live_nodes }
         .iter()
         .cloned()
-        .choose_multiple(rng, GOSSIP_COUNT);
+        .choose_multiple(rng, fanout);
 
     let mut has_gossiped_with_a_seed_node = false;
     for chitchat_id in &nodes {
@@ -721,6 +729,7 @@ mod tests {
         let mut rng = RngForTest::default();
         let (nodes, dead_node, seed_node) = select_nodes_for_gossip(
             &mut rng,
+            3,
             to_hash_set(vec![
                 node1.gossip_advertise_addr,
                 node2.gossip_advertise_addr,
@@ -744,6 +753,7 @@ mod tests {
         let mut rng = RngForTest::default();
         let (nodes, dead_node, seed_node) = select_nodes_for_gossip(
             &mut rng,
+            3,
             nodes.clone(),
             nodes,
             to_hash_set(Vec::new()),
@@ -764,6 +774,7 @@ mod tests {
         let mut rng = RngForTest::default();
         let (gossip_nodes, gossip_dead_node, gossip_seed_node) = select_nodes_for_gossip(
             &mut rng,
+            3,
             to_hash_set(nodes.clone()),
             to_hash_set(vec![nodes[0]]),
             nodes[1..].iter().cloned().collect(),
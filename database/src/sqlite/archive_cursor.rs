@@ -0,0 +1,95 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use rusqlite::Connection;
+use rusqlite::Transaction;
+
+use super::ArchBlock;
+
+/// Last block of the thread written to the archive. It is advanced in the
+/// transaction that writes the block with all its records, so the archive
+/// never has a part of a block and a restarted writer resumes after it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveCursor {
+    pub thread_id: String,
+    pub block_id: String,
+    pub seq_no: u32,
+    pub chain_order: String,
+}
+
+/// Moves the cursor of the block thread to the block unless the cursor is
+/// already further.
+pub fn advance(tx: &Transaction, block: &ArchBlock) -> rusqlite::Result<()> {
+    let (Some(thread_id), Some(chain_order)) = (&block.thread_id, &block.chain_order) else {
+        return Ok(());
+    };
+    tx.prepare_cached(
+        "INSERT INTO archive_cursor (thread_id, block_id, seq_no, chain_order)
+        VALUES (?1, ?2, ?3, ?4)
+        ON CONFLICT(thread_id) DO UPDATE SET
+            block_id=excluded.block_id,
+            seq_no=excluded.seq_no,
+            chain_order=excluded.chain_order
+        WHERE excluded.chain_order > archive_cursor.chain_order",
+    )?
+    .execute(rusqlite::params![thread_id, block.id, block.seq_no, chain_order])?;
+    Ok(())
+}
+
+pub fn load(conn: &Connection) -> anyhow::Result<Vec<ArchiveCursor>> {
+    let cursors = conn
+        .prepare(
+            "SELECT thread_id, block_id, seq_no, chain_order FROM archive_cursor
+            ORDER BY thread_id",
+        )?
+        .query_map([], |row| {
+            Ok(ArchiveCursor {
+                thread_id: row.get(0)?,
+                block_id: row.get(1)?,
+                seq_no: row.get(2)?,
+                chain_order: row.get(3)?,
+            })
+        })?
+        .collect::<Result<_, _>>()?;
+    Ok(cursors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(id: &str, seq_no: u32, chain_order: &str) -> ArchBlock {
+        ArchBlock {
+            id: id.to_string(),
+            seq_no,
+            chain_order: Some(chain_order.to_string()),
+            thread_id: Some("00".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_advance_cursor() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!(
+            "../../../migration-tool/migrations/bm-archive/008-archive_cursor/up.sql"
+        ))
+        .unwrap();
+        let tx = conn.transaction().unwrap();
+        advance(&tx, &block("aa", 1, "0001")).unwrap();
+        advance(&tx, &block("bb", 2, "0002")).unwrap();
+        // Redelivered block doesn't move the cursor back
+        advance(&tx, &block("aa", 1, "0001")).unwrap();
+        advance(&tx, &ArchBlock { thread_id: None, ..block("cc", 3, "0003") }).unwrap();
+        tx.commit().unwrap();
+        assert_eq!(
+            load(&conn).unwrap(),
+            vec![ArchiveCursor {
+                thread_id: "00".to_string(),
+                block_id: "bb".to_string(),
+                seq_no: 2,
+                chain_order: "0002".to_string(),
+            }]
+        );
+    }
+}
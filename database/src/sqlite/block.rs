@@ -31,6 +31,16 @@ pub struct BlockValueFlow {
     to_next_blk: Option<String>,
 }
 
+/// Fee accounting of a block, amounts are decimal strings.
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+pub struct ArchBlockFees {
+    pub fees_collected: String,
+    pub minted: String,
+    pub burned: String,
+    /// JSON object with the shell minted by each dapp (negative if burned)
+    pub minted_by_dapp: Option<String>,
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug, Default)]
 pub struct ArchBlock {
     pub id: String,
@@ -76,6 +86,7 @@ pub struct ArchBlock {
     pub value_flow: Option<BlockValueFlow>,
     pub thread_id: Option<String>,
     pub producer_id: Option<String>,
+    pub fees: Option<ArchBlockFees>,
 }
 
 with_prefix!(prefix_prev_ref "prev_ref_");
@@ -135,6 +146,7 @@ impl From<&Row<'_>> for ArchBlock {
             thread_id: val.get(45).ok(),
             value_flow: None,
             producer_id: val.get(46).ok(),
+            fees: None,
         }
     }
 }
@@ -0,0 +1,276 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use rusqlite::types::ValueRef;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Map;
+use serde_json::Value;
+
+use super::sqlite_helper::SqliteHelper;
+
+pub const COLD_STORAGE_MANIFEST: &str = "manifest.json";
+const ZSTD_COMPRESSION_LEVEL: i32 = 3;
+
+#[derive(Clone, Debug)]
+pub struct ColdStorageConfig {
+    /// Directory synchronized with the object storage bucket.
+    pub target_dir: PathBuf,
+    /// Prefix of the segment URI in the object storage, e.g.
+    /// `s3://bucket/archive`. Returned to clients as a retrieval hint.
+    pub uri_prefix: String,
+    /// Blocks older than this are moved to cold storage.
+    pub retention: Duration,
+    /// Maximum number of blocks moved in one segment.
+    pub max_blocks_per_segment: usize,
+}
+
+/// Segment of archive rows moved to cold storage. Segment is a zstd
+/// compressed JSON lines file, each line is `{"table": ..., "row": {...}}`
+/// with BLOB columns encoded as hex strings.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ColdSegment {
+    pub file: String,
+    pub uri: String,
+    pub min_gen_utime: i64,
+    pub max_gen_utime: i64,
+    pub blocks: usize,
+    pub transactions: usize,
+    pub messages: usize,
+    pub created_at: i64,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct ColdStorageManifest {
+    pub segments: Vec<ColdSegment>,
+}
+
+// Rows are selected by the blocks they belong to, so a block is always
+// moved together with its transactions and messages produced by them.
+const SELECT_QUERIES: [(&str, &str); 3] = [
+    ("blocks", "SELECT * FROM blocks WHERE id IN (SELECT id FROM cold_blocks)"),
+    ("transactions", "SELECT * FROM transactions WHERE block_id IN (SELECT id FROM cold_blocks)"),
+    (
+        "messages",
+        "SELECT * FROM messages WHERE transaction_id IN (
+            SELECT id FROM transactions WHERE block_id IN (SELECT id FROM cold_blocks)
+        )",
+    ),
+];
+
+impl SqliteHelper {
+    /// Moves rows of blocks older than the retention threshold into a new
+    /// cold storage segment. Returns `None` if there is nothing to move.
+    pub fn move_to_cold_storage(
+        &self,
+        config: &ColdStorageConfig,
+    ) -> anyhow::Result<Option<ColdSegment>> {
+        let now = chrono::Utc::now().timestamp();
+        let mut guarded = self.conn.lock();
+        move_to_cold_storage(&mut guarded, config, now)
+    }
+}
+
+pub fn move_to_cold_storage(
+    conn: &mut rusqlite::Connection,
+    config: &ColdStorageConfig,
+    now: i64,
+) -> anyhow::Result<Option<ColdSegment>> {
+    let threshold = now - config.retention.as_secs() as i64;
+    let tx = conn.transaction()?;
+    tx.execute("DROP TABLE IF EXISTS temp.cold_blocks", [])?;
+    tx.execute(
+        "CREATE TEMP TABLE cold_blocks AS
+            SELECT id, gen_utime FROM blocks WHERE gen_utime < ?1 ORDER BY chain_order LIMIT ?2",
+        rusqlite::params![threshold, config.max_blocks_per_segment as i64],
+    )?;
+    let (blocks, min_gen_utime, max_gen_utime): (usize, Option<i64>, Option<i64>) = tx.query_row(
+        "SELECT COUNT(*), MIN(gen_utime), MAX(gen_utime) FROM cold_blocks",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    let (Some(min_gen_utime), Some(max_gen_utime)) = (min_gen_utime, max_gen_utime) else {
+        return Ok(None);
+    };
+
+    let file = format!("segment-{now}-{min_gen_utime}-{max_gen_utime}.jsonl.zst");
+    let mut counts = vec![];
+    let mut encoder = zstd::Encoder::new(Vec::new(), ZSTD_COMPRESSION_LEVEL)?;
+    for (table, query) in SELECT_QUERIES {
+        let mut stmt = tx.prepare(query)?;
+        let columns =
+            stmt.column_names().into_iter().map(|name| name.to_string()).collect::<Vec<_>>();
+        let mut rows = stmt.query([])?;
+        let mut count = 0;
+        while let Some(row) = rows.next()? {
+            let mut values = Map::new();
+            for (i, column) in columns.iter().enumerate() {
+                values.insert(column.clone(), column_to_json(row.get_ref(i)?));
+            }
+            serde_json::to_writer(
+                &mut encoder,
+                &serde_json::json!({ "table": table, "row": values }),
+            )?;
+            encoder.write_all(b"\n")?;
+            count += 1;
+        }
+        counts.push(count);
+    }
+    let segment = ColdSegment {
+        uri: format!("{}/{file}", config.uri_prefix.trim_end_matches('/')),
+        file,
+        min_gen_utime,
+        max_gen_utime,
+        blocks,
+        transactions: counts[1],
+        messages: counts[2],
+        created_at: now,
+    };
+
+    // Rows are deleted only after the segment and the manifest are stored,
+    // a failure in between leaves duplicates but never loses data.
+    std::fs::create_dir_all(&config.target_dir)?;
+    write_atomically(&config.target_dir.join(&segment.file), &encoder.finish()?)?;
+    let mut manifest = read_manifest(&config.target_dir)?;
+    manifest.segments.push(segment.clone());
+    write_atomically(
+        &config.target_dir.join(COLD_STORAGE_MANIFEST),
+        &serde_json::to_vec_pretty(&manifest)?,
+    )?;
+
+    tx.execute(
+        "INSERT INTO cold_storage_segments (
+            uri, min_gen_utime, max_gen_utime, blocks, transactions, messages, created_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        rusqlite::params![
+            segment.uri,
+            segment.min_gen_utime,
+            segment.max_gen_utime,
+            segment.blocks,
+            segment.transactions,
+            segment.messages,
+            segment.created_at,
+        ],
+    )?;
+    let segment_id = tx.last_insert_rowid();
+    tx.execute_batch(&format!(
+        "CREATE TEMP TABLE cold_transactions AS
+            SELECT id FROM transactions WHERE block_id IN (SELECT id FROM cold_blocks);
+        INSERT OR REPLACE INTO cold_storage_index (id, kind, segment)
+            SELECT id, 'block', {segment_id} FROM cold_blocks;
+        INSERT OR REPLACE INTO cold_storage_index (id, kind, segment)
+            SELECT id, 'transaction', {segment_id} FROM cold_transactions;
+        INSERT OR REPLACE INTO cold_storage_index (id, kind, segment)
+            SELECT id, 'message', {segment_id} FROM messages
+            WHERE transaction_id IN (SELECT id FROM cold_transactions);
+        DELETE FROM messages WHERE transaction_id IN (SELECT id FROM cold_transactions);
+        DELETE FROM transactions WHERE id IN (SELECT id FROM cold_transactions);
+        DELETE FROM blocks WHERE id IN (SELECT id FROM cold_blocks);
+        DROP TABLE temp.cold_transactions;
+        DROP TABLE temp.cold_blocks;"
+    ))?;
+    tx.commit()?;
+
+    tracing::info!(
+        target: "sqlite",
+        "Moved {} block(s), {} transaction(s), {} message(s) to cold storage: {}",
+        segment.blocks,
+        segment.transactions,
+        segment.messages,
+        segment.uri
+    );
+    Ok(Some(segment))
+}
+
+pub fn read_manifest(target_dir: &Path) -> anyhow::Result<ColdStorageManifest> {
+    let path = target_dir.join(COLD_STORAGE_MANIFEST);
+    if !path.exists() {
+        return Ok(ColdStorageManifest::default());
+    }
+    let data = std::fs::read(&path)?;
+    serde_json::from_slice(&data)
+        .map_err(|e| anyhow::format_err!("Failed to read cold storage manifest {path:?}: {e}"))
+}
+
+fn write_atomically(path: &Path, data: &[u8]) -> anyhow::Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    let mut file = std::fs::File::create(&tmp_path)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+fn column_to_json(value: ValueRef<'_>) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(value) => Value::from(value),
+        ValueRef::Real(value) => Value::from(value),
+        ValueRef::Text(value) => Value::from(String::from_utf8_lossy(value).into_owned()),
+        ValueRef::Blob(value) => {
+            Value::from(value.iter().map(|byte| format!("{byte:02x}")).collect::<String>())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_move_to_cold_storage() {
+        let target_dir = tempfile::tempdir().unwrap();
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE blocks (id TEXT, gen_utime INTEGER, chain_order TEXT, boc BLOB);
+            CREATE TABLE transactions (id TEXT, block_id TEXT);
+            CREATE TABLE messages (id TEXT, transaction_id TEXT);
+            CREATE TABLE cold_storage_segments (
+                rowid INTEGER PRIMARY KEY, uri TEXT, min_gen_utime INTEGER, max_gen_utime INTEGER,
+                blocks INTEGER, transactions INTEGER, messages INTEGER, created_at INTEGER
+            );
+            CREATE TABLE cold_storage_index (id TEXT PRIMARY KEY, kind TEXT, segment INTEGER);
+            INSERT INTO blocks VALUES ('b1', 100, '01', x'0102'), ('b2', 900, '02', NULL);
+            INSERT INTO transactions VALUES ('t1', 'b1'), ('t2', 'b2');
+            INSERT INTO messages VALUES ('m1', 't1'), ('m2', 't2');",
+        )
+        .unwrap();
+        let config = ColdStorageConfig {
+            target_dir: target_dir.path().to_path_buf(),
+            uri_prefix: "s3://archive/".to_string(),
+            retention: Duration::from_secs(500),
+            max_blocks_per_segment: 10,
+        };
+
+        let segment = move_to_cold_storage(&mut conn, &config, 1000).unwrap().unwrap();
+        assert_eq!((segment.blocks, segment.transactions, segment.messages), (1, 1, 1));
+        assert_eq!(segment.uri, format!("s3://archive/{}", segment.file));
+        assert_eq!(read_manifest(target_dir.path()).unwrap().segments, vec![segment]);
+
+        let moved: Vec<(String, String)> = conn
+            .prepare("SELECT id, kind FROM cold_storage_index ORDER BY id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            moved,
+            vec![
+                ("b1".to_string(), "block".to_string()),
+                ("m1".to_string(), "message".to_string()),
+                ("t1".to_string(), "transaction".to_string()),
+            ]
+        );
+        let hot_blocks: i64 =
+            conn.query_row("SELECT COUNT(*) FROM blocks", [], |row| row.get(0)).unwrap();
+        assert_eq!(hot_blocks, 1);
+
+        assert_eq!(move_to_cold_storage(&mut conn, &config, 1000).unwrap(), None);
+    }
+}
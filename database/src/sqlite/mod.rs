@@ -1,13 +1,18 @@
 // 2022-2024 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
 //
 pub mod account;
+pub mod archive_cursor;
 pub mod block;
+pub mod cold_storage;
 pub mod message;
+pub mod sharded_helper;
 pub mod sqlite_helper;
+pub mod statistics;
 pub mod transaction;
 
 pub use account::ArchAccount;
 pub use block::ArchBlock;
+pub use block::ArchBlockFees;
 pub use message::ArchMessage;
 pub use transaction::ArchTransaction;
 pub use transaction::FlatTransaction;
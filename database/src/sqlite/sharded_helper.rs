@@ -0,0 +1,136 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+use super::cold_storage::ColdSegment;
+use super::cold_storage::ColdStorageConfig;
+use super::sqlite_helper::SqliteHelper;
+use super::sqlite_helper::SqliteHelperConfig;
+use super::sqlite_helper::SQLITE_EMPTY_DB;
+
+pub const SQLITE_SHARD_PREFIX: &str = "bm-archive-thread-";
+
+/// Archive split into per-thread database files. Every shard has its own
+/// writer, so blocks of different threads are stored in parallel.
+pub struct ShardedSqliteHelper {
+    data_dir: PathBuf,
+    shards: HashMap<String, Arc<Mutex<SqliteHelper>>>,
+}
+
+impl ShardedSqliteHelper {
+    /// Opens the shards that already exist in `data_dir`, shards of new
+    /// threads are created on the first block.
+    pub fn new(data_dir: PathBuf) -> anyhow::Result<Self> {
+        let mut helper = Self { data_dir, shards: HashMap::new() };
+        for thread_id in shard_threads(&helper.data_dir)? {
+            helper.shard(&thread_id)?;
+        }
+        Ok(helper)
+    }
+
+    pub fn shard_file(thread_id: &str) -> PathBuf {
+        format!("{SQLITE_SHARD_PREFIX}{thread_id}.db").into()
+    }
+
+    /// Returns the shard of the thread, creating it from the empty database
+    /// with the applied schema if needed.
+    pub fn shard(&mut self, thread_id: &str) -> anyhow::Result<Arc<Mutex<SqliteHelper>>> {
+        if let Some(shard) = self.shards.get(thread_id) {
+            return Ok(shard.clone());
+        }
+        let db_file = Self::shard_file(thread_id);
+        let db_path = self.data_dir.join(&db_file);
+        if !db_path.exists() {
+            std::fs::copy(self.data_dir.join(SQLITE_EMPTY_DB), &db_path).map_err(|e| {
+                anyhow::format_err!("Failed to create archive shard {db_path:?}: {e}")
+            })?;
+            tracing::info!(target: "sqlite", "Archive shard created: {db_path:?}");
+        }
+        let config = SqliteHelperConfig::new(self.data_dir.clone(), Some(db_file));
+        let (helper, _writer_join_handle) = SqliteHelper::from_config(config)?;
+        let shard = Arc::new(Mutex::new(helper));
+        self.shards.insert(thread_id.to_string(), shard.clone());
+        Ok(shard)
+    }
+
+    pub fn shards(&self) -> Vec<Arc<Mutex<SqliteHelper>>> {
+        self.shards.values().cloned().collect()
+    }
+
+    pub fn rotate_db_files(&mut self) -> anyhow::Result<()> {
+        for shard in self.shards.values() {
+            shard.lock().rotate_db_file()?;
+        }
+        Ok(())
+    }
+
+    /// Moves old rows of every shard to cold storage. Segments of a shard
+    /// are stored in its own subdirectory, so shards never share a manifest.
+    pub fn move_to_cold_storage(
+        &self,
+        config: &ColdStorageConfig,
+    ) -> anyhow::Result<Vec<ColdSegment>> {
+        let mut segments = vec![];
+        for (thread_id, shard) in &self.shards {
+            let shard_config = ColdStorageConfig {
+                target_dir: config.target_dir.join(format!("thread-{thread_id}")),
+                uri_prefix: format!(
+                    "{}/thread-{thread_id}",
+                    config.uri_prefix.trim_end_matches('/')
+                ),
+                ..config.clone()
+            };
+            segments.extend(shard.lock().move_to_cold_storage(&shard_config)?);
+        }
+        Ok(segments)
+    }
+
+    pub fn shutdown(&mut self) -> anyhow::Result<()> {
+        for shard in self.shards.values() {
+            shard.lock().shutdown()?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns thread ids of the shards stored in `data_dir`. Rotated shards
+/// (`<shard>-<timestamp>.db`) are skipped.
+pub fn shard_threads(data_dir: &Path) -> anyhow::Result<Vec<String>> {
+    let mut threads = vec![];
+    for entry in std::fs::read_dir(data_dir)? {
+        let file_name = entry?.file_name();
+        if let Some(thread_id) = shard_thread_id(&file_name.to_string_lossy()) {
+            threads.push(thread_id.to_string());
+        }
+    }
+    threads.sort();
+    Ok(threads)
+}
+
+pub fn shard_thread_id(file_name: &str) -> Option<&str> {
+    let thread_id = file_name.strip_prefix(SQLITE_SHARD_PREFIX)?.strip_suffix(".db")?;
+    if thread_id.is_empty() || !thread_id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(thread_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shard_thread_id() {
+        let file = ShardedSqliteHelper::shard_file("00ff");
+        assert_eq!(shard_thread_id(&file.to_string_lossy()), Some("00ff"));
+        assert_eq!(shard_thread_id("bm-archive-thread-00ff-1700000000.db"), None);
+        assert_eq!(shard_thread_id("bm-archive-thread-00ff.db-wal"), None);
+        assert_eq!(shard_thread_id("bm-archive.db"), None);
+    }
+}
@@ -13,6 +13,8 @@ use std::thread;
 use parking_lot::Mutex;
 use rusqlite::OpenFlags;
 
+use super::archive_cursor;
+use super::statistics;
 use super::ArchAccount;
 use super::ArchBlock;
 use super::ArchMessage;
@@ -120,6 +122,11 @@ impl SqliteHelper {
         Ok(conn)
     }
 
+    /// Last archived block of every thread written to this database.
+    pub fn archive_cursors(&self) -> anyhow::Result<Vec<archive_cursor::ArchiveCursor>> {
+        archive_cursor::load(&self.conn.lock())
+    }
+
     pub fn shutdown(&mut self) -> anyhow::Result<()> {
         let (dummy_sender, _) = channel::<DBStoredRecord>();
         self.record_sender = dummy_sender;
@@ -140,7 +147,9 @@ impl SqliteHelper {
         std::thread::sleep(std::time::Duration::from_millis(200));
 
         let timestamp = chrono::Utc::now().timestamp();
-        let archived_path = self.config.data_dir.join(format!("bm-archive-{timestamp}.db"));
+        // shards of the same data dir are rotated at the same time
+        let stem = self.config.db_file.file_stem().unwrap_or_default().to_string_lossy();
+        let archived_path = self.config.data_dir.join(format!("{stem}-{timestamp}.db"));
 
         // move DB files (db, wal, shm)
         rename_with_suffixes(&self.db_files.work, &archived_path)?;
@@ -169,40 +178,57 @@ impl SqliteHelper {
         Ok(writer_join_handle)
     }
 
+    // Records of a block are put before the block itself. They are kept until
+    // the block arrives and written with it in one transaction, so a crash
+    // never leaves a part of a block in the archive.
     fn put_records_worker(receiver: Receiver<DBStoredRecord>, context: &mut SqliteHelperContext) {
+        let mut block_records = vec![];
         for record in receiver {
-            let result = match record {
-                DBStoredRecord::Block(ref block) => Self::store_block(context, block.clone()),
-                DBStoredRecord::Transactions(ref transactions) => {
-                    Self::store_transactions(context, transactions.to_vec())
-                }
-                DBStoredRecord::Accounts(ref accounts) => {
-                    Self::store_accounts(context, accounts.to_vec())
-                }
-                DBStoredRecord::Messages(ref messages) => {
-                    Self::store_messages(context, messages.to_vec())
-                }
+            let DBStoredRecord::Block(block) = record else {
+                block_records.push(record);
+                continue;
             };
-
-            if let Err(err) = result {
+            let records = std::mem::take(&mut block_records);
+            if let Err(err) = Self::store_block_with_records(context, &block, records) {
                 tracing::error!(target: "sqlite", "Error store object(s) into sqlite: {err}");
-                tracing::error!(target: "sqlite", "bad object: {:?}", record);
-
-                if let DBStoredRecord::Block(_) = record {
-                    panic!("This error is fatal, thread exiting")
-                };
+                tracing::error!(target: "sqlite", "bad object: Block({})", block.id);
+                panic!("This error is fatal, thread exiting")
             }
         }
         tracing::debug!(target: "sqlite", "receiver dropped");
     }
 
-    fn store_accounts(
+    fn store_block_with_records(
         context: &mut SqliteHelperContext,
-        accounts: Vec<ArchAccount>,
+        block: &ArchBlock,
+        records: Vec<DBStoredRecord>,
     ) -> anyhow::Result<()> {
-        let cnt_accounts = accounts.len();
         let mut guarded = context.conn.lock();
         let tx = guarded.transaction()?;
+        for record in records {
+            match record {
+                DBStoredRecord::Transactions(transactions) => {
+                    Self::store_transactions(&tx, transactions)?
+                }
+                DBStoredRecord::Accounts(accounts) => Self::store_accounts(&tx, accounts)?,
+                DBStoredRecord::Messages(messages) => Self::store_messages(&tx, messages)?,
+                DBStoredRecord::Block(_) => unreachable!("blocks are not kept as block records"),
+            }
+        }
+        Self::store_block(&tx, block)?;
+        archive_cursor::advance(&tx, block)?;
+
+        let now_committed = std::time::Instant::now();
+        tx.commit()?;
+        tracing::debug!(target: "sqlite", "TIME: committed ({}:{}) block {}ms", block.seq_no, block.id, now_committed.elapsed().as_millis());
+        Ok(())
+    }
+
+    fn store_accounts(
+        tx: &rusqlite::Transaction,
+        accounts: Vec<ArchAccount>,
+    ) -> anyhow::Result<()> {
+        let cnt_accounts = accounts.len();
 
         let now_batched = std::time::Instant::now();
         {
@@ -262,19 +288,13 @@ impl SqliteHelper {
         }
         tracing::debug!(target: "sqlite", "TIME: batched {} account(s) {}ms", cnt_accounts, now_batched.elapsed().as_millis());
 
-        let now_committed = std::time::Instant::now();
-        tx.commit()?;
-        tracing::debug!(target: "sqlite", "TIME: committed {} account(s) {}ms", cnt_accounts, now_committed.elapsed().as_millis());
-
         Ok(())
     }
 
-    fn store_block(context: &mut SqliteHelperContext, block: Box<ArchBlock>) -> anyhow::Result<()> {
-        let mut guarded = context.conn.lock();
-        let tx = guarded.transaction()?;
-
+    fn store_block(tx: &rusqlite::Transaction, block: &ArchBlock) -> anyhow::Result<()> {
         let now = std::time::Instant::now();
         {
+            let is_new_block = !statistics::is_block_archived(tx, &block.id)?;
             let result = if !cfg!(feature = "store_events_only") {
                 let mut stmt = tx.prepare_cached(
                     "INSERT INTO blocks (
@@ -297,8 +317,8 @@ impl SqliteHelper {
                         status=excluded.status"
                 )?;
 
-                let prev_ref = block.prev_ref.unwrap_or_default();
-                let prev_alt_ref = block.prev_alt_ref.unwrap_or_default();
+                let prev_ref = block.prev_ref.clone().unwrap_or_default();
+                let prev_alt_ref = block.prev_alt_ref.clone().unwrap_or_default();
                 let params = rusqlite::params![
                     block.id,
                     block.status,
@@ -355,7 +375,9 @@ impl SqliteHelper {
                         id,status,seq_no,parent,producer_id,thread_id,gen_utime,chain_order,boc
                     ) VALUES (
                         ?1,?2,?3,?4,?5,?6,?7,?8,?9
-                    )",
+                    )
+                    ON CONFLICT(id) DO UPDATE SET
+                        status=excluded.status",
                 )?;
 
                 let params = rusqlite::params![
@@ -373,26 +395,54 @@ impl SqliteHelper {
                 stmt.execute(params)
             };
 
-            if let Err(err) = result {
-                tracing::error!("store_block(): failed to store block: {err}")
+            match result {
+                Ok(_) => {
+                    if let Some(gen_utime) = block.gen_utime.filter(|_| is_new_block) {
+                        let fees_collected =
+                            block.fees.as_ref().map(|fees| fees.fees_collected.as_str());
+                        if let Err(err) = statistics::record_block(tx, gen_utime, fees_collected) {
+                            tracing::error!("store_block(): failed to update statistics: {err}")
+                        }
+                    }
+                }
+                Err(err) => tracing::error!("store_block(): failed to store block: {err}"),
+            }
+
+            if let Some(fees) = &block.fees {
+                let mut stmt = tx.prepare_cached(
+                    "INSERT INTO block_fees (
+                        block_id,thread_id,seq_no,gen_utime,fees_collected,minted,burned,
+                        minted_by_dapp
+                    ) VALUES (
+                        ?1,?2,?3,?4,?5,?6,?7,?8
+                    )
+                    ON CONFLICT(block_id) DO NOTHING",
+                )?;
+                let params = rusqlite::params![
+                    block.id,
+                    block.thread_id,
+                    block.seq_no,
+                    block.gen_utime,
+                    fees.fees_collected,
+                    fees.minted,
+                    fees.burned,
+                    fees.minted_by_dapp,
+                ];
+                if let Err(err) = stmt.execute(params) {
+                    tracing::error!("store_block(): failed to store block fees: {err}")
+                }
             }
         }
         tracing::debug!(target: "sqlite", "TIME: batched ({}:{}) block {}ms", block.seq_no, block.id, now.elapsed().as_millis());
 
-        let now_committed = std::time::Instant::now();
-        tx.commit()?;
-        tracing::debug!(target: "sqlite", "TIME: committed ({}:{}) block {}ms", block.seq_no, block.id, now_committed.elapsed().as_millis());
-
         Ok(())
     }
 
     fn store_messages(
-        context: &mut SqliteHelperContext,
+        tx: &rusqlite::Transaction,
         messages: Vec<ArchMessage>,
     ) -> anyhow::Result<()> {
         let cnt_messages = messages.len();
-        let mut guarded = context.conn.lock();
-        let tx = guarded.transaction()?;
 
         let now_batched = std::time::Instant::now();
         {
@@ -482,20 +532,14 @@ impl SqliteHelper {
             }
         }
         tracing::debug!(target: "sqlite", "TIME: batched {} message(s) {}ms", cnt_messages, now_batched.elapsed().as_millis());
-
-        let now_committed = std::time::Instant::now();
-        tx.commit()?;
-        tracing::debug!(target: "sqlite", "TIME: committed {} message(s) {}ms", cnt_messages, now_committed.elapsed().as_millis());
         Ok(())
     }
 
     fn store_transactions(
-        context: &mut SqliteHelperContext,
+        tx: &rusqlite::Transaction,
         transactions: Vec<ArchTransaction>,
     ) -> anyhow::Result<()> {
         let cnt_transactions = transactions.len();
-        let mut guarded = context.conn.lock();
-        let tx = guarded.transaction()?;
 
         let now_batched = std::time::Instant::now();
         {
@@ -574,23 +618,26 @@ impl SqliteHelper {
                     trx.chain_order,
                 ];
 
-                if let Err(err) = stmt.execute(params) {
-                    tracing::error!("store_transactions(): failed to store transaction: {err}")
+                match stmt.execute(params) {
+                    // Transactions are stored once, a conflict means the transaction is counted
+                    Ok(1) => {
+                        if let Err(err) =
+                            statistics::record_transaction(tx, trx.now as i64, &trx.account_addr)
+                        {
+                            tracing::error!(
+                                "store_transactions(): failed to update statistics: {err}"
+                            )
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        tracing::error!("store_transactions(): failed to store transaction: {err}")
+                    }
                 }
             }
         }
         tracing::debug!(target: "sqlite", "TIME: batched {} transaction(s) {}ms", cnt_transactions, now_batched.elapsed().as_millis());
 
-        let now_committed = std::time::Instant::now();
-        tracing::debug!(target: "sqlite", "TIME: commiting...");
-        match tx.commit() {
-            Ok(_) => {
-                tracing::debug!(target: "sqlite", "TIME: committed {} transaction(s) {}ms", cnt_transactions, now_committed.elapsed().as_millis())
-            }
-            Err(e) => tracing::error!("transactions commit error: {e}"),
-        }
-        tracing::debug!(target: "sqlite", "TIME: commit complete");
-
         Ok(())
     }
 }
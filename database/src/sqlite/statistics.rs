@@ -0,0 +1,134 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use rusqlite::OptionalExtension;
+use rusqlite::Transaction;
+
+/// Rollup periods: name stored in the `period` column and length in seconds.
+/// Period starts are aligned to the unix epoch (UTC).
+pub const STATISTICS_PERIODS: [(&str, i64); 2] = [("hour", 3600), ("day", 86400)];
+
+fn period_start(time: i64, period_secs: i64) -> i64 {
+    time - time.rem_euclid(period_secs)
+}
+
+fn ensure_rollup(tx: &Transaction, period: &str, period_start: i64) -> rusqlite::Result<()> {
+    tx.prepare_cached(
+        "INSERT INTO statistics_rollups (period, period_start) VALUES (?1, ?2)
+        ON CONFLICT(period, period_start) DO NOTHING",
+    )?
+    .execute(rusqlite::params![period, period_start])?;
+    Ok(())
+}
+
+/// Counts a newly archived block and its collected fees (decimal string) in
+/// the rollups of its generation time.
+pub fn record_block(
+    tx: &Transaction,
+    gen_utime: i64,
+    fees_collected: Option<&str>,
+) -> anyhow::Result<()> {
+    let fees_collected = fees_collected
+        .map(|fees| fees.parse::<u128>())
+        .transpose()
+        .map_err(|e| anyhow::format_err!("Invalid fees collected: {e}"))?
+        .unwrap_or_default();
+    for (period, period_secs) in STATISTICS_PERIODS {
+        let period_start = period_start(gen_utime, period_secs);
+        ensure_rollup(tx, period, period_start)?;
+        // Amounts don't fit into the SQLite integer, they are summed here
+        let total: String = tx
+            .prepare_cached(
+                "SELECT fees_collected FROM statistics_rollups
+                WHERE period = ?1 AND period_start = ?2",
+            )?
+            .query_row(rusqlite::params![period, period_start], |row| row.get(0))?;
+        let total = total.parse::<u128>().unwrap_or_default().saturating_add(fees_collected);
+        tx.prepare_cached(
+            "UPDATE statistics_rollups SET blocks = blocks + 1, fees_collected = ?3
+            WHERE period = ?1 AND period_start = ?2",
+        )?
+        .execute(rusqlite::params![period, period_start, total.to_string()])?;
+    }
+    Ok(())
+}
+
+/// Counts a newly archived transaction and its account as active in the
+/// rollups of the transaction time.
+pub fn record_transaction(tx: &Transaction, now: i64, account_addr: &str) -> anyhow::Result<()> {
+    for (period, period_secs) in STATISTICS_PERIODS {
+        let period_start = period_start(now, period_secs);
+        ensure_rollup(tx, period, period_start)?;
+        let new_account = tx
+            .prepare_cached(
+                "INSERT INTO statistics_active_accounts (period, period_start, account_addr)
+                VALUES (?1, ?2, ?3)
+                ON CONFLICT(period, period_start, account_addr) DO NOTHING",
+            )?
+            .execute(rusqlite::params![period, period_start, account_addr])?;
+        tx.prepare_cached(
+            "UPDATE statistics_rollups
+            SET transactions = transactions + 1, active_accounts = active_accounts + ?3
+            WHERE period = ?1 AND period_start = ?2",
+        )?
+        .execute(rusqlite::params![period, period_start, new_account])?;
+    }
+    Ok(())
+}
+
+/// Whether the block is already archived: rollups count every block once,
+/// while blocks are stored again on status updates.
+pub fn is_block_archived(tx: &Transaction, block_id: &str) -> rusqlite::Result<bool> {
+    Ok(tx
+        .prepare_cached("SELECT 1 FROM blocks WHERE id = ?1")?
+        .query_row([block_id], |_| Ok(()))
+        .optional()?
+        .is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statistics_rollups() {
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!(
+            "../../../migration-tool/migrations/bm-archive/007-statistics_rollups/up.sql"
+        ))
+        .unwrap();
+        let tx = conn.transaction().unwrap();
+        // 2024-01-01 00:10:00 and 01:10:00 UTC
+        let t0 = 1704067800;
+        let t1 = t0 + 3600;
+        record_block(&tx, t0, Some("340282366920938463463374607431768211455")).unwrap();
+        record_block(&tx, t1, Some("5")).unwrap();
+        record_block(&tx, t1, None).unwrap();
+        assert!(record_block(&tx, t1, Some("x")).is_err());
+        record_transaction(&tx, t0, "0:aa").unwrap();
+        record_transaction(&tx, t1, "0:aa").unwrap();
+        record_transaction(&tx, t1, "0:aa").unwrap();
+        record_transaction(&tx, t1, "0:bb").unwrap();
+
+        let rollups: Vec<(String, i64, i64, i64, String, i64)> = tx
+            .prepare(
+                "SELECT period, period_start, blocks, transactions, fees_collected, active_accounts
+                FROM statistics_rollups ORDER BY period, period_start",
+            )
+            .unwrap()
+            .query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?))
+            })
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            rollups,
+            vec![
+                ("day".to_string(), 1704067200, 3, 4, u128::MAX.to_string(), 2),
+                ("hour".to_string(), 1704067200, 1, 1, u128::MAX.to_string(), 1),
+                ("hour".to_string(), 1704070800, 2, 3, "5".to_string(), 2),
+            ]
+        );
+    }
+}
@@ -80,6 +80,9 @@ struct Opt {
 
     #[structopt(long = "interval_ms", default_value = "500")]
     interval: u64,
+
+    #[structopt(long = "fanout", default_value = "3")]
+    fanout: usize,
 }
 
 fn generate_server_id(public_addr: SocketAddr) -> String {
@@ -99,6 +102,9 @@ async fn main() -> anyhow::Result<()> {
         cluster_id: "testing".to_string(),
         chitchat_id,
         gossip_interval: Duration::from_millis(opt.interval),
+        gossip_fanout: opt.fanout,
+        max_gossip_payload_size: None,
+        adaptive_gossip: None,
         listen_addr: opt.listen_addr,
         seed_nodes: opt.seeds.clone(),
         failure_detector_config: FailureDetectorConfig::default(),
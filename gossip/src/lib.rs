@@ -6,6 +6,7 @@ use std::time::Duration;
 use std::time::SystemTime;
 
 use chitchat::spawn_chitchat;
+use chitchat::AdaptiveGossipConfig;
 use chitchat::ChitchatConfig;
 use chitchat::ChitchatHandle;
 use chitchat::ChitchatId;
@@ -24,7 +25,9 @@ use serde::Deserialize;
 use serde::Serialize;
 use tokio::task::JoinHandle;
 
-static DEFAULT_GOSSIP_INTERVAL: Duration = Duration::from_millis(500);
+mod snapshot;
+
+pub use snapshot::GossipSnapshotConfig;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ApiResponse {
@@ -94,6 +97,68 @@ pub struct GossipConfig {
     /// Chitchat cluster id for gossip
     #[serde(default = "default_chitchat_cluster_id")]
     pub cluster_id: String,
+
+    /// Gossip rate and datagram size settings.
+    #[serde(default)]
+    pub tuning: GossipTuning,
+
+    /// Cluster state persistence across restarts.
+    #[serde(default)]
+    pub snapshot: GossipSnapshotConfig,
+}
+
+/// Gossip rate and datagram size settings. The defaults suit clusters of a
+/// few dozen nodes, for bigger clusters set `adaptive_cluster_size` to reduce
+/// the UDP traffic.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct GossipTuning {
+    /// Interval between gossip rounds in milliseconds.
+    /// Defaults to 500
+    #[serde(default = "default_gossip_interval_ms")]
+    pub interval_ms: u64,
+
+    /// Number of live nodes gossiped with each round.
+    /// Defaults to 3
+    #[serde(default = "default_gossip_fanout")]
+    pub fanout: usize,
+
+    /// Max gossip datagram payload size in bytes.
+    /// Defaults to the UDP datagram limit (65507)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_payload_size: Option<usize>,
+
+    /// Cluster size above which the interval and the payload size grow and
+    /// the fanout shrinks proportionally to the number of live nodes. None
+    /// disables the adaptation.
+    /// Defaults to None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub adaptive_cluster_size: Option<usize>,
+
+    /// Upper bound of the adapted interval in milliseconds.
+    /// Defaults to 5000
+    #[serde(default = "default_max_gossip_interval_ms")]
+    pub max_interval_ms: u64,
+}
+
+impl Default for GossipTuning {
+    fn default() -> Self {
+        Self {
+            interval_ms: default_gossip_interval_ms(),
+            fanout: default_gossip_fanout(),
+            max_payload_size: None,
+            adaptive_cluster_size: None,
+            max_interval_ms: default_max_gossip_interval_ms(),
+        }
+    }
+}
+
+impl GossipTuning {
+    fn adaptive_gossip(&self) -> Option<AdaptiveGossipConfig> {
+        self.adaptive_cluster_size.map(|reference_cluster_size| AdaptiveGossipConfig {
+            reference_cluster_size,
+            max_gossip_interval: Duration::from_millis(self.max_interval_ms),
+        })
+    }
 }
 
 impl Default for GossipConfig {
@@ -103,6 +168,8 @@ impl Default for GossipConfig {
             advertise_addr: None,
             seeds: Vec::new(),
             cluster_id: default_chitchat_cluster_id(),
+            tuning: GossipTuning::default(),
+            snapshot: GossipSnapshotConfig::default(),
         }
     }
 }
@@ -121,8 +188,20 @@ fn default_chitchat_cluster_id() -> String {
     "acki_nacki".to_string()
 }
 
+fn default_gossip_interval_ms() -> u64 {
+    500
+}
+
+fn default_gossip_fanout() -> usize {
+    3
+}
+
+fn default_max_gossip_interval_ms() -> u64 {
+    5000
+}
+
 pub async fn run(
-    _shutdown_rx: tokio::sync::watch::Receiver<bool>,
+    shutdown_rx: tokio::sync::watch::Receiver<bool>,
     config_rx: tokio::sync::watch::Receiver<GossipConfig>,
     transport: impl chitchat::transport::Transport,
 ) -> anyhow::Result<(ChitchatHandle, JoinHandle<anyhow::Result<()>>)> {
@@ -133,7 +212,10 @@ pub async fn run(
     let chitchat_config = ChitchatConfig {
         cluster_id: config.cluster_id.clone(),
         chitchat_id,
-        gossip_interval: DEFAULT_GOSSIP_INTERVAL,
+        gossip_interval: Duration::from_millis(config.tuning.interval_ms),
+        gossip_fanout: config.tuning.fanout,
+        max_gossip_payload_size: config.tuning.max_payload_size,
+        adaptive_gossip: config.tuning.adaptive_gossip(),
         listen_addr: config.listen_addr,
         seed_nodes: config.seeds.iter().map(|x| x.to_string()).collect(),
         failure_detector_config: FailureDetectorConfig::default(),
@@ -149,6 +231,19 @@ pub async fn run(
 
     let chitchat_handle = spawn_chitchat(chitchat_config, Vec::new(), &transport).await?;
     let chitchat = chitchat_handle.chitchat();
+    if let Some(path) = config.snapshot.path.clone() {
+        let max_age = Duration::from_secs(config.snapshot.max_age_secs);
+        match snapshot::restore(&chitchat, &path, max_age) {
+            Ok(restored) => tracing::info!("Restored {restored} gossip peers from {path:?}"),
+            Err(e) => tracing::warn!("Failed to restore gossip snapshot from {path:?}: {e}"),
+        }
+        tokio::spawn(snapshot::run_saver(
+            shutdown_rx,
+            chitchat.clone(),
+            path,
+            Duration::from_secs(config.snapshot.save_interval_secs),
+        ));
+    }
     let api = Api { chitchat: chitchat.clone() };
     let api_service = OpenApiService::new(api, "Acki Nacki", "1.0")
         .server(format!("http://{}/", config.advertise_addr()));
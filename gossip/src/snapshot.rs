@@ -0,0 +1,125 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use chitchat::ChitchatRef;
+use chitchat::NodeState;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Persistence of the cluster state. A restarted node restores the peers
+/// from the snapshot instead of waiting for several gossip rounds.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct GossipSnapshotConfig {
+    /// File of the saved cluster state.
+    /// Defaults to None (the state is not persisted)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+
+    /// Interval between saves in seconds, the state is saved on shutdown too.
+    /// Defaults to 60
+    #[serde(default = "default_save_interval_secs")]
+    pub save_interval_secs: u64,
+
+    /// Snapshot older than this is ignored on startup, in seconds.
+    /// Defaults to 600
+    #[serde(default = "default_max_age_secs")]
+    pub max_age_secs: u64,
+}
+
+impl Default for GossipSnapshotConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            save_interval_secs: default_save_interval_secs(),
+            max_age_secs: default_max_age_secs(),
+        }
+    }
+}
+
+fn default_save_interval_secs() -> u64 {
+    60
+}
+
+fn default_max_age_secs() -> u64 {
+    600
+}
+
+#[derive(Serialize, Deserialize)]
+struct GossipSnapshot {
+    cluster_id: String,
+    // Unix time in seconds
+    saved_at: u64,
+    node_states: Vec<NodeState>,
+}
+
+fn now_secs() -> anyhow::Result<u64> {
+    Ok(SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?.as_secs())
+}
+
+/// Saves the states of the live peers.
+pub fn save(chitchat: &ChitchatRef, path: &Path) -> anyhow::Result<()> {
+    let snapshot = {
+        let chitchat = chitchat.lock();
+        let self_id = chitchat.self_chitchat_id();
+        GossipSnapshot {
+            cluster_id: chitchat.cluster_id().to_string(),
+            saved_at: now_secs()?,
+            node_states: chitchat
+                .live_nodes()
+                .filter(|chitchat_id| *chitchat_id != self_id)
+                .filter_map(|chitchat_id| chitchat.node_state(chitchat_id).cloned())
+                .collect(),
+        }
+    };
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, serde_json::to_vec(&snapshot)?)?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// Restores the peers from the snapshot unless it is stale or made for
+/// another cluster. Returns the number of restored peers.
+pub fn restore(chitchat: &ChitchatRef, path: &Path, max_age: Duration) -> anyhow::Result<usize> {
+    if !path.exists() {
+        return Ok(0);
+    }
+    let snapshot: GossipSnapshot = serde_json::from_slice(&std::fs::read(path)?)?;
+    let age = now_secs()?.saturating_sub(snapshot.saved_at);
+    if age > max_age.as_secs() {
+        tracing::info!("Gossip snapshot is {age}s old, skip it");
+        return Ok(0);
+    }
+    let mut chitchat = chitchat.lock();
+    anyhow::ensure!(
+        snapshot.cluster_id == chitchat.cluster_id(),
+        "Gossip snapshot is made for cluster {}",
+        snapshot.cluster_id
+    );
+    Ok(chitchat.restore_node_states(snapshot.node_states))
+}
+
+/// Saves the snapshot periodically and on shutdown.
+pub async fn run_saver(
+    mut shutdown_rx: tokio::sync::watch::Receiver<bool>,
+    chitchat: ChitchatRef,
+    path: PathBuf,
+    interval: Duration,
+) {
+    loop {
+        let stop = tokio::select! {
+            sender = shutdown_rx.changed() => sender.is_err() || *shutdown_rx.borrow(),
+            _ = tokio::time::sleep(interval) => false,
+        };
+        if let Err(e) = save(&chitchat, &path) {
+            tracing::error!("Failed to save gossip snapshot to {path:?}: {e}");
+        }
+        if stop {
+            return;
+        }
+    }
+}
@@ -2,5 +2,6 @@
 //
 pub const PATH_TO_DB: &str = "sqlite://data/bm-archive.db";
 pub const LISTEN: &str = "127.0.0.1:3000";
+pub const MAX_ARCHIVE_LAG_MS: u64 = 60_000;
 
 pub const QUERY_BATCH_SIZE: u16 = 50;
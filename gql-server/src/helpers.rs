@@ -9,7 +9,9 @@ use futures::stream::FuturesUnordered;
 use num::bigint::Sign;
 use num::BigInt;
 use num::Num;
+use opentelemetry::trace::TracerProvider;
 use sqlx::SqlitePool;
+use telemetry_config::TelemetryConfig;
 use tracing::level_filters::LevelFilter;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
@@ -228,7 +230,16 @@ pub async fn _load_blocks_in_messages(
     Ok(())
 }
 
-pub fn init_tracing() {
+pub fn init_tracing(telemetry: &TelemetryConfig) {
+    let tracer_provider =
+        telemetry.tracer_provider("acki-nacki-gql-server").unwrap_or_else(|err| {
+            eprintln!("Failed to init OTLP traces exporter: {err}");
+            None
+        });
+    let telemetry_layer = tracer_provider.map(|tracer_provider| {
+        opentelemetry::global::set_tracer_provider(tracer_provider.clone());
+        tracing_opentelemetry::layer().with_tracer(tracer_provider.tracer("gql_server"))
+    });
     // Init tracing
     let filter = match std::env::var("NODE_VERBOSE") {
         Ok(v) if !v.is_empty() => tracing_subscriber::filter::Targets::new()
@@ -250,6 +261,7 @@ pub fn init_tracing() {
                 .with_ansi(false)
                 .with_writer(std::io::stderr),
         )
+        .with(telemetry_layer)
         .with(filter)
         .init();
 }
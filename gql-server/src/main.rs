@@ -5,6 +5,7 @@ use std::path::PathBuf;
 
 use clap::Parser;
 use helpers::init_tracing;
+use telemetry_config::TelemetryConfig;
 
 mod defaults;
 mod helpers;
@@ -19,21 +20,45 @@ struct Args {
     #[arg(short = 'd', long = "db", env, num_args = 0..=1)]
     db: Option<String>,
 
+    /// Directory with the per-thread archive shards (bm-archive-thread-*.db)
+    /// written by the block manager with `--sqlite-shard-by-thread`. If set,
+    /// `--db` is ignored
+    #[arg(long, env)]
+    shards_dir: Option<PathBuf>,
+
     /// The host address and TCP port on which the service will accept
     /// connections (default: 127.0.0.1:3000)
     #[arg(short = 'l', long = "listen", env, num_args = 0..=1)]
     listen: Option<String>,
+
+    /// Maximum archive lag in ms (server time - latest block gen time) at
+    /// which `/readyz` reports the service as ready (default: 60000)
+    #[arg(long, env)]
+    max_archive_lag_ms: Option<u64>,
+    /// YAML file with OpenTelemetry exporters config. Unset fields are taken
+    /// from the `OTEL_*` environment variables
+    #[arg(long, env)]
+    telemetry_config: Option<PathBuf>,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    init_tracing();
-
     let args = Args::parse();
 
+    let telemetry = args
+        .telemetry_config
+        .as_ref()
+        .map(TelemetryConfig::from_file)
+        .transpose()?
+        .unwrap_or_default()
+        .with_env_defaults();
+    init_tracing(&telemetry);
+
     let db = PathBuf::from(args.db.unwrap_or(defaults::PATH_TO_DB.to_string()));
 
     let listen = args.listen.unwrap_or(defaults::LISTEN.to_string());
 
-    web::start(listen, db).await
+    let max_archive_lag_ms = args.max_archive_lag_ms.unwrap_or(defaults::MAX_ARCHIVE_LAG_MS);
+
+    web::start(listen, db, args.shards_dir, max_archive_lag_ms).await
 }
@@ -1,11 +1,11 @@
 // 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
 //
 
-use async_graphql::futures_util::TryStreamExt;
 use sqlx::prelude::FromRow;
 use sqlx::SqlitePool;
 
 use crate::defaults;
+use crate::schema::db::ArchiveShards;
 use crate::schema::graphql::query::PaginateDirection;
 use crate::schema::graphql::query::PaginationArgs;
 
@@ -47,8 +47,9 @@ pub struct BlockchainAccountsQueryArgs {
 }
 
 impl Account {
+    /// Shard results are merged in the query order.
     pub async fn list(
-        pool: &SqlitePool,
+        archive: &ArchiveShards,
         where_clause: String,
         order_by: String,
         limit: Option<i32>,
@@ -60,10 +61,7 @@ impl Account {
 
         let sql = format!("SELECT * FROM accounts {where_clause} {order_by} LIMIT {limit}");
         tracing::debug!("SQL: {sql}");
-        let accounts =
-            sqlx::query_as(&sql).fetch(pool).map_ok(|b| b).try_collect::<Vec<Account>>().await?;
-
-        Ok(accounts)
+        archive.ordered(&sql, &order_by, limit as usize).await
     }
 
     pub async fn by_address(
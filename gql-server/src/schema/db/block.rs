@@ -1,14 +1,21 @@
 // 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
 //
 
-use async_graphql::futures_util::TryStreamExt;
+use std::cmp::Ordering;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
 use sqlx::prelude::FromRow;
 use sqlx::SqlitePool;
 
 use crate::defaults;
 use crate::helpers::u64_to_string;
+use crate::schema::db::ArchiveShards;
 use crate::schema::graphql::query::PaginateDirection;
+use crate::schema::graphql_ext::blockchain_api::blocks::BlockchainBlocksOrderByField;
 use crate::schema::graphql_ext::blockchain_api::blocks::BlockchainBlocksQueryArgs;
+use crate::schema::graphql_ext::blockchain_api::filter::order_by_clause;
+use crate::schema::graphql_ext::QueryOrderByDirection;
 
 #[allow(dead_code)]
 #[derive(Clone, Debug, FromRow)]
@@ -63,9 +70,35 @@ pub struct Block {
     pub thread_id: Option<String>,
 }
 
+/// Latest archived block, used to estimate how far the archive is behind the
+/// network.
+#[derive(Clone, Debug, FromRow)]
+pub struct ArchiveHead {
+    pub seq_no: i64,
+    pub gen_utime: Option<i64>,
+    pub gen_utime_ms_part: Option<i64>,
+    pub chain_order: Option<String>,
+}
+
+/// Latest archived block time of a thread.
+#[derive(Clone, Debug, FromRow)]
+pub struct ThreadHead {
+    pub thread_id: Option<String>,
+    pub gen_utime: Option<i64>,
+}
+
+impl ArchiveHead {
+    pub fn lag_ms(&self) -> i64 {
+        let now_ms = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as i64;
+        let gen_time_ms = self.gen_utime.unwrap_or(0) * 1000 + self.gen_utime_ms_part.unwrap_or(0);
+        (now_ms - gen_time_ms).max(0)
+    }
+}
+
 impl Block {
+    /// Shard results are merged in the query order.
     pub async fn list(
-        pool: &SqlitePool,
+        archive: &ArchiveShards,
         where_clause: String,
         order_by: String,
         limit: Option<i32>,
@@ -77,7 +110,7 @@ impl Block {
 
         let sql = format!("SELECT * FROM blocks {where_clause} {order_by} LIMIT {limit}");
         tracing::debug!("SQL: {sql}");
-        let res = sqlx::query_as(&sql).fetch(pool).map_ok(|b| b).try_collect::<Vec<Block>>().await;
+        let res = archive.ordered(&sql, &order_by, limit as usize).await;
 
         let blocks = if let Err(err) = res {
             tracing::error!("ERROR: {:?}", err);
@@ -90,12 +123,64 @@ impl Block {
         Ok(blocks)
     }
 
-    pub async fn latest_block(pool: &SqlitePool) -> anyhow::Result<Option<Block>> {
-        let block = sqlx::query_as("SELECT * FROM blocks ORDER BY chain_order DESC LIMIT 1")
-            .fetch_optional(pool)
+    pub async fn latest_block(archive: &ArchiveShards) -> anyhow::Result<Option<Block>> {
+        let blocks = archive
+            .collect(|pool| async move {
+                let block: Option<Block> =
+                    sqlx::query_as("SELECT * FROM blocks ORDER BY chain_order DESC LIMIT 1")
+                        .fetch_optional(&pool)
+                        .await?;
+                Ok::<_, anyhow::Error>(block.into_iter().collect())
+            })
             .await?;
 
-        Ok(block)
+        Ok(blocks.into_iter().max_by(|a, b| a.chain_order.cmp(&b.chain_order)))
+    }
+
+    /// Latest archived block of all the shards.
+    pub async fn archive_head(archive: &ArchiveShards) -> anyhow::Result<Option<ArchiveHead>> {
+        let heads = archive
+            .collect(|pool| async move {
+                let head: Option<ArchiveHead> = sqlx::query_as(
+                    "SELECT seq_no, gen_utime, gen_utime_ms_part, chain_order FROM blocks ORDER BY chain_order DESC LIMIT 1",
+                )
+                .fetch_optional(&pool)
+                .await?;
+                Ok::<_, anyhow::Error>(head.into_iter().collect())
+            })
+            .await?;
+
+        Ok(heads.into_iter().max_by(|a, b| a.chain_order.cmp(&b.chain_order)))
+    }
+
+    /// Latest archived block time of every thread of the shard.
+    pub async fn thread_heads(pool: &SqlitePool) -> anyhow::Result<Vec<ThreadHead>> {
+        let heads = sqlx::query_as(
+            "SELECT thread_id, MAX(gen_utime) AS gen_utime FROM blocks GROUP BY thread_id",
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(heads)
+    }
+
+    /// Blocks with the chain order after `after` and before `end`, in the
+    /// chain order.
+    pub async fn in_chain_order_range(
+        pool: &SqlitePool,
+        after: &str,
+        end: &str,
+        limit: usize,
+    ) -> anyhow::Result<Vec<Block>> {
+        let blocks = sqlx::query_as(
+            "SELECT * FROM blocks WHERE chain_order > ?1 AND chain_order < ?2
+            ORDER BY chain_order LIMIT ?3",
+        )
+        .bind(after)
+        .bind(end)
+        .bind(limit as i64)
+        .fetch_all(pool)
+        .await?;
+        Ok(blocks)
     }
 
     pub async fn blockchain_blocks(
@@ -104,6 +189,11 @@ impl Block {
     ) -> anyhow::Result<Vec<Block>> {
         let direction = args.pagination.get_direction();
         let limit = args.pagination.get_limit();
+        let order_by = order_by_clause(
+            args.order_by.as_ref().map(|v| v.field.column()),
+            args.order_by.as_ref().and_then(|v| v.direction),
+            &args.pagination,
+        )?;
 
         let mut where_ops: Vec<String> = vec![];
 
@@ -129,6 +219,10 @@ impl Block {
             }
         }
 
+        if let Some(snapshot) = &args.snapshot {
+            snapshot.push_where_ops("chain_order", &mut where_ops);
+        }
+
         if let Some(min_tr_count) = args.min_tr_count {
             where_ops.push(format!("tr_count >= {min_tr_count}"));
         }
@@ -137,10 +231,18 @@ impl Block {
             where_ops.push(format!("tr_count <= {max_tr_count}"));
         }
 
-        let order_by = match direction {
-            PaginateDirection::Forward => "ASC",
-            PaginateDirection::Backward => "DESC",
-        };
+        if let Some(filter) = &args.filter {
+            filter.validate()?;
+            if let Some(workchain_id) = filter.workchain_id {
+                where_ops.push(format!("workchain_id = {workchain_id}"));
+            }
+            if let Some(thread_id) = &filter.thread_id {
+                where_ops.push(format!("thread_id = {thread_id:?}"));
+            }
+            if let Some(gen_utime) = &filter.gen_utime {
+                gen_utime.push_where_ops("gen_utime", &mut where_ops);
+            }
+        }
 
         let where_clause = if !where_ops.is_empty() {
             format!("WHERE {}", where_ops.join(" AND "))
@@ -148,9 +250,7 @@ impl Block {
             "".to_string()
         };
 
-        let sql = format!(
-            "SELECT * FROM blocks {where_clause} ORDER BY chain_order {order_by} LIMIT {limit}",
-        );
+        let sql = format!("SELECT * FROM blocks {where_clause} {order_by} LIMIT {limit}");
 
         tracing::trace!(target: "blockchain_api", "SQL: {sql}");
 
@@ -171,6 +271,28 @@ impl Block {
         }
     }
 
+    /// Order of the blocks returned by [`Block::blockchain_blocks`], used to
+    /// merge the pages of the archive shards.
+    pub fn blockchain_order(
+        args: &BlockchainBlocksQueryArgs,
+    ) -> impl Fn(&Block, &Block) -> Ordering {
+        let field = args.order_by.as_ref().map(|v| v.field);
+        let direction = args.order_by.as_ref().and_then(|v| v.direction);
+        move |a, b| {
+            let ordering = match field {
+                Some(BlockchainBlocksOrderByField::SeqNo) => a.seq_no.cmp(&b.seq_no),
+                Some(BlockchainBlocksOrderByField::GenUtime) => a.gen_utime.cmp(&b.gen_utime),
+                Some(BlockchainBlocksOrderByField::TrCount) => a.tr_count.cmp(&b.tr_count),
+                Some(BlockchainBlocksOrderByField::ChainOrder) | None => Ordering::Equal,
+            }
+            .then_with(|| a.chain_order.cmp(&b.chain_order));
+            match direction {
+                Some(QueryOrderByDirection::DESC) => ordering.reverse(),
+                _ => ordering,
+            }
+        }
+    }
+
     // pub async fn by_seq_no(pool: &SqlitePool, seq_no: i64) ->
     // anyhow::Result<Option<Block>> {     let block =
     // sqlx::query_as_unchecked!(         Block,
@@ -0,0 +1,50 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use async_graphql::ErrorExtensions;
+use sqlx::prelude::FromRow;
+use sqlx::SqlitePool;
+
+/// Archive entity that was moved from the archive DB to cold storage.
+#[derive(Clone, Debug, FromRow)]
+pub struct ColdStorageEntry {
+    pub kind: String,
+    pub uri: String,
+    pub min_gen_utime: i64,
+    pub max_gen_utime: i64,
+}
+
+impl ColdStorageEntry {
+    pub async fn find(pool: &SqlitePool, id: &str) -> Option<ColdStorageEntry> {
+        let result = sqlx::query_as(
+            "SELECT i.kind, s.uri, s.min_gen_utime, s.max_gen_utime
+            FROM cold_storage_index i JOIN cold_storage_segments s ON s.rowid = i.segment
+            WHERE i.id = ?1",
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await;
+
+        // Archives created before cold storage support have no such tables
+        result.unwrap_or_else(|err| {
+            tracing::debug!("Failed to look up cold storage: {err}");
+            None
+        })
+    }
+
+    /// Error returned instead of an entity that is no longer in the archive DB.
+    pub fn to_error(&self) -> async_graphql::Error {
+        async_graphql::Error::new(format!("The {} was moved to cold storage", self.kind))
+            .extend_with(|_, e| {
+                e.set("code", "MOVED_TO_COLD_STORAGE");
+                e.set("segment", self.uri.clone());
+                e.set("min_gen_utime", self.min_gen_utime);
+                e.set("max_gen_utime", self.max_gen_utime);
+                e.set(
+                    "retrieval_hint",
+                    "Download the segment, it is a zstd compressed JSON lines file with the \
+                     archive rows",
+                );
+            })
+    }
+}
@@ -0,0 +1,48 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use sqlx::prelude::FromRow;
+use sqlx::SqlitePool;
+
+/// Fee accounting of an archived block, amounts are decimal strings.
+#[derive(Clone, Debug, FromRow)]
+pub struct BlockFees {
+    pub block_id: String,
+    pub thread_id: Option<String>,
+    pub seq_no: i64,
+    pub gen_utime: Option<i64>,
+    pub fees_collected: String,
+    pub minted: String,
+    pub burned: String,
+    pub minted_by_dapp: Option<String>,
+}
+
+impl BlockFees {
+    pub async fn by_block_id(pool: &SqlitePool, block_id: &str) -> anyhow::Result<Option<Self>> {
+        let fees = sqlx::query_as("SELECT * FROM block_fees WHERE block_id = ?1")
+            .bind(block_id)
+            .fetch_optional(pool)
+            .await?;
+        Ok(fees)
+    }
+
+    /// Fees of the thread blocks with seq no in the inclusive range.
+    pub async fn in_seq_no_range(
+        pool: &SqlitePool,
+        thread_id: &str,
+        seq_no_start: i64,
+        seq_no_end: i64,
+    ) -> anyhow::Result<Vec<Self>> {
+        let fees = sqlx::query_as(
+            "SELECT * FROM block_fees
+            WHERE thread_id = ?1 AND seq_no >= ?2 AND seq_no <= ?3
+            ORDER BY seq_no",
+        )
+        .bind(thread_id)
+        .bind(seq_no_start)
+        .bind(seq_no_end)
+        .fetch_all(pool)
+        .await?;
+        Ok(fees)
+    }
+}
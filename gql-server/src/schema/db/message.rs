@@ -7,10 +7,18 @@ use sqlx::SqlitePool;
 
 use crate::defaults;
 use crate::helpers::u64_to_string;
+use crate::schema::db::ArchiveShards;
 use crate::schema::graphql::query::PaginateDirection;
 use crate::schema::graphql::query::PaginationArgs;
 use crate::schema::graphql_ext::blockchain_api::account::BlockchainMasterSeqNoFilter;
+use crate::schema::graphql_ext::blockchain_api::account::BlockchainMessageDirectionFilterEnum;
 use crate::schema::graphql_ext::blockchain_api::account::BlockchainMessageTypeFilterEnum;
+use crate::schema::graphql_ext::blockchain_api::filter::parse_amount;
+use crate::schema::graphql_ext::blockchain_api::filter::push_hex_amount_range;
+use crate::schema::graphql_ext::blockchain_api::filter::validate_address;
+use crate::schema::graphql_ext::blockchain_api::filter::BlockchainSnapshot;
+
+const MAX_COUNTERPARTIES: usize = 5;
 
 #[allow(dead_code)]
 #[derive(Clone, Debug, FromRow)]
@@ -23,9 +31,12 @@ pub struct InBlockMessage {
 pub struct AccountMessagesQueryArgs {
     allow_latest_inconsistent_data: Option<bool>,
     master_seq_no_range: Option<BlockchainMasterSeqNoFilter>,
+    snapshot: Option<BlockchainSnapshot>,
+    direction: Option<BlockchainMessageDirectionFilterEnum>,
     counterparties: Option<Vec<String>>,
     msg_type: Option<Vec<BlockchainMessageTypeFilterEnum>>,
     min_value: Option<String>,
+    max_value: Option<String>,
     pub pagination: PaginationArgs,
 }
 
@@ -34,21 +45,65 @@ impl AccountMessagesQueryArgs {
     pub fn new(
         allow_latest_inconsistent_data: Option<bool>,
         master_seq_no_range: Option<BlockchainMasterSeqNoFilter>,
+        snapshot: Option<BlockchainSnapshot>,
+        direction: Option<BlockchainMessageDirectionFilterEnum>,
         counterparties: Option<Vec<String>>,
         msg_type: Option<Vec<BlockchainMessageTypeFilterEnum>>,
         min_value: Option<String>,
+        max_value: Option<String>,
         pagination: PaginationArgs,
     ) -> Self {
         Self {
             allow_latest_inconsistent_data,
             master_seq_no_range,
+            snapshot,
+            direction,
             counterparties,
             msg_type,
             min_value,
+            max_value,
             pagination,
         }
     }
 
+    fn has_direction(&self, value: BlockchainMessageDirectionFilterEnum) -> bool {
+        self.direction.is_none_or(|direction| direction == value)
+    }
+
+    fn has_inbound(&self) -> bool {
+        (self.has_ext_in() || self.has_int_in())
+            && self.has_direction(BlockchainMessageDirectionFilterEnum::In)
+    }
+
+    fn has_outbound(&self) -> bool {
+        (self.has_ext_out() || self.has_int_out())
+            && self.has_direction(BlockchainMessageDirectionFilterEnum::Out)
+    }
+
+    /// Returns the pagination cursor of the message, matches the cursor field
+    /// of [`Message::account_messages`] query.
+    pub fn cursor(&self, message: &Message) -> Option<String> {
+        if self.has_inbound() {
+            message.dst_chain_order.clone().or_else(|| message.src_chain_order.clone())
+        } else {
+            message.src_chain_order.clone()
+        }
+    }
+
+    fn counterparties(&self) -> anyhow::Result<Option<String>> {
+        let Some(counterparties) = self.counterparties.as_ref().filter(|list| !list.is_empty())
+        else {
+            return Ok(None);
+        };
+        if counterparties.len() > MAX_COUNTERPARTIES {
+            anyhow::bail!("Too many counterparties: max {MAX_COUNTERPARTIES} are allowed");
+        }
+        for counterparty in counterparties {
+            validate_address(counterparty)?;
+        }
+        Ok(Some(counterparties.iter().map(|c| format!("{c:?}")).collect::<Vec<_>>().join(",")))
+    }
+
     fn has_msg_type(&self, value: BlockchainMessageTypeFilterEnum) -> bool {
         match &self.msg_type {
             Some(list) => list.contains(&value),
@@ -110,8 +165,9 @@ pub struct Message {
 }
 
 impl Message {
+    /// Shard results are merged in the query order.
     pub async fn list(
-        pool: &SqlitePool,
+        archive: &ArchiveShards,
         filter: String,
         order_by: String,
         limit: Option<i32>,
@@ -123,10 +179,7 @@ impl Message {
 
         let sql = format!("SELECT * FROM messages {filter} {order_by} LIMIT {limit}");
         tracing::debug!("SQL: {sql}");
-        let messages =
-            sqlx::query_as(&sql).fetch(pool).map_ok(|b| b).try_collect::<Vec<Message>>().await?;
-
-        Ok(messages)
+        archive.ordered(&sql, &order_by, limit as usize).await
     }
 
     pub async fn in_block_msgs(
@@ -158,8 +211,12 @@ impl Message {
         account: String,
         args: &AccountMessagesQueryArgs,
     ) -> anyhow::Result<Vec<Message>> {
-        let has_inbound = args.has_ext_in() || args.has_int_in();
-        let has_outbound = args.has_ext_out() || args.has_int_out();
+        let has_inbound = args.has_inbound();
+        let has_outbound = args.has_outbound();
+        if !has_inbound && !has_outbound {
+            return Ok(vec![]);
+        }
+        let counterparties = args.counterparties()?;
         let limit = args.pagination.get_limit();
         let direction = args.pagination.get_direction();
 
@@ -168,11 +225,17 @@ impl Message {
         {
             let mut ops = vec![];
             if has_inbound {
-                ops.push(format!("dst={account:?}"));
+                match &counterparties {
+                    Some(list) => ops.push(format!("(dst={account:?} AND src IN ({list}))")),
+                    None => ops.push(format!("dst={account:?}")),
+                }
                 cursor_field = "dst_chain_order";
             }
             if has_outbound {
-                ops.push(format!("src={account:?}"));
+                match &counterparties {
+                    Some(list) => ops.push(format!("(src={account:?} AND dst IN ({list}))")),
+                    None => ops.push(format!("src={account:?}")),
+                }
                 cursor_field = "src_chain_order";
             }
             if has_inbound && has_outbound {
@@ -193,6 +256,15 @@ impl Message {
             }
         }
 
+        let min_value = args.min_value.as_deref().map(parse_amount).transpose()?;
+        let max_value = args.max_value.as_deref().map(parse_amount).transpose()?;
+        if let (Some(min), Some(max)) = (min_value, max_value) {
+            if min > max {
+                anyhow::bail!("Invalid value range: min {min} is greater than max {max}");
+            }
+        }
+        push_hex_amount_range("value", min_value, max_value, &mut where_ops);
+
         if let Some(after) = &args.pagination.after {
             if !after.is_empty() {
                 where_ops.push(format!("{cursor_field} > {after:?}"));
@@ -215,6 +287,11 @@ impl Message {
             }
         }
 
+        // Pinned by the cursor, so the pages of the snapshot don't change
+        if let Some(snapshot) = &args.snapshot {
+            snapshot.push_where_ops(cursor_field, &mut where_ops);
+        }
+
         let order_by_sort = match direction {
             PaginateDirection::Forward => "ASC",
             PaginateDirection::Backward => "DESC",
@@ -2,11 +2,21 @@
 //
 pub mod account;
 pub mod block;
+pub mod cold_storage;
+pub mod fees;
 pub mod message;
+pub mod shards;
+pub mod statistics;
 pub(crate) mod transaction;
 
 pub use account::Account;
+pub use block::ArchiveHead;
 pub use block::Block;
+pub use block::ThreadHead;
+pub use cold_storage::ColdStorageEntry;
+pub use fees::BlockFees;
 pub(crate) use message::AccountMessagesQueryArgs;
 pub use message::Message;
+pub use shards::ArchiveShards;
+pub use statistics::StatisticsRollup;
 pub(crate) use transaction::Transaction;
@@ -0,0 +1,329 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::cmp::Ordering;
+use std::future::Future;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::future;
+use parking_lot::RwLock;
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::sqlite::SqliteRow;
+use sqlx::FromRow;
+use sqlx::Row;
+use sqlx::SqlitePool;
+use sqlx::TypeInfo;
+use sqlx::ValueRef;
+
+use crate::schema::graphql::query::PaginateDirection;
+use crate::schema::graphql::query::PaginationArgs;
+
+const SHARD_PREFIX: &str = "bm-archive-thread-";
+// Period of looking for the shards of new threads
+const SHARDS_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Archive databases the queries run on: a single database or the per-thread
+/// shards written by the block manager with `--sqlite-shard-by-thread`.
+/// Queries run on every shard concurrently and their results are merged.
+#[derive(Clone)]
+pub struct ArchiveShards {
+    shards: Arc<RwLock<Vec<(String, SqlitePool)>>>,
+}
+
+impl ArchiveShards {
+    pub fn single(pool: SqlitePool) -> Self {
+        Self { shards: Arc::new(RwLock::new(vec![(String::new(), pool)])) }
+    }
+
+    /// Opens the shards stored in `shards_dir` and keeps looking for the
+    /// shards of new threads.
+    pub async fn open_dir(shards_dir: PathBuf) -> anyhow::Result<Self> {
+        anyhow::ensure!(shards_dir.is_dir(), "Shards dir not found: {}", shards_dir.display());
+        let archive = Self { shards: Arc::new(RwLock::new(vec![])) };
+        archive.refresh(&shards_dir).await?;
+        let refreshed = archive.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(SHARDS_REFRESH_INTERVAL).await;
+                if let Err(err) = refreshed.refresh(&shards_dir).await {
+                    tracing::error!("Failed to refresh archive shards: {err}");
+                }
+            }
+        });
+        Ok(archive)
+    }
+
+    async fn refresh(&self, shards_dir: &Path) -> anyhow::Result<()> {
+        for entry in std::fs::read_dir(shards_dir)? {
+            let path = entry?.path();
+            let Some(thread_id) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(shard_thread_id)
+                .map(|thread_id| thread_id.to_string())
+            else {
+                continue;
+            };
+            if self.shards.read().iter().any(|(opened, _)| *opened == thread_id) {
+                continue;
+            }
+            let pool = SqlitePool::connect_with(SqliteConnectOptions::new().filename(&path))
+                .await
+                .map_err(|e| anyhow::format_err!("Failed to open shard {path:?}: {e}"))?;
+            tracing::info!("Archive shard opened: {path:?}");
+            self.shards.write().push((thread_id, pool));
+        }
+        Ok(())
+    }
+
+    fn pools(&self) -> Vec<SqlitePool> {
+        self.shards.read().iter().map(|(_, pool)| pool.clone()).collect()
+    }
+
+    pub fn is_sharded(&self) -> bool {
+        self.shards.read().first().is_some_and(|(thread_id, _)| !thread_id.is_empty())
+    }
+
+    /// Runs the query on every shard and concatenates the results.
+    pub async fn collect<T, F, Fut>(&self, query: F) -> anyhow::Result<Vec<T>>
+    where
+        F: Fn(SqlitePool) -> Fut,
+        Fut: Future<Output = anyhow::Result<Vec<T>>>,
+    {
+        let results = future::try_join_all(self.pools().into_iter().map(query)).await?;
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    /// Runs the lookup on every shard and returns the first found row.
+    pub async fn find<T, F, Fut>(&self, query: F) -> anyhow::Result<Option<T>>
+    where
+        F: Fn(SqlitePool) -> Fut,
+        Fut: Future<Output = anyhow::Result<Option<T>>>,
+    {
+        let results = future::try_join_all(self.pools().into_iter().map(query)).await?;
+        Ok(results.into_iter().flatten().next())
+    }
+
+    /// Runs the list query with the `ORDER BY` clause on every shard and
+    /// merges the rows in the query order, so the limit keeps the same rows as
+    /// on a single database. Rows of a query without the order are kept in the
+    /// shard order.
+    pub async fn ordered<T>(
+        &self,
+        sql: &str,
+        order_by: &str,
+        limit: usize,
+    ) -> anyhow::Result<Vec<T>>
+    where
+        T: for<'r> FromRow<'r, SqliteRow> + Send + Unpin,
+    {
+        let columns = order_by_columns(order_by);
+        let columns = &columns;
+        let rows = self
+            .collect(|pool| async move {
+                let rows = sqlx::query(sql).fetch_all(&pool).await?;
+                rows.iter()
+                    .map(|row| {
+                        let key = columns
+                            .iter()
+                            .map(|(column, _)| SortValue::from_row(row, column))
+                            .collect::<Vec<_>>();
+                        Ok((key, T::from_row(row)?))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()
+            })
+            .await?;
+        Ok(merge_ordered(rows, columns, limit))
+    }
+
+    /// Fetches the cursor page from every shard and merges them into one
+    /// page. `compare` must match the order of the rows in the shard pages.
+    pub async fn page<T, F, Fut>(
+        &self,
+        pagination: &PaginationArgs,
+        compare: impl Fn(&T, &T) -> Ordering,
+        query: F,
+    ) -> anyhow::Result<Vec<T>>
+    where
+        F: Fn(SqlitePool) -> Fut,
+        Fut: Future<Output = anyhow::Result<Vec<T>>>,
+    {
+        let rows = self.collect(query).await?;
+        Ok(merge_page(rows, pagination, compare))
+    }
+}
+
+/// Every shard page holds up to the pagination limit rows next to the
+/// cursor, so the merged page keeps the nearest ones: the first rows for the
+/// forward pagination and the last rows for the backward one.
+pub fn merge_page<T>(
+    mut rows: Vec<T>,
+    pagination: &PaginationArgs,
+    compare: impl Fn(&T, &T) -> Ordering,
+) -> Vec<T> {
+    rows.sort_by(compare);
+    let limit = pagination.get_limit();
+    if rows.len() > limit {
+        match pagination.get_direction() {
+            PaginateDirection::Forward => rows.truncate(limit),
+            PaginateDirection::Backward => {
+                rows.drain(..rows.len() - limit);
+            }
+        }
+    }
+    rows
+}
+
+/// Value of the sort column compared as SQLite does: NULL first, then the
+/// numbers, the texts (binary collation) and the blobs.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SortValue {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl SortValue {
+    fn from_row(row: &SqliteRow, column: &str) -> Self {
+        let Ok(value) = row.try_get_raw(column) else {
+            return Self::Null;
+        };
+        if value.is_null() {
+            return Self::Null;
+        }
+        let value = match value.type_info().name() {
+            "INTEGER" => row.try_get(column).map(Self::Integer),
+            "REAL" => row.try_get(column).map(Self::Real),
+            "TEXT" => row.try_get(column).map(Self::Text),
+            _ => row.try_get(column).map(Self::Blob),
+        };
+        value.unwrap_or(Self::Null)
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Self::Null => 0,
+            Self::Integer(_) | Self::Real(_) => 1,
+            Self::Text(_) => 2,
+            Self::Blob(_) => 3,
+        }
+    }
+
+    fn compare(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Integer(a), Self::Integer(b)) => a.cmp(b),
+            (Self::Integer(a), Self::Real(b)) => (*a as f64).total_cmp(b),
+            (Self::Real(a), Self::Integer(b)) => a.total_cmp(&(*b as f64)),
+            (Self::Real(a), Self::Real(b)) => a.total_cmp(b),
+            (Self::Text(a), Self::Text(b)) => a.cmp(b),
+            (Self::Blob(a), Self::Blob(b)) => a.cmp(b),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+/// Columns of the `ORDER BY` clause with the descending flag.
+fn order_by_columns(order_by: &str) -> Vec<(String, bool)> {
+    let order_by = order_by.trim();
+    let Some(columns) = order_by.strip_prefix("ORDER BY") else {
+        return vec![];
+    };
+    columns
+        .split(',')
+        .filter_map(|column| {
+            let mut parts = column.split_whitespace();
+            let name = parts.next()?.to_string();
+            let descending = parts.next().is_some_and(|direction| direction == "DESC");
+            Some((name, descending))
+        })
+        .collect()
+}
+
+/// Sorts the rows of all the shards by their sort keys and keeps the first
+/// `limit` ones. The sort is stable, so rows with equal keys stay in the
+/// shard order.
+pub fn merge_ordered<T>(
+    mut rows: Vec<(Vec<SortValue>, T)>,
+    columns: &[(String, bool)],
+    limit: usize,
+) -> Vec<T> {
+    if !columns.is_empty() {
+        rows.sort_by(|(a, _), (b, _)| {
+            a.iter()
+                .zip(b)
+                .zip(columns)
+                .map(|((a, b), (_, descending))| {
+                    let ordering = a.compare(b);
+                    if *descending {
+                        ordering.reverse()
+                    } else {
+                        ordering
+                    }
+                })
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(Ordering::Equal)
+        });
+    }
+    rows.truncate(limit);
+    rows.into_iter().map(|(_, row)| row).collect()
+}
+
+fn shard_thread_id(file_name: &str) -> Option<&str> {
+    let thread_id = file_name.strip_prefix(SHARD_PREFIX)?.strip_suffix(".db")?;
+    // Rotated shards have the timestamp suffix and are not served
+    if thread_id.is_empty() || !thread_id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(thread_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pagination(first: Option<usize>, last: Option<usize>) -> PaginationArgs {
+        PaginationArgs { first, after: None, last, before: None }
+    }
+
+    #[test]
+    fn test_merge_page() {
+        // Shard pages of 3 rows (2 requested + 1 to detect the next page)
+        let rows = vec![1, 4, 5, 2, 3, 6];
+        assert_eq!(merge_page(rows.clone(), &pagination(Some(2), None), i32::cmp), vec![1, 2, 3]);
+        assert_eq!(merge_page(rows, &pagination(None, Some(2)), i32::cmp), vec![4, 5, 6]);
+        assert_eq!(merge_page(vec![2, 1], &pagination(Some(2), None), i32::cmp), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_merge_ordered() {
+        let columns = order_by_columns(" ORDER BY seq_no DESC,id ASC ");
+        assert_eq!(columns, vec![("seq_no".to_string(), true), ("id".to_string(), false)]);
+        let key = |seq_no: i64, id: &str| {
+            vec![SortValue::Integer(seq_no), SortValue::Text(id.to_string())]
+        };
+        // Shard rows are ordered within each shard only
+        let rows = vec![
+            (key(5, "a"), 1),
+            (key(1, "a"), 2),
+            (key(7, "b"), 3),
+            (key(5, "0"), 4),
+            (vec![SortValue::Null, SortValue::Null], 5),
+        ];
+        assert_eq!(merge_ordered(rows.clone(), &columns, 3), vec![3, 4, 1]);
+        assert_eq!(merge_ordered(rows, &[], 3), vec![1, 2, 3]);
+        assert_eq!(SortValue::Integer(2).compare(&SortValue::Real(1.5)), Ordering::Greater);
+        assert_eq!(SortValue::Null.compare(&SortValue::Integer(0)), Ordering::Less);
+    }
+
+    #[test]
+    fn test_shard_thread_id() {
+        assert_eq!(shard_thread_id("bm-archive-thread-00ff.db"), Some("00ff"));
+        assert_eq!(shard_thread_id("bm-archive-thread-00ff-1700000000.db"), None);
+        assert_eq!(shard_thread_id("bm-archive.db"), None);
+    }
+}
@@ -0,0 +1,39 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use sqlx::prelude::FromRow;
+use sqlx::SqlitePool;
+
+/// Hourly or daily rollup of an archive shard, maintained by the archive
+/// writer. Fees are a decimal string.
+#[derive(Clone, Debug, FromRow)]
+pub struct StatisticsRollup {
+    pub period: String,
+    pub period_start: i64,
+    pub blocks: i64,
+    pub transactions: i64,
+    pub fees_collected: String,
+    pub active_accounts: i64,
+}
+
+impl StatisticsRollup {
+    /// Rollups of the period starting in the half-open time range.
+    pub async fn in_range(
+        pool: &SqlitePool,
+        period: &str,
+        from: i64,
+        to: i64,
+    ) -> anyhow::Result<Vec<Self>> {
+        let rollups = sqlx::query_as(
+            "SELECT * FROM statistics_rollups
+            WHERE period = ?1 AND period_start >= ?2 AND period_start < ?3
+            ORDER BY period_start",
+        )
+        .bind(period)
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await?;
+        Ok(rollups)
+    }
+}
@@ -1,21 +1,28 @@
 // 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
 //
 
-use async_graphql::futures_util::TryStreamExt;
+use std::cmp::Ordering;
+
 use sqlx::prelude::FromRow;
 use sqlx::SqlitePool;
 
 use crate::defaults;
 use crate::helpers::u64_to_string;
+use crate::schema::db::ArchiveShards;
 use crate::schema::graphql::query::PaginateDirection;
 use crate::schema::graphql::query::PaginationArgs;
 use crate::schema::graphql_ext::blockchain_api::account::BlockchainMasterSeqNoFilter;
+use crate::schema::graphql_ext::blockchain_api::filter::order_by_clause;
+use crate::schema::graphql_ext::blockchain_api::filter::BlockchainSnapshot;
+use crate::schema::graphql_ext::blockchain_api::transactions::BlockchainTransactionsOrderByField;
 use crate::schema::graphql_ext::blockchain_api::transactions::BlockchainTransactionsQueryArgs;
+use crate::schema::graphql_ext::QueryOrderByDirection;
 
 #[allow(dead_code)]
 pub struct AccountTransactionsQueryArgs {
     allow_latest_inconsistent_data: Option<bool>,
     block_seq_no_range: Option<BlockchainMasterSeqNoFilter>,
+    snapshot: Option<BlockchainSnapshot>,
     aborted: Option<bool>,
     min_balance_delta: Option<String>,
     max_balance_delta: Option<String>,
@@ -27,6 +34,7 @@ impl AccountTransactionsQueryArgs {
     pub fn new(
         allow_latest_inconsistent_data: Option<bool>,
         block_seq_no_range: Option<BlockchainMasterSeqNoFilter>,
+        snapshot: Option<BlockchainSnapshot>,
         aborted: Option<bool>,
         min_balance_delta: Option<String>,
         max_balance_delta: Option<String>,
@@ -35,6 +43,7 @@ impl AccountTransactionsQueryArgs {
         Self {
             allow_latest_inconsistent_data,
             block_seq_no_range,
+            snapshot,
             aborted,
             min_balance_delta,
             max_balance_delta,
@@ -105,8 +114,9 @@ pub struct Transaction {
 }
 
 impl Transaction {
+    /// Shard results are merged in the query order.
     pub async fn list(
-        pool: &SqlitePool,
+        archive: &ArchiveShards,
         filter: String,
         order_by: String,
         limit: Option<i32>,
@@ -118,13 +128,7 @@ impl Transaction {
 
         let sql = format!("SELECT * FROM transactions {filter} {order_by} LIMIT {limit}");
         tracing::debug!("SQL: {sql}");
-        let transactions = sqlx::query_as(&sql)
-            .fetch(pool)
-            .map_ok(|b| b)
-            .try_collect::<Vec<Transaction>>()
-            .await?;
-
-        Ok(transactions)
+        archive.ordered(&sql, &order_by, limit as usize).await
     }
 
     pub async fn blockchain_transactions(
@@ -133,6 +137,11 @@ impl Transaction {
     ) -> anyhow::Result<Vec<Transaction>> {
         let direction = args.pagination.get_direction();
         let limit = args.pagination.get_limit();
+        let order_by = order_by_clause(
+            args.order_by.as_ref().map(|v| v.field.column()),
+            args.order_by.as_ref().and_then(|v| v.direction),
+            &args.pagination,
+        )?;
 
         let mut where_ops: Vec<String> = vec![];
 
@@ -160,10 +169,30 @@ impl Transaction {
             where_ops.push(format!("balance_delta+0 <= {}", max_balance_delta.parse::<u128>()?));
         }
 
-        let order_by = match direction {
-            PaginateDirection::Forward => "ASC",
-            PaginateDirection::Backward => "DESC",
-        };
+        if let Some(filter) = &args.filter {
+            filter.validate()?;
+            if let Some(workchain_id) = filter.workchain_id {
+                where_ops.push(format!("workchain_id = {workchain_id}"));
+            }
+            if let Some(aborted) = filter.aborted {
+                where_ops.push(format!("aborted = {}", aborted as u8));
+            }
+            if let Some(thread_id) = &filter.thread_id {
+                where_ops.push(format!(
+                    "block_id IN (SELECT id FROM blocks WHERE thread_id = {thread_id:?})"
+                ));
+            }
+            if let Some(gen_utime) = &filter.gen_utime {
+                gen_utime.push_where_ops("now", &mut where_ops);
+            }
+            if let Some(chain_order) = &filter.chain_order {
+                chain_order.push_where_ops(&mut where_ops);
+            }
+        }
+
+        if let Some(snapshot) = &args.snapshot {
+            snapshot.push_where_ops("chain_order", &mut where_ops);
+        }
 
         let where_clause = if !where_ops.is_empty() {
             format!("WHERE {}", where_ops.join(" AND "))
@@ -171,9 +200,7 @@ impl Transaction {
             "".to_string()
         };
 
-        let sql = format!(
-            "SELECT * FROM transactions {where_clause} ORDER BY chain_order {order_by} LIMIT {limit}",
-        );
+        let sql = format!("SELECT * FROM transactions {where_clause} {order_by} LIMIT {limit}");
 
         tracing::trace!(target: "blockchain_api", "SQL: {sql}");
 
@@ -197,6 +224,27 @@ impl Transaction {
         })
     }
 
+    /// Order of the transactions returned by
+    /// [`Transaction::blockchain_transactions`], used to merge the pages of
+    /// the archive shards.
+    pub fn blockchain_order(
+        args: &BlockchainTransactionsQueryArgs,
+    ) -> impl Fn(&Transaction, &Transaction) -> Ordering {
+        let field = args.order_by.as_ref().map(|v| v.field);
+        let direction = args.order_by.as_ref().and_then(|v| v.direction);
+        move |a, b| {
+            let ordering = match field {
+                Some(BlockchainTransactionsOrderByField::GenUtime) => a.now.cmp(&b.now),
+                Some(BlockchainTransactionsOrderByField::ChainOrder) | None => Ordering::Equal,
+            }
+            .then_with(|| a.chain_order.cmp(&b.chain_order));
+            match direction {
+                Some(QueryOrderByDirection::DESC) => ordering.reverse(),
+                _ => ordering,
+            }
+        }
+    }
+
     pub async fn account_transactions(
         pool: &SqlitePool,
         account: String,
@@ -232,6 +280,10 @@ impl Transaction {
             }
         }
 
+        if let Some(snapshot) = &args.snapshot {
+            snapshot.push_where_ops("chain_order", &mut where_ops);
+        }
+
         if let Some(min_balance_delta) = &args.min_balance_delta {
             where_ops.push(format!("balance_delta+0 >= {}", min_balance_delta.parse::<u128>()?));
         }
@@ -279,6 +331,26 @@ impl Transaction {
         })
     }
 
+    /// Transactions with the chain order after `after` and before `end`, in
+    /// the chain order.
+    pub async fn in_chain_order_range(
+        pool: &SqlitePool,
+        after: &str,
+        end: &str,
+        limit: usize,
+    ) -> anyhow::Result<Vec<Transaction>> {
+        let transactions = sqlx::query_as(
+            "SELECT * FROM transactions WHERE chain_order > ?1 AND chain_order < ?2
+            ORDER BY chain_order LIMIT ?3",
+        )
+        .bind(after)
+        .bind(end)
+        .bind(limit as i64)
+        .fetch_all(pool)
+        .await?;
+        Ok(transactions)
+    }
+
     pub async fn by_in_message(
         pool: &SqlitePool,
         msg_id: &str,
@@ -11,9 +11,9 @@ use async_graphql::Context;
 use async_graphql::Object;
 use async_graphql::OutputType;
 use async_graphql::SimpleObject;
-use sqlx::SqlitePool;
 
 use crate::schema::db;
+use crate::schema::db::ArchiveShards;
 use crate::schema::graphql::query::PaginationArgs;
 use crate::schema::graphql_std::events::Event;
 
@@ -63,8 +63,11 @@ impl AccountQuery {
             return Some(preloaded.clone().into());
         }
 
-        let pool = ctx.data::<SqlitePool>().unwrap();
-        db::Account::by_address(pool, Some(self.address.clone()))
+        let archive = ctx.data::<ArchiveShards>().unwrap();
+        archive
+            .find(|pool| async move {
+                db::Account::by_address(&pool, Some(self.address.clone())).await
+            })
             .await
             .unwrap()
             .map(|db_account| db_account.into())
@@ -99,12 +102,19 @@ impl AccountQuery {
             );
 
             let pagination = PaginationArgs { first, after, last, before };
-            let mut messages = db::Message::account_events(
-                ctx.data::<SqlitePool>().unwrap(),
-                self.address.clone(),
-                &pagination,
-            )
-            .await?;
+            let pagination_ref = &pagination;
+            let mut messages = ctx
+                .data::<ArchiveShards>()
+                .unwrap()
+                .page(
+                    &pagination,
+                    |a: &db::Message, b| a.msg_chain_order.cmp(&b.msg_chain_order),
+                    |pool| async move {
+                        db::Message::account_events(&pool, self.address.clone(), pagination_ref)
+                            .await
+                    },
+                )
+                .await?;
 
             let (has_previous_page, has_next_page) = pagination.get_bound_markers(messages.len());
             tracing::debug!("has_previous_page={:?}, after={:?}", has_previous_page, has_next_page);
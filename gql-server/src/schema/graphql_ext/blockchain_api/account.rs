@@ -13,11 +13,12 @@ use async_graphql::Enum;
 use async_graphql::InputObject;
 use async_graphql::Object;
 use async_graphql::OutputType;
-use sqlx::SqlitePool;
 
+use super::filter::BlockchainSnapshot;
 use super::transactions::BlockchainTransaction;
 use crate::schema::db;
 use crate::schema::db::transaction::AccountTransactionsQueryArgs;
+use crate::schema::db::ArchiveShards;
 use crate::schema::graphql::account::Account;
 use crate::schema::graphql::query::PaginationArgs;
 use crate::schema::graphql::transaction::Transaction;
@@ -52,6 +53,15 @@ impl From<BlockchainMessageTypeFilterEnum> for u8 {
     }
 }
 
+#[derive(Enum, Clone, Copy, PartialEq, Eq)]
+#[graphql(rename_items = "PascalCase")]
+pub enum BlockchainMessageDirectionFilterEnum {
+    /// Messages received by the account
+    In,
+    /// Messages sent by the account
+    Out,
+}
+
 #[derive(Clone, InputObject)]
 pub struct BlockchainMasterSeqNoFilter {
     pub start: Option<i32>,
@@ -95,6 +105,7 @@ pub struct BlockchainAccountQuery<'a> {
     pub ctx: &'a Context<'a>,
     pub address: String,
     pub preloaded: Option<db::Account>,
+    pub snapshot: Option<BlockchainSnapshot>,
 }
 
 #[Object]
@@ -111,8 +122,11 @@ impl BlockchainAccountQuery<'_> {
         if let Some(preloaded) = &self.preloaded {
             return Some(preloaded.clone().into());
         }
-        let pool = self.ctx.data::<SqlitePool>().unwrap();
-        db::Account::by_address(pool, Some(self.address.clone()))
+        let archive = self.ctx.data::<ArchiveShards>().unwrap();
+        archive
+            .find(|pool| async move {
+                db::Account::by_address(&pool, Some(self.address.clone())).await
+            })
             .await
             .unwrap()
             .map(|db_account| db_account.into())
@@ -131,9 +145,13 @@ impl BlockchainAccountQuery<'_> {
         #[graphql(name = "master_seq_no_range")] block_seq_no_range: Option<
             BlockchainMasterSeqNoFilter,
         >,
+        #[graphql(
+            desc = "Filter messages by direction: received (In) or sent (Out) by the account (all messages if not specified)."
+        )]
+        direction: Option<BlockchainMessageDirectionFilterEnum>,
         #[graphql(
             name = "counterparties",
-            desc = "Filter messages by counterparties (max - 5 counterparties)."
+            desc = "Filter messages by counterparties (max - 5 counterparties): senders of received and receivers of sent messages."
         )]
         counterparties: Option<Vec<String>>,
         #[graphql(
@@ -146,6 +164,11 @@ impl BlockchainAccountQuery<'_> {
             desc = "Optional filter by min value (unoptimized, query could be dropped by timeout)."
         )]
         min_value: Option<String>,
+        #[graphql(
+            name = "max_value",
+            desc = "Optional filter by max value (unoptimized, query could be dropped by timeout)."
+        )]
+        max_value: Option<String>,
         #[graphql(desc = "This field is mutually exclusive with 'last'.")] first: Option<i32>,
         after: Option<String>,
         #[graphql(desc = "This field is mutually exclusive with 'first'.")] last: Option<i32>,
@@ -180,17 +203,27 @@ impl BlockchainAccountQuery<'_> {
             let args = db::AccountMessagesQueryArgs::new(
                 allow_latest_inconsistent_data,
                 block_seq_no_range,
+                self.snapshot,
+                direction,
                 counterparties,
                 msg_type,
                 min_value,
+                max_value,
                 PaginationArgs { first, after, last, before },
             );
-            let mut messages = db::Message::account_messages(
-                self.ctx.data::<SqlitePool>().unwrap(),
-                self.address.clone(),
-                &args,
-            )
-            .await?;
+            let args_ref = &args;
+            let mut messages = self
+                .ctx
+                .data::<ArchiveShards>()
+                .unwrap()
+                .page(
+                    &args.pagination,
+                    |a: &db::Message, b| args.cursor(a).cmp(&args.cursor(b)),
+                    |pool| async move {
+                        db::Message::account_messages(&pool, self.address.clone(), args_ref).await
+                    },
+                )
+                .await?;
 
             let (has_previous_page, has_next_page) =
                 args.pagination.get_bound_markers(messages.len());
@@ -220,6 +253,10 @@ impl BlockchainAccountQuery<'_> {
             let mut edges: Vec<Edge<String, Message, EmptyFields, BlockchainMessageEdge>> = vec![];
             for message in messages {
                 let parent_transaction = message.transaction_id.clone();
+                let Some(cursor) = args.cursor(&message) else {
+                    tracing::warn!("Message {} has no chain order", message.id);
+                    continue;
+                };
                 let mut message: BlockchainMessage = message.into();
                 if is_parent_transaction {
                     if let Some(parent_transaction) = parent_transaction {
@@ -233,13 +270,17 @@ impl BlockchainAccountQuery<'_> {
                     }
                 }
                 if is_child_transaction {
-                    let dst_transaction = db::transaction::Transaction::by_in_message(
-                        self.ctx.data::<SqlitePool>().unwrap(),
-                        &message.id,
-                        None,
-                    )
-                    .await
-                    .expect("Failed to load transaction by inbound message");
+                    let message_id = message.id.as_str();
+                    let dst_transaction = self
+                        .ctx
+                        .data::<ArchiveShards>()
+                        .unwrap()
+                        .find(|pool| async move {
+                            db::transaction::Transaction::by_in_message(&pool, message_id, None)
+                                .await
+                        })
+                        .await
+                        .expect("Failed to load transaction by inbound message");
 
                     if let Some(transaction) = dst_transaction {
                         message.dst_transaction = transaction_loader
@@ -251,7 +292,6 @@ impl BlockchainAccountQuery<'_> {
                             .map(Box::new);
                     }
                 }
-                let cursor = message.dst_chain_order.clone().unwrap();
                 let edge: Edge<String, Message, EmptyFields, BlockchainMessageEdge> =
                     Edge::with_additional_fields(cursor, message, EmptyFields);
                 edges.push(edge);
@@ -316,18 +356,27 @@ impl BlockchainAccountQuery<'_> {
             let args = AccountTransactionsQueryArgs::new(
                 allow_latest_inconsistent_data,
                 block_seq_no_range,
+                self.snapshot,
                 aborted,
                 min_balance_delta,
                 max_balance_delta,
                 PaginationArgs { first, after, last, before },
             );
 
-            let mut transactions: Vec<db::Transaction> = db::Transaction::account_transactions(
-                self.ctx.data::<SqlitePool>().unwrap(),
-                self.address.clone(),
-                &args,
-            )
-            .await?;
+            let args_ref = &args;
+            let mut transactions: Vec<db::Transaction> = self
+                .ctx
+                .data::<ArchiveShards>()
+                .unwrap()
+                .page(
+                    &args.pagination,
+                    |a: &db::Transaction, b| a.chain_order.cmp(&b.chain_order),
+                    |pool| async move {
+                        db::Transaction::account_transactions(&pool, self.address.clone(), args_ref)
+                            .await
+                    },
+                )
+                .await?;
 
             let (has_previous_page, has_next_page) =
                 args.pagination.get_bound_markers(transactions.len());
@@ -3,19 +3,77 @@
 
 use async_graphql::connection::ConnectionNameType;
 use async_graphql::connection::EdgeNameType;
+use async_graphql::Enum;
+use async_graphql::InputObject;
 use async_graphql::OutputType;
 
 use super::account::BlockchainMasterSeqNoFilter;
+use super::filter::validate_thread_id;
+use super::filter::BlockchainGenUtimeFilter;
+use super::filter::BlockchainSnapshot;
 use crate::schema::graphql::query::PaginationArgs;
 use crate::schema::graphql_ext::Block;
+use crate::schema::graphql_ext::QueryOrderByDirection;
 
 pub(crate) type BlockchainBlock = Block;
 
+#[derive(Debug, Enum, Copy, Clone, Eq, PartialEq)]
+/// Block fields that can be used to sort blocks
+pub enum BlockchainBlocksOrderByField {
+    ChainOrder,
+    SeqNo,
+    GenUtime,
+    TrCount,
+}
+
+impl BlockchainBlocksOrderByField {
+    pub(crate) fn column(&self) -> &'static str {
+        match self {
+            BlockchainBlocksOrderByField::ChainOrder => "chain_order",
+            BlockchainBlocksOrderByField::SeqNo => "seq_no",
+            BlockchainBlocksOrderByField::GenUtime => "gen_utime",
+            BlockchainBlocksOrderByField::TrCount => "tr_count",
+        }
+    }
+}
+
+#[derive(Clone, InputObject)]
+/// Sort order of blocks. Fields other than `CHAIN_ORDER` can't be combined with
+/// `after`/`before` cursors.
+pub struct BlockchainBlocksOrderBy {
+    pub field: BlockchainBlocksOrderByField,
+    pub direction: Option<QueryOrderByDirection>,
+}
+
+#[derive(Clone, Default, InputObject)]
+/// Composite filter of blocks, all specified conditions must match
+pub struct BlockchainBlocksFilter {
+    pub workchain_id: Option<i32>,
+    /// Thread identifier (hex)
+    pub thread_id: Option<String>,
+    pub gen_utime: Option<BlockchainGenUtimeFilter>,
+}
+
+impl BlockchainBlocksFilter {
+    pub(crate) fn validate(&self) -> anyhow::Result<()> {
+        if let Some(thread_id) = &self.thread_id {
+            validate_thread_id(thread_id)?;
+        }
+        if let Some(gen_utime) = &self.gen_utime {
+            gen_utime.validate()?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone)]
 pub struct BlockchainBlocksQueryArgs {
     pub block_seq_no_range: Option<BlockchainMasterSeqNoFilter>,
     pub min_tr_count: Option<i32>,
     pub max_tr_count: Option<i32>,
+    pub filter: Option<BlockchainBlocksFilter>,
+    pub order_by: Option<BlockchainBlocksOrderBy>,
+    pub snapshot: Option<BlockchainSnapshot>,
     pub pagination: PaginationArgs,
 }
 
@@ -0,0 +1,108 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use async_graphql::SimpleObject;
+
+use super::blocks::BlockchainBlock;
+use super::transactions::BlockchainTransaction;
+use crate::helpers::u64_to_string;
+use crate::schema::db;
+
+/// Maximum number of items returned by one stream query.
+pub const MAX_CHAIN_ORDER_STREAM_LIMIT: usize = 1000;
+
+// Threads with the latest block older than the newest thread head by more
+// than this are considered stopped (merged or collapsed) and don't hold the
+// watermark back.
+const STOPPED_THREAD_LAG_SEC: i64 = 60;
+
+#[derive(SimpleObject)]
+#[graphql(rename_fields = "snake_case")]
+/// Block or transaction of the chain order stream. Exactly one of `block`
+/// and `transaction` is set. A block precedes its transactions.
+pub struct BlockchainChainOrderStreamItem {
+    pub chain_order: String,
+    pub block: Option<BlockchainBlock>,
+    pub transaction: Option<BlockchainTransaction>,
+}
+
+#[derive(SimpleObject)]
+#[graphql(rename_fields = "snake_case")]
+/// Portion of the archived blocks and transactions in the chain order. Only
+/// the items below the watermark are returned, so the stream has no gaps.
+pub struct BlockchainChainOrderStream {
+    pub items: Vec<BlockchainChainOrderStreamItem>,
+    /// Cursor to pass as `after` of the next request: the chain order of the
+    /// last item, or `after` of this request if there are no items.
+    pub end_cursor: Option<String>,
+    /// True if there are more items below the watermark.
+    pub has_more: bool,
+    /// Exclusive upper boundary of the consistent chain orders, no items are
+    /// archived below it anymore. Null if the archive is empty.
+    pub watermark: Option<String>,
+}
+
+/// Chain orders start with the block time, so the items below the oldest
+/// thread head can't be inserted anymore.
+pub(crate) fn chain_order_watermark(heads: &[db::ThreadHead]) -> Option<String> {
+    let gen_utimes = heads.iter().filter_map(|head| head.gen_utime).collect::<Vec<_>>();
+    let newest = gen_utimes.iter().max()?;
+    let oldest_running = gen_utimes
+        .iter()
+        .filter(|gen_utime| newest - *gen_utime <= STOPPED_THREAD_LAG_SEC)
+        .min()?;
+    Some(u64_to_string(*oldest_running as u64))
+}
+
+/// Merges the blocks and transactions of the archive shards into the stream
+/// portion of `limit` items.
+pub(crate) fn merge_stream(
+    blocks: Vec<db::Block>,
+    transactions: Vec<db::Transaction>,
+    after: Option<String>,
+    limit: usize,
+    watermark: Option<String>,
+) -> BlockchainChainOrderStream {
+    let mut items = blocks
+        .into_iter()
+        .filter_map(|block| {
+            let chain_order = block.chain_order.clone()?;
+            Some(BlockchainChainOrderStreamItem {
+                chain_order,
+                block: Some(block.into()),
+                transaction: None,
+            })
+        })
+        .chain(transactions.into_iter().map(|transaction| BlockchainChainOrderStreamItem {
+            chain_order: transaction.chain_order.clone(),
+            block: None,
+            transaction: Some(transaction.into()),
+        }))
+        .collect::<Vec<_>>();
+    items.sort_by(|a, b| a.chain_order.cmp(&b.chain_order));
+    let has_more = items.len() > limit;
+    items.truncate(limit);
+    let end_cursor = items.last().map(|item| item.chain_order.clone()).or(after);
+    BlockchainChainOrderStream { items, end_cursor, has_more, watermark }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn head(thread_id: &str, gen_utime: Option<i64>) -> db::ThreadHead {
+        db::ThreadHead { thread_id: Some(thread_id.to_string()), gen_utime }
+    }
+
+    #[test]
+    fn test_chain_order_watermark() {
+        assert_eq!(chain_order_watermark(&[]), None);
+        assert_eq!(chain_order_watermark(&[head("00", None)]), None);
+        let heads = [head("00", Some(1000)), head("01", Some(990)), head("02", Some(100))];
+        // The stopped thread "02" is skipped
+        assert_eq!(chain_order_watermark(&heads), Some(u64_to_string(990)));
+        // Items of the watermark second are not consistent yet
+        assert!(u64_to_string(989) + "00" < u64_to_string(990));
+        assert!(u64_to_string(990) + "00" > u64_to_string(990));
+    }
+}
@@ -0,0 +1,90 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use async_graphql::SimpleObject;
+use tvm_block::Deserializable;
+use tvm_block::MsgAddressInt;
+use tvm_types::read_single_root_boc;
+use tvm_types::SliceData;
+
+use crate::schema::db;
+
+// DappConfig storage fields before the credit config: `_pubkey`,
+// `_timestamp` and `_constructorFlag`
+const CONFIG_DATA_OFFSET: usize = 256 + 64 + 1;
+
+#[derive(SimpleObject, Clone, Debug, PartialEq)]
+#[graphql(rename_fields = "snake_case")]
+/// Credit config of a dapp stored in its DappConfig contract. Amounts are
+/// decimal strings.
+pub struct BlockchainDappConfig {
+    pub address: String,
+    pub dapp_id: String,
+    pub is_unlimit: bool,
+    /// Credit left to the dapp, reduced by the shell minted for its
+    /// transactions. Can be negative.
+    pub available_balance: String,
+    pub last_trans_lt: String,
+}
+
+impl TryFrom<db::Account> for BlockchainDappConfig {
+    type Error = anyhow::Error;
+
+    fn try_from(account: db::Account) -> anyhow::Result<Self> {
+        let data = account.data.ok_or_else(|| anyhow::format_err!("Account has no data"))?;
+        let (is_unlimit, available_balance, dapp_id) = decode_config_data(&data)?;
+        Ok(Self {
+            address: account.id,
+            dapp_id,
+            is_unlimit,
+            available_balance: available_balance.to_string(),
+            last_trans_lt: account.last_trans_lt,
+        })
+    }
+}
+
+/// Decodes `(is_unlimit, available_balance, dapp_id)` from the DappConfig
+/// contract data BOC.
+fn decode_config_data(data: &[u8]) -> anyhow::Result<(bool, i128, String)> {
+    let decode = || -> tvm_types::Result<(bool, i128, String)> {
+        let mut slice = SliceData::load_cell(read_single_root_boc(data)?)?;
+        slice.move_by(CONFIG_DATA_OFFSET)?;
+        let is_unlimit = slice.get_next_bit()?;
+        let mut available_balance = [0u8; 16];
+        available_balance.copy_from_slice(&slice.get_next_bytes(16)?);
+        // `_owner`
+        MsgAddressInt::construct_from(&mut slice)?;
+        let dapp_id = slice.get_next_hash()?.to_hex_string();
+        Ok((is_unlimit, i128::from_be_bytes(available_balance), dapp_id))
+    };
+    decode().map_err(|e| anyhow::format_err!("Failed to decode DappConfig data: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use tvm_block::Serializable;
+    use tvm_types::write_boc;
+    use tvm_types::BuilderData;
+    use tvm_types::UInt256;
+
+    use super::*;
+
+    #[test]
+    fn test_decode_config_data() {
+        let mut builder = BuilderData::new();
+        builder.append_raw(&[0x11; 32], 256).unwrap();
+        builder.append_raw(&[0; 8], 64).unwrap();
+        builder.append_bit_one().unwrap();
+        builder.append_bit_zero().unwrap();
+        builder.append_raw(&(-5i128).to_be_bytes(), 128).unwrap();
+        MsgAddressInt::with_standart(None, 0, UInt256::from([0x99; 32]).into())
+            .unwrap()
+            .write_to(&mut builder)
+            .unwrap();
+        builder.append_raw(&[0xab; 32], 256).unwrap();
+        let data = write_boc(&builder.into_cell().unwrap()).unwrap();
+
+        assert_eq!(decode_config_data(&data).unwrap(), (false, -5, "ab".repeat(32)));
+        assert!(decode_config_data(&data[..data.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,175 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::BTreeMap;
+
+use async_graphql::SimpleObject;
+
+use crate::schema::db;
+
+#[derive(SimpleObject, Clone, Debug, PartialEq)]
+#[graphql(rename_fields = "snake_case")]
+/// Shell minted by a dapp, negative if the dapp burned shell. Decimal string.
+pub struct BlockchainDappMintedShell {
+    pub dapp_id: String,
+    pub minted: String,
+}
+
+#[derive(SimpleObject, Clone, Debug)]
+#[graphql(rename_fields = "snake_case")]
+/// Fees collected and shell minted and burned in a block. Amounts are decimal
+/// strings.
+pub struct BlockchainBlockFees {
+    pub block_id: String,
+    pub thread_id: Option<String>,
+    pub seq_no: i64,
+    pub gen_utime: Option<i64>,
+    pub fees_collected: String,
+    pub minted: String,
+    pub burned: String,
+    pub minted_by_dapp: Vec<BlockchainDappMintedShell>,
+}
+
+#[derive(SimpleObject, Clone, Debug)]
+#[graphql(rename_fields = "snake_case")]
+/// Fee totals of the archived thread blocks in a seq no range (e.g. of a block
+/// keeper epoch). Amounts are decimal strings.
+pub struct BlockchainFeeSummary {
+    pub thread_id: String,
+    pub seq_no_start: i64,
+    pub seq_no_end: i64,
+    /// Number of blocks with fee accounting in the range
+    pub blocks: i64,
+    pub fees_collected: String,
+    pub minted: String,
+    pub burned: String,
+    pub minted_by_dapp: Vec<BlockchainDappMintedShell>,
+}
+
+impl TryFrom<db::BlockFees> for BlockchainBlockFees {
+    type Error = anyhow::Error;
+
+    fn try_from(fees: db::BlockFees) -> anyhow::Result<Self> {
+        let minted_by_dapp = parse_minted_by_dapp(fees.minted_by_dapp.as_deref())?
+            .into_iter()
+            .map(|(dapp_id, minted)| BlockchainDappMintedShell {
+                dapp_id,
+                minted: minted.to_string(),
+            })
+            .collect();
+        Ok(Self {
+            block_id: fees.block_id,
+            thread_id: fees.thread_id,
+            seq_no: fees.seq_no,
+            gen_utime: fees.gen_utime,
+            fees_collected: fees.fees_collected,
+            minted: fees.minted,
+            burned: fees.burned,
+            minted_by_dapp,
+        })
+    }
+}
+
+impl BlockchainFeeSummary {
+    pub(crate) fn aggregate(
+        thread_id: String,
+        seq_no_start: i64,
+        seq_no_end: i64,
+        blocks: &[db::BlockFees],
+    ) -> anyhow::Result<Self> {
+        let mut fees_collected = 0u128;
+        let mut minted = 0u128;
+        let mut burned = 0u128;
+        let mut minted_by_dapp = BTreeMap::<String, i128>::new();
+        for block in blocks {
+            fees_collected = fees_collected.saturating_add(parse_amount(&block.fees_collected)?);
+            minted = minted.saturating_add(parse_amount(&block.minted)?);
+            burned = burned.saturating_add(parse_amount(&block.burned)?);
+            for (dapp_id, value) in parse_minted_by_dapp(block.minted_by_dapp.as_deref())? {
+                let total = minted_by_dapp.entry(dapp_id).or_default();
+                *total = total.saturating_add(value);
+            }
+        }
+        Ok(Self {
+            thread_id,
+            seq_no_start,
+            seq_no_end,
+            blocks: blocks.len() as i64,
+            fees_collected: fees_collected.to_string(),
+            minted: minted.to_string(),
+            burned: burned.to_string(),
+            minted_by_dapp: minted_by_dapp
+                .into_iter()
+                .map(|(dapp_id, minted)| BlockchainDappMintedShell {
+                    dapp_id,
+                    minted: minted.to_string(),
+                })
+                .collect(),
+        })
+    }
+}
+
+fn parse_amount(value: &str) -> anyhow::Result<u128> {
+    value.parse().map_err(|e| anyhow::format_err!("Invalid amount {value}: {e}"))
+}
+
+fn parse_minted_by_dapp(value: Option<&str>) -> anyhow::Result<BTreeMap<String, i128>> {
+    let Some(value) = value else {
+        return Ok(BTreeMap::new());
+    };
+    let minted_by_dapp: BTreeMap<String, String> = serde_json::from_str(value)?;
+    minted_by_dapp
+        .into_iter()
+        .map(|(dapp_id, minted)| {
+            let minted =
+                minted.parse().map_err(|e| anyhow::format_err!("Invalid amount {minted}: {e}"))?;
+            Ok((dapp_id, minted))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_fees(seq_no: i64, fees: &str, minted_by_dapp: Option<&str>) -> db::BlockFees {
+        db::BlockFees {
+            block_id: format!("{seq_no:064x}"),
+            thread_id: Some("00".to_string()),
+            seq_no,
+            gen_utime: None,
+            fees_collected: fees.to_string(),
+            minted: "10".to_string(),
+            burned: "3".to_string(),
+            minted_by_dapp: minted_by_dapp.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_fee_summary() {
+        let blocks = vec![
+            block_fees(1, "100", Some(r#"{"aa":"10","bb":"-3"}"#)),
+            block_fees(2, "340282366920938463463374607431768211455", Some(r#"{"aa":"5"}"#)),
+            block_fees(3, "1", None),
+        ];
+        let summary = BlockchainFeeSummary::aggregate("00".to_string(), 1, 3, &blocks).unwrap();
+        assert_eq!(summary.blocks, 3);
+        assert_eq!(summary.fees_collected, u128::MAX.to_string());
+        assert_eq!(summary.minted, "30");
+        assert_eq!(summary.burned, "9");
+        assert_eq!(
+            summary.minted_by_dapp,
+            vec![
+                BlockchainDappMintedShell { dapp_id: "aa".to_string(), minted: "15".to_string() },
+                BlockchainDappMintedShell { dapp_id: "bb".to_string(), minted: "-3".to_string() },
+            ]
+        );
+        assert!(BlockchainFeeSummary::aggregate(
+            "00".to_string(),
+            1,
+            1,
+            &[block_fees(1, "x", None)]
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,281 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use async_graphql::InputObject;
+
+use crate::helpers::u64_to_string;
+use crate::schema::graphql::query::PaginateDirection;
+use crate::schema::graphql::query::PaginationArgs;
+use crate::schema::graphql_ext::QueryOrderByDirection;
+
+/// Unix time range. `start` is inclusive, `end` is exclusive.
+#[derive(Clone, InputObject)]
+pub struct BlockchainGenUtimeFilter {
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+}
+
+impl BlockchainGenUtimeFilter {
+    pub(crate) fn validate(&self) -> anyhow::Result<()> {
+        if let (Some(start), Some(end)) = (self.start, self.end) {
+            if start > end {
+                anyhow::bail!("Invalid gen_utime range: start {start} is greater than end {end}");
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn push_where_ops(&self, column: &str, where_ops: &mut Vec<String>) {
+        if let Some(start) = self.start {
+            where_ops.push(format!("{column} >= {start}"));
+        }
+        if let Some(end) = self.end {
+            where_ops.push(format!("{column} < {end}"));
+        }
+    }
+}
+
+/// Chain order range. `start` is inclusive, `end` is exclusive.
+///
+/// Consumers that track a chain order watermark pass the last processed
+/// chain order as `start` of the next request.
+#[derive(Clone, InputObject)]
+pub struct BlockchainChainOrderFilter {
+    pub start: Option<String>,
+    pub end: Option<String>,
+}
+
+impl BlockchainChainOrderFilter {
+    pub(crate) fn validate(&self) -> anyhow::Result<()> {
+        for chain_order in [&self.start, &self.end].into_iter().flatten() {
+            validate_chain_order(chain_order)?;
+        }
+        if let (Some(start), Some(end)) = (&self.start, &self.end) {
+            // Chain orders are length-prefixed, so they are compared as strings
+            if start > end {
+                anyhow::bail!("Invalid chain_order range: start {start} is greater than end {end}");
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn push_where_ops(&self, where_ops: &mut Vec<String>) {
+        if let Some(start) = &self.start {
+            where_ops.push(format!("chain_order >= {start:?}"));
+        }
+        if let Some(end) = &self.end {
+            where_ops.push(format!("chain_order < {end:?}"));
+        }
+    }
+}
+
+/// Archive snapshot the operation is pinned to with `at_seq_no`: only the
+/// items with the master seq_no (the time prefix of the chain order) up to
+/// `at_seq_no` inclusive are read, so the pages of all fields of the operation
+/// reflect the same archive state while new blocks are archived.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlockchainSnapshot {
+    pub at_seq_no: i32,
+}
+
+impl BlockchainSnapshot {
+    pub(crate) fn new(at_seq_no: i32) -> anyhow::Result<Self> {
+        if at_seq_no < 0 {
+            anyhow::bail!("Invalid at_seq_no: {at_seq_no} is negative");
+        }
+        Ok(Self { at_seq_no })
+    }
+
+    /// Exclusive chain order boundary of the snapshot.
+    pub(crate) fn chain_order_end(&self) -> String {
+        u64_to_string(self.at_seq_no as u64 + 1)
+    }
+
+    pub(crate) fn push_where_ops(&self, cursor_field: &str, where_ops: &mut Vec<String>) {
+        where_ops.push(format!("{cursor_field} < {:?}", self.chain_order_end()));
+    }
+}
+
+fn validate_chain_order(chain_order: &str) -> anyhow::Result<()> {
+    if chain_order.is_empty() || !chain_order.chars().all(|c| c.is_ascii_hexdigit()) {
+        anyhow::bail!("Invalid chain_order: expected a hex string");
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_thread_id(thread_id: &str) -> anyhow::Result<()> {
+    if thread_id.is_empty() || !thread_id.chars().all(|c| c.is_ascii_hexdigit()) {
+        anyhow::bail!("Invalid thread_id: expected a hex string");
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_address(address: &str) -> anyhow::Result<()> {
+    let valid = address.split_once(':').is_some_and(|(workchain_id, hex)| {
+        workchain_id.parse::<i32>().is_ok()
+            && hex.len() == 64
+            && hex.chars().all(|c| c.is_ascii_hexdigit())
+    });
+    if !valid {
+        anyhow::bail!("Invalid address: expected <workchain_id>:<64 hex digits>");
+    }
+    Ok(())
+}
+
+/// Parses decimal or `0x` prefixed hex amount.
+pub(crate) fn parse_amount(value: &str) -> anyhow::Result<u128> {
+    let parsed = match value.strip_prefix("0x") {
+        Some(hex) => u128::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    parsed.map_err(|e| anyhow::format_err!("Invalid amount {value}: {e}"))
+}
+
+/// Pushes inclusive range conditions on `column` storing amounts as
+/// lowercase hex without leading zeros, so a longer value is greater.
+pub(crate) fn push_hex_amount_range(
+    column: &str,
+    min: Option<u128>,
+    max: Option<u128>,
+    where_ops: &mut Vec<String>,
+) {
+    if let Some(min) = min {
+        let hex = format!("{min:x}");
+        let len = hex.len();
+        where_ops.push(format!(
+            "(length({column}) > {len} OR (length({column}) = {len} AND {column} >= '{hex}'))"
+        ));
+    }
+    if let Some(max) = max {
+        let hex = format!("{max:x}");
+        let len = hex.len();
+        where_ops.push(format!(
+            "(length({column}) < {len} OR (length({column}) = {len} AND {column} <= '{hex}'))"
+        ));
+    }
+}
+
+/// Returns `ORDER BY` clause for the requested sort column.
+///
+/// `chain_order` is always appended as a tiebreaker so the result is stable.
+/// Sort column other than `chain_order` can't be combined with cursors,
+/// because cursors are chain orders.
+pub(crate) fn order_by_clause(
+    column: Option<&'static str>,
+    direction: Option<QueryOrderByDirection>,
+    pagination: &PaginationArgs,
+) -> anyhow::Result<String> {
+    let column = column.unwrap_or("chain_order");
+    let has_cursor = pagination.after.as_ref().is_some_and(|v| !v.is_empty())
+        || pagination.before.as_ref().is_some_and(|v| !v.is_empty());
+    if column != "chain_order" && has_cursor {
+        anyhow::bail!("order_by {column} can't be combined with after/before cursors");
+    }
+
+    let direction = direction.unwrap_or(QueryOrderByDirection::ASC);
+    // Backward pagination reads the tail of the sorted set and reverses it afterwards
+    let direction = match (pagination.get_direction(), direction) {
+        (PaginateDirection::Forward, direction) => direction,
+        (PaginateDirection::Backward, QueryOrderByDirection::ASC) => QueryOrderByDirection::DESC,
+        (PaginateDirection::Backward, QueryOrderByDirection::DESC) => QueryOrderByDirection::ASC,
+    };
+
+    Ok(if column == "chain_order" {
+        format!("ORDER BY chain_order {direction}")
+    } else {
+        format!("ORDER BY {column} {direction}, chain_order {direction}")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pagination(after: Option<&str>, last: Option<usize>) -> PaginationArgs {
+        PaginationArgs { first: None, after: after.map(|v| v.to_string()), last, before: None }
+    }
+
+    #[test]
+    fn test_order_by_clause() {
+        assert_eq!(
+            order_by_clause(None, None, &pagination(None, None)).unwrap(),
+            "ORDER BY chain_order ASC"
+        );
+        assert_eq!(
+            order_by_clause(None, None, &pagination(None, Some(10))).unwrap(),
+            "ORDER BY chain_order DESC"
+        );
+        assert_eq!(
+            order_by_clause(
+                Some("gen_utime"),
+                Some(QueryOrderByDirection::DESC),
+                &pagination(None, None)
+            )
+            .unwrap(),
+            "ORDER BY gen_utime DESC, chain_order DESC"
+        );
+        assert!(order_by_clause(Some("gen_utime"), None, &pagination(Some("1a"), None)).is_err());
+        assert!(order_by_clause(Some("chain_order"), None, &pagination(Some("1a"), None)).is_ok());
+    }
+
+    #[test]
+    fn test_validate_thread_id() {
+        assert!(validate_thread_id("00000000000000000000").is_ok());
+        assert!(validate_thread_id("").is_err());
+        assert!(validate_thread_id("00\" OR 1=1 --").is_err());
+    }
+
+    #[test]
+    fn test_validate_address() {
+        assert!(validate_address(&format!("0:{}", "a".repeat(64))).is_ok());
+        assert!(validate_address(&format!("-1:{}", "A".repeat(64))).is_ok());
+        assert!(validate_address(&"a".repeat(64)).is_err());
+        assert!(validate_address(&format!("0:{}\" OR 1=1 --", "a".repeat(46))).is_err());
+    }
+
+    #[test]
+    fn test_hex_amount_range() {
+        assert_eq!(parse_amount("255").unwrap(), 255);
+        assert_eq!(parse_amount("0xff").unwrap(), 255);
+        assert!(parse_amount("-1").is_err());
+
+        let mut where_ops = vec![];
+        push_hex_amount_range("value", Some(255), Some(4096), &mut where_ops);
+        assert_eq!(
+            where_ops,
+            vec![
+                "(length(value) > 2 OR (length(value) = 2 AND value >= 'ff'))",
+                "(length(value) < 4 OR (length(value) = 4 AND value <= '1000'))",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_chain_order_filter() {
+        let filter = |start: Option<&str>, end: Option<&str>| BlockchainChainOrderFilter {
+            start: start.map(|v| v.to_string()),
+            end: end.map(|v| v.to_string()),
+        };
+        assert!(filter(Some("1a"), Some("1b")).validate().is_ok());
+        assert!(filter(Some("1b"), Some("1a")).validate().is_err());
+        assert!(filter(Some("1a\" OR 1=1 --"), None).validate().is_err());
+
+        let mut where_ops = vec![];
+        filter(Some("1a"), Some("1b")).push_where_ops(&mut where_ops);
+        assert_eq!(where_ops, vec!["chain_order >= \"1a\"", "chain_order < \"1b\""]);
+    }
+
+    #[test]
+    fn test_snapshot() {
+        assert!(BlockchainSnapshot::new(-1).is_err());
+        let snapshot = BlockchainSnapshot::new(1000).unwrap();
+        // Items of the pinned seq_no are in the snapshot, the next ones aren't
+        let end = snapshot.chain_order_end();
+        assert!(u64_to_string(1000) + "00" < end);
+        assert!(u64_to_string(1001) + "00" > end);
+
+        let mut where_ops = vec![];
+        snapshot.push_where_ops("src_chain_order", &mut where_ops);
+        assert_eq!(where_ops, vec![format!("src_chain_order < {end:?}")]);
+    }
+}
@@ -13,17 +13,35 @@ use async_graphql::Object;
 use blocks::BlockchainBlock;
 use blocks::BlockchainBlocksConnection;
 use blocks::BlockchainBlocksEdge;
+use blocks::BlockchainBlocksFilter;
+use blocks::BlockchainBlocksOrderBy;
 use blocks::BlockchainBlocksQueryArgs;
-use sqlx::SqlitePool;
+use chain_order_stream::chain_order_watermark;
+use chain_order_stream::merge_stream;
+use chain_order_stream::BlockchainChainOrderStream;
+use chain_order_stream::MAX_CHAIN_ORDER_STREAM_LIMIT;
+use dapp_config::BlockchainDappConfig;
+use fees::BlockchainBlockFees;
+use fees::BlockchainFeeSummary;
+use filter::validate_thread_id;
+use filter::BlockchainChainOrderFilter;
+use filter::BlockchainSnapshot;
+use statistics::BlockchainStatistics;
+use statistics::BlockchainStatisticsPeriod;
+use statistics::MAX_STATISTICS_PERIODS;
 use transactions::BlockchainMessage;
 use transactions::BlockchainTransaction;
 use transactions::BlockchainTransactionsConnection;
 use transactions::BlockchainTransactionsEdge;
+use transactions::BlockchainTransactionsFilter;
+use transactions::BlockchainTransactionsOrderBy;
 use transactions::BlockchainTransactionsQueryArgs;
 
 use super::message::MessageLoader;
+use crate::defaults;
 use crate::schema::db;
 use crate::schema::db::account::BlockchainAccountsQueryArgs;
+use crate::schema::db::ArchiveShards;
 use crate::schema::graphql;
 use crate::schema::graphql::block::BlockLoader;
 use crate::schema::graphql::query::PaginationArgs;
@@ -34,11 +52,17 @@ use crate::schema::graphql_ext::blockchain_api::account::BlockchainAccountsConne
 
 pub mod account;
 pub mod blocks;
+pub mod chain_order_stream;
+pub mod dapp_config;
+pub mod fees;
+pub mod filter;
+pub mod statistics;
 pub mod transactions;
 
 /// Blockchain-related information (blocks, transactions, etc.).
 pub struct BlockchainQuery<'a> {
     pub ctx: &'a Context<'a>,
+    pub snapshot: Option<BlockchainSnapshot>,
 }
 
 #[Object]
@@ -46,7 +70,12 @@ impl BlockchainQuery<'_> {
     #[graphql(name = "account")]
     /// Account-related information.
     async fn account(&self, address: String) -> Option<BlockchainAccountQuery<'_>> {
-        Some(BlockchainAccountQuery { ctx: self.ctx, address, preloaded: None })
+        Some(BlockchainAccountQuery {
+            ctx: self.ctx,
+            address,
+            preloaded: None,
+            snapshot: self.snapshot,
+        })
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -77,11 +106,17 @@ impl BlockchainQuery<'_> {
                 code_hash: code_hash.clone(),
                 pagination: PaginationArgs { first, after, last, before },
             };
-            let mut accounts: Vec<db::Account> = db::account::Account::blockchain_accounts(
-                self.ctx.data::<SqlitePool>().unwrap(),
-                &query_args,
-            )
-            .await?;
+            let archive = self.ctx.data::<ArchiveShards>().unwrap();
+            // Accounts are paginated by the row order, which is local to a shard
+            if archive.is_sharded() {
+                return Err("Accounts pagination is not supported on the sharded archive".into());
+            }
+            let query_args_ref = &query_args;
+            let mut accounts: Vec<db::Account> = archive
+                .collect(|pool| async move {
+                    db::account::Account::blockchain_accounts(&pool, query_args_ref).await
+                })
+                .await?;
 
             let (has_previous_page, has_next_page) =
                 query_args.pagination.get_bound_markers(accounts.len());
@@ -100,8 +135,12 @@ impl BlockchainQuery<'_> {
             connection.edges.extend(accounts.into_iter().map(|account| {
                 let cursor = account.id.clone();
                 let address = account.id.clone();
-                let account =
-                    BlockchainAccountQuery { address, ctx: self.ctx, preloaded: Some(account) };
+                let account = BlockchainAccountQuery {
+                    address,
+                    ctx: self.ctx,
+                    preloaded: Some(account),
+                    snapshot: self.snapshot,
+                };
                 let edge: Edge<String, BlockchainAccountQuery, EmptyFields, BlockchainAccountEdge> =
                     Edge::with_additional_fields(cursor, account, EmptyFields);
                 edge
@@ -120,14 +159,14 @@ impl BlockchainQuery<'_> {
         }
     }
 
-    async fn block(&self, hash: String) -> Option<BlockchainBlock> {
+    async fn block(&self, hash: String) -> async_graphql::Result<Option<BlockchainBlock>> {
         let block_loader = self.ctx.data_unchecked::<DataLoader<BlockLoader>>();
         let message_loader = self.ctx.data_unchecked::<DataLoader<MessageLoader>>();
-        let block = block_loader.load_one(hash).await.expect("Failed to load block");
-
-        block.as_ref()?;
+        let block = block_loader.load_one(hash.clone()).await.expect("Failed to load block");
 
-        let block = block.unwrap();
+        let Some(block) = block else {
+            return not_found_in_archive(self.ctx, &hash).await;
+        };
 
         // if self.ctx.look_ahead().field("block").field("in_message").exists() {
         //     let in_message =
@@ -144,7 +183,135 @@ impl BlockchainQuery<'_> {
             //     Some(out_messages.into_values().map(|m| Some(m)).collect());
         }
 
-        Some(block)
+        Ok(Some(block))
+    }
+
+    /// Credit config of a dapp stored in its DappConfig contract at the
+    /// address.
+    async fn dapp_config(
+        &self,
+        address: String,
+    ) -> async_graphql::Result<Option<BlockchainDappConfig>> {
+        let archive = self.ctx.data::<ArchiveShards>()?;
+        let address_ref = &address;
+        let account =
+            archive
+                .find(|pool| async move {
+                    db::Account::by_address(&pool, Some(address_ref.clone())).await
+                })
+                .await?;
+        Ok(account.map(BlockchainDappConfig::try_from).transpose()?)
+    }
+
+    /// Fees collected and shell minted and burned in the block.
+    async fn block_fees(&self, hash: String) -> async_graphql::Result<Option<BlockchainBlockFees>> {
+        let archive = self.ctx.data::<ArchiveShards>()?;
+        let hash_ref = hash.as_str();
+        let fees = archive
+            .find(|pool| async move { db::BlockFees::by_block_id(&pool, hash_ref).await })
+            .await?;
+        let Some(fees) = fees else {
+            return not_found_in_archive(self.ctx, &hash).await;
+        };
+        Ok(Some(fees.try_into()?))
+    }
+
+    /// Fee totals of the thread blocks in the inclusive seq no range, e.g. of
+    /// a block keeper epoch.
+    async fn fee_summary(
+        &self,
+        thread_id: String,
+        seq_no_start: i64,
+        seq_no_end: i64,
+    ) -> async_graphql::Result<BlockchainFeeSummary> {
+        validate_thread_id(&thread_id)?;
+        if seq_no_start > seq_no_end {
+            return Err("seq_no_start must not be greater than seq_no_end".into());
+        }
+        let archive = self.ctx.data::<ArchiveShards>()?;
+        let thread_id_ref = thread_id.as_str();
+        let mut blocks = archive
+            .collect(|pool| async move {
+                db::BlockFees::in_seq_no_range(&pool, thread_id_ref, seq_no_start, seq_no_end).await
+            })
+            .await?;
+        blocks.sort_by_key(|block| block.seq_no);
+        Ok(BlockchainFeeSummary::aggregate(thread_id, seq_no_start, seq_no_end, &blocks)?)
+    }
+
+    /// Hourly or daily totals of the archived blocks and transactions of the
+    /// periods starting in the [from, to) unix time range.
+    async fn statistics(
+        &self,
+        period: BlockchainStatisticsPeriod,
+        from: i64,
+        to: i64,
+    ) -> async_graphql::Result<Vec<BlockchainStatistics>> {
+        if from > to {
+            return Err("from must not be greater than to".into());
+        }
+        if (to - from) / period.seconds() > MAX_STATISTICS_PERIODS {
+            return Err(format!("Range exceeds {MAX_STATISTICS_PERIODS} periods").into());
+        }
+        let archive = self.ctx.data::<ArchiveShards>()?;
+        let period_name = period.name();
+        let rollups = archive
+            .collect(|pool| async move {
+                db::StatisticsRollup::in_range(&pool, period_name, from, to).await
+            })
+            .await?;
+        Ok(BlockchainStatistics::merge(rollups)?)
+    }
+
+    /// Exclusive upper boundary of the chain orders that are archived
+    /// consistently: no blocks and transactions are inserted below it anymore.
+    async fn chain_order_watermark(&self) -> async_graphql::Result<Option<String>> {
+        let heads = self
+            .ctx
+            .data::<ArchiveShards>()?
+            .collect(|pool| async move { db::Block::thread_heads(&pool).await })
+            .await?;
+        Ok(chain_order_watermark(&heads))
+    }
+
+    /// Blocks and transactions strictly in the chain order, for the ETL
+    /// consumers. Only the items below the chain order watermark (and in the
+    /// pinned snapshot) are returned, so a consumer passing `end_cursor` as
+    /// `after` of the next request never misses an item.
+    async fn chain_order_stream(
+        &self,
+        #[graphql(desc = "Chain order of the last processed item.")] after: Option<String>,
+        #[graphql(desc = "50 by default, 1000 at most.")] limit: Option<i32>,
+    ) -> async_graphql::Result<BlockchainChainOrderStream> {
+        if let Some(after) = &after {
+            BlockchainChainOrderFilter { start: Some(after.clone()), end: None }.validate()?;
+        }
+        let limit =
+            limit.map_or(defaults::QUERY_BATCH_SIZE as usize, |limit| limit.max(1) as usize);
+        if limit > MAX_CHAIN_ORDER_STREAM_LIMIT {
+            return Err(format!("limit must not exceed {MAX_CHAIN_ORDER_STREAM_LIMIT}").into());
+        }
+        let archive = self.ctx.data::<ArchiveShards>()?;
+        let heads =
+            archive.collect(|pool| async move { db::Block::thread_heads(&pool).await }).await?;
+        let Some(watermark) = chain_order_watermark(&heads) else {
+            return Ok(merge_stream(vec![], vec![], after, limit, None));
+        };
+        let end = match self.snapshot {
+            Some(snapshot) => watermark.clone().min(snapshot.chain_order_end()),
+            None => watermark.clone(),
+        };
+        let (after_ref, end_ref) = (after.as_deref().unwrap_or_default(), end.as_str());
+        // One more item to detect the next portion
+        let (blocks, transactions) = futures::try_join!(
+            archive.collect(|pool| async move {
+                db::Block::in_chain_order_range(&pool, after_ref, end_ref, limit + 1).await
+            }),
+            archive.collect(|pool| async move {
+                db::Transaction::in_chain_order_range(&pool, after_ref, end_ref, limit + 1).await
+            }),
+        )?;
+        Ok(merge_stream(blocks, transactions, after, limit, Some(watermark)))
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -169,6 +336,13 @@ impl BlockchainQuery<'_> {
             desc = "Optional filter by maximum transactions in a block (unoptimized, query could be dropped by timeout)"
         )]
         max_tr_count: Option<i32>,
+        #[graphql(desc = "Optional filter by workchain, thread and gen_utime range.")]
+        filter: Option<BlockchainBlocksFilter>,
+        #[graphql(
+            name = "order_by",
+            desc = "Optional sort order (default is chain_order ASC). Sort fields other than CHAIN_ORDER can't be combined with 'after'/'before'."
+        )]
+        order_by: Option<BlockchainBlocksOrderBy>,
         #[graphql(desc = "This field is mutually exclusive with 'last'.")] first: Option<i32>,
         after: Option<String>,
         #[graphql(desc = "This field is mutually exclusive with 'first'.")] last: Option<i32>,
@@ -188,11 +362,20 @@ impl BlockchainQuery<'_> {
                 block_seq_no_range,
                 min_tr_count,
                 max_tr_count,
+                filter,
+                order_by,
+                snapshot: self.snapshot,
                 pagination: PaginationArgs { first, after, last, before },
             };
-            let mut blocks: Vec<db::Block> =
-                db::block::Block::blockchain_blocks(self.ctx.data::<SqlitePool>().unwrap(), &args)
-                    .await?;
+            let args_ref = &args;
+            let mut blocks: Vec<db::Block> = self
+                .ctx
+                .data::<ArchiveShards>()
+                .unwrap()
+                .page(&args.pagination, db::block::Block::blockchain_order(&args), |pool| async move {
+                    db::block::Block::blockchain_blocks(&pool, args_ref).await
+                })
+                .await?;
 
             let (has_previous_page, has_next_page) = (
                 args.pagination.has_previous_page(blocks.len()),
@@ -225,14 +408,14 @@ impl BlockchainQuery<'_> {
         .ok()
     }
 
-    async fn message(&self, hash: String) -> Option<BlockchainMessage> {
+    async fn message(&self, hash: String) -> async_graphql::Result<Option<BlockchainMessage>> {
         let transaction_loader = self.ctx.data_unchecked::<DataLoader<TransactionLoader>>();
         let message_loader = self.ctx.data_unchecked::<DataLoader<MessageLoader>>();
 
-        let message = message_loader.load_one(hash).await.expect("Failed to load message");
-        message.as_ref()?;
-
-        let mut message = message.unwrap();
+        let message = message_loader.load_one(hash.clone()).await.expect("Failed to load message");
+        let Some(mut message) = message else {
+            return not_found_in_archive(self.ctx, &hash).await;
+        };
         if self.ctx.look_ahead().field("message").field("src_transaction").exists() {
             if let Some(transaction_id) = message.transaction_id.clone() {
                 message.src_transaction = transaction_loader
@@ -244,13 +427,16 @@ impl BlockchainQuery<'_> {
         }
 
         if self.ctx.look_ahead().field("message").field("dst_transaction").exists() {
-            let dst_transaction = db::transaction::Transaction::by_in_message(
-                self.ctx.data::<SqlitePool>().unwrap(),
-                &message.id,
-                None,
-            )
-            .await
-            .expect("Failed to load transaction by inbound message");
+            let message_id = message.id.as_str();
+            let dst_transaction = self
+                .ctx
+                .data::<ArchiveShards>()
+                .unwrap()
+                .find(|pool| async move {
+                    db::transaction::Transaction::by_in_message(&pool, message_id, None).await
+                })
+                .await
+                .expect("Failed to load transaction by inbound message");
 
             if let Some(transaction) = dst_transaction {
                 message.dst_transaction = transaction_loader
@@ -261,18 +447,21 @@ impl BlockchainQuery<'_> {
             }
         }
 
-        Some(message)
+        Ok(Some(message))
     }
 
-    async fn transaction(&self, hash: String) -> Option<BlockchainTransaction> {
+    async fn transaction(
+        &self,
+        hash: String,
+    ) -> async_graphql::Result<Option<BlockchainTransaction>> {
         let transaction_loader = self.ctx.data_unchecked::<DataLoader<TransactionLoader>>();
         let message_loader = self.ctx.data_unchecked::<DataLoader<MessageLoader>>();
         let transaction =
-            transaction_loader.load_one(hash).await.expect("Failed to load transaction");
-
-        transaction.as_ref()?;
+            transaction_loader.load_one(hash.clone()).await.expect("Failed to load transaction");
 
-        let mut transaction = transaction.unwrap();
+        let Some(mut transaction) = transaction else {
+            return not_found_in_archive(self.ctx, &hash).await;
+        };
 
         if self.ctx.look_ahead().field("transaction").field("in_message").exists() {
             let in_message = message_loader
@@ -290,7 +479,7 @@ impl BlockchainQuery<'_> {
             transaction.out_messages = Some(out_messages.into_values().map(Some).collect());
         }
 
-        Some(transaction)
+        Ok(Some(transaction))
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -317,6 +506,15 @@ impl BlockchainQuery<'_> {
             desc = "Optional filter by code hash of the account before execution."
         )]
         code_hash: Option<String>,
+        #[graphql(
+            desc = "Optional filter by workchain, thread, aborted flag, gen_utime and chain_order ranges."
+        )]
+        filter: Option<BlockchainTransactionsFilter>,
+        #[graphql(
+            name = "order_by",
+            desc = "Optional sort order (default is chain_order ASC). Sort fields other than CHAIN_ORDER can't be combined with 'after'/'before'."
+        )]
+        order_by: Option<BlockchainTransactionsOrderBy>,
         #[graphql(desc = "This field is mutually exclusive with 'last'.")] first: Option<i32>,
         after: Option<String>,
         #[graphql(desc = "This field is mutually exclusive with 'first'.")] last: Option<i32>,
@@ -341,14 +539,26 @@ impl BlockchainQuery<'_> {
                     min_balance_delta,
                     max_balance_delta,
                     code_hash,
+                    filter,
+                    order_by,
+                    snapshot: self.snapshot,
                     pagination: PaginationArgs { first, after, last, before },
                 };
                 let message_loader = self.ctx.data_unchecked::<DataLoader<MessageLoader>>();
-                let mut transactions = db::transaction::Transaction::blockchain_transactions(
-                    self.ctx.data::<SqlitePool>().unwrap(),
-                    &args,
-                )
-                .await?;
+                let args_ref = &args;
+                let mut transactions = self
+                    .ctx
+                    .data::<ArchiveShards>()
+                    .unwrap()
+                    .page(
+                        &args.pagination,
+                        db::transaction::Transaction::blockchain_order(&args),
+                        |pool| async move {
+                            db::transaction::Transaction::blockchain_transactions(&pool, args_ref)
+                                .await
+                        },
+                    )
+                    .await?;
 
                 let (has_previous_page, has_next_page) = (
                     args.pagination.has_previous_page(transactions.len()),
@@ -410,3 +620,18 @@ impl BlockchainQuery<'_> {
         .ok()
     }
 }
+
+// Entities moved to cold storage are reported with an error that tells
+// where to retrieve them, missing ones are just null.
+async fn not_found_in_archive<T>(ctx: &Context<'_>, id: &str) -> async_graphql::Result<Option<T>> {
+    let entry = ctx
+        .data::<ArchiveShards>()
+        .unwrap()
+        .find(|pool| async move { Ok(db::ColdStorageEntry::find(&pool, id).await) })
+        .await
+        .unwrap_or(None);
+    match entry {
+        Some(entry) => Err(entry.to_error()),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,123 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::BTreeMap;
+
+use async_graphql::Enum;
+use async_graphql::SimpleObject;
+
+use crate::schema::db;
+
+/// Maximum number of periods returned by one statistics query.
+pub const MAX_STATISTICS_PERIODS: i64 = 24 * 92;
+
+#[derive(Enum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlockchainStatisticsPeriod {
+    Hour,
+    Day,
+}
+
+impl BlockchainStatisticsPeriod {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Hour => "hour",
+            Self::Day => "day",
+        }
+    }
+
+    pub(crate) fn seconds(&self) -> i64 {
+        match self {
+            Self::Hour => 3600,
+            Self::Day => 86400,
+        }
+    }
+}
+
+#[derive(SimpleObject, Clone, Debug, PartialEq)]
+#[graphql(rename_fields = "snake_case")]
+/// Totals of the archived blocks and transactions of a period. Fees are a
+/// decimal string.
+pub struct BlockchainStatistics {
+    /// Unix time of the period start (UTC)
+    pub period_start: i64,
+    pub blocks: i64,
+    pub transactions: i64,
+    pub fees_collected: String,
+    /// Accounts with transactions in the period. An account that moved to
+    /// another thread within the period is counted in both threads.
+    pub active_accounts: i64,
+}
+
+impl BlockchainStatistics {
+    /// Sums the rollups of the archive shards by period start.
+    pub(crate) fn merge(rollups: Vec<db::StatisticsRollup>) -> anyhow::Result<Vec<Self>> {
+        let mut merged = BTreeMap::<i64, (i64, i64, u128, i64)>::new();
+        for rollup in rollups {
+            let fees_collected = rollup.fees_collected.parse::<u128>().map_err(|e| {
+                anyhow::format_err!("Invalid amount {}: {e}", rollup.fees_collected)
+            })?;
+            let totals = merged.entry(rollup.period_start).or_default();
+            totals.0 += rollup.blocks;
+            totals.1 += rollup.transactions;
+            totals.2 = totals.2.saturating_add(fees_collected);
+            totals.3 += rollup.active_accounts;
+        }
+        Ok(merged
+            .into_iter()
+            .map(|(period_start, (blocks, transactions, fees_collected, active_accounts))| Self {
+                period_start,
+                blocks,
+                transactions,
+                fees_collected: fees_collected.to_string(),
+                active_accounts,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rollup(period_start: i64, blocks: i64, fees: &str) -> db::StatisticsRollup {
+        db::StatisticsRollup {
+            period: "hour".to_string(),
+            period_start,
+            blocks,
+            transactions: blocks * 2,
+            fees_collected: fees.to_string(),
+            active_accounts: 1,
+        }
+    }
+
+    #[test]
+    fn test_merge_statistics() {
+        let merged = BlockchainStatistics::merge(vec![
+            rollup(7200, 1, "5"),
+            rollup(3600, 2, "340282366920938463463374607431768211455"),
+            rollup(7200, 3, "10"),
+            rollup(3600, 1, "1"),
+        ])
+        .unwrap();
+        assert_eq!(
+            merged,
+            vec![
+                BlockchainStatistics {
+                    period_start: 3600,
+                    blocks: 3,
+                    transactions: 6,
+                    fees_collected: u128::MAX.to_string(),
+                    active_accounts: 2,
+                },
+                BlockchainStatistics {
+                    period_start: 7200,
+                    blocks: 4,
+                    transactions: 8,
+                    fees_collected: "15".to_string(),
+                    active_accounts: 2,
+                },
+            ]
+        );
+        assert!(BlockchainStatistics::merge(vec![rollup(0, 1, "x")]).is_err());
+    }
+}
@@ -3,20 +3,84 @@
 
 use async_graphql::connection::ConnectionNameType;
 use async_graphql::connection::EdgeNameType;
+use async_graphql::Enum;
+use async_graphql::InputObject;
 use async_graphql::OutputType;
 
+use super::filter::validate_thread_id;
+use super::filter::BlockchainChainOrderFilter;
+use super::filter::BlockchainGenUtimeFilter;
+use super::filter::BlockchainSnapshot;
 use crate::schema::graphql::query::PaginationArgs;
 use crate::schema::graphql_ext::message::Message;
+use crate::schema::graphql_ext::QueryOrderByDirection;
 use crate::schema::graphql_ext::Transaction;
 
 pub(crate) type BlockchainMessage = Message;
 pub(crate) type BlockchainTransaction = Transaction;
 
+#[derive(Debug, Enum, Copy, Clone, Eq, PartialEq)]
+/// Transaction fields that can be used to sort transactions
+pub enum BlockchainTransactionsOrderByField {
+    ChainOrder,
+    /// Transaction time (`now`)
+    GenUtime,
+}
+
+impl BlockchainTransactionsOrderByField {
+    pub(crate) fn column(&self) -> &'static str {
+        match self {
+            BlockchainTransactionsOrderByField::ChainOrder => "chain_order",
+            BlockchainTransactionsOrderByField::GenUtime => "now",
+        }
+    }
+}
+
+#[derive(Clone, InputObject)]
+/// Sort order of transactions. Fields other than `CHAIN_ORDER` can't be
+/// combined with `after`/`before` cursors.
+pub struct BlockchainTransactionsOrderBy {
+    pub field: BlockchainTransactionsOrderByField,
+    pub direction: Option<QueryOrderByDirection>,
+}
+
+#[derive(Clone, Default, InputObject)]
+/// Composite filter of transactions, all specified conditions must match
+pub struct BlockchainTransactionsFilter {
+    pub workchain_id: Option<i32>,
+    /// Thread identifier (hex) of the block containing the transaction, i.e.
+    /// the destination thread of the inbound message
+    pub thread_id: Option<String>,
+    pub aborted: Option<bool>,
+    /// Range of transaction time (`now`)
+    pub gen_utime: Option<BlockchainGenUtimeFilter>,
+    /// Range of transaction chain orders
+    pub chain_order: Option<BlockchainChainOrderFilter>,
+}
+
+impl BlockchainTransactionsFilter {
+    pub(crate) fn validate(&self) -> anyhow::Result<()> {
+        if let Some(thread_id) = &self.thread_id {
+            validate_thread_id(thread_id)?;
+        }
+        if let Some(gen_utime) = &self.gen_utime {
+            gen_utime.validate()?;
+        }
+        if let Some(chain_order) = &self.chain_order {
+            chain_order.validate()?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone)]
 pub struct BlockchainTransactionsQueryArgs {
     pub min_balance_delta: Option<String>,
     pub max_balance_delta: Option<String>,
     pub code_hash: Option<String>,
+    pub filter: Option<BlockchainTransactionsFilter>,
+    pub order_by: Option<BlockchainTransactionsOrderBy>,
+    pub snapshot: Option<BlockchainSnapshot>,
     pub pagination: PaginationArgs,
 }
 
@@ -10,15 +10,16 @@ use async_graphql::FieldResult;
 use async_graphql::InputObject;
 use async_graphql::Object;
 use message::MessageLoader;
-use sqlx::SqlitePool;
 
 mod account;
 pub mod blockchain_api;
 
+use self::blockchain_api::filter::BlockchainSnapshot;
 use self::blockchain_api::BlockchainQuery;
 use self::message::Message;
 use self::message::MessageFilter;
 use super::db;
+use super::db::ArchiveShards;
 use crate::helpers::query_order_by_str;
 use crate::schema::graphql::account::Account;
 use crate::schema::graphql::account::AccountFilter;
@@ -66,10 +67,10 @@ pub struct QueryRoot;
 impl QueryRoot {
     async fn info(&self, ctx: &Context<'_>) -> FieldResult<Option<Info>> {
         tracing::info!("info query");
-        let pool = ctx.data::<SqlitePool>()?;
+        let archive = ctx.data::<ArchiveShards>()?;
 
         let gen_utime = if ctx.look_ahead().field("last_block_time").exists() {
-            let block = db::Block::latest_block(pool).await?;
+            let block = db::Block::latest_block(archive).await?;
             match block {
                 Some(db::Block { gen_utime, .. }) => gen_utime,
                 None => None,
@@ -77,7 +78,18 @@ impl QueryRoot {
         } else {
             None
         };
-        Ok(Some(Info { last_block_time: Some(gen_utime.unwrap_or(0) as f64), ..Info::default() }))
+        let look_ahead = ctx.look_ahead();
+        let archive_head = if look_ahead.field("latestSeqNo").exists()
+            || look_ahead.field("archiveLagMs").exists()
+        {
+            db::Block::archive_head(archive).await?
+        } else {
+            None
+        };
+        Ok(Some(
+            Info { last_block_time: Some(gen_utime.unwrap_or(0) as f64), ..Info::default() }
+                .with_archive_head(archive_head),
+        ))
     }
 
     async fn account(&self, address: String) -> Option<AccountQuery> {
@@ -92,14 +104,14 @@ impl QueryRoot {
         limit: Option<i32>,
         _timeout: Option<f64>,
     ) -> FieldResult<Option<Vec<Option<Block>>>> {
-        let pool = ctx.data::<SqlitePool>()?;
+        let archive = ctx.data::<ArchiveShards>()?;
         let filter = match filter {
             Some(f) => BlockFilter::to_where(&f).unwrap_or("".to_string()),
             None => "".to_string(),
         };
         let order_by_clause = query_order_by_str(order_by);
         let db_blocks: Vec<db::Block> =
-            db::Block::list(pool, filter, order_by_clause, limit).await?;
+            db::Block::list(archive, filter, order_by_clause, limit).await?;
         let mut blocks = db_blocks
             .into_iter()
             .map(|b| Some(Into::<Block>::into(b)))
@@ -107,7 +119,12 @@ impl QueryRoot {
 
         if ctx.look_ahead().field("in_msg_descr").exists() {
             for b in blocks.iter_mut().flatten() {
-                let in_msgs = db::Message::in_block_msgs(pool, b.id.to_string()).await?;
+                let block_id = b.id.as_str();
+                let in_msgs = archive
+                    .collect(|pool| async move {
+                        db::Message::in_block_msgs(&pool, block_id.to_string()).await
+                    })
+                    .await?;
                 b.set_in_msg_descr(in_msgs);
             }
         }
@@ -135,14 +152,14 @@ impl QueryRoot {
         limit: Option<i32>,
         _timeout: Option<f64>,
     ) -> FieldResult<Option<Vec<Option<Account>>>> {
-        let pool = ctx.data::<SqlitePool>()?;
+        let archive = ctx.data::<ArchiveShards>()?;
         let filter = match filter {
             Some(f) => AccountFilter::to_where(&f).unwrap_or("".to_string()),
             None => "".to_string(),
         };
         let order_by_clause = query_order_by_str(order_by);
         let db_accounts: Vec<db::Account> =
-            db::Account::list(pool, filter, order_by_clause, limit).await?;
+            db::Account::list(archive, filter, order_by_clause, limit).await?;
         let accounts: Vec<Option<Account>> =
             db_accounts.into_iter().map(|b| Some(b.into())).collect();
 
@@ -157,14 +174,14 @@ impl QueryRoot {
         limit: Option<i32>,
         _timeout: Option<f64>,
     ) -> FieldResult<Option<Vec<Option<Message>>>> {
-        let pool = ctx.data::<SqlitePool>()?;
+        let archive = ctx.data::<ArchiveShards>()?;
         let filter = match filter {
             Some(f) => MessageFilter::to_where(&f).unwrap_or("".to_string()),
             None => "".to_string(),
         };
         let order_by_clause = query_order_by_str(order_by);
         let db_messages: Vec<db::Message> =
-            db::Message::list(pool, filter, order_by_clause, limit).await?;
+            db::Message::list(archive, filter, order_by_clause, limit).await?;
         let mut messages: Vec<Option<Message>> =
             db_messages.into_iter().map(|b| Some(b.into())).collect();
 
@@ -182,13 +199,13 @@ impl QueryRoot {
         }
         if ctx.look_ahead().field("dst_transaction").exists() {
             for message in messages.iter_mut().flatten() {
-                let dst_transaction = db::transaction::Transaction::by_in_message(
-                    ctx.data::<SqlitePool>().unwrap(),
-                    &message.id,
-                    None,
-                )
-                .await
-                .expect("Failed to load transaction by inbound message");
+                let message_id = message.id.as_str();
+                let dst_transaction = archive
+                    .find(|pool| async move {
+                        db::transaction::Transaction::by_in_message(&pool, message_id, None).await
+                    })
+                    .await
+                    .expect("Failed to load transaction by inbound message");
 
                 if let Some(transaction) = dst_transaction {
                     message.dst_transaction = transaction_loader
@@ -213,14 +230,14 @@ impl QueryRoot {
         limit: Option<i32>,
         _timeout: Option<f64>,
     ) -> FieldResult<Option<Vec<Option<Transaction>>>> {
-        let pool = ctx.data::<SqlitePool>()?;
+        let archive = ctx.data::<ArchiveShards>()?;
         let filter = match filter {
             Some(f) => TransactionFilter::to_where(&f).unwrap_or("".to_string()),
             None => "".to_string(),
         };
         let order_by_clause = query_order_by_str(order_by);
         let db_transactions: Vec<db::Transaction> =
-            db::Transaction::list(pool, filter, order_by_clause, limit).await?;
+            db::Transaction::list(archive, filter, order_by_clause, limit).await?;
         let mut transactions: Vec<Option<Transaction>> =
             db_transactions.into_iter().map(|b| Some(b.into())).collect();
 
@@ -244,7 +261,16 @@ impl QueryRoot {
     async fn blockchain<'ctx>(
         &'ctx self,
         ctx: &'ctx Context<'ctx>,
+        #[graphql(
+            name = "at_seq_no",
+            desc = "Optional master seq_no to pin the reads to: blocks, transactions and messages archived after it are not returned, so the pages stay consistent while new blocks are archived. Overrides the X-At-Seq-No header."
+        )]
+        at_seq_no: Option<i32>,
     ) -> FieldResult<Option<BlockchainQuery<'ctx>>> {
-        Ok(Some(BlockchainQuery { ctx }))
+        let snapshot = match at_seq_no {
+            Some(at_seq_no) => Some(BlockchainSnapshot::new(at_seq_no)?),
+            None => ctx.data_opt::<BlockchainSnapshot>().copied(),
+        };
+        Ok(Some(BlockchainQuery { ctx, snapshot }))
     }
 }
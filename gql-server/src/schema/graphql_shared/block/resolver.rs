@@ -5,13 +5,12 @@ use std::collections::HashMap;
 
 use async_graphql::dataloader::Loader;
 use async_graphql::Error;
-use futures::TryStreamExt;
-use sqlx::SqlitePool;
 
 use crate::schema::db;
+use crate::schema::db::ArchiveShards;
 
 pub struct BlockLoader {
-    pub pool: SqlitePool,
+    pub archive: ArchiveShards,
 }
 
 impl Loader<String> for BlockLoader {
@@ -25,16 +24,22 @@ impl Loader<String> for BlockLoader {
         let ids = keys.iter().map(|m| format!("{m:?}")).collect::<Vec<_>>().join(",");
         let sql = format!("SELECT * FROM blocks WHERE id IN ({ids})");
         tracing::trace!(target: "data_loader",  "SQL: {sql}");
-        let messages = sqlx::query_as(&sql)
-            .fetch(&self.pool)
-            .map_ok(|block: db::Block| {
+        let sql = sql.as_str();
+        let rows = self
+            .archive
+            .collect(|pool| async move {
+                Ok::<_, anyhow::Error>(sqlx::query_as::<_, db::Block>(sql).fetch_all(&pool).await?)
+            })
+            .await?;
+        let blocks = rows
+            .into_iter()
+            .map(|block| {
                 let block: Self::Value = block.into();
                 let block_id = block.id.clone();
                 (block_id, block)
             })
-            .try_collect::<HashMap<String, Self::Value>>()
-            .await?;
+            .collect::<HashMap<String, Self::Value>>();
 
-        Ok(messages)
+        Ok(blocks)
     }
 }
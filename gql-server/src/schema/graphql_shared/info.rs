@@ -7,6 +7,7 @@ use std::time::UNIX_EPOCH;
 use async_graphql::SimpleObject;
 
 use crate::defaults;
+use crate::schema::db;
 
 #[derive(SimpleObject, Clone)]
 #[graphql(rename_fields = "camelCase")]
@@ -41,6 +42,10 @@ pub struct Info {
     pub remp_enabled: Option<bool>,
     /// The maximum number of records returned by the request.
     pub batch_size: Option<u16>,
+    /// Seq no of the latest archived block.
+    pub latest_seq_no: Option<i64>,
+    /// Archive lag in ms (server time - latest archived block gen time).
+    pub archive_lag_ms: Option<f64>,
 }
 
 impl Default for Info {
@@ -57,6 +62,18 @@ impl Default for Info {
             chain_order_boundary: None,
             remp_enabled: Some(false),
             batch_size: Some(defaults::QUERY_BATCH_SIZE),
+            latest_seq_no: None,
+            archive_lag_ms: None,
+        }
+    }
+}
+
+impl Info {
+    pub(crate) fn with_archive_head(self, head: Option<db::ArchiveHead>) -> Self {
+        Self {
+            latest_seq_no: head.as_ref().map(|head| head.seq_no),
+            archive_lag_ms: head.as_ref().map(|head| head.lag_ms() as f64),
+            ..self
         }
     }
 }
@@ -5,13 +5,12 @@ use std::collections::HashMap;
 
 use async_graphql::dataloader::Loader;
 use async_graphql::Error;
-use futures::TryStreamExt;
-use sqlx::SqlitePool;
 
 use crate::schema::db;
+use crate::schema::db::ArchiveShards;
 
 pub struct MessageLoader {
-    pub pool: SqlitePool,
+    pub archive: ArchiveShards,
 }
 
 impl Loader<String> for MessageLoader {
@@ -25,15 +24,23 @@ impl Loader<String> for MessageLoader {
         let ids = keys.iter().map(|m| format!("{m:?}")).collect::<Vec<_>>().join(",");
         let sql = format!("SELECT * FROM messages WHERE id IN ({ids})");
         tracing::trace!(target: "data_loader",  "SQL: {sql}");
-        let messages = sqlx::query_as(&sql)
-            .fetch(&self.pool)
-            .map_ok(|msg: db::Message| {
+        let sql = sql.as_str();
+        let rows = self
+            .archive
+            .collect(|pool| async move {
+                Ok::<_, anyhow::Error>(
+                    sqlx::query_as::<_, db::Message>(sql).fetch_all(&pool).await?,
+                )
+            })
+            .await?;
+        let messages = rows
+            .into_iter()
+            .map(|msg| {
                 let message: Self::Value = msg.into();
                 let message_id = message.id.clone();
                 (message_id, message)
             })
-            .try_collect::<HashMap<String, Self::Value>>()
-            .await?;
+            .collect::<HashMap<String, Self::Value>>();
 
         Ok(messages)
     }
@@ -5,13 +5,12 @@ use std::collections::HashMap;
 
 use async_graphql::dataloader::Loader;
 use async_graphql::Error;
-use futures::TryStreamExt;
-use sqlx::SqlitePool;
 
 use crate::schema::db;
+use crate::schema::db::ArchiveShards;
 
 pub struct TransactionLoader {
-    pub pool: SqlitePool,
+    pub archive: ArchiveShards,
 }
 
 impl Loader<String> for TransactionLoader {
@@ -25,16 +24,24 @@ impl Loader<String> for TransactionLoader {
         let ids = keys.iter().map(|m| format!("{m:?}")).collect::<Vec<_>>().join(",");
         let sql = format!("SELECT * FROM transactions WHERE id IN ({ids})");
         tracing::trace!(target: "data_loader",  "SQL: {sql}");
-        let messages = sqlx::query_as(&sql)
-            .fetch(&self.pool)
-            .map_ok(|transaction: db::Transaction| {
+        let sql = sql.as_str();
+        let rows = self
+            .archive
+            .collect(|pool| async move {
+                Ok::<_, anyhow::Error>(
+                    sqlx::query_as::<_, db::Transaction>(sql).fetch_all(&pool).await?,
+                )
+            })
+            .await?;
+        let transactions = rows
+            .into_iter()
+            .map(|transaction| {
                 let transaction: Self::Value = transaction.into();
                 let transaction_id = transaction.id.clone();
                 (transaction_id, transaction)
             })
-            .try_collect::<HashMap<String, Self::Value>>()
-            .await?;
+            .collect::<HashMap<String, Self::Value>>();
 
-        Ok(messages)
+        Ok(transactions)
     }
 }
@@ -5,13 +5,13 @@ use async_graphql::dataloader::DataLoader;
 use async_graphql::Context;
 use async_graphql::FieldResult;
 use async_graphql::Object;
-use sqlx::SqlitePool;
 
 mod account;
 pub(crate) mod events;
 
 use crate::helpers::query_order_by_str;
 use crate::schema::db;
+use crate::schema::db::ArchiveShards;
 use crate::schema::graphql::block::Block;
 use crate::schema::graphql::block::BlockFilter;
 use crate::schema::graphql::filter::WhereOp;
@@ -26,10 +26,10 @@ pub struct QueryRoot;
 impl QueryRoot {
     async fn info(&self, ctx: &Context<'_>) -> FieldResult<Option<Info>> {
         tracing::info!("info query");
-        let pool = ctx.data::<SqlitePool>()?;
+        let archive = ctx.data::<ArchiveShards>()?;
 
         let gen_utime = if ctx.look_ahead().field("last_block_time").exists() {
-            let block = db::Block::latest_block(pool).await?;
+            let block = db::Block::latest_block(archive).await?;
             match block {
                 Some(db::Block { gen_utime, .. }) => gen_utime,
                 None => None,
@@ -37,7 +37,18 @@ impl QueryRoot {
         } else {
             None
         };
-        Ok(Some(Info { last_block_time: Some(gen_utime.unwrap_or(0) as f64), ..Info::default() }))
+        let look_ahead = ctx.look_ahead();
+        let archive_head = if look_ahead.field("latestSeqNo").exists()
+            || look_ahead.field("archiveLagMs").exists()
+        {
+            db::Block::archive_head(archive).await?
+        } else {
+            None
+        };
+        Ok(Some(
+            Info { last_block_time: Some(gen_utime.unwrap_or(0) as f64), ..Info::default() }
+                .with_archive_head(archive_head),
+        ))
     }
 
     async fn account(&self, address: String) -> Option<AccountQuery> {
@@ -51,14 +62,14 @@ impl QueryRoot {
         order_by: Option<Vec<Option<QueryOrderBy>>>,
         limit: Option<i32>,
     ) -> FieldResult<Option<Vec<Option<Block>>>> {
-        let pool = ctx.data::<SqlitePool>()?;
+        let archive = ctx.data::<ArchiveShards>()?;
         let filter = match filter {
             Some(f) => BlockFilter::to_where(&f).unwrap_or("".to_string()),
             None => "".to_string(),
         };
         let order_by_clause = query_order_by_str(order_by);
         let db_blocks: Vec<db::Block> =
-            db::Block::list(pool, filter, order_by_clause, limit).await?;
+            db::Block::list(archive, filter, order_by_clause, limit).await?;
         let mut blocks = db_blocks
             .into_iter()
             .map(|b| Some(Into::<Block>::into(b)))
@@ -66,7 +77,12 @@ impl QueryRoot {
 
         if ctx.look_ahead().field("in_msg_descr").exists() {
             for b in blocks.iter_mut().flatten() {
-                let in_msgs = db::Message::in_block_msgs(pool, b.id.to_string()).await?;
+                let block_id = b.id.as_str();
+                let in_msgs = archive
+                    .collect(|pool| async move {
+                        db::Message::in_block_msgs(&pool, block_id.to_string()).await
+                    })
+                    .await?;
                 b.set_in_msg_descr(in_msgs);
             }
         }
@@ -24,12 +24,19 @@ use warp::http::StatusCode;
 use warp::Filter;
 use warp::Rejection;
 
+use crate::schema::db;
+use crate::schema::db::ArchiveShards;
 use crate::schema::graphql::block::BlockLoader;
 use crate::schema::graphql::message::MessageLoader;
 use crate::schema::graphql::transaction::TransactionLoader;
 use crate::schema::graphql_ext;
+use crate::schema::graphql_ext::blockchain_api::filter::BlockchainSnapshot;
 use crate::schema::graphql_std;
 
+// Pins the `blockchain` reads of the operation to a master seq_no, same as
+// the `at_seq_no` argument
+const AT_SEQ_NO_HEADER: &str = "x-at-seq-no";
+
 async fn open_db(db_path: PathBuf) -> anyhow::Result<Pool<Sqlite>> {
     let db_path_str = db_path.display().to_string();
     let mut interval = time::interval(time::Duration::from_secs(3));
@@ -58,9 +65,81 @@ async fn open_db(db_path: PathBuf) -> anyhow::Result<Pool<Sqlite>> {
     Ok(pool)
 }
 
-pub async fn start(bind_to: String, db_path: PathBuf) -> anyhow::Result<()> {
-    let pool = open_db(db_path).await?;
+// `/healthz` reports whether the DB is reachable, `/readyz` additionally
+// requires the archive to be close to the network head, so load balancers
+// can route away from stale replicas.
+fn health_routes(
+    archive: ArchiveShards,
+    max_archive_lag_ms: u64,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    let healthz_archive = archive.clone();
+    let healthz = warp::path!("healthz").and(warp::get()).then(move || {
+        let archive = healthz_archive.clone();
+        async move {
+            let ping = archive
+                .collect(|pool| async move {
+                    sqlx::query("SELECT 1").execute(&pool).await?;
+                    Ok::<_, anyhow::Error>(vec![()])
+                })
+                .await;
+            match ping {
+                Ok(_) => warp::reply::with_status("OK".to_string(), StatusCode::OK),
+                Err(err) => {
+                    tracing::warn!("Health check failed: {err}");
+                    warp::reply::with_status(
+                        format!("DB is unreachable: {err}"),
+                        StatusCode::SERVICE_UNAVAILABLE,
+                    )
+                }
+            }
+        }
+    });
+
+    let readyz = warp::path!("readyz").and(warp::get()).then(move || {
+        let archive = archive.clone();
+        async move {
+            match db::Block::archive_head(&archive).await {
+                Ok(Some(head)) if head.lag_ms() as u64 <= max_archive_lag_ms => {
+                    warp::reply::with_status("OK".to_string(), StatusCode::OK)
+                }
+                Ok(Some(head)) => warp::reply::with_status(
+                    format!(
+                        "Archive lag {} ms exceeds {max_archive_lag_ms} ms (seq_no: {})",
+                        head.lag_ms(),
+                        head.seq_no
+                    ),
+                    StatusCode::SERVICE_UNAVAILABLE,
+                ),
+                Ok(None) => warp::reply::with_status(
+                    "Archive is empty".to_string(),
+                    StatusCode::SERVICE_UNAVAILABLE,
+                ),
+                Err(err) => {
+                    tracing::warn!("Readiness check failed: {err}");
+                    warp::reply::with_status(
+                        format!("DB is unreachable: {err}"),
+                        StatusCode::SERVICE_UNAVAILABLE,
+                    )
+                }
+            }
+        }
+    });
+
+    healthz.or(readyz).unify()
+}
+
+pub async fn start(
+    bind_to: String,
+    db_path: PathBuf,
+    shards_dir: Option<PathBuf>,
+    max_archive_lag_ms: u64,
+) -> anyhow::Result<()> {
+    let archive = match shards_dir {
+        Some(shards_dir) => ArchiveShards::open_dir(shards_dir).await?,
+        None => ArchiveShards::single(open_db(db_path).await?),
+    };
     let socket_addr = bind_to.parse::<SocketAddr>()?;
+    let health = health_routes(archive.clone(), max_archive_lag_ms);
 
     let graphql_playground = warp::path!("graphql_old").and(warp::get()).map(move || {
         HttpResponse::builder()
@@ -76,24 +155,39 @@ pub async fn start(bind_to: String, db_path: PathBuf) -> anyhow::Result<()> {
 
     if !cfg!(feature = "store_events_only") {
         let schema = Schema::build(graphql_ext::QueryRoot, EmptyMutation, EmptySubscription)
-            .data(pool.clone())
-            .data(DataLoader::new(BlockLoader { pool: pool.clone() }, tokio::spawn))
-            .data(DataLoader::new(MessageLoader { pool: pool.clone() }, tokio::spawn))
-            .data(DataLoader::new(TransactionLoader { pool }, tokio::spawn))
+            .data(archive.clone())
+            .data(DataLoader::new(BlockLoader { archive: archive.clone() }, tokio::spawn))
+            .data(DataLoader::new(MessageLoader { archive: archive.clone() }, tokio::spawn))
+            .data(DataLoader::new(TransactionLoader { archive }, tokio::spawn))
             .with_sorted_fields()
             .finish();
 
-        let graphql_post = async_graphql_warp::graphql(schema).and_then(
-            |(schema, request): (
-                Schema<graphql_ext::QueryRoot, EmptyMutation, EmptySubscription>,
-                async_graphql::Request,
-            )| async move {
-                Ok::<_, Infallible>(GraphQLResponse::from(schema.execute(request).await))
-            },
-        );
-
-        let routes =
-            graphql_post.or(graphql_playground).or(graphiql).recover(|err: Rejection| async move {
+        let graphql_post = async_graphql_warp::graphql(schema)
+            .and(warp::header::optional::<i32>(AT_SEQ_NO_HEADER))
+            .and_then(
+                |(schema, mut request): (
+                    Schema<graphql_ext::QueryRoot, EmptyMutation, EmptySubscription>,
+                    async_graphql::Request,
+                ),
+                 at_seq_no: Option<i32>| async move {
+                    if let Some(at_seq_no) = at_seq_no {
+                        match BlockchainSnapshot::new(at_seq_no) {
+                            Ok(snapshot) => request = request.data(snapshot),
+                            Err(err) => {
+                                return Ok::<_, Infallible>(GraphQLResponse::from(
+                                    async_graphql::Response::from_errors(vec![
+                                        async_graphql::ServerError::new(err.to_string(), None),
+                                    ]),
+                                ));
+                            }
+                        }
+                    }
+                    Ok(GraphQLResponse::from(schema.execute(request).await))
+                },
+            );
+
+        let routes = health.or(graphql_post).or(graphql_playground).or(graphiql).recover(
+            |err: Rejection| async move {
                 if let Some(GraphQLBadRequest(err)) = err.find() {
                     return Ok::<_, Infallible>(warp::reply::with_status(
                         err.to_string(),
@@ -105,13 +199,14 @@ pub async fn start(bind_to: String, db_path: PathBuf) -> anyhow::Result<()> {
                     "INTERNAL_SERVER_ERROR".to_string(),
                     StatusCode::INTERNAL_SERVER_ERROR,
                 ))
-            });
+            },
+        );
 
         tracing::info!("[API:extended] Listening on: {}\n", bind_to);
         warp::serve(routes).run((socket_addr.ip(), socket_addr.port())).await;
     } else {
         let schema = Schema::build(graphql_std::QueryRoot, EmptyMutation, EmptySubscription)
-            .data(pool.clone())
+            .data(archive.clone())
             .with_sorted_fields()
             .finish();
 
@@ -124,8 +219,8 @@ pub async fn start(bind_to: String, db_path: PathBuf) -> anyhow::Result<()> {
             },
         );
 
-        let routes =
-            graphql_post.or(graphql_playground).or(graphiql).recover(|err: Rejection| async move {
+        let routes = health.or(graphql_post).or(graphql_playground).or(graphiql).recover(
+            |err: Rejection| async move {
                 if let Some(GraphQLBadRequest(err)) = err.find() {
                     return Ok::<_, Infallible>(warp::reply::with_status(
                         err.to_string(),
@@ -137,7 +232,8 @@ pub async fn start(bind_to: String, db_path: PathBuf) -> anyhow::Result<()> {
                     "INTERNAL_SERVER_ERROR".to_string(),
                     StatusCode::INTERNAL_SERVER_ERROR,
                 ))
-            });
+            },
+        );
 
         tracing::info!("[API:standard] Listening on: {}\n", bind_to);
         warp::serve(routes).run((socket_addr.ip(), socket_addr.port())).await;
@@ -0,0 +1,149 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::marker::PhantomData;
+
+use salvo::prelude::*;
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+
+use crate::ApiError;
+use crate::ResolvingResult;
+use crate::WebServer;
+
+/// Request to the node for the account replay protection state.
+pub struct AccountNonceRequest {
+    pub address: String,
+    pub response: oneshot::Sender<anyhow::Result<AccountNonce>>,
+}
+
+pub type AccountNonceRequestSender = mpsc::Sender<AccountNonceRequest>;
+
+/// Contracts with the `time` ABI header accept an external message only if
+/// its `time` is greater than the one stored by the previous accepted message.
+/// Nonce is the `time` value a wallet should use for the next message.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct AccountNonce {
+    pub address: String,
+    /// Replay protection timestamp stored in the account data of the
+    /// optimistic state. None if the account data doesn't contain it.
+    pub state_seqno: Option<u64>,
+    /// Number of the account external messages waiting in the mempool.
+    pub pending_messages: usize,
+    /// Max `time` header of the pending messages.
+    pub pending_max_seqno: Option<u64>,
+    pub next_seqno: u64,
+}
+
+impl AccountNonce {
+    /// `now` is unix time in ms: `time` of the message is also checked
+    /// against the current time, so the nonce never lags behind it.
+    pub fn new(
+        address: String,
+        state_seqno: Option<u64>,
+        pending_seqnos: &[u64],
+        pending_messages: usize,
+        now: u64,
+    ) -> Self {
+        let pending_max_seqno = pending_seqnos.iter().max().copied();
+        let next_seqno = state_seqno
+            .into_iter()
+            .chain(pending_max_seqno)
+            .map(|seqno| seqno.saturating_add(1))
+            .fold(now, u64::max);
+        Self { address, state_seqno, pending_messages, pending_max_seqno, next_seqno }
+    }
+}
+
+pub struct AccountNonceHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>(
+    PhantomData<(TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter)>,
+);
+
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+    AccountNonceHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+{
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[async_trait]
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter> Handler
+    for AccountNonceHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+where
+    TMessage: Clone + Send + Sync + 'static + std::fmt::Debug,
+    TMsgConverter: Clone
+        + Send
+        + Sync
+        + 'static
+        + Fn(tvm_block::Message, [u8; 34]) -> anyhow::Result<TMessage>,
+    TBPResolver: Clone + Send + Sync + 'static + FnMut([u8; 34]) -> ResolvingResult,
+    TBocByAddrGetter:
+        Clone + Send + Sync + 'static + Fn(String) -> anyhow::Result<(String, Option<String>)>,
+    TSeqnoGetter: Clone + Send + Sync + 'static + Fn() -> anyhow::Result<u32>,
+{
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        _ctrl: &mut FlowCtrl,
+    ) {
+        let address: String = req.query("address").unwrap_or_default();
+        let address = address.trim_start_matches("0:").to_string();
+        if address.is_empty() {
+            res.status_code(StatusCode::BAD_REQUEST);
+            res.render("Address parameter required");
+            return;
+        }
+
+        let Ok(web_server) = depot.obtain::<WebServer<
+            TMessage,
+            TMsgConverter,
+            TBPResolver,
+            TBocByAddrGetter,
+            TSeqnoGetter,
+        >>() else {
+            res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+            res.render("Internal server error: Web Server state not found");
+            return;
+        };
+
+        let (response, response_rx) = oneshot::channel();
+        let result = match web_server
+            .account_nonce_request_sender
+            .send(AccountNonceRequest { address, response })
+            .await
+        {
+            Ok(()) => response_rx
+                .await
+                .unwrap_or_else(|_| Err(anyhow::format_err!("Account nonce request dropped"))),
+            Err(_) => Err(anyhow::format_err!("Account nonce service is not running")),
+        };
+        match result {
+            Ok(nonce) => res.render(Json(nonce)),
+            Err(e) => {
+                ApiError::from_anyhow(&e, "ACCOUNT_NOT_FOUND").render(res, StatusCode::NOT_FOUND);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_seqno() {
+        let nonce = AccountNonce::new("a".to_string(), Some(100), &[], 0, 50);
+        assert_eq!(nonce.next_seqno, 101);
+
+        let nonce = AccountNonce::new("a".to_string(), Some(100), &[120, 110], 3, 50);
+        assert_eq!(nonce.pending_max_seqno, Some(120));
+        assert_eq!(nonce.next_seqno, 121);
+
+        let nonce = AccountNonce::new("a".to_string(), None, &[], 0, 50);
+        assert_eq!(nonce.next_seqno, 50);
+    }
+}
@@ -0,0 +1,220 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use salvo::async_trait;
+use salvo::http::StatusCode;
+use salvo::prelude::Json;
+use salvo::Depot;
+use salvo::FlowCtrl;
+use salvo::Handler;
+use salvo::Request;
+use salvo::Response;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+
+use crate::ApiError;
+use crate::ResolvingResult;
+use crate::WebServer;
+
+const MIN_BIT_COUNT: u64 = 64;
+
+/// Bloom filter of the accounts owned by a thread. A miss means the account
+/// doesn't belong to the thread, a hit may be false with the configured rate.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AccountOwnershipFilter {
+    /// Hex of the filter bits, bit `i` is `bits[i / 8] >> (i % 8) & 1`
+    #[serde(serialize_with = "serialize_hex", deserialize_with = "deserialize_hex")]
+    bits: Vec<u8>,
+    bit_count: u64,
+    hash_count: u32,
+}
+
+fn serialize_hex<S: Serializer>(bits: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&hex::encode(bits))
+}
+
+fn deserialize_hex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    let bits = String::deserialize(deserializer)?;
+    hex::decode(bits).map_err(serde::de::Error::custom)
+}
+
+impl AccountOwnershipFilter {
+    pub fn new(expected_accounts: usize, false_positive_rate: f64) -> Self {
+        let ln2 = std::f64::consts::LN_2;
+        let accounts = expected_accounts.max(1) as f64;
+        let bit_count = ((-accounts * false_positive_rate.clamp(f64::MIN_POSITIVE, 0.5).ln())
+            / (ln2 * ln2))
+            .ceil() as u64;
+        let bit_count = bit_count.max(MIN_BIT_COUNT);
+        let hash_count = ((bit_count as f64 / accounts) * ln2).round().max(1.0) as u32;
+        Self { bits: vec![0; bit_count.div_ceil(8) as usize], bit_count, hash_count }
+    }
+
+    // Account addresses are hashes already, their parts are used as the two
+    // base hashes of the double hashing
+    fn bit_indexes(&self, account: &[u8; 32]) -> impl Iterator<Item = u64> + '_ {
+        let h1 = u64::from_le_bytes(account[0..8].try_into().expect("8 bytes"));
+        let h2 = u64::from_le_bytes(account[8..16].try_into().expect("8 bytes")) | 1;
+        (0..self.hash_count as u64)
+            .map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % self.bit_count)
+    }
+
+    pub fn insert(&mut self, account: &[u8; 32]) {
+        let indexes = self.bit_indexes(account).collect::<Vec<_>>();
+        for index in indexes {
+            self.bits[(index / 8) as usize] |= 1 << (index % 8);
+        }
+    }
+
+    pub fn may_contain(&self, account: &[u8; 32]) -> bool {
+        self.bit_indexes(account)
+            .all(|index| self.bits[(index / 8) as usize] & (1 << (index % 8)) != 0)
+    }
+}
+
+/// Ownership of the accounts of the dapp id table by a thread, as of its last
+/// finalized state. Accounts missing in the table are routed by their address
+/// with `threads_table` rules.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AccountOwnership {
+    pub thread_id: String,
+    pub block_seq_no: u32,
+    /// Number of the dapp id table accounts owned by the thread.
+    pub accounts: usize,
+    pub filter: AccountOwnershipFilter,
+}
+
+/// Shared between the account ownership service and the web server: the
+/// service publishes the filters of the finalized states, the web server
+/// serves them to the proxies and routers.
+#[derive(Clone, Default)]
+pub struct AccountOwnershipFeed {
+    latest: Arc<parking_lot::RwLock<HashMap<String, Arc<AccountOwnership>>>>,
+}
+
+impl AccountOwnershipFeed {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn publish(&self, ownership: AccountOwnership) {
+        self.latest.write().insert(ownership.thread_id.clone(), Arc::new(ownership));
+    }
+
+    pub fn remove_thread(&self, thread_id: &str) {
+        self.latest.write().remove(thread_id);
+    }
+
+    pub fn get(&self, thread_id: &str) -> Option<Arc<AccountOwnership>> {
+        self.latest.read().get(thread_id).cloned()
+    }
+}
+
+pub struct AccountOwnershipHandler<
+    TMessage,
+    TMsgConverter,
+    TBPResolver,
+    TBocByAddrGetter,
+    TSeqnoGetter,
+>(PhantomData<(TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter)>);
+
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+    AccountOwnershipHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+{
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[async_trait]
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter> Handler
+    for AccountOwnershipHandler<
+        TMessage,
+        TMsgConverter,
+        TBPResolver,
+        TBocByAddrGetter,
+        TSeqnoGetter,
+    >
+where
+    TMessage: Clone + Send + Sync + 'static + std::fmt::Debug,
+    TMsgConverter: Clone
+        + Send
+        + Sync
+        + 'static
+        + Fn(tvm_block::Message, [u8; 34]) -> anyhow::Result<TMessage>,
+    TBPResolver: Clone + Send + Sync + 'static + FnMut([u8; 34]) -> ResolvingResult,
+    TBocByAddrGetter:
+        Clone + Send + Sync + 'static + Fn(String) -> anyhow::Result<(String, Option<String>)>,
+    TSeqnoGetter: Clone + Send + Sync + 'static + Fn() -> anyhow::Result<u32>,
+{
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        _ctrl: &mut FlowCtrl,
+    ) {
+        let Some(thread_id) = req.query::<String>("thread_id") else {
+            res.status_code(StatusCode::BAD_REQUEST);
+            res.render("thread_id parameter required");
+            return;
+        };
+        let Ok(web_server) = depot.obtain::<WebServer<
+            TMessage,
+            TMsgConverter,
+            TBPResolver,
+            TBocByAddrGetter,
+            TSeqnoGetter,
+        >>() else {
+            res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+            res.render("Internal server error: Web Server state not found");
+            return;
+        };
+        match web_server.account_ownership.get(&thread_id.to_lowercase()) {
+            Some(ownership) => res.render(Json(ownership.as_ref())),
+            None => {
+                ApiError::new(
+                    "THREAD_NOT_FOUND",
+                    "Account ownership of the thread is not known",
+                    false,
+                )
+                .render(res, StatusCode::NOT_FOUND);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(seed: u64) -> [u8; 32] {
+        let mut account = [0; 32];
+        // Spread the seed over the both base hashes
+        account[0..8].copy_from_slice(&seed.wrapping_mul(0x9e3779b97f4a7c15).to_le_bytes());
+        account[8..16].copy_from_slice(&seed.wrapping_mul(0xc2b2ae3d27d4eb4f).to_le_bytes());
+        account
+    }
+
+    #[test]
+    fn test_account_ownership_filter() {
+        let mut filter = AccountOwnershipFilter::new(1000, 0.01);
+        for seed in 0..1000 {
+            filter.insert(&account(seed));
+        }
+        assert!((0..1000).all(|seed| filter.may_contain(&account(seed))));
+        let false_positives =
+            (1000..11000).filter(|seed| filter.may_contain(&account(*seed))).count();
+        assert!(false_positives < 300, "Too many false positives: {false_positives}");
+
+        let json = serde_json::to_string(&filter).unwrap();
+        let restored: AccountOwnershipFilter = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, filter);
+    }
+}
@@ -0,0 +1,207 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::marker::PhantomData;
+
+use salvo::async_trait;
+use salvo::http::StatusCode;
+use salvo::sse::SseEvent;
+use salvo::sse::SseKeepAlive;
+use salvo::Depot;
+use salvo::FlowCtrl;
+use salvo::Handler;
+use salvo::Request;
+use salvo::Response;
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+use crate::ApiError;
+use crate::ResolvingResult;
+use crate::WebServer;
+
+// The node stops exporting while the client doesn't read the stream
+const ACCOUNTS_EXPORT_CHANNEL_CAPACITY: usize = 256;
+
+/// Accounts to export. At least one of the fields is set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccountsExportFilter {
+    /// Hex code hash of the accounts
+    pub code_hash: Option<String>,
+    /// Hex prefix of the account id (without workchain)
+    pub address_prefix: Option<String>,
+}
+
+/// Request to the node to stream the accounts matching the filter. The node
+/// sends the manifest first, then the accounts and the end (or error) record.
+pub struct AccountsExportRequest {
+    pub filter: AccountsExportFilter,
+    pub records: mpsc::Sender<AccountsExportRecord>,
+}
+
+pub type AccountsExportRequestSender = mpsc::Sender<AccountsExportRequest>;
+
+/// Finalized state of a thread the accounts are exported from.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ExportedThreadState {
+    pub thread_id: String,
+    pub block_id: String,
+    pub block_seq_no: u32,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum AccountsExportRecord {
+    Manifest {
+        code_hash: Option<String>,
+        address_prefix: Option<String>,
+        states: Vec<ExportedThreadState>,
+    },
+    Account {
+        address: String,
+        thread_id: String,
+        code_hash: Option<String>,
+        last_trans_lt: u64,
+        /// Base64 account BOC
+        boc: String,
+    },
+    End {
+        count: usize,
+    },
+    Error {
+        message: String,
+    },
+}
+
+impl AccountsExportRecord {
+    fn event_name(&self) -> &'static str {
+        match self {
+            Self::Manifest { .. } => "manifest",
+            Self::Account { .. } => "account",
+            Self::End { .. } => "end",
+            Self::Error { .. } => "error",
+        }
+    }
+}
+
+fn parse_hex_param(value: Option<String>, max_len: usize) -> Result<Option<String>, String> {
+    let Some(value) = value.map(|value| value.to_lowercase()) else {
+        return Ok(None);
+    };
+    if value.is_empty() || value.len() > max_len || !value.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Expected up to {max_len} hex digits, got: {value}"));
+    }
+    Ok(Some(value))
+}
+
+pub struct AccountsExportHandler<
+    TMessage,
+    TMsgConverter,
+    TBPResolver,
+    TBocByAddrGetter,
+    TSeqnoGetter,
+>(PhantomData<(TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter)>);
+
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+    AccountsExportHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+{
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[async_trait]
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter> Handler
+    for AccountsExportHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+where
+    TMessage: Clone + Send + Sync + 'static + std::fmt::Debug,
+    TMsgConverter: Clone
+        + Send
+        + Sync
+        + 'static
+        + Fn(tvm_block::Message, [u8; 34]) -> anyhow::Result<TMessage>,
+    TBPResolver: Clone + Send + Sync + 'static + FnMut([u8; 34]) -> ResolvingResult,
+    TBocByAddrGetter:
+        Clone + Send + Sync + 'static + Fn(String) -> anyhow::Result<(String, Option<String>)>,
+    TSeqnoGetter: Clone + Send + Sync + 'static + Fn() -> anyhow::Result<u32>,
+{
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        _ctrl: &mut FlowCtrl,
+    ) {
+        let filter = parse_hex_param(req.query::<String>("code_hash"), 64).and_then(|code_hash| {
+            Ok(AccountsExportFilter {
+                code_hash,
+                address_prefix: parse_hex_param(req.query::<String>("address_prefix"), 64)?,
+            })
+        });
+        let filter = match filter {
+            Ok(filter) if filter.code_hash.is_some() || filter.address_prefix.is_some() => filter,
+            Ok(_) => {
+                ApiError::new(
+                    "INVALID_FILTER",
+                    "code_hash or address_prefix parameter required",
+                    false,
+                )
+                .render(res, StatusCode::BAD_REQUEST);
+                return;
+            }
+            Err(e) => {
+                ApiError::new("INVALID_FILTER", e, false).render(res, StatusCode::BAD_REQUEST);
+                return;
+            }
+        };
+
+        let Ok(web_server) = depot.obtain::<WebServer<
+            TMessage,
+            TMsgConverter,
+            TBPResolver,
+            TBocByAddrGetter,
+            TSeqnoGetter,
+        >>() else {
+            res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+            res.render("Internal server error: Web Server state not found");
+            return;
+        };
+
+        let (records, records_rx) = mpsc::channel(ACCOUNTS_EXPORT_CHANNEL_CAPACITY);
+        if web_server
+            .accounts_export_request_sender
+            .send(AccountsExportRequest { filter, records })
+            .await
+            .is_err()
+        {
+            ApiError::new(
+                "ACCOUNTS_EXPORT_FAILED",
+                "Accounts export service is not running",
+                false,
+            )
+            .render(res, StatusCode::SERVICE_UNAVAILABLE);
+            return;
+        }
+
+        let events = futures::stream::unfold(records_rx, |mut records_rx| async move {
+            let record = records_rx.recv().await?;
+            let event = serde_json::to_string(&record)
+                .map(|data| SseEvent::default().name(record.event_name()).text(data));
+            Some((event, records_rx))
+        });
+        SseKeepAlive::new(events).stream(res);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hex_param() {
+        assert_eq!(parse_hex_param(None, 64), Ok(None));
+        assert_eq!(parse_hex_param(Some("AB01".to_string()), 64), Ok(Some("ab01".to_string())));
+        assert!(parse_hex_param(Some(String::new()), 64).is_err());
+        assert!(parse_hex_param(Some("0:ab".to_string()), 64).is_err());
+        assert!(parse_hex_param(Some("a".repeat(65)), 64).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::BTreeMap;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::marker::PhantomData;
+use std::path::Path;
+use std::sync::Arc;
+
+use salvo::async_trait;
+use salvo::http::StatusCode;
+use salvo::prelude::Json;
+use salvo::Depot;
+use salvo::FlowCtrl;
+use salvo::Handler;
+use salvo::Request;
+use salvo::Response;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::ResolvingResult;
+use crate::WebServer;
+
+// Number of the latest entries served by the web server, older ones are only
+// kept in the log file
+const RECENT_ENTRIES_CAPACITY: usize = 1024;
+
+/// Admin endpoint invocation that could change the node state (any method
+/// but GET).
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AdminAuditEntry {
+    /// Unix time (ms) of the invocation.
+    pub timestamp: u64,
+    /// `auth_token` or `wallet:<signing pubkey>`, depending on the admin auth
+    /// mode.
+    pub principal: String,
+    pub method: String,
+    pub path: String,
+    pub params: BTreeMap<String, String>,
+    /// Status code of the response.
+    pub status: u16,
+}
+
+/// Shared between the admin auth middleware and the web server: the
+/// middleware appends the invocations to the log file, the web server serves
+/// the recent ones.
+#[derive(Clone, Default)]
+pub struct AdminAuditLog {
+    // Not set if the log is kept in memory only
+    file: Option<Arc<parking_lot::Mutex<File>>>,
+    recent: Arc<parking_lot::RwLock<VecDeque<AdminAuditEntry>>>,
+}
+
+impl AdminAuditLog {
+    /// Opens the JSON lines log for appending and loads its latest entries.
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let mut recent = VecDeque::new();
+        if path.exists() {
+            let file = File::open(path)
+                .map_err(|e| anyhow::format_err!("Failed to open admin audit log {path:?}: {e}"))?;
+            for line in BufReader::new(file).lines() {
+                // An interrupted write leaves a broken last line
+                let Ok(entry) = serde_json::from_str::<AdminAuditEntry>(&line?) else {
+                    continue;
+                };
+                if recent.len() == RECENT_ENTRIES_CAPACITY {
+                    recent.pop_front();
+                }
+                recent.push_back(entry);
+            }
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| anyhow::format_err!("Failed to open admin audit log {path:?}: {e}"))?;
+        tracing::info!("Admin audit log opened: {} recent entries", recent.len());
+        Ok(Self {
+            file: Some(Arc::new(parking_lot::Mutex::new(file))),
+            recent: Arc::new(parking_lot::RwLock::new(recent)),
+        })
+    }
+
+    pub fn record(&self, entry: AdminAuditEntry) {
+        tracing::info!(
+            "Admin action: {} {} {:?} by {} -> {}",
+            entry.method,
+            entry.path,
+            entry.params,
+            entry.principal,
+            entry.status
+        );
+        if let Some(file) = &self.file {
+            let appended = serde_json::to_string(&entry)
+                .map_err(anyhow::Error::from)
+                .and_then(|line| Ok(writeln!(file.lock(), "{line}")?));
+            if let Err(e) = appended {
+                tracing::error!("Failed to append admin audit log: {e}");
+            }
+        }
+        let mut recent = self.recent.write();
+        if recent.len() == RECENT_ENTRIES_CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(entry);
+    }
+
+    /// Entries from the newest to the oldest.
+    pub fn recent(&self, principal: Option<&str>, limit: usize) -> Vec<AdminAuditEntry> {
+        self.recent
+            .read()
+            .iter()
+            .rev()
+            .filter(|entry| principal.is_none_or(|principal| entry.principal == principal))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+pub struct AdminAuditHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>(
+    PhantomData<(TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter)>,
+);
+
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+    AdminAuditHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+{
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[async_trait]
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter> Handler
+    for AdminAuditHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+where
+    TMessage: Clone + Send + Sync + 'static + std::fmt::Debug,
+    TMsgConverter: Clone
+        + Send
+        + Sync
+        + 'static
+        + Fn(tvm_block::Message, [u8; 34]) -> anyhow::Result<TMessage>,
+    TBPResolver: Clone + Send + Sync + 'static + FnMut([u8; 34]) -> ResolvingResult,
+    TBocByAddrGetter:
+        Clone + Send + Sync + 'static + Fn(String) -> anyhow::Result<(String, Option<String>)>,
+    TSeqnoGetter: Clone + Send + Sync + 'static + Fn() -> anyhow::Result<u32>,
+{
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        _ctrl: &mut FlowCtrl,
+    ) {
+        let Ok(web_server) = depot.obtain::<WebServer<
+            TMessage,
+            TMsgConverter,
+            TBPResolver,
+            TBocByAddrGetter,
+            TSeqnoGetter,
+        >>() else {
+            res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+            res.render("Internal server error: Web Server state not found");
+            return;
+        };
+        let principal = req.query::<String>("principal");
+        let limit = req.query::<usize>("limit").unwrap_or(RECENT_ENTRIES_CAPACITY);
+        res.status_code(StatusCode::OK);
+        res.render(Json(web_server.admin_audit_log.recent(principal.as_deref(), limit)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(principal: &str, timestamp: u64) -> AdminAuditEntry {
+        AdminAuditEntry {
+            timestamp,
+            principal: principal.to_string(),
+            method: "POST".to_string(),
+            path: "/v2/thread_splits".to_string(),
+            params: BTreeMap::from([("action".to_string(), "approve".to_string())]),
+            status: 200,
+        }
+    }
+
+    #[test]
+    fn test_admin_audit_log_is_restored() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("admin-audit");
+        let log = AdminAuditLog::open(&path).unwrap();
+        log.record(entry("auth_token", 1));
+        log.record(entry("wallet:aa", 2));
+        // Broken line of an interrupted write is skipped
+        writeln!(log.file.as_ref().unwrap().lock(), "{{\"timestamp\":").unwrap();
+        drop(log);
+
+        let log = AdminAuditLog::open(&path).unwrap();
+        log.record(entry("auth_token", 3));
+        let recent = log.recent(None, usize::MAX);
+        assert_eq!(recent.iter().map(|entry| entry.timestamp).collect::<Vec<_>>(), vec![3, 2, 1]);
+        assert_eq!(log.recent(Some("wallet:aa"), usize::MAX), vec![entry("wallet:aa", 2)]);
+        assert_eq!(log.recent(Some("auth_token"), 1), vec![entry("auth_token", 3)]);
+    }
+}
@@ -0,0 +1,401 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use ed25519_dalek::Signature;
+use ed25519_dalek::Signer;
+use ed25519_dalek::SigningKey;
+use ed25519_dalek::Verifier;
+use ed25519_dalek::VerifyingKey;
+use ext_messages_auth::auth::get_bk_wallet_signing_pubkey;
+use ext_messages_auth::auth::AccountRequest;
+use hex::FromHex;
+use hex::ToHex;
+use salvo::async_trait;
+use salvo::http::StatusCode;
+use salvo::prelude::Json;
+use salvo::Depot;
+use salvo::FlowCtrl;
+use salvo::Handler;
+use salvo::Request;
+use salvo::Response;
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
+use tokio::sync::mpsc;
+
+use crate::api::AdminAuditEntry;
+use crate::api::AdminAuditLog;
+use crate::ApiError;
+use crate::AUTH_HEADER;
+
+const SIGNATURE_AUTH_SCHEME: &str = "Signature ";
+const CHALLENGE_TTL: Duration = Duration::from_secs(300);
+const MAX_CHALLENGES: usize = 1024;
+const MAX_CHALLENGES_PER_CLIENT: usize = 8;
+const SIGNING_PUBKEY_CACHE_TTL: Duration = Duration::from_secs(60);
+const TOKEN_PRINCIPAL: &str = "auth_token";
+
+/// How the node admin endpoints authenticate requests.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AdminAuthMode {
+    /// `Authorization: Bearer <AUTH_TOKEN>`
+    #[default]
+    Token,
+    /// `Authorization: Signature <challenge>:<signature>`, where the challenge
+    /// is issued by `v2/auth/challenge` and signed together with the request
+    /// method, path, query and body by the node owner wallet signing key
+    /// (`node-helper gen-keys`). The signature is verified against the signing
+    /// pubkey stored in the wallet at the node id address. A challenge
+    /// authorizes a single request.
+    WalletSignature,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AdminChallenge {
+    /// Hex string to be signed as
+    /// `<challenge>:<METHOD>:<path>:<sha256(query)>:<sha256(body)>`, the
+    /// hashes are hex encoded.
+    pub challenge: String,
+    /// Unix time (ms) after which the challenge is not accepted.
+    pub expires_at: u64,
+}
+
+/// Signs the challenge for the request with the hex encoded ed25519 secret
+/// key, returns the `Authorization` header value.
+pub fn sign_admin_challenge(
+    secret: &str,
+    challenge: &str,
+    method: &str,
+    path: &str,
+    query: &str,
+    body: &[u8],
+) -> anyhow::Result<String> {
+    let secret_bytes = <[u8; 32]>::from_hex(secret)?;
+    let signature = SigningKey::from_bytes(&secret_bytes)
+        .sign(signed_payload(challenge, method, path, query, body).as_bytes());
+    Ok(format!(
+        "{SIGNATURE_AUTH_SCHEME}{challenge}:{}",
+        signature.to_bytes().encode_hex::<String>()
+    ))
+}
+
+/// Authentication middleware of the node admin endpoints. Authorized
+/// invocations other than GET are recorded to the audit log.
+#[derive(Clone)]
+pub struct AdminAuth {
+    mode: AdminAuthMode,
+    node_id: String,
+    audit_log: AdminAuditLog,
+    account_request_tx: mpsc::Sender<AccountRequest>,
+    // challenge -> (expires at, client it was issued to)
+    challenges: Arc<parking_lot::Mutex<HashMap<String, (Instant, Option<IpAddr>)>>>,
+    signing_pubkey: Arc<parking_lot::RwLock<Option<(String, Instant)>>>,
+}
+
+impl AdminAuth {
+    pub fn new(
+        mode: AdminAuthMode,
+        node_id: String,
+        account_request_tx: mpsc::Sender<AccountRequest>,
+        audit_log: AdminAuditLog,
+    ) -> Self {
+        Self {
+            mode,
+            node_id,
+            audit_log,
+            account_request_tx,
+            challenges: Arc::default(),
+            signing_pubkey: Arc::default(),
+        }
+    }
+
+    // Returns None if the client or all clients hold too many live
+    // challenges. The endpoint is not authenticated, but live challenges are
+    // never evicted, so other clients can't invalidate the challenge of the
+    // operator.
+    fn issue_challenge(&self, client: Option<IpAddr>) -> Option<AdminChallenge> {
+        let now = Instant::now();
+        let mut challenges = self.challenges.lock();
+        challenges.retain(|_, (expires_at, _)| *expires_at > now);
+        if challenges.len() >= MAX_CHALLENGES {
+            return None;
+        }
+        let client_challenges =
+            challenges.values().filter(|(_, issued_to)| *issued_to == client).count();
+        if client_challenges >= MAX_CHALLENGES_PER_CLIENT {
+            return None;
+        }
+        let challenge = rand::random::<[u8; 32]>().encode_hex::<String>();
+        challenges.insert(challenge.clone(), (now + CHALLENGE_TTL, client));
+        let expires_at = telemetry_utils::now_ms() + CHALLENGE_TTL.as_millis() as u64;
+        Some(AdminChallenge { challenge, expires_at })
+    }
+
+    fn is_challenge_valid(&self, challenge: &str) -> bool {
+        self.challenges
+            .lock()
+            .get(challenge)
+            .is_some_and(|(expires_at, _)| *expires_at > Instant::now())
+    }
+
+    // Removes the challenge, returns false if it was already used or expired
+    fn take_challenge(&self, challenge: &str) -> bool {
+        self.challenges
+            .lock()
+            .remove(challenge)
+            .is_some_and(|(expires_at, _)| expires_at > Instant::now())
+    }
+
+    async fn wallet_signing_pubkey(&self) -> anyhow::Result<Option<String>> {
+        if let Some((pubkey, expires_at)) = self.signing_pubkey.read().as_ref() {
+            if Instant::now() < *expires_at {
+                return Ok(Some(pubkey.clone()));
+            }
+        }
+        let pubkey =
+            get_bk_wallet_signing_pubkey(&self.node_id, self.account_request_tx.clone()).await?;
+        if let Some(pubkey) = &pubkey {
+            *self.signing_pubkey.write() =
+                Some((pubkey.clone(), Instant::now() + SIGNING_PUBKEY_CACHE_TTL));
+        }
+        Ok(pubkey)
+    }
+
+    /// Returns the principal of the request if the signature is valid.
+    async fn signature_principal(&self, req: &mut Request) -> Option<String> {
+        let Some((challenge, signature)) = req
+            .headers()
+            .get(AUTH_HEADER)
+            .and_then(|auth_header| auth_header.to_str().ok())
+            .and_then(|auth_str| auth_str.strip_prefix(SIGNATURE_AUTH_SCHEME))
+            .and_then(|credentials| credentials.split_once(':'))
+            .map(|(challenge, signature)| (challenge.to_string(), signature.to_string()))
+        else {
+            return None;
+        };
+        let (challenge, signature) = (challenge.as_str(), signature.as_str());
+        if !self.is_challenge_valid(challenge) {
+            tracing::trace!("Admin auth: unknown or expired challenge");
+            return None;
+        }
+        let pubkey = match self.wallet_signing_pubkey().await {
+            Ok(Some(pubkey)) => pubkey,
+            Ok(None) => {
+                tracing::warn!("Admin auth: signing pubkey not found in wallet {}", self.node_id);
+                return None;
+            }
+            Err(e) => {
+                tracing::warn!("Admin auth: failed to get wallet signing pubkey: {e}");
+                return None;
+            }
+        };
+        let method = req.method().as_str().to_string();
+        let path = req.uri().path().to_string();
+        let query = req.uri().query().unwrap_or_default().to_string();
+        // The body is cached by the request, so the handlers still read it
+        let body = match req.payload().await {
+            Ok(body) => body.clone(),
+            Err(e) => {
+                tracing::trace!("Admin auth: failed to read request body: {e}");
+                return None;
+            }
+        };
+        let payload = signed_payload(challenge, &method, &path, &query, &body);
+        if !verify_signature(&pubkey, &payload, signature) {
+            return None;
+        }
+        // The challenge is removed only after the signature is verified, so
+        // a forged header doesn't burn the challenge of the operator
+        if !self.take_challenge(challenge) {
+            tracing::trace!("Admin auth: challenge is already used");
+            return None;
+        }
+        Some(format!("wallet:{pubkey}"))
+    }
+}
+
+fn signed_payload(challenge: &str, method: &str, path: &str, query: &str, body: &[u8]) -> String {
+    format!(
+        "{challenge}:{}:{path}:{}:{}",
+        method.to_uppercase(),
+        content_hash(query.as_bytes()),
+        content_hash(body)
+    )
+}
+
+fn content_hash(data: &[u8]) -> String {
+    Sha256::digest(data).encode_hex::<String>()
+}
+
+fn client_ip(req: &Request) -> Option<IpAddr> {
+    let addr = req.remote_addr();
+    addr.as_ipv4()
+        .map(|addr| IpAddr::V4(*addr.ip()))
+        .or_else(|| addr.as_ipv6().map(|addr| IpAddr::V6(*addr.ip())))
+}
+
+fn verify_signature(pubkey: &str, payload: &str, signature: &str) -> bool {
+    let Ok(public_bytes) = <[u8; 32]>::from_hex(pubkey) else {
+        return false;
+    };
+    let Ok(signature_bytes) = <[u8; 64]>::from_hex(signature) else {
+        return false;
+    };
+    let Ok(verifying_key) = VerifyingKey::from_bytes(&public_bytes) else {
+        return false;
+    };
+    verifying_key.verify(payload.as_bytes(), &Signature::from_bytes(&signature_bytes)).is_ok()
+}
+
+#[async_trait]
+impl Handler for AdminAuth {
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        ctrl: &mut FlowCtrl,
+    ) {
+        let principal = match self.mode {
+            AdminAuthMode::Token => {
+                crate::is_token_authorized(req).then(|| TOKEN_PRINCIPAL.to_string())
+            }
+            AdminAuthMode::WalletSignature => self.signature_principal(req).await,
+        };
+        let Some(principal) = principal else {
+            res.status_code(StatusCode::UNAUTHORIZED);
+            res.render("Unauthorized");
+            ctrl.skip_rest();
+            return;
+        };
+        ctrl.call_next(req, depot, res).await;
+        if req.method() != salvo::http::Method::GET {
+            self.audit_log.record(AdminAuditEntry {
+                timestamp: telemetry_utils::now_ms(),
+                principal,
+                method: req.method().to_string(),
+                path: req.uri().path().to_string(),
+                params: req
+                    .queries()
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.clone()))
+                    .collect(),
+                status: res.status_code.unwrap_or(StatusCode::OK).as_u16(),
+            });
+        }
+    }
+}
+
+/// Issues a challenge for the wallet signature authentication.
+pub struct AdminChallengeHandler(pub AdminAuth);
+
+#[async_trait]
+impl Handler for AdminChallengeHandler {
+    async fn handle(
+        &self,
+        req: &mut Request,
+        _depot: &mut Depot,
+        res: &mut Response,
+        _ctrl: &mut FlowCtrl,
+    ) {
+        if self.0.mode != AdminAuthMode::WalletSignature {
+            ApiError::new("AUTH_MODE_DISABLED", "Wallet signature auth is disabled", false)
+                .render(res, StatusCode::NOT_FOUND);
+            return;
+        }
+        match self.0.issue_challenge(client_ip(req)) {
+            Some(challenge) => res.render(Json(challenge)),
+            None => ApiError::new("TOO_MANY_CHALLENGES", "Too many issued challenges", true)
+                .render(res, StatusCode::TOO_MANY_REQUESTS),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_challenge_verification() {
+        let signing_key = SigningKey::generate(&mut rand::rngs::OsRng);
+        let secret = signing_key.to_bytes().encode_hex::<String>();
+        let public = signing_key.verifying_key().to_bytes().encode_hex::<String>();
+        let (tx, _rx) = mpsc::channel(1);
+        let auth = AdminAuth::new(
+            AdminAuthMode::WalletSignature,
+            "00".repeat(32),
+            tx,
+            AdminAuditLog::default(),
+        );
+
+        let challenge = auth.issue_challenge(None).unwrap().challenge;
+        assert!(auth.is_challenge_valid(&challenge));
+        assert!(!auth.is_challenge_valid("unknown"));
+
+        let header =
+            sign_admin_challenge(&secret, &challenge, "post", "/v2/admin/x", "a=1", b"{}").unwrap();
+        let (signed_challenge, signature) =
+            header.strip_prefix(SIGNATURE_AUTH_SCHEME).unwrap().split_once(':').unwrap();
+        assert_eq!(signed_challenge, challenge);
+        let payload = signed_payload(&challenge, "POST", "/v2/admin/x", "a=1", b"{}");
+        assert!(verify_signature(&public, &payload, signature));
+        // The signature is bound to the method, path, query and body
+        for other_payload in [
+            signed_payload(&challenge, "GET", "/v2/admin/x", "a=1", b"{}"),
+            signed_payload(&challenge, "POST", "/v2/admin/y", "a=1", b"{}"),
+            signed_payload(&challenge, "POST", "/v2/admin/x", "a=2", b"{}"),
+            signed_payload(&challenge, "POST", "/v2/admin/x", "a=1", b"{\"a\":1}"),
+        ] {
+            assert!(!verify_signature(&public, &other_payload, signature));
+        }
+
+        let other_public = SigningKey::generate(&mut rand::rngs::OsRng)
+            .verifying_key()
+            .to_bytes()
+            .encode_hex::<String>();
+        assert!(!verify_signature(&other_public, &payload, signature));
+
+        // A challenge is accepted once
+        assert!(auth.take_challenge(&challenge));
+        assert!(!auth.take_challenge(&challenge));
+    }
+
+    #[test]
+    fn test_challenge_issuance_is_limited() {
+        let (tx, _rx) = mpsc::channel(1);
+        let auth = AdminAuth::new(
+            AdminAuthMode::WalletSignature,
+            "00".repeat(32),
+            tx,
+            AdminAuditLog::default(),
+        );
+        let client = Some(IpAddr::from([10, 0, 0, 1]));
+        let first = auth.issue_challenge(client).unwrap().challenge;
+        for _ in 1..MAX_CHALLENGES_PER_CLIENT {
+            assert!(auth.issue_challenge(client).is_some());
+        }
+        assert!(auth.issue_challenge(client).is_none());
+        // Live challenges are not evicted by other clients
+        let mut clients = (0..=u32::MAX).map(|ip| Some(IpAddr::from(ip.to_be_bytes())));
+        while auth.challenges.lock().len() < MAX_CHALLENGES {
+            let other = clients.next().unwrap();
+            if other != client {
+                assert!(auth.issue_challenge(other).is_some());
+            }
+        }
+        assert!(auth.issue_challenge(clients.next().unwrap()).is_none());
+        assert!(auth.is_challenge_valid(&first));
+
+        // A used challenge frees the slot of the client
+        assert!(auth.take_challenge(&first));
+        assert!(auth.issue_challenge(client).is_some());
+    }
+}
@@ -70,6 +70,10 @@ impl BkSetSnapshot {
         self.future_nodes = bk_update.future;
         self.update_time = SystemTime::now();
     }
+
+    pub fn contains_node(&self, node_id: &str) -> bool {
+        self.nodes.iter().any(|(id, _)| id == node_id)
+    }
 }
 
 #[derive(Serialize, Clone, Debug, Default)]
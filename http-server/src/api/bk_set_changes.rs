@@ -0,0 +1,145 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::marker::PhantomData;
+
+use salvo::async_trait;
+use salvo::http::StatusCode;
+use salvo::Depot;
+use salvo::FlowCtrl;
+use salvo::Handler;
+use salvo::Request;
+use salvo::Response;
+use serde::Serialize;
+
+use crate::api::event_feed::stream_events;
+use crate::api::event_feed::EventFeed;
+use crate::api::event_feed::FeedEvent;
+use crate::BkHistoryInfo;
+use crate::ResolvingResult;
+use crate::WebServer;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BkSetChangeKind {
+    Added,
+    Removed,
+    FutureAdded,
+}
+
+/// Block keeper set change extracted from the common section of a finalized block.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct BkSetChangeEvent {
+    pub thread_id: String,
+    pub block_id: String,
+    pub block_seq_no: u32,
+    /// Changes are applied to the BK set of the block descendants.
+    pub effective_seq_no: u32,
+    pub change: BkSetChangeKind,
+    pub block_keeper: BkHistoryInfo,
+}
+
+impl FeedEvent for BkSetChangeEvent {
+    const NAME: &'static str = "bk_set_change";
+    const RECENT_CAPACITY: usize = 1000;
+
+    fn thread_id(&self) -> &str {
+        &self.thread_id
+    }
+}
+
+/// BK set changes of the finalized blocks published by the node.
+pub type BkSetChangeFeed = EventFeed<BkSetChangeEvent>;
+
+pub struct BkSetChangesHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>(
+    PhantomData<(TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter)>,
+);
+
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+    BkSetChangesHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+{
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[async_trait]
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter> Handler
+    for BkSetChangesHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+where
+    TMessage: Clone + Send + Sync + 'static + std::fmt::Debug,
+    TMsgConverter: Clone
+        + Send
+        + Sync
+        + 'static
+        + Fn(tvm_block::Message, [u8; 34]) -> anyhow::Result<TMessage>,
+    TBPResolver: Clone + Send + Sync + 'static + FnMut([u8; 34]) -> ResolvingResult,
+    TBocByAddrGetter:
+        Clone + Send + Sync + 'static + Fn(String) -> anyhow::Result<(String, Option<String>)>,
+    TSeqnoGetter: Clone + Send + Sync + 'static + Fn() -> anyhow::Result<u32>,
+{
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        _ctrl: &mut FlowCtrl,
+    ) {
+        let Ok(web_server) = depot.obtain::<WebServer<
+            TMessage,
+            TMsgConverter,
+            TBPResolver,
+            TBocByAddrGetter,
+            TSeqnoGetter,
+        >>() else {
+            res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+            res.render("Internal server error: Web Server state not found");
+            return;
+        };
+        let thread_id = req.query::<String>("thread_id").map(|thread_id| thread_id.to_lowercase());
+        let from_seq_no = req.query::<u32>("from_seq_no");
+
+        // Replays the recent changes effective from `from_seq_no`
+        let (snapshot, receiver) = web_server.bk_set_changes.subscribe(|event| {
+            from_seq_no.is_some_and(|from_seq_no| event.effective_seq_no >= from_seq_no)
+        });
+        stream_events(snapshot, receiver, thread_id, res);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(block_seq_no: u32, signer_index: u16) -> BkSetChangeEvent {
+        BkSetChangeEvent {
+            thread_id: "00".to_string(),
+            block_id: String::new(),
+            block_seq_no,
+            effective_seq_no: block_seq_no + 1,
+            change: BkSetChangeKind::Added,
+            block_keeper: BkHistoryInfo {
+                signer_index,
+                node_id: String::new(),
+                pubkey: String::new(),
+                node_owner_pk: String::new(),
+                stake: "0".to_string(),
+                epoch_finish_seq_no: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_bk_set_change_feed() {
+        let feed = BkSetChangeFeed::new();
+        feed.publish(event(10, 1));
+        feed.publish(event(20, 2));
+
+        let (snapshot, mut receiver) = feed.subscribe(|event| event.effective_seq_no >= 15);
+        assert_eq!(snapshot, vec![event(20, 2)]);
+        assert!(feed.subscribe(|_| false).0.is_empty());
+
+        feed.publish(event(30, 3));
+        assert_eq!(receiver.try_recv().unwrap(), event(30, 3));
+    }
+}
@@ -0,0 +1,331 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use ed25519_dalek::Signature;
+use ed25519_dalek::Signer;
+use ed25519_dalek::SigningKey;
+use ed25519_dalek::Verifier;
+use ed25519_dalek::VerifyingKey;
+use hex::FromHex;
+use salvo::async_trait;
+use salvo::http::StatusCode;
+use salvo::prelude::Json;
+use salvo::Depot;
+use salvo::FlowCtrl;
+use salvo::Handler;
+use salvo::Request;
+use salvo::Response;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::ResolvingResult;
+use crate::WebServer;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BkHistoryInfo {
+    pub signer_index: u16,
+    pub node_id: String,
+    /// BLS pubkey used to sign attestations (hex).
+    pub pubkey: String,
+    pub node_owner_pk: String,
+    /// Decimal string, stake doesn't fit into JSON number.
+    pub stake: String,
+    pub epoch_finish_seq_no: Option<u64>,
+}
+
+/// BK set that was in effect for blocks with seq_no in `[from_seq_no, to_seq_no)`.
+/// `to_seq_no` is not set for the latest window of a thread.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BkSetWindow {
+    pub from_seq_no: u32,
+    pub to_seq_no: Option<u32>,
+    pub bk_set: Vec<BkHistoryInfo>,
+    pub future_bk_set: Vec<BkHistoryInfo>,
+}
+
+#[derive(Clone, Debug)]
+pub struct BkSetHistoryUpdate {
+    pub thread_id: String,
+    pub seq_no: u32,
+    pub current: Vec<BkHistoryInfo>,
+    pub future: Vec<BkHistoryInfo>,
+}
+
+/// BK set windows per thread, recorded from finalized blocks.
+pub struct BkSetHistory {
+    update_time: SystemTime,
+    threads: BTreeMap<String, Vec<BkSetWindow>>,
+    /// File the history is saved to on every change, keeps it across restarts
+    path: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PersistedBkSetHistory {
+    update_time_ms: u64,
+    threads: BTreeMap<String, Vec<BkSetWindow>>,
+}
+
+impl Default for BkSetHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BkSetHistory {
+    pub fn new() -> Self {
+        Self { update_time: UNIX_EPOCH, threads: BTreeMap::new(), path: None }
+    }
+
+    /// Loads the history saved to the file, the file is created on the first
+    /// change if it does not exist.
+    pub fn load(path: PathBuf) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self { path: Some(path), ..Self::new() });
+        }
+        let persisted: PersistedBkSetHistory = serde_json::from_slice(&std::fs::read(&path)?)
+            .map_err(|e| anyhow::format_err!("Failed to parse {}: {e}", path.display()))?;
+        Ok(Self {
+            update_time: UNIX_EPOCH + Duration::from_millis(persisted.update_time_ms),
+            threads: persisted.threads,
+            path: Some(path),
+        })
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let persisted = PersistedBkSetHistory {
+            update_time_ms: self.update_time.duration_since(UNIX_EPOCH)?.as_millis() as u64,
+            threads: self.threads.clone(),
+        };
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec(&persisted)?)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    pub fn record(&mut self, update: BkSetHistoryUpdate) {
+        let windows = self.threads.entry(update.thread_id).or_default();
+        if let Some(last) = windows.last_mut() {
+            if update.seq_no < last.from_seq_no {
+                return;
+            }
+            if last.bk_set == update.current && last.future_bk_set == update.future {
+                return;
+            }
+            last.to_seq_no = Some(update.seq_no);
+        }
+        windows.push(BkSetWindow {
+            from_seq_no: update.seq_no,
+            to_seq_no: None,
+            bk_set: update.current,
+            future_bk_set: update.future,
+        });
+        self.update_time = SystemTime::now();
+        if let Err(e) = self.save() {
+            tracing::error!(target: "http_server", "Failed to save BK set history: {e}");
+        }
+    }
+
+    fn result(&self, thread_id: Option<&str>) -> BkSetHistoryResult {
+        let threads = match thread_id {
+            Some(thread_id) => self
+                .threads
+                .get_key_value(thread_id)
+                .map(|(k, v)| BTreeMap::from([(k.clone(), v.clone())]))
+                .unwrap_or_default(),
+            None => self.threads.clone(),
+        };
+        BkSetHistoryResult { threads }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct BkSetHistoryResult {
+    pub threads: BTreeMap<String, Vec<BkSetWindow>>,
+}
+
+/// History export signed with the node signing key (ed25519 over the JSON of `history`).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SignedBkSetHistory {
+    pub history: BkSetHistoryResult,
+    pub signer_pubkey: Option<String>,
+    pub signature: Option<String>,
+}
+
+impl SignedBkSetHistory {
+    pub fn sign(history: BkSetHistoryResult, secret: Option<&str>) -> anyhow::Result<Self> {
+        let Some(secret) = secret else {
+            return Ok(Self { history, signer_pubkey: None, signature: None });
+        };
+        let signing_key = SigningKey::from_bytes(&<[u8; 32]>::from_hex(secret)?);
+        let signature = signing_key.sign(&serde_json::to_vec(&history)?);
+        Ok(Self {
+            history,
+            signer_pubkey: Some(hex::encode(signing_key.verifying_key().to_bytes())),
+            signature: Some(hex::encode(signature.to_bytes())),
+        })
+    }
+
+    pub fn verify(&self) -> anyhow::Result<()> {
+        let (Some(signer_pubkey), Some(signature)) = (&self.signer_pubkey, &self.signature) else {
+            anyhow::bail!("BK set history is not signed");
+        };
+        let verifying_key = VerifyingKey::from_bytes(&<[u8; 32]>::from_hex(signer_pubkey)?)?;
+        let signature = Signature::from_bytes(&<[u8; 64]>::from_hex(signature)?);
+        verifying_key
+            .verify(&serde_json::to_vec(&self.history)?, &signature)
+            .map_err(|e| anyhow::format_err!("Invalid BK set history signature: {e}"))
+    }
+}
+
+pub struct BkSetHistoryHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>(
+    PhantomData<TMessage>,
+    PhantomData<TMsgConverter>,
+    PhantomData<TBPResolver>,
+    PhantomData<TBocByAddrGetter>,
+    PhantomData<TSeqnoGetter>,
+);
+
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+    BkSetHistoryHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+{
+    pub fn new() -> Self {
+        Self(PhantomData, PhantomData, PhantomData, PhantomData, PhantomData)
+    }
+}
+
+#[async_trait]
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter> Handler
+    for BkSetHistoryHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+where
+    TMessage: Clone + Send + Sync + 'static + std::fmt::Debug,
+    TMsgConverter: Clone
+        + Send
+        + Sync
+        + 'static
+        + Fn(tvm_block::Message, [u8; 34]) -> anyhow::Result<TMessage>,
+    TBPResolver: Clone + Send + Sync + 'static + FnMut([u8; 34]) -> ResolvingResult,
+    TBocByAddrGetter:
+        Clone + Send + Sync + 'static + Fn(String) -> anyhow::Result<(String, Option<String>)>,
+    TSeqnoGetter: Clone + Send + Sync + 'static + Fn() -> anyhow::Result<u32>,
+{
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        _ctrl: &mut FlowCtrl,
+    ) {
+        let Ok(web_server_state) = depot.obtain::<WebServer<
+            TMessage,
+            TMsgConverter,
+            TBPResolver,
+            TBocByAddrGetter,
+            TSeqnoGetter,
+        >>() else {
+            res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+            return;
+        };
+        let thread_id = req.query::<String>("thread_id");
+        let (result, update_time) = {
+            let history = web_server_state.bk_set_history.read();
+            (history.result(thread_id.as_deref()), history.update_time)
+        };
+        let secret = web_server_state.signing_keys.as_ref().map(|keys| keys.secret.as_str());
+        match SignedBkSetHistory::sign(result, secret) {
+            Ok(signed) => {
+                res.status_code(StatusCode::OK);
+                let _ = res.add_header("Last-Modified", httpdate::fmt_http_date(update_time), true);
+                res.render(Json(signed));
+            }
+            Err(e) => {
+                tracing::error!(target: "http_server", "Failed to sign BK set history: {e}");
+                res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(thread_id: &str, seq_no: u32, signers: &[u16]) -> BkSetHistoryUpdate {
+        BkSetHistoryUpdate {
+            thread_id: thread_id.to_string(),
+            seq_no,
+            current: signers
+                .iter()
+                .map(|signer_index| BkHistoryInfo {
+                    signer_index: *signer_index,
+                    node_id: format!("{signer_index}"),
+                    pubkey: String::new(),
+                    node_owner_pk: String::new(),
+                    stake: "1".to_string(),
+                    epoch_finish_seq_no: None,
+                })
+                .collect(),
+            future: vec![],
+        }
+    }
+
+    #[test]
+    fn test_history_windows() {
+        let mut history = BkSetHistory::new();
+        history.record(update("00", 0, &[1]));
+        history.record(update("00", 5, &[1]));
+        history.record(update("00", 10, &[1, 2]));
+        history.record(update("00", 3, &[3]));
+        history.record(update("01", 7, &[2]));
+
+        let result = history.result(Some("00"));
+        let windows = &result.threads["00"];
+        assert_eq!(result.threads.len(), 1);
+        assert_eq!(windows.len(), 2);
+        assert_eq!((windows[0].from_seq_no, windows[0].to_seq_no), (0, Some(10)));
+        assert_eq!((windows[1].from_seq_no, windows[1].to_seq_no), (10, None));
+        assert_eq!(history.result(None).threads.len(), 2);
+    }
+
+    #[test]
+    fn test_history_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bk-set-history.json");
+        let mut history = BkSetHistory::load(path.clone()).unwrap();
+        history.record(update("00", 0, &[1]));
+        history.record(update("00", 10, &[1, 2]));
+
+        let restored = BkSetHistory::load(path).unwrap();
+        assert_eq!(restored.result(None), history.result(None));
+        assert_eq!(
+            restored.update_time.duration_since(UNIX_EPOCH).unwrap().as_millis(),
+            history.update_time.duration_since(UNIX_EPOCH).unwrap().as_millis()
+        );
+    }
+
+    #[test]
+    fn test_signed_history() {
+        let secret = hex::encode([7_u8; 32]);
+        let mut history = BkSetHistory::new();
+        history.record(update("00", 0, &[1]));
+
+        let signed = SignedBkSetHistory::sign(history.result(None), Some(&secret)).unwrap();
+        assert!(signed.verify().is_ok());
+
+        let mut tampered = signed.clone();
+        tampered.history.threads.get_mut("00").unwrap()[0].from_seq_no = 1;
+        assert!(tampered.verify().is_err());
+
+        let unsigned = SignedBkSetHistory::sign(history.result(None), None).unwrap();
+        assert!(unsigned.verify().is_err());
+    }
+}
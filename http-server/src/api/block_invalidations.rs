@@ -0,0 +1,112 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::marker::PhantomData;
+
+use salvo::async_trait;
+use salvo::http::StatusCode;
+use salvo::Depot;
+use salvo::FlowCtrl;
+use salvo::Handler;
+use salvo::Request;
+use salvo::Response;
+use serde::Serialize;
+
+use crate::api::event_feed::stream_events;
+use crate::api::event_feed::EventFeed;
+use crate::api::event_feed::FeedEvent;
+use crate::ResolvingResult;
+use crate::WebServer;
+
+/// Block that was applied optimistically and then invalidated by the fork
+/// resolution. Consumers of the optimistic data roll back its effects.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct BlockInvalidationEvent {
+    pub thread_id: String,
+    pub block_id: String,
+    pub block_seq_no: u32,
+    pub parent_block_id: Option<String>,
+    /// Sibling block that won the fork: the finalized or the applied valid
+    /// one. None for the descendants of the invalidated branch root and if
+    /// the winner is not known yet.
+    pub replaced_by: Option<String>,
+    pub timestamp: u64,
+}
+
+impl FeedEvent for BlockInvalidationEvent {
+    const NAME: &'static str = "block_invalidated";
+    const RECENT_CAPACITY: usize = 1000;
+
+    fn thread_id(&self) -> &str {
+        &self.thread_id
+    }
+}
+
+/// Invalidated blocks published by the node.
+pub type BlockInvalidationFeed = EventFeed<BlockInvalidationEvent>;
+
+pub struct BlockInvalidationsHandler<
+    TMessage,
+    TMsgConverter,
+    TBPResolver,
+    TBocByAddrGetter,
+    TSeqnoGetter,
+>(PhantomData<(TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter)>);
+
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+    BlockInvalidationsHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+{
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[async_trait]
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter> Handler
+    for BlockInvalidationsHandler<
+        TMessage,
+        TMsgConverter,
+        TBPResolver,
+        TBocByAddrGetter,
+        TSeqnoGetter,
+    >
+where
+    TMessage: Clone + Send + Sync + 'static + std::fmt::Debug,
+    TMsgConverter: Clone
+        + Send
+        + Sync
+        + 'static
+        + Fn(tvm_block::Message, [u8; 34]) -> anyhow::Result<TMessage>,
+    TBPResolver: Clone + Send + Sync + 'static + FnMut([u8; 34]) -> ResolvingResult,
+    TBocByAddrGetter:
+        Clone + Send + Sync + 'static + Fn(String) -> anyhow::Result<(String, Option<String>)>,
+    TSeqnoGetter: Clone + Send + Sync + 'static + Fn() -> anyhow::Result<u32>,
+{
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        _ctrl: &mut FlowCtrl,
+    ) {
+        let Ok(web_server) = depot.obtain::<WebServer<
+            TMessage,
+            TMsgConverter,
+            TBPResolver,
+            TBocByAddrGetter,
+            TSeqnoGetter,
+        >>() else {
+            res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+            res.render("Internal server error: Web Server state not found");
+            return;
+        };
+        let thread_id = req.query::<String>("thread_id").map(|thread_id| thread_id.to_lowercase());
+        let from_seq_no = req.query::<u32>("from_seq_no");
+
+        // Replays the recent invalidations of blocks from `from_seq_no`
+        let (snapshot, receiver) = web_server.block_invalidations.subscribe(|event| {
+            from_seq_no.is_some_and(|from_seq_no| event.block_seq_no >= from_seq_no)
+        });
+        stream_events(snapshot, receiver, thread_id, res);
+    }
+}
@@ -6,6 +6,7 @@ use std::time::Instant;
 
 use salvo::prelude::*;
 
+use crate::ApiError;
 use crate::ResolvingResult;
 use crate::WebServer;
 pub struct BocByAddressHandler<
@@ -90,9 +91,7 @@ where
                 StatusCode::OK
             }
             Err(e) => {
-                res.status_code(StatusCode::NOT_FOUND);
-                res.render(format!("Original error: {e}"));
-                StatusCode::NOT_FOUND
+                ApiError::from_anyhow(&e, "ACCOUNT_NOT_FOUND").render(res, StatusCode::NOT_FOUND)
             }
         };
 
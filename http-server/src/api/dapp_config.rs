@@ -0,0 +1,113 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::marker::PhantomData;
+
+use salvo::prelude::*;
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+
+use crate::ApiError;
+use crate::ResolvingResult;
+use crate::WebServer;
+
+/// Request to the node for the credit config of the dapp.
+pub struct DappConfigRequest {
+    pub dapp_id: String,
+    pub response: oneshot::Sender<anyhow::Result<DappConfigInfo>>,
+}
+
+pub type DappConfigRequestSender = mpsc::Sender<DappConfigRequest>;
+
+/// Credit config of the dapp read from the last finalized state of the
+/// DappConfig contract. Amounts are decimal strings.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct DappConfigInfo {
+    pub dapp_id: String,
+    /// DappConfig contract address
+    pub address: String,
+    pub is_unlimit: bool,
+    /// Credit left to the dapp, reduced by the shell minted for its
+    /// transactions. Can be negative.
+    pub available_balance: String,
+    /// Credit the producer grants to a transaction of the dapp, None if the
+    /// dapp is unlimited.
+    pub available_credit: Option<String>,
+    /// Seq no of the block of the state the config is read from
+    pub block_seq_no: u32,
+}
+
+pub struct DappConfigHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>(
+    PhantomData<(TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter)>,
+);
+
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+    DappConfigHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+{
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[async_trait]
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter> Handler
+    for DappConfigHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+where
+    TMessage: Clone + Send + Sync + 'static + std::fmt::Debug,
+    TMsgConverter: Clone
+        + Send
+        + Sync
+        + 'static
+        + Fn(tvm_block::Message, [u8; 34]) -> anyhow::Result<TMessage>,
+    TBPResolver: Clone + Send + Sync + 'static + FnMut([u8; 34]) -> ResolvingResult,
+    TBocByAddrGetter:
+        Clone + Send + Sync + 'static + Fn(String) -> anyhow::Result<(String, Option<String>)>,
+    TSeqnoGetter: Clone + Send + Sync + 'static + Fn() -> anyhow::Result<u32>,
+{
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        _ctrl: &mut FlowCtrl,
+    ) {
+        let dapp_id: String = req.query("dapp_id").unwrap_or_default();
+        if dapp_id.is_empty() {
+            res.status_code(StatusCode::BAD_REQUEST);
+            res.render("dapp_id parameter required");
+            return;
+        }
+
+        let Ok(web_server) = depot.obtain::<WebServer<
+            TMessage,
+            TMsgConverter,
+            TBPResolver,
+            TBocByAddrGetter,
+            TSeqnoGetter,
+        >>() else {
+            res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+            res.render("Internal server error: Web Server state not found");
+            return;
+        };
+
+        let (response, response_rx) = oneshot::channel();
+        let result = match web_server
+            .dapp_config_request_sender
+            .send(DappConfigRequest { dapp_id, response })
+            .await
+        {
+            Ok(()) => response_rx
+                .await
+                .unwrap_or_else(|_| Err(anyhow::format_err!("Dapp config request dropped"))),
+            Err(_) => Err(anyhow::format_err!("Dapp config service is not running")),
+        };
+        match result {
+            Ok(config) => res.render(Json(config)),
+            Err(e) => {
+                ApiError::from_anyhow(&e, "DAPP_CONFIG_NOT_FOUND")
+                    .render(res, StatusCode::NOT_FOUND);
+            }
+        }
+    }
+}
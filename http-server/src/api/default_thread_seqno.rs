@@ -5,6 +5,7 @@ use std::marker::PhantomData;
 
 use salvo::prelude::*;
 
+use crate::ApiError;
 use crate::ResolvingResult;
 use crate::WebServer;
 pub struct LastSeqnoHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter> {
@@ -60,8 +61,8 @@ where
                 res.render(Json(response));
             }
             Err(e) => {
-                res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
-                res.render(format!("Original error: {e}"));
+                ApiError::from_anyhow(&e, "INTERNAL_ERROR")
+                    .render(res, StatusCode::INTERNAL_SERVER_ERROR);
             }
         };
     }
@@ -0,0 +1,178 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::marker::PhantomData;
+
+use salvo::prelude::*;
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+
+use crate::ApiError;
+use crate::ResolvingResult;
+use crate::WebServer;
+
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1000;
+
+/// Lookup in the durable storage of the internal messages.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DurableMessagesQuery {
+    /// Hex message hash
+    ByHash(String),
+    /// Messages to the destination stored after `after_seq`, optionally
+    /// narrowed to the messages created within the lt range (inclusive).
+    /// Up to `limit` matching messages are returned.
+    ByDestination {
+        /// Hex account id, the workchain prefix is ignored
+        dst: String,
+        after_seq: i64,
+        lt_from: Option<u64>,
+        lt_to: Option<u64>,
+        limit: usize,
+    },
+}
+
+/// Request to the node to read the message durable storage.
+pub struct DurableMessagesRequest {
+    pub query: DurableMessagesQuery,
+    pub response: oneshot::Sender<anyhow::Result<DurableMessagesPage>>,
+}
+
+pub type DurableMessagesRequestSender = mpsc::Sender<DurableMessagesRequest>;
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct DurableMessage {
+    pub hash: String,
+    /// Position of the message in the destination index
+    pub seq: i64,
+    pub src: Option<String>,
+    pub dst: Option<String>,
+    pub created_lt: Option<u64>,
+    /// Base64 message BOC
+    pub boc: String,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Default)]
+pub struct DurableMessagesPage {
+    pub messages: Vec<DurableMessage>,
+    /// Seq of the last scanned message of the destination, the next page
+    /// starts after it. None if nothing is stored after `after_seq`.
+    pub last_seq: Option<i64>,
+}
+
+pub struct DurableMessagesHandler<
+    TMessage,
+    TMsgConverter,
+    TBPResolver,
+    TBocByAddrGetter,
+    TSeqnoGetter,
+>(PhantomData<(TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter)>);
+
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+    DurableMessagesHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+{
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+fn parse_query(req: &mut Request) -> Result<DurableMessagesQuery, String> {
+    if let Some(hash) = req.param::<String>("message_hash") {
+        return Ok(DurableMessagesQuery::ByHash(hash.to_lowercase()));
+    }
+    let dst: String = req.query("dst").unwrap_or_default();
+    if dst.is_empty() {
+        return Err("dst parameter required".to_string());
+    }
+    let dst = match dst.split_once(':') {
+        Some((_workchain, account_id)) => account_id.to_lowercase(),
+        None => dst.to_lowercase(),
+    };
+    let Some(after_seq) = req.query::<i64>("after_seq") else {
+        return Err("after_seq parameter required".to_string());
+    };
+    let limit = req.query::<usize>("limit").unwrap_or(DEFAULT_LIMIT);
+    if limit == 0 || limit > MAX_LIMIT {
+        return Err(format!("limit must be within 1..={MAX_LIMIT}"));
+    }
+    Ok(DurableMessagesQuery::ByDestination {
+        dst,
+        after_seq,
+        lt_from: req.query("lt_from"),
+        lt_to: req.query("lt_to"),
+        limit,
+    })
+}
+
+#[async_trait]
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter> Handler
+    for DurableMessagesHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+where
+    TMessage: Clone + Send + Sync + 'static + std::fmt::Debug,
+    TMsgConverter: Clone
+        + Send
+        + Sync
+        + 'static
+        + Fn(tvm_block::Message, [u8; 34]) -> anyhow::Result<TMessage>,
+    TBPResolver: Clone + Send + Sync + 'static + FnMut([u8; 34]) -> ResolvingResult,
+    TBocByAddrGetter:
+        Clone + Send + Sync + 'static + Fn(String) -> anyhow::Result<(String, Option<String>)>,
+    TSeqnoGetter: Clone + Send + Sync + 'static + Fn() -> anyhow::Result<u32>,
+{
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        _ctrl: &mut FlowCtrl,
+    ) {
+        let query = match parse_query(req) {
+            Ok(query) => query,
+            Err(e) => {
+                res.status_code(StatusCode::BAD_REQUEST);
+                res.render(e);
+                return;
+            }
+        };
+
+        let Ok(web_server) = depot.obtain::<WebServer<
+            TMessage,
+            TMsgConverter,
+            TBPResolver,
+            TBocByAddrGetter,
+            TSeqnoGetter,
+        >>() else {
+            res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+            res.render("Internal server error: Web Server state not found");
+            return;
+        };
+
+        let by_hash = matches!(query, DurableMessagesQuery::ByHash(_));
+        let (response, response_rx) = oneshot::channel();
+        let result = match web_server
+            .durable_messages_request_sender
+            .send(DurableMessagesRequest { query, response })
+            .await
+        {
+            Ok(()) => response_rx
+                .await
+                .unwrap_or_else(|_| Err(anyhow::format_err!("Durable messages request dropped"))),
+            Err(_) => Err(anyhow::format_err!("Durable messages service is not running")),
+        };
+        match result {
+            Ok(mut page) if by_hash => match page.messages.pop() {
+                Some(message) => res.render(Json(message)),
+                None => {
+                    ApiError::new("MESSAGE_NOT_FOUND", "Message is not stored", false)
+                        .render(res, StatusCode::NOT_FOUND);
+                }
+            },
+            Ok(page) => res.render(Json(page)),
+            Err(e) => {
+                ApiError::from_anyhow(&e, "DURABLE_MESSAGES_ERROR")
+                    .render(res, StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        }
+    }
+}
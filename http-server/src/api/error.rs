@@ -0,0 +1,69 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use salvo::http::StatusCode;
+use salvo::prelude::Json;
+use salvo::Response;
+use serde::Serialize;
+
+/// Error returned by the node getters passed to the web server. Getters
+/// return `anyhow::Result`, an `ApiError` wrapped into it is rendered as is,
+/// any other error is rendered with the handler default code.
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct ApiError {
+    /// Stable machine readable code, e.g. `REPOSITORY_BLOCK_NOT_FOUND`
+    pub code: String,
+    pub message: String,
+    /// The same request may succeed later, e.g. when the node is synced
+    pub retryable: bool,
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl ApiError {
+    pub fn new(code: impl Into<String>, message: impl Into<String>, retryable: bool) -> Self {
+        Self { code: code.into(), message: message.into(), retryable }
+    }
+
+    pub(crate) fn from_anyhow(err: &anyhow::Error, default_code: &str) -> Self {
+        match err.downcast_ref::<ApiError>() {
+            Some(api_error) => api_error.clone(),
+            None => Self::new(default_code, err.to_string(), false),
+        }
+    }
+
+    /// Renders `{"error": {"code": ..., "message": ..., "retryable": ...}}`.
+    /// Retryable errors are reported with `503 Service Unavailable`.
+    pub(crate) fn render(&self, res: &mut Response, default_status: StatusCode) -> StatusCode {
+        let status = if self.retryable { StatusCode::SERVICE_UNAVAILABLE } else { default_status };
+        res.status_code(status);
+        res.render(Json(serde_json::json!({ "error": self })));
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_anyhow() {
+        let err = anyhow::Error::new(ApiError::new("STATE_NOT_READY", "not synced", true));
+        assert_eq!(
+            ApiError::from_anyhow(&err, "INTERNAL_ERROR"),
+            ApiError::new("STATE_NOT_READY", "not synced", true)
+        );
+
+        let err = anyhow::format_err!("Account not found");
+        assert_eq!(
+            ApiError::from_anyhow(&err, "ACCOUNT_NOT_FOUND"),
+            ApiError::new("ACCOUNT_NOT_FOUND", "Account not found", false)
+        );
+    }
+}
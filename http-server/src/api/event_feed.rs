@@ -0,0 +1,106 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use salvo::sse::SseEvent;
+use salvo::sse::SseKeepAlive;
+use salvo::Response;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+// Slow subscribers skip events that didn't fit into the channel
+const EVENT_FEED_CHANNEL_CAPACITY: usize = 1024;
+
+/// Event published by the node and streamed by the web server as SSE.
+pub trait FeedEvent: Serialize + Clone + Send + Sync + 'static {
+    /// Name of the SSE event.
+    const NAME: &'static str;
+    /// Number of the latest events replayed to a new subscriber.
+    const RECENT_CAPACITY: usize;
+
+    /// Thread the event belongs to, subscribers can filter by it.
+    fn thread_id(&self) -> &str;
+}
+
+/// Shared between the node and the web server: the node publishes the
+/// events, the web server streams them to the subscribers.
+#[derive(Clone)]
+pub struct EventFeed<T: FeedEvent> {
+    sender: broadcast::Sender<T>,
+    recent: Arc<parking_lot::RwLock<VecDeque<T>>>,
+}
+
+impl<T: FeedEvent> Default for EventFeed<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: FeedEvent> EventFeed<T> {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_FEED_CHANNEL_CAPACITY);
+        Self {
+            sender,
+            recent: Arc::new(parking_lot::RwLock::new(VecDeque::with_capacity(T::RECENT_CAPACITY))),
+        }
+    }
+
+    pub fn publish(&self, event: T) {
+        if T::RECENT_CAPACITY > 0 {
+            let mut recent = self.recent.write();
+            if recent.len() == T::RECENT_CAPACITY {
+                recent.pop_front();
+            }
+            recent.push_back(event.clone());
+        }
+        // No receivers is not an error: nobody watches the feed
+        let _ = self.sender.send(event);
+    }
+
+    /// Returns the recent events accepted by `replay` and a receiver of the
+    /// following events.
+    pub fn subscribe(&self, replay: impl Fn(&T) -> bool) -> (Vec<T>, broadcast::Receiver<T>) {
+        let recent = self.recent.read();
+        let receiver = self.sender.subscribe();
+        let snapshot = recent.iter().filter(|event| replay(event)).cloned().collect();
+        (snapshot, receiver)
+    }
+}
+
+/// Streams the snapshot and then the received events of the thread (or of
+/// all threads) to the response.
+pub(crate) fn stream_events<T: FeedEvent>(
+    snapshot: Vec<T>,
+    receiver: broadcast::Receiver<T>,
+    thread_id: Option<String>,
+    res: &mut Response,
+) {
+    let snapshot = futures::stream::iter(snapshot);
+    let received = futures::stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => return Some((event, receiver)),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::debug!(target: "http_server", "Feed {} lagged: {skipped}", T::NAME);
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    let events = futures::StreamExt::filter_map(
+        futures::StreamExt::chain(snapshot, received),
+        move |event| {
+            let event = thread_id
+                .as_ref()
+                .is_none_or(|thread_id| thread_id == event.thread_id())
+                .then(|| {
+                    serde_json::to_string(&event)
+                        .map(|data| SseEvent::default().name(T::NAME).text(data))
+                });
+            std::future::ready(event)
+        },
+    );
+    SseKeepAlive::new(events).stream(res);
+}
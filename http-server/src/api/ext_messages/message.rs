@@ -18,6 +18,9 @@ pub struct IncomingExternalMessage {
     body: String,
     thread_id: Option<String>,
     ext_message_token: Option<Token>,
+    /// Request execution trace of the message
+    #[serde(default)]
+    trace: bool,
 }
 
 impl IncomingExternalMessage {
@@ -31,6 +34,7 @@ pub struct ExternalMessage {
     message: Message,
     thread_id: ThreadIdentifier,
     pub ext_message_token: Option<Token>,
+    pub trace: bool,
 }
 
 impl ExternalMessage {
@@ -55,7 +59,7 @@ impl TryFrom<&IncomingExternalMessage> for ExternalMessage {
     type Error = anyhow::Error;
 
     fn try_from(incoming: &IncomingExternalMessage) -> Result<Self, Self::Error> {
-        let IncomingExternalMessage { id, body, thread_id, ext_message_token } = incoming;
+        let IncomingExternalMessage { id, body, thread_id, ext_message_token, trace } = incoming;
 
         let message = parse_message(id, body)
             .map_err(|err| anyhow::anyhow!("Failed to parse message {id:?}: {err}"))?;
@@ -69,6 +73,7 @@ impl TryFrom<&IncomingExternalMessage> for ExternalMessage {
             message,
             thread_id,
             ext_message_token: ext_message_token.clone(),
+            trace: *trace,
         })
     }
 }
@@ -79,6 +84,7 @@ impl fmt::Debug for ExternalMessage {
             .field("hash", &self.hash)
             .field("thread_id", &self.thread_id)
             .field("ext_message_token", &self.ext_message_token)
+            .field("trace", &self.trace)
             .finish()
     }
 }
@@ -19,6 +19,8 @@ use serde_with::Bytes;
 use tvm_types::write_boc;
 use tvm_types::SliceData;
 
+use crate::api::TxTraceStatus;
+
 mod message;
 pub mod v2;
 
@@ -73,6 +75,8 @@ pub struct ExtMsgResponse {
     result: Option<ExtMsgResult>,
     error: Option<ExtMsgError>,
     ext_message_token: Option<Token>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trace: Option<TxTraceStatus>,
 }
 
 impl ExtMsgResponse {
@@ -81,9 +85,14 @@ impl ExtMsgResponse {
             result: None,
             error: Some(ExtMsgError::new(code, message, data)),
             ext_message_token: None,
+            trace: None,
         }
     }
 
+    fn set_trace(&mut self, trace: Option<TxTraceStatus>) {
+        self.trace = trace;
+    }
+
     fn set_producers(&mut self, producers: Vec<String>) {
         if let Some(mut result) = self.result.take() {
             result.producers = producers.clone();
@@ -215,6 +224,8 @@ pub enum FeedbackErrorCode {
     InternalError,
     ComputeSkipped,
     QueueOverflow,
+    RejectedByPolicy,
+    Quarantined,
 }
 
 impl FeedbackErrorCode {
@@ -232,6 +243,8 @@ impl FeedbackErrorCode {
             FeedbackErrorCode::InternalError => Cow::Borrowed("INTERNAL_ERROR"),
             FeedbackErrorCode::ComputeSkipped => Cow::Borrowed("COMPUTE_SKIPPED"),
             FeedbackErrorCode::QueueOverflow => Cow::Borrowed("QUEUE_OVERFLOW"),
+            FeedbackErrorCode::RejectedByPolicy => Cow::Borrowed("REJECTED_BY_POLICY"),
+            FeedbackErrorCode::Quarantined => Cow::Borrowed("QUARANTINED"),
         }
     }
 }
@@ -365,6 +378,7 @@ pub(crate) fn render_error_response(
             data,
         }),
         ext_message_token,
+        trace: None,
     }));
 }
 
@@ -383,5 +397,6 @@ pub(crate) fn render_error(
             data: None,
         }),
         ext_message_token,
+        trace: None,
     }));
 }
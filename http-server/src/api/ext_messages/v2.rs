@@ -169,6 +169,12 @@ where
             return;
         };
 
+        let trace = message.trace.then(|| {
+            let status = web_server.tx_traces.request(&message.hash());
+            tracing::debug!(target: "http_server", "Ext message trace requested: {status:?}");
+            status
+        });
+
         let convert = &web_server.into_external_message;
         let wrapped_message: TMessage = match convert(
             message.tvm_message(),
@@ -233,6 +239,7 @@ where
                 };
                 let mut result: ExtMsgResponse = feedback.into();
                 result.set_producers(producers);
+                result.set_trace(trace);
                 tracing::trace!(target: "http_server", "Response message: {:?}", result);
                 res.status_code(StatusCode::OK);
                 res.render(Json(result));
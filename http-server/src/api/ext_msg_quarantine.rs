@@ -0,0 +1,302 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use salvo::async_trait;
+use salvo::http::StatusCode;
+use salvo::prelude::Json;
+use salvo::Depot;
+use salvo::FlowCtrl;
+use salvo::Handler;
+use salvo::Request;
+use salvo::Response;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::ApiError;
+use crate::ResolvingResult;
+use crate::WebServer;
+
+// Number of the failed, but not yet quarantined messages tracked
+const TRACKED_FAILURES_CAPACITY: usize = 10_000;
+
+/// External message that failed to execute (executor panic or error) too
+/// many times and is dropped by the producer.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct QuarantinedExtMessage {
+    pub message_hash: String,
+    pub thread_id: String,
+    pub destination: String,
+    pub failures: u32,
+    /// Error of the last failed execution.
+    pub last_error: String,
+    /// Unix time (ms) of the first failed execution.
+    pub first_failed_at: u64,
+    /// Unix time (ms) the message was quarantined at.
+    pub quarantined_at: u64,
+}
+
+struct FailedExecutions {
+    failures: u32,
+    first_failed_at: u64,
+}
+
+#[derive(Default)]
+struct QuarantineState {
+    failures: HashMap<String, FailedExecutions>,
+    quarantined: BTreeMap<String, QuarantinedExtMessage>,
+    // Incremented on each change of the quarantined messages
+    version: u64,
+}
+
+/// Shared between the block producers and the web server: the producers
+/// report failed executions of the external messages and drop the
+/// quarantined ones, the web server lists and purges the quarantine.
+#[derive(Clone)]
+pub struct ExtMsgQuarantine {
+    max_failures: u32,
+    state: Arc<parking_lot::RwLock<QuarantineState>>,
+}
+
+impl ExtMsgQuarantine {
+    pub fn new(max_failures: u32, quarantined: Vec<QuarantinedExtMessage>) -> Self {
+        let state = QuarantineState {
+            quarantined: quarantined
+                .into_iter()
+                .map(|message| (message.message_hash.clone(), message))
+                .collect(),
+            ..Default::default()
+        };
+        Self { max_failures, state: Arc::new(parking_lot::RwLock::new(state)) }
+    }
+
+    /// Returns true if the message is quarantined after this failure.
+    pub fn report_failure(
+        &self,
+        message_hash: &str,
+        thread_id: &str,
+        destination: &str,
+        error: &str,
+        now_ms: u64,
+    ) -> bool {
+        let mut state = self.state.write();
+        if state.quarantined.contains_key(message_hash) {
+            return true;
+        }
+        if !state.failures.contains_key(message_hash)
+            && state.failures.len() >= TRACKED_FAILURES_CAPACITY
+        {
+            let oldest = state
+                .failures
+                .iter()
+                .min_by_key(|(_, failed)| failed.first_failed_at)
+                .map(|(hash, _)| hash.clone());
+            if let Some(oldest) = oldest {
+                state.failures.remove(&oldest);
+            }
+        }
+        let failed = state
+            .failures
+            .entry(message_hash.to_string())
+            .or_insert(FailedExecutions { failures: 0, first_failed_at: now_ms });
+        failed.failures += 1;
+        if failed.failures < self.max_failures {
+            return false;
+        }
+        let FailedExecutions { failures, first_failed_at } =
+            state.failures.remove(message_hash).expect("Failure was just tracked");
+        state.quarantined.insert(
+            message_hash.to_string(),
+            QuarantinedExtMessage {
+                message_hash: message_hash.to_string(),
+                thread_id: thread_id.to_string(),
+                destination: destination.to_string(),
+                failures,
+                last_error: error.to_string(),
+                first_failed_at,
+                quarantined_at: now_ms,
+            },
+        );
+        state.version += 1;
+        true
+    }
+
+    /// Resets the failures of the message, only consecutive failures lead to
+    /// the quarantine.
+    pub fn report_success(&self, message_hash: &str) {
+        if self.state.read().failures.contains_key(message_hash) {
+            self.state.write().failures.remove(message_hash);
+        }
+    }
+
+    pub fn is_quarantined(&self, message_hash: &str) -> bool {
+        self.state.read().quarantined.contains_key(message_hash)
+    }
+
+    pub fn list(&self, thread_id: Option<&str>) -> Vec<QuarantinedExtMessage> {
+        self.state
+            .read()
+            .quarantined
+            .values()
+            .filter(|message| thread_id.is_none_or(|thread_id| message.thread_id == thread_id))
+            .cloned()
+            .collect()
+    }
+
+    /// Purged message is executed again if it is sent again.
+    pub fn purge(&self, message_hash: &str) -> bool {
+        let mut state = self.state.write();
+        let found = state.quarantined.remove(message_hash).is_some();
+        if found {
+            state.version += 1;
+        }
+        found
+    }
+
+    /// Returns the number of the purged messages.
+    pub fn purge_all(&self, thread_id: Option<&str>) -> usize {
+        let mut state = self.state.write();
+        let before = state.quarantined.len();
+        state
+            .quarantined
+            .retain(|_, message| thread_id.is_some_and(|thread_id| message.thread_id != thread_id));
+        let purged = before - state.quarantined.len();
+        if purged > 0 {
+            state.version += 1;
+        }
+        purged
+    }
+
+    /// Changes with each quarantined or purged message.
+    pub fn version(&self) -> u64 {
+        self.state.read().version
+    }
+}
+
+pub struct ExtMsgQuarantineHandler<
+    TMessage,
+    TMsgConverter,
+    TBPResolver,
+    TBocByAddrGetter,
+    TSeqnoGetter,
+>(PhantomData<(TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter)>);
+
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+    ExtMsgQuarantineHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+{
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[async_trait]
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter> Handler
+    for ExtMsgQuarantineHandler<
+        TMessage,
+        TMsgConverter,
+        TBPResolver,
+        TBocByAddrGetter,
+        TSeqnoGetter,
+    >
+where
+    TMessage: Clone + Send + Sync + 'static + std::fmt::Debug,
+    TMsgConverter: Clone
+        + Send
+        + Sync
+        + 'static
+        + Fn(tvm_block::Message, [u8; 34]) -> anyhow::Result<TMessage>,
+    TBPResolver: Clone + Send + Sync + 'static + FnMut([u8; 34]) -> ResolvingResult,
+    TBocByAddrGetter:
+        Clone + Send + Sync + 'static + Fn(String) -> anyhow::Result<(String, Option<String>)>,
+    TSeqnoGetter: Clone + Send + Sync + 'static + Fn() -> anyhow::Result<u32>,
+{
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        _ctrl: &mut FlowCtrl,
+    ) {
+        let Ok(web_server) = depot.obtain::<WebServer<
+            TMessage,
+            TMsgConverter,
+            TBPResolver,
+            TBocByAddrGetter,
+            TSeqnoGetter,
+        >>() else {
+            res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+            res.render("Internal server error: Web Server state not found");
+            return;
+        };
+        let Some(quarantine) = web_server.ext_msg_quarantine.as_ref() else {
+            ApiError::new(
+                "EXT_MSG_QUARANTINE_DISABLED",
+                "Failed external messages are retried",
+                false,
+            )
+            .render(res, StatusCode::NOT_FOUND);
+            return;
+        };
+        let thread_id = req.query::<String>("thread_id").map(|thread_id| thread_id.to_lowercase());
+        // POST purges the message (or the whole quarantine), GET only lists them
+        if req.method() == salvo::http::Method::POST {
+            match req.query::<String>("message_hash") {
+                Some(message_hash) => {
+                    let message_hash = message_hash.to_lowercase();
+                    if !quarantine.purge(&message_hash) {
+                        ApiError::new(
+                            "MESSAGE_NOT_QUARANTINED",
+                            format!("Message {message_hash} is not quarantined"),
+                            false,
+                        )
+                        .render(res, StatusCode::NOT_FOUND);
+                        return;
+                    }
+                }
+                None => {
+                    let purged = quarantine.purge_all(thread_id.as_deref());
+                    tracing::info!("Purged {purged} quarantined external messages");
+                }
+            }
+        }
+        res.status_code(StatusCode::OK);
+        res.render(Json(quarantine.list(thread_id.as_deref())));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quarantine_after_consecutive_failures() {
+        let quarantine = ExtMsgQuarantine::new(2, vec![]);
+        assert!(!quarantine.report_failure("aa", "01", "dst", "panic", 1));
+        quarantine.report_success("aa");
+        assert!(!quarantine.report_failure("aa", "01", "dst", "panic", 2));
+        assert!(quarantine.report_failure("aa", "01", "dst", "timeout", 3));
+        assert!(quarantine.is_quarantined("aa"));
+        assert!(!quarantine.report_failure("bb", "02", "dst", "panic", 4));
+        assert!(quarantine.report_failure("bb", "02", "dst", "panic", 5));
+
+        let [message] = quarantine.list(Some("01")).try_into().unwrap();
+        assert_eq!(message.failures, 2);
+        assert_eq!(message.last_error, "timeout");
+        assert_eq!(message.first_failed_at, 2);
+        assert_eq!(message.quarantined_at, 3);
+
+        let restored = ExtMsgQuarantine::new(2, quarantine.list(None));
+        assert!(restored.is_quarantined("bb"));
+        let version = restored.version();
+        assert!(restored.purge("aa"));
+        assert!(!restored.purge("aa"));
+        assert_eq!(restored.purge_all(Some("01")), 0);
+        assert_eq!(restored.purge_all(None), 1);
+        assert!(restored.list(None).is_empty());
+        assert_eq!(restored.version(), version + 2);
+    }
+}
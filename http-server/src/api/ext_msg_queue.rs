@@ -0,0 +1,162 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use salvo::async_trait;
+use salvo::http::StatusCode;
+use salvo::prelude::Json;
+use salvo::Depot;
+use salvo::FlowCtrl;
+use salvo::Handler;
+use salvo::Request;
+use salvo::Response;
+use serde::Serialize;
+
+use crate::ResolvingResult;
+use crate::WebServer;
+
+/// State of the external messages queue of a thread.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ExtMsgQueueStats {
+    pub thread_id: String,
+    /// Number of messages waiting to be processed.
+    pub depth: usize,
+    /// Unix time (ms) the oldest waiting message was received at.
+    pub oldest_message_timestamp: Option<u64>,
+    /// Messages processed per second over the last minute.
+    pub processing_rate: f64,
+    /// Unix time (ms) of the update.
+    pub timestamp: u64,
+}
+
+/// Shared between the external messages thread states and the web server:
+/// the states publish their queue stats on each change, the web server shows
+/// the last ones.
+#[derive(Clone, Default)]
+pub struct ExtMsgQueueStatus {
+    latest: Arc<parking_lot::RwLock<HashMap<String, ExtMsgQueueStats>>>,
+}
+
+impl ExtMsgQueueStatus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn publish(&self, stats: ExtMsgQueueStats) {
+        self.latest.write().insert(stats.thread_id.clone(), stats);
+    }
+
+    pub fn remove_thread(&self, thread_id: &str) {
+        self.latest.write().remove(thread_id);
+    }
+
+    pub fn snapshot(&self) -> Vec<ExtMsgQueueStats> {
+        let mut snapshot = self.latest.read().values().cloned().collect::<Vec<_>>();
+        snapshot.sort_by(|a, b| a.thread_id.cmp(&b.thread_id));
+        snapshot
+    }
+}
+
+#[derive(Serialize)]
+struct ExtMsgQueueResult {
+    #[serde(flatten)]
+    stats: ExtMsgQueueStats,
+    /// Age (ms) of the oldest waiting message at the time of the request.
+    oldest_message_age: Option<u64>,
+}
+
+pub struct ExtMsgQueueHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>(
+    PhantomData<(TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter)>,
+);
+
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+    ExtMsgQueueHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+{
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[async_trait]
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter> Handler
+    for ExtMsgQueueHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+where
+    TMessage: Clone + Send + Sync + 'static + std::fmt::Debug,
+    TMsgConverter: Clone
+        + Send
+        + Sync
+        + 'static
+        + Fn(tvm_block::Message, [u8; 34]) -> anyhow::Result<TMessage>,
+    TBPResolver: Clone + Send + Sync + 'static + FnMut([u8; 34]) -> ResolvingResult,
+    TBocByAddrGetter:
+        Clone + Send + Sync + 'static + Fn(String) -> anyhow::Result<(String, Option<String>)>,
+    TSeqnoGetter: Clone + Send + Sync + 'static + Fn() -> anyhow::Result<u32>,
+{
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        _ctrl: &mut FlowCtrl,
+    ) {
+        let Ok(web_server) = depot.obtain::<WebServer<
+            TMessage,
+            TMsgConverter,
+            TBPResolver,
+            TBocByAddrGetter,
+            TSeqnoGetter,
+        >>() else {
+            res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+            res.render("Internal server error: Web Server state not found");
+            return;
+        };
+        let thread_id = req.query::<String>("thread_id").map(|thread_id| thread_id.to_lowercase());
+        let now = telemetry_utils::now_ms();
+        let result = web_server
+            .ext_msg_queue
+            .snapshot()
+            .into_iter()
+            .filter(|stats| {
+                thread_id.as_ref().is_none_or(|thread_id| *thread_id == stats.thread_id)
+            })
+            .map(|stats| ExtMsgQueueResult {
+                oldest_message_age: stats
+                    .oldest_message_timestamp
+                    .map(|timestamp| now.saturating_sub(timestamp)),
+                stats,
+            })
+            .collect::<Vec<_>>();
+        res.status_code(StatusCode::OK);
+        res.render(Json(result));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(thread_id: &str, depth: usize) -> ExtMsgQueueStats {
+        ExtMsgQueueStats {
+            thread_id: thread_id.to_string(),
+            depth,
+            oldest_message_timestamp: None,
+            processing_rate: 0.0,
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn test_ext_msg_queue_status() {
+        let status = ExtMsgQueueStatus::new();
+        status.publish(stats("02", 10));
+        status.publish(stats("01", 20));
+        status.publish(stats("02", 30));
+        assert_eq!(status.snapshot(), vec![stats("01", 20), stats("02", 30)]);
+
+        status.remove_thread("01");
+        assert_eq!(status.snapshot(), vec![stats("02", 30)]);
+    }
+}
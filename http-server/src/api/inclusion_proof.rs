@@ -0,0 +1,162 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+
+use salvo::prelude::*;
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+
+use crate::ApiError;
+use crate::ResolvingResult;
+use crate::WebServer;
+
+/// Request to the node for the proof that the transaction is included in
+/// the block.
+pub struct InclusionProofRequest {
+    pub block_id: String,
+    pub transaction_id: String,
+    pub response: oneshot::Sender<anyhow::Result<InclusionProof>>,
+}
+
+pub type InclusionProofRequestSender = mpsc::Sender<InclusionProofRequest>;
+
+/// Proof of the transaction inclusion that can be verified with the block
+/// keeper set as the only trust root: the Merkle proof binds the transaction
+/// to the block id and the aggregated attestation binds the block id to the
+/// keeper signatures.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct InclusionProof {
+    pub block_id: String,
+    pub thread_id: String,
+    pub block_seq_no: u32,
+    pub transaction_id: String,
+    /// Merkle proof BOC (base64) of the transaction cell, the proof root
+    /// hash is the block id.
+    pub proof: String,
+    /// None if no attestation of the block has been included in its
+    /// descendants yet.
+    pub attestation: Option<BlockAttestation>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct BlockAttestation {
+    /// Serialized attestation data (hex): the message signed by the keepers.
+    /// It contains the attested block id.
+    pub data: String,
+    /// Aggregated BLS signature (hex).
+    pub aggregated_signature: String,
+    /// Bit `i % 8` of byte `i / 8` is set if the keeper with signer index `i`
+    /// signed the attestation (hex).
+    pub signer_bitmap: String,
+    /// Number of times each signature is aggregated, keys are signer indexes.
+    pub signature_occurrences: BTreeMap<u16, u16>,
+    /// Block the attestation was included in.
+    pub included_in_block_id: String,
+}
+
+/// Signer bitmap: bit `i % 8` of byte `i / 8` is set for signer index `i`.
+pub fn signer_bitmap(signers: impl IntoIterator<Item = u16>) -> Vec<u8> {
+    let mut bitmap = vec![];
+    for signer in signers {
+        let byte = signer as usize / 8;
+        if bitmap.len() <= byte {
+            bitmap.resize(byte + 1, 0);
+        }
+        bitmap[byte] |= 1 << (signer % 8);
+    }
+    bitmap
+}
+
+pub struct InclusionProofHandler<
+    TMessage,
+    TMsgConverter,
+    TBPResolver,
+    TBocByAddrGetter,
+    TSeqnoGetter,
+>(PhantomData<(TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter)>);
+
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+    InclusionProofHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+{
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[async_trait]
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter> Handler
+    for InclusionProofHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+where
+    TMessage: Clone + Send + Sync + 'static + std::fmt::Debug,
+    TMsgConverter: Clone
+        + Send
+        + Sync
+        + 'static
+        + Fn(tvm_block::Message, [u8; 34]) -> anyhow::Result<TMessage>,
+    TBPResolver: Clone + Send + Sync + 'static + FnMut([u8; 34]) -> ResolvingResult,
+    TBocByAddrGetter:
+        Clone + Send + Sync + 'static + Fn(String) -> anyhow::Result<(String, Option<String>)>,
+    TSeqnoGetter: Clone + Send + Sync + 'static + Fn() -> anyhow::Result<u32>,
+{
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        _ctrl: &mut FlowCtrl,
+    ) {
+        let block_id: String = req.query("block_id").unwrap_or_default();
+        let transaction_id: String = req.query("transaction_id").unwrap_or_default();
+        if block_id.is_empty() || transaction_id.is_empty() {
+            res.status_code(StatusCode::BAD_REQUEST);
+            res.render("block_id and transaction_id parameters required");
+            return;
+        }
+
+        let Ok(web_server) = depot.obtain::<WebServer<
+            TMessage,
+            TMsgConverter,
+            TBPResolver,
+            TBocByAddrGetter,
+            TSeqnoGetter,
+        >>() else {
+            res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+            res.render("Internal server error: Web Server state not found");
+            return;
+        };
+
+        let (response, response_rx) = oneshot::channel();
+        let result = match web_server
+            .inclusion_proof_request_sender
+            .send(InclusionProofRequest { block_id, transaction_id, response })
+            .await
+        {
+            Ok(()) => response_rx
+                .await
+                .unwrap_or_else(|_| Err(anyhow::format_err!("Inclusion proof request dropped"))),
+            Err(_) => Err(anyhow::format_err!("Inclusion proof service is not running")),
+        };
+        match result {
+            Ok(proof) => res.render(Json(proof)),
+            Err(e) => {
+                ApiError::from_anyhow(&e, "TRANSACTION_NOT_FOUND")
+                    .render(res, StatusCode::NOT_FOUND);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signer_bitmap() {
+        assert_eq!(signer_bitmap([]), Vec::<u8>::new());
+        assert_eq!(signer_bitmap([0, 3]), vec![0b0000_1001]);
+        assert_eq!(signer_bitmap([9, 1]), vec![0b0000_0010, 0b0000_0010]);
+    }
+}
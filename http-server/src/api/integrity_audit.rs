@@ -0,0 +1,177 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::marker::PhantomData;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use salvo::async_trait;
+use salvo::http::StatusCode;
+use salvo::prelude::Json;
+use salvo::Depot;
+use salvo::FlowCtrl;
+use salvo::Handler;
+use salvo::Request;
+use salvo::Response;
+use serde::Serialize;
+
+use crate::ResolvingResult;
+use crate::WebServer;
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct CorruptedEntry {
+    /// Stored object kind: `optimistic_state` or `block`.
+    pub kind: String,
+    pub path: String,
+    pub reason: String,
+}
+
+/// Result of a single pass over the stored states and blocks.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct IntegrityAuditSummary {
+    /// Unix time (ms) of the audit start.
+    pub started_at: u64,
+    /// Unix time (ms) of the audit finish.
+    pub finished_at: u64,
+    pub optimistic_states_checked: usize,
+    pub blocks_checked: usize,
+    pub corrupted: Vec<CorruptedEntry>,
+}
+
+/// Shared between the node audit task and the web server: the task publishes
+/// summaries, the web server shows the last one and requests on-demand runs.
+#[derive(Clone, Default)]
+pub struct IntegrityAudit {
+    last_summary: Arc<parking_lot::RwLock<Option<IntegrityAuditSummary>>>,
+    requested: Arc<AtomicBool>,
+    in_progress: Arc<AtomicBool>,
+}
+
+impl IntegrityAudit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::Release);
+    }
+
+    /// Returns true once after the audit was requested.
+    pub fn take_request(&self) -> bool {
+        self.requested.swap(false, Ordering::AcqRel)
+    }
+
+    pub fn set_in_progress(&self, in_progress: bool) {
+        self.in_progress.store(in_progress, Ordering::Release);
+    }
+
+    pub fn publish(&self, summary: IntegrityAuditSummary) {
+        *self.last_summary.write() = Some(summary);
+    }
+
+    pub fn last_summary(&self) -> Option<IntegrityAuditSummary> {
+        self.last_summary.read().clone()
+    }
+}
+
+#[derive(Serialize)]
+struct IntegrityAuditResult {
+    in_progress: bool,
+    requested: bool,
+    last: Option<IntegrityAuditSummary>,
+}
+
+pub struct IntegrityAuditHandler<
+    TMessage,
+    TMsgConverter,
+    TBPResolver,
+    TBocByAddrGetter,
+    TSeqnoGetter,
+>(
+    PhantomData<TMessage>,
+    PhantomData<TMsgConverter>,
+    PhantomData<TBPResolver>,
+    PhantomData<TBocByAddrGetter>,
+    PhantomData<TSeqnoGetter>,
+);
+
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+    IntegrityAuditHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+{
+    pub fn new() -> Self {
+        Self(PhantomData, PhantomData, PhantomData, PhantomData, PhantomData)
+    }
+}
+
+#[async_trait]
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter> Handler
+    for IntegrityAuditHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+where
+    TMessage: Clone + Send + Sync + 'static + std::fmt::Debug,
+    TMsgConverter: Clone
+        + Send
+        + Sync
+        + 'static
+        + Fn(tvm_block::Message, [u8; 34]) -> anyhow::Result<TMessage>,
+    TBPResolver: Clone + Send + Sync + 'static + FnMut([u8; 34]) -> ResolvingResult,
+    TBocByAddrGetter:
+        Clone + Send + Sync + 'static + Fn(String) -> anyhow::Result<(String, Option<String>)>,
+    TSeqnoGetter: Clone + Send + Sync + 'static + Fn() -> anyhow::Result<u32>,
+{
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        _ctrl: &mut FlowCtrl,
+    ) {
+        let Ok(web_server_state) = depot.obtain::<WebServer<
+            TMessage,
+            TMsgConverter,
+            TBPResolver,
+            TBocByAddrGetter,
+            TSeqnoGetter,
+        >>() else {
+            res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+            return;
+        };
+        let audit = &web_server_state.integrity_audit;
+        // POST starts a new audit pass, GET only reports the last one
+        if req.method() == salvo::http::Method::POST {
+            audit.request();
+            res.status_code(StatusCode::ACCEPTED);
+        } else {
+            res.status_code(StatusCode::OK);
+        }
+        res.render(Json(IntegrityAuditResult {
+            in_progress: audit.in_progress.load(Ordering::Acquire),
+            requested: audit.requested.load(Ordering::Acquire),
+            last: audit.last_summary(),
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_is_taken_once() {
+        let audit = IntegrityAudit::new();
+        assert!(!audit.take_request());
+        audit.request();
+        audit.request();
+        assert!(audit.take_request());
+        assert!(!audit.take_request());
+    }
+
+    #[test]
+    fn test_publish_replaces_summary() {
+        let audit = IntegrityAudit::new();
+        assert_eq!(audit.last_summary(), None);
+        audit.publish(IntegrityAuditSummary { blocks_checked: 1, ..Default::default() });
+        audit.publish(IntegrityAuditSummary { blocks_checked: 2, ..Default::default() });
+        assert_eq!(audit.last_summary().map(|s| s.blocks_checked), Some(2));
+    }
+}
@@ -1,17 +1,143 @@
 // 2022-2024 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
 //
 
+mod account_nonce;
+mod account_ownership;
+mod accounts_export;
+mod admin_audit;
+mod admin_auth;
 mod bk_set;
+mod bk_set_changes;
+mod bk_set_history;
+mod block_invalidations;
 mod boc_by_address;
+mod dapp_config;
 mod default_thread_seqno;
+mod durable_messages;
+mod error;
+mod event_feed;
 pub(crate) mod ext_messages;
+mod ext_msg_quarantine;
+mod ext_msg_queue;
+mod inclusion_proof;
+mod integrity_audit;
+mod node_status;
+mod out_msg_queues;
+mod producer_rotations;
+mod production_stalls;
+mod run_get;
 pub(crate) mod storage_latest;
+mod thread_load;
+mod thread_splits;
+mod tx_trace;
 
+pub use account_nonce::AccountNonce;
+pub use account_nonce::AccountNonceHandler;
+pub use account_nonce::AccountNonceRequest;
+pub use account_nonce::AccountNonceRequestSender;
+pub use account_ownership::AccountOwnership;
+pub use account_ownership::AccountOwnershipFeed;
+pub use account_ownership::AccountOwnershipFilter;
+pub use account_ownership::AccountOwnershipHandler;
+pub use accounts_export::AccountsExportFilter;
+pub use accounts_export::AccountsExportHandler;
+pub use accounts_export::AccountsExportRecord;
+pub use accounts_export::AccountsExportRequest;
+pub use accounts_export::AccountsExportRequestSender;
+pub use accounts_export::ExportedThreadState;
+pub use admin_audit::AdminAuditEntry;
+pub use admin_audit::AdminAuditHandler;
+pub use admin_audit::AdminAuditLog;
+pub use admin_auth::sign_admin_challenge;
+pub use admin_auth::AdminAuth;
+pub use admin_auth::AdminAuthMode;
+pub use admin_auth::AdminChallenge;
+pub use admin_auth::AdminChallengeHandler;
 pub use bk_set::BkInfo;
 pub use bk_set::BkSetHandler;
 pub use bk_set::BkSetResult;
 pub use bk_set::BkSetSnapshot;
 pub use bk_set::BlockKeeperSetUpdate;
+pub use bk_set_changes::BkSetChangeEvent;
+pub use bk_set_changes::BkSetChangeFeed;
+pub use bk_set_changes::BkSetChangeKind;
+pub use bk_set_changes::BkSetChangesHandler;
+pub use bk_set_history::BkHistoryInfo;
+pub use bk_set_history::BkSetHistory;
+pub use bk_set_history::BkSetHistoryHandler;
+pub use bk_set_history::BkSetHistoryResult;
+pub use bk_set_history::BkSetHistoryUpdate;
+pub use bk_set_history::BkSetWindow;
+pub use bk_set_history::SignedBkSetHistory;
+pub use block_invalidations::BlockInvalidationEvent;
+pub use block_invalidations::BlockInvalidationFeed;
+pub use block_invalidations::BlockInvalidationsHandler;
 pub use boc_by_address::BocByAddressHandler;
+pub use dapp_config::DappConfigHandler;
+pub use dapp_config::DappConfigInfo;
+pub use dapp_config::DappConfigRequest;
+pub use dapp_config::DappConfigRequestSender;
 pub use default_thread_seqno::LastSeqnoHandler;
+pub use durable_messages::DurableMessage;
+pub use durable_messages::DurableMessagesHandler;
+pub use durable_messages::DurableMessagesPage;
+pub use durable_messages::DurableMessagesQuery;
+pub use durable_messages::DurableMessagesRequest;
+pub use durable_messages::DurableMessagesRequestSender;
+pub use error::ApiError;
+pub use event_feed::EventFeed;
+pub use event_feed::FeedEvent;
+pub use ext_msg_quarantine::ExtMsgQuarantine;
+pub use ext_msg_quarantine::ExtMsgQuarantineHandler;
+pub use ext_msg_quarantine::QuarantinedExtMessage;
+pub use ext_msg_queue::ExtMsgQueueHandler;
+pub use ext_msg_queue::ExtMsgQueueStats;
+pub use ext_msg_queue::ExtMsgQueueStatus;
+pub use inclusion_proof::signer_bitmap;
+pub use inclusion_proof::BlockAttestation;
+pub use inclusion_proof::InclusionProof;
+pub use inclusion_proof::InclusionProofHandler;
+pub use inclusion_proof::InclusionProofRequest;
+pub use inclusion_proof::InclusionProofRequestSender;
+pub use integrity_audit::CorruptedEntry;
+pub use integrity_audit::IntegrityAudit;
+pub use integrity_audit::IntegrityAuditHandler;
+pub use integrity_audit::IntegrityAuditSummary;
+pub use node_status::HealthFlags;
+pub use node_status::NodeStatus;
+pub use node_status::NodeStatusBoard;
+pub use node_status::NodeStatusHandler;
+pub use node_status::SyncState;
+pub use node_status::ThreadRole;
+pub use node_status::ThreadStatus;
+pub use out_msg_queues::OutMsgQueueEntry;
+pub use out_msg_queues::OutMsgQueueFilter;
+pub use out_msg_queues::OutMsgQueueIndex;
+pub use out_msg_queues::OutMsgQueueRecord;
+pub use out_msg_queues::OutMsgQueuesHandler;
+pub use producer_rotations::ProducerRotationEvent;
+pub use producer_rotations::ProducerRotationFeed;
+pub use producer_rotations::ProducerRotationTrigger;
+pub use producer_rotations::ProducerRotationsHandler;
+pub use production_stalls::ProductionStallFeed;
+pub use production_stalls::ProductionStallReport;
+pub use production_stalls::ProductionStallsHandler;
+pub use run_get::RunGetHandler;
+pub use run_get::RunGetParams;
+pub use run_get::RunGetRequest;
+pub use run_get::RunGetRequestSender;
+pub use run_get::RunGetResult;
+pub use run_get::RunGetState;
 pub use storage_latest::StorageLatestHandler;
+pub use thread_load::ThreadLoadDecision;
+pub use thread_load::ThreadLoadFeed;
+pub use thread_load::ThreadLoadHandler;
+pub use thread_load::ThreadLoadUpdate;
+pub use thread_splits::PendingThreadSplit;
+pub use thread_splits::ThreadSplitGate;
+pub use thread_splits::ThreadSplitPreview;
+pub use thread_splits::ThreadSplitsHandler;
+pub use thread_splits::ThreadsTableRowPreview;
+pub use tx_trace::TxTraceHandler;
+pub use tx_trace::TxTraceRegistry;
+pub use tx_trace::TxTraceStatus;
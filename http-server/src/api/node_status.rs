@@ -0,0 +1,328 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::marker::PhantomData;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use salvo::async_trait;
+use salvo::http::StatusCode;
+use salvo::prelude::Json;
+use salvo::Depot;
+use salvo::FlowCtrl;
+use salvo::Handler;
+use salvo::Request;
+use salvo::Response;
+use serde::Serialize;
+
+use crate::ExtMsgQueueStats;
+use crate::IntegrityAuditSummary;
+use crate::ResolvingResult;
+use crate::WebServer;
+
+// Number of the last applied blocks the attestation participation is
+// calculated over
+const ATTESTATION_WINDOW: usize = 100;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ThreadRole {
+    /// Produces blocks of the thread
+    Producer,
+    /// Member of the block keeper set
+    Keeper,
+    Observer,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncState {
+    Synced,
+    Syncing,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ThreadStatus {
+    pub thread_id: String,
+    pub role: ThreadRole,
+    pub sync_state: SyncState,
+    pub last_finalized_seq_no: Option<u32>,
+    pub last_applied_seq_no: Option<u32>,
+    /// External messages waiting to be processed.
+    pub mempool_depth: usize,
+    /// Fraction of the last applied blocks attested by the node, None until
+    /// a block is applied.
+    pub attestation_participation: Option<f64>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct HealthFlags {
+    /// None until the first integrity audit finishes.
+    pub integrity_ok: Option<bool>,
+    /// Some thread exceeds its storage quota.
+    pub storage_quota_exceeded: bool,
+    /// API queries are shed under production pressure.
+    pub queries_shed: bool,
+}
+
+/// Summary for the orchestration tooling deciding on restarts and drains.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct NodeStatus {
+    pub node_id: String,
+    pub threads: Vec<ThreadStatus>,
+    /// Live gossip peers.
+    pub gossip_peers: usize,
+    pub health: HealthFlags,
+    /// Unix time (ms) of the request.
+    pub timestamp: u64,
+}
+
+#[derive(Default)]
+struct ThreadState {
+    producing: bool,
+    syncing: bool,
+    last_finalized_seq_no: Option<u32>,
+    last_applied_seq_no: Option<u32>,
+    applied: BTreeSet<u32>,
+    attested: BTreeSet<u32>,
+    storage_quota_exceeded: bool,
+}
+
+impl ThreadState {
+    fn attestation_participation(&self) -> Option<f64> {
+        if self.applied.is_empty() {
+            return None;
+        }
+        let attested = self.applied.iter().filter(|seq_no| self.attested.contains(seq_no)).count();
+        Some(attested as f64 / self.applied.len() as f64)
+    }
+
+    fn trim_window(&mut self) {
+        while self.applied.len() > ATTESTATION_WINDOW {
+            self.applied.pop_first();
+        }
+        if let Some(first) = self.applied.first() {
+            self.attested = self.attested.split_off(first);
+        }
+    }
+}
+
+/// Shared between the node services and the web server: the services report
+/// the state of the threads as it changes, the web server combines it with
+/// the queue and health data into the node status.
+#[derive(Clone)]
+pub struct NodeStatusBoard {
+    node_id: String,
+    threads: Arc<parking_lot::RwLock<BTreeMap<String, ThreadState>>>,
+    gossip_peers: Arc<AtomicUsize>,
+}
+
+impl NodeStatusBoard {
+    pub fn new(node_id: impl Into<String>) -> Self {
+        Self {
+            node_id: node_id.into(),
+            threads: Default::default(),
+            gossip_peers: Default::default(),
+        }
+    }
+
+    fn update(&self, thread_id: &str, update: impl FnOnce(&mut ThreadState)) {
+        update(self.threads.write().entry(thread_id.to_string()).or_default());
+    }
+
+    pub fn set_producing(&self, thread_id: &str, producing: bool) {
+        self.update(thread_id, |state| state.producing = producing);
+    }
+
+    pub fn set_syncing(&self, thread_id: &str, syncing: bool) {
+        self.update(thread_id, |state| state.syncing = syncing);
+    }
+
+    pub fn report_finalized(&self, thread_id: &str, seq_no: u32) {
+        self.update(thread_id, |state| state.last_finalized_seq_no = Some(seq_no));
+    }
+
+    pub fn report_applied(&self, thread_id: &str, seq_no: u32) {
+        self.update(thread_id, |state| {
+            state.last_applied_seq_no = state.last_applied_seq_no.max(Some(seq_no));
+            state.applied.insert(seq_no);
+            state.trim_window();
+        });
+    }
+
+    pub fn report_attested(&self, thread_id: &str, seq_no: u32) {
+        self.update(thread_id, |state| {
+            state.attested.insert(seq_no);
+            state.trim_window();
+        });
+    }
+
+    pub fn set_storage_quota_exceeded(&self, thread_id: &str, exceeded: bool) {
+        self.update(thread_id, |state| state.storage_quota_exceeded = exceeded);
+    }
+
+    pub fn set_gossip_peers(&self, peers: usize) {
+        self.gossip_peers.store(peers, Ordering::Relaxed);
+    }
+
+    pub fn remove_thread(&self, thread_id: &str) {
+        self.threads.write().remove(thread_id);
+    }
+
+    fn status(
+        &self,
+        is_keeper: bool,
+        ext_msg_queue: &[ExtMsgQueueStats],
+        integrity_audit: Option<IntegrityAuditSummary>,
+        queries_shed: bool,
+        timestamp: u64,
+    ) -> NodeStatus {
+        let threads = self.threads.read();
+        let status = threads
+            .iter()
+            .map(|(thread_id, state)| ThreadStatus {
+                thread_id: thread_id.clone(),
+                role: if state.producing {
+                    ThreadRole::Producer
+                } else if is_keeper {
+                    ThreadRole::Keeper
+                } else {
+                    ThreadRole::Observer
+                },
+                sync_state: if state.syncing { SyncState::Syncing } else { SyncState::Synced },
+                last_finalized_seq_no: state.last_finalized_seq_no,
+                last_applied_seq_no: state.last_applied_seq_no,
+                mempool_depth: ext_msg_queue
+                    .iter()
+                    .find(|stats| stats.thread_id == *thread_id)
+                    .map(|stats| stats.depth)
+                    .unwrap_or_default(),
+                attestation_participation: state.attestation_participation(),
+            })
+            .collect();
+        NodeStatus {
+            node_id: self.node_id.clone(),
+            threads: status,
+            gossip_peers: self.gossip_peers.load(Ordering::Relaxed),
+            health: HealthFlags {
+                integrity_ok: integrity_audit.map(|summary| summary.corrupted.is_empty()),
+                storage_quota_exceeded: threads.values().any(|state| state.storage_quota_exceeded),
+                queries_shed,
+            },
+            timestamp,
+        }
+    }
+}
+
+pub struct NodeStatusHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>(
+    PhantomData<(TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter)>,
+);
+
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+    NodeStatusHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+{
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[async_trait]
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter> Handler
+    for NodeStatusHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+where
+    TMessage: Clone + Send + Sync + 'static + std::fmt::Debug,
+    TMsgConverter: Clone
+        + Send
+        + Sync
+        + 'static
+        + Fn(tvm_block::Message, [u8; 34]) -> anyhow::Result<TMessage>,
+    TBPResolver: Clone + Send + Sync + 'static + FnMut([u8; 34]) -> ResolvingResult,
+    TBocByAddrGetter:
+        Clone + Send + Sync + 'static + Fn(String) -> anyhow::Result<(String, Option<String>)>,
+    TSeqnoGetter: Clone + Send + Sync + 'static + Fn() -> anyhow::Result<u32>,
+{
+    async fn handle(
+        &self,
+        _req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        _ctrl: &mut FlowCtrl,
+    ) {
+        let Ok(web_server) = depot.obtain::<WebServer<
+            TMessage,
+            TMsgConverter,
+            TBPResolver,
+            TBocByAddrGetter,
+            TSeqnoGetter,
+        >>() else {
+            res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+            res.render("Internal server error: Web Server state not found");
+            return;
+        };
+        let board = &web_server.node_status;
+        let is_keeper = web_server.bk_set.read().contains_node(&board.node_id);
+        let status = board.status(
+            is_keeper,
+            &web_server.ext_msg_queue.snapshot(),
+            web_server.integrity_audit.last_summary(),
+            web_server.queries_shed.load(Ordering::Relaxed),
+            telemetry_utils::now_ms(),
+        );
+        res.status_code(StatusCode::OK);
+        res.render(Json(status));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_node_status() {
+        let board = NodeStatusBoard::new("node");
+        board.set_producing("01", true);
+        board.set_syncing("02", true);
+        board.report_finalized("01", 5);
+        for seq_no in 1..=4 {
+            board.report_applied("01", seq_no);
+        }
+        board.report_attested("01", 2);
+        board.report_attested("01", 4);
+        board.set_gossip_peers(3);
+
+        let queue = ExtMsgQueueStats {
+            thread_id: "01".to_string(),
+            depth: 7,
+            oldest_message_timestamp: None,
+            processing_rate: 0.0,
+            timestamp: 0,
+        };
+        let status = board.status(true, &[queue], None, false, 0);
+        assert_eq!(status.gossip_peers, 3);
+        assert_eq!(status.health.integrity_ok, None);
+        let [first, second] = status.threads.as_slice() else {
+            panic!("Unexpected threads: {:?}", status.threads);
+        };
+        assert_eq!(first.role, ThreadRole::Producer);
+        assert_eq!(first.sync_state, SyncState::Synced);
+        assert_eq!(first.last_finalized_seq_no, Some(5));
+        assert_eq!(first.last_applied_seq_no, Some(4));
+        assert_eq!(first.mempool_depth, 7);
+        assert_eq!(first.attestation_participation, Some(0.5));
+        assert_eq!(second.role, ThreadRole::Keeper);
+        assert_eq!(second.sync_state, SyncState::Syncing);
+        assert_eq!(second.attestation_participation, None);
+
+        // Participation is calculated over the last applied blocks
+        for seq_no in 5..=(ATTESTATION_WINDOW as u32 + 4) {
+            board.report_applied("01", seq_no);
+            board.report_attested("01", seq_no);
+        }
+        let status = board.status(false, &[], None, false, 0);
+        assert_eq!(status.threads[0].attestation_participation, Some(1.0));
+        assert_eq!(status.threads[1].role, ThreadRole::Observer);
+    }
+}
@@ -0,0 +1,223 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use salvo::async_trait;
+use salvo::http::StatusCode;
+use salvo::prelude::Json;
+use salvo::Depot;
+use salvo::FlowCtrl;
+use salvo::Handler;
+use salvo::Request;
+use salvo::Response;
+use serde::Serialize;
+
+use crate::ResolvingResult;
+use crate::WebServer;
+
+// Number of the latest produced blocks kept for all threads
+const OUT_MSG_QUEUES_CAPACITY: usize = 4096;
+
+/// Messages of the out queue addressed to one thread.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct OutMsgQueueEntry {
+    pub dst_thread_id: String,
+    pub count: usize,
+    /// Creation lt of the oldest message in the queue.
+    pub oldest_lt: u64,
+}
+
+/// Out message queue of the state produced by the block, grouped by the
+/// destination thread.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct OutMsgQueueRecord {
+    pub thread_id: String,
+    pub block_id: String,
+    pub block_seq_no: u32,
+    pub queues: Vec<OutMsgQueueEntry>,
+    /// Unix time (ms) of the block.
+    pub timestamp: u64,
+}
+
+/// Filter of the indexed records, the fields are optional.
+#[derive(Clone, Debug, Default)]
+pub struct OutMsgQueueFilter {
+    pub thread_id: Option<String>,
+    /// Keeps only the queues addressed to the thread.
+    pub dst_thread_id: Option<String>,
+    pub from_seq_no: Option<u32>,
+    pub to_seq_no: Option<u32>,
+}
+
+impl OutMsgQueueFilter {
+    fn matches(&self, record: &OutMsgQueueRecord) -> bool {
+        self.thread_id.as_ref().is_none_or(|thread_id| &record.thread_id == thread_id)
+            && self.from_seq_no.is_none_or(|from| record.block_seq_no >= from)
+            && self.to_seq_no.is_none_or(|to| record.block_seq_no <= to)
+    }
+}
+
+/// Shared between the block producers and the web server: producers index
+/// the out queues of the produced blocks, so the block where a cross-thread
+/// message stopped being enqueued (or was never dequeued) can be found.
+#[derive(Clone, Default)]
+pub struct OutMsgQueueIndex {
+    latest: Arc<parking_lot::RwLock<VecDeque<OutMsgQueueRecord>>>,
+}
+
+impl OutMsgQueueIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn publish(&self, record: OutMsgQueueRecord) {
+        let mut latest = self.latest.write();
+        if latest.len() == OUT_MSG_QUEUES_CAPACITY {
+            latest.pop_front();
+        }
+        latest.push_back(record);
+    }
+
+    /// Records from the newest to the oldest. With the destination filter
+    /// set, the records keep an empty queue list if nothing is addressed to
+    /// the thread, that shows where the queue was drained.
+    pub fn query(&self, filter: &OutMsgQueueFilter, limit: usize) -> Vec<OutMsgQueueRecord> {
+        self.latest
+            .read()
+            .iter()
+            .rev()
+            .filter(|record| filter.matches(record))
+            .take(limit)
+            .map(|record| {
+                let mut record = record.clone();
+                if let Some(dst_thread_id) = &filter.dst_thread_id {
+                    record.queues.retain(|queue| &queue.dst_thread_id == dst_thread_id);
+                }
+                record
+            })
+            .collect()
+    }
+}
+
+pub struct OutMsgQueuesHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>(
+    PhantomData<(TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter)>,
+);
+
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+    OutMsgQueuesHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+{
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[async_trait]
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter> Handler
+    for OutMsgQueuesHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+where
+    TMessage: Clone + Send + Sync + 'static + std::fmt::Debug,
+    TMsgConverter: Clone
+        + Send
+        + Sync
+        + 'static
+        + Fn(tvm_block::Message, [u8; 34]) -> anyhow::Result<TMessage>,
+    TBPResolver: Clone + Send + Sync + 'static + FnMut([u8; 34]) -> ResolvingResult,
+    TBocByAddrGetter:
+        Clone + Send + Sync + 'static + Fn(String) -> anyhow::Result<(String, Option<String>)>,
+    TSeqnoGetter: Clone + Send + Sync + 'static + Fn() -> anyhow::Result<u32>,
+{
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        _ctrl: &mut FlowCtrl,
+    ) {
+        let Ok(web_server) = depot.obtain::<WebServer<
+            TMessage,
+            TMsgConverter,
+            TBPResolver,
+            TBocByAddrGetter,
+            TSeqnoGetter,
+        >>() else {
+            res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+            res.render("Internal server error: Web Server state not found");
+            return;
+        };
+        let filter = OutMsgQueueFilter {
+            thread_id: req.query::<String>("thread_id").map(|thread_id| thread_id.to_lowercase()),
+            dst_thread_id: req
+                .query::<String>("dst_thread_id")
+                .map(|thread_id| thread_id.to_lowercase()),
+            from_seq_no: req.query::<u32>("from_seq_no"),
+            to_seq_no: req.query::<u32>("to_seq_no"),
+        };
+        let limit = req.query::<usize>("limit").unwrap_or(OUT_MSG_QUEUES_CAPACITY);
+        res.status_code(StatusCode::OK);
+        res.render(Json(web_server.out_msg_queues.query(&filter, limit)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(thread_id: &str, block_seq_no: u32, queues: &[(&str, usize)]) -> OutMsgQueueRecord {
+        OutMsgQueueRecord {
+            thread_id: thread_id.to_string(),
+            block_id: String::new(),
+            block_seq_no,
+            queues: queues
+                .iter()
+                .map(|(dst_thread_id, count)| OutMsgQueueEntry {
+                    dst_thread_id: dst_thread_id.to_string(),
+                    count: *count,
+                    oldest_lt: block_seq_no as u64,
+                })
+                .collect(),
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn test_out_msg_queue_index() {
+        let index = OutMsgQueueIndex::new();
+        for block_seq_no in 0..OUT_MSG_QUEUES_CAPACITY as u32 + 2 {
+            index.publish(record("01", block_seq_no, &[("01", 1), ("02", 2)]));
+        }
+        let all = index.query(&OutMsgQueueFilter::default(), usize::MAX);
+        assert_eq!(all.len(), OUT_MSG_QUEUES_CAPACITY);
+        assert_eq!(all[0].block_seq_no, OUT_MSG_QUEUES_CAPACITY as u32 + 1);
+        assert_eq!(all.last().unwrap().block_seq_no, 2);
+
+        index.publish(record("02", 10, &[("01", 3)]));
+        let filter = OutMsgQueueFilter {
+            thread_id: Some("01".to_string()),
+            dst_thread_id: Some("02".to_string()),
+            from_seq_no: Some(10),
+            to_seq_no: Some(11),
+        };
+        let found = index.query(&filter, usize::MAX);
+        assert_eq!(
+            found.iter().map(|record| record.block_seq_no).collect::<Vec<_>>(),
+            vec![11, 10]
+        );
+        assert!(found.iter().all(|record| record.queues
+            == vec![OutMsgQueueEntry {
+                dst_thread_id: "02".to_string(),
+                count: 2,
+                oldest_lt: record.block_seq_no as u64
+            }]));
+
+        // Drained queues are kept as empty records
+        let filter =
+            OutMsgQueueFilter { dst_thread_id: Some("03".to_string()), ..Default::default() };
+        let found = index.query(&filter, 1);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].thread_id, "02");
+        assert!(found[0].queues.is_empty());
+    }
+}
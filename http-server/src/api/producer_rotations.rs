@@ -0,0 +1,192 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use salvo::async_trait;
+use salvo::http::StatusCode;
+use salvo::prelude::Json;
+use salvo::Depot;
+use salvo::FlowCtrl;
+use salvo::Handler;
+use salvo::Request;
+use salvo::Response;
+use serde::Serialize;
+
+use crate::ResolvingResult;
+use crate::WebServer;
+
+// Number of the latest rotations kept for all threads
+const PRODUCER_ROTATIONS_CAPACITY: usize = 1024;
+
+/// Cause of a producer rotation.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProducerRotationTrigger {
+    /// The producer was replaced in an authority switch round.
+    AuthoritySwitch,
+    /// The producer selector was reseeded after the BK set change.
+    BkSetChange,
+    /// The next producer took over after the block gap.
+    ProducerChange,
+}
+
+/// Finalized block produced by a producer other than the producer of its
+/// parent.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ProducerRotationEvent {
+    pub thread_id: String,
+    pub block_id: String,
+    pub block_seq_no: u32,
+    pub previous_producer: String,
+    pub new_producer: String,
+    /// Time (ms) between the parent block and the block.
+    pub gap_ms: u64,
+    /// Number of the blocks that were expected to be produced in the gap.
+    pub blocks_missed: u64,
+    pub trigger: ProducerRotationTrigger,
+    /// Round of the block, non zero after authority switches.
+    pub round: u64,
+    /// Producer schedule offset of the new producer.
+    pub new_selector_index: usize,
+    /// Unix time (ms) of the block.
+    pub timestamp: u64,
+}
+
+/// Shared between the finalization loops and the web server: the loops
+/// publish rotations of the finalized blocks, the web server serves the
+/// recent history.
+#[derive(Clone, Default)]
+pub struct ProducerRotationFeed {
+    latest: Arc<parking_lot::RwLock<VecDeque<ProducerRotationEvent>>>,
+}
+
+impl ProducerRotationFeed {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn publish(&self, event: ProducerRotationEvent) {
+        let mut latest = self.latest.write();
+        if latest.len() == PRODUCER_ROTATIONS_CAPACITY {
+            latest.pop_front();
+        }
+        latest.push_back(event);
+    }
+
+    /// Rotations from the newest to the oldest.
+    pub fn recent(&self, thread_id: Option<&str>, limit: usize) -> Vec<ProducerRotationEvent> {
+        self.latest
+            .read()
+            .iter()
+            .rev()
+            .filter(|event| thread_id.is_none_or(|thread_id| event.thread_id == thread_id))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+pub struct ProducerRotationsHandler<
+    TMessage,
+    TMsgConverter,
+    TBPResolver,
+    TBocByAddrGetter,
+    TSeqnoGetter,
+>(PhantomData<(TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter)>);
+
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+    ProducerRotationsHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+{
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[async_trait]
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter> Handler
+    for ProducerRotationsHandler<
+        TMessage,
+        TMsgConverter,
+        TBPResolver,
+        TBocByAddrGetter,
+        TSeqnoGetter,
+    >
+where
+    TMessage: Clone + Send + Sync + 'static + std::fmt::Debug,
+    TMsgConverter: Clone
+        + Send
+        + Sync
+        + 'static
+        + Fn(tvm_block::Message, [u8; 34]) -> anyhow::Result<TMessage>,
+    TBPResolver: Clone + Send + Sync + 'static + FnMut([u8; 34]) -> ResolvingResult,
+    TBocByAddrGetter:
+        Clone + Send + Sync + 'static + Fn(String) -> anyhow::Result<(String, Option<String>)>,
+    TSeqnoGetter: Clone + Send + Sync + 'static + Fn() -> anyhow::Result<u32>,
+{
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        _ctrl: &mut FlowCtrl,
+    ) {
+        let Ok(web_server) = depot.obtain::<WebServer<
+            TMessage,
+            TMsgConverter,
+            TBPResolver,
+            TBocByAddrGetter,
+            TSeqnoGetter,
+        >>() else {
+            res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+            res.render("Internal server error: Web Server state not found");
+            return;
+        };
+        let thread_id = req.query::<String>("thread_id").map(|thread_id| thread_id.to_lowercase());
+        let limit = req.query::<usize>("limit").unwrap_or(PRODUCER_ROTATIONS_CAPACITY);
+        res.status_code(StatusCode::OK);
+        res.render(Json(web_server.producer_rotations.recent(thread_id.as_deref(), limit)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(thread_id: &str, block_seq_no: u32) -> ProducerRotationEvent {
+        ProducerRotationEvent {
+            thread_id: thread_id.to_string(),
+            block_id: String::new(),
+            block_seq_no,
+            previous_producer: "1".to_string(),
+            new_producer: "2".to_string(),
+            gap_ms: 0,
+            blocks_missed: 0,
+            trigger: ProducerRotationTrigger::ProducerChange,
+            round: 0,
+            new_selector_index: 0,
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn test_producer_rotation_feed() {
+        let feed = ProducerRotationFeed::new();
+        for block_seq_no in 0..PRODUCER_ROTATIONS_CAPACITY as u32 + 2 {
+            let thread_id = if block_seq_no % 2 == 0 { "01" } else { "02" };
+            feed.publish(event(thread_id, block_seq_no));
+        }
+        let recent = feed.recent(None, usize::MAX);
+        assert_eq!(recent.len(), PRODUCER_ROTATIONS_CAPACITY);
+        assert_eq!(recent[0].block_seq_no, PRODUCER_ROTATIONS_CAPACITY as u32 + 1);
+        assert_eq!(recent.last().unwrap().block_seq_no, 2);
+
+        let recent = feed.recent(Some("01"), 2);
+        assert_eq!(
+            recent.iter().map(|event| event.block_seq_no).collect::<Vec<_>>(),
+            vec![PRODUCER_ROTATIONS_CAPACITY as u32, PRODUCER_ROTATIONS_CAPACITY as u32 - 2]
+        );
+    }
+}
@@ -0,0 +1,170 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::BTreeMap;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use salvo::async_trait;
+use salvo::http::StatusCode;
+use salvo::prelude::Json;
+use salvo::Depot;
+use salvo::FlowCtrl;
+use salvo::Handler;
+use salvo::Request;
+use salvo::Response;
+use serde::Serialize;
+
+use crate::ResolvingResult;
+use crate::WebServer;
+
+// Number of the latest reports kept
+const PRODUCTION_STALLS_CAPACITY: usize = 32;
+
+/// Diagnostics captured when a block production iteration stalled.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ProductionStallReport {
+    pub thread_id: String,
+    /// Block the stalled iteration produces on top of.
+    pub parent_block_seq_no: u32,
+    /// Duration (ms) of the iteration at the moment of the capture.
+    pub iteration_time_ms: u64,
+    /// Block production timeout (ms) of the iteration.
+    pub production_timeout_ms: u64,
+    /// Stack of the producer thread at its first block builder checkpoint
+    /// after the stall, empty if it was not captured.
+    pub stack: Vec<String>,
+    /// Number of external messages waiting in the thread queue.
+    pub ext_msg_queue_depth: Option<usize>,
+    /// Latency (ms) of the repository reads made at the moment of the
+    /// capture. None means the read didn't finish in time.
+    pub repository_latency_ms: BTreeMap<String, Option<u64>>,
+    /// Unix time (ms) of the capture.
+    pub timestamp: u64,
+}
+
+/// Shared between the production watchdog and the web server: the watchdog
+/// publishes stall reports, the web server shows the latest ones.
+#[derive(Clone, Default)]
+pub struct ProductionStallFeed {
+    latest: Arc<parking_lot::RwLock<VecDeque<ProductionStallReport>>>,
+}
+
+impl ProductionStallFeed {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn publish(&self, report: ProductionStallReport) {
+        let mut latest = self.latest.write();
+        if latest.len() == PRODUCTION_STALLS_CAPACITY {
+            latest.pop_front();
+        }
+        latest.push_back(report);
+    }
+
+    /// Reports from the newest to the oldest.
+    pub fn snapshot(&self) -> Vec<ProductionStallReport> {
+        self.latest.read().iter().rev().cloned().collect()
+    }
+}
+
+pub struct ProductionStallsHandler<
+    TMessage,
+    TMsgConverter,
+    TBPResolver,
+    TBocByAddrGetter,
+    TSeqnoGetter,
+>(PhantomData<(TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter)>);
+
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+    ProductionStallsHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+{
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[async_trait]
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter> Handler
+    for ProductionStallsHandler<
+        TMessage,
+        TMsgConverter,
+        TBPResolver,
+        TBocByAddrGetter,
+        TSeqnoGetter,
+    >
+where
+    TMessage: Clone + Send + Sync + 'static + std::fmt::Debug,
+    TMsgConverter: Clone
+        + Send
+        + Sync
+        + 'static
+        + Fn(tvm_block::Message, [u8; 34]) -> anyhow::Result<TMessage>,
+    TBPResolver: Clone + Send + Sync + 'static + FnMut([u8; 34]) -> ResolvingResult,
+    TBocByAddrGetter:
+        Clone + Send + Sync + 'static + Fn(String) -> anyhow::Result<(String, Option<String>)>,
+    TSeqnoGetter: Clone + Send + Sync + 'static + Fn() -> anyhow::Result<u32>,
+{
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        _ctrl: &mut FlowCtrl,
+    ) {
+        let Ok(web_server) = depot.obtain::<WebServer<
+            TMessage,
+            TMsgConverter,
+            TBPResolver,
+            TBocByAddrGetter,
+            TSeqnoGetter,
+        >>() else {
+            res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+            res.render("Internal server error: Web Server state not found");
+            return;
+        };
+        let thread_id = req.query::<String>("thread_id").map(|thread_id| thread_id.to_lowercase());
+        let result = web_server
+            .production_stalls
+            .snapshot()
+            .into_iter()
+            .filter(|report| {
+                thread_id.as_ref().is_none_or(|thread_id| *thread_id == report.thread_id)
+            })
+            .collect::<Vec<_>>();
+        res.status_code(StatusCode::OK);
+        res.render(Json(result));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(thread_id: &str, timestamp: u64) -> ProductionStallReport {
+        ProductionStallReport {
+            thread_id: thread_id.to_string(),
+            parent_block_seq_no: 0,
+            iteration_time_ms: 0,
+            production_timeout_ms: 0,
+            stack: vec![],
+            ext_msg_queue_depth: None,
+            repository_latency_ms: BTreeMap::new(),
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn test_production_stall_feed() {
+        let feed = ProductionStallFeed::new();
+        for timestamp in 0..PRODUCTION_STALLS_CAPACITY as u64 + 2 {
+            feed.publish(report("01", timestamp));
+        }
+        let snapshot = feed.snapshot();
+        assert_eq!(snapshot.len(), PRODUCTION_STALLS_CAPACITY);
+        assert_eq!(snapshot[0].timestamp, PRODUCTION_STALLS_CAPACITY as u64 + 1);
+        assert_eq!(snapshot.last().unwrap().timestamp, 2);
+    }
+}
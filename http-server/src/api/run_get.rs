@@ -0,0 +1,156 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::marker::PhantomData;
+
+use salvo::prelude::*;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+
+use crate::ApiError;
+use crate::ResolvingResult;
+use crate::WebServer;
+
+/// State the get-method is run against.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RunGetState {
+    /// The latest state applied by the node, it may be not finalized yet
+    #[default]
+    Optimistic,
+    Finalized,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct RunGetParams {
+    pub address: String,
+    pub method: String,
+    /// Get-method arguments: a value or an array of values in the stack
+    /// order
+    #[serde(default)]
+    pub input: Option<serde_json::Value>,
+    #[serde(default)]
+    pub state: RunGetState,
+}
+
+/// Request to the node to run a get-method of the account.
+pub struct RunGetRequest {
+    pub params: RunGetParams,
+    pub response: oneshot::Sender<anyhow::Result<RunGetResult>>,
+}
+
+pub type RunGetRequestSender = mpsc::Sender<RunGetRequest>;
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct RunGetResult {
+    pub address: String,
+    pub method: String,
+    pub state: RunGetState,
+    /// Seq_no of the block the state belongs to
+    pub block_seq_no: u32,
+    /// Decoded result stack
+    pub output: serde_json::Value,
+}
+
+pub struct RunGetHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>(
+    PhantomData<(TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter)>,
+);
+
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+    RunGetHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+{
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[async_trait]
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter> Handler
+    for RunGetHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+where
+    TMessage: Clone + Send + Sync + 'static + std::fmt::Debug,
+    TMsgConverter: Clone
+        + Send
+        + Sync
+        + 'static
+        + Fn(tvm_block::Message, [u8; 34]) -> anyhow::Result<TMessage>,
+    TBPResolver: Clone + Send + Sync + 'static + FnMut([u8; 34]) -> ResolvingResult,
+    TBocByAddrGetter:
+        Clone + Send + Sync + 'static + Fn(String) -> anyhow::Result<(String, Option<String>)>,
+    TSeqnoGetter: Clone + Send + Sync + 'static + Fn() -> anyhow::Result<u32>,
+{
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        _ctrl: &mut FlowCtrl,
+    ) {
+        let mut params = match req.parse_json::<RunGetParams>().await {
+            Ok(params) => params,
+            Err(e) => {
+                ApiError::new("BAD_REQUEST", format!("Invalid request: {e}"), false)
+                    .render(res, StatusCode::BAD_REQUEST);
+                return;
+            }
+        };
+        params.address = params.address.trim_start_matches("0:").to_string();
+        if params.address.is_empty() || params.method.is_empty() {
+            ApiError::new("BAD_REQUEST", "Address and method are required", false)
+                .render(res, StatusCode::BAD_REQUEST);
+            return;
+        }
+
+        let Ok(web_server) = depot.obtain::<WebServer<
+            TMessage,
+            TMsgConverter,
+            TBPResolver,
+            TBocByAddrGetter,
+            TSeqnoGetter,
+        >>() else {
+            res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+            res.render("Internal server error: Web Server state not found");
+            return;
+        };
+
+        let (response, response_rx) = oneshot::channel();
+        let result = match web_server
+            .run_get_request_sender
+            .send(RunGetRequest { params, response })
+            .await
+        {
+            Ok(()) => response_rx
+                .await
+                .unwrap_or_else(|_| Err(anyhow::format_err!("Run get request dropped"))),
+            Err(_) => Err(anyhow::format_err!("Run get service is not running")),
+        };
+        match result {
+            Ok(result) => res.render(Json(result)),
+            Err(e) => {
+                ApiError::from_anyhow(&e, "RUN_GET_FAILED").render(res, StatusCode::BAD_REQUEST);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_get_params() {
+        let params: RunGetParams =
+            serde_json::from_str(r#"{"address": "0:00", "method": "seqno"}"#).unwrap();
+        assert_eq!(params.state, RunGetState::Optimistic);
+        assert_eq!(params.input, None);
+
+        let params: RunGetParams = serde_json::from_str(
+            r#"{"address": "00", "method": "get_balance", "input": ["1"], "state": "finalized"}"#,
+        )
+        .unwrap();
+        assert_eq!(params.state, RunGetState::Finalized);
+        assert!(serde_json::from_str::<RunGetParams>(r#"{"address": "00"}"#).is_err());
+    }
+}
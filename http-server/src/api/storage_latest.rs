@@ -4,6 +4,12 @@
 use std::path::PathBuf;
 
 use salvo::prelude::*;
+
+// Chunk manifests are published next to the shared states
+const SHARED_STATE_MANIFEST_SUFFIX: &str = ".manifest";
+// Temporary files and staging dirs of the shares
+const TEMPORARY_FILE_PREFIX: &str = "_";
+
 // pub type GetBlockFn = Arc<dyn Fn(Vec<u8>) -> anyhow::Result<Vec<u8>> + Send + Sync>;
 
 pub struct StorageLatestHandler {
@@ -38,9 +44,15 @@ impl Handler for StorageLatestHandler {
     }
 }
 
+// Returns the id of the latest shared state
 fn get_latest_file(storage_dir: &PathBuf) -> anyhow::Result<String> {
     match std::fs::read_dir(storage_dir)?
         .flatten()
+        .filter(|e| {
+            let file_name = e.file_name().to_string_lossy().to_string();
+            !file_name.starts_with(TEMPORARY_FILE_PREFIX)
+                && !file_name.ends_with(SHARED_STATE_MANIFEST_SUFFIX)
+        })
         .filter_map(|e| match e.metadata() {
             Ok(metadata) if metadata.is_file() => Some((e.file_name(), metadata.modified())),
             _ => None,
@@ -0,0 +1,191 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use salvo::async_trait;
+use salvo::http::StatusCode;
+use salvo::Depot;
+use salvo::FlowCtrl;
+use salvo::Handler;
+use salvo::Request;
+use salvo::Response;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::api::event_feed::stream_events;
+use crate::api::event_feed::EventFeed;
+use crate::api::event_feed::FeedEvent;
+use crate::ResolvingResult;
+use crate::WebServer;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ThreadLoadDecision {
+    ContinueAsIs,
+    Split,
+    Collapse,
+}
+
+/// Load of a thread as seen by the load balancing service.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ThreadLoadUpdate {
+    pub thread_id: String,
+    /// Block the update was produced for.
+    pub block_id: String,
+    /// Unix time (ms) of the update.
+    pub timestamp: u64,
+    /// Internal messages queue length summed over the sliding window.
+    pub queue_length: usize,
+    /// Number of transactions in the thread accounts over the sliding window.
+    pub accounts_load: i64,
+    pub window_size: usize,
+    /// The load is not compared with the threshold until the window is filled.
+    pub window_filled: bool,
+    pub load_threshold: usize,
+    /// `queue_length / load_threshold`, split is considered at 1.0.
+    pub threshold_proximity: f64,
+    /// Set when the update was published by the split check.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decision: Option<ThreadLoadDecision>,
+}
+
+impl FeedEvent for ThreadLoadUpdate {
+    const NAME: &'static str = "thread_load";
+    // The feed replays the last known load of every thread instead
+    const RECENT_CAPACITY: usize = 0;
+
+    fn thread_id(&self) -> &str {
+        &self.thread_id
+    }
+}
+
+/// Shared between the load balancing service and the web server: the
+/// service publishes thread load updates, the web server streams them.
+#[derive(Clone)]
+pub struct ThreadLoadFeed {
+    feed: EventFeed<ThreadLoadUpdate>,
+    latest: Arc<parking_lot::RwLock<HashMap<String, ThreadLoadUpdate>>>,
+}
+
+impl Default for ThreadLoadFeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ThreadLoadFeed {
+    pub fn new() -> Self {
+        Self { feed: EventFeed::new(), latest: Arc::new(parking_lot::RwLock::new(HashMap::new())) }
+    }
+
+    pub fn publish(&self, update: ThreadLoadUpdate) {
+        self.latest.write().insert(update.thread_id.clone(), update.clone());
+        self.feed.publish(update);
+    }
+
+    pub fn remove_thread(&self, thread_id: &str) {
+        self.latest.write().remove(thread_id);
+    }
+
+    /// Returns the last known load of every thread and a receiver of
+    /// the following updates.
+    pub fn subscribe(&self) -> (Vec<ThreadLoadUpdate>, broadcast::Receiver<ThreadLoadUpdate>) {
+        let latest = self.latest.read();
+        let (_, receiver) = self.feed.subscribe(|_| false);
+        let mut snapshot = latest.values().cloned().collect::<Vec<_>>();
+        snapshot.sort_by(|a, b| a.thread_id.cmp(&b.thread_id));
+        (snapshot, receiver)
+    }
+}
+
+pub struct ThreadLoadHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>(
+    PhantomData<(TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter)>,
+);
+
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+    ThreadLoadHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+{
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[async_trait]
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter> Handler
+    for ThreadLoadHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+where
+    TMessage: Clone + Send + Sync + 'static + std::fmt::Debug,
+    TMsgConverter: Clone
+        + Send
+        + Sync
+        + 'static
+        + Fn(tvm_block::Message, [u8; 34]) -> anyhow::Result<TMessage>,
+    TBPResolver: Clone + Send + Sync + 'static + FnMut([u8; 34]) -> ResolvingResult,
+    TBocByAddrGetter:
+        Clone + Send + Sync + 'static + Fn(String) -> anyhow::Result<(String, Option<String>)>,
+    TSeqnoGetter: Clone + Send + Sync + 'static + Fn() -> anyhow::Result<u32>,
+{
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        _ctrl: &mut FlowCtrl,
+    ) {
+        let Ok(web_server) = depot.obtain::<WebServer<
+            TMessage,
+            TMsgConverter,
+            TBPResolver,
+            TBocByAddrGetter,
+            TSeqnoGetter,
+        >>() else {
+            res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+            res.render("Internal server error: Web Server state not found");
+            return;
+        };
+        let thread_id = req.query::<String>("thread_id").map(|thread_id| thread_id.to_lowercase());
+
+        let (snapshot, receiver) = web_server.thread_load.subscribe();
+        stream_events(snapshot, receiver, thread_id, res);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(thread_id: &str, queue_length: usize) -> ThreadLoadUpdate {
+        ThreadLoadUpdate {
+            thread_id: thread_id.to_string(),
+            block_id: String::new(),
+            timestamp: 0,
+            queue_length,
+            accounts_load: 0,
+            window_size: 10,
+            window_filled: true,
+            load_threshold: 100,
+            threshold_proximity: queue_length as f64 / 100.0,
+            decision: None,
+        }
+    }
+
+    #[test]
+    fn test_thread_load_feed() {
+        let feed = ThreadLoadFeed::new();
+        feed.publish(update("02", 10));
+        feed.publish(update("01", 20));
+        feed.publish(update("02", 30));
+
+        let (snapshot, mut receiver) = feed.subscribe();
+        assert_eq!(snapshot, vec![update("01", 20), update("02", 30)]);
+
+        feed.publish(update("01", 40));
+        assert_eq!(receiver.try_recv().unwrap(), update("01", 40));
+
+        feed.remove_thread("01");
+        assert_eq!(feed.subscribe().0, vec![update("02", 30)]);
+    }
+}
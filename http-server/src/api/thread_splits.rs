@@ -0,0 +1,249 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::Duration;
+
+use salvo::async_trait;
+use salvo::http::StatusCode;
+use salvo::prelude::Json;
+use salvo::Depot;
+use salvo::FlowCtrl;
+use salvo::Handler;
+use salvo::Request;
+use salvo::Response;
+use serde::Serialize;
+
+use crate::ApiError;
+use crate::ResolvingResult;
+use crate::WebServer;
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ThreadsTableRowPreview {
+    /// Meaningful bits of the account routing, e.g. `account[3]=1`.
+    pub bitmask: String,
+    pub thread_id: String,
+}
+
+/// Split proposed by the load balancing service and what it would change.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ThreadSplitPreview {
+    pub thread_id: String,
+    /// Block the split was proposed for.
+    pub block_id: String,
+    pub current_bitmask: String,
+    /// Bitmask of the spawned thread.
+    pub proposed_bitmask: String,
+    pub new_thread_id: String,
+    pub proposed_threads_table: Vec<ThreadsTableRowPreview>,
+    /// Internal messages queue length summed over the sliding window.
+    pub queue_length: usize,
+    /// Transactions of the thread accounts over the sliding window that stay
+    /// in the thread after the split.
+    pub expected_kept_load: i64,
+    /// Transactions of the thread accounts over the sliding window that move
+    /// to the spawned thread.
+    pub expected_moved_load: i64,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct PendingThreadSplit {
+    #[serde(flatten)]
+    pub preview: ThreadSplitPreview,
+    /// Unix time (ms) of the first proposal.
+    pub proposed_at: u64,
+    /// Unix time (ms) the split is approved at without an operator.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_approve_at: Option<u64>,
+    pub approved: bool,
+}
+
+/// Shared between the load balancing service and the web server: the service
+/// holds proposed splits until an operator approves them (or the auto approve
+/// delay passes), the web server lists, approves and rejects them. Only the
+/// splits of the blocks produced by this node are held.
+#[derive(Clone)]
+pub struct ThreadSplitGate {
+    auto_approve_delay: Option<Duration>,
+    pending: Arc<parking_lot::RwLock<HashMap<String, PendingThreadSplit>>>,
+}
+
+impl ThreadSplitGate {
+    pub fn new(auto_approve_delay: Option<Duration>) -> Self {
+        Self { auto_approve_delay, pending: Arc::new(parking_lot::RwLock::new(HashMap::new())) }
+    }
+
+    /// Returns true if the split may be applied. Otherwise the split is held
+    /// and its preview is updated, the service proposes it again with the
+    /// next block of the thread.
+    pub fn review(&self, preview: ThreadSplitPreview, now_ms: u64) -> bool {
+        let mut pending = self.pending.write();
+        let thread_id = preview.thread_id.clone();
+        let split = pending.entry(thread_id.clone()).or_insert_with(|| PendingThreadSplit {
+            preview: preview.clone(),
+            proposed_at: now_ms,
+            auto_approve_at: self
+                .auto_approve_delay
+                .map(|delay| now_ms.saturating_add(delay.as_millis() as u64)),
+            approved: false,
+        });
+        if split.approved || split.auto_approve_at.is_some_and(|at| at <= now_ms) {
+            pending.remove(&thread_id);
+            return true;
+        }
+        split.preview = preview;
+        false
+    }
+
+    pub fn pending(&self) -> Vec<PendingThreadSplit> {
+        let mut pending = self.pending.read().values().cloned().collect::<Vec<_>>();
+        pending.sort_by(|a, b| a.preview.thread_id.cmp(&b.preview.thread_id));
+        pending
+    }
+
+    /// Returns false if there is no split held for the thread.
+    pub fn approve(&self, thread_id: &str) -> bool {
+        self.pending.write().get_mut(thread_id).map(|split| split.approved = true).is_some()
+    }
+
+    /// Drops the held split. It is proposed again if the thread load stays
+    /// above the threshold.
+    pub fn reject(&self, thread_id: &str) -> bool {
+        self.pending.write().remove(thread_id).is_some()
+    }
+
+    pub fn remove_thread(&self, thread_id: &str) {
+        self.pending.write().remove(thread_id);
+    }
+}
+
+pub struct ThreadSplitsHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>(
+    PhantomData<(TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter)>,
+);
+
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+    ThreadSplitsHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+{
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[async_trait]
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter> Handler
+    for ThreadSplitsHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+where
+    TMessage: Clone + Send + Sync + 'static + std::fmt::Debug,
+    TMsgConverter: Clone
+        + Send
+        + Sync
+        + 'static
+        + Fn(tvm_block::Message, [u8; 34]) -> anyhow::Result<TMessage>,
+    TBPResolver: Clone + Send + Sync + 'static + FnMut([u8; 34]) -> ResolvingResult,
+    TBocByAddrGetter:
+        Clone + Send + Sync + 'static + Fn(String) -> anyhow::Result<(String, Option<String>)>,
+    TSeqnoGetter: Clone + Send + Sync + 'static + Fn() -> anyhow::Result<u32>,
+{
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        _ctrl: &mut FlowCtrl,
+    ) {
+        let Ok(web_server) = depot.obtain::<WebServer<
+            TMessage,
+            TMsgConverter,
+            TBPResolver,
+            TBocByAddrGetter,
+            TSeqnoGetter,
+        >>() else {
+            res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+            res.render("Internal server error: Web Server state not found");
+            return;
+        };
+        let Some(gate) = web_server.thread_splits.as_ref() else {
+            ApiError::new(
+                "THREAD_SPLIT_APPROVAL_DISABLED",
+                "Splits are applied immediately",
+                false,
+            )
+            .render(res, StatusCode::NOT_FOUND);
+            return;
+        };
+        // POST approves or rejects the held split, GET only lists them
+        if req.method() == salvo::http::Method::POST {
+            let thread_id = req.query::<String>("thread_id").unwrap_or_default().to_lowercase();
+            let found = match req.query::<String>("action").as_deref() {
+                Some("approve") => gate.approve(&thread_id),
+                Some("reject") => gate.reject(&thread_id),
+                _ => {
+                    ApiError::new("INVALID_ACTION", "action must be approve or reject", false)
+                        .render(res, StatusCode::BAD_REQUEST);
+                    return;
+                }
+            };
+            if !found {
+                ApiError::new(
+                    "THREAD_SPLIT_NOT_FOUND",
+                    format!("No split is held for thread {thread_id}"),
+                    false,
+                )
+                .render(res, StatusCode::NOT_FOUND);
+                return;
+            }
+        }
+        res.status_code(StatusCode::OK);
+        res.render(Json(gate.pending()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preview(thread_id: &str, block_id: &str) -> ThreadSplitPreview {
+        ThreadSplitPreview {
+            thread_id: thread_id.to_string(),
+            block_id: block_id.to_string(),
+            current_bitmask: "*".to_string(),
+            proposed_bitmask: "account[0]=1".to_string(),
+            new_thread_id: format!("{block_id}0000"),
+            proposed_threads_table: vec![],
+            queue_length: 100,
+            expected_kept_load: 60,
+            expected_moved_load: 40,
+        }
+    }
+
+    #[test]
+    fn test_manual_approval() {
+        let gate = ThreadSplitGate::new(None);
+        assert!(!gate.review(preview("01", "aa"), 0));
+        assert!(!gate.review(preview("01", "bb"), u64::MAX));
+        let pending = gate.pending();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].preview, preview("01", "bb"));
+        assert_eq!(pending[0].proposed_at, 0);
+
+        assert!(!gate.approve("02"));
+        assert!(gate.approve("01"));
+        assert!(gate.review(preview("01", "cc"), 1));
+        assert!(gate.pending().is_empty());
+
+        assert!(!gate.review(preview("01", "dd"), 2));
+        assert!(gate.reject("01"));
+        assert!(!gate.reject("01"));
+    }
+
+    #[test]
+    fn test_auto_approval() {
+        let gate = ThreadSplitGate::new(Some(Duration::from_secs(60)));
+        assert!(!gate.review(preview("01", "aa"), 1000));
+        assert_eq!(gate.pending()[0].auto_approve_at, Some(61000));
+        assert!(!gate.review(preview("01", "bb"), 60999));
+        assert!(gate.review(preview("01", "cc"), 61000));
+    }
+}
@@ -0,0 +1,247 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use salvo::async_trait;
+use salvo::http::StatusCode;
+use salvo::prelude::Json;
+use salvo::Depot;
+use salvo::FlowCtrl;
+use salvo::Handler;
+use salvo::Request;
+use salvo::Response;
+use serde::Serialize;
+
+use crate::ResolvingResult;
+use crate::WebServer;
+
+// Trace requests for messages that were not executed during this time are dropped
+const TRACE_REQUEST_TTL: Duration = Duration::from_secs(60);
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TxTraceStatus {
+    /// Message execution will be traced, trace can be retrieved by the message hash.
+    Accepted,
+    NotSampled,
+    RateLimited,
+    Disabled,
+}
+
+/// Execution traces requested by API clients for their external messages.
+#[derive(Clone)]
+pub struct TxTraceRegistry {
+    inner: Arc<parking_lot::Mutex<TxTraceRegistryInner>>,
+    sample_rate: f64,
+    max_per_minute: u32,
+    capacity: usize,
+}
+
+struct TxTraceRegistryInner {
+    requested: HashMap<String, Instant>,
+    traces: HashMap<String, serde_json::Value>,
+    traces_order: VecDeque<String>,
+    window_start: Instant,
+    window_count: u32,
+}
+
+impl TxTraceRegistry {
+    pub fn new(sample_rate: f64, max_per_minute: u32, capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(parking_lot::Mutex::new(TxTraceRegistryInner {
+                requested: HashMap::new(),
+                traces: HashMap::new(),
+                traces_order: VecDeque::new(),
+                window_start: Instant::now(),
+                window_count: 0,
+            })),
+            sample_rate: sample_rate.clamp(0.0, 1.0),
+            max_per_minute,
+            capacity,
+        }
+    }
+
+    /// Requests execution trace for the message with the given hash (hex).
+    pub fn request(&self, message_hash: &str) -> TxTraceStatus {
+        if self.max_per_minute == 0 || self.capacity == 0 {
+            return TxTraceStatus::Disabled;
+        }
+        if !self.is_sampled(message_hash) {
+            return TxTraceStatus::NotSampled;
+        }
+        let mut inner = self.inner.lock();
+        let now = Instant::now();
+        inner
+            .requested
+            .retain(|_, requested_at| now.duration_since(*requested_at) < TRACE_REQUEST_TTL);
+        if inner.requested.contains_key(message_hash) {
+            return TxTraceStatus::Accepted;
+        }
+        if now.duration_since(inner.window_start) >= RATE_LIMIT_WINDOW {
+            inner.window_start = now;
+            inner.window_count = 0;
+        }
+        if inner.window_count >= self.max_per_minute {
+            return TxTraceStatus::RateLimited;
+        }
+        inner.window_count += 1;
+        inner.requested.insert(message_hash.to_string(), now);
+        TxTraceStatus::Accepted
+    }
+
+    /// Returns true once if the message execution should be traced.
+    pub fn take_request(&self, message_hash: &str) -> bool {
+        if self.max_per_minute == 0 {
+            return false;
+        }
+        self.inner.lock().requested.remove(message_hash).is_some()
+    }
+
+    pub fn store(&self, message_hash: String, trace: serde_json::Value) {
+        let mut inner = self.inner.lock();
+        if inner.traces.insert(message_hash.clone(), trace).is_none() {
+            inner.traces_order.push_back(message_hash);
+        }
+        while inner.traces_order.len() > self.capacity {
+            if let Some(oldest) = inner.traces_order.pop_front() {
+                inner.traces.remove(&oldest);
+            }
+        }
+    }
+
+    pub fn get(&self, message_hash: &str) -> Option<serde_json::Value> {
+        self.inner.lock().traces.get(message_hash).cloned()
+    }
+
+    // Sampling depends on the message hash only, so resending the same message
+    // gets the same decision.
+    fn is_sampled(&self, message_hash: &str) -> bool {
+        if self.sample_rate >= 1.0 {
+            return true;
+        }
+        let prefix = message_hash.get(..16).and_then(|s| u64::from_str_radix(s, 16).ok());
+        match prefix {
+            Some(prefix) => (prefix as f64) < self.sample_rate * (u64::MAX as f64),
+            None => false,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct TxTraceResult {
+    message_hash: String,
+    trace: serde_json::Value,
+}
+
+pub struct TxTraceHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>(
+    PhantomData<TMessage>,
+    PhantomData<TMsgConverter>,
+    PhantomData<TBPResolver>,
+    PhantomData<TBocByAddrGetter>,
+    PhantomData<TSeqnoGetter>,
+);
+
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+    TxTraceHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+{
+    pub fn new() -> Self {
+        Self(PhantomData, PhantomData, PhantomData, PhantomData, PhantomData)
+    }
+}
+
+#[async_trait]
+impl<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter> Handler
+    for TxTraceHandler<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSeqnoGetter>
+where
+    TMessage: Clone + Send + Sync + 'static + std::fmt::Debug,
+    TMsgConverter: Clone
+        + Send
+        + Sync
+        + 'static
+        + Fn(tvm_block::Message, [u8; 34]) -> anyhow::Result<TMessage>,
+    TBPResolver: Clone + Send + Sync + 'static + FnMut([u8; 34]) -> ResolvingResult,
+    TBocByAddrGetter:
+        Clone + Send + Sync + 'static + Fn(String) -> anyhow::Result<(String, Option<String>)>,
+    TSeqnoGetter: Clone + Send + Sync + 'static + Fn() -> anyhow::Result<u32>,
+{
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        _ctrl: &mut FlowCtrl,
+    ) {
+        let Ok(web_server_state) = depot.obtain::<WebServer<
+            TMessage,
+            TMsgConverter,
+            TBPResolver,
+            TBocByAddrGetter,
+            TSeqnoGetter,
+        >>() else {
+            res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+            return;
+        };
+        let Some(message_hash) = req.param::<String>("message_hash") else {
+            res.status_code(StatusCode::BAD_REQUEST);
+            return;
+        };
+        let message_hash = message_hash.to_lowercase();
+        match web_server_state.tx_traces.get(&message_hash) {
+            Some(trace) => {
+                res.status_code(StatusCode::OK);
+                res.render(Json(TxTraceResult { message_hash, trace }));
+            }
+            None => {
+                res.status_code(StatusCode::NOT_FOUND);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH_1: &str = "0000000000000001000000000000000000000000000000000000000000000000";
+    const HASH_2: &str = "0000000000000002000000000000000000000000000000000000000000000000";
+    const HASH_3: &str = "0000000000000003000000000000000000000000000000000000000000000000";
+
+    #[test]
+    fn test_request_rate_limit() {
+        let registry = TxTraceRegistry::new(1.0, 2, 10);
+        assert_eq!(registry.request(HASH_1), TxTraceStatus::Accepted);
+        assert_eq!(registry.request(HASH_1), TxTraceStatus::Accepted);
+        assert_eq!(registry.request(HASH_2), TxTraceStatus::Accepted);
+        assert_eq!(registry.request(HASH_3), TxTraceStatus::RateLimited);
+
+        assert!(registry.take_request(HASH_1));
+        assert!(!registry.take_request(HASH_1));
+        assert!(!registry.take_request(HASH_3));
+    }
+
+    #[test]
+    fn test_request_sampling() {
+        assert_eq!(TxTraceRegistry::new(0.0, 10, 10).request(HASH_1), TxTraceStatus::NotSampled);
+        assert_eq!(TxTraceRegistry::new(1.0, 0, 10).request(HASH_1), TxTraceStatus::Disabled);
+        let registry = TxTraceRegistry::new(0.5, 10, 10);
+        assert_eq!(registry.request(HASH_1), TxTraceStatus::Accepted);
+        assert_eq!(registry.request(&format!("f{}", &HASH_1[1..])), TxTraceStatus::NotSampled);
+    }
+
+    #[test]
+    fn test_store_capacity() {
+        let registry = TxTraceRegistry::new(1.0, 10, 2);
+        registry.store(HASH_1.to_string(), serde_json::json!([1]));
+        registry.store(HASH_2.to_string(), serde_json::json!([2]));
+        registry.store(HASH_3.to_string(), serde_json::json!([3]));
+        assert!(registry.get(HASH_1).is_none());
+        assert_eq!(registry.get(HASH_3), Some(serde_json::json!([3])));
+    }
+}
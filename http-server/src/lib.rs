@@ -3,6 +3,8 @@
 
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 // pub use api::ext_messages::token::EXT_MESSAGE_AUTH_REQUIRED;
@@ -14,9 +16,82 @@ pub use api::ext_messages::ExtMsgResponse;
 pub use api::ext_messages::FeedbackError;
 pub use api::ext_messages::FeedbackErrorCode;
 pub use api::ext_messages::ResolvingResult;
+pub use api::sign_admin_challenge;
+pub use api::signer_bitmap;
+pub use api::AccountNonce;
+pub use api::AccountNonceRequest;
+pub use api::AccountNonceRequestSender;
+pub use api::AccountOwnership;
+pub use api::AccountOwnershipFeed;
+pub use api::AccountOwnershipFilter;
+pub use api::AccountsExportFilter;
+pub use api::AccountsExportRecord;
+pub use api::AccountsExportRequest;
+pub use api::AccountsExportRequestSender;
+pub use api::AdminAuditEntry;
+pub use api::AdminAuditLog;
+pub use api::AdminAuthMode;
+pub use api::AdminChallenge;
+pub use api::ApiError;
+pub use api::BkHistoryInfo;
 pub use api::BkInfo;
+pub use api::BkSetChangeEvent;
+pub use api::BkSetChangeFeed;
+pub use api::BkSetChangeKind;
+pub use api::BkSetHistory;
+pub use api::BkSetHistoryResult;
+pub use api::BkSetHistoryUpdate;
 pub use api::BkSetResult;
+pub use api::BkSetWindow;
+pub use api::BlockAttestation;
+pub use api::BlockInvalidationEvent;
+pub use api::BlockInvalidationFeed;
 pub use api::BlockKeeperSetUpdate;
+pub use api::CorruptedEntry;
+pub use api::DappConfigInfo;
+pub use api::DappConfigRequest;
+pub use api::DappConfigRequestSender;
+pub use api::DurableMessage;
+pub use api::DurableMessagesPage;
+pub use api::DurableMessagesQuery;
+pub use api::DurableMessagesRequest;
+pub use api::DurableMessagesRequestSender;
+pub use api::EventFeed;
+pub use api::ExportedThreadState;
+pub use api::ExtMsgQuarantine;
+pub use api::ExtMsgQueueStats;
+pub use api::ExtMsgQueueStatus;
+pub use api::FeedEvent;
+pub use api::InclusionProof;
+pub use api::InclusionProofRequest;
+pub use api::InclusionProofRequestSender;
+pub use api::IntegrityAudit;
+pub use api::IntegrityAuditSummary;
+pub use api::NodeStatus;
+pub use api::NodeStatusBoard;
+pub use api::OutMsgQueueEntry;
+pub use api::OutMsgQueueIndex;
+pub use api::OutMsgQueueRecord;
+pub use api::ProducerRotationEvent;
+pub use api::ProducerRotationFeed;
+pub use api::ProducerRotationTrigger;
+pub use api::ProductionStallFeed;
+pub use api::ProductionStallReport;
+pub use api::QuarantinedExtMessage;
+pub use api::RunGetParams;
+pub use api::RunGetRequest;
+pub use api::RunGetRequestSender;
+pub use api::RunGetResult;
+pub use api::RunGetState;
+pub use api::SignedBkSetHistory;
+pub use api::ThreadLoadDecision;
+pub use api::ThreadLoadFeed;
+pub use api::ThreadLoadUpdate;
+pub use api::ThreadSplitGate;
+pub use api::ThreadSplitPreview;
+pub use api::ThreadsTableRowPreview;
+pub use api::TxTraceRegistry;
+pub use api::TxTraceStatus;
 use ext_messages_auth::auth::AccountRequest;
 use ext_messages_auth::auth::Token;
 use ext_messages_auth::read_keys_from_file;
@@ -34,6 +109,8 @@ use tvm_block::Message;
 use crate::api::ext_messages::render_error;
 use crate::api::ext_messages::ExternalMessage;
 use crate::api::ext_messages::IncomingExternalMessage;
+use crate::api::AdminAuth;
+use crate::api::AdminChallengeHandler;
 use crate::api::BkSetSnapshot;
 
 mod api;
@@ -51,13 +128,39 @@ pub struct WebServer<TMessage, TMsgConverter, TBPResolver, TBocByAddrGetter, TSe
     pub incoming_message_sender:
         InstrumentedSender<(TMessage, Option<oneshot::Sender<ExtMsgFeedback>>)>,
     pub signing_pubkey_request_senber: mpsc::Sender<AccountRequest>,
+    pub account_nonce_request_sender: AccountNonceRequestSender,
+    pub run_get_request_sender: RunGetRequestSender,
+    pub inclusion_proof_request_sender: InclusionProofRequestSender,
+    pub dapp_config_request_sender: DappConfigRequestSender,
+    pub accounts_export_request_sender: AccountsExportRequestSender,
+    pub durable_messages_request_sender: DurableMessagesRequestSender,
     pub bk_set: Arc<parking_lot::RwLock<BkSetSnapshot>>,
+    pub bk_set_history: Arc<parking_lot::RwLock<BkSetHistory>>,
+    pub bk_set_changes: BkSetChangeFeed,
+    pub block_invalidations: BlockInvalidationFeed,
+    pub tx_traces: TxTraceRegistry,
+    pub integrity_audit: IntegrityAudit,
+    pub thread_load: ThreadLoadFeed,
+    /// Set if thread splits wait for an operator approval
+    pub thread_splits: Option<ThreadSplitGate>,
+    pub ext_msg_queue: ExtMsgQueueStatus,
+    /// Set if repeatedly failing external messages are quarantined
+    pub ext_msg_quarantine: Option<ExtMsgQuarantine>,
+    pub production_stalls: ProductionStallFeed,
+    pub producer_rotations: ProducerRotationFeed,
+    pub out_msg_queues: OutMsgQueueIndex,
+    pub node_status: NodeStatusBoard,
+    pub account_ownership: AccountOwnershipFeed,
+    /// Set by the node while API queries are shed under production pressure
+    pub queries_shed: Arc<AtomicBool>,
     pub into_external_message: TMsgConverter,
     pub bp_resolver: TBPResolver,
     pub get_boc_by_addr: TBocByAddrGetter,
     pub get_default_thread_seqno: TSeqnoGetter,
     pub owner_wallet_pubkey: Option<String>,
     pub signing_keys: Option<KeyPair>,
+    pub admin_auth: AdminAuth,
+    pub admin_audit_log: AdminAuditLog,
     pub metrics: Option<RoutingMetrics>,
 }
 
@@ -81,40 +184,116 @@ where
             Option<oneshot::Sender<ExtMsgFeedback>>,
         )>,
         signing_pubkey_request_senber: mpsc::Sender<AccountRequest>,
+        account_nonce_request_sender: AccountNonceRequestSender,
+        run_get_request_sender: RunGetRequestSender,
+        inclusion_proof_request_sender: InclusionProofRequestSender,
+        dapp_config_request_sender: DappConfigRequestSender,
+        accounts_export_request_sender: AccountsExportRequestSender,
+        durable_messages_request_sender: DurableMessagesRequestSender,
+        bk_set_history: Arc<parking_lot::RwLock<BkSetHistory>>,
+        bk_set_changes: BkSetChangeFeed,
+        block_invalidations: BlockInvalidationFeed,
+        tx_traces: TxTraceRegistry,
+        integrity_audit: IntegrityAudit,
+        thread_load: ThreadLoadFeed,
+        thread_splits: Option<ThreadSplitGate>,
+        ext_msg_queue: ExtMsgQueueStatus,
+        ext_msg_quarantine: Option<ExtMsgQuarantine>,
+        production_stalls: ProductionStallFeed,
+        producer_rotations: ProducerRotationFeed,
+        out_msg_queues: OutMsgQueueIndex,
+        node_status: NodeStatusBoard,
+        account_ownership: AccountOwnershipFeed,
+        queries_shed: Arc<AtomicBool>,
         into_external_message: TMsgConverter,
         bp_resolver: TBPResolver,
         get_boc_by_addr: TBocByAddrGetter,
         get_default_thread_seqno: TSeqnoGetter,
         owner_wallet_pubkey: Option<String>,
         signing_keys_path: Option<String>,
+        admin_auth_mode: AdminAuthMode,
+        admin_audit_log: AdminAuditLog,
+        node_id: String,
         metrics: Option<RoutingMetrics>,
     ) -> Self {
         let signing_keys =
             signing_keys_path.as_ref().and_then(|path| read_keys_from_file(path).ok());
+        let admin_auth = AdminAuth::new(
+            admin_auth_mode,
+            node_id,
+            signing_pubkey_request_senber.clone(),
+            admin_audit_log.clone(),
+        );
         Self {
             addr: addr.as_ref().to_string(),
             local_storage_dir: local_storage_dir.as_ref().to_path_buf(),
             incoming_message_sender,
             signing_pubkey_request_senber,
+            account_nonce_request_sender,
+            run_get_request_sender,
+            inclusion_proof_request_sender,
+            dapp_config_request_sender,
+            accounts_export_request_sender,
+            durable_messages_request_sender,
             into_external_message,
             bp_resolver,
             bk_set: Arc::new(parking_lot::RwLock::new(BkSetSnapshot::new())),
+            bk_set_history,
+            bk_set_changes,
+            block_invalidations,
+            tx_traces,
+            integrity_audit,
+            thread_load,
+            thread_splits,
+            ext_msg_queue,
+            ext_msg_quarantine,
+            production_stalls,
+            producer_rotations,
+            out_msg_queues,
+            node_status,
+            account_ownership,
+            queries_shed,
             get_boc_by_addr,
             get_default_thread_seqno,
             owner_wallet_pubkey,
             signing_keys,
+            admin_auth,
+            admin_audit_log,
             metrics,
         }
     }
 
     pub fn route(self) -> Router {
-        // Returns latest shard state
+        let shed_gate = QueriesShedGate(self.queries_shed.clone());
+        let admin_auth = self.admin_auth.clone();
+        // Returns latest shard state. Shared state routes are not shed: lagging
+        // nodes sync from them
         let storage_latest_router = Router::with_path("storage_latest")
             .get(api::StorageLatestHandler::new(self.local_storage_dir.clone()));
         // Returns selected shard state
         let storage_router = Router::with_path("storage/{*path}")
             .get(StaticDir::new([&self.local_storage_dir]).auto_list(true));
 
+        let router_inclusion_proof = Router::with_path("inclusion_proof")
+            .hoop(shed_gate.clone())
+            .get(api::InclusionProofHandler::<
+                TMessage,
+                TMsgConverter,
+                TBPResolver,
+                TBocByAddrGetter,
+                TSeqnoGetter,
+            >::new());
+
+        let account_ownership_router = Router::with_path("account_ownership")
+            .hoop(shed_gate.clone())
+            .get(api::AccountOwnershipHandler::<
+                TMessage,
+                TMsgConverter,
+                TBPResolver,
+                TBocByAddrGetter,
+                TSeqnoGetter,
+            >::new());
+
         let bk_set_router = Router::with_path("bk_set").get(api::BkSetHandler::<
             TMessage,
             TMsgConverter,
@@ -123,6 +302,186 @@ where
             TSeqnoGetter,
         >::new());
 
+        let bk_set_history_router =
+            Router::with_path("bk_set_history").get(api::BkSetHistoryHandler::<
+                TMessage,
+                TMsgConverter,
+                TBPResolver,
+                TBocByAddrGetter,
+                TSeqnoGetter,
+            >::new());
+
+        let router_tx_trace = Router::with_path("trace/{message_hash}")
+            .hoop(shed_gate.clone())
+            .get(api::TxTraceHandler::<
+                TMessage,
+                TMsgConverter,
+                TBPResolver,
+                TBocByAddrGetter,
+                TSeqnoGetter,
+            >::new());
+
+        let integrity_audit_handler = api::IntegrityAuditHandler::<
+            TMessage,
+            TMsgConverter,
+            TBPResolver,
+            TBocByAddrGetter,
+            TSeqnoGetter,
+        >::new;
+        let integrity_audit_router = Router::with_path("integrity_audit")
+            .hoop(admin_auth.clone())
+            .get(integrity_audit_handler())
+            .post(integrity_audit_handler());
+
+        let bk_set_changes_router = Router::with_path("bk_set_changes")
+            .hoop(admin_auth.clone())
+            .get(api::BkSetChangesHandler::<
+                TMessage,
+                TMsgConverter,
+                TBPResolver,
+                TBocByAddrGetter,
+                TSeqnoGetter,
+            >::new());
+
+        let block_invalidations_router = Router::with_path("block_invalidations")
+            .hoop(admin_auth.clone())
+            .get(api::BlockInvalidationsHandler::<
+                TMessage,
+                TMsgConverter,
+                TBPResolver,
+                TBocByAddrGetter,
+                TSeqnoGetter,
+            >::new());
+
+        let thread_load_router = Router::with_path("thread_load").hoop(admin_auth.clone()).get(
+            api::ThreadLoadHandler::<
+                TMessage,
+                TMsgConverter,
+                TBPResolver,
+                TBocByAddrGetter,
+                TSeqnoGetter,
+            >::new(),
+        );
+
+        let thread_splits_handler = api::ThreadSplitsHandler::<
+            TMessage,
+            TMsgConverter,
+            TBPResolver,
+            TBocByAddrGetter,
+            TSeqnoGetter,
+        >::new;
+        let thread_splits_router = Router::with_path("thread_splits")
+            .hoop(admin_auth.clone())
+            .get(thread_splits_handler())
+            .post(thread_splits_handler());
+
+        let ext_msg_queue_router = Router::with_path("ext_msg_queue").hoop(admin_auth.clone()).get(
+            api::ExtMsgQueueHandler::<
+                TMessage,
+                TMsgConverter,
+                TBPResolver,
+                TBocByAddrGetter,
+                TSeqnoGetter,
+            >::new(),
+        );
+
+        let ext_msg_quarantine_handler = api::ExtMsgQuarantineHandler::<
+            TMessage,
+            TMsgConverter,
+            TBPResolver,
+            TBocByAddrGetter,
+            TSeqnoGetter,
+        >::new;
+        let ext_msg_quarantine_router = Router::with_path("ext_msg_quarantine")
+            .hoop(admin_auth.clone())
+            .get(ext_msg_quarantine_handler())
+            .post(ext_msg_quarantine_handler());
+
+        let node_status_router =
+            Router::with_path("status").hoop(admin_auth.clone()).get(api::NodeStatusHandler::<
+                TMessage,
+                TMsgConverter,
+                TBPResolver,
+                TBocByAddrGetter,
+                TSeqnoGetter,
+            >::new());
+
+        let production_stalls_router = Router::with_path("production_stalls")
+            .hoop(admin_auth.clone())
+            .get(api::ProductionStallsHandler::<
+                TMessage,
+                TMsgConverter,
+                TBPResolver,
+                TBocByAddrGetter,
+                TSeqnoGetter,
+            >::new());
+
+        let producer_rotations_router = Router::with_path("rotations")
+            .hoop(admin_auth.clone())
+            .get(api::ProducerRotationsHandler::<
+                TMessage,
+                TMsgConverter,
+                TBPResolver,
+                TBocByAddrGetter,
+                TSeqnoGetter,
+            >::new());
+
+        let out_msg_queues_router = Router::with_path("out_msg_queues")
+            .hoop(admin_auth.clone())
+            .get(api::OutMsgQueuesHandler::<
+                TMessage,
+                TMsgConverter,
+                TBPResolver,
+                TBocByAddrGetter,
+                TSeqnoGetter,
+            >::new());
+
+        let dapp_config_router = Router::with_path("dapp_config").hoop(admin_auth.clone()).get(
+            api::DappConfigHandler::<
+                TMessage,
+                TMsgConverter,
+                TBPResolver,
+                TBocByAddrGetter,
+                TSeqnoGetter,
+            >::new(),
+        );
+
+        let accounts_export_router = Router::with_path("accounts_export")
+            .hoop(admin_auth.clone())
+            .get(api::AccountsExportHandler::<
+                TMessage,
+                TMsgConverter,
+                TBPResolver,
+                TBocByAddrGetter,
+                TSeqnoGetter,
+            >::new());
+
+        let durable_messages_handler = api::DurableMessagesHandler::<
+            TMessage,
+            TMsgConverter,
+            TBPResolver,
+            TBocByAddrGetter,
+            TSeqnoGetter,
+        >::new;
+        let durable_messages_router = Router::with_path("durable_messages")
+            .hoop(admin_auth.clone())
+            .hoop(shed_gate.clone())
+            .get(durable_messages_handler())
+            .push(Router::with_path("{message_hash}").get(durable_messages_handler()));
+
+        let admin_audit_router = Router::with_path("admin_audit").hoop(admin_auth.clone()).get(
+            api::AdminAuditHandler::<
+                TMessage,
+                TMsgConverter,
+                TBPResolver,
+                TBocByAddrGetter,
+                TSeqnoGetter,
+            >::new(),
+        );
+
+        let auth_challenge_router =
+            Router::with_path("auth/challenge").get(AdminChallengeHandler(admin_auth));
+
         let router_ext_messages = Router::with_path("messages")
             .hoop(pass_unauthorized)
             .hoop(auth)
@@ -135,8 +494,20 @@ where
                 TSeqnoGetter,
             >::new());
 
-        let router_account =
-            Router::with_path("account").hoop(auth).get(api::BocByAddressHandler::<
+        let router_account = Router::with_path("account").hoop(auth).hoop(shed_gate.clone()).get(
+            api::BocByAddressHandler::<
+                TMessage,
+                TMsgConverter,
+                TBPResolver,
+                TBocByAddrGetter,
+                TSeqnoGetter,
+            >::new(),
+        );
+
+        let router_account_nonce = Router::with_path("account_nonce")
+            .hoop(auth)
+            .hoop(shed_gate.clone())
+            .get(api::AccountNonceHandler::<
                 TMessage,
                 TMsgConverter,
                 TBPResolver,
@@ -144,8 +515,20 @@ where
                 TSeqnoGetter,
             >::new());
 
-        let router_seqno =
-            Router::with_path("default_thread_seqno").hoop(auth).get(api::LastSeqnoHandler::<
+        let router_run_get = Router::with_path("run_get").hoop(auth).hoop(shed_gate.clone()).post(
+            api::RunGetHandler::<
+                TMessage,
+                TMsgConverter,
+                TBPResolver,
+                TBocByAddrGetter,
+                TSeqnoGetter,
+            >::new(),
+        );
+
+        let router_seqno = Router::with_path("default_thread_seqno")
+            .hoop(auth)
+            .hoop(shed_gate.clone())
+            .get(api::LastSeqnoHandler::<
                 TMessage,
                 TMsgConverter,
                 TBPResolver,
@@ -155,17 +538,62 @@ where
 
         // Routes:
         // v2/bk_set
+        // v2/bk_set_history?thread_id=<thread_id>
+        // v2/bk_set_changes?thread_id=<thread_id>&from_seq_no=<seq_no>
+        // v2/block_invalidations?thread_id=<thread_id>&from_seq_no=<seq_no>
         // v2/messages
         // v2/account?address=<address>
+        // v2/account_nonce?address=<address>
+        // v2/run_get
         // v2/default_thread_seqno
+        // v2/trace/<message_hash>
+        // v2/inclusion_proof?block_id=<block_id>&transaction_id=<transaction_id>
+        // v2/account_ownership?thread_id=<thread_id>
+        // v2/integrity_audit
+        // v2/thread_load?thread_id=<thread_id>
+        // v2/thread_splits?thread_id=<thread_id>&action=<approve|reject>
+        // v2/ext_msg_queue?thread_id=<thread_id>
+        // v2/ext_msg_quarantine?thread_id=<thread_id>&message_hash=<message_hash>
+        // v2/production_stalls?thread_id=<thread_id>
+        // v2/rotations?thread_id=<thread_id>&limit=<limit>
+        // v2/out_msg_queues?thread_id=<thread_id>&dst_thread_id=<thread_id>&from_seq_no=<seq_no>&to_seq_no=<seq_no>&limit=<limit>
+        // v2/status
+        // v2/dapp_config?dapp_id=<dapp_id>
+        // v2/accounts_export?code_hash=<code_hash>&address_prefix=<address_prefix>
+        // v2/durable_messages/<message_hash>
+        // v2/durable_messages?dst=<address>&after_seq=<seq>&lt_from=<lt>&lt_to=<lt>&limit=<limit>
+        // v2/admin_audit?principal=<principal>&limit=<limit>
+        // v2/auth/challenge
 
         Router::new().hoop(Logger::new()).hoop(affix_state::inject(self.clone())).push(
             Router::new()
                 .path("v2")
                 .push(router_account)
+                .push(router_account_nonce)
+                .push(router_run_get)
                 .push(router_ext_messages)
                 .push(bk_set_router)
+                .push(bk_set_history_router)
+                .push(bk_set_changes_router)
+                .push(block_invalidations_router)
                 .push(router_seqno)
+                .push(router_tx_trace)
+                .push(router_inclusion_proof)
+                .push(account_ownership_router)
+                .push(integrity_audit_router)
+                .push(thread_load_router)
+                .push(thread_splits_router)
+                .push(ext_msg_queue_router)
+                .push(ext_msg_quarantine_router)
+                .push(production_stalls_router)
+                .push(producer_rotations_router)
+                .push(out_msg_queues_router)
+                .push(node_status_router)
+                .push(dapp_config_router)
+                .push(accounts_export_router)
+                .push(durable_messages_router)
+                .push(admin_audit_router)
+                .push(auth_challenge_router)
                 .push(storage_latest_router)
                 .push(storage_router),
         )
@@ -235,6 +663,29 @@ pub fn rustls_config() -> RustlsConfig {
     RustlsConfig::new(keycert)
 }
 
+/// Rejects queries with `503 Service Unavailable` while they are shed.
+#[derive(Clone)]
+struct QueriesShedGate(Arc<AtomicBool>);
+
+#[async_trait]
+impl Handler for QueriesShedGate {
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        ctrl: &mut FlowCtrl,
+    ) {
+        if self.0.load(Ordering::Relaxed) {
+            ApiError::new("NODE_OVERLOADED", "Node is busy with block production", true)
+                .render(res, StatusCode::SERVICE_UNAVAILABLE);
+            ctrl.skip_rest();
+            return;
+        }
+        ctrl.call_next(req, depot, res).await;
+    }
+}
+
 #[handler]
 pub async fn pass_unauthorized(
     req: &mut Request,
@@ -249,14 +700,7 @@ pub async fn pass_unauthorized(
 // This is authentication middleware. By default, if std::env::var `AUTH_TOKEN` is not set, access is denied.
 #[handler]
 pub async fn auth(req: &mut Request, res: &mut Response, depot: &mut Depot, ctrl: &mut FlowCtrl) {
-    let token = std::env::var("AUTH_TOKEN").ok();
-
-    let authorized = token.as_ref().is_some_and(|token| {
-        req.headers()
-            .get(AUTH_HEADER)
-            .and_then(|auth_header| auth_header.to_str().ok())
-            .is_some_and(|auth_str| auth_str == format!("Bearer {token}"))
-    });
+    let authorized = is_token_authorized(req);
 
     let pass_unauth = depot.get::<bool>(PASS_UNAUTHORIZED_KEY).copied().unwrap_or(false);
 
@@ -272,6 +716,17 @@ pub async fn auth(req: &mut Request, res: &mut Response, depot: &mut Depot, ctrl
     }
 }
 
+fn is_token_authorized(req: &Request) -> bool {
+    let token = std::env::var("AUTH_TOKEN").ok();
+
+    token.as_ref().is_some_and(|token| {
+        req.headers()
+            .get(AUTH_HEADER)
+            .and_then(|auth_header| auth_header.to_str().ok())
+            .is_some_and(|auth_str| auth_str == format!("Bearer {token}"))
+    })
+}
+
 #[handler]
 async fn validate_ext_message(
     req: &mut Request,
@@ -0,0 +1,113 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::Duration;
+use std::time::Instant;
+
+#[derive(Debug, Default, Clone)]
+struct DestinationHealth {
+    consecutive_failures: u32,
+    // Circuit is open: the destination is skipped until this moment
+    open_until: Option<Instant>,
+}
+
+/// Tracks delivery results per block producer address. The last producer
+/// that accepted messages of a thread is tried first (sticky routing), while
+/// producers that failed several times in a row are skipped for a while
+/// (circuit breaking).
+#[derive(Debug)]
+pub struct BPHealthTracker {
+    failure_threshold: u32,
+    open_timeout: Duration,
+    destinations: HashMap<SocketAddr, DestinationHealth>,
+    sticky: HashMap<String, SocketAddr>,
+}
+
+impl BPHealthTracker {
+    pub fn new(failure_threshold: u32, open_timeout: Duration) -> Self {
+        Self {
+            failure_threshold,
+            open_timeout,
+            destinations: HashMap::new(),
+            sticky: HashMap::new(),
+        }
+    }
+
+    /// Orders resolved producers for delivery. If circuits of all producers
+    /// are open they are still returned, so messages are not dropped.
+    pub fn order(
+        &self,
+        thread_id: &str,
+        recipients: Vec<SocketAddr>,
+        now: Instant,
+    ) -> Vec<SocketAddr> {
+        let (mut available, open): (Vec<_>, Vec<_>) =
+            recipients.into_iter().partition(|recipient| !self.is_open(recipient, now));
+        if available.is_empty() {
+            return open;
+        }
+        if let Some(sticky) = self.sticky.get(thread_id) {
+            if let Some(position) = available.iter().position(|recipient| recipient == sticky) {
+                let sticky = available.remove(position);
+                available.insert(0, sticky);
+            }
+        }
+        available
+    }
+
+    pub fn report_success(&mut self, thread_id: &str, recipient: SocketAddr) {
+        self.destinations.remove(&recipient);
+        self.sticky.insert(thread_id.to_string(), recipient);
+    }
+
+    pub fn report_failure(&mut self, recipient: SocketAddr, now: Instant) {
+        let health = self.destinations.entry(recipient).or_default();
+        health.consecutive_failures += 1;
+        if health.consecutive_failures >= self.failure_threshold {
+            tracing::warn!(
+                target: "message_router",
+                "BP {recipient} failed {} times in a row, skip it for {:?}",
+                health.consecutive_failures,
+                self.open_timeout
+            );
+            health.open_until = Some(now + self.open_timeout);
+        }
+        self.sticky.retain(|_, sticky| *sticky != recipient);
+    }
+
+    fn is_open(&self, recipient: &SocketAddr, now: Instant) -> bool {
+        self.destinations
+            .get(recipient)
+            .and_then(|health| health.open_until)
+            .is_some_and(|open_until| now < open_until)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bp_health_tracker() {
+        let thread_id = "00";
+        let bp1: SocketAddr = "127.0.0.1:8600".parse().unwrap();
+        let bp2: SocketAddr = "127.0.0.2:8600".parse().unwrap();
+        let now = Instant::now();
+        let mut tracker = BPHealthTracker::new(2, Duration::from_secs(30));
+
+        tracker.report_success(thread_id, bp2);
+        assert_eq!(tracker.order(thread_id, vec![bp1, bp2], now), vec![bp2, bp1]);
+
+        tracker.report_failure(bp2, now);
+        assert_eq!(tracker.order(thread_id, vec![bp1, bp2], now), vec![bp1, bp2]);
+
+        tracker.report_failure(bp2, now);
+        assert_eq!(tracker.order(thread_id, vec![bp1, bp2], now), vec![bp1]);
+        assert_eq!(tracker.order(thread_id, vec![bp2], now), vec![bp2]);
+
+        let later = now + Duration::from_secs(31);
+        assert_eq!(tracker.order(thread_id, vec![bp1, bp2], later), vec![bp1, bp2]);
+    }
+}
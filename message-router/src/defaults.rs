@@ -5,3 +5,5 @@ pub(crate) const DEFAULT_NODE_URL_PROTO: &str = "http";
 pub const DEFAULT_NODE_URL_PORT: u16 = 8600;
 pub const DEFAULT_URL_PATH: &str = "/bm/v2/messages";
 pub(crate) const DEFAULT_BK_API_TIMEOUT: u64 = 5; // in seconds
+pub(crate) const DEFAULT_BP_FAILURE_THRESHOLD: u32 = 3;
+pub(crate) const DEFAULT_BP_CIRCUIT_OPEN_TIMEOUT: u64 = 30; // in seconds
@@ -1,5 +1,6 @@
 // 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
 //
+pub mod bp_health;
 pub mod bp_resolver;
 mod defaults;
 pub mod message_router;
@@ -5,6 +5,7 @@ use std::fmt::Display;
 use std::net::SocketAddr;
 use std::net::ToSocketAddrs;
 use std::sync::Arc;
+use std::time::Duration;
 
 use actix_web::middleware;
 use actix_web::middleware::Logger;
@@ -15,7 +16,10 @@ use actix_web::HttpServer;
 use ext_messages_auth::auth::Token;
 use parking_lot::Mutex;
 
+use crate::bp_health::BPHealthTracker;
 use crate::bp_resolver::BPResolver;
+use crate::defaults::DEFAULT_BP_CIRCUIT_OPEN_TIMEOUT;
+use crate::defaults::DEFAULT_BP_FAILURE_THRESHOLD;
 use crate::defaults::DEFAULT_NODE_URL_PATH;
 use crate::defaults::DEFAULT_URL_PATH;
 use crate::KeyPair;
@@ -40,6 +44,7 @@ pub struct MessageRouter {
     pub owner_wallet_pubkey: Option<String>,
     pub signing_keys: Option<KeyPair>,
     pub bp_resolver: Arc<Mutex<dyn BPResolver>>,
+    pub bp_health: Arc<Mutex<BPHealthTracker>>,
 }
 
 impl Display for MessageRouter {
@@ -54,7 +59,12 @@ impl MessageRouter {
         let owner_wallet_pubkey = config.owner_wallet_pubkey;
         let signing_keys = config.signing_keys;
         let bp_resolver = config.bp_resolver;
-        let message_router = Self { bind, owner_wallet_pubkey, signing_keys, bp_resolver };
+        let bp_health = Arc::new(Mutex::new(BPHealthTracker::new(
+            DEFAULT_BP_FAILURE_THRESHOLD,
+            Duration::from_secs(DEFAULT_BP_CIRCUIT_OPEN_TIMEOUT),
+        )));
+        let message_router =
+            Self { bind, owner_wallet_pubkey, signing_keys, bp_resolver, bp_health };
 
         tracing::info!("Starting MessageRouter: {message_router}");
 
@@ -5,6 +5,7 @@ use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
+use std::time::Instant;
 
 use serde_json::json;
 use telemetry_utils::now_ms;
@@ -54,6 +55,8 @@ pub async fn run(
     tracing::info!(target: "message_router", "Ext messages received: {:?}", nrs.iter().map(|nr| format!("{}", nr["id"])));
 
     let recipients = message_router.bp_resolver.lock().resolve(Some(thread_id.clone()));
+    // The last BP that accepted messages goes first, BPs with open circuits are skipped
+    let recipients = message_router.bp_health.lock().order(&thread_id, recipients, Instant::now());
     tracing::trace!(target: "message_router", "Resolved BPs (thread={:?}): {:?}", thread_id, recipients);
     let mut result = serde_json::json!({});
     if recipients.is_empty() {
@@ -83,20 +86,38 @@ pub async fn run(
 
         result = match request.send().await {
             Ok(response) => {
+                let recipient_addr = recipient;
                 let recipient = recipient.ip().to_string();
 
-                let body = response.text().await;
+                // Rejected messages come back as client errors from a healthy BP,
+                // server errors and unreadable bodies move on to the next BP
+                let status = response.status();
+                let body = if status.is_server_error() {
+                    Err(anyhow::format_err!("Block Producer responded with {status}"))
+                } else {
+                    response
+                        .text()
+                        .await
+                        .map_err(|e| anyhow::format_err!("Failed to read the response: {e}"))
+                        .and_then(|body_str| {
+                            tracing::info!(target: "message_router", "response body (src={}): {:?}", recipient, body_str);
+                            serde_json::from_str::<serde_json::Value>(&body_str)
+                                .map_err(|e| anyhow::format_err!("Failed to parse the response: {e}"))
+                        })
+                };
                 match body {
-                    Ok(body_str) => {
-                        tracing::info!(target: "message_router", "response body (src={}): {:?}", recipient, body_str);
-                        let mut response_json: serde_json::Value = serde_json::from_str(&body_str)?;
-
+                    Ok(mut response_json) => {
+                        message_router.bp_health.lock().report_success(&thread_id, recipient_addr);
                         response_json["ext_message_token"] = json!(message_router.issue_token());
                         tracing::trace!(target: "message_router", "add token to response: {:?}", response_json["ext_message_token"]);
                         return Ok(response_json);
                     }
                     Err(err) => {
                         tracing::error!(target: "message_router", "redirection to {url} failed: {err}");
+                        message_router
+                            .bp_health
+                            .lock()
+                            .report_failure(recipient_addr, Instant::now());
                         let err_data = http_server::ExtMsgErrorData::new(
                             vec![recipient],
                             ids.get(&nrs[0]["id"]).unwrap().to_string(),
@@ -105,7 +126,7 @@ pub async fn run(
                         );
                         serde_json::json!(http_server::ExtMsgResponse::new_with_error(
                             "INTERNAL_ERROR".into(),
-                            format!("Failed to parse the response from the Block Producer: {err}"),
+                            format!("The Block Producer failed to process the messages: {err}"),
                             Some(err_data),
                         ))
                     }
@@ -113,6 +134,7 @@ pub async fn run(
             }
             Err(err) => {
                 tracing::error!(target: "message_router", "redirection to {url} failed: {err}");
+                message_router.bp_health.lock().report_failure(recipient, Instant::now());
                 let err_data = http_server::ExtMsgErrorData::new(
                     vec![recipient.ip().to_string()],
                     ids.get(&nrs[0]["id"]).unwrap().to_string(),
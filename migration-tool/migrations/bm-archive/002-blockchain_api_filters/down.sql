@@ -0,0 +1,8 @@
+DROP INDEX index_blocks_workchain_id_chain_order;
+DROP INDEX index_blocks_thread_id_chain_order;
+DROP INDEX index_blocks_gen_utime;
+DROP INDEX index_transactions_chain_order;
+DROP INDEX index_transactions_block_id;
+DROP INDEX index_transactions_aborted_chain_order;
+DROP INDEX index_transactions_workchain_id_chain_order;
+DROP INDEX index_transactions_now;
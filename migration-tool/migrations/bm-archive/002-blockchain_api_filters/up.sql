@@ -0,0 +1,8 @@
+CREATE INDEX index_blocks_workchain_id_chain_order ON blocks (workchain_id, chain_order);
+CREATE INDEX index_blocks_thread_id_chain_order ON blocks (thread_id, chain_order);
+CREATE INDEX index_blocks_gen_utime ON blocks (gen_utime);
+CREATE INDEX index_transactions_chain_order ON transactions (chain_order);
+CREATE INDEX index_transactions_block_id ON transactions (block_id);
+CREATE INDEX index_transactions_aborted_chain_order ON transactions (aborted, chain_order);
+CREATE INDEX index_transactions_workchain_id_chain_order ON transactions (workchain_id, chain_order);
+CREATE INDEX index_transactions_now ON transactions (now);
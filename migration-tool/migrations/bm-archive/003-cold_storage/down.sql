@@ -0,0 +1,3 @@
+DROP INDEX index_messages_transaction_id;
+DROP TABLE cold_storage_index;
+DROP TABLE cold_storage_segments;
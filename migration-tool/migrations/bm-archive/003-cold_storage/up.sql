@@ -0,0 +1,19 @@
+CREATE TABLE cold_storage_segments (
+    rowid INTEGER PRIMARY KEY,
+    uri TEXT NOT NULL UNIQUE,
+    min_gen_utime INTEGER NOT NULL,
+    max_gen_utime INTEGER NOT NULL,
+    blocks INTEGER NOT NULL,
+    transactions INTEGER NOT NULL,
+    messages INTEGER NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+CREATE TABLE cold_storage_index (
+    id TEXT NOT NULL PRIMARY KEY,
+    kind TEXT NOT NULL,
+    segment INTEGER NOT NULL
+) WITHOUT ROWID;
+
+-- Messages are moved together with the transactions that produced them
+CREATE INDEX index_messages_transaction_id ON messages (transaction_id);
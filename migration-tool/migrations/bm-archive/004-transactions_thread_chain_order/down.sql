@@ -0,0 +1,3 @@
+DROP INDEX index_blocks_thread_id_id;
+CREATE INDEX index_transactions_block_id ON transactions (block_id);
+DROP INDEX index_transactions_block_id_chain_order;
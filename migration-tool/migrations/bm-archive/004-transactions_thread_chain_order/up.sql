@@ -0,0 +1,3 @@
+CREATE INDEX index_transactions_block_id_chain_order ON transactions (block_id, chain_order);
+DROP INDEX index_transactions_block_id;
+CREATE INDEX index_blocks_thread_id_id ON blocks (thread_id, id);
@@ -0,0 +1,12 @@
+CREATE TABLE block_fees (
+    block_id TEXT NOT NULL PRIMARY KEY,
+    thread_id TEXT,
+    seq_no INTEGER NOT NULL,
+    gen_utime INTEGER,
+    fees_collected TEXT NOT NULL,
+    minted TEXT NOT NULL,
+    burned TEXT NOT NULL,
+    minted_by_dapp TEXT
+) WITHOUT ROWID;
+
+CREATE INDEX index_block_fees_thread_id_seq_no ON block_fees (thread_id, seq_no);
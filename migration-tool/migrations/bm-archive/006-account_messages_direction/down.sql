@@ -0,0 +1,6 @@
+CREATE INDEX index_messages_dst ON messages (dst);
+CREATE INDEX index_messages_src ON messages (src);
+DROP INDEX index_messages_dst_dst_chain_order;
+DROP INDEX index_messages_src_src_chain_order;
+DROP INDEX index_messages_dst_src_dst_chain_order;
+DROP INDEX index_messages_src_dst_src_chain_order;
@@ -0,0 +1,6 @@
+CREATE INDEX index_messages_dst_dst_chain_order ON messages (dst, dst_chain_order);
+CREATE INDEX index_messages_src_src_chain_order ON messages (src, src_chain_order);
+CREATE INDEX index_messages_dst_src_dst_chain_order ON messages (dst, src, dst_chain_order);
+CREATE INDEX index_messages_src_dst_src_chain_order ON messages (src, dst, src_chain_order);
+DROP INDEX index_messages_dst;
+DROP INDEX index_messages_src;
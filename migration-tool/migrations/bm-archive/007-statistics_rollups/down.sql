@@ -0,0 +1,2 @@
+DROP TABLE statistics_active_accounts;
+DROP TABLE statistics_rollups;
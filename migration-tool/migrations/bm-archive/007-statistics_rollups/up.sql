@@ -0,0 +1,16 @@
+CREATE TABLE statistics_rollups (
+    period TEXT NOT NULL,
+    period_start INTEGER NOT NULL,
+    blocks INTEGER NOT NULL DEFAULT 0,
+    transactions INTEGER NOT NULL DEFAULT 0,
+    fees_collected TEXT NOT NULL DEFAULT '0',
+    active_accounts INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (period, period_start)
+) WITHOUT ROWID;
+
+CREATE TABLE statistics_active_accounts (
+    period TEXT NOT NULL,
+    period_start INTEGER NOT NULL,
+    account_addr TEXT NOT NULL,
+    PRIMARY KEY (period, period_start, account_addr)
+) WITHOUT ROWID;
@@ -0,0 +1,6 @@
+CREATE TABLE archive_cursor (
+    thread_id TEXT NOT NULL PRIMARY KEY,
+    block_id TEXT NOT NULL,
+    seq_no INTEGER NOT NULL,
+    chain_order TEXT NOT NULL
+) WITHOUT ROWID;
@@ -0,0 +1,217 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::metrics::NetMetrics;
+
+// Occupancy samples kept between adjustments, the rest are dropped
+const MAX_OCCUPANCY_SAMPLES: usize = 10_000;
+// Too few samples say nothing about the load
+const MIN_OCCUPANCY_SAMPLES: usize = 100;
+const OCCUPANCY_PERCENTILE: f64 = 0.99;
+// The limit grows when the percentile occupancy reaches this share of it and
+// shrinks when it stays below the other one
+const GROW_OCCUPANCY: f64 = 0.9;
+const SHRINK_OCCUPANCY: f64 = 0.25;
+
+/// Bounds a buffer is sized within at runtime.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapacityTuningConfig {
+    pub min_size: usize,
+    pub max_size: usize,
+    /// Seconds between adjustments
+    /// Defaults to 10
+    #[serde(default = "default_tuning_interval_secs")]
+    pub interval_secs: u64,
+}
+
+fn default_tuning_interval_secs() -> u64 {
+    10
+}
+
+/// Capacity of a buffer adjusted from its observed occupancy. The buffer is
+/// allocated with the maximum capacity and senders treat it as full when the
+/// occupancy reaches the current limit. The limit doubles when the 99th
+/// percentile of the occupancy gets close to it and halves when the buffer
+/// is mostly empty.
+#[derive(Clone)]
+pub struct AdaptiveCapacity {
+    name: &'static str,
+    min: usize,
+    max: usize,
+    limit: Arc<AtomicUsize>,
+    samples: Arc<parking_lot::Mutex<Vec<usize>>>,
+}
+
+impl AdaptiveCapacity {
+    /// Fixed at `size` if there is no tuning config.
+    pub fn new(name: &'static str, size: usize, tuning: Option<&CapacityTuningConfig>) -> Self {
+        let (min, max) = match tuning {
+            Some(tuning) => (tuning.min_size.max(1), tuning.max_size.max(tuning.min_size.max(1))),
+            None => (size.max(1), size.max(1)),
+        };
+        Self {
+            name,
+            min,
+            max,
+            limit: Arc::new(AtomicUsize::new(size.clamp(min, max))),
+            samples: Arc::new(parking_lot::Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit.load(Ordering::Relaxed)
+    }
+
+    pub fn is_tuned(&self) -> bool {
+        self.min < self.max
+    }
+
+    /// Records the occupancy seen by a sender, returns true if the buffer is
+    /// full.
+    pub fn record(&self, occupancy: usize) -> bool {
+        if self.is_tuned() {
+            let mut samples = self.samples.lock();
+            if samples.len() < MAX_OCCUPANCY_SAMPLES {
+                samples.push(occupancy);
+            }
+        }
+        occupancy >= self.limit()
+    }
+
+    /// Returns the new limit if it was changed.
+    fn adjust(&self) -> Option<usize> {
+        let mut samples = std::mem::take(&mut *self.samples.lock());
+        if samples.len() < MIN_OCCUPANCY_SAMPLES {
+            return None;
+        }
+        let index = ((samples.len() - 1) as f64 * OCCUPANCY_PERCENTILE) as usize;
+        let occupancy = *samples.select_nth_unstable(index).1 as f64;
+        let limit = self.limit();
+        let new_limit = if occupancy >= limit as f64 * GROW_OCCUPANCY {
+            limit.saturating_mul(2).min(self.max)
+        } else if occupancy < limit as f64 * SHRINK_OCCUPANCY {
+            (limit / 2).max(self.min)
+        } else {
+            limit
+        };
+        if new_limit == limit {
+            return None;
+        }
+        self.limit.store(new_limit, Ordering::Relaxed);
+        Some(new_limit)
+    }
+}
+
+/// Periodically adjusts the tuned capacities and reports them as metrics.
+pub async fn run_capacity_tuning(
+    mut shutdown_rx: tokio::sync::watch::Receiver<bool>,
+    capacities: Vec<AdaptiveCapacity>,
+    interval: Duration,
+    metrics: Option<NetMetrics>,
+) {
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        tokio::select! {
+            sender = shutdown_rx.changed() => if sender.is_err() || *shutdown_rx.borrow() {
+                return;
+            },
+            _ = interval.tick() => {
+                for capacity in &capacities {
+                    let limit = capacity.limit();
+                    if let Some(new_limit) = capacity.adjust() {
+                        tracing::info!(
+                            "Capacity of {} adjusted from {limit} to {new_limit}",
+                            capacity.name()
+                        );
+                        metrics.as_ref().inspect(|m| {
+                            m.report_buffer_capacity_adjustment(capacity.name(), new_limit > limit)
+                        });
+                    }
+                    metrics
+                        .as_ref()
+                        .inspect(|m| m.report_buffer_capacity(capacity.name(), capacity.limit()));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tuning(min_size: usize, max_size: usize) -> CapacityTuningConfig {
+        CapacityTuningConfig { min_size, max_size, interval_secs: 1 }
+    }
+
+    #[test]
+    fn test_fixed_capacity() {
+        let capacity = AdaptiveCapacity::new("test", 100, None);
+        assert!(!capacity.is_tuned());
+        assert_eq!(capacity.max(), 100);
+        assert!(!capacity.record(99));
+        assert!(capacity.record(100));
+        assert_eq!(capacity.adjust(), None);
+    }
+
+    #[test]
+    fn test_capacity_adjustment() {
+        let capacity = AdaptiveCapacity::new("test", 100, Some(&tuning(50, 300)));
+        assert_eq!(capacity.max(), 300);
+
+        // Not enough samples
+        capacity.record(100);
+        assert_eq!(capacity.adjust(), None);
+
+        // Occupancy close to the limit grows it up to the maximum
+        for i in 0..200 {
+            capacity.record(if i % 10 == 0 { 95 } else { 10 });
+        }
+        assert_eq!(capacity.adjust(), Some(200));
+        for _ in 0..200 {
+            capacity.record(190);
+        }
+        assert_eq!(capacity.adjust(), Some(300));
+        for _ in 0..200 {
+            capacity.record(300);
+        }
+        assert_eq!(capacity.adjust(), None);
+
+        // Occupancy between the thresholds keeps it
+        for _ in 0..200 {
+            capacity.record(100);
+        }
+        assert_eq!(capacity.adjust(), None);
+
+        // Mostly empty buffer shrinks it down to the minimum
+        for _ in 0..200 {
+            capacity.record(10);
+        }
+        assert_eq!(capacity.adjust(), Some(150));
+        for _ in 0..200 {
+            capacity.record(0);
+        }
+        assert_eq!(capacity.adjust(), Some(75));
+        for _ in 0..200 {
+            capacity.record(0);
+        }
+        assert_eq!(capacity.adjust(), Some(50));
+        assert_eq!(capacity.limit(), 50);
+    }
+}
@@ -0,0 +1,154 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::time::Duration;
+use std::time::Instant;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+// Backpressure frames start with this marker, same as subscription frames
+// they can't be confused with a serialized NetMessage.
+const BACKPRESSURE_FRAME_MARKER: [u8; 8] = *b"ANBKPRS\0";
+
+/// When a subscriber reports sustained pressure to its publishers.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackpressureConfig {
+    /// Delivery delay of the messages received from a publisher (since they
+    /// were sent by the origin) that is considered pressure
+    /// Defaults to 1000
+    #[serde(default = "default_delay_threshold_millis")]
+    pub delay_threshold_millis: u64,
+    /// Pressure (and then its absence) lasting this long is reported
+    /// Defaults to 5000
+    #[serde(default = "default_sustain_millis")]
+    pub sustain_millis: u64,
+    /// Largest message a publisher sends to the subscriber under pressure,
+    /// larger ones (e.g. blocks) are skipped and are expected to be synced
+    /// from the announcements
+    /// Defaults to 1024
+    #[serde(default = "default_max_message_size")]
+    pub max_message_size: usize,
+}
+
+fn default_delay_threshold_millis() -> u64 {
+    1000
+}
+
+fn default_sustain_millis() -> u64 {
+    5000
+}
+
+fn default_max_message_size() -> usize {
+    1024
+}
+
+impl Default for BackpressureConfig {
+    fn default() -> Self {
+        Self {
+            delay_threshold_millis: default_delay_threshold_millis(),
+            sustain_millis: default_sustain_millis(),
+            max_message_size: default_max_message_size(),
+        }
+    }
+}
+
+/// Sent by a subscriber over the subscription connection when the pressure
+/// state changes. Publishers that don't know about it just ignore it.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum BackpressureAdvisory {
+    /// Only messages up to this size should be sent until recovered
+    Slow {
+        max_message_size: usize,
+    },
+    Recovered,
+}
+
+pub fn is_backpressure_frame(frame: &[u8]) -> bool {
+    frame.starts_with(&BACKPRESSURE_FRAME_MARKER)
+}
+
+pub fn encode_backpressure_frame(advisory: &BackpressureAdvisory) -> bincode::Result<Vec<u8>> {
+    let mut frame = BACKPRESSURE_FRAME_MARKER.to_vec();
+    bincode::serialize_into(&mut frame, advisory)?;
+    Ok(frame)
+}
+
+pub fn decode_backpressure_frame(frame: &[u8]) -> anyhow::Result<BackpressureAdvisory> {
+    let Some(data) = frame.strip_prefix(&BACKPRESSURE_FRAME_MARKER) else {
+        anyhow::bail!("Frame is not a backpressure advisory");
+    };
+    bincode::deserialize(data)
+        .map_err(|err| anyhow::format_err!("Invalid backpressure advisory frame: {err}"))
+}
+
+/// Tracks the delivery delays of a subscription and decides when to advise
+/// the publisher. Both states must last `sustain_millis` to be reported, so
+/// single slow messages and short recoveries don't flip the publisher mode.
+#[derive(Debug)]
+pub struct PressureDetector {
+    config: BackpressureConfig,
+    slow: bool,
+    // Start of the current run of delays on the other side of the threshold
+    changed_since: Option<Instant>,
+}
+
+impl PressureDetector {
+    pub fn new(config: BackpressureConfig) -> Self {
+        Self { config, slow: false, changed_since: None }
+    }
+
+    pub fn observe(&mut self, delay: Duration, now: Instant) -> Option<BackpressureAdvisory> {
+        let under_pressure = delay > Duration::from_millis(self.config.delay_threshold_millis);
+        if under_pressure == self.slow {
+            self.changed_since = None;
+            return None;
+        }
+        let since = *self.changed_since.get_or_insert(now);
+        if now.duration_since(since) < Duration::from_millis(self.config.sustain_millis) {
+            return None;
+        }
+        self.slow = under_pressure;
+        self.changed_since = None;
+        Some(if self.slow {
+            BackpressureAdvisory::Slow { max_message_size: self.config.max_message_size }
+        } else {
+            BackpressureAdvisory::Recovered
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pressure_detector() {
+        let config = BackpressureConfig {
+            delay_threshold_millis: 100,
+            sustain_millis: 1000,
+            max_message_size: 10,
+        };
+        let slow = Duration::from_millis(500);
+        let fast = Duration::from_millis(10);
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut detector = PressureDetector::new(config);
+
+        assert_eq!(detector.observe(slow, at(0)), None);
+        // Short recovery resets the pressure run
+        assert_eq!(detector.observe(fast, at(500)), None);
+        assert_eq!(detector.observe(slow, at(600)), None);
+        assert_eq!(detector.observe(slow, at(1500)), None);
+        let advisory = detector.observe(slow, at(1600)).unwrap();
+        assert_eq!(advisory, BackpressureAdvisory::Slow { max_message_size: 10 });
+        assert_eq!(detector.observe(slow, at(5000)), None);
+
+        assert_eq!(detector.observe(fast, at(6000)), None);
+        assert_eq!(detector.observe(fast, at(7000)), Some(BackpressureAdvisory::Recovered));
+
+        let frame = encode_backpressure_frame(&advisory).unwrap();
+        assert!(is_backpressure_frame(&frame));
+        assert_eq!(decode_backpressure_frame(&frame).unwrap(), advisory);
+    }
+}
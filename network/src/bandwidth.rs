@@ -0,0 +1,150 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::metrics::NetMetrics;
+
+const TOP_TALKERS_INTERVAL: Duration = Duration::from_secs(60);
+const TOP_TALKERS_LIMIT: usize = 10;
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Traffic {
+    pub sent_bytes: u64,
+    pub received_bytes: u64,
+}
+
+impl Traffic {
+    pub fn total_bytes(&self) -> u64 {
+        self.sent_bytes + self.received_bytes
+    }
+
+    fn add(&mut self, other: &Traffic) {
+        self.sent_bytes += other.sent_bytes;
+        self.received_bytes += other.received_bytes;
+    }
+}
+
+/// Traffic of a peer since the previous summary.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PeerBandwidth {
+    pub peer: String,
+    pub total: Traffic,
+    /// Traffic per message label, the largest first.
+    pub labels: Vec<(String, Traffic)>,
+}
+
+/// Bytes sent to and received from the peers per message label. Peers are
+/// keyed by the peer id, or by the host id prefix if the connection identity
+/// is not verified.
+#[derive(Clone, Default)]
+pub struct BandwidthLedger {
+    traffic: Arc<parking_lot::Mutex<HashMap<String, HashMap<String, Traffic>>>>,
+}
+
+impl BandwidthLedger {
+    pub fn record_sent(&self, peer: &str, label: &str, bytes: usize) {
+        self.entry(peer, label, |traffic| traffic.sent_bytes += bytes as u64);
+    }
+
+    pub fn record_received(&self, peer: &str, label: &str, bytes: usize) {
+        self.entry(peer, label, |traffic| traffic.received_bytes += bytes as u64);
+    }
+
+    fn entry(&self, peer: &str, label: &str, update: impl FnOnce(&mut Traffic)) {
+        let mut traffic = self.traffic.lock();
+        // Lookups first, the keys are allocated only for new peers and labels
+        let labels = match traffic.get_mut(peer) {
+            Some(labels) => labels,
+            None => traffic.entry(peer.to_string()).or_default(),
+        };
+        match labels.get_mut(label) {
+            Some(label_traffic) => update(label_traffic),
+            None => update(labels.entry(label.to_string()).or_default()),
+        }
+    }
+
+    /// Returns the peers with the largest traffic and starts the next period.
+    pub fn take_top_talkers(&self, limit: usize) -> Vec<PeerBandwidth> {
+        let traffic = std::mem::take(&mut *self.traffic.lock());
+        let mut peers = traffic
+            .into_iter()
+            .map(|(peer, labels)| {
+                let mut total = Traffic::default();
+                labels.values().for_each(|traffic| total.add(traffic));
+                let mut labels = labels.into_iter().collect::<Vec<_>>();
+                labels.sort_by(|a, b| b.1.total_bytes().cmp(&a.1.total_bytes()));
+                PeerBandwidth { peer, total, labels }
+            })
+            .collect::<Vec<_>>();
+        peers.sort_by(|a, b| b.total.total_bytes().cmp(&a.total.total_bytes()));
+        peers.truncate(limit);
+        peers
+    }
+}
+
+/// Logs the top talkers of every period, so operators can attribute the
+/// bandwidth to the peers and spot excessive (e.g. state) downloads.
+pub async fn log_top_talkers(
+    mut shutdown_rx: tokio::sync::watch::Receiver<bool>,
+    metrics: NetMetrics,
+) {
+    loop {
+        tokio::select! {
+            sender = shutdown_rx.changed() => if sender.is_err() || *shutdown_rx.borrow() {
+                return;
+            },
+            _ = tokio::time::sleep(TOP_TALKERS_INTERVAL) => {
+                for talker in metrics.bandwidth().take_top_talkers(TOP_TALKERS_LIMIT) {
+                    let labels = talker
+                        .labels
+                        .iter()
+                        .map(|(label, traffic)| {
+                            format!("{label}: {}/{}", traffic.sent_bytes, traffic.received_bytes)
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    tracing::info!(
+                        peer = talker.peer,
+                        sent_bytes = talker.total.sent_bytes,
+                        received_bytes = talker.total.received_bytes,
+                        "Top talker in {}s (sent/received bytes by label): {labels}",
+                        TOP_TALKERS_INTERVAL.as_secs(),
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_top_talkers() {
+        let ledger = BandwidthLedger::default();
+        ledger.record_sent("a", "Block", 100);
+        ledger.record_received("a", "StateRequest", 10);
+        ledger.record_received("b", "StateResponse", 1000);
+        ledger.record_received("b", "StateResponse", 500);
+        ledger.record_sent("c", "Ack", 1);
+
+        let top = ledger.take_top_talkers(2);
+        assert_eq!(top.len(), 2);
+        assert_eq!(top[0].peer, "b");
+        assert_eq!(top[0].total, Traffic { sent_bytes: 0, received_bytes: 1500 });
+        assert_eq!(top[1].peer, "a");
+        assert_eq!(
+            top[1].labels,
+            vec![
+                ("Block".to_string(), Traffic { sent_bytes: 100, received_bytes: 0 }),
+                ("StateRequest".to_string(), Traffic { sent_bytes: 0, received_bytes: 10 }),
+            ]
+        );
+        // The next period starts empty
+        assert!(ledger.take_top_talkers(2).is_empty());
+    }
+}
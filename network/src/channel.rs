@@ -1,5 +1,8 @@
+use std::collections::HashMap;
 use std::fmt::Debug;
+use std::hash::Hash;
 use std::marker::PhantomData;
+use std::sync::Arc;
 use std::time::Duration;
 use std::time::Instant;
 
@@ -12,11 +15,16 @@ use crate::pub_sub::connection::OutgoingMessage;
 use crate::DeliveryPhase;
 use crate::SendMode;
 
+/// Round trip times of the direct connections to peers, updated by the
+/// direct sender after each message transfer.
+pub(crate) type PeerRtts<PeerId> = Arc<parking_lot::RwLock<HashMap<PeerId, Duration>>>;
+
 #[derive(Clone)]
 pub struct NetDirectSender<PeerId, Message> {
     inner: tokio::sync::mpsc::UnboundedSender<(PeerId, NetMessage, Instant)>,
     metrics: Option<NetMetrics>,
     self_peer_id: PeerId,
+    peer_rtts: PeerRtts<PeerId>,
     _message_type: PhantomData<Message>,
 }
 
@@ -69,8 +77,9 @@ where
         inner: tokio::sync::mpsc::UnboundedSender<(PeerId, NetMessage, Instant)>,
         metrics: Option<NetMetrics>,
         self_peer_id: PeerId,
+        peer_rtts: PeerRtts<PeerId>,
     ) -> Self {
-        Self { inner, metrics, self_peer_id, _message_type: PhantomData }
+        Self { inner, metrics, self_peer_id, peer_rtts, _message_type: PhantomData }
     }
 
     pub fn send(&self, args: (PeerId, Message)) -> Result<(), NetSendError<Message>> {
@@ -140,10 +149,26 @@ where
     }
 }
 
+impl<PeerId, Message> NetDirectSender<PeerId, Message>
+where
+    PeerId: Hash + Eq,
+{
+    /// Smoothed RTT of the direct connection to the peer. It is unknown until
+    /// a message is transferred to the peer over the current connection.
+    pub fn peer_rtt(&self, peer_id: &PeerId) -> Option<Duration> {
+        self.peer_rtts.read().get(peer_id).copied()
+    }
+}
+
+type ThreadTag<Message> = Arc<dyn Fn(&Message) -> Option<String> + Send + Sync>;
+type MinProtocolVersion<Message> = Arc<dyn Fn(&Message) -> u16 + Send + Sync>;
+
 #[derive(Clone)]
 pub struct NetBroadcastSender<Message> {
     inner: tokio::sync::broadcast::Sender<OutgoingMessage>,
     metrics: Option<NetMetrics>,
+    thread_tag: Option<ThreadTag<Message>>,
+    min_protocol_version: Option<MinProtocolVersion<Message>>,
     _message_type: PhantomData<Message>,
 }
 
@@ -155,11 +180,42 @@ where
         inner: tokio::sync::broadcast::Sender<OutgoingMessage>,
         metrics: Option<NetMetrics>,
     ) -> Self {
-        Self { inner, metrics, _message_type: PhantomData }
+        Self {
+            inner,
+            metrics,
+            thread_tag: None,
+            min_protocol_version: None,
+            _message_type: PhantomData,
+        }
+    }
+
+    /// Sets the function that returns hex encoded thread identifier of
+    /// a message, so publishers can filter it for thread-restricted subscribers.
+    pub fn with_thread_tag(
+        mut self,
+        thread_tag: impl Fn(&Message) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.thread_tag = Some(Arc::new(thread_tag));
+        self
+    }
+
+    /// Sets the function that returns the oldest protocol version that
+    /// decodes a message, so it is not sent to peers of the older versions.
+    pub fn with_min_protocol_version(
+        mut self,
+        min_protocol_version: impl Fn(&Message) -> u16 + Send + Sync + 'static,
+    ) -> Self {
+        self.min_protocol_version = Some(Arc::new(min_protocol_version));
+        self
     }
 
     pub fn send(&self, message: Message) -> Result<usize, NetSendError<Message>> {
-        let (_, net_message, orig_size) = encode_outgoing(message)?;
+        let thread_id = self.thread_tag.as_ref().and_then(|thread_tag| thread_tag(&message));
+        let min_protocol_version =
+            self.min_protocol_version.as_ref().map(|version| version(&message)).unwrap_or(0);
+        let (_, mut net_message, orig_size) = encode_outgoing(message)?;
+        net_message.thread_id = thread_id;
+        net_message.min_protocol_version = min_protocol_version;
         let label = net_message.label.clone();
 
         if let Some(metrics) = self.metrics.as_ref() {
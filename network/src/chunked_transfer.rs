@@ -0,0 +1,324 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+use std::collections::HashMap;
+use std::time::Duration;
+use std::time::Instant;
+
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
+
+/// Serialized messages larger than this are transferred as a sequence of chunks.
+pub const CHUNKED_TRANSFER_THRESHOLD: usize = 4 * 1024 * 1024;
+pub const CHUNK_SIZE: usize = 1024 * 1024;
+
+// Chunk frames start with this marker. A serialized NetMessage can't start
+// with it because it would be a delivery timestamp far in the future,
+// so plain frames stay compatible with nodes that don't know about chunks.
+const CHUNK_FRAME_MARKER: [u8; 8] = *b"ANCHUNK\0";
+const MAX_PENDING_TRANSFERS: usize = 64;
+const PENDING_TRANSFER_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Limits of the chunked messages accepted over a connection.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ChunkedTransferConfig {
+    /// Chunked messages larger than this are rejected by their first chunk.
+    /// Defaults to 1 GiB
+    #[serde(default = "default_max_message_size")]
+    pub max_message_size: u64,
+    /// Total size of the messages reassembled at once over a connection.
+    /// Chunks of a new transfer that doesn't fit are rejected until the
+    /// pending ones complete or expire.
+    /// Defaults to 1 GiB
+    #[serde(default = "default_max_pending_bytes")]
+    pub max_pending_bytes: u64,
+}
+
+fn default_max_message_size() -> u64 {
+    1024 * 1024 * 1024
+}
+
+fn default_max_pending_bytes() -> u64 {
+    1024 * 1024 * 1024
+}
+
+impl Default for ChunkedTransferConfig {
+    fn default() -> Self {
+        Self {
+            max_message_size: default_max_message_size(),
+            max_pending_bytes: default_max_pending_bytes(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NetChunk {
+    pub transfer_id: u64,
+    pub index: u32,
+    pub count: u32,
+    pub total_len: u64,
+    /// SHA-256 of the chunk data.
+    pub checksum: [u8; 32],
+    pub data: Vec<u8>,
+}
+
+/// Transfer identifier is derived from the data: chunks of a message sent
+/// again after a failed transfer are merged into the one pending on the
+/// receiver. The sender doesn't know which chunks arrived and sends all of
+/// them again.
+pub fn transfer_id(data: &[u8]) -> u64 {
+    let hash = checksum(data);
+    u64::from_le_bytes(hash[..8].try_into().expect("hash is longer than 8 bytes"))
+}
+
+pub fn split_into_chunks(transfer_id: u64, data: &[u8], chunk_size: usize) -> Vec<NetChunk> {
+    let count = data.len().div_ceil(chunk_size) as u32;
+    let total_len = data.len() as u64;
+    data.chunks(chunk_size)
+        .enumerate()
+        .map(|(index, chunk)| NetChunk {
+            transfer_id,
+            index: index as u32,
+            count,
+            total_len,
+            checksum: checksum(chunk),
+            data: chunk.to_vec(),
+        })
+        .collect()
+}
+
+pub fn is_chunk_frame(frame: &[u8]) -> bool {
+    frame.starts_with(&CHUNK_FRAME_MARKER)
+}
+
+pub fn encode_chunk_frame(chunk: &NetChunk) -> bincode::Result<Vec<u8>> {
+    let mut frame = CHUNK_FRAME_MARKER.to_vec();
+    bincode::serialize_into(&mut frame, chunk)?;
+    Ok(frame)
+}
+
+pub fn decode_chunk_frame(frame: &[u8]) -> anyhow::Result<NetChunk> {
+    let Some(data) = frame.strip_prefix(&CHUNK_FRAME_MARKER) else {
+        anyhow::bail!("Frame is not a chunk");
+    };
+    bincode::deserialize(data).map_err(|err| anyhow::format_err!("Invalid chunk frame: {err}"))
+}
+
+fn checksum(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+struct PendingTransfer {
+    count: u32,
+    total_len: u64,
+    chunks: Vec<Option<Vec<u8>>>,
+    received: u32,
+    started_at: Instant,
+}
+
+/// Collects chunks received over a single connection. Chunks of different
+/// transfers may interleave and arrive in any order. Chunks are checked
+/// against the message length before anything is allocated for them.
+pub struct ChunkAssembler {
+    config: ChunkedTransferConfig,
+    chunk_size: usize,
+    transfers: HashMap<u64, PendingTransfer>,
+    // Sum of the message lengths of the pending transfers
+    pending_bytes: u64,
+}
+
+impl ChunkAssembler {
+    pub fn new(config: ChunkedTransferConfig) -> Self {
+        Self::with_chunk_size(config, CHUNK_SIZE)
+    }
+
+    fn with_chunk_size(config: ChunkedTransferConfig, chunk_size: usize) -> Self {
+        Self { config, chunk_size, transfers: HashMap::new(), pending_bytes: 0 }
+    }
+
+    /// Returns the reassembled data and the time since its first chunk
+    /// arrived once the last missing chunk was added.
+    pub fn add(&mut self, chunk: NetChunk) -> anyhow::Result<Option<(Vec<u8>, Duration)>> {
+        if chunk.total_len > self.config.max_message_size {
+            anyhow::bail!("Chunked message is too large: {} bytes", chunk.total_len);
+        }
+        let chunk_size = self.chunk_size as u64;
+        let expected_count = chunk.total_len.div_ceil(chunk_size);
+        if chunk.total_len == 0 || chunk.count as u64 != expected_count {
+            anyhow::bail!(
+                "Chunk count {} does not match the message length {}",
+                chunk.count,
+                chunk.total_len
+            );
+        }
+        if chunk.index >= chunk.count {
+            anyhow::bail!("Chunk {} is out of range 0..{}", chunk.index, chunk.count);
+        }
+        let expected_len = if chunk.index + 1 == chunk.count {
+            chunk.total_len - chunk.index as u64 * chunk_size
+        } else {
+            chunk_size
+        };
+        if chunk.data.len() as u64 != expected_len {
+            anyhow::bail!(
+                "Chunk {} of transfer {} has length {}, expected {}",
+                chunk.index,
+                chunk.transfer_id,
+                chunk.data.len(),
+                expected_len
+            );
+        }
+        if checksum(&chunk.data) != chunk.checksum {
+            anyhow::bail!(
+                "Chunk {} of transfer {} has invalid checksum",
+                chunk.index,
+                chunk.transfer_id
+            );
+        }
+        self.remove_expired();
+
+        if !self.transfers.contains_key(&chunk.transfer_id) {
+            if self.transfers.len() >= MAX_PENDING_TRANSFERS {
+                anyhow::bail!("Too many pending chunked transfers");
+            }
+            if self.pending_bytes + chunk.total_len > self.config.max_pending_bytes {
+                anyhow::bail!(
+                    "Pending chunked transfers exceed {} bytes",
+                    self.config.max_pending_bytes
+                );
+            }
+            self.pending_bytes += chunk.total_len;
+        }
+        let transfer = self.transfers.entry(chunk.transfer_id).or_insert_with(|| PendingTransfer {
+            count: chunk.count,
+            total_len: chunk.total_len,
+            chunks: vec![None; chunk.count as usize],
+            received: 0,
+            started_at: Instant::now(),
+        });
+        if transfer.count != chunk.count || transfer.total_len != chunk.total_len {
+            self.remove(chunk.transfer_id);
+            anyhow::bail!(
+                "Chunk {} of transfer {} does not match it",
+                chunk.index,
+                chunk.transfer_id
+            );
+        }
+        let slot = &mut transfer.chunks[chunk.index as usize];
+        // Retransmitted duplicates are ignored
+        if slot.is_none() {
+            *slot = Some(chunk.data);
+            transfer.received += 1;
+        }
+        if transfer.received < transfer.count {
+            return Ok(None);
+        }
+
+        let Some(transfer) = self.remove(chunk.transfer_id) else {
+            return Ok(None);
+        };
+        let mut data = Vec::with_capacity(transfer.total_len as usize);
+        for chunk in transfer.chunks.into_iter().flatten() {
+            data.extend_from_slice(&chunk);
+        }
+        if data.len() as u64 != transfer.total_len {
+            anyhow::bail!(
+                "Transfer {} length mismatch: expected {}, actual {}",
+                chunk.transfer_id,
+                transfer.total_len,
+                data.len()
+            );
+        }
+        Ok(Some((data, transfer.started_at.elapsed())))
+    }
+
+    fn remove(&mut self, transfer_id: u64) -> Option<PendingTransfer> {
+        let transfer = self.transfers.remove(&transfer_id)?;
+        self.pending_bytes -= transfer.total_len;
+        Some(transfer)
+    }
+
+    fn remove_expired(&mut self) {
+        let pending_bytes = &mut self.pending_bytes;
+        self.transfers.retain(|transfer_id, transfer| {
+            let is_expired = transfer.started_at.elapsed() > PENDING_TRANSFER_TIMEOUT;
+            if is_expired {
+                tracing::warn!(
+                    transfer_id,
+                    received = transfer.received,
+                    count = transfer.count,
+                    "Chunked transfer expired"
+                );
+                *pending_bytes -= transfer.total_len;
+            }
+            !is_expired
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_are_reassembled_in_any_order() {
+        let data = (0..1000u32).flat_map(|x| x.to_le_bytes()).collect::<Vec<_>>();
+        let mut chunks = split_into_chunks(1, &data, 300);
+        assert_eq!(chunks.len(), 14);
+        chunks.reverse();
+        let last = chunks.pop().unwrap();
+
+        let mut assembler = ChunkAssembler::with_chunk_size(ChunkedTransferConfig::default(), 300);
+        for chunk in chunks {
+            let frame = encode_chunk_frame(&chunk).unwrap();
+            assert!(is_chunk_frame(&frame));
+            assert_eq!(assembler.add(decode_chunk_frame(&frame).unwrap()).unwrap(), None);
+        }
+        let (assembled, _) = assembler.add(last).unwrap().unwrap();
+        assert_eq!(assembled, data);
+    }
+
+    #[test]
+    fn test_chunks_of_resent_message_are_merged() {
+        let data = vec![7u8; 1000];
+        let chunks = split_into_chunks(transfer_id(&data), &data, 400);
+        let mut assembler = ChunkAssembler::with_chunk_size(ChunkedTransferConfig::default(), 400);
+        assert_eq!(assembler.add(chunks[0].clone()).unwrap(), None);
+
+        let mut corrupted = chunks[1].clone();
+        corrupted.data[0] = 0;
+        assert!(assembler.add(corrupted).is_err());
+
+        assert_eq!(assembler.add(chunks[2].clone()).unwrap(), None);
+        // Received chunks are kept, the missing one is added when the message
+        // is sent again
+        let resent = split_into_chunks(transfer_id(&data), &data, 400);
+        assert_eq!(assembler.add(resent[0].clone()).unwrap(), None);
+        let (assembled, _) = assembler.add(resent[1].clone()).unwrap().unwrap();
+        assert_eq!(assembled, data);
+        assert_eq!(assembler.pending_bytes, 0);
+    }
+
+    #[test]
+    fn test_chunks_are_checked_before_allocation() {
+        let config = ChunkedTransferConfig { max_message_size: 2000, max_pending_bytes: 2500 };
+        let mut assembler = ChunkAssembler::with_chunk_size(config, 400);
+        let data = vec![1u8; 1000];
+        let chunk = split_into_chunks(1, &data, 400).remove(0);
+
+        let huge_count = NetChunk { count: u32::MAX, ..chunk.clone() };
+        assert!(assembler.add(huge_count).is_err());
+        let short_data = NetChunk { data: vec![1u8; 10], checksum: checksum(&[1u8; 10]), ..chunk };
+        assert!(assembler.add(short_data).is_err());
+        let too_large = vec![1u8; 2400];
+        assert!(assembler.add(split_into_chunks(2, &too_large, 400).remove(0)).is_err());
+        assert!(assembler.transfers.is_empty());
+
+        // The second transfer doesn't fit into the pending bytes
+        assert_eq!(assembler.add(split_into_chunks(3, &data, 400).remove(0)).unwrap(), None);
+        assert!(assembler.add(split_into_chunks(4, &[2u8; 1600], 400).remove(0)).is_err());
+        assert_eq!(assembler.add(split_into_chunks(5, &data, 400).remove(0)).unwrap(), None);
+        assert_eq!(assembler.pending_bytes, 2000);
+    }
+}
@@ -4,20 +4,60 @@
 use std::collections::HashSet;
 use std::fmt::Debug;
 use std::net::SocketAddr;
+use std::time::Duration;
 
 use transport_layer::NetCredential;
 use transport_layer::TlsCertCache;
 
+use crate::adaptive_capacity::CapacityTuningConfig;
+use crate::backpressure::BackpressureConfig;
+use crate::chunked_transfer::ChunkedTransferConfig;
 use crate::pub_sub::CertFile;
 use crate::pub_sub::CertStore;
 use crate::pub_sub::PrivateKeyFile;
 
+const DEFAULT_SRV_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+const DEFAULT_SEND_BUFFER_SIZE: usize = 100;
+
 #[derive(Clone, PartialEq)]
 pub struct NetworkConfig {
     pub bind: SocketAddr,
     pub credential: NetCredential,
     pub subscribe: Vec<Vec<SocketAddr>>,
     pub proxies: Vec<SocketAddr>,
+    /// Subscription without incoming messages for this duration is considered
+    /// dead and is re-established (preferring alternate publisher addresses).
+    /// `None` disables the check.
+    pub subscription_silence_timeout: Option<Duration>,
+    /// SRV record names resolved into additional subscribe entries.
+    pub subscribe_srv: Vec<String>,
+    /// Interval to re-resolve `subscribe_srv` records.
+    pub srv_refresh_interval: Duration,
+    /// Hex encoded thread identifiers requested from publishers at
+    /// subscription time. Empty list means all threads.
+    pub subscribe_threads: Vec<String>,
+    /// Interval to check whether the OS routes outgoing connections through
+    /// another local address (e.g. after a failover of the egress link).
+    /// Outgoing connections are migrated to the new address instead of being
+    /// re-established, incoming ones are closed so the peers reconnect at
+    /// once. `None` disables the check.
+    pub connection_migration_check_interval: Option<Duration>,
+    /// Capacity of the per peer buffers of the direct sender.
+    pub send_buffer_size: usize,
+    /// Bounds the send buffer capacity is adjusted within from its observed
+    /// occupancy. `None` keeps it fixed at `send_buffer_size`.
+    pub send_buffer_tuning: Option<CapacityTuningConfig>,
+    /// Sustained delivery delays of a subscription are reported to the
+    /// publisher, which then sends only small messages until recovered.
+    /// `None` disables the advisories.
+    pub backpressure: Option<BackpressureConfig>,
+    /// Nodes claim their peer ids on the connections they establish and the
+    /// listener rejects peers whose claim doesn't match the certificate key.
+    /// Peers of previous versions don't support the claims, so it is enabled
+    /// only after all peers are upgraded.
+    pub enforce_peer_identities: bool,
+    /// Limits of the chunked messages received from the peers.
+    pub chunked_transfer: ChunkedTransferConfig,
 }
 
 impl Debug for NetworkConfig {
@@ -46,7 +86,23 @@ impl NetworkConfig {
             my_key,
             trusted_ed_pubkeys: peer_ed_pubkeys,
             trusted_cert_hashes: peer_certs.cert_hashes(),
+            reject_unknown_peers: false,
         };
-        Ok(Self { bind, credential, subscribe, proxies })
+        Ok(Self {
+            bind,
+            credential,
+            subscribe,
+            proxies,
+            subscription_silence_timeout: None,
+            subscribe_srv: vec![],
+            srv_refresh_interval: DEFAULT_SRV_REFRESH_INTERVAL,
+            subscribe_threads: vec![],
+            connection_migration_check_interval: None,
+            send_buffer_size: DEFAULT_SEND_BUFFER_SIZE,
+            send_buffer_tuning: None,
+            backpressure: None,
+            enforce_peer_identities: false,
+            chunked_transfer: ChunkedTransferConfig::default(),
+        })
     }
 }
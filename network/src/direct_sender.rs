@@ -11,12 +11,17 @@ use transport_layer::NetConnection;
 use transport_layer::NetCredential;
 use transport_layer::NetTransport;
 
+use crate::adaptive_capacity::AdaptiveCapacity;
+use crate::channel::PeerRtts;
 use crate::config::NetworkConfig;
 use crate::detailed;
 use crate::host_id_prefix;
 use crate::message::NetMessage;
 use crate::metrics::NetMetrics;
 use crate::network::PeerData;
+use crate::peer_identity::send_identity_claim;
+use crate::protocol_version::connection_protocol_version;
+use crate::protocol_version::supported_alpns;
 use crate::pub_sub::connection::connection_remote_host_id;
 use crate::pub_sub::start_critical_task_ex;
 use crate::transfer::transfer;
@@ -36,6 +41,7 @@ impl DirectPeer {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn run_direct_sender<Transport, PeerId>(
     mut shutdown_rx: tokio::sync::watch::Receiver<bool>,
     mut network_config_rx: tokio::sync::watch::Receiver<NetworkConfig>,
@@ -43,6 +49,9 @@ pub async fn run_direct_sender<Transport, PeerId>(
     metrics: Option<NetMetrics>,
     mut messages_rx: tokio::sync::mpsc::UnboundedReceiver<(PeerId, NetMessage, Instant)>,
     peers_rx: tokio::sync::watch::Receiver<HashMap<PeerId, PeerData>>,
+    peer_rtts: PeerRtts<PeerId>,
+    self_peer_id: PeerId,
+    send_buffer: AdaptiveCapacity,
 ) where
     Transport: NetTransport + 'static,
     PeerId: Display + Hash + Eq + Clone + Send + Sync + 'static,
@@ -84,7 +93,8 @@ pub async fn run_direct_sender<Transport, PeerId>(
                 let messages_tx = if let Some(peer) = peers.get(&peer_id) {
                     &peer.messages_tx
                 } else {
-                    let (peer_messages_tx, peer_messages_rx) = tokio::sync::mpsc::channel(100);
+                    let (peer_messages_tx, peer_messages_rx) =
+                        tokio::sync::mpsc::channel(send_buffer.max());
                     start_critical_task_ex(
                         "Direct peer sender",
                         peer_id.clone(),
@@ -97,13 +107,28 @@ pub async fn run_direct_sender<Transport, PeerId>(
                             peer_messages_rx,
                             peers_rx.clone(),
                             network_config.credential.clone(),
+                            peer_rtts.clone(),
+                            network_config
+                                .enforce_peer_identities
+                                .then(|| self_peer_id.to_string()),
                         ),
                     );
                     peers.insert(peer_id.clone(), DirectPeer::new(peer_messages_tx));
                     &peers.get(&peer_id).unwrap().messages_tx
                 };
                 let label = net_message.label.clone();
-                let is_sent = match messages_tx.try_send((net_message, buffer_duration)) {
+                // The channel is allocated with the maximum capacity, the
+                // current limit is applied here
+                let occupancy = messages_tx.max_capacity() - messages_tx.capacity();
+                let send_result = if send_buffer.record(occupancy) {
+                    Err(tokio::sync::mpsc::error::TrySendError::Full((
+                        net_message,
+                        buffer_duration,
+                    )))
+                } else {
+                    messages_tx.try_send((net_message, buffer_duration))
+                };
+                let is_sent = match send_result {
                     Ok(()) => true,
                     Err(tokio::sync::mpsc::error::TrySendError::Full(_)) => {
                         tracing::error!(
@@ -143,6 +168,7 @@ pub async fn run_direct_sender<Transport, PeerId>(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn peer_sender<Transport, PeerId>(
     mut shutdown_rx: tokio::sync::watch::Receiver<bool>,
     transport: Transport,
@@ -151,6 +177,8 @@ async fn peer_sender<Transport, PeerId>(
     mut messages_rx: tokio::sync::mpsc::Receiver<(NetMessage, Instant)>,
     mut peers_rx: tokio::sync::watch::Receiver<HashMap<PeerId, PeerData>>,
     credential: NetCredential,
+    peer_rtts: PeerRtts<PeerId>,
+    self_peer_id: Option<String>,
 ) -> anyhow::Result<()>
 where
     PeerId: Display + Hash + Eq + Clone + Send + Sync + 'static,
@@ -187,6 +215,19 @@ where
                 }
             }
         };
+        if let Some(self_peer_id) = &self_peer_id {
+            if let Err(err) = send_identity_claim(&connection, self_peer_id).await {
+                tracing::error!(
+                    peer_id = peer_id.to_string(),
+                    addr = addr.to_string(),
+                    "Failed to send identity claim: {}",
+                    detailed(&err)
+                );
+                connection.close(0).await;
+                continue;
+            }
+        }
+        let protocol_version = connection_protocol_version(&connection);
         let (transfer_result_tx, mut transfer_result_rx) = tokio::sync::mpsc::channel(10);
         loop {
             tokio::select! {
@@ -227,7 +268,7 @@ where
                                 "Message delivery: outgoing transfer started"
                             );
                             let transfer_duration = Instant::now();
-                            let transfer_result = transfer(&connection, &net_message, &metrics).await;
+                            let transfer_result = transfer(&connection, &net_message, protocol_version, &metrics).await;
                             metrics.as_ref().inspect(|x|x.finish_delivery_phase(
                                 DeliveryPhase::OutgoingTransfer,
                                 1,
@@ -256,8 +297,16 @@ where
                                 "Message delivery: outgoing transfer finished"
                             );
                             metrics.as_ref().inspect(|m| {
-                                m.report_sent_bytes(bytes_sent, &message.label, SendMode::Direct);
+                                m.report_sent_bytes(
+                                    bytes_sent,
+                                    &peer_id.to_string(),
+                                    &message.label,
+                                    SendMode::Direct,
+                                );
                             });
+                            if let Some(rtt) = connection.rtt() {
+                                peer_rtts.write().insert(peer_id.clone(), rtt);
+                            }
                         }
                         (Err(err), net_message, _) => {
                             tracing::error!(
@@ -272,6 +321,7 @@ where
                             metrics.as_ref().inspect(|x| {
                                 x.report_outgoing_transfer_error(&net_message.label, SendMode::Direct, err);
                             });
+                            peer_rtts.write().remove(&peer_id);
                             connection.close(0).await;
                             break;
                         }
@@ -315,10 +365,11 @@ where
     let mut attempt = 0;
     let mut retry_timeout = tokio_retry::strategy::FibonacciBackoff::from_millis(100)
         .max_delay(Duration::from_secs(60 * 60));
+    let alpn = supported_alpns(&[ACKI_NACKI_DIRECT_PROTOCOL]);
+    let alpn = alpn.iter().map(String::as_str).collect::<Vec<_>>();
     loop {
         for addr in addrs {
-            match transport.connect(*addr, &[ACKI_NACKI_DIRECT_PROTOCOL], credential.clone()).await
-            {
+            match transport.connect(*addr, &alpn, credential.clone()).await {
                 Ok(connection) => {
                     let host_id = connection_remote_host_id(&connection);
                     tracing::trace!(
@@ -12,23 +12,36 @@ use serde::Deserializer;
 use serde::Serialize;
 use serde::Serializer;
 
+pub mod adaptive_capacity;
+pub mod backpressure;
+pub mod bandwidth;
 pub mod channel;
+pub mod chunked_transfer;
 pub mod cli;
 pub mod config;
 mod direct_sender;
 pub mod message;
 pub mod metrics;
 pub mod network;
+pub mod peer_identity;
+pub mod port_mapping;
+pub mod protocol_version;
 pub mod pub_sub;
+pub mod reachability;
 pub mod resolver;
+#[cfg(any(test, feature = "network_simulator"))]
+pub mod simulator;
+pub mod srv_discovery;
 #[cfg(test)]
 pub mod tests;
+pub mod thread_filter;
 pub mod transfer;
 pub mod unix_signals;
 
 const ACKI_NACKI_DIRECT_PROTOCOL: &str = "acki-nacki-direct";
 const ACKI_NACKI_SUBSCRIPTION_FROM_NODE_PROTOCOL: &str = "acki-nacki-subscription-from-node";
 const ACKI_NACKI_SUBSCRIPTION_FROM_PROXY_PROTOCOL: &str = "acki-nacki-subscription-from-proxy";
+const ACKI_NACKI_REACHABILITY_PROTOCOL: &str = "acki-nacki-reachability";
 const DEFAULT_PUBLISHER_PORT: u16 = 8500;
 
 #[derive(Copy, Clone)]
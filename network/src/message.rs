@@ -9,6 +9,8 @@ use serde::Deserialize;
 use serde::Serialize;
 use telemetry_utils::now_ms;
 
+use crate::protocol_version::PROTOCOL_VERSION;
+
 const MAX_UNCOMPRESSED_SIZE: usize = 1000;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,8 +21,20 @@ pub struct NetMessage {
     pub compressed: bool,
     pub data: Arc<Vec<u8>>,
     pub last_sender_is_proxy: bool,
+    /// Hex encoded identifier of the thread the message belongs to. Used by
+    /// publishers to filter messages for thread-restricted subscribers.
+    /// It's not a part of the message layout, versioned frames carry it.
+    #[serde(skip)]
+    pub thread_id: Option<String>,
     #[serde(skip)]
     pub received_at: u64,
+    /// Protocol version of the frame the message was received in
+    #[serde(skip)]
+    pub protocol_version: u16,
+    /// Oldest protocol version of the receiver that decodes the message,
+    /// peers of the older versions don't get it.
+    #[serde(skip)]
+    pub min_protocol_version: u16,
 }
 
 impl NetMessage {
@@ -88,7 +102,10 @@ impl NetMessage {
                 label,
                 compressed,
                 last_sender_is_proxy: false,
+                thread_id: None,
                 received_at: u64::default(),
+                protocol_version: PROTOCOL_VERSION,
+                min_protocol_version: 0,
             },
             uncompressed_size,
         ))
@@ -10,6 +10,8 @@ use opentelemetry::metrics::UpDownCounter;
 use opentelemetry::KeyValue;
 use telemetry_utils::out_of_bounds_guard;
 
+use crate::backpressure::BackpressureAdvisory;
+use crate::bandwidth::BandwidthLedger;
 use crate::transfer::TransportError;
 use crate::DeliveryPhase;
 use crate::SendMode;
@@ -27,6 +29,12 @@ pub struct NetMetrics {
     outgoing_transfer_duration: Histogram<u64>,
     outgoing_transfer_error: Counter<u64>,
     subscriber_count: Gauge<u64>,
+    silent_subscriptions: Counter<u64>,
+    migrated_connections: Counter<u64>,
+    backpressure_advisories: Counter<u64>,
+    slow_consumer_skipped: Counter<u64>,
+    buffer_capacity: Gauge<u64>,
+    buffer_capacity_adjustments: Counter<u64>,
     transfer_after_ser: Histogram<u64>,
     receive_before_deser: Histogram<u64>,
     original_message_size: Histogram<u64>,
@@ -43,6 +51,8 @@ pub struct NetMetrics {
     _network_incoming_transfer_inflight: ObservableGauge<u64>,
     _outgoing_transfer_inflight: ObservableGauge<u64>,
     state: Arc<parking_lot::Mutex<NetworkState>>,
+    // Per peer traffic, not exported as attributes to keep the cardinality low
+    bandwidth: BandwidthLedger,
 }
 
 #[derive(Default)]
@@ -166,11 +176,22 @@ impl NetMetrics {
                 .u64_counter("node_network_outgoing_transfer_error")
                 .build(),
             subscriber_count: meter.u64_gauge("node_network_subscriber_count").build(),
+            silent_subscriptions: meter.u64_counter("node_network_silent_subscriptions").build(),
+            migrated_connections: meter.u64_counter("node_network_migrated_connections").build(),
+            backpressure_advisories: meter
+                .u64_counter("node_network_backpressure_advisories")
+                .build(),
+            slow_consumer_skipped: meter.u64_counter("node_network_slow_consumer_skipped").build(),
+            buffer_capacity: meter.u64_gauge("node_network_buffer_capacity").build(),
+            buffer_capacity_adjustments: meter
+                .u64_counter("node_network_buffer_capacity_adjustments")
+                .build(),
             _incoming_buffer_size: network_incoming_buffer_size,
             _outgoing_buffer_size: network_outgoing_buffer_size,
             _network_incoming_transfer_inflight: network_incoming_transfer_inflight,
             _outgoing_transfer_inflight: network_outgoing_transfer_inflight,
             state,
+            bandwidth: BandwidthLedger::default(),
         }
     }
 
@@ -204,6 +225,39 @@ impl NetMetrics {
         self.subscriber_count.record(value as u64, &[]);
     }
 
+    pub fn report_silent_subscriptions(&self, value: usize) {
+        self.silent_subscriptions.add(value as u64, &[]);
+    }
+
+    pub fn report_migrated_connections(&self, value: usize) {
+        self.migrated_connections.add(value as u64, &[]);
+    }
+
+    /// Advisories sent to the publishers or received from the subscribers
+    pub fn report_backpressure_advisory(&self, advisory: &BackpressureAdvisory, received: bool) {
+        let state = match advisory {
+            BackpressureAdvisory::Slow { .. } => "slow",
+            BackpressureAdvisory::Recovered => "recovered",
+        };
+        let direction = if received { "received" } else { "sent" };
+        self.backpressure_advisories
+            .add(1, &[KeyValue::new("state", state), KeyValue::new("direction", direction)]);
+    }
+
+    pub fn report_slow_consumer_skipped(&self, msg_type: &str) {
+        self.slow_consumer_skipped.add(1, &[msg_type_attr(msg_type)]);
+    }
+
+    pub fn report_buffer_capacity(&self, buffer: &'static str, capacity: usize) {
+        self.buffer_capacity.record(capacity as u64, &[KeyValue::new("buffer", buffer)]);
+    }
+
+    pub fn report_buffer_capacity_adjustment(&self, buffer: &'static str, grown: bool) {
+        let direction = if grown { "grow" } else { "shrink" };
+        self.buffer_capacity_adjustments
+            .add(1, &[KeyValue::new("buffer", buffer), KeyValue::new("direction", direction)]);
+    }
+
     pub fn report_transfer_after_ser(&self, value: u128) {
         out_of_bounds_guard!(value, "transfer_after_ser");
         self.transfer_after_ser.record(value as u64, &[]);
@@ -248,12 +302,24 @@ impl NetMetrics {
         self.sent_to_outgoing_buffer_bytes.add(bytes, &attrs(msg_type, send_mode));
     }
 
-    pub fn report_sent_bytes(&self, bytes: usize, msg_type: &str, send_mode: SendMode) {
+    pub fn report_sent_bytes(&self, bytes: usize, peer: &str, msg_type: &str, send_mode: SendMode) {
         self.sent_bytes.add(bytes as u64, &attrs(msg_type, send_mode));
+        self.bandwidth.record_sent(peer, msg_type, bytes);
     }
 
-    pub fn report_received_bytes(&self, bytes: usize, msg_type: &str, send_mode: SendMode) {
+    pub fn report_received_bytes(
+        &self,
+        bytes: usize,
+        peer: &str,
+        msg_type: &str,
+        send_mode: SendMode,
+    ) {
         self.received_bytes.add(bytes as u64, &attrs(msg_type, send_mode));
+        self.bandwidth.record_received(peer, msg_type, bytes);
+    }
+
+    pub fn bandwidth(&self) -> &BandwidthLedger {
+        &self.bandwidth
     }
 
     pub fn start_delivery_phase(
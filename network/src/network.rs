@@ -7,6 +7,8 @@ use std::fmt::Display;
 use std::hash::Hash;
 use std::net::SocketAddr;
 use std::str::FromStr;
+use std::time::Duration;
+use std::time::Instant;
 
 use chitchat::ChitchatRef;
 use serde::Serialize;
@@ -15,13 +17,18 @@ use telemetry_utils::mpsc::InstrumentedChannelMetrics;
 use telemetry_utils::mpsc::InstrumentedReceiver;
 use transport_layer::NetTransport;
 
+use crate::adaptive_capacity::run_capacity_tuning;
+use crate::adaptive_capacity::AdaptiveCapacity;
+use crate::bandwidth::log_top_talkers;
 use crate::channel::NetBroadcastSender;
 use crate::channel::NetDirectSender;
+use crate::channel::PeerRtts;
 use crate::config::NetworkConfig;
 use crate::detailed;
 use crate::direct_sender;
 use crate::message::NetMessage;
 use crate::metrics::NetMetrics;
+use crate::peer_identity::PeerIdentities;
 use crate::pub_sub::connection::IncomingMessage;
 use crate::pub_sub::connection::OutgoingMessage;
 use crate::pub_sub::spawn_critical_task;
@@ -29,6 +36,7 @@ use crate::pub_sub::IncomingSender;
 use crate::resolver::watch_gossip;
 use crate::resolver::SubscribeStrategy;
 use crate::resolver::WatchGossipConfig;
+use crate::srv_discovery;
 
 pub const BROADCAST_RETENTION_CAPACITY: usize = 100;
 const DEFAULT_MAX_CONNECTIONS: usize = 1000;
@@ -85,6 +93,7 @@ impl<Transport: NetTransport + 'static> BasicNetwork<Transport> {
         let (subscribe_tx, subscribe_rx) = tokio::sync::watch::channel(Vec::new());
         let (peers_tx, peers_rx) = tokio::sync::watch::channel(HashMap::new());
         let (gossip_subscribe_tx, gossip_subscribe_rx) = tokio::sync::watch::channel(Vec::new());
+        let (identities_tx, identities_rx) = tokio::sync::watch::channel(PeerIdentities::new());
         tokio::spawn(combine_subscribe(
             self.shutdown_tx.subscribe(),
             self.config_rx.clone(),
@@ -101,6 +110,7 @@ impl<Transport: NetTransport + 'static> BasicNetwork<Transport> {
                 chitchat.clone(),
                 gossip_subscribe_tx,
                 peers_tx,
+                identities_tx,
                 metrics.clone(),
             ),
         );
@@ -111,6 +121,8 @@ impl<Transport: NetTransport + 'static> BasicNetwork<Transport> {
         let transport_clone = self.transport.clone();
         let shutdown_rx_clone = self.shutdown_tx.subscribe();
         let config_rx_clone = self.config_rx.clone();
+        let self_peer_id_clone = (!is_proxy && self.config_rx.borrow().enforce_peer_identities)
+            .then(|| self_peer_id.to_string());
         spawn_critical_task("Pub/Sub", async move {
             if let Err(e) = crate::pub_sub::run(
                 shutdown_rx_clone,
@@ -122,6 +134,8 @@ impl<Transport: NetTransport + 'static> BasicNetwork<Transport> {
                 subscribe_rx,
                 outgoing_broadcast_tx_clone,
                 IncomingSender::SyncUnbounded(incoming_tx),
+                self_peer_id_clone,
+                identities_rx,
             )
             .await
             {
@@ -129,10 +143,32 @@ impl<Transport: NetTransport + 'static> BasicNetwork<Transport> {
             }
         });
 
+        if let Some(metrics) = metrics.clone() {
+            tokio::spawn(log_top_talkers(self.shutdown_tx.subscribe(), metrics));
+        }
+
         // listen for outgoing directed messages
+        let peer_rtts = PeerRtts::default();
         let peers_rx_clone = peers_rx.clone();
         let metrics_clone = metrics.clone();
         let transport_clone = self.transport.clone();
+        let (send_buffer_size, send_buffer_tuning) = {
+            let config = self.config_rx.borrow();
+            (config.send_buffer_size, config.send_buffer_tuning)
+        };
+        let send_buffer = AdaptiveCapacity::new(
+            "direct_send_buffer",
+            send_buffer_size,
+            send_buffer_tuning.as_ref(),
+        );
+        if let Some(tuning) = send_buffer_tuning.filter(|_| send_buffer.is_tuned()) {
+            tokio::spawn(run_capacity_tuning(
+                self.shutdown_tx.subscribe(),
+                vec![send_buffer.clone()],
+                Duration::from_secs(tuning.interval_secs.max(1)),
+                metrics.clone(),
+            ));
+        }
         spawn_critical_task(
             "Direct sender",
             direct_sender::run_direct_sender(
@@ -142,6 +178,9 @@ impl<Transport: NetTransport + 'static> BasicNetwork<Transport> {
                 metrics_clone,
                 outgoing_direct_rx,
                 peers_rx_clone,
+                peer_rtts.clone(),
+                self_peer_id.clone(),
+                send_buffer,
             ),
         );
 
@@ -150,6 +189,7 @@ impl<Transport: NetTransport + 'static> BasicNetwork<Transport> {
                 outgoing_direct_tx,
                 metrics.clone(),
                 self_peer_id,
+                peer_rtts,
             ),
             NetBroadcastSender::<Message>::new(outgoing_broadcast_tx, metrics.clone()),
             incoming_rx,
@@ -166,14 +206,48 @@ async fn combine_subscribe(
 ) {
     let mut network_config = network_config_rx.borrow().clone();
     let mut gossip_subscribe = gossip_subscribe_rx.borrow().clone();
+    let resolver = match srv_discovery::resolver() {
+        Ok(resolver) => Some(resolver),
+        Err(err) => {
+            tracing::error!("Failed to create DNS resolver for subscribe SRV records: {err}");
+            None
+        }
+    };
+    let mut srv_resolved = HashMap::new();
+    let mut srv_subscribe = Vec::new();
+    let mut srv_resolve_at = Instant::now();
     loop {
-        if !network_config.subscribe.is_empty() {
-            subscribe_tx.send_replace(network_config.subscribe.clone());
+        if let Some(resolver) = resolver.as_ref() {
+            if !network_config.subscribe_srv.is_empty() && srv_resolve_at <= Instant::now() {
+                srv_subscribe = srv_discovery::resolve_publishers(
+                    resolver,
+                    &network_config.subscribe_srv,
+                    &mut srv_resolved,
+                )
+                .await;
+                srv_resolve_at = Instant::now() + network_config.srv_refresh_interval;
+            }
+        }
+        let configured_subscribe = if network_config.subscribe_srv.is_empty() {
+            network_config.subscribe.clone()
+        } else {
+            network_config.subscribe.iter().cloned().chain(srv_subscribe.iter().cloned()).collect()
+        };
+        let subscribe = if !configured_subscribe.is_empty() {
+            configured_subscribe
         } else if !network_config.proxies.is_empty() {
-            subscribe_tx.send_replace(vec![network_config.proxies.clone()]);
+            vec![network_config.proxies.clone()]
         } else {
-            subscribe_tx.send_replace(gossip_subscribe.clone());
+            gossip_subscribe.clone()
         };
+        subscribe_tx.send_if_modified(|current| {
+            if *current != subscribe {
+                *current = subscribe;
+                true
+            } else {
+                false
+            }
+        });
         tokio::select! {
             sender = shutdown_rx.changed() => if sender.is_err() || *shutdown_rx.borrow() {
                 return;
@@ -181,13 +255,19 @@ async fn combine_subscribe(
             sender = network_config_rx.changed() => if sender.is_err() {
                 return;
             } else {
+                let subscribe_srv = network_config.subscribe_srv.clone();
                 network_config = network_config_rx.borrow().clone();
+                if network_config.subscribe_srv != subscribe_srv {
+                    srv_resolve_at = Instant::now();
+                }
             },
             sender = gossip_subscribe_rx.changed() => if sender.is_err() {
                 return;
             } else {
                 gossip_subscribe = gossip_subscribe_rx.borrow().clone();
-            }
+            },
+            _ = tokio::time::sleep_until(srv_resolve_at.into()),
+                if resolver.is_some() && !network_config.subscribe_srv.is_empty() => {}
         }
     }
 }
@@ -0,0 +1,116 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde::Serialize;
+use transport_layer::NetConnection;
+use transport_layer::VerifyingKey;
+
+// Identity frames start with this marker, same as chunk and subscription
+// frames they can't be confused with a serialized NetMessage.
+const IDENTITY_FRAME_MARKER: [u8; 8] = *b"ANIDENT\0";
+
+/// Time the listener waits for the identity claim of a connected peer.
+pub const IDENTITY_CLAIM_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Connection close code sent to peers whose identity claim is rejected.
+pub const IDENTITY_REJECTED_CLOSE_CODE: usize = 403;
+
+/// Ed25519 keys each peer signed its gossip entry with, by peer id. Only keys
+/// from the trust list are included, so a peer id maps to the keys of the
+/// block keepers allowed to act as this peer.
+pub type PeerIdentities = HashMap<String, HashSet<VerifyingKey>>;
+
+/// Sent by a node right after it establishes a subscription or a direct
+/// connection, before any other frame. Listeners that don't enforce peer
+/// identities just ignore it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IdentityClaim {
+    pub peer_id: String,
+}
+
+pub fn is_identity_frame(frame: &[u8]) -> bool {
+    frame.starts_with(&IDENTITY_FRAME_MARKER)
+}
+
+pub fn encode_identity_frame(claim: &IdentityClaim) -> bincode::Result<Vec<u8>> {
+    let mut frame = IDENTITY_FRAME_MARKER.to_vec();
+    bincode::serialize_into(&mut frame, claim)?;
+    Ok(frame)
+}
+
+pub fn decode_identity_frame(frame: &[u8]) -> anyhow::Result<IdentityClaim> {
+    let Some(data) = frame.strip_prefix(&IDENTITY_FRAME_MARKER) else {
+        anyhow::bail!("Frame is not an identity claim");
+    };
+    bincode::deserialize(data).map_err(|err| anyhow::format_err!("Invalid identity frame: {err}"))
+}
+
+/// Checks the claimed peer id is bound to the ed25519 key from the remote
+/// certificate: the certificate extension binds the TLS key to the ed25519
+/// key and the signed gossip entry binds the ed25519 key to the peer id.
+pub fn verify_identity_claim(
+    claim: &IdentityClaim,
+    remote_ed_pubkey: &Option<VerifyingKey>,
+    identities: &PeerIdentities,
+) -> anyhow::Result<()> {
+    let Some(remote_ed_pubkey) = remote_ed_pubkey else {
+        anyhow::bail!("Peer {} presented a certificate without ed25519 key", claim.peer_id);
+    };
+    let Some(pubkeys) = identities.get(&claim.peer_id) else {
+        anyhow::bail!("Peer {} is unknown or not signed by a trusted key", claim.peer_id);
+    };
+    if !pubkeys.contains(remote_ed_pubkey) {
+        anyhow::bail!("Peer {} certificate key does not match its gossip key", claim.peer_id);
+    }
+    Ok(())
+}
+
+pub async fn send_identity_claim(
+    connection: &impl NetConnection,
+    peer_id: &str,
+) -> anyhow::Result<()> {
+    let claim = IdentityClaim { peer_id: peer_id.to_string() };
+    connection.send(&encode_identity_frame(&claim)?).await
+}
+
+/// Waits for the identity claim, it must be the first frame of the
+/// connection. Returns the verified peer id.
+pub async fn receive_identity_claim(
+    connection: &impl NetConnection,
+    remote_ed_pubkey: &Option<VerifyingKey>,
+    identities: &PeerIdentities,
+) -> anyhow::Result<String> {
+    let (frame, _) = tokio::time::timeout(IDENTITY_CLAIM_TIMEOUT, connection.recv())
+        .await
+        .map_err(|_| anyhow::format_err!("Peer did not send identity claim"))??;
+    let claim = decode_identity_frame(&frame)?;
+    verify_identity_claim(&claim, remote_ed_pubkey, identities)?;
+    Ok(claim.peer_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_identity_claim() {
+        let key = transport_layer::SigningKey::generate(&mut rand::rngs::OsRng).verifying_key();
+        let other = transport_layer::SigningKey::generate(&mut rand::rngs::OsRng).verifying_key();
+        let identities = PeerIdentities::from([("1".to_string(), HashSet::from([key]))]);
+
+        let claim = IdentityClaim { peer_id: "1".to_string() };
+        let frame = encode_identity_frame(&claim).unwrap();
+        assert!(is_identity_frame(&frame));
+        assert_eq!(decode_identity_frame(&frame).unwrap(), claim);
+
+        assert!(verify_identity_claim(&claim, &Some(key), &identities).is_ok());
+        assert!(verify_identity_claim(&claim, &Some(other), &identities).is_err());
+        assert!(verify_identity_claim(&claim, &None, &identities).is_err());
+        let claim = IdentityClaim { peer_id: "2".to_string() };
+        assert!(verify_identity_claim(&claim, &Some(key), &identities).is_err());
+    }
+}
@@ -0,0 +1,301 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use igd_next::aio::tokio::search_gateway;
+use igd_next::PortMappingProtocol;
+use igd_next::SearchOptions;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::net::UdpSocket;
+
+const NAT_PMP_PORT: u16 = 5351;
+const NAT_PMP_VERSION: u8 = 0;
+const NAT_PMP_OP_EXTERNAL_ADDRESS: u8 = 0;
+const NAT_PMP_OP_MAP_UDP: u8 = 1;
+const NAT_PMP_RESPONSE_OP: u8 = 128;
+// RFC 6886 starts with 250ms and doubles the timeout on every retry
+const NAT_PMP_INITIAL_TIMEOUT: Duration = Duration::from_millis(250);
+const NAT_PMP_TRIES: u32 = 5;
+const UPNP_SEARCH_TIMEOUT: Duration = Duration::from_secs(5);
+const PORT_MAPPING_DESCRIPTION: &str = "acki-nacki-node";
+// Failed mappings are retried with this interval
+const PORT_MAPPING_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PortMappingProtocolKind {
+    #[default]
+    None,
+    Upnp,
+    NatPmp,
+}
+
+/// Port mapping of the QUIC bind and gossip listen ports.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PortMappingConfig {
+    /// Defaults to "none"
+    #[serde(default)]
+    pub protocol: PortMappingProtocolKind,
+
+    /// NAT-PMP gateway. Defaults to the gateway of the default route.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gateway: Option<Ipv4Addr>,
+
+    /// Mapping lease, mappings are renewed at the half of the lease.
+    /// Defaults to 3600
+    #[serde(default = "default_lease_secs")]
+    pub lease_secs: u32,
+}
+
+impl Default for PortMappingConfig {
+    fn default() -> Self {
+        Self {
+            protocol: PortMappingProtocolKind::None,
+            gateway: None,
+            lease_secs: default_lease_secs(),
+        }
+    }
+}
+
+fn default_lease_secs() -> u32 {
+    3600
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortMapping {
+    pub local_addr: SocketAddr,
+    pub external_addr: SocketAddr,
+    pub lease: Duration,
+}
+
+/// Maps the UDP ports of the local addresses on the router (UPnP IGD or
+/// NAT-PMP) and renews the mappings until shutdown. External ports are
+/// requested equal to the local ones, so advertised addresses keep the local
+/// port numbers. Returns immediately if port mapping is disabled.
+pub async fn run_port_mapping(
+    config: PortMappingConfig,
+    local_addrs: Vec<SocketAddr>,
+    mut shutdown_rx: tokio::sync::watch::Receiver<bool>,
+) -> anyhow::Result<()> {
+    if config.protocol == PortMappingProtocolKind::None {
+        return Ok(());
+    }
+    let mut mapped = Vec::<PortMapping>::new();
+    loop {
+        let mut renew_in = Duration::from_secs(config.lease_secs.max(2) as u64 / 2);
+        for local_addr in &local_addrs {
+            match map_port(&config, *local_addr).await {
+                Ok(mapping) => {
+                    if !mapped.contains(&mapping) {
+                        tracing::info!(
+                            "Port mapped ({:?}): udp {} -> {}",
+                            config.protocol,
+                            mapping.local_addr,
+                            mapping.external_addr
+                        );
+                        mapped.retain(|m| m.local_addr != mapping.local_addr);
+                        mapped.push(mapping);
+                    }
+                    renew_in = renew_in.min(mapping.lease / 2);
+                }
+                Err(err) => {
+                    tracing::warn!(
+                        "Failed to map udp port {local_addr} ({:?}): {err}",
+                        config.protocol
+                    );
+                    renew_in = renew_in.min(PORT_MAPPING_RETRY_INTERVAL);
+                }
+            }
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(renew_in.max(Duration::from_secs(1))) => {}
+            sender = shutdown_rx.changed() => if sender.is_err() || *shutdown_rx.borrow() {
+                return Ok(());
+            },
+        }
+    }
+}
+
+async fn map_port(
+    config: &PortMappingConfig,
+    local_addr: SocketAddr,
+) -> anyhow::Result<PortMapping> {
+    match config.protocol {
+        PortMappingProtocolKind::None => anyhow::bail!("Port mapping is disabled"),
+        PortMappingProtocolKind::Upnp => map_port_upnp(local_addr, config.lease_secs).await,
+        PortMappingProtocolKind::NatPmp => {
+            let gateway = match config.gateway {
+                Some(gateway) => gateway,
+                None => default_gateway()?,
+            };
+            map_port_nat_pmp(gateway, local_addr, config.lease_secs).await
+        }
+    }
+}
+
+async fn map_port_upnp(local_addr: SocketAddr, lease_secs: u32) -> anyhow::Result<PortMapping> {
+    let gateway =
+        search_gateway(SearchOptions { timeout: Some(UPNP_SEARCH_TIMEOUT), ..Default::default() })
+            .await?;
+    // The router forwards to the LAN address, not to the unspecified bind
+    let local_addr = if local_addr.ip().is_unspecified() {
+        SocketAddr::new(transport_layer::route_local_ip(gateway.addr)?, local_addr.port())
+    } else {
+        local_addr
+    };
+    gateway
+        .add_port(
+            PortMappingProtocol::UDP,
+            local_addr.port(),
+            local_addr,
+            lease_secs,
+            PORT_MAPPING_DESCRIPTION,
+        )
+        .await?;
+    let external_ip = gateway.get_external_ip().await?;
+    Ok(PortMapping {
+        local_addr,
+        external_addr: SocketAddr::new(external_ip, local_addr.port()),
+        lease: Duration::from_secs(lease_secs as u64),
+    })
+}
+
+async fn map_port_nat_pmp(
+    gateway: Ipv4Addr,
+    local_addr: SocketAddr,
+    lease_secs: u32,
+) -> anyhow::Result<PortMapping> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    socket.connect((gateway, NAT_PMP_PORT)).await?;
+    let response =
+        nat_pmp_request(&socket, &[NAT_PMP_VERSION, NAT_PMP_OP_EXTERNAL_ADDRESS]).await?;
+    let external_ip = decode_nat_pmp_external_address(&response)?;
+    let request = encode_nat_pmp_map_request(local_addr.port(), local_addr.port(), lease_secs);
+    let response = nat_pmp_request(&socket, &request).await?;
+    let (external_port, lease_secs) = decode_nat_pmp_map_response(&response, local_addr.port())?;
+    Ok(PortMapping {
+        local_addr,
+        external_addr: SocketAddr::new(IpAddr::V4(external_ip), external_port),
+        lease: Duration::from_secs(lease_secs as u64),
+    })
+}
+
+async fn nat_pmp_request(socket: &UdpSocket, request: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut timeout = NAT_PMP_INITIAL_TIMEOUT;
+    let mut buf = [0u8; 16];
+    for _ in 0..NAT_PMP_TRIES {
+        socket.send(request).await?;
+        if let Ok(received) = tokio::time::timeout(timeout, socket.recv(&mut buf)).await {
+            return Ok(buf[..received?].to_vec());
+        }
+        timeout *= 2;
+    }
+    anyhow::bail!("NAT-PMP gateway did not respond")
+}
+
+fn check_nat_pmp_response(response: &[u8], op: u8, len: usize) -> anyhow::Result<()> {
+    anyhow::ensure!(response.len() >= len, "NAT-PMP response is too short: {}", response.len());
+    anyhow::ensure!(
+        response[0] == NAT_PMP_VERSION && response[1] == NAT_PMP_RESPONSE_OP + op,
+        "Unexpected NAT-PMP response: version {} op {}",
+        response[0],
+        response[1]
+    );
+    let result_code = u16::from_be_bytes([response[2], response[3]]);
+    anyhow::ensure!(result_code == 0, "NAT-PMP gateway returned result code {result_code}");
+    Ok(())
+}
+
+fn decode_nat_pmp_external_address(response: &[u8]) -> anyhow::Result<Ipv4Addr> {
+    check_nat_pmp_response(response, NAT_PMP_OP_EXTERNAL_ADDRESS, 12)?;
+    Ok(Ipv4Addr::new(response[8], response[9], response[10], response[11]))
+}
+
+fn encode_nat_pmp_map_request(internal_port: u16, external_port: u16, lease_secs: u32) -> [u8; 12] {
+    let mut request = [0u8; 12];
+    request[0] = NAT_PMP_VERSION;
+    request[1] = NAT_PMP_OP_MAP_UDP;
+    request[4..6].copy_from_slice(&internal_port.to_be_bytes());
+    request[6..8].copy_from_slice(&external_port.to_be_bytes());
+    request[8..12].copy_from_slice(&lease_secs.to_be_bytes());
+    request
+}
+
+/// Returns the mapped external port and the granted lease.
+fn decode_nat_pmp_map_response(response: &[u8], internal_port: u16) -> anyhow::Result<(u16, u32)> {
+    check_nat_pmp_response(response, NAT_PMP_OP_MAP_UDP, 16)?;
+    let mapped_internal_port = u16::from_be_bytes([response[8], response[9]]);
+    anyhow::ensure!(
+        mapped_internal_port == internal_port,
+        "NAT-PMP gateway mapped port {mapped_internal_port} instead of {internal_port}"
+    );
+    let external_port = u16::from_be_bytes([response[10], response[11]]);
+    let lease_secs = u32::from_be_bytes([response[12], response[13], response[14], response[15]]);
+    Ok((external_port, lease_secs))
+}
+
+/// Gateway of the default IPv4 route.
+fn default_gateway() -> anyhow::Result<Ipv4Addr> {
+    let routes = std::fs::read_to_string("/proc/net/route").map_err(|e| {
+        anyhow::format_err!("Failed to read routes, set the port mapping gateway: {e}")
+    })?;
+    parse_default_gateway(&routes)
+        .ok_or_else(|| anyhow::format_err!("Default route not found, set the port mapping gateway"))
+}
+
+// `/proc/net/route` columns: Iface Destination Gateway ..., addresses are
+// little endian hex
+fn parse_default_gateway(routes: &str) -> Option<Ipv4Addr> {
+    routes.lines().skip(1).find_map(|line| {
+        let mut columns = line.split_whitespace().skip(1);
+        let destination = u32::from_str_radix(columns.next()?, 16).ok()?;
+        let gateway = u32::from_str_radix(columns.next()?, 16).ok()?;
+        (destination == 0 && gateway != 0).then(|| Ipv4Addr::from(gateway.swap_bytes()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nat_pmp_messages() {
+        assert_eq!(
+            encode_nat_pmp_map_request(8500, 8500, 3600),
+            [0, 1, 0, 0, 0x21, 0x34, 0x21, 0x34, 0, 0, 0x0e, 0x10]
+        );
+
+        let response = [0, 128, 0, 0, 0, 0, 0, 1, 203, 0, 113, 7];
+        assert_eq!(
+            decode_nat_pmp_external_address(&response).unwrap(),
+            Ipv4Addr::new(203, 0, 113, 7)
+        );
+
+        let mut response = [0, 129, 0, 0, 0, 0, 0, 1, 0x21, 0x34, 0x21, 0x35, 0, 0, 0x07, 0x08];
+        assert_eq!(decode_nat_pmp_map_response(&response, 8500).unwrap(), (8501, 1800));
+        assert!(decode_nat_pmp_map_response(&response, 8501).is_err());
+        // Not authorized
+        response[3] = 2;
+        assert!(decode_nat_pmp_map_response(&response, 8500).is_err());
+        assert!(decode_nat_pmp_map_response(&response[..12], 8500).is_err());
+    }
+
+    #[test]
+    fn test_parse_default_gateway() {
+        let routes = "\
+Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask
+eth0\t0000A8C0\t00000000\t0001\t0\t0\t0\t00FFFFFF
+eth0\t00000000\t0100A8C0\t0003\t0\t0\t0\t00000000
+";
+        assert_eq!(parse_default_gateway(routes), Some(Ipv4Addr::new(192, 168, 0, 1)));
+        assert_eq!(
+            parse_default_gateway(routes.lines().take(2).collect::<Vec<_>>().join("\n").as_str()),
+            None
+        );
+    }
+}
@@ -0,0 +1,202 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use serde::Deserialize;
+use serde::Serialize;
+use transport_layer::NetConnection;
+
+use crate::message::NetMessage;
+
+/// Version of the `NetMessage` wire format. It's bumped on any change of the
+/// message or the frames that nodes of the previous version can't decode.
+pub const PROTOCOL_VERSION: u16 = 2;
+
+/// First version that accepts chunk frames.
+pub const CHUNKED_TRANSFER_VERSION: u16 = 1;
+
+/// First version that accepts subscription requests and carries message
+/// threads in the frame extension.
+pub const THREAD_FILTER_VERSION: u16 = 1;
+
+/// First version that decodes authority switch skip votes.
+pub const SKIP_VOTE_VERSION: u16 = 2;
+
+/// Oldest version a node still talks to: nodes of the previous version are
+/// accepted, so the fleet can be upgraded one node at a time. Version 0 is
+/// the unversioned protocol of nodes released before the negotiation.
+pub const MIN_PROTOCOL_VERSION: u16 = PROTOCOL_VERSION.saturating_sub(1);
+
+// Versioned frames start with this marker followed by the version, as with
+// chunk frames a serialized NetMessage can't start with it.
+const VERSIONED_FRAME_MARKER: [u8; 8] = *b"ANVERSN\0";
+const ALPN_VERSION_SEPARATOR: &str = "/v";
+
+pub fn is_supported_version(version: u16) -> bool {
+    (MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&version)
+}
+
+/// Chunk frames are sent only to peers that negotiated a version knowing them.
+pub fn supports_chunked_transfer(version: u16) -> bool {
+    version >= CHUNKED_TRANSFER_VERSION
+}
+
+/// Subscription requests are sent only to publishers that negotiated a
+/// version knowing them, others would fail to decode the frame.
+pub fn supports_thread_filter(version: u16) -> bool {
+    version >= THREAD_FILTER_VERSION
+}
+
+/// Skip votes are broadcast only to peers that negotiated a version knowing
+/// them, others would fail to decode the message.
+pub fn supports_skip_vote(version: u16) -> bool {
+    version >= SKIP_VOTE_VERSION
+}
+
+/// Fields of the versioned frame that are not a part of the `NetMessage`
+/// layout, so it stays the same for the unversioned protocol.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct FrameExtension {
+    thread_id: Option<String>,
+}
+
+/// ALPN of the protocol for the given version, version 0 has no suffix.
+pub fn versioned_alpn(protocol: &str, version: u16) -> String {
+    if version == 0 {
+        protocol.to_string()
+    } else {
+        format!("{protocol}{ALPN_VERSION_SEPARATOR}{version}")
+    }
+}
+
+/// ALPNs of the protocols for all supported versions, newest first: the
+/// newest version both sides support is negotiated.
+pub fn supported_alpns(protocols: &[&str]) -> Vec<String> {
+    (MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION)
+        .rev()
+        .flat_map(|version| protocols.iter().map(move |protocol| versioned_alpn(protocol, version)))
+        .collect()
+}
+
+/// Splits the negotiated ALPN into the protocol and its version.
+pub fn split_alpn(alpn: &str) -> (&str, u16) {
+    alpn.rsplit_once(ALPN_VERSION_SEPARATOR)
+        .and_then(|(protocol, version)| Some((protocol, version.parse().ok()?)))
+        .unwrap_or((alpn, 0))
+}
+
+/// Version negotiated for the connection. Connections without negotiated
+/// ALPN use the unversioned protocol.
+pub fn connection_protocol_version(connection: &impl NetConnection) -> u16 {
+    connection.alpn_negotiated().map(|alpn| split_alpn(&alpn).1).unwrap_or(0)
+}
+
+/// Encodes the message in the format of the version negotiated with the
+/// receiver.
+pub fn encode_versioned_frame(message: &NetMessage, version: u16) -> bincode::Result<Vec<u8>> {
+    if version == 0 {
+        return bincode::serialize(message);
+    }
+    let mut frame = VERSIONED_FRAME_MARKER.to_vec();
+    frame.extend_from_slice(&version.to_le_bytes());
+    let extension = FrameExtension { thread_id: message.thread_id.clone() };
+    bincode::serialize_into(&mut frame, &extension)?;
+    bincode::serialize_into(&mut frame, message)?;
+    Ok(frame)
+}
+
+/// Decodes a message of any supported version. Messages of other versions
+/// are rejected before deserialization.
+pub fn decode_versioned_frame(frame: &[u8]) -> anyhow::Result<NetMessage> {
+    let (version, mut data) = match frame.strip_prefix(&VERSIONED_FRAME_MARKER) {
+        Some(rest) if rest.len() >= 2 => (u16::from_le_bytes([rest[0], rest[1]]), &rest[2..]),
+        Some(_) => anyhow::bail!("Truncated versioned frame"),
+        None => (0, frame),
+    };
+    anyhow::ensure!(
+        is_supported_version(version),
+        "Unsupported protocol version {version}, supported {MIN_PROTOCOL_VERSION}..={}",
+        PROTOCOL_VERSION
+    );
+    let extension = if version == 0 {
+        FrameExtension::default()
+    } else {
+        bincode::deserialize_from::<_, FrameExtension>(&mut data).map_err(|err| {
+            anyhow::format_err!("Invalid frame extension of version {version}: {err}")
+        })?
+    };
+    let mut message: NetMessage = bincode::deserialize(data)
+        .map_err(|err| anyhow::format_err!("Invalid message of version {version}: {err}"))?;
+    message.thread_id = extension.thread_id;
+    message.protocol_version = version;
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_versioned_frames() {
+        assert_eq!(split_alpn("acki-nacki-direct"), ("acki-nacki-direct", 0));
+        assert_eq!(split_alpn(&versioned_alpn("acki-nacki-direct", 3)), ("acki-nacki-direct", 3));
+        let alpns = supported_alpns(&["acki-nacki-direct"]);
+        assert_eq!(alpns.first(), Some(&versioned_alpn("acki-nacki-direct", PROTOCOL_VERSION)));
+        assert_eq!(alpns.len(), (PROTOCOL_VERSION - MIN_PROTOCOL_VERSION + 1) as usize);
+        assert!(!supports_chunked_transfer(0));
+        assert!(supports_chunked_transfer(PROTOCOL_VERSION));
+        assert!(!supports_skip_vote(THREAD_FILTER_VERSION));
+        assert!(supports_skip_vote(PROTOCOL_VERSION));
+
+        let (mut message, _) = NetMessage::encode(&"test".to_string()).unwrap();
+        message.thread_id = Some("ab01".to_string());
+        for version in [PROTOCOL_VERSION, MIN_PROTOCOL_VERSION] {
+            let frame = encode_versioned_frame(&message, version).unwrap();
+            let decoded = decode_versioned_frame(&frame).unwrap();
+            assert_eq!(decoded.id, message.id);
+            assert_eq!(decoded.protocol_version, version);
+            let thread_id = supports_thread_filter(version).then(|| "ab01".to_string());
+            assert_eq!(decoded.thread_id, thread_id);
+        }
+        let frame = encode_versioned_frame(&message, PROTOCOL_VERSION + 1).unwrap();
+        assert!(decode_versioned_frame(&frame).is_err());
+    }
+
+    /// `NetMessage` layout of the nodes released before the negotiation.
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct UnversionedNetMessage {
+        delivery_start_timestamp_ms: u64,
+        id: String,
+        label: String,
+        compressed: bool,
+        data: Vec<u8>,
+        last_sender_is_proxy: bool,
+    }
+
+    #[test]
+    fn test_unversioned_frame_layout() {
+        let (mut message, _) = NetMessage::encode(&"test".to_string()).unwrap();
+        message.thread_id = Some("ab01".to_string());
+        let unversioned = UnversionedNetMessage {
+            delivery_start_timestamp_ms: message.delivery_start_timestamp_ms,
+            id: message.id.clone(),
+            label: message.label.clone(),
+            compressed: message.compressed,
+            data: message.data.to_vec(),
+            last_sender_is_proxy: message.last_sender_is_proxy,
+        };
+        let frame = encode_versioned_frame(&message, 0).unwrap();
+        assert_eq!(frame, bincode::serialize(&unversioned).unwrap());
+        assert_eq!(bincode::deserialize::<UnversionedNetMessage>(&frame).unwrap(), unversioned);
+
+        // Unversioned frames are accepted while the previous version is 0
+        let decoded = decode_versioned_frame(&bincode::serialize(&unversioned).unwrap());
+        if is_supported_version(0) {
+            let decoded = decoded.unwrap();
+            assert_eq!(decoded.id, unversioned.id);
+            assert_eq!(decoded.protocol_version, 0);
+            assert_eq!(decoded.thread_id, None);
+        } else {
+            assert!(decoded.is_err());
+        }
+    }
+}
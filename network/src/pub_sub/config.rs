@@ -129,7 +129,7 @@ impl CertStore {
         Ok(Self { paths: paths.to_vec(), certs })
     }
 
-    pub(crate) fn cert_hashes(&self) -> HashSet<CertHash> {
+    pub fn cert_hashes(&self) -> HashSet<CertHash> {
         self.certs.iter().map(CertHash::from).collect()
     }
 }
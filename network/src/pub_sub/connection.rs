@@ -1,6 +1,7 @@
 use std::fmt::Debug;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use std::time::Instant;
 
 use ed25519_dalek::VerifyingKey;
@@ -9,14 +10,20 @@ use transport_layer::CertHash;
 use transport_layer::NetConnection;
 use transport_layer::NetTransport;
 
+use crate::backpressure::BackpressureAdvisory;
+use crate::backpressure::BackpressureConfig;
+use crate::backpressure::PressureDetector;
+use crate::chunked_transfer::ChunkedTransferConfig;
 use crate::detailed;
 use crate::host_id_prefix;
 use crate::message::NetMessage;
 use crate::metrics::NetMetrics;
+use crate::protocol_version::connection_protocol_version;
 use crate::pub_sub::receiver;
 use crate::pub_sub::sender;
 use crate::pub_sub::IncomingSender;
 use crate::pub_sub::PubSub;
+use crate::thread_filter::ThreadFilter;
 use crate::DeliveryPhase;
 use crate::SendMode;
 
@@ -66,9 +73,15 @@ pub struct ConnectionInfo {
     pub remote_addr: SocketAddr,
     pub remote_host_id: String,
     pub remote_host_id_prefix: String,
+    /// Peer id verified against the remote certificate, set only when the
+    /// listener enforces peer identities
+    pub remote_peer_id: Option<String>,
     pub remote_is_proxy: bool,
     pub remote_cert_hash: CertHash,
     pub remote_ed_pubkey: Option<VerifyingKey>,
+    /// Protocol version negotiated with the remote, messages are sent in
+    /// its format
+    pub protocol_version: u16,
 }
 
 impl ConnectionInfo {
@@ -88,12 +101,26 @@ impl ConnectionInfo {
         }
         info
     }
+
+    /// Verified peer id, or the host id prefix for the unverified peers.
+    pub fn remote_peer(&self) -> &str {
+        self.remote_peer_id.as_deref().unwrap_or(&self.remote_host_id_prefix)
+    }
 }
 
 #[derive(Debug)]
 pub struct ConnectionWrapper<Connection: NetConnection> {
     pub info: Arc<ConnectionInfo>,
     pub connection: Connection,
+    last_received: parking_lot::Mutex<Instant>,
+    // Threads requested by the remote subscriber
+    thread_filter: parking_lot::RwLock<ThreadFilter>,
+    // Message size limit advised by the remote subscriber under pressure
+    slow_consumer_limit: parking_lot::RwLock<Option<usize>>,
+    // Delivery delays of the messages from the remote publisher
+    pressure: Option<parking_lot::Mutex<PressureDetector>>,
+    // Limits of the chunked messages received from the peer
+    pub chunked_transfer: ChunkedTransferConfig,
 }
 
 pub fn connection_remote_host_id(connection: &impl NetConnection) -> String {
@@ -101,18 +128,23 @@ pub fn connection_remote_host_id(connection: &impl NetConnection) -> String {
 }
 
 impl<Connection: NetConnection> ConnectionWrapper<Connection> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         id: u64,
         local_is_proxy: bool,
         remote_addr: Option<SocketAddr>,
         remote_host_id: String,
+        remote_peer_id: Option<String>,
         remote_is_proxy: bool,
         connection: Connection,
         roles: ConnectionRoles,
+        backpressure: Option<BackpressureConfig>,
+        chunked_transfer: ChunkedTransferConfig,
     ) -> anyhow::Result<Self> {
         let remote_host_id_prefix = host_id_prefix(&remote_host_id).to_string();
         let cert =
             connection.remote_certificate().ok_or_else(|| anyhow::anyhow!("No certificate"))?;
+        let protocol_version = connection_protocol_version(&connection);
         Ok(Self {
             info: Arc::new(ConnectionInfo {
                 id,
@@ -124,23 +156,70 @@ impl<Connection: NetConnection> ConnectionWrapper<Connection> {
                 },
                 remote_host_id,
                 remote_host_id_prefix,
+                remote_peer_id,
                 remote_is_proxy,
                 remote_cert_hash: CertHash::from(&cert),
                 remote_ed_pubkey: get_ed_pubkey_from_cert_der(&cert)?,
+                protocol_version,
                 roles,
             }),
             connection,
+            last_received: parking_lot::Mutex::new(Instant::now()),
+            thread_filter: parking_lot::RwLock::new(ThreadFilter::default()),
+            slow_consumer_limit: parking_lot::RwLock::new(None),
+            pressure: backpressure
+                .filter(|_| roles.subscriber)
+                .map(|config| parking_lot::Mutex::new(PressureDetector::new(config))),
+            chunked_transfer,
         })
     }
 
+    pub fn report_received(&self) {
+        *self.last_received.lock() = Instant::now();
+    }
+
+    // Time since the last message was received (or since the connection was established)
+    pub fn silence(&self) -> Duration {
+        self.last_received.lock().elapsed()
+    }
+
+    pub fn set_thread_filter(&self, thread_filter: ThreadFilter) {
+        *self.thread_filter.write() = thread_filter;
+    }
+
+    pub fn apply_backpressure_advisory(&self, advisory: BackpressureAdvisory) {
+        *self.slow_consumer_limit.write() = match advisory {
+            BackpressureAdvisory::Slow { max_message_size } => Some(max_message_size),
+            BackpressureAdvisory::Recovered => None,
+        };
+    }
+
+    /// The remote subscriber advised it is too slow for this message.
+    pub fn is_too_large_for_slow_consumer(&self, outgoing: &OutgoingMessage) -> bool {
+        self.slow_consumer_limit.read().is_some_and(|limit| outgoing.message.data.len() > limit)
+    }
+
+    /// Returns an advisory for the remote publisher if the pressure state of
+    /// the subscription changed.
+    pub fn observe_delivery_delay(&self, delay: Duration) -> Option<BackpressureAdvisory> {
+        self.pressure.as_ref()?.lock().observe(delay, Instant::now())
+    }
+
     pub fn allow_sending(&self, outgoing: &OutgoingMessage) -> bool {
         if outgoing.message.last_sender_is_proxy && self.info.remote_is_proxy {
             return false;
         }
+        if self.info.protocol_version < outgoing.message.min_protocol_version {
+            return false;
+        }
+        let thread_allowed =
+            || self.thread_filter.read().allows(outgoing.message.thread_id.as_deref());
         match &outgoing.delivery {
-            MessageDelivery::Broadcast => self.info.roles.publisher,
+            MessageDelivery::Broadcast => self.info.roles.publisher && thread_allowed(),
             MessageDelivery::BroadcastExcluding(excluding) => {
-                self.info.roles.publisher && self.info.remote_host_id != excluding.remote_host_id
+                self.info.roles.publisher
+                    && self.info.remote_host_id != excluding.remote_host_id
+                    && thread_allowed()
             }
             MessageDelivery::Addr(addr) => self.info.remote_addr == *addr,
         }
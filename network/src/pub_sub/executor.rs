@@ -9,6 +9,7 @@ use super::trace_task_result;
 use super::PubSub;
 use crate::config::NetworkConfig;
 use crate::metrics::NetMetrics;
+use crate::peer_identity::PeerIdentities;
 use crate::pub_sub::connection::IncomingMessage;
 use crate::pub_sub::connection::OutgoingMessage;
 use crate::pub_sub::server::listen_incoming_connections;
@@ -46,10 +47,13 @@ pub async fn run<Transport: NetTransport + 'static>(
     outgoing_tx: broadcast::Sender<OutgoingMessage>,
     // pub sub forwards all received network messages to this sender
     incoming_tx: IncomingSender,
+    // claimed on established connections, proxies have no peer id
+    self_peer_id: Option<String>,
+    identities_rx: tokio::sync::watch::Receiver<PeerIdentities>,
 ) -> anyhow::Result<()> {
     tracing::info!("Starting server");
 
-    let pub_sub = PubSub::new(transport, is_proxy);
+    let pub_sub = PubSub::new(transport, is_proxy, self_peer_id, identities_rx);
 
     let (connection_closed_tx, connection_closed_rx) = mpsc::channel(100);
     let listen_incoming_connections_task = tokio::spawn(listen_incoming_connections(
@@ -28,11 +28,20 @@ use transport_layer::NetConnection;
 use transport_layer::NetCredential;
 use transport_layer::NetTransport;
 
+use crate::backpressure::BackpressureConfig;
+use crate::chunked_transfer::ChunkedTransferConfig;
 use crate::detailed;
 use crate::metrics::NetMetrics;
+use crate::peer_identity::send_identity_claim;
+use crate::peer_identity::PeerIdentities;
+use crate::protocol_version::connection_protocol_version;
+use crate::protocol_version::supported_alpns;
+use crate::protocol_version::supports_thread_filter;
 use crate::pub_sub::connection::connection_remote_host_id;
 use crate::pub_sub::connection::ConnectionInfo;
 use crate::pub_sub::connection::ConnectionRoles;
+use crate::thread_filter::encode_subscription_frame;
+use crate::thread_filter::SubscriptionRequest;
 use crate::ACKI_NACKI_SUBSCRIPTION_FROM_NODE_PROTOCOL;
 use crate::ACKI_NACKI_SUBSCRIPTION_FROM_PROXY_PROTOCOL;
 
@@ -40,6 +49,9 @@ use crate::ACKI_NACKI_SUBSCRIPTION_FROM_PROXY_PROTOCOL;
 pub struct PubSub<Transport: NetTransport + 'static> {
     pub transport: Transport,
     pub is_proxy: bool,
+    /// Peer id this node claims on the connections it establishes
+    pub self_peer_id: Option<String>,
+    pub identities_rx: tokio::sync::watch::Receiver<PeerIdentities>,
     inner: Arc<parking_lot::RwLock<PubSubInner<Transport::Connection>>>,
 }
 
@@ -58,10 +70,17 @@ impl<Connection: NetConnection> PubSubInner<Connection> {
 }
 
 impl<Transport: NetTransport> PubSub<Transport> {
-    pub fn new(transport: Transport, is_proxy: bool) -> Self {
+    pub fn new(
+        transport: Transport,
+        is_proxy: bool,
+        self_peer_id: Option<String>,
+        identities_rx: tokio::sync::watch::Receiver<PeerIdentities>,
+    ) -> Self {
         PubSub {
             transport,
             is_proxy,
+            self_peer_id,
+            identities_rx,
             inner: Arc::new(parking_lot::RwLock::new(PubSubInner::<Transport::Connection> {
                 next_connection_id: 1,
                 connections: HashMap::new(),
@@ -89,6 +108,87 @@ impl<Transport: NetTransport> PubSub<Transport> {
         diff(subscribed, subscribe)
     }
 
+    pub async fn disconnect_silent_subscriptions(
+        &self,
+        max_silence: std::time::Duration,
+    ) -> Vec<SocketAddr> {
+        let silent = {
+            let inner = self.inner.read();
+            inner
+                .connections
+                .values()
+                .filter(|x| x.info.roles.subscriber && x.silence() > max_silence)
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        let mut addrs = Vec::with_capacity(silent.len());
+        for connection in silent {
+            tracing::warn!(
+                peer = connection.info.remote_info(),
+                silence = connection.silence().as_millis(),
+                "Disconnect silent subscription"
+            );
+            connection.connection.close(0).await;
+            addrs.push(connection.info.remote_addr);
+        }
+        addrs
+    }
+
+    /// Moves connections whose route to the peer now goes through another
+    /// local IP. Outgoing connections are migrated keeping their sessions. A
+    /// QUIC server can't migrate its side of a connection, so incoming
+    /// connections (e.g. keepers subscribed to a producer) are closed right
+    /// away and the peers reconnect to the new address instead of waiting for
+    /// their silence timeout. Returns the number of moved connections.
+    pub async fn migrate_rerouted_connections(&self) -> usize {
+        let connections = {
+            let inner = self.inner.read();
+            inner.connections.values().cloned().collect::<Vec<_>>()
+        };
+        let mut migrated = 0;
+        for connection in connections {
+            let local_ip = connection.connection.local_addr().ip();
+            let route_ip = match transport_layer::route_local_ip(connection.info.remote_addr) {
+                Ok(ip) => ip,
+                Err(err) => {
+                    tracing::trace!(
+                        peer = connection.info.remote_info(),
+                        "Failed to resolve route: {err}"
+                    );
+                    continue;
+                }
+            };
+            if local_ip.is_unspecified() || local_ip == route_ip {
+                continue;
+            }
+            let roles = &connection.info.roles;
+            if !roles.subscriber && !roles.direct_sender {
+                tracing::info!(
+                    peer = connection.info.remote_info(),
+                    "Incoming connection rerouted from {local_ip} to {route_ip}, reconnecting"
+                );
+                connection.connection.close(0).await;
+                migrated += 1;
+                continue;
+            }
+            match connection.connection.migrate(SocketAddr::new(route_ip, 0)) {
+                Ok(()) => {
+                    tracing::info!(
+                        peer = connection.info.remote_info(),
+                        "Connection migrated from {local_ip} to {route_ip}"
+                    );
+                    migrated += 1;
+                }
+                Err(err) => tracing::warn!(
+                    peer = connection.info.remote_info(),
+                    "Failed to migrate connection from {local_ip} to {route_ip}: {}",
+                    detailed(&err)
+                ),
+            }
+        }
+        migrated
+    }
+
     pub async fn disconnect_untrusted(&self, credential: &NetCredential) {
         let untrusted = {
             let inner = self.inner.read();
@@ -120,14 +220,18 @@ impl<Transport: NetTransport> PubSub<Transport> {
         connection_closed: mpsc::Sender<Arc<ConnectionInfo>>,
         credential: NetCredential,
         publisher_addrs: Vec<SocketAddr>,
+        subscribe_threads: Vec<String>,
+        backpressure: Option<BackpressureConfig>,
+        chunked_transfer: ChunkedTransferConfig,
     ) -> anyhow::Result<()> {
+        let alpn = supported_alpns(&[if self.is_proxy {
+            ACKI_NACKI_SUBSCRIPTION_FROM_PROXY_PROTOCOL
+        } else {
+            ACKI_NACKI_SUBSCRIPTION_FROM_NODE_PROTOCOL
+        }]);
+        let alpn = alpn.iter().map(String::as_str).collect::<Vec<_>>();
         let (connection, peer_host_id, peer_addr) = 'connect: {
             for publisher_addr in publisher_addrs {
-                let alpn = [if self.is_proxy {
-                    ACKI_NACKI_SUBSCRIPTION_FROM_PROXY_PROTOCOL
-                } else {
-                    ACKI_NACKI_SUBSCRIPTION_FROM_NODE_PROTOCOL
-                }];
                 tracing::debug!(
                     publisher_addr = publisher_addr.to_string(),
                     "Connecting to publisher"
@@ -148,6 +252,22 @@ impl<Transport: NetTransport> PubSub<Transport> {
             return Err(anyhow::anyhow!("Failed to connect to peer: no more addrs"));
         };
 
+        if let Some(self_peer_id) = &self.self_peer_id {
+            send_identity_claim(&connection, self_peer_id).await?;
+        }
+
+        if !subscribe_threads.is_empty() {
+            if supports_thread_filter(connection_protocol_version(&connection)) {
+                let request = SubscriptionRequest { threads: subscribe_threads };
+                connection.send(&encode_subscription_frame(&request)?).await?;
+            } else {
+                tracing::warn!(
+                    peer = peer_addr.to_string(),
+                    "Publisher does not support thread filter, all threads are received"
+                );
+            }
+        }
+
         self.add_connection_handler(
             shutdown_rx,
             metrics.clone(),
@@ -156,9 +276,12 @@ impl<Transport: NetTransport> PubSub<Transport> {
             &connection_closed,
             connection,
             peer_host_id,
+            None,
             Some(peer_addr),
             false,
             ConnectionRoles::subscriber(),
+            backpressure,
+            chunked_transfer,
         )
     }
 
@@ -172,9 +295,12 @@ impl<Transport: NetTransport> PubSub<Transport> {
         connection_closed_tx: &mpsc::Sender<Arc<ConnectionInfo>>,
         connection: Transport::Connection,
         remote_host_id: String,
+        remote_peer_id: Option<String>,
         remote_addr: Option<SocketAddr>,
         remote_is_proxy: bool,
         roles: ConnectionRoles,
+        backpressure: Option<BackpressureConfig>,
+        chunked_transfer: ChunkedTransferConfig,
     ) -> anyhow::Result<()> {
         let id = { self.inner.write().generate_connection_id() };
         let connection = Arc::new(ConnectionWrapper::new(
@@ -182,9 +308,12 @@ impl<Transport: NetTransport> PubSub<Transport> {
             self.is_proxy,
             remote_addr,
             remote_host_id,
+            remote_peer_id,
             remote_is_proxy,
             connection,
             roles,
+            backpressure,
+            chunked_transfer,
         )?);
 
         let (outgoing_messages_tx, incoming_messages_tx) = if roles.publisher {
@@ -1,11 +1,18 @@
 use std::sync::Arc;
+use std::time::Duration;
 use std::time::Instant;
 
 use transport_layer::NetConnection;
 
+use crate::backpressure::encode_backpressure_frame;
+use crate::chunked_transfer::decode_chunk_frame;
+use crate::chunked_transfer::is_chunk_frame;
+use crate::chunked_transfer::ChunkAssembler;
 use crate::detailed;
-use crate::message::NetMessage;
 use crate::metrics::NetMetrics;
+use crate::peer_identity::is_identity_frame;
+use crate::protocol_version::decode_versioned_frame;
+use crate::protocol_version::supports_chunked_transfer;
 use crate::pub_sub::connection::ConnectionWrapper;
 use crate::pub_sub::connection::IncomingMessage;
 use crate::pub_sub::IncomingSender;
@@ -25,12 +32,13 @@ pub async fn receiver<Connection: NetConnection + 'static>(
         peer = connection.info.remote_info(),
         "Receiver loop started"
     );
+    let mut chunks = ChunkAssembler::new(connection.chunked_transfer);
     loop {
         tokio::select! {
             sender = shutdown_rx.changed() => if sender.is_err() || *shutdown_rx.borrow() {
                 break;
             },
-            _ = receive_message(metrics.clone(), connection.clone(), incoming_tx.clone(), receiver_stop_tx.clone()) => {
+            _ = receive_message(metrics.clone(), connection.clone(), &mut chunks, incoming_tx.clone(), receiver_stop_tx.clone()) => {
             },
             sender = receiver_stop_rx.changed() => if sender.is_err() || *receiver_stop_rx.borrow() {
                 break;
@@ -49,21 +57,62 @@ pub async fn receiver<Connection: NetConnection + 'static>(
 async fn receive_message<Connection: NetConnection + 'static>(
     metrics: Option<NetMetrics>,
     connection: Arc<ConnectionWrapper<Connection>>,
+    chunks: &mut ChunkAssembler,
     incoming_tx: IncomingSender,
     receiver_stop_tx: tokio::sync::watch::Sender<bool>,
 ) {
     let info = connection.info.clone();
     match connection.connection.recv().await {
         Ok((data, duration)) => {
-            let net_message = match bincode::deserialize::<NetMessage>(&data) {
+            connection.report_received();
+            let (data, duration) = if is_chunk_frame(&data) {
+                if !supports_chunked_transfer(info.protocol_version) {
+                    tracing::error!(
+                        peer = info.remote_info(),
+                        "Chunk frame on a connection of protocol version {}",
+                        info.protocol_version
+                    );
+                    return;
+                }
+                match decode_chunk_frame(&data).and_then(|chunk| chunks.add(chunk)) {
+                    Ok(Some(assembled)) => assembled,
+                    Ok(None) => return,
+                    Err(err) => {
+                        // The chunk is dropped, already received chunks of
+                        // the transfer are kept until it expires
+                        tracing::error!(
+                            peer = info.remote_info(),
+                            "Failed to accept message chunk: {}",
+                            detailed(&err)
+                        );
+                        return;
+                    }
+                }
+            } else {
+                (data, duration)
+            };
+            if is_identity_frame(&data) {
+                // Identity claims are verified by the listener before the
+                // connection is added, unverified ones are ignored
+                return;
+            }
+            let net_message = match decode_versioned_frame(&data) {
                 Ok(msg) => msg,
                 Err(err) => {
-                    tracing::error!("Failed to deserialize net message: {}", err);
+                    tracing::error!(
+                        peer = info.remote_info(),
+                        "Failed to decode net message: {}",
+                        detailed(&err)
+                    );
                     receiver_stop_tx.send_replace(true);
                     return;
                 }
             };
 
+            if let Ok(delay) = net_message.delivery_duration_ms() {
+                advise_publisher(&metrics, &connection, Duration::from_millis(delay)).await;
+            }
+
             let msg_type = net_message.label.clone();
             tracing::debug!(
                 broadcast = info.roles.is_broadcast(),
@@ -76,7 +125,12 @@ async fn receive_message<Connection: NetConnection + 'static>(
                 "Message delivery: incoming transfer finished",
             );
             metrics.as_ref().inspect(|x| {
-                x.report_received_bytes(data.len(), &msg_type, info.roles.send_mode());
+                x.report_received_bytes(
+                    data.len(),
+                    info.remote_peer(),
+                    &msg_type,
+                    info.roles.send_mode(),
+                );
                 x.start_delivery_phase(
                     DeliveryPhase::IncomingBuffer,
                     1,
@@ -117,3 +171,31 @@ async fn receive_message<Connection: NetConnection + 'static>(
         }
     }
 }
+
+// Reports the pressure state change of the subscription to the publisher
+async fn advise_publisher<Connection: NetConnection + 'static>(
+    metrics: &Option<NetMetrics>,
+    connection: &ConnectionWrapper<Connection>,
+    delay: Duration,
+) {
+    let Some(advisory) = connection.observe_delivery_delay(delay) else {
+        return;
+    };
+    tracing::warn!(
+        peer = connection.info.remote_info(),
+        delay = delay.as_millis(),
+        "Subscription pressure changed, advising publisher: {advisory:?}"
+    );
+    metrics.as_ref().inspect(|m| m.report_backpressure_advisory(&advisory, false));
+    let result = match encode_backpressure_frame(&advisory) {
+        Ok(frame) => connection.connection.send(&frame).await,
+        Err(err) => Err(err.into()),
+    };
+    if let Err(err) = result {
+        tracing::error!(
+            peer = connection.info.remote_info(),
+            "Failed to send backpressure advisory: {}",
+            detailed(&err)
+        );
+    }
+}
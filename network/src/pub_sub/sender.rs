@@ -3,10 +3,16 @@ use std::time::Instant;
 
 use transport_layer::NetConnection;
 
+use crate::backpressure::decode_backpressure_frame;
+use crate::backpressure::is_backpressure_frame;
 use crate::detailed;
 use crate::metrics::NetMetrics;
+use crate::peer_identity::is_identity_frame;
 use crate::pub_sub::connection::ConnectionWrapper;
 use crate::pub_sub::connection::OutgoingMessage;
+use crate::thread_filter::decode_subscription_frame;
+use crate::thread_filter::is_subscription_frame;
+use crate::thread_filter::ThreadFilter;
 use crate::transfer::transfer;
 use crate::DeliveryPhase;
 use crate::SendMode;
@@ -25,6 +31,9 @@ pub async fn sender<Connection: NetConnection + 'static>(
         peer = connection.info.remote_info(),
         "Sender loop started"
     );
+    // Subscribers can send a subscription request and backpressure advisories
+    // over the publisher connection
+    let mut wait_subscription_request = connection.info.roles.publisher;
     loop {
         tokio::select! {
             sender = shutdown_rx.changed() => if sender.is_err() || *shutdown_rx.borrow() {
@@ -36,6 +45,12 @@ pub async fn sender<Connection: NetConnection + 'static>(
             _ = connection.connection.watch_close() => {
                 break;
             },
+            frame = connection.connection.recv(), if wait_subscription_request => {
+                wait_subscription_request = frame.is_ok();
+                if let Ok((frame, _)) = frame {
+                    apply_subscriber_frame(&metrics, &connection, &frame);
+                }
+            },
             recv_result = outgoing_messages_rx.recv() => {
                 match recv_result {
                     Ok(message) => {
@@ -76,6 +91,57 @@ pub async fn sender<Connection: NetConnection + 'static>(
     Ok(())
 }
 
+fn apply_subscriber_frame<Connection: NetConnection + 'static>(
+    metrics: &Option<NetMetrics>,
+    connection: &ConnectionWrapper<Connection>,
+    frame: &[u8],
+) {
+    if is_identity_frame(frame) {
+        return;
+    }
+    if is_backpressure_frame(frame) {
+        match decode_backpressure_frame(frame) {
+            Ok(advisory) => {
+                tracing::warn!(
+                    peer = connection.info.remote_info(),
+                    "Subscriber advised backpressure: {advisory:?}"
+                );
+                metrics.as_ref().inspect(|m| m.report_backpressure_advisory(&advisory, true));
+                connection.apply_backpressure_advisory(advisory);
+            }
+            Err(err) => {
+                tracing::error!(
+                    peer = connection.info.remote_info(),
+                    "Invalid backpressure advisory: {}",
+                    detailed(&err)
+                );
+            }
+        }
+        return;
+    }
+    if !is_subscription_frame(frame) {
+        tracing::warn!(peer = connection.info.remote_info(), "Unexpected frame from subscriber");
+        return;
+    }
+    match decode_subscription_frame(frame) {
+        Ok(request) => {
+            tracing::info!(
+                peer = connection.info.remote_info(),
+                threads = request.threads.len(),
+                "Subscriber requested threads"
+            );
+            connection.set_thread_filter(ThreadFilter::from(&request));
+        }
+        Err(err) => {
+            tracing::error!(
+                peer = connection.info.remote_info(),
+                "Invalid subscription request: {}",
+                detailed(&err)
+            );
+        }
+    }
+}
+
 async fn send_message<Connection: NetConnection + 'static>(
     metrics: Option<NetMetrics>,
     connection: Arc<ConnectionWrapper<Connection>>,
@@ -94,6 +160,16 @@ async fn send_message<Connection: NetConnection + 'static>(
     if !connection.allow_sending(&outgoing) {
         return;
     }
+    if connection.is_too_large_for_slow_consumer(&outgoing) {
+        tracing::debug!(
+            host_id = connection.info.remote_host_id_prefix,
+            msg_id = outgoing.message.id,
+            msg_type = outgoing.message.label,
+            "Message delivery: skipped for slow subscriber"
+        );
+        metrics.as_ref().inspect(|m| m.report_slow_consumer_skipped(&outgoing.message.label));
+        return;
+    }
     outgoing.message.last_sender_is_proxy = connection.info.local_is_proxy;
     outgoing.message.id.push(':');
     outgoing.message.id.push_str(&connection.info.remote_host_id_prefix);
@@ -123,7 +199,13 @@ async fn send_message<Connection: NetConnection + 'static>(
         "Message delivery: outgoing transfer started"
     );
     let transfer_duration = Instant::now();
-    let transfer_result = transfer(&connection.connection, &outgoing.message, &metrics).await;
+    let transfer_result = transfer(
+        &connection.connection,
+        &outgoing.message,
+        connection.info.protocol_version,
+        &metrics,
+    )
+    .await;
     metrics.as_ref().inspect(|x| {
         x.finish_delivery_phase(
             DeliveryPhase::OutgoingTransfer,
@@ -145,7 +227,12 @@ async fn send_message<Connection: NetConnection + 'static>(
                 "Message delivery: outgoing transfer finished"
             );
             metrics.as_ref().inspect(|m| {
-                m.report_sent_bytes(bytes_sent, &outgoing.message.label, SendMode::Broadcast);
+                m.report_sent_bytes(
+                    bytes_sent,
+                    connection.info.remote_peer(),
+                    &outgoing.message.label,
+                    SendMode::Broadcast,
+                );
             });
         }
         Err(err) => {
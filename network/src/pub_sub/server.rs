@@ -2,21 +2,31 @@ use std::sync::Arc;
 
 use tokio::sync::broadcast;
 use tokio::sync::mpsc;
+use transport_layer::get_ed_pubkey_from_cert_der;
 use transport_layer::NetConnection;
+use transport_layer::NetCredential;
 use transport_layer::NetIncomingRequest;
 use transport_layer::NetListener;
 use transport_layer::NetTransport;
 
+use crate::chunked_transfer::ChunkedTransferConfig;
 use crate::config::NetworkConfig;
 use crate::detailed;
+use crate::host_id_prefix;
 use crate::metrics::NetMetrics;
+use crate::peer_identity::receive_identity_claim;
+use crate::peer_identity::IDENTITY_REJECTED_CLOSE_CODE;
+use crate::protocol_version::split_alpn;
+use crate::protocol_version::supported_alpns;
 use crate::pub_sub::connection::connection_remote_host_id;
 use crate::pub_sub::connection::ConnectionInfo;
 use crate::pub_sub::connection::ConnectionRoles;
 use crate::pub_sub::connection::OutgoingMessage;
 use crate::pub_sub::executor::IncomingSender;
 use crate::pub_sub::PubSub;
+use crate::reachability::serve_dial_back;
 use crate::ACKI_NACKI_DIRECT_PROTOCOL;
+use crate::ACKI_NACKI_REACHABILITY_PROTOCOL;
 use crate::ACKI_NACKI_SUBSCRIPTION_FROM_NODE_PROTOCOL;
 use crate::ACKI_NACKI_SUBSCRIPTION_FROM_PROXY_PROTOCOL;
 
@@ -36,19 +46,16 @@ where
 {
     let mut bind = network_config_rx.borrow().bind;
     let mut credential = network_config_rx.borrow().credential.clone();
+    let alpn_supported = supported_alpns(&[
+        ACKI_NACKI_SUBSCRIPTION_FROM_PROXY_PROTOCOL,
+        ACKI_NACKI_SUBSCRIPTION_FROM_NODE_PROTOCOL,
+        ACKI_NACKI_DIRECT_PROTOCOL,
+        ACKI_NACKI_REACHABILITY_PROTOCOL,
+    ]);
+    let alpn_supported = alpn_supported.iter().map(String::as_str).collect::<Vec<_>>();
     loop {
-        let listener = pub_sub
-            .transport
-            .create_listener(
-                bind,
-                &[
-                    ACKI_NACKI_SUBSCRIPTION_FROM_PROXY_PROTOCOL,
-                    ACKI_NACKI_SUBSCRIPTION_FROM_NODE_PROTOCOL,
-                    ACKI_NACKI_DIRECT_PROTOCOL,
-                ],
-                credential.clone(),
-            )
-            .await?;
+        let listener =
+            pub_sub.transport.create_listener(bind, &alpn_supported, credential.clone()).await?;
         tracing::info!("Start listening for incoming connections on {}", bind.to_string());
         tracing::info!("Pub sub started with host id {}", credential.identity_prefix());
         loop {
@@ -88,6 +95,9 @@ where
                     shutdown_rx.clone(),
                     pub_sub.clone(),
                     metrics.clone(),
+                    credential.clone(),
+                    enforce_peer_identities(&network_config_rx.borrow()),
+                    network_config_rx.borrow().chunked_transfer,
                     incoming_tx.clone(),
                     outgoing_messages.clone(),
                     connection_closed_tx.clone(),
@@ -104,10 +114,21 @@ where
     }
 }
 
+/// Peer identities are enforced when enabled and the trust list contains
+/// ed25519 keys: only then gossip entries are verified and bound to the peer
+/// ids.
+fn enforce_peer_identities(config: &NetworkConfig) -> bool {
+    config.enforce_peer_identities && !config.credential.trusted_ed_pubkeys.is_empty()
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_incoming_connection<Transport: NetTransport>(
     shutdown_rx: tokio::sync::watch::Receiver<bool>,
     pub_sub: PubSub<Transport>,
     metrics: Option<NetMetrics>,
+    credential: NetCredential,
+    enforce_identity: bool,
+    chunked_transfer: ChunkedTransferConfig,
     incoming_tx: IncomingSender,
     outgoing_messages: broadcast::Sender<OutgoingMessage>,
     connection_closed_tx: mpsc::Sender<Arc<ConnectionInfo>>,
@@ -129,16 +150,37 @@ pub async fn handle_incoming_connection<Transport: NetTransport>(
         "Incoming request accepted",
     );
 
-    let (role, remote_is_proxy) =
-        if connection.alpn_negotiated_is(ACKI_NACKI_SUBSCRIPTION_FROM_PROXY_PROTOCOL) {
-            (ConnectionRoles::publisher(), true)
-        } else if connection.alpn_negotiated_is(ACKI_NACKI_SUBSCRIPTION_FROM_NODE_PROTOCOL) {
-            (ConnectionRoles::publisher(), false)
-        } else {
-            (ConnectionRoles::direct_sender(), false)
-        };
+    let alpn = connection.alpn_negotiated().unwrap_or_default();
+    if split_alpn(&alpn).0 == ACKI_NACKI_REACHABILITY_PROTOCOL {
+        serve_dial_back(pub_sub.transport.clone(), credential, connection).await;
+        return;
+    }
+    let (role, remote_is_proxy) = match split_alpn(&alpn).0 {
+        ACKI_NACKI_SUBSCRIPTION_FROM_PROXY_PROTOCOL => (ConnectionRoles::publisher(), true),
+        ACKI_NACKI_SUBSCRIPTION_FROM_NODE_PROTOCOL => (ConnectionRoles::publisher(), false),
+        _ => (ConnectionRoles::direct_sender(), false),
+    };
     let host_id = connection_remote_host_id(&connection);
 
+    // Proxies relay messages of other peers and don't claim an identity
+    let remote_peer_id = if enforce_identity && !remote_is_proxy {
+        match verify_remote_identity(&pub_sub, &connection).await {
+            Ok(peer_id) => Some(peer_id),
+            Err(err) => {
+                tracing::warn!(
+                    remote_addr = %connection.remote_addr(),
+                    host_id = host_id_prefix(&host_id),
+                    "Peer identity rejected: {}",
+                    detailed(&err)
+                );
+                connection.close(IDENTITY_REJECTED_CLOSE_CODE).await;
+                return;
+            }
+        }
+    } else {
+        None
+    };
+
     if let Err(err) = pub_sub.add_connection_handler(
         shutdown_rx,
         metrics.clone(),
@@ -147,10 +189,23 @@ pub async fn handle_incoming_connection<Transport: NetTransport>(
         &connection_closed_tx,
         connection,
         host_id,
+        remote_peer_id,
         None,
         remote_is_proxy,
         role,
+        None,
+        chunked_transfer,
     ) {
         tracing::error!("Error adding connection: {}", detailed(&err));
     }
 }
+
+async fn verify_remote_identity<Transport: NetTransport>(
+    pub_sub: &PubSub<Transport>,
+    connection: &Transport::Connection,
+) -> anyhow::Result<String> {
+    let cert = connection.remote_certificate().ok_or_else(|| anyhow::anyhow!("No certificate"))?;
+    let remote_ed_pubkey = get_ed_pubkey_from_cert_der(&cert)?;
+    let identities = pub_sub.identities_rx.borrow().clone();
+    receive_identity_claim(connection, &remote_ed_pubkey, &identities).await
+}
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
@@ -6,6 +7,8 @@ use std::time::Duration;
 use tokio::sync::broadcast;
 use tokio::sync::mpsc;
 use tokio::task::JoinSet;
+use tokio::time::Interval;
+use tokio::time::MissedTickBehavior;
 use transport_layer::NetConnection;
 use transport_layer::NetTransport;
 
@@ -21,10 +24,27 @@ fn join_addrs(addrs: &[SocketAddr]) -> String {
     addrs.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(",")
 }
 
+// Moves addresses of recently silent publishers to the end, so alternates are tried first
+fn prefer_alternates(addrs: &[SocketAddr], demoted: &HashSet<SocketAddr>) -> Vec<SocketAddr> {
+    let mut addrs = addrs.to_vec();
+    addrs.sort_by_key(|addr| demoted.contains(addr));
+    addrs
+}
+
 fn task_addrs(addrs: &HashMap<tokio::task::Id, Vec<SocketAddr>>, id: tokio::task::Id) -> String {
     addrs.get(&id).map(|x| join_addrs(x)).unwrap_or_default()
 }
 
+// Restarts the check timer if its period was reconfigured. Disabled checks
+// tick once an hour
+fn reset_check_interval(interval: &mut Interval, period: Option<Duration>) {
+    let period = period.unwrap_or(Duration::from_secs(60 * 60));
+    if interval.period() != period {
+        *interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 pub async fn handle_subscriptions<Transport: NetTransport + 'static>(
     mut shutdown_rx: tokio::sync::watch::Receiver<bool>,
@@ -40,6 +60,11 @@ pub async fn handle_subscriptions<Transport: NetTransport + 'static>(
     tracing::trace!("Subscription loop started");
     let mut reason = "starting".to_string();
     let mut subscriptions = subscribe_rx.borrow().clone();
+    let mut demoted_publishers = HashSet::<SocketAddr>::new();
+    // Check timers live across the loop passes, otherwise every pass restarts
+    // them and they never fire. Their periods are set on the first pass
+    let mut silence_check = tokio::time::interval(Duration::MAX);
+    let mut migration_check = tokio::time::interval(Duration::MAX);
     loop {
         let count = subscriptions.iter().flatten().count();
         metrics.as_ref().inspect(|m| m.report_subscribers_count(count));
@@ -59,12 +84,30 @@ pub async fn handle_subscriptions<Transport: NetTransport + 'static>(
             connection.connection.close(0).await;
         }
 
-        let credential = network_config_rx.borrow().credential.clone();
+        let (
+            credential,
+            silence_timeout,
+            subscribe_threads,
+            migration_check_interval,
+            backpressure,
+            chunked_transfer,
+        ) = {
+            let config = network_config_rx.borrow();
+            (
+                config.credential.clone(),
+                config.subscription_silence_timeout,
+                config.subscribe_threads.clone(),
+                config.connection_migration_check_interval,
+                config.backpressure,
+                config.chunked_transfer,
+            )
+        };
         let mut successfully_subscribed = 0;
         let should_be_subscribed_len = should_be_subscribed.len();
         let mut connect_tasks = JoinSet::new();
         let mut addrs_by_task_id = HashMap::<tokio::task::Id, Vec<SocketAddr>>::new();
         for publisher_addrs in should_be_subscribed.clone() {
+            let publisher_addrs = prefer_alternates(&publisher_addrs, &demoted_publishers);
             let abort_handle = connect_tasks.spawn(pub_sub.clone().subscribe_to_publisher(
                 shutdown_rx.clone(),
                 metrics.clone(),
@@ -73,6 +116,9 @@ pub async fn handle_subscriptions<Transport: NetTransport + 'static>(
                 connection_closed_tx.clone(),
                 credential.clone(),
                 publisher_addrs.clone(),
+                subscribe_threads.clone(),
+                backpressure,
+                chunked_transfer,
             ));
             addrs_by_task_id.insert(abort_handle.id(), publisher_addrs);
         }
@@ -114,10 +160,17 @@ pub async fn handle_subscriptions<Transport: NetTransport + 'static>(
             Duration::from_secs(60 * 60)
         };
 
+        let retry = tokio::time::sleep(sleep_duration);
+        tokio::pin!(retry);
+        reset_check_interval(&mut silence_check, silence_timeout.map(|x| x / 2));
+        reset_check_interval(&mut migration_check, migration_check_interval);
+
         // Waiting for one of:
         // - a subscribe list was changed
         // - 100 ms timeout after failed subscriptions
         // - our subscription connection was closed
+        // - our subscription is silent for too long
+        // Rerouted connections are migrated while waiting
         loop {
             tokio::select! {
                 sender = shutdown_rx.changed() => if sender.is_err() || *shutdown_rx.borrow() {
@@ -133,14 +186,32 @@ pub async fn handle_subscriptions<Transport: NetTransport + 'static>(
                     let new_subscriptions = subscribe_rx.borrow().clone();
                     if new_subscriptions != subscriptions {
                         subscriptions = new_subscriptions;
+                        demoted_publishers.clear();
                         reason = "subscribe changed".to_string();
                     } else {
                         continue;
                     }
                 },
-                _ = tokio::time::sleep(sleep_duration) => {
+                _ = &mut retry => {
                     reason = format!("{} failed to subscribe", should_be_subscribed_len - successfully_subscribed);
                 }
+                _ = silence_check.tick() => {
+                    let Some(silence_timeout) = silence_timeout else {
+                        continue;
+                    };
+                    let silent = pub_sub.disconnect_silent_subscriptions(silence_timeout).await;
+                    if silent.is_empty() {
+                        continue;
+                    }
+                    metrics.as_ref().inspect(|m| m.report_silent_subscriptions(silent.len()));
+                    reason = format!("{} silent subscriptions", silent.len());
+                    demoted_publishers.extend(silent);
+                }
+                _ = migration_check.tick() => {
+                    let migrated = pub_sub.migrate_rerouted_connections().await;
+                    metrics.as_ref().inspect(|m| m.report_migrated_connections(migrated));
+                    continue;
+                }
                 connection = connection_closed_rx.recv() => {
                     if let Some(connection) = connection {
                         // if the closed connection is not our subscription, continue waiting
@@ -164,3 +235,18 @@ fn diff_info(total: usize, include: usize, exclude: usize) -> String {
         (false, false) => format!(" {total}"),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefer_alternates() {
+        let a: SocketAddr = "127.0.0.1:1".parse().unwrap();
+        let b: SocketAddr = "127.0.0.1:2".parse().unwrap();
+        let c: SocketAddr = "127.0.0.1:3".parse().unwrap();
+        assert_eq!(prefer_alternates(&[a, b, c], &HashSet::new()), vec![a, b, c]);
+        assert_eq!(prefer_alternates(&[a, b, c], &HashSet::from([a])), vec![b, c, a]);
+        assert_eq!(prefer_alternates(&[a, b, c], &HashSet::from([a, b, c])), vec![a, b, c]);
+    }
+}
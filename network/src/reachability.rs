@@ -0,0 +1,189 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde::Serialize;
+use transport_layer::NetConnection;
+use transport_layer::NetCredential;
+use transport_layer::NetTransport;
+
+use crate::detailed;
+use crate::network::PeerData;
+use crate::protocol_version::supported_alpns;
+use crate::ACKI_NACKI_REACHABILITY_PROTOCOL;
+
+/// Time to connect, to dial back and to wait for the dial back result.
+pub const REACHABILITY_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+// Peers asked to dial back until one of them answers
+const REACHABILITY_CHECK_MAX_PEERS: usize = 3;
+// Time to wait for gossip peers if there are no configured ones
+const REACHABILITY_CHECK_PEERS_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct DialBackRequest {
+    port: u16,
+}
+
+/// Result of the dial back. The peer dials back only to the address the
+/// request came from, so it can't be used to probe third party hosts.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DialBackResponse {
+    /// Address the peer sees the request coming from
+    pub observed_addr: SocketAddr,
+    /// Address the peer dialed back: observed IP and requested port
+    pub dialed_addr: SocketAddr,
+    /// None if the dial back succeeded
+    pub error: Option<String>,
+}
+
+fn reachability_alpn() -> Vec<String> {
+    supported_alpns(&[ACKI_NACKI_REACHABILITY_PROTOCOL])
+}
+
+/// Asks the peer to dial back to the port of the advertised address.
+pub async fn request_dial_back<Transport: NetTransport>(
+    transport: &Transport,
+    credential: NetCredential,
+    peer: SocketAddr,
+    advertise_addr: SocketAddr,
+) -> anyhow::Result<DialBackResponse> {
+    let alpn = reachability_alpn();
+    let alpn = alpn.iter().map(String::as_str).collect::<Vec<_>>();
+    let connection = tokio::time::timeout(
+        REACHABILITY_CHECK_TIMEOUT,
+        transport.connect(peer, &alpn, credential),
+    )
+    .await
+    .map_err(|_| anyhow::format_err!("Connection timed out"))??;
+    let request = DialBackRequest { port: advertise_addr.port() };
+    let result = async {
+        connection.send(&bincode::serialize(&request)?).await?;
+        // The peer waits for the dial back before it answers
+        let (data, _) = tokio::time::timeout(REACHABILITY_CHECK_TIMEOUT * 2, connection.recv())
+            .await
+            .map_err(|_| anyhow::format_err!("Peer did not answer"))??;
+        Ok(bincode::deserialize::<DialBackResponse>(&data)?)
+    }
+    .await;
+    connection.close(0).await;
+    result
+}
+
+/// Serves an incoming reachability connection: a dial back request of a peer
+/// or a dial back of this node to the peer, which carries no request.
+pub(crate) async fn serve_dial_back<Transport: NetTransport>(
+    transport: Transport,
+    credential: NetCredential,
+    connection: Transport::Connection,
+) {
+    let Ok(Ok((data, _))) =
+        tokio::time::timeout(REACHABILITY_CHECK_TIMEOUT, connection.recv()).await
+    else {
+        connection.close(0).await;
+        return;
+    };
+    let request = match bincode::deserialize::<DialBackRequest>(&data) {
+        Ok(request) => request,
+        Err(err) => {
+            tracing::debug!("Invalid dial back request: {err}");
+            connection.close(0).await;
+            return;
+        }
+    };
+    let observed_addr = connection.remote_addr();
+    let dialed_addr = SocketAddr::new(observed_addr.ip(), request.port);
+    tracing::debug!("Dial back to {dialed_addr} requested");
+    let alpn = reachability_alpn();
+    let alpn = alpn.iter().map(String::as_str).collect::<Vec<_>>();
+    let error = match tokio::time::timeout(
+        REACHABILITY_CHECK_TIMEOUT,
+        transport.connect(dialed_addr, &alpn, credential),
+    )
+    .await
+    {
+        Ok(Ok(dialed)) => {
+            dialed.close(0).await;
+            None
+        }
+        Ok(Err(err)) => Some(detailed(&err)),
+        Err(_) => Some("Connection timed out".to_string()),
+    };
+    let response = DialBackResponse { observed_addr, dialed_addr, error };
+    match bincode::serialize(&response) {
+        Ok(data) => {
+            if let Err(err) = connection.send(&data).await {
+                tracing::debug!("Failed to send dial back response: {err}");
+            }
+        }
+        Err(err) => tracing::debug!("Failed to serialize dial back response: {err}"),
+    }
+    // The requester closes the connection when it gets the response
+    let _ = tokio::time::timeout(REACHABILITY_CHECK_TIMEOUT, connection.watch_close()).await;
+}
+
+/// Startup self-test: asks configured peers (or gossip peers if there are
+/// none) to dial back to the advertised address and reports the result.
+pub async fn run_reachability_self_test<Transport, PeerId>(
+    transport: Transport,
+    credential: NetCredential,
+    advertise_addr: SocketAddr,
+    static_peers: Vec<SocketAddr>,
+    mut peers_rx: tokio::sync::watch::Receiver<HashMap<PeerId, PeerData>>,
+) where
+    Transport: NetTransport,
+{
+    let mut peers = static_peers;
+    if peers.is_empty() {
+        let gossip_peers = tokio::time::timeout(
+            REACHABILITY_CHECK_PEERS_TIMEOUT,
+            peers_rx.wait_for(|peers| peers.values().any(|peer| peer.peer_addr != advertise_addr)),
+        )
+        .await;
+        if let Ok(Ok(gossip_peers)) = gossip_peers {
+            peers = gossip_peers
+                .values()
+                .map(|peer| peer.peer_addr)
+                .filter(|peer_addr| *peer_addr != advertise_addr)
+                .collect();
+        }
+    }
+    if peers.is_empty() {
+        tracing::warn!("Reachability self-test skipped: no peers to ask for a dial back");
+        return;
+    }
+    for peer in peers.into_iter().take(REACHABILITY_CHECK_MAX_PEERS) {
+        let response =
+            match request_dial_back(&transport, credential.clone(), peer, advertise_addr).await {
+                Ok(response) => response,
+                Err(err) => {
+                    tracing::warn!("Reachability self-test: peer {peer} failed: {err}");
+                    continue;
+                }
+            };
+        if response.observed_addr.ip() != advertise_addr.ip() {
+            tracing::warn!(
+                "Reachability self-test: advertised address {advertise_addr} differs from the \
+                address {} peer {peer} sees",
+                response.observed_addr
+            );
+        }
+        match response.error {
+            None => tracing::info!(
+                "Reachability self-test: {} is reachable from peer {peer}",
+                response.dialed_addr
+            ),
+            Some(err) => tracing::warn!(
+                "Reachability self-test: {} is not reachable from peer {peer}, other nodes can't \
+                subscribe to this node. Check the firewall and the port forwarding or enable \
+                port mapping: {err}",
+                response.dialed_addr
+            ),
+        }
+        return;
+    }
+    tracing::warn!("Reachability self-test failed: no peer answered the dial back request");
+}
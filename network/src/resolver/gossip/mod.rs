@@ -3,6 +3,7 @@ mod watch;
 
 pub use node::sign_gossip_node;
 pub use node::GossipPeer;
+pub use node::VOLATILE_KEY_PREFIX;
 pub use watch::watch_gossip;
 pub use watch::SubscribeStrategy;
 pub use watch::WatchGossipConfig;
@@ -24,6 +24,9 @@ const ID_KEY: &str = "node_id";
 const PROXIES_KEY: &str = "node_proxies";
 // pubkey_signature is base64 buf with (VerifyingKey([u8; 32]), Signature([u8; 64]))
 const PUBKEY_SIGNATURE_KEY: &str = "pubkey_signature";
+/// Prefix of the node state keys updated at runtime. They are signed as all
+/// the other keys, so the node state is re-signed after every update.
+pub const VOLATILE_KEY_PREFIX: &str = "volatile:";
 
 impl<PeerId> GossipPeer<PeerId>
 where
@@ -262,3 +265,32 @@ fn test_signature() {
     assert!(GossipPeer::<String>::try_from_values(values.iter().map(|(k, v)| (*k, v.as_str())))
         .is_none());
 }
+
+#[test]
+fn test_volatile_keys_are_signed() {
+    let signing_key = transport_layer::SigningKey::generate(&mut rand::rngs::OsRng);
+    let a = GossipPeer::new(
+        "1".to_string(),
+        ([127, 0, 0, 1], 1234).into(),
+        vec![],
+        None,
+        None,
+        Some(signing_key.clone()),
+    )
+    .unwrap();
+    let mut values = a.values();
+    values.push(("volatile:state_checksum:00", "1:00:00".to_string()));
+    // Forged volatile values are rejected
+    assert!(GossipPeer::<String>::try_from_values(values.iter().map(|(k, v)| (*k, v.as_str())))
+        .is_none());
+
+    // Values are accepted after the node re-signs its state
+    let signature = signing_key.sign(&bytes_to_sign(values.iter().map(|(k, v)| (*k, v.as_str()))));
+    values.retain(|(k, _)| *k != PUBKEY_SIGNATURE_KEY);
+    values.push((
+        PUBKEY_SIGNATURE_KEY,
+        pubkey_signature_to_string(&signing_key.verifying_key(), &signature),
+    ));
+    assert!(GossipPeer::<String>::try_from_values(values.iter().map(|(k, v)| (*k, v.as_str())))
+        .is_some());
+}
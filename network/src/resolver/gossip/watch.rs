@@ -10,6 +10,7 @@ use itertools::Itertools;
 
 use crate::metrics::NetMetrics;
 use crate::network::PeerData;
+use crate::peer_identity::PeerIdentities;
 use crate::resolver::GossipPeer;
 
 pub enum SubscribeStrategy<PeerId> {
@@ -22,6 +23,7 @@ pub struct WatchGossipConfig {
     pub trusted_pubkeys: HashSet<transport_layer::VerifyingKey>,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn watch_gossip<PeerId>(
     mut shutdown_rx: tokio::sync::watch::Receiver<bool>,
     mut config_rx: tokio::sync::watch::Receiver<WatchGossipConfig>,
@@ -29,6 +31,7 @@ pub async fn watch_gossip<PeerId>(
     chitchat: ChitchatRef,
     subscribe_tx: tokio::sync::watch::Sender<Vec<Vec<SocketAddr>>>,
     peers_tx: tokio::sync::watch::Sender<HashMap<PeerId, PeerData>>,
+    identities_tx: tokio::sync::watch::Sender<PeerIdentities>,
     metrics: Option<NetMetrics>,
 ) where
     PeerId: Clone + Display + Send + Sync + Hash + Eq + FromStr<Err: Display> + 'static,
@@ -71,6 +74,18 @@ pub async fn watch_gossip<PeerId>(
             peers_tx.send_replace(peers_to_send.clone());
         }
 
+        let identities = peers
+            .iter()
+            .map(|(peer_id, (_, pubkeys))| (peer_id.to_string(), pubkeys.clone()))
+            .collect::<PeerIdentities>();
+        identities_tx.send_if_modified(|current| {
+            let changed = *current != identities;
+            if changed {
+                *current = identities;
+            }
+            changed
+        });
+
         tokio::select! {
             sender = shutdown_rx.changed() => if sender.is_err() || *shutdown_rx.borrow() {
                 break;
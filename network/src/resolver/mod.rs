@@ -13,3 +13,4 @@ pub use gossip::watch_gossip;
 pub use gossip::GossipPeer;
 pub use gossip::SubscribeStrategy;
 pub use gossip::WatchGossipConfig;
+pub use gossip::VOLATILE_KEY_PREFIX;
@@ -0,0 +1,435 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+//! Test network that shapes the links between the hosts: latency, jitter,
+//! message loss and bandwidth. It wraps a real transport, so the pub_sub
+//! stack runs unchanged on top of it.
+//!
+//! Each host gets its own transport from [`NetworkSimulator::transport`].
+//! Hosts are matched to connections by the TLS identity of the credential
+//! they listen or connect with. Links are shaped on the receiving side and
+//! can be reconfigured while the test runs (e.g. to partition the hosts).
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use parking_lot::RwLock;
+use rustls::pki_types::CertificateDer;
+use tokio::sync::mpsc;
+use transport_layer::NetConnection;
+use transport_layer::NetCredential;
+use transport_layer::NetIncomingRequest;
+use transport_layer::NetListener;
+use transport_layer::NetTransport;
+
+type ShapedMessage = anyhow::Result<(Vec<u8>, Duration, Instant)>;
+
+/// Shaping of the messages sent from one host to another.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LinkProfile {
+    pub latency: Duration,
+    /// Random extra delay in `[0, jitter)`. Messages are never reordered.
+    pub jitter: Duration,
+    /// Probability in `[0, 1]` that a message is dropped.
+    pub loss: f64,
+    /// None means unlimited bandwidth.
+    pub bandwidth_bytes_per_sec: Option<u64>,
+}
+
+impl LinkProfile {
+    /// Drops all messages: the hosts stay connected but can't communicate.
+    pub fn disconnected() -> Self {
+        Self { loss: 1.0, ..Default::default() }
+    }
+}
+
+#[derive(Default)]
+struct LinkState {
+    busy_until: Option<Instant>,
+    last_delivery: Option<Instant>,
+}
+
+impl LinkState {
+    /// Returns the time to deliver the message that arrived from the real
+    /// transport, None if the message is lost. `draw` returns a random
+    /// number in `[0, 1)`.
+    fn schedule(
+        &mut self,
+        arrived: Instant,
+        len: usize,
+        profile: &LinkProfile,
+        mut draw: impl FnMut() -> f64,
+    ) -> Option<Instant> {
+        if profile.loss > 0.0 && draw() < profile.loss {
+            return None;
+        }
+        let mut transferred = arrived;
+        if let Some(bandwidth) = profile.bandwidth_bytes_per_sec.filter(|bandwidth| *bandwidth > 0)
+        {
+            let start = self.busy_until.map_or(arrived, |busy_until| busy_until.max(arrived));
+            transferred = start + Duration::from_secs_f64(len as f64 / bandwidth as f64);
+            self.busy_until = Some(transferred);
+        }
+        let jitter =
+            if profile.jitter.is_zero() { Duration::ZERO } else { profile.jitter.mul_f64(draw()) };
+        let delivery = transferred + profile.latency + jitter;
+        let delivery = self.last_delivery.map_or(delivery, |last| last.max(delivery));
+        self.last_delivery = Some(delivery);
+        Some(delivery)
+    }
+}
+
+struct SimulatorState {
+    default_link: LinkProfile,
+    links: HashMap<(String, String), LinkProfile>,
+    // TLS identity -> host
+    hosts: HashMap<String, String>,
+}
+
+/// Shared configuration of the simulated links. Random draws are seeded, so
+/// a test run is reproducible given the same message order.
+#[derive(Clone)]
+pub struct NetworkSimulator {
+    state: Arc<RwLock<SimulatorState>>,
+    rng_state: Arc<Mutex<u64>>,
+}
+
+impl NetworkSimulator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: Arc::new(RwLock::new(SimulatorState {
+                default_link: LinkProfile::default(),
+                links: HashMap::new(),
+                hosts: HashMap::new(),
+            })),
+            rng_state: Arc::new(Mutex::new(seed)),
+        }
+    }
+
+    /// Wraps the transport of the host. All hosts of the test must use
+    /// transports of the same simulator.
+    pub fn transport<Transport: NetTransport>(
+        &self,
+        inner: Transport,
+        host: impl Into<String>,
+    ) -> SimulatedTransport<Transport> {
+        SimulatedTransport { inner, simulator: self.clone(), host: host.into() }
+    }
+
+    /// Profile of the links without an explicit profile and of the
+    /// connections to unknown hosts.
+    pub fn set_default_link(&self, profile: LinkProfile) {
+        self.state.write().default_link = profile;
+    }
+
+    /// Sets the profile of the messages sent from `from` to `to`.
+    pub fn set_link(&self, from: &str, to: &str, profile: LinkProfile) {
+        self.state.write().links.insert((from.to_string(), to.to_string()), profile);
+    }
+
+    /// Sets the profile of the links in both directions.
+    pub fn set_duplex_link(&self, a: &str, b: &str, profile: LinkProfile) {
+        self.set_link(a, b, profile.clone());
+        self.set_link(b, a, profile);
+    }
+
+    /// Restores the default profile of the links in both directions.
+    pub fn reset_duplex_link(&self, a: &str, b: &str) {
+        let mut state = self.state.write();
+        state.links.remove(&(a.to_string(), b.to_string()));
+        state.links.remove(&(b.to_string(), a.to_string()));
+    }
+
+    fn register_host(&self, identity: String, host: &str) {
+        if !identity.is_empty() {
+            self.state.write().hosts.insert(identity, host.to_string());
+        }
+    }
+
+    fn link_profile(&self, remote_identity: &str, local_host: &str) -> LinkProfile {
+        let state = self.state.read();
+        state
+            .hosts
+            .get(remote_identity)
+            .and_then(|remote_host| state.links.get(&(remote_host.clone(), local_host.to_string())))
+            .unwrap_or(&state.default_link)
+            .clone()
+    }
+
+    // SplitMix64
+    fn draw(&self) -> f64 {
+        let mut rng_state = self.rng_state.lock();
+        *rng_state = rng_state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = *rng_state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[derive(Clone)]
+pub struct SimulatedTransport<Transport: NetTransport> {
+    inner: Transport,
+    simulator: NetworkSimulator,
+    host: String,
+}
+
+#[async_trait]
+impl<Transport> NetTransport for SimulatedTransport<Transport>
+where
+    Transport: NetTransport + 'static,
+    Transport::Connection: 'static,
+{
+    type Connection = SimulatedConnection<Transport::Connection>;
+    type Listener = SimulatedListener<Transport::Listener>;
+
+    async fn create_listener(
+        &self,
+        bind_addr: SocketAddr,
+        alpn_supported: &[&str],
+        credential: NetCredential,
+    ) -> anyhow::Result<Self::Listener> {
+        self.simulator.register_host(credential.identity(), &self.host);
+        let inner = self.inner.create_listener(bind_addr, alpn_supported, credential).await?;
+        Ok(SimulatedListener { inner, simulator: self.simulator.clone(), host: self.host.clone() })
+    }
+
+    async fn connect(
+        &self,
+        addr: SocketAddr,
+        alpn_preferred: &[&str],
+        credential: NetCredential,
+    ) -> anyhow::Result<Self::Connection> {
+        self.simulator.register_host(credential.identity(), &self.host);
+        let inner = self.inner.connect(addr, alpn_preferred, credential).await?;
+        Ok(SimulatedConnection::new(inner, self.simulator.clone(), self.host.clone()))
+    }
+}
+
+pub struct SimulatedListener<Listener: NetListener> {
+    inner: Listener,
+    simulator: NetworkSimulator,
+    host: String,
+}
+
+#[async_trait]
+impl<Listener> NetListener for SimulatedListener<Listener>
+where
+    Listener: NetListener,
+    Listener::Connection: 'static,
+{
+    type Connection = SimulatedConnection<Listener::Connection>;
+    type IncomingRequest = SimulatedIncomingRequest<Listener::IncomingRequest>;
+
+    async fn accept(&self) -> anyhow::Result<Self::IncomingRequest> {
+        let inner = self.inner.accept().await?;
+        Ok(SimulatedIncomingRequest {
+            inner,
+            simulator: self.simulator.clone(),
+            host: self.host.clone(),
+        })
+    }
+}
+
+pub struct SimulatedIncomingRequest<IncomingRequest: NetIncomingRequest> {
+    inner: IncomingRequest,
+    simulator: NetworkSimulator,
+    host: String,
+}
+
+#[async_trait]
+impl<IncomingRequest> NetIncomingRequest for SimulatedIncomingRequest<IncomingRequest>
+where
+    IncomingRequest: NetIncomingRequest,
+    IncomingRequest::Connection: 'static,
+{
+    type Connection = SimulatedConnection<IncomingRequest::Connection>;
+
+    async fn accept(self) -> anyhow::Result<Self::Connection> {
+        let inner = self.inner.accept().await?;
+        Ok(SimulatedConnection::new(inner, self.simulator, self.host))
+    }
+}
+
+#[derive(Clone)]
+pub struct SimulatedConnection<Connection: NetConnection> {
+    inner: Connection,
+    simulator: NetworkSimulator,
+    host: String,
+    // Started by the first `recv`: connections used only for sending
+    // leave the real transport receiving untouched
+    incoming: Arc<tokio::sync::Mutex<Option<mpsc::UnboundedReceiver<ShapedMessage>>>>,
+}
+
+impl<Connection: NetConnection + 'static> SimulatedConnection<Connection> {
+    fn new(inner: Connection, simulator: NetworkSimulator, host: String) -> Self {
+        Self { inner, simulator, host, incoming: Arc::new(tokio::sync::Mutex::new(None)) }
+    }
+
+    fn link_profile(&self) -> LinkProfile {
+        self.simulator.link_profile(&self.inner.remote_identity(), &self.host)
+    }
+
+    // Receives the messages from the real transport as soon as they arrive,
+    // so the shaping delays of consecutive messages overlap. Stops when the
+    // connection fails or all its clones are dropped.
+    fn start_shaping(&self) -> mpsc::UnboundedReceiver<ShapedMessage> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let inner = self.inner.clone();
+        let simulator = self.simulator.clone();
+        let host = self.host.clone();
+        tokio::spawn(async move {
+            let mut link = LinkState::default();
+            loop {
+                let received = tokio::select! {
+                    received = inner.recv() => received,
+                    _ = tx.closed() => break,
+                };
+                let (data, duration) = match received {
+                    Ok(received) => received,
+                    Err(err) => {
+                        let _ = tx.send(Err(err));
+                        break;
+                    }
+                };
+                let arrived = Instant::now();
+                let profile = simulator.link_profile(&inner.remote_identity(), &host);
+                let Some(delivery) =
+                    link.schedule(arrived, data.len(), &profile, || simulator.draw())
+                else {
+                    tracing::trace!(
+                        "Simulated network dropped {} bytes from {}",
+                        data.len(),
+                        inner.remote_addr()
+                    );
+                    continue;
+                };
+                let duration = duration + delivery.saturating_duration_since(arrived);
+                if tx.send(Ok((data, duration, delivery))).is_err() {
+                    break;
+                }
+            }
+        });
+        rx
+    }
+}
+
+#[async_trait]
+impl<Connection: NetConnection + 'static> NetConnection for SimulatedConnection<Connection> {
+    fn local_addr(&self) -> SocketAddr {
+        self.inner.local_addr()
+    }
+
+    fn remote_addr(&self) -> SocketAddr {
+        self.inner.remote_addr()
+    }
+
+    fn local_identity(&self) -> String {
+        self.inner.local_identity()
+    }
+
+    fn remote_identity(&self) -> String {
+        self.inner.remote_identity()
+    }
+
+    fn remote_certificate(&self) -> Option<CertificateDer<'static>> {
+        self.inner.remote_certificate()
+    }
+
+    fn alpn_negotiated(&self) -> Option<String> {
+        self.inner.alpn_negotiated()
+    }
+
+    fn rtt(&self) -> Option<Duration> {
+        // Assumes a symmetric link: only the incoming profile is known here
+        self.inner.rtt().map(|rtt| rtt + self.link_profile().latency * 2)
+    }
+
+    fn migrate(&self, local_addr: SocketAddr) -> anyhow::Result<()> {
+        self.inner.migrate(local_addr)
+    }
+
+    async fn send(&self, data: &[u8]) -> anyhow::Result<()> {
+        self.inner.send(data).await
+    }
+
+    async fn recv(&self) -> anyhow::Result<(Vec<u8>, Duration)> {
+        let mut incoming = self.incoming.lock().await;
+        let incoming = incoming.get_or_insert_with(|| self.start_shaping());
+        match incoming.recv().await {
+            Some(Ok((data, duration, delivery))) => {
+                tokio::time::sleep_until(delivery.into()).await;
+                Ok((data, duration))
+            }
+            Some(Err(err)) => Err(err),
+            None => anyhow::bail!("Simulated connection receiving stopped"),
+        }
+    }
+
+    async fn close(&self, code: usize) {
+        self.inner.close(code).await
+    }
+
+    async fn watch_close(&self) {
+        self.inner.watch_close().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link_schedule() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+
+        let mut link = LinkState::default();
+        let profile = LinkProfile {
+            latency: ms(100),
+            jitter: ms(20),
+            loss: 0.0,
+            bandwidth_bytes_per_sec: Some(1000),
+        };
+        // 500 bytes take 500ms at 1000 bytes/s
+        assert_eq!(link.schedule(start, 500, &profile, || 0.5), Some(start + ms(610)));
+        // The second message waits until the first one is transferred
+        assert_eq!(link.schedule(start, 500, &profile, || 0.0), Some(start + ms(1100)));
+        // The later message is never delivered before the earlier one
+        let profile = LinkProfile { jitter: ms(0), bandwidth_bytes_per_sec: None, ..profile };
+        assert_eq!(link.schedule(start, 500, &profile, || 0.0), Some(start + ms(1100)));
+
+        let lossy = LinkProfile { loss: 0.3, ..Default::default() };
+        assert_eq!(link.schedule(start, 1, &lossy, || 0.29), None);
+        assert_eq!(link.schedule(start + ms(2000), 1, &lossy, || 0.3), Some(start + ms(2000)));
+        assert_eq!(link.schedule(start, 1, &LinkProfile::disconnected(), || 0.99), None);
+    }
+
+    #[test]
+    fn test_link_profile() {
+        let simulator = NetworkSimulator::new(0);
+        simulator.register_host("id_a".to_string(), "a");
+        simulator.register_host("id_b".to_string(), "b");
+        let slow = LinkProfile { latency: Duration::from_millis(300), ..Default::default() };
+        simulator.set_link("a", "b", slow.clone());
+
+        assert_eq!(simulator.link_profile("id_a", "b"), slow);
+        assert_eq!(simulator.link_profile("id_b", "a"), LinkProfile::default());
+        assert_eq!(simulator.link_profile("unknown", "b"), LinkProfile::default());
+
+        simulator.set_duplex_link("a", "b", LinkProfile::disconnected());
+        assert_eq!(simulator.link_profile("id_b", "a"), LinkProfile::disconnected());
+        simulator.reset_duplex_link("a", "b");
+        assert_eq!(simulator.link_profile("id_a", "b"), LinkProfile::default());
+
+        let draws = (0..1000).map(|_| simulator.draw()).collect::<Vec<_>>();
+        assert!(draws.iter().all(|draw| (0.0..1.0).contains(draw)));
+        assert_eq!(NetworkSimulator::new(0).draw(), draws[0]);
+    }
+}
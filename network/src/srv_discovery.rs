@@ -0,0 +1,147 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use hickory_resolver::TokioResolver;
+use url::Url;
+
+/// Scheme prefix of static storage urls that are resolved via SRV records,
+/// e.g. `srv+https://_storage._tcp.example.com/storage/`.
+pub const SRV_URL_SCHEME_PREFIX: &str = "srv+";
+
+pub fn resolver() -> anyhow::Result<TokioResolver> {
+    Ok(TokioResolver::builder_tokio()?.build())
+}
+
+/// Returns SRV record targets as `(host, port)` ordered by priority (lowest
+/// first) and weight (highest first).
+async fn lookup_srv(resolver: &TokioResolver, name: &str) -> anyhow::Result<Vec<(String, u16)>> {
+    let lookup = resolver.srv_lookup(name).await?;
+    let mut records = lookup.iter().collect::<Vec<_>>();
+    records.sort_by_key(|srv| (srv.priority(), u16::MAX - srv.weight()));
+    let targets = records
+        .into_iter()
+        .map(|srv| (srv.target().to_utf8().trim_end_matches('.').to_string(), srv.port()))
+        .collect::<Vec<_>>();
+    if targets.is_empty() {
+        anyhow::bail!("SRV record {name} has no targets");
+    }
+    Ok(targets)
+}
+
+/// Resolves SRV record into target socket addresses keeping the SRV order.
+pub async fn resolve_srv(resolver: &TokioResolver, name: &str) -> anyhow::Result<Vec<SocketAddr>> {
+    let mut addrs = Vec::new();
+    for (host, port) in lookup_srv(resolver, name).await? {
+        let ips = resolver.lookup_ip(host).await?;
+        addrs.extend(ips.iter().map(|ip| SocketAddr::new(ip, port)));
+    }
+    Ok(addrs)
+}
+
+/// Resolves SRV names into subscribe entries, one publisher per name with SRV
+/// targets as its alternate addresses. On resolution failure the previously
+/// resolved addresses of the name are kept.
+pub async fn resolve_publishers(
+    resolver: &TokioResolver,
+    names: &[String],
+    resolved: &mut HashMap<String, Vec<SocketAddr>>,
+) -> Vec<Vec<SocketAddr>> {
+    resolved.retain(|name, _| names.contains(name));
+    for name in names {
+        match resolve_srv(resolver, name).await {
+            Ok(addrs) => {
+                resolved.insert(name.clone(), addrs);
+            }
+            Err(err) => tracing::warn!("Failed to resolve subscribe SRV record {name}: {err}"),
+        }
+    }
+    names.iter().filter_map(|name| resolved.get(name).cloned()).collect()
+}
+
+pub fn is_srv_url(url: &Url) -> bool {
+    url.scheme().starts_with(SRV_URL_SCHEME_PREFIX)
+}
+
+/// Urls that do not need SRV resolution, available before the first one.
+pub fn static_urls(storages: &[Url]) -> Vec<Url> {
+    storages.iter().filter(|url| !is_srv_url(url)).cloned().collect()
+}
+
+/// Replaces SRV name of the url with the resolved targets:
+/// `srv+https://_storage._tcp.example.com/storage/` -> `https://<target>:<port>/storage/`.
+pub async fn resolve_srv_url(resolver: &TokioResolver, url: &Url) -> anyhow::Result<Vec<Url>> {
+    let scheme = url.scheme().trim_start_matches(SRV_URL_SCHEME_PREFIX);
+    let name = url.host_str().ok_or_else(|| anyhow::format_err!("SRV url has no host: {url}"))?;
+    let path = &url[url::Position::BeforePath..];
+    lookup_srv(resolver, name)
+        .await?
+        .into_iter()
+        .map(|(host, port)| Ok(Url::parse(&format!("{scheme}://{host}:{port}{path}"))?))
+        .collect()
+}
+
+/// Publishes static storages list with SRV urls replaced by resolved targets.
+/// Re-resolves SRV urls every `refresh_interval`. Returns immediately if
+/// there are no SRV urls.
+pub async fn run_static_storages_discovery(
+    storages: Vec<Url>,
+    refresh_interval: Duration,
+    storages_tx: tokio::sync::watch::Sender<Vec<Url>>,
+) -> anyhow::Result<()> {
+    let (srv_urls, static_urls): (Vec<_>, Vec<_>) = storages.into_iter().partition(is_srv_url);
+    // The channel is seeded with the static urls, see `static_urls`
+    if srv_urls.is_empty() {
+        storages_tx.send_replace(static_urls);
+        return Ok(());
+    }
+    let resolver = resolver()?;
+    let mut resolved = HashMap::<Url, Vec<Url>>::new();
+    loop {
+        for url in &srv_urls {
+            match resolve_srv_url(&resolver, url).await {
+                Ok(urls) => {
+                    resolved.insert(url.clone(), urls);
+                }
+                Err(err) => tracing::warn!("Failed to resolve static storage SRV url {url}: {err}"),
+            }
+        }
+        let storages = static_urls
+            .iter()
+            .cloned()
+            .chain(srv_urls.iter().flat_map(|url| resolved.get(url).cloned().unwrap_or_default()))
+            .collect::<Vec<_>>();
+        storages_tx.send_if_modified(|current| {
+            if *current != storages {
+                tracing::info!("Static storages updated: {storages:?}");
+                *current = storages;
+                true
+            } else {
+                false
+            }
+        });
+        tokio::time::sleep(refresh_interval).await;
+        if storages_tx.is_closed() {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_srv_url() {
+        assert!(is_srv_url(&Url::parse("srv+https://_storage._tcp.example.com/storage/").unwrap()));
+        assert!(!is_srv_url(&Url::parse("https://example.com/storage/").unwrap()));
+        let storages = vec![
+            Url::parse("srv+https://_storage._tcp.example.com/storage/").unwrap(),
+            Url::parse("https://example.com/storage/").unwrap(),
+        ];
+        assert_eq!(static_urls(&storages), vec![storages[1].clone()]);
+    }
+}
@@ -31,6 +31,7 @@ use crate::channel::NetDirectSender;
 use crate::config::NetworkConfig;
 use crate::network::BasicNetwork;
 use crate::network::PeerData;
+use crate::peer_identity::PeerIdentities;
 use crate::pub_sub::connection::IncomingMessage;
 use crate::pub_sub::connection::MessageDelivery;
 use crate::pub_sub::connection::OutgoingMessage;
@@ -126,6 +127,7 @@ impl<Transport: NetTransport + 'static> Proxy<Transport> {
             tokio::sync::watch::channel(WatchGossipConfig { trusted_pubkeys: HashSet::new() });
         let (subscribe_tx, subscribe_rx) = tokio::sync::watch::channel(Vec::new());
         let (peers_tx, _) = tokio::sync::watch::channel(HashMap::new());
+        let (identities_tx, identities_rx) = tokio::sync::watch::channel(PeerIdentities::new());
         tokio::spawn(watch_gossip(
             shutdown_rx.clone(),
             watch_gossip_config_rx,
@@ -133,6 +135,7 @@ impl<Transport: NetTransport + 'static> Proxy<Transport> {
             chitchat_handle.chitchat(),
             subscribe_tx.clone(),
             peers_tx,
+            identities_tx,
             None,
         ));
 
@@ -147,6 +150,8 @@ impl<Transport: NetTransport + 'static> Proxy<Transport> {
             subscribe_rx,
             outgoing_messages_tx,
             IncomingSender::AsyncUnbounded(incoming_messages_tx),
+            None,
+            identities_rx,
         ));
 
         let chitchat = chitchat_handle.chitchat();
@@ -227,6 +232,8 @@ impl NodeConfig {
                 listen_addr: gossip_addr,
                 seeds: gossip_seeds,
                 cluster_id: "transport_test".to_string(),
+                tuning: Default::default(),
+                snapshot: Default::default(),
             },
         }
     }
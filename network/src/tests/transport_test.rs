@@ -14,6 +14,8 @@ use crate::channel::NetBroadcastSender;
 use crate::channel::NetDirectSender;
 use crate::network::PeerData;
 use crate::pub_sub::connection::IncomingMessage;
+use crate::simulator::LinkProfile;
+use crate::simulator::NetworkSimulator;
 use crate::tests::gossip_addr;
 use crate::tests::init_logs;
 use crate::tests::node_addr;
@@ -29,6 +31,19 @@ async fn test_msquic_transport() {
     test_transport(MsQuicTransport::new()).await;
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+#[ignore]
+async fn test_msquic_transport_with_simulated_network() {
+    let simulator = NetworkSimulator::new(0);
+    simulator.set_default_link(LinkProfile {
+        latency: Duration::from_millis(50),
+        jitter: Duration::from_millis(20),
+        loss: 0.0,
+        bandwidth_bytes_per_sec: Some(10_000_000),
+    });
+    test_transport(simulator.transport(MsQuicTransport::new(), "test_host")).await;
+}
+
 async fn test_transport(transport: impl NetTransport + 'static) {
     init_logs();
     let dc_count = 2;
@@ -0,0 +1,81 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+use std::collections::HashSet;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+// Subscription frames start with this marker, same as chunk frames they
+// can't be confused with a serialized NetMessage.
+const SUBSCRIPTION_FRAME_MARKER: [u8; 8] = *b"ANSUBSC\0";
+
+/// Sent by a subscriber right after the subscription connection is
+/// established. Publishers of the protocol versions before the thread filter
+/// can't decode it, so it's sent only when a later version was negotiated.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SubscriptionRequest {
+    /// Hex encoded thread identifiers. Empty list means all threads.
+    pub threads: Vec<String>,
+}
+
+pub fn is_subscription_frame(frame: &[u8]) -> bool {
+    frame.starts_with(&SUBSCRIPTION_FRAME_MARKER)
+}
+
+pub fn encode_subscription_frame(request: &SubscriptionRequest) -> bincode::Result<Vec<u8>> {
+    let mut frame = SUBSCRIPTION_FRAME_MARKER.to_vec();
+    bincode::serialize_into(&mut frame, request)?;
+    Ok(frame)
+}
+
+pub fn decode_subscription_frame(frame: &[u8]) -> anyhow::Result<SubscriptionRequest> {
+    let Some(data) = frame.strip_prefix(&SUBSCRIPTION_FRAME_MARKER) else {
+        anyhow::bail!("Frame is not a subscription request");
+    };
+    bincode::deserialize(data)
+        .map_err(|err| anyhow::format_err!("Invalid subscription request frame: {err}"))
+}
+
+/// Threads a subscriber is interested in. Messages that are not bound to
+/// a thread are always delivered.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ThreadFilter {
+    threads: Option<HashSet<String>>,
+}
+
+impl From<&SubscriptionRequest> for ThreadFilter {
+    fn from(request: &SubscriptionRequest) -> Self {
+        if request.threads.is_empty() {
+            return Self::default();
+        }
+        Self { threads: Some(request.threads.iter().map(|thread| thread.to_lowercase()).collect()) }
+    }
+}
+
+impl ThreadFilter {
+    pub fn allows(&self, thread_id: Option<&str>) -> bool {
+        match (&self.threads, thread_id) {
+            (Some(threads), Some(thread_id)) => threads.contains(thread_id),
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thread_filter() {
+        let request = SubscriptionRequest { threads: vec!["AB01".to_string()] };
+        let frame = encode_subscription_frame(&request).unwrap();
+        assert!(is_subscription_frame(&frame));
+        let filter = ThreadFilter::from(&decode_subscription_frame(&frame).unwrap());
+        assert!(filter.allows(Some("ab01")));
+        assert!(!filter.allows(Some("ab02")));
+        assert!(filter.allows(None));
+
+        let filter = ThreadFilter::from(&SubscriptionRequest::default());
+        assert!(filter.allows(Some("ab02")));
+    }
+}
@@ -1,3 +1,4 @@
+use std::time::Duration;
 use std::time::Instant;
 
 use thiserror::Error;
@@ -6,9 +7,19 @@ use wtransport::error::ConnectionError;
 use wtransport::error::StreamOpeningError;
 use wtransport::error::StreamWriteError;
 
+use crate::chunked_transfer::encode_chunk_frame;
+use crate::chunked_transfer::split_into_chunks;
+use crate::chunked_transfer::transfer_id;
+use crate::chunked_transfer::CHUNKED_TRANSFER_THRESHOLD;
+use crate::chunked_transfer::CHUNK_SIZE;
 use crate::detailed;
 use crate::message::NetMessage;
 use crate::metrics::NetMetrics;
+use crate::protocol_version::encode_versioned_frame;
+use crate::protocol_version::supports_chunked_transfer;
+
+const CHUNK_SEND_ATTEMPTS: usize = 3;
+const CHUNK_RETRY_DELAY: Duration = Duration::from_millis(100);
 
 #[derive(Error, Debug, Clone, Eq, PartialEq)]
 pub enum TransportError {
@@ -59,22 +70,66 @@ impl TransportError {
 pub async fn transfer(
     connection: &impl NetConnection,
     net_message: &NetMessage,
+    protocol_version: u16,
     metrics: &Option<NetMetrics>,
 ) -> Result<usize, TransportError> {
-    let data = bincode::serialize(net_message)
+    let data = encode_versioned_frame(net_message, protocol_version)
         .map_err(|err| TransportError::BincodeSerialization(err.to_string()))?;
 
     let moment = Instant::now();
-    connection
-        .send(&data)
-        .await
-        .inspect_err(|err| {
-            tracing::error!("Failed to send outgoing data: {}", detailed(err));
-        })
-        .map_err(|err| TransportError::Send(err.to_string()))?;
+    // Peers of the unversioned protocol don't know chunk frames
+    if data.len() > CHUNKED_TRANSFER_THRESHOLD && supports_chunked_transfer(protocol_version) {
+        transfer_chunks(connection, &data).await?;
+    } else {
+        send_frame(connection, &data).await?;
+    }
 
     metrics.as_ref().inspect(|m| {
         m.report_transfer_after_ser(moment.elapsed().as_millis());
     });
     Ok(data.len())
 }
+
+// Every chunk is sent in a separate stream, a failed stream is retried for
+// its chunk only. If the retries fail, the transfer fails and the chunks
+// already sent are kept by the receiver until it expires.
+async fn transfer_chunks(
+    connection: &impl NetConnection,
+    data: &[u8],
+) -> Result<(), TransportError> {
+    let transfer_id = transfer_id(data);
+    for chunk in split_into_chunks(transfer_id, data, CHUNK_SIZE) {
+        let frame = encode_chunk_frame(&chunk)
+            .map_err(|err| TransportError::BincodeSerialization(err.to_string()))?;
+        let mut attempt = 1;
+        loop {
+            match send_frame(connection, &frame).await {
+                Ok(()) => break,
+                Err(err) if attempt < CHUNK_SEND_ATTEMPTS => {
+                    tracing::warn!(
+                        transfer_id,
+                        chunk = chunk.index,
+                        count = chunk.count,
+                        attempt,
+                        "Failed to send chunk, retrying: {}",
+                        detailed(&err)
+                    );
+                    attempt += 1;
+                    tokio::time::sleep(CHUNK_RETRY_DELAY).await;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn send_frame(connection: &impl NetConnection, frame: &[u8]) -> Result<(), TransportError> {
+    connection
+        .send(frame)
+        .await
+        .inspect_err(|err| {
+            tracing::error!("Failed to send outgoing data: {}", detailed(err));
+        })
+        .map_err(|err| TransportError::Send(err.to_string()))
+}
@@ -0,0 +1,209 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+
+use database::sqlite::sqlite_helper::SqliteHelper;
+use database::sqlite::sqlite_helper::SqliteHelperConfig;
+use database::sqlite::sqlite_helper::SQLITE_EMPTY_DB;
+use node::bls::envelope::BLSSignedEnvelope;
+use node::bls::envelope::Envelope;
+use node::bls::GoshBLS;
+use node::database::serialize_block::reflect_block_in_db;
+use node::repository::repository_impl::RepositoryImpl;
+use node::types::AckiNackiBlock;
+use node::types::BlockSeqNo;
+use parking_lot::Mutex;
+use serde::Serialize;
+use tvm_block::ShardStateUnsplit;
+
+const PROGRESS_REPORT_INTERVAL: usize = 1000;
+const REPOSITORY_BLOCKS_DIR: &str = "blocks";
+
+pub enum BlocksSource {
+    /// Node repository: chains are walked back from the last finalized
+    /// block of each thread, so unfinalized blocks are not archived
+    Repository(PathBuf),
+    /// Directory with exported block envelopes, all of them are finalized
+    Export(PathBuf),
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct RebuildSummary {
+    pub total: usize,
+    pub archived: usize,
+    /// Blocks that are already in the archive, skipped on resume
+    pub skipped: usize,
+    pub failed: Vec<String>,
+}
+
+struct BlockFile {
+    seq_no: BlockSeqNo,
+    block_id: String,
+    path: PathBuf,
+}
+
+/// Repopulates the archive with the blocks from the source in seq_no order.
+/// Blocks are written to the archive last, after their transactions,
+/// accounts and messages, so an interrupted rebuild is resumed by skipping
+/// blocks already present in the archive.
+pub fn rebuild(
+    source: &BlocksSource,
+    data_dir: &Path,
+    db_file: &Path,
+    resume: bool,
+) -> anyhow::Result<RebuildSummary> {
+    let mut blocks = match source {
+        BlocksSource::Repository(repo_dir) => finalized_repository_blocks(repo_dir)?,
+        BlocksSource::Export(blocks_dir) => exported_blocks(blocks_dir)?,
+    };
+    blocks.sort_by_key(|block| block.seq_no);
+    eprintln!("Found {} blocks to archive", blocks.len());
+
+    let db_path = data_dir.join(db_file);
+    let archived = if resume && db_path.exists() {
+        archived_block_ids(&db_path)?
+    } else {
+        reset_archive(data_dir, &db_path)?;
+        HashSet::new()
+    };
+
+    let config = SqliteHelperConfig::new(data_dir.to_path_buf(), Some(db_file.to_path_buf()));
+    let (sqlite_helper, writer_join_handle) = SqliteHelper::from_config(config)?;
+    let sqlite_helper = Arc::new(Mutex::new(sqlite_helper));
+    // Shard state is not used for finalized blocks, the same as in the block manager
+    let shard_state = Arc::new(ShardStateUnsplit::default());
+    let mut transaction_traces = HashMap::new();
+
+    let mut summary = RebuildSummary { total: blocks.len(), ..Default::default() };
+    let started = Instant::now();
+    for (index, block) in blocks.iter().enumerate() {
+        if archived.contains(&block.block_id) {
+            summary.skipped += 1;
+        } else {
+            let result = load_block(&block.path).and_then(|(envelope, raw_block)| {
+                reflect_block_in_db(
+                    sqlite_helper.clone(),
+                    envelope,
+                    Some(raw_block),
+                    shard_state.clone(),
+                    &mut transaction_traces,
+                )
+            });
+            match result {
+                Ok(()) => summary.archived += 1,
+                Err(e) => {
+                    eprintln!("Failed to archive block {} ({}): {e}", block.block_id, block.seq_no);
+                    summary.failed.push(block.block_id.clone());
+                }
+            }
+        }
+        let processed = index + 1;
+        if processed % PROGRESS_REPORT_INTERVAL == 0 || processed == blocks.len() {
+            eprintln!(
+                "Processed {processed}/{} blocks: archived {}, skipped {}, failed {}, {}s elapsed",
+                blocks.len(),
+                summary.archived,
+                summary.skipped,
+                summary.failed.len(),
+                started.elapsed().as_secs()
+            );
+        }
+    }
+
+    // Dropping the records sender stops the writer after the queued records are stored
+    sqlite_helper.lock().shutdown()?;
+    writer_join_handle.join().map_err(|_| {
+        anyhow::format_err!("Archive writer failed, rerun the rebuild with --resume")
+    })?;
+    Ok(summary)
+}
+
+fn load_block(path: &Path) -> anyhow::Result<(Envelope<GoshBLS, AckiNackiBlock>, Vec<u8>)> {
+    let raw_block = std::fs::read(path)
+        .map_err(|e| anyhow::format_err!("Failed to read {}: {e}", path.display()))?;
+    let envelope = bincode::deserialize(&raw_block)
+        .map_err(|e| anyhow::format_err!("Failed to decode {}: {e}", path.display()))?;
+    Ok((envelope, raw_block))
+}
+
+fn finalized_repository_blocks(repo_dir: &Path) -> anyhow::Result<Vec<BlockFile>> {
+    let blocks_dir = repo_dir.join(REPOSITORY_BLOCKS_DIR);
+    let mut blocks = vec![];
+    let mut visited = HashSet::new();
+    for (thread_id, metadata) in RepositoryImpl::load_metadata(repo_dir)? {
+        let mut cursor = metadata.lock().last_finalized_block_id().clone();
+        let mut thread_blocks = 0;
+        // Thread chains share ancestors: stop at the first block already visited
+        while visited.insert(cursor.clone()) {
+            let path = blocks_dir.join(cursor.to_string());
+            if !path.exists() {
+                break;
+            }
+            let (envelope, _) = load_block(&path)?;
+            let block = envelope.data();
+            blocks.push(BlockFile { seq_no: block.seq_no(), block_id: cursor.to_string(), path });
+            thread_blocks += 1;
+            cursor = block.parent();
+        }
+        eprintln!("Thread {thread_id:?}: {thread_blocks} finalized blocks found");
+    }
+    Ok(blocks)
+}
+
+fn exported_blocks(blocks_dir: &Path) -> anyhow::Result<Vec<BlockFile>> {
+    let mut blocks = vec![];
+    for entry in std::fs::read_dir(blocks_dir)? {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        let envelope = match load_block(&path) {
+            Ok((envelope, _)) => envelope,
+            Err(e) => {
+                eprintln!("Skip {}: {e}", path.display());
+                continue;
+            }
+        };
+        let block = envelope.data();
+        blocks.push(BlockFile {
+            seq_no: block.seq_no(),
+            block_id: block.identifier().to_string(),
+            path,
+        });
+    }
+    Ok(blocks)
+}
+
+fn archived_block_ids(db_path: &Path) -> anyhow::Result<HashSet<String>> {
+    let conn = SqliteHelper::create_connection_ro(db_path.to_path_buf())?;
+    let mut stmt = conn.prepare("SELECT id FROM blocks")?;
+    let ids =
+        stmt.query_map([], |row| row.get::<_, String>(0))?.collect::<Result<HashSet<_>, _>>()?;
+    Ok(ids)
+}
+
+fn reset_archive(data_dir: &Path, db_path: &Path) -> anyhow::Result<()> {
+    let empty_db = data_dir.join(SQLITE_EMPTY_DB);
+    anyhow::ensure!(
+        empty_db.exists(),
+        "Empty archive with the applied schema not found: {}",
+        empty_db.display()
+    );
+    for suffix in ["-wal", "-shm"] {
+        let mut path = db_path.as_os_str().to_owned();
+        path.push(suffix);
+        let path = PathBuf::from(path);
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+    }
+    std::fs::copy(&empty_db, db_path)?;
+    eprintln!("Archive {} is recreated from {}", db_path.display(), empty_db.display());
+    Ok(())
+}
@@ -0,0 +1,147 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use node::block::producer::builder::replay::replay_external_messages;
+use node::config::load_blockchain_config;
+use node::config::load_config_from_file;
+use node::config::BlockchainConfigSource;
+use node::repository::accounts::AccountsRepository;
+use node::repository::optimistic_state::OptimisticStateImpl;
+use node::types::AccountAddress;
+use serde::Serialize;
+use tvm_block::Deserializable;
+use tvm_block::Message;
+
+#[derive(Serialize, Debug)]
+pub struct ExecuteReport {
+    pub messages: usize,
+    pub levels: Vec<LevelReport>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct LevelReport {
+    pub parallelization_level: usize,
+    pub tx_cnt: usize,
+    pub gas_used: u64,
+    /// Median of the runs
+    pub elapsed_ms: f64,
+    pub tx_per_sec: f64,
+    pub gas_per_sec: f64,
+    /// Throughput relative to the level 1
+    pub scaling: f64,
+}
+
+/// Messages file has one message BOC per line, hex encoded.
+pub fn read_messages(path: &Path) -> anyhow::Result<Vec<Message>> {
+    std::fs::read_to_string(path)
+        .map_err(|e| anyhow::format_err!("Failed to read {}: {e}", path.display()))?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .enumerate()
+        .map(|(index, line)| {
+            let boc = hex::decode(line.trim_start_matches("0x"))
+                .map_err(|e| anyhow::format_err!("Invalid hex of message {index}: {e}"))?;
+            Message::construct_from_bytes(&boc)
+                .map_err(|e| anyhow::format_err!("Failed to decode message {index}: {e}"))
+        })
+        .collect()
+}
+
+/// Levels 1, 2, 4 and so on up to the max level, the max level is always
+/// measured.
+pub fn parallelization_levels(max_level: usize) -> Vec<usize> {
+    let mut levels = vec![];
+    let mut level = 1;
+    while level < max_level {
+        levels.push(level);
+        level *= 2;
+    }
+    levels.push(max_level.max(1));
+    levels
+}
+
+/// Replays the messages against the state with the production executor for
+/// each parallelization level. Every run starts from the same state.
+pub fn execute(
+    config_path: &Path,
+    repo_dir: &Path,
+    state_path: &Path,
+    messages_path: &Path,
+    max_level: usize,
+    runs: usize,
+) -> anyhow::Result<ExecuteReport> {
+    let config = load_config_from_file(&config_path.to_path_buf())?;
+    let state = OptimisticStateImpl::load_from_file(state_path)
+        .map_err(|e| anyhow::format_err!("Failed to load state {}: {e}", state_path.display()))?;
+    let messages = read_messages(messages_path)?;
+    let blockchain_config_account = config
+        .global
+        .blockchain_config_account
+        .as_deref()
+        .map(AccountAddress::from_str)
+        .transpose()
+        .map_err(|e| anyhow::format_err!("Invalid blockchain config account: {e}"))?;
+    let state_save_policy = config.state_save_policy()?;
+    // Accounts unloaded from the state are read from the node repository
+    let accounts_repository =
+        || AccountsRepository::new(repo_dir.to_path_buf(), None, state_save_policy.clone());
+    let blockchain_config = BlockchainConfigSource::new(
+        load_blockchain_config(&config.local.blockchain_config_path)?,
+        blockchain_config_account,
+        config.global.blockchain_config_activation_seq_no,
+    )
+    .for_state(&state, &accounts_repository())?;
+
+    let mut levels: Vec<LevelReport> = vec![];
+    for parallelization_level in parallelization_levels(max_level) {
+        let mut elapsed = vec![];
+        let mut result = None;
+        for _ in 0..runs.max(1) {
+            let run = replay_external_messages(
+                state.clone(),
+                &messages,
+                &blockchain_config,
+                // Each run starts with cold caches
+                accounts_repository(),
+                &config,
+                parallelization_level,
+            )?;
+            elapsed.push(run.elapsed);
+            result = Some(run);
+        }
+        let result = result.expect("At least one run is made");
+        elapsed.sort();
+        let median = elapsed[elapsed.len() / 2].max(Duration::from_micros(1)).as_secs_f64();
+        let tx_per_sec = result.tx_cnt as f64 / median;
+        let base = levels.first().map_or(tx_per_sec, |level| level.tx_per_sec);
+        eprintln!("Parallelization level {parallelization_level}: {tx_per_sec:.0} tx/s");
+        levels.push(LevelReport {
+            parallelization_level,
+            tx_cnt: result.tx_cnt,
+            gas_used: result.gas_used,
+            elapsed_ms: median * 1000.0,
+            tx_per_sec,
+            gas_per_sec: result.gas_used as f64 / median,
+            scaling: if base > 0.0 { tx_per_sec / base } else { 0.0 },
+        });
+    }
+    Ok(ExecuteReport { messages: messages.len(), levels })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parallelization_levels() {
+        assert_eq!(parallelization_levels(1), vec![1]);
+        assert_eq!(parallelization_levels(4), vec![1, 2, 4]);
+        assert_eq!(parallelization_levels(6), vec![1, 2, 4, 6]);
+        assert_eq!(parallelization_levels(0), vec![1]);
+    }
+}
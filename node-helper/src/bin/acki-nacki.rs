@@ -0,0 +1,94 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::path::PathBuf;
+
+use clap::Parser;
+use clap::Subcommand;
+use node::launcher::LONG_VERSION;
+use node_helper::AdminAuth;
+use node_helper::Archive;
+use node_helper::Bench;
+use node_helper::BkSetHistory;
+use node_helper::Bls;
+use node_helper::Commands;
+use node_helper::Config;
+use node_helper::Decode;
+use node_helper::GenKeys;
+use node_helper::Repo;
+use node_helper::Zerostate;
+
+/// Acki-Nacki node and its tooling
+#[derive(Parser, Debug)]
+#[command(name = "acki-nacki", author, long_version = &**LONG_VERSION, about, long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run the node
+    Node {
+        #[command(subcommand)]
+        action: NodeAction,
+    },
+    /// Set up AckiNacki node config
+    Config(Config),
+    /// Generate key pairs and sign with them
+    Keys {
+        #[command(subcommand)]
+        action: KeysAction,
+    },
+    /// Inspect the zerostate file
+    Zerostate(Zerostate),
+    /// Maintain the block manager SQLite archive
+    Archive(Archive),
+    /// Inspect the node repository
+    Repo(Repo),
+    /// Export signed BK set history of a running node
+    BkSetHistory(BkSetHistory),
+    /// Decode block or message BOC and print it as JSON
+    Decode(Decode),
+    /// Measure node performance on this hardware
+    Bench(Bench),
+}
+
+#[derive(Subcommand, Debug)]
+enum NodeAction {
+    /// Run the node, the same as the `node` binary
+    Run {
+        #[arg(short, long)]
+        config_path: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum KeysAction {
+    /// Generate BLS key pair
+    Bls(Bls),
+    /// Generate wallet key pair
+    Wallet(GenKeys),
+    /// Sign admin API challenge of a running node with the node owner wallet keys
+    AdminAuth(AdminAuth),
+}
+
+fn main() -> anyhow::Result<()> {
+    let command = match Args::parse().command {
+        Command::Node { action: NodeAction::Run { config_path } } => {
+            node::launcher::run(config_path)
+        }
+        Command::Config(cmd) => Commands::Config(cmd),
+        Command::Keys { action: KeysAction::Bls(cmd) } => Commands::Bls(cmd),
+        Command::Keys { action: KeysAction::Wallet(cmd) } => Commands::GenKeys(cmd),
+        Command::Keys { action: KeysAction::AdminAuth(cmd) } => Commands::AdminAuth(cmd),
+        Command::Zerostate(cmd) => Commands::Zerostate(cmd),
+        Command::Archive(cmd) => Commands::Archive(cmd),
+        Command::Repo(cmd) => Commands::Repo(cmd),
+        Command::BkSetHistory(cmd) => Commands::BkSetHistory(cmd),
+        Command::Decode(cmd) => Commands::Decode(cmd),
+        Command::Bench(cmd) => Commands::Bench(cmd),
+    };
+    node_helper::run(command)
+}
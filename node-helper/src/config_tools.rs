@@ -0,0 +1,144 @@
+use std::path::PathBuf;
+
+use node::config::load_config_from_file;
+use node::config::Config;
+use node::config::GlobalConfig;
+use node::config::NodeConfig;
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ConfigDifference {
+    /// Dot separated path of the field, e.g. `global.time_to_produce_block_millis`
+    pub path: String,
+    pub a: Value,
+    pub b: Value,
+    /// Default value of the field. Not set for fields without default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<Value>,
+}
+
+// Network config has no defaults, so only global and local sections are annotated
+fn default_values() -> anyhow::Result<Value> {
+    Ok(serde_json::json!({
+        "global": serde_json::to_value(GlobalConfig::default())?,
+        "local": serde_json::to_value(NodeConfig::default())?,
+    }))
+}
+
+fn config_to_value(path: &PathBuf) -> anyhow::Result<Value> {
+    let config = load_config_from_file(path)?;
+    Ok(serde_json::to_value(config)?)
+}
+
+pub fn diff_config_files(a: &PathBuf, b: &PathBuf) -> anyhow::Result<Vec<ConfigDifference>> {
+    let mut differences = vec![];
+    diff_values(
+        "",
+        &config_to_value(a)?,
+        &config_to_value(b)?,
+        Some(&default_values()?),
+        &mut differences,
+    );
+    Ok(differences)
+}
+
+/// Applies override on top of the base config. Override may contain only the
+/// fields that differ for the node, the result must be a valid config.
+pub fn merge_config_files(base: &PathBuf, override_path: &PathBuf) -> anyhow::Result<Config> {
+    let mut merged = config_to_value(base)?;
+    let override_str = std::fs::read_to_string(override_path)
+        .map_err(|e| anyhow::format_err!("Failed to open override file: {e}"))?;
+    let override_value = serde_yaml::from_str::<Value>(&override_str)
+        .map_err(|e| anyhow::format_err!("Failed to deserialize override: {e}"))?;
+    merge_values(&mut merged, override_value);
+    serde_json::from_value(merged).map_err(|e| anyhow::format_err!("Merged config is invalid: {e}"))
+}
+
+fn diff_values(
+    path: &str,
+    a: &Value,
+    b: &Value,
+    default: Option<&Value>,
+    differences: &mut Vec<ConfigDifference>,
+) {
+    if a == b {
+        return;
+    }
+    if let (Value::Object(a), Value::Object(b)) = (a, b) {
+        let mut keys = a.keys().chain(b.keys()).collect::<Vec<_>>();
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            let field_path = if path.is_empty() { key.clone() } else { format!("{path}.{key}") };
+            diff_values(
+                &field_path,
+                a.get(key).unwrap_or(&Value::Null),
+                b.get(key).unwrap_or(&Value::Null),
+                default.and_then(|default| default.get(key)),
+                differences,
+            );
+        }
+        return;
+    }
+    differences.push(ConfigDifference {
+        path: path.to_string(),
+        a: a.clone(),
+        b: b.clone(),
+        default: default.cloned(),
+    });
+}
+
+// Objects are merged field by field, any other override value replaces the base one
+fn merge_values(base: &mut Value, override_value: Value) {
+    match (base, override_value) {
+        (Value::Object(base), Value::Object(override_value)) => {
+            for (key, value) in override_value {
+                match base.get_mut(&key) {
+                    Some(base_value) => merge_values(base_value, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, override_value) => *base = override_value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_diff_and_merge_values() {
+        let base = json!({"global": {"a": 1, "b": [1, 2], "c": {"d": "x"}}});
+        let override_value = json!({"global": {"b": [3], "c": {"e": "y"}}});
+        let mut merged = base.clone();
+        merge_values(&mut merged, override_value);
+        assert_eq!(merged, json!({"global": {"a": 1, "b": [3], "c": {"d": "x", "e": "y"}}}));
+
+        let defaults = json!({"global": {"b": []}});
+        let mut differences = vec![];
+        diff_values("", &base, &merged, Some(&defaults), &mut differences);
+        assert_eq!(
+            differences,
+            vec![
+                ConfigDifference {
+                    path: "global.b".to_string(),
+                    a: json!([1, 2]),
+                    b: json!([3]),
+                    default: Some(json!([])),
+                },
+                ConfigDifference {
+                    path: "global.c.e".to_string(),
+                    a: Value::Null,
+                    b: json!("y"),
+                    default: None,
+                },
+            ]
+        );
+    }
+}
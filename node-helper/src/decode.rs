@@ -0,0 +1,288 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use node::bls::envelope::BLSSignedEnvelope;
+use node::bls::envelope::Envelope;
+use node::bls::GoshBLS;
+use node::types::extensions::EXTENSION_REGISTRY;
+use node::types::AckiNackiBlock;
+use serde::Serialize;
+use tvm_block::CommonMsgInfo;
+use tvm_block::Deserializable;
+use tvm_block::GetRepresentationHash;
+use tvm_block::HashmapAugType;
+use tvm_block::Message;
+use tvm_block::Transaction;
+use tvm_types::HashmapType;
+use tvm_types::SliceData;
+
+#[derive(Serialize, Debug)]
+pub struct DecodedBlock {
+    /// `envelope` for blocks stored by the node repository or sent over the
+    /// network, `boc` for bare TVM blocks.
+    pub format: &'static str,
+    pub seq_no: u32,
+    pub gen_utime: u32,
+    pub parent_seq_no: Option<u32>,
+    pub parent_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thread_id: Option<String>,
+    /// Number of signatures of each signer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signatures: Option<BTreeMap<u16, u16>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub common_section: Option<DecodedCommonSection>,
+    pub transactions: Vec<DecodedTransaction>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DecodedCommonSection {
+    pub round: u64,
+    pub producer_id: String,
+    pub block_height: u64,
+    pub verify_complexity: u16,
+    pub refs: Vec<String>,
+    pub threads_table_rows: Option<usize>,
+    pub block_keeper_set_changes: usize,
+    pub block_attestations: usize,
+    pub acks: usize,
+    pub nacks: usize,
+    pub has_producer_selector: bool,
+    /// Ids of the common section extensions with the registered names
+    pub extensions: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DecodedTransaction {
+    pub hash: String,
+    pub account: String,
+    pub lt: u64,
+    pub now: u32,
+    pub in_msg: Option<String>,
+    pub out_msgs: usize,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct DecodedMessage {
+    pub hash: String,
+    /// `internal`, `external_in` or `external_out`
+    #[serde(rename = "type")]
+    pub msg_type: &'static str,
+    pub src: String,
+    pub dst: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_lt: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bounce: Option<bool>,
+    pub has_state_init: bool,
+    pub body: Option<DecodedBody>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct DecodedBody {
+    pub bits: usize,
+    pub refs: usize,
+    /// Hex of the root cell data bits
+    pub data: String,
+}
+
+/// Input is either a path to a binary or hex file, or a hex string itself.
+pub fn read_input(input: &str) -> anyhow::Result<Vec<u8>> {
+    let path = Path::new(input);
+    let data = if path.exists() {
+        std::fs::read(path).map_err(|e| anyhow::format_err!("Failed to read {input}: {e}"))?
+    } else {
+        input.as_bytes().to_vec()
+    };
+    match std::str::from_utf8(&data) {
+        Ok(text) if !text.trim().is_empty() => {
+            let text = text.trim().trim_start_matches("0x");
+            if text.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return hex::decode(text).map_err(|e| anyhow::format_err!("Invalid hex: {e}"));
+            }
+            if !path.exists() {
+                anyhow::bail!("Input is neither an existing file nor a hex string");
+            }
+            Ok(data)
+        }
+        _ => Ok(data),
+    }
+}
+
+pub fn decode_block(data: &[u8]) -> anyhow::Result<DecodedBlock> {
+    // Repository and network blocks are bincode serialized envelopes, bare
+    // TVM blocks start with the BOC magic
+    if let Ok(block) = tvm_block::Block::construct_from_bytes(data) {
+        return decode_tvm_block(&block, "boc");
+    }
+    let envelope =
+        bincode::deserialize::<Envelope<GoshBLS, AckiNackiBlock>>(data).map_err(|e| {
+            anyhow::format_err!("Data is neither a block BOC nor a block envelope: {e}")
+        })?;
+    let block = envelope.data();
+    let mut decoded = decode_tvm_block(block.tvm_block(), "envelope")?;
+    let common_section = block.get_common_section();
+    decoded.block_id = Some(format!("{:x}", block.identifier()));
+    decoded.parent_id = Some(format!("{:x}", block.parent()));
+    decoded.thread_id = Some(format!("{:x}", common_section.thread_id));
+    decoded.signatures = Some(envelope.clone_signature_occurrences().into_iter().collect());
+    decoded.common_section = Some(DecodedCommonSection {
+        round: common_section.round,
+        producer_id: common_section.producer_id.to_string(),
+        block_height: *common_section.block_height.height(),
+        verify_complexity: common_section.verify_complexity,
+        refs: common_section.refs.iter().map(|block_id| format!("{block_id:x}")).collect(),
+        threads_table_rows: common_section
+            .threads_table
+            .as_ref()
+            .map(|threads_table| threads_table.rows().count()),
+        block_keeper_set_changes: common_section.block_keeper_set_changes.len(),
+        block_attestations: common_section.block_attestations.len(),
+        acks: common_section.acks.len(),
+        nacks: common_section.nacks.len(),
+        has_producer_selector: common_section.producer_selector.is_some(),
+        extensions: common_section
+            .extensions
+            .entries()
+            .map(|(id, entry)| match EXTENSION_REGISTRY.get(*id) {
+                Some(info) => format!("{id}:{}@{}", info.name, entry.version),
+                None => format!("{id}@{}", entry.version),
+            })
+            .collect(),
+    });
+    Ok(decoded)
+}
+
+fn decode_tvm_block(
+    block: &tvm_block::Block,
+    format: &'static str,
+) -> anyhow::Result<DecodedBlock> {
+    let info =
+        block.read_info().map_err(|e| anyhow::format_err!("Failed to read block info: {e}"))?;
+    let prev = info.read_prev_ref().ok().and_then(|prev_ref| prev_ref.prev1().ok());
+    let mut transactions = vec![];
+    block
+        .read_extra()
+        .map_err(|e| anyhow::format_err!("Failed to read block extra: {e}"))?
+        .read_account_blocks()
+        .map_err(|e| anyhow::format_err!("Failed to read account blocks: {e}"))?
+        .iterate_objects(|account_block| {
+            account_block.transactions().iterate_slices(|_, transaction_slice| {
+                let cell = transaction_slice.reference(0)?;
+                let transaction = Transaction::construct_from(&mut SliceData::load_cell(cell)?)?;
+                transactions.push(DecodedTransaction {
+                    hash: transaction.hash()?.to_hex_string(),
+                    account: transaction.account_id().to_hex_string(),
+                    lt: transaction.logical_time(),
+                    now: transaction.now(),
+                    in_msg: transaction.in_msg_cell().map(|cell| cell.repr_hash().to_hex_string()),
+                    out_msgs: transaction.outmsg_cnt as usize,
+                });
+                Ok(true)
+            })?;
+            Ok(true)
+        })
+        .map_err(|e| anyhow::format_err!("Failed to read block transactions: {e}"))?;
+    transactions.sort_by_key(|transaction| transaction.lt);
+    Ok(DecodedBlock {
+        format,
+        seq_no: info.seq_no(),
+        gen_utime: info.gen_utime().as_u32(),
+        parent_seq_no: prev.as_ref().map(|prev| prev.seq_no),
+        parent_id: prev.as_ref().map(|prev| prev.root_hash.to_hex_string()),
+        block_id: None,
+        thread_id: None,
+        signatures: None,
+        common_section: None,
+        transactions,
+    })
+}
+
+pub fn decode_message(data: &[u8]) -> anyhow::Result<DecodedMessage> {
+    let message = Message::construct_from_bytes(data)
+        .map_err(|e| anyhow::format_err!("Failed to decode message BOC: {e}"))?;
+    let hash = message
+        .hash()
+        .map_err(|e| anyhow::format_err!("Failed to calculate message hash: {e}"))?
+        .to_hex_string();
+    let body = message.body().map(|body| DecodedBody {
+        bits: body.remaining_bits(),
+        refs: body.remaining_references(),
+        data: body.to_hex_string(),
+    });
+    let has_state_init = message.state_init().is_some();
+    let decoded = match message.header() {
+        CommonMsgInfo::IntMsgInfo(header) => DecodedMessage {
+            hash,
+            msg_type: "internal",
+            src: header.src.to_string(),
+            dst: header.dst.to_string(),
+            value: Some(header.value.grams.to_string()),
+            created_lt: Some(header.created_lt),
+            created_at: Some(header.created_at.as_u32()),
+            bounce: Some(header.bounce),
+            has_state_init,
+            body,
+        },
+        CommonMsgInfo::ExtInMsgInfo(header) => DecodedMessage {
+            hash,
+            msg_type: "external_in",
+            src: header.src.to_string(),
+            dst: header.dst.to_string(),
+            value: None,
+            created_lt: None,
+            created_at: None,
+            bounce: None,
+            has_state_init,
+            body,
+        },
+        CommonMsgInfo::ExtOutMsgInfo(header) => DecodedMessage {
+            hash,
+            msg_type: "external_out",
+            src: header.src.to_string(),
+            dst: header.dst.to_string(),
+            value: None,
+            created_lt: Some(header.created_lt),
+            created_at: Some(header.created_at.as_u32()),
+            bounce: None,
+            has_state_init,
+            body,
+        },
+    };
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use tvm_block::ExternalInboundMessageHeader;
+    use tvm_block::Serializable;
+    use tvm_types::BuilderData;
+    use tvm_types::IBitstring;
+
+    use super::*;
+
+    #[test]
+    fn test_decode_message() {
+        let mut body = BuilderData::new();
+        body.append_u32(0xdeadbeef).unwrap();
+        let mut message = Message::with_ext_in_header(ExternalInboundMessageHeader::default());
+        message.set_body(SliceData::load_builder(body).unwrap());
+        let boc = hex::encode(message.write_to_bytes().unwrap());
+
+        let decoded = decode_message(&read_input(&format!("0x{boc}")).unwrap()).unwrap();
+        assert_eq!(decoded.msg_type, "external_in");
+        assert_eq!(decoded.hash, message.hash().unwrap().to_hex_string());
+        assert!(!decoded.has_state_init);
+        let body = decoded.body.unwrap();
+        assert_eq!(body.bits, 32);
+        assert_eq!(body.data, "deadbeef");
+
+        assert!(read_input("not a hex").is_err());
+    }
+}
@@ -0,0 +1,1036 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process::exit;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::ArgAction;
+use clap::Parser;
+use clap::Subcommand;
+use database::sqlite::sqlite_helper::SQLITE_DATA_DIR;
+use gosh_blst::gen_bls_key_pair;
+use gosh_blst::BLSKeyPair;
+use http_server::sign_admin_challenge;
+use http_server::AdminChallenge;
+use http_server::SignedBkSetHistory;
+use network::parse_publisher_addr;
+use network::try_parse_socket_addr;
+use node::bls::gosh_bls::PubKey;
+use node::bls::gosh_bls::Secret;
+use node::bls::GoshBLS;
+use node::config::load_config_from_file;
+use node::config::save_config_to_file;
+use node::config::GlobalConfig;
+use node::config::NetworkConfig;
+use node::config::NodeConfig;
+use node::helper::key_handling::key_pairs_from_file;
+use node::node::NodeIdentifier;
+use node::types::AccountAddress;
+use node::types::RndSeed;
+use serde_json::json;
+use tvm_client::ClientConfig;
+use tvm_client::ClientContext;
+
+mod archive;
+mod bench;
+mod config_tools;
+mod decode;
+mod repo;
+mod slash;
+mod zerostate;
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    /// Set up AckiNacki node config
+    Config(Config),
+    /// Generate BLS key pair
+    Bls(Bls),
+    GenKeys(GenKeys),
+    /// Export signed BK set history of a running node
+    BkSetHistory(BkSetHistory),
+    /// Sign admin API challenge of a running node with the node owner wallet keys
+    AdminAuth(AdminAuth),
+    /// Decode block or message BOC and print it as JSON
+    Decode(Decode),
+    /// Maintain the block manager SQLite archive
+    Archive(Archive),
+    /// Inspect the zerostate file
+    Zerostate(Zerostate),
+    /// Inspect the node repository
+    Repo(Repo),
+    /// Measure node performance on this hardware
+    Bench(Bench),
+    /// Build the block keeper wallet message that slashes the BLS key owner
+    Slash(Slash),
+}
+
+#[derive(Parser, Debug)]
+pub struct Bls {
+    /// Path where to store BLS key pair
+    #[arg(long)]
+    path: Option<PathBuf>,
+
+    /// Flag that indicates that helper should remove old file, before generating new BLS key pair
+    /// If not set, new key will be appended to file
+    #[clap(short, long, action=ArgAction::SetTrue, default_value = "false", requires("path"))]
+    remove_old: bool,
+
+    /// Quiet execution. Do not print new pubkey
+    #[clap(short, long, action=ArgAction::SetTrue, default_value = "false", requires("path"))]
+    quiet: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct GenKeys {
+    /// Path where to store key pair
+    #[arg(long)]
+    path: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+pub struct BkSetHistory {
+    /// Node SDK API base url (e.g. "http://127.0.0.1:8600")
+    #[arg(long)]
+    url: url::Url,
+
+    /// Export history of the single thread only
+    #[arg(long)]
+    thread_id: Option<String>,
+
+    /// Expected signer pubkey (64-char hex). Export fails if it was signed by another key
+    #[arg(long)]
+    signer_pubkey: Option<String>,
+
+    /// Path where to store the export. If not set, export is printed to stdout
+    #[arg(long)]
+    path: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+pub struct AdminAuth {
+    /// Node SDK API base url (e.g. "http://127.0.0.1:8600")
+    #[arg(long)]
+    url: url::Url,
+
+    /// Path to the node owner wallet key pair (generated by `gen-keys`)
+    #[arg(long)]
+    keys: PathBuf,
+
+    /// Method of the authorized request
+    #[arg(long, default_value = "GET")]
+    method: String,
+
+    /// Endpoint of the authorized request with the query if any (e.g.
+    /// "v2/status")
+    #[arg(long)]
+    endpoint: String,
+
+    /// Path to the exact body of the authorized request. If not set, the
+    /// request is signed with an empty body
+    #[arg(long)]
+    body: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+#[group(required = true, multiple = false)]
+pub struct Decode {
+    /// Path to the block file (repository envelope or BOC) or its hex
+    #[arg(long)]
+    block: Option<String>,
+
+    /// Path to the message BOC file or its hex
+    #[arg(long)]
+    message: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct Archive {
+    #[command(subcommand)]
+    action: ArchiveAction,
+}
+
+#[derive(Subcommand, Debug)]
+enum ArchiveAction {
+    /// Repopulate the archive from finalized blocks without resyncing the chain
+    Rebuild {
+        /// Node repository directory. Finalized chains of all threads are archived
+        #[arg(long, required_unless_present = "blocks_dir", conflicts_with = "blocks_dir")]
+        repo_dir: Option<PathBuf>,
+
+        /// Directory with exported block envelopes
+        #[arg(long)]
+        blocks_dir: Option<PathBuf>,
+
+        /// Archive directory. It must contain the empty archive with the applied schema
+        #[arg(long, env = "SQLITE_PATH", default_value = SQLITE_DATA_DIR)]
+        data_dir: PathBuf,
+
+        /// Archive file name
+        #[arg(long, default_value = "bm-archive.db")]
+        db_file: PathBuf,
+
+        /// Continue an interrupted rebuild: keep the archive and skip blocks already stored
+        #[clap(long, action=ArgAction::SetTrue, default_value = "false")]
+        resume: bool,
+    },
+}
+
+#[derive(Parser, Debug)]
+pub struct Zerostate {
+    #[command(subcommand)]
+    action: ZerostateAction,
+}
+
+#[derive(Subcommand, Debug)]
+enum ZerostateAction {
+    /// Print threads and block keepers of the zerostate
+    Info {
+        /// Path to the zerostate file
+        #[arg(long)]
+        path: PathBuf,
+    },
+}
+
+#[derive(Parser, Debug)]
+pub struct Repo {
+    #[command(subcommand)]
+    action: RepoAction,
+}
+
+#[derive(Subcommand, Debug)]
+enum RepoAction {
+    /// Print the last finalized block of each thread
+    Info {
+        /// Node repository directory
+        #[arg(long, default_value = "./data")]
+        repo_dir: PathBuf,
+    },
+    /// Convert a thread snapshot made by another node version or the block
+    /// manager into the current layout and share it in the directory
+    Import {
+        /// Snapshot file
+        #[arg(long)]
+        input: PathBuf,
+        /// Directory of the shared states (`external_state_share_local_base_dir`
+        /// of the node or a directory served as a static storage)
+        #[arg(long)]
+        share_dir: PathBuf,
+    },
+}
+
+#[derive(Parser, Debug)]
+pub struct Slash {
+    /// Address of the slashed block keeper wallet (64-char hex)
+    #[arg(long)]
+    wallet: String,
+
+    /// BLS public key of the slashed block keeper (hex)
+    #[arg(long)]
+    bls_key: String,
+
+    /// "full" to slash the whole stake or the slashed percent (1..=99)
+    #[arg(long, default_value = "full")]
+    slash_type: String,
+}
+
+#[derive(Parser, Debug)]
+pub struct Bench {
+    #[command(subcommand)]
+    action: BenchAction,
+}
+
+#[derive(Subcommand, Debug)]
+enum BenchAction {
+    /// Replay messages against a state with the block producer executor and report throughput
+    /// for parallelization levels 1, 2, 4 and so on up to the given one
+    Execute {
+        /// Path to the node config. Blockchain config and contract code hashes are taken from it
+        #[arg(short, long)]
+        config_path: PathBuf,
+
+        /// Path to the saved optimistic state
+        #[arg(long)]
+        state: PathBuf,
+
+        /// Path to the file with external message BOCs, one hex per line
+        #[arg(long)]
+        messages: PathBuf,
+
+        /// Highest parallelization level to measure
+        #[arg(long, default_value = "1")]
+        parallelization: usize,
+
+        /// Node repository directory. Accounts unloaded from the state are read from it
+        #[arg(long, default_value = "./data")]
+        repo_dir: PathBuf,
+
+        /// Number of runs for each level, the median time is reported
+        #[arg(long, default_value = "3")]
+        runs: usize,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigAction {
+    /// Print fields that differ between two configs, annotated with their defaults
+    Diff { a: PathBuf, b: PathBuf },
+    /// Apply per-node override on top of the base config
+    Merge {
+        /// Path to the base config
+        #[arg(long)]
+        base: PathBuf,
+
+        /// Path to the override. It may contain only the fields to override
+        #[arg(long = "override")]
+        override_path: PathBuf,
+
+        /// Path where to store the merged config. If not set, it is printed to stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Parser, Debug)]
+#[command(subcommand_negates_reqs = true, args_conflicts_with_subcommands = true)]
+pub struct Config {
+    #[command(subcommand)]
+    action: Option<ConfigAction>,
+
+    /// Path to the config file
+    #[arg(short, long, required = true)]
+    config_file_path: Option<PathBuf>,
+
+    /// Create default config if config is invalid or does not exist
+    #[clap(short, long, action=ArgAction::SetTrue, default_value = "false")]
+    default: bool,
+
+    /// Node id should be specified as 64-len hex string with keeper wallet address.
+    #[arg(long, env)]
+    node_id: Option<String>,
+
+    /// Blockchain config path
+    #[arg(long, env)]
+    blockchain_config: Option<PathBuf>,
+
+    /// Path to file with node key pair
+    #[arg(long, env)]
+    keys_path: Option<String>,
+
+    /// Optional secret key of the block keeper's owner wallet key pair.
+    /// Should be represented as a 64-char hex.
+    /// If specified, then owner_key_path should be omitted.
+    #[arg(long)]
+    pub network_my_ed_secret: Option<String>,
+
+    /// Optional path to the block keeper's owner wallet key file.
+    /// Should be stored as json `{ "public": "64-char hex", "secret": "64-char hex" }`.
+    /// If specified, then owner_key_secret should be omitted.
+    #[arg(long)]
+    pub network_my_ed_key_path: Option<String>,
+
+    /// Node socket to listen on (QUIC UDP)
+    #[arg(long, env)]
+    pub bind: Option<SocketAddr>,
+
+    /// Node address to advertise (QUIC UDP)
+    #[arg(long, env)]
+    #[arg(value_parser = parse_node_addr)]
+    pub node_advertise_addr: Option<SocketAddr>,
+
+    /// Gossip UDP socket (e.g., 127.0.0.1:10000)
+    #[arg(long, env)]
+    pub gossip_listen_addr: Option<SocketAddr>,
+
+    /// Gossip advertise address (e.g., hostname:port or ip:port)
+    #[arg(long, env)]
+    #[arg(value_parser = parse_gossip_addr)]
+    pub gossip_advertise_addr: Option<SocketAddr>,
+
+    /// Gossip seed nodes addresses (e.g., hostname:port or ip:port)
+    #[arg(long, env)]
+    #[arg(long, env, value_delimiter = ',', value_parser = parse_gossip_addr)]
+    pub gossip_seeds: Option<Vec<SocketAddr>>,
+
+    #[arg(long, env)]
+    pub block_manager_listen_addr: Option<SocketAddr>,
+
+    /// All static stores urls-bases (e.g. "https://example.com/storage/").
+    /// Use "srv+" scheme prefix to resolve url via SRV record
+    /// (e.g. "srv+https://_storage._tcp.example.com/storage/")
+    #[arg(long, env, value_delimiter = ',')]
+    pub static_storages: Option<Vec<url::Url>>,
+
+    /// Socket address for SDK API
+    #[arg(long, env)]
+    pub api_addr: Option<String>,
+
+    /// Advertise address for SDK API
+    #[arg(long, env)]
+    pub api_advertise_addr: Option<url::Url>,
+
+    /// Path to zerostate file
+    #[arg(long, env)]
+    pub zerostate_path: Option<PathBuf>,
+
+    /// Local shared path where to store files for sync.
+    #[arg(long, env)]
+    pub external_state_share_local_base_dir: Option<PathBuf>,
+
+    #[arg(long, env)]
+    pub network_send_buffer_size: Option<usize>,
+
+    #[arg(long, env)]
+    #[arg(value_parser = parse_duration::parse)]
+    pub min_time_between_state_publish_directives: Option<Duration>,
+
+    #[arg(long, env)]
+    pub bm_api_socket: Option<SocketAddr>,
+
+    #[arg(long, env)]
+    pub bk_api_socket: Option<SocketAddr>,
+
+    #[arg(long, env)]
+    pub parallelization_level: Option<usize>,
+
+    #[arg(long, env)]
+    pub verification_parallelization_level: Option<usize>,
+
+    #[arg(long, env)]
+    #[arg(value_parser = parse_duration::parse)]
+    pub node_joining_timeout: Option<Duration>,
+
+    #[arg(long, env)]
+    pub block_keeper_seed_path: Option<String>,
+
+    #[arg(long)]
+    pub producer_change_gap_size: Option<usize>,
+
+    /// Number of max tries to download shared state
+    #[arg(long)]
+    pub shared_state_max_download_tries: Option<u8>,
+
+    /// Retry timeout for shared state download
+    #[arg(long)]
+    pub shared_state_retry_download_timeout_millis: Option<u64>,
+
+    /// Comma separated files and directories with network TLS certificates
+    #[arg(long)]
+    pub network_peer_certs: Option<String>,
+
+    /// The name of the TLS cert file used for auth.
+    #[arg(long)]
+    pub network_my_cert: Option<PathBuf>,
+
+    /// The name of the TLS key file used for auth.
+    #[arg(long)]
+    pub network_my_key: Option<PathBuf>,
+
+    /// Predefined subscriptions to peers.
+    #[arg(long)]
+    pub network_subscribe: Option<String>,
+
+    /// Subscribe SRV records, comma separated (e.g. "_publisher._udp.proxies.example.com").
+    #[arg(long)]
+    pub network_subscribe_srv: Option<String>,
+
+    /// Interval to re-resolve SRV records, milliseconds.
+    #[arg(long)]
+    pub network_srv_refresh_interval_millis: Option<u64>,
+
+    /// Interval to reload peer certificates, milliseconds. Zero disables the reload.
+    #[arg(long)]
+    pub network_peer_certs_refresh_interval_millis: Option<u64>,
+
+    /// Proxy list to propagate via gossip.
+    #[arg(long)]
+    pub network_proxies: Option<String>,
+
+    /// Chitchat cluster id for gossip
+    #[arg(long)]
+    pub chitchat_cluster_id: Option<String>,
+
+    /// Number of block intervals without incoming messages before resubscribing (0 disables)
+    #[arg(long)]
+    pub subscription_silence_blocks: Option<u32>,
+
+    // /// Number of blocks after which the account is unloaded from shard state.
+    // #[arg(long)]
+    // pub unload_after: Option<u32>,
+    /// Thread load (aggregated number of messages in a queue to start splitting a thread) threshold for split
+    #[arg(long)]
+    pub thread_load_threshold: Option<usize>,
+
+    /// Thread load window size, which is used to calculate thread load
+    #[arg(long)]
+    pub thread_load_window_size: Option<usize>,
+
+    /// Maximum of threads
+    #[arg(long)]
+    pub thread_count_soft_limit: Option<usize>,
+
+    /// State cache size in local repository
+    #[arg(long)]
+    pub state_cache_size: Option<usize>,
+
+    /// Path to the local message durable storage
+    #[arg(long)]
+    pub message_storage_path: Option<PathBuf>,
+
+    /// Epoch contract code hash, overrides the hash resolved by the node from
+    /// the block keeper root contract
+    #[arg(long, env)]
+    pub block_keeper_epoch_code_hash: Option<String>,
+
+    /// PreEpoch contract code hash, overrides the hash resolved by the node
+    /// from the block keeper root contract
+    #[arg(long, env)]
+    pub block_keeper_preepoch_code_hash: Option<String>,
+
+    /// BlockKeeper node owner wallet pubkey
+    #[arg(long, env)]
+    pub node_wallet_pubkey: Option<String>,
+
+    /// Path to file with keys pair for signing the authorization token of incoming external messages
+    /// Required for direct sending external messages via node
+    #[arg(long)]
+    pub signing_keys: Option<String>,
+
+    /// BP rotation round min time in millis
+    #[arg(long, env)]
+    pub round_min_time_millis: Option<u64>,
+
+    /// BP rotation round step in millis
+    #[arg(long, env)]
+    pub round_step_millis: Option<u64>,
+
+    /// BP rotation round max time in millis
+    #[arg(long, env)]
+    pub round_max_time_millis: Option<u64>,
+
+    /// Number of block times without a block after which BP skip votes are sent (0 to disable)
+    #[arg(long, env)]
+    pub producer_skip_vote_after_blocks: Option<u64>,
+
+    /// Address of the account that stores blockchain config params
+    #[arg(long, env)]
+    pub blockchain_config_account: Option<String>,
+
+    /// Seq no starting from which blockchain config is read from the config account
+    #[arg(long, env)]
+    pub blockchain_config_activation_seq_no: Option<u32>,
+
+    /// Address of the system contract that schedules deferred messages
+    #[arg(long, env)]
+    pub timer_contract_account: Option<String>,
+
+    /// Maximum number of cells in an account state (0 to disable)
+    #[arg(long, env)]
+    pub max_account_state_cells: Option<u64>,
+}
+
+const DEFAULT_NODE_PORT: u16 = 8500;
+const DEFAULT_GOSSIP_PORT: u16 = 10000;
+fn parse_node_addr(s: &str) -> Result<SocketAddr, String> {
+    try_parse_socket_addr(s, DEFAULT_NODE_PORT).map_err(|err| err.to_string())
+}
+
+fn parse_gossip_addr(s: &str) -> Result<SocketAddr, String> {
+    try_parse_socket_addr(s, DEFAULT_GOSSIP_PORT).map_err(|err| err.to_string())
+}
+
+/// Executes the helper command. The commands are shared by the `node-helper`
+/// and `acki-nacki` binaries.
+pub fn run(command: Commands) -> anyhow::Result<()> {
+    match command {
+        Commands::Config(Config { action: Some(action), .. }) => match action {
+            ConfigAction::Diff { a, b } => {
+                let differences = config_tools::diff_config_files(&a, &b)?;
+                println!("{}", serde_json::to_string_pretty(&differences)?);
+                Ok(())
+            }
+            ConfigAction::Merge { base, override_path, output } => {
+                let config = config_tools::merge_config_files(&base, &override_path)?;
+                if let Some(path) = output {
+                    save_config_to_file(&config, &path)
+                } else {
+                    println!("{}", serde_yaml::to_string(&config)?);
+                    Ok(())
+                }
+            }
+        },
+        Commands::Config(config_cmd) => {
+            let Some(config_file_path) = config_cmd.config_file_path.clone() else {
+                anyhow::bail!("config_file_path must be specified");
+            };
+            let mut config = match load_config_from_file(&config_file_path) {
+                Ok(config) => config,
+                Err(e) => {
+                    if config_cmd.default {
+                        println!("Failed to open config, create a default one");
+                        let Some(node_id) = config_cmd.node_id.clone() else {
+                            eprintln!("node_id must be specified for default config");
+                            exit(2);
+                        };
+                        let Some(cluster_id) = config_cmd.chitchat_cluster_id.clone() else {
+                            eprintln!("chitchat_cluster_id must be specified for default config");
+                            exit(2);
+                        };
+                        let Some(node_advertise_addr) = config_cmd.node_advertise_addr else {
+                            eprintln!("node_advertise_addr must be specified for default config");
+                            exit(2);
+                        };
+                        let Some(api_addr) = config_cmd.api_addr.clone() else {
+                            eprintln!("api_addr must be specified for default config");
+                            exit(2);
+                        };
+                        let Some(ref api_advertise_addr) = config_cmd.api_advertise_addr else {
+                            eprintln!("api_advertise_addr must be specified for default config");
+                            exit(2);
+                        };
+                        let local = NodeConfig::builder()
+                            .node_id(NodeIdentifier::from_str(&node_id).expect("Invalid node ID"))
+                            .build();
+                        let network_config = NetworkConfig::builder()
+                            .chitchat_cluster_id(cluster_id)
+                            .node_advertise_addr(node_advertise_addr)
+                            .api_addr(api_addr)
+                            .api_advertise_addr(api_advertise_addr.clone())
+                            .build();
+
+                        node::config::Config {
+                            global: GlobalConfig::default(),
+                            network: network_config,
+                            local,
+                        }
+                    } else {
+                        eprint!("Error: {e}");
+                        exit(1);
+                    }
+                }
+            };
+
+            if let Some(node_id) = config_cmd.node_id {
+                config.local.node_id = NodeIdentifier::from_str(&node_id)
+                    .map_err(|err| anyhow::anyhow!("Invalid node_id [{node_id}]: {err}"))?;
+            }
+
+            if let Some(blockchain_config) = config_cmd.blockchain_config {
+                config.local.blockchain_config_path = blockchain_config;
+            }
+
+            if let Some(keys_path) = config_cmd.keys_path {
+                config.local.key_path = keys_path;
+            }
+
+            if let Some(zerostate_path) = config_cmd.zerostate_path {
+                config.local.zerostate_path = zerostate_path;
+            }
+
+            if let Some(external_state_share_local_base_dir) =
+                config_cmd.external_state_share_local_base_dir
+            {
+                config.local.external_state_share_local_base_dir =
+                    external_state_share_local_base_dir;
+            }
+
+            if let Some(bind) = config_cmd.bind {
+                config.network.bind = bind;
+            }
+
+            if let Some(node_advertise_addr) = config_cmd.node_advertise_addr {
+                config.network.node_advertise_addr = node_advertise_addr;
+            }
+
+            if let Some(gossip_listen_addr) = config_cmd.gossip_listen_addr {
+                config.network.gossip_listen_addr = gossip_listen_addr;
+            }
+
+            if let Some(gossip_advertise_addr) = config_cmd.gossip_advertise_addr {
+                config.network.gossip_advertise_addr = Some(gossip_advertise_addr);
+            }
+
+            if let Some(gossip_seeds) = config_cmd.gossip_seeds {
+                config.network.gossip_seeds = gossip_seeds;
+            }
+
+            if let Some(block_manager_listen_addr) = config_cmd.block_manager_listen_addr {
+                config.network.block_manager_listen_addr = block_manager_listen_addr;
+            }
+
+            if let Some(static_storages) = config_cmd.static_storages {
+                config.network.static_storages = static_storages;
+            }
+
+            if let Some(api_addr) = config_cmd.api_addr {
+                config.network.api_addr = api_addr;
+            }
+
+            if let Some(ref api_advertise_addr) = config_cmd.api_advertise_addr {
+                config.network.api_advertise_addr = api_advertise_addr.clone();
+            }
+
+            if let Some(network_send_buffer_size) = config_cmd.network_send_buffer_size {
+                config.network.send_buffer_size = network_send_buffer_size;
+            }
+
+            if let Some(min_time_between_state_publish_directives) =
+                config_cmd.min_time_between_state_publish_directives
+            {
+                config.global.min_time_between_state_publish_directives =
+                    min_time_between_state_publish_directives;
+            }
+
+            if let Some(node_joining_timeout) = config_cmd.node_joining_timeout {
+                config.global.node_joining_timeout = node_joining_timeout;
+            }
+
+            if let Some(bm_api_socket) = config_cmd.bm_api_socket {
+                config.network.bm_api_socket = Some(bm_api_socket);
+            }
+
+            if let Some(bk_api_socket) = config_cmd.bk_api_socket {
+                config.network.bk_api_socket = Some(bk_api_socket);
+            }
+
+            if let Some(parallelization_level) = config_cmd.parallelization_level {
+                config.local.parallelization_level = parallelization_level;
+            }
+
+            if let Some(level) = config_cmd.verification_parallelization_level {
+                config.local.verification_parallelization_level = Some(level);
+            }
+
+            if let Some(block_keeper_epoch_code_hash) = config_cmd.block_keeper_epoch_code_hash {
+                config.global.block_keeper_epoch_code_hash =
+                    block_keeper_epoch_code_hash.trim_start_matches("0x").to_string();
+            }
+
+            if let Some(block_keeper_preepoch_code_hash) =
+                config_cmd.block_keeper_preepoch_code_hash
+            {
+                config.global.block_keeper_preepoch_code_hash =
+                    block_keeper_preepoch_code_hash.trim_start_matches("0x").to_string();
+            }
+
+            if let Some(block_keeper_seed_path) = config_cmd.block_keeper_seed_path {
+                config.local.block_keeper_seed_path = block_keeper_seed_path;
+            }
+
+            if let Some(producer_change_gap_size) = config_cmd.producer_change_gap_size {
+                config.global.producer_change_gap_size = producer_change_gap_size;
+            }
+
+            if let Some(shared_state_max_download_tries) =
+                config_cmd.shared_state_max_download_tries
+            {
+                config.network.shared_state_max_download_tries = shared_state_max_download_tries;
+            }
+
+            if let Some(shared_state_retry_download_timeout_millis) =
+                config_cmd.shared_state_retry_download_timeout_millis
+            {
+                config.network.shared_state_retry_download_timeout_millis =
+                    shared_state_retry_download_timeout_millis;
+            }
+
+            if let Some(subscription_silence_blocks) = config_cmd.subscription_silence_blocks {
+                config.network.subscription_silence_blocks = subscription_silence_blocks;
+            }
+
+            if let Some(certs) = config_cmd.network_peer_certs {
+                config.network.peer_certs = certs.split(',').map(PathBuf::from).collect();
+            }
+
+            if let Some(cert) = config_cmd.network_my_cert {
+                config.network.my_cert = cert;
+            }
+            if let Some(key) = config_cmd.network_my_key {
+                config.network.my_key = key;
+            }
+
+            if let Some(subscribe) = config_cmd.network_subscribe {
+                config.network.subscribe = subscribe
+                    .split(',')
+                    .filter_map(
+                        |x| if !x.is_empty() { Some(parse_publisher_addr(x)) } else { None },
+                    )
+                    .map(|x| x.map(|x| vec![x]))
+                    .collect::<Result<_, _>>()?;
+            }
+
+            if let Some(subscribe_srv) = config_cmd.network_subscribe_srv {
+                config.network.subscribe_srv = subscribe_srv
+                    .split(',')
+                    .filter(|x| !x.is_empty())
+                    .map(|x| x.to_string())
+                    .collect();
+            }
+
+            if let Some(interval) = config_cmd.network_srv_refresh_interval_millis {
+                config.network.srv_refresh_interval_millis = interval;
+            }
+
+            if let Some(interval) = config_cmd.network_peer_certs_refresh_interval_millis {
+                config.network.peer_certs_refresh_interval_millis = interval;
+            }
+
+            if let Some(proxies) = config_cmd.network_proxies {
+                config.network.proxies = proxies
+                    .split(',')
+                    .filter_map(
+                        |x| if !x.is_empty() { Some(parse_publisher_addr(x)) } else { None },
+                    )
+                    .collect::<Result<_, _>>()?;
+            }
+
+            if let Some(cluster_id) = config_cmd.chitchat_cluster_id {
+                config.network.chitchat_cluster_id = cluster_id;
+            }
+
+            if let Some(thread_load_threshold) = config_cmd.thread_load_threshold {
+                config.global.thread_load_threshold = thread_load_threshold;
+            }
+
+            if let Some(thread_load_window_size) = config_cmd.thread_load_window_size {
+                config.global.thread_load_window_size = thread_load_window_size;
+            }
+
+            if let Some(thread_count_soft_limit) = config_cmd.thread_count_soft_limit {
+                config.global.thread_count_soft_limit = thread_count_soft_limit;
+            }
+
+            if let Some(state_cache_size) = config_cmd.state_cache_size {
+                config.local.state_cache_size = state_cache_size;
+            }
+
+            if let Some(secret) = config_cmd.network_my_ed_secret {
+                config.network.my_ed_key_secret = Some(secret);
+                config.network.my_ed_key_path = None;
+            }
+
+            if let Some(key_path) = config_cmd.network_my_ed_key_path {
+                config.network.my_ed_key_secret = None;
+                config.network.my_ed_key_path = Some(key_path);
+            }
+
+            if let Some(node_wallet_pubkey) = config_cmd.node_wallet_pubkey {
+                config.local.node_wallet_pubkey = node_wallet_pubkey;
+            }
+
+            config.local.signing_keys = config_cmd.signing_keys;
+
+            if let Some(round_min_time_millis) = config_cmd.round_min_time_millis {
+                config.global.round_min_time_millis = round_min_time_millis;
+            }
+            if let Some(round_step_millis) = config_cmd.round_step_millis {
+                config.global.round_step_millis = round_step_millis;
+            }
+            if let Some(round_max_time_millis) = config_cmd.round_max_time_millis {
+                config.global.round_max_time_millis = round_max_time_millis;
+            }
+            if let Some(blocks) = config_cmd.producer_skip_vote_after_blocks {
+                config.global.producer_skip_vote_after_blocks = blocks;
+            }
+            if let Some(account) = config_cmd.blockchain_config_account {
+                config.global.blockchain_config_account = Some(account);
+            }
+            if let Some(seq_no) = config_cmd.blockchain_config_activation_seq_no {
+                config.global.blockchain_config_activation_seq_no = seq_no;
+            }
+            if let Some(account) = config_cmd.timer_contract_account {
+                config.global.timer_contract_account = Some(account);
+            }
+            if let Some(cells) = config_cmd.max_account_state_cells {
+                config.global.max_account_state_cells = cells;
+            }
+
+            save_config_to_file(&config, &config_file_path)
+        }
+        Commands::Bls(bls_cmd) => {
+            let keypair = BLSKeyPair::from(gen_bls_key_pair());
+            let rng_seed = RndSeed::from(gen_bls_key_pair().1.to_bytes());
+            if let Some(path) = bls_cmd.path {
+                if !bls_cmd.quiet {
+                    let pubkey = json!({"pubkey": format!("{}", hex::encode(keypair.public))});
+                    println!("{}", serde_json::to_string_pretty(&pubkey)?);
+                }
+                let mut bls_keys_map = if bls_cmd.remove_old {
+                    let _ = std::fs::remove_file(&path);
+                    HashMap::new()
+                } else if std::fs::exists(&path)? {
+                    key_pairs_from_file::<GoshBLS>(path.to_str().unwrap())
+                } else {
+                    HashMap::new()
+                };
+                bls_keys_map
+                    .insert(PubKey::from(keypair.public), (Secret::from(keypair.secret), rng_seed));
+                save_keys_map_to_file(path, bls_keys_map)
+            } else {
+                println!("{}", keypair.to_string()?);
+                Ok(())
+            }
+        }
+        Commands::GenKeys(gen_key_cmd) => {
+            let client = Arc::new(
+                ClientContext::new(ClientConfig::default())
+                    .map_err(|e| anyhow::format_err!("failed to create sdk client: {}", e))?,
+            );
+            let key_pair = tvm_client::crypto::generate_random_sign_keys(client)
+                .map_err(|e| anyhow::format_err!("failed to generate keys: {}", e))?;
+            let keys_json = serde_json::to_string_pretty(&key_pair)
+                .map_err(|e| anyhow::format_err!("failed to serialize the keypair: {}", e))?;
+            if let Some(keys_path) = gen_key_cmd.path {
+                std::fs::write(keys_path, &keys_json)
+                    .map_err(|e| anyhow::format_err!("failed to create file with keys: {}", e))?;
+            } else {
+                println!("{keys_json}");
+            }
+
+            Ok(())
+        }
+        Commands::BkSetHistory(history_cmd) => {
+            let mut url = history_cmd.url.join("v2/bk_set_history")?;
+            if let Some(thread_id) = &history_cmd.thread_id {
+                url.query_pairs_mut().append_pair("thread_id", thread_id);
+            }
+            let history: SignedBkSetHistory = reqwest::blocking::get(url)?
+                .error_for_status()?
+                .json()
+                .map_err(|e| anyhow::format_err!("failed to parse BK set history: {e}"))?;
+            history.verify()?;
+            if let Some(expected) = &history_cmd.signer_pubkey {
+                if history.signer_pubkey.as_ref() != Some(expected) {
+                    anyhow::bail!(
+                        "BK set history is signed by unexpected key: {:?}",
+                        history.signer_pubkey
+                    );
+                }
+            }
+            let history_json = serde_json::to_string_pretty(&history)?;
+            if let Some(path) = history_cmd.path {
+                std::fs::write(path, &history_json)?;
+            } else {
+                println!("{history_json}");
+            }
+            Ok(())
+        }
+        Commands::AdminAuth(auth_cmd) => {
+            let keys: serde_json::Value = serde_json::from_str(
+                &std::fs::read_to_string(&auth_cmd.keys)
+                    .map_err(|e| anyhow::format_err!("failed to read keys file: {e}"))?,
+            )?;
+            let secret = keys["secret"]
+                .as_str()
+                .ok_or_else(|| anyhow::format_err!("keys file has no secret key"))?;
+            let challenge: AdminChallenge =
+                reqwest::blocking::get(auth_cmd.url.join("v2/auth/challenge")?)?
+                    .error_for_status()?
+                    .json()
+                    .map_err(|e| anyhow::format_err!("failed to parse challenge: {e}"))?;
+            let endpoint = auth_cmd.url.join(&auth_cmd.endpoint)?;
+            let body = match &auth_cmd.body {
+                Some(path) => std::fs::read(path).map_err(|e| {
+                    anyhow::format_err!("failed to read body file {}: {e}", path.display())
+                })?,
+                None => vec![],
+            };
+            // Value of the `Authorization` header of a single request, valid
+            // until the challenge expires
+            println!(
+                "{}",
+                sign_admin_challenge(
+                    secret,
+                    &challenge.challenge,
+                    &auth_cmd.method,
+                    endpoint.path(),
+                    endpoint.query().unwrap_or_default(),
+                    &body
+                )?
+            );
+            Ok(())
+        }
+        Commands::Decode(decode_cmd) => {
+            let decoded = match (decode_cmd.block, decode_cmd.message) {
+                (Some(block), _) => {
+                    let block = decode::decode_block(&decode::read_input(&block)?)?;
+                    serde_json::to_string_pretty(&block)?
+                }
+                (_, Some(message)) => {
+                    let message = decode::decode_message(&decode::read_input(&message)?)?;
+                    serde_json::to_string_pretty(&message)?
+                }
+                (None, None) => anyhow::bail!("Either --block or --message must be specified"),
+            };
+            println!("{decoded}");
+            Ok(())
+        }
+        Commands::Archive(Archive {
+            action: ArchiveAction::Rebuild { repo_dir, blocks_dir, data_dir, db_file, resume },
+        }) => {
+            let source = match (repo_dir, blocks_dir) {
+                (Some(repo_dir), _) => archive::BlocksSource::Repository(repo_dir),
+                (_, Some(blocks_dir)) => archive::BlocksSource::Export(blocks_dir),
+                (None, None) => {
+                    anyhow::bail!("Either --repo-dir or --blocks-dir must be specified")
+                }
+            };
+            let summary = archive::rebuild(&source, &data_dir, &db_file, resume)?;
+            println!("{}", serde_json::to_string_pretty(&summary)?);
+            if !summary.failed.is_empty() {
+                anyhow::bail!("{} blocks failed to be archived", summary.failed.len());
+            }
+            Ok(())
+        }
+        Commands::Slash(slash_cmd) => {
+            let wallet = AccountAddress::from_str(&slash_cmd.wallet)
+                .map_err(|e| anyhow::format_err!("Invalid wallet address: {e}"))?;
+            let bls_key = PubKey::from_str(&slash_cmd.bls_key)
+                .map_err(|e| anyhow::format_err!("Invalid BLS key: {e}"))?;
+            let message = slash::build(&wallet, slash_cmd.slash_type.parse()?, &bls_key)?;
+            println!("{}", serde_json::to_string_pretty(&message)?);
+            Ok(())
+        }
+        Commands::Zerostate(Zerostate { action: ZerostateAction::Info { path } }) => {
+            let summary = zerostate::info(&path)?;
+            println!("{}", serde_json::to_string_pretty(&summary)?);
+            Ok(())
+        }
+        Commands::Repo(Repo { action: RepoAction::Info { repo_dir } }) => {
+            let threads = repo::info(&repo_dir)?;
+            println!("{}", serde_json::to_string_pretty(&threads)?);
+            Ok(())
+        }
+        Commands::Repo(Repo { action: RepoAction::Import { input, share_dir } }) => {
+            let imported = repo::import(&input, &share_dir)?;
+            println!("{}", serde_json::to_string_pretty(&imported)?);
+            Ok(())
+        }
+        Commands::Bench(Bench {
+            action:
+                BenchAction::Execute { config_path, state, messages, parallelization, repo_dir, runs },
+        }) => {
+            let report =
+                bench::execute(&config_path, &repo_dir, &state, &messages, parallelization, runs)?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            Ok(())
+        }
+    }
+}
+
+fn save_keys_map_to_file(
+    path: PathBuf,
+    keys_map: HashMap<PubKey, (Secret, RndSeed)>,
+) -> anyhow::Result<()> {
+    let mut keys_vec = vec![];
+    for (pubkey, (secret, rnd_seed)) in keys_map {
+        let mut json_map = serde_json::Map::new();
+        json_map.insert("public".to_string(), json!(hex::encode(pubkey.as_ref().to_bytes())));
+        json_map.insert("secret".to_string(), json!(hex::encode(secret.take_as_seed())));
+        json_map.insert("rnd".to_string(), json!(hex::encode(rnd_seed.as_ref())));
+        keys_vec.push(json_map);
+    }
+    Ok(std::fs::write(path, serde_json::to_string_pretty(&keys_vec)?)?)
+}
@@ -0,0 +1,54 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::path::Path;
+
+use node::repository::repository_impl::RepositoryImpl;
+use node::repository::snapshot_import::import_snapshot_file;
+use node::repository::snapshot_import::SnapshotFormat;
+use node::types::BlockSeqNo;
+use serde::Serialize;
+
+#[derive(Serialize, Debug)]
+pub struct ThreadSummary {
+    pub thread_id: String,
+    pub last_finalized_block_id: String,
+    pub last_finalized_block_seq_no: BlockSeqNo,
+}
+
+/// Reads the repository metadata, the node should be stopped to get
+/// consistent results.
+pub fn info(repo_dir: &Path) -> anyhow::Result<Vec<ThreadSummary>> {
+    let mut threads = vec![];
+    for (thread_id, metadata) in RepositoryImpl::load_metadata(repo_dir)? {
+        let metadata = metadata.lock();
+        threads.push(ThreadSummary {
+            thread_id: format!("{thread_id:x}"),
+            last_finalized_block_id: metadata.last_finalized_block_id().to_string(),
+            last_finalized_block_seq_no: *metadata.last_finalized_block_seq_no(),
+        });
+    }
+    threads.sort_by(|a, b| a.thread_id.cmp(&b.thread_id));
+    Ok(threads)
+}
+
+#[derive(Serialize, Debug)]
+pub struct ImportSummary {
+    pub thread_id: String,
+    pub block_id: String,
+    pub block_seq_no: BlockSeqNo,
+    /// Format the snapshot was converted from
+    pub format: SnapshotFormat,
+}
+
+/// Converts the snapshot into the current layout, so that nodes syncing from
+/// the share directory can load it.
+pub fn import(input: &Path, share_dir: &Path) -> anyhow::Result<ImportSummary> {
+    let imported = import_snapshot_file(input, share_dir)?;
+    Ok(ImportSummary {
+        thread_id: format!("{:x}", imported.thread_id),
+        block_id: imported.block_id.to_string(),
+        block_seq_no: imported.block_seq_no,
+        format: imported.format,
+    })
+}
@@ -0,0 +1,42 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use node::block_keeper_system::slash::build_slash_message;
+use node::block_keeper_system::slash::SlashType;
+use node::bls::gosh_bls::PubKey;
+use node::types::AccountAddress;
+use serde::Serialize;
+use tvm_block::GetRepresentationHash;
+use tvm_block::Serializable;
+
+#[derive(Serialize, Debug)]
+pub struct SlashMessage {
+    pub wallet: String,
+    pub slash_type: u8,
+    pub hash: String,
+    /// Message BOC in hex
+    pub boc: String,
+}
+
+/// Builds the message that slashes the BLS key owner, the same one producers
+/// add to blocks for confirmed nacks.
+pub fn build(
+    wallet: &AccountAddress,
+    slash_type: SlashType,
+    bls_pubkey: &PubKey,
+) -> anyhow::Result<SlashMessage> {
+    let message = build_slash_message(wallet, slash_type, bls_pubkey)?;
+    let cell =
+        message.serialize().map_err(|e| anyhow::format_err!("Failed to serialize message: {e}"))?;
+    let boc = tvm_types::write_boc(&cell)
+        .map_err(|e| anyhow::format_err!("Failed to write message BOC: {e}"))?;
+    Ok(SlashMessage {
+        wallet: wallet.to_hex_string(),
+        slash_type: slash_type.code(),
+        hash: message
+            .hash()
+            .map_err(|e| anyhow::format_err!("Failed to calculate message hash: {e}"))?
+            .to_hex_string(),
+        boc: hex::encode(boc),
+    })
+}
@@ -0,0 +1,49 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::path::Path;
+
+use node::repository::optimistic_state::OptimisticState;
+use node::zerostate::ZeroState;
+use serde::Serialize;
+
+#[derive(Serialize, Debug)]
+pub struct ThreadSummary {
+    pub thread_id: String,
+    pub accounts: usize,
+    pub block_keepers: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ZerostateSummary {
+    pub threads: Vec<ThreadSummary>,
+}
+
+pub fn info(path: &Path) -> anyhow::Result<ZerostateSummary> {
+    let zerostate = ZeroState::load_from_file(path)?;
+    let mut threads = vec![];
+    for (thread_id, state) in zerostate.states() {
+        let mut accounts = 0;
+        state
+            .get_shard_state()
+            .read_accounts()
+            .map_err(|e| anyhow::format_err!("Failed to read accounts: {e}"))?
+            .iterate_accounts(|_, _, _| {
+                accounts += 1;
+                Ok(true)
+            })
+            .map_err(|e| anyhow::format_err!("Failed to iterate accounts: {e}"))?;
+        let block_keepers = zerostate
+            .unwrapped_block_keeper_sets()
+            .get(thread_id)
+            .map(|bk_set| bk_set.iter_node_ids().map(|node_id| node_id.to_string()).collect())
+            .unwrap_or_default();
+        threads.push(ThreadSummary {
+            thread_id: format!("{thread_id:x}"),
+            accounts,
+            block_keepers,
+        });
+    }
+    threads.sort_by(|a, b| a.thread_id.cmp(&b.thread_id));
+    Ok(ZerostateSummary { threads })
+}
@@ -129,10 +129,9 @@ pub fn postprocess(
     let mut cached_accounts = initial_optimistic_state.cached_accounts;
     if let Some(unload_after) = accounts_repo.get_unload_after() {
         changed_accounts.extend(block_accounts.into_iter().map(|acc| (acc, block_seq_no)));
+        let store_after = accounts_repo.get_store_after(&current_thread_id);
         cached_accounts.retain(|account_id, (seq_no, _)| {
-            if *seq_no + accounts_repo.get_store_after() >= block_seq_no
-                && !changed_accounts.contains_key(account_id)
-            {
+            if *seq_no + store_after >= block_seq_no && !changed_accounts.contains_key(account_id) {
                 true
             } else {
                 tracing::trace!(
@@ -196,6 +195,7 @@ pub fn postprocess(
         .dapp_id_table(new_dapp_id_table)
         .thread_refs_state(new_thread_refs)
         .cropped(initial_optimistic_state.cropped)
+        .deferred_messages(initial_optimistic_state.deferred_messages)
         .changed_accounts(changed_accounts)
         .cached_accounts(cached_accounts)
         .accounts_number(updated_accounts_number)
@@ -217,6 +217,7 @@ pub fn postprocess(
         .dapp_id_table(new_dapp_id_table)
         .thread_refs_state(new_thread_refs)
         .cropped(initial_optimistic_state.cropped)
+        .deferred_messages(initial_optimistic_state.deferred_messages)
         .changed_accounts(changed_accounts)
         .cached_accounts(cached_accounts)
         .build();
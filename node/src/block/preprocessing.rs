@@ -24,6 +24,7 @@ use crate::types::AccountRouting;
 use crate::types::BlockIdentifier;
 use crate::types::ThreadIdentifier;
 use crate::types::ThreadsTable;
+use crate::utilities::clock::CLOCK;
 
 type State = crate::repository::optimistic_state::OptimisticStateImpl;
 
@@ -239,8 +240,7 @@ pub fn convert_epoch_messages(
     high_priority_map: &mut HashMap<AccountAddress, Vec<(MessageIdentifier, Arc<WrappedMessage>)>>,
     epoch_message: Vec<BlockKeeperData>,
 ) -> anyhow::Result<()> {
-    let now = std::time::SystemTime::now();
-    let time = now.duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as u32;
+    let time = CLOCK.now_secs();
     for data in epoch_message.into_iter() {
         let msg = create_epoch_touch_message(&data, time).map_err(|e| {
             anyhow::Error::msg(format!("Failed to create epoch touch message: {e}"))
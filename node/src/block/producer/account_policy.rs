@@ -0,0 +1,250 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+use sha2::Digest;
+use sha2::Sha256;
+use tvm_block::GetRepresentationHash;
+use tvm_block::HashmapAugType;
+use tvm_block::Message;
+
+use crate::config::AccountPolicyConfig;
+use crate::config::AccountPolicyMode;
+use crate::external_messages::Stamp;
+use crate::types::extensions::AppliedAccountPolicy;
+use crate::types::AccountAddress;
+
+/// Decides which external messages the producer includes in blocks. Blocks
+/// produced with a policy record its name and digest, so the policy must give
+/// the same decisions for the same digest.
+pub trait MessagePolicy: Send + Sync {
+    fn name(&self) -> String;
+
+    fn digest(&self) -> [u8; 32];
+
+    fn admits(&self, message: &Message) -> bool;
+}
+
+/// Policy of the node config: allow or deny list of accounts.
+pub struct AccountListPolicy {
+    mode: AccountPolicyMode,
+    accounts: HashSet<AccountAddress>,
+    digest: [u8; 32],
+}
+
+impl AccountListPolicy {
+    /// Returns `None` for the disabled policy.
+    pub fn from_config(config: &AccountPolicyConfig) -> anyhow::Result<Option<Self>> {
+        if config.mode == AccountPolicyMode::Disabled {
+            return Ok(None);
+        }
+        let accounts = config
+            .accounts
+            .iter()
+            .map(|address| {
+                let hex = address.rsplit_once(':').map_or(address.as_str(), |(_, hex)| hex);
+                AccountAddress::from_str(hex).map_err(|e| {
+                    anyhow::format_err!("Invalid account {address} in the account policy: {e}")
+                })
+            })
+            .collect::<anyhow::Result<HashSet<_>>>()?;
+        let mut sorted_accounts = accounts.iter().collect::<Vec<_>>();
+        sorted_accounts.sort();
+        let mut hasher = Sha256::new();
+        hasher.update(format!("{:?}", config.mode));
+        for account in sorted_accounts {
+            hasher.update(account.0.as_array());
+        }
+        Ok(Some(Self { mode: config.mode, accounts, digest: hasher.finalize().into() }))
+    }
+}
+
+impl MessagePolicy for AccountListPolicy {
+    fn name(&self) -> String {
+        match self.mode {
+            AccountPolicyMode::Disabled => "disabled",
+            AccountPolicyMode::Allowlist => "allowlist",
+            AccountPolicyMode::Denylist => "denylist",
+        }
+        .to_string()
+    }
+
+    fn digest(&self) -> [u8; 32] {
+        self.digest
+    }
+
+    fn admits(&self, message: &Message) -> bool {
+        // External messages have only the destination account, the source is
+        // checked for messages that have one
+        let mut accounts = [message.get_int_src_account_id(), message.int_dst_account_id()]
+            .into_iter()
+            .flatten()
+            .map(AccountAddress::from);
+        match self.mode {
+            AccountPolicyMode::Disabled => true,
+            AccountPolicyMode::Allowlist => {
+                accounts.all(|account| self.accounts.contains(&account))
+            }
+            AccountPolicyMode::Denylist => {
+                !accounts.any(|account| self.accounts.contains(&account))
+            }
+        }
+    }
+}
+
+/// Removes the messages the policy doesn't admit from the queue. Returns the
+/// record for the block common section and the excluded messages.
+pub fn apply_message_policy(
+    policy: &dyn MessagePolicy,
+    queue: &mut HashMap<AccountAddress, VecDeque<(Stamp, Message)>>,
+) -> (AppliedAccountPolicy, Vec<(Stamp, Message)>) {
+    let mut excluded = vec![];
+    for messages in queue.values_mut() {
+        messages.retain(|(stamp, message)| {
+            let admitted = policy.admits(message);
+            if !admitted {
+                excluded.push((stamp.clone(), message.clone()));
+            }
+            admitted
+        });
+    }
+    queue.retain(|_, messages| !messages.is_empty());
+    let excluded_messages = excluded
+        .iter()
+        .filter_map(|(_, message)| message.hash().ok())
+        .map(|hash| *hash.as_array())
+        .collect::<Vec<_>>();
+    if !excluded_messages.is_empty() {
+        tracing::info!(
+            target: "builder",
+            "Account policy {} excluded {} external messages",
+            policy.name(),
+            excluded_messages.len()
+        );
+    }
+    let record =
+        AppliedAccountPolicy { policy: policy.name(), digest: policy.digest(), excluded_messages };
+    (record, excluded)
+}
+
+/// Checks the account policy the block claims against the local policy.
+/// Policies are local, so blocks without a claim and claims the node can't
+/// check without a policy of its own are accepted. Returns false if the claim
+/// differs from the local policy or the block includes external messages the
+/// policy doesn't admit.
+pub fn verify_applied_policy(
+    policy: Option<&dyn MessagePolicy>,
+    applied: Option<&AppliedAccountPolicy>,
+    block: &tvm_block::Block,
+) -> anyhow::Result<bool> {
+    let (Some(policy), Some(applied)) = (policy, applied) else {
+        return Ok(true);
+    };
+    if applied.policy != policy.name() || applied.digest != policy.digest() {
+        tracing::trace!(
+            "Block account policy {} differs from the local policy {}",
+            applied.policy,
+            policy.name()
+        );
+        return Ok(false);
+    }
+    let excluded = applied.excluded_messages.iter().collect::<HashSet<_>>();
+    let mut admitted = true;
+    block
+        .read_extra()
+        .map_err(|e| anyhow::format_err!("Failed to read block extra: {e}"))?
+        .read_in_msg_descr()
+        .map_err(|e| anyhow::format_err!("Failed to read in msg descr: {e}"))?
+        .iterate_objects(|in_msg| {
+            let message = in_msg.read_message()?;
+            if message.is_inbound_external() {
+                admitted =
+                    policy.admits(&message) && !excluded.contains(message.hash()?.as_array());
+            }
+            Ok(admitted)
+        })
+        .map_err(|e| anyhow::format_err!("Failed to iterate in msgs: {e}"))?;
+    if !admitted {
+        tracing::trace!("Block includes external messages the account policy doesn't admit");
+    }
+    Ok(admitted)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use tvm_block::ExternalInboundMessageHeader;
+    use tvm_block::MsgAddressInt;
+    use tvm_types::AccountId;
+    use tvm_types::UInt256;
+
+    use super::*;
+
+    fn message(dst: u8) -> Message {
+        let dst = MsgAddressInt::with_standart(None, 0, AccountId::from(UInt256::from([dst; 32])))
+            .unwrap();
+        Message::with_ext_in_header(ExternalInboundMessageHeader { dst, ..Default::default() })
+    }
+
+    fn queue(dsts: &[u8]) -> HashMap<AccountAddress, VecDeque<(Stamp, Message)>> {
+        dsts.iter()
+            .enumerate()
+            .map(|(index, dst)| {
+                let stamp = Stamp { index: index as u64, timestamp: Utc::now() };
+                (
+                    AccountAddress(UInt256::from([*dst; 32])),
+                    VecDeque::from([(stamp, message(*dst))]),
+                )
+            })
+            .collect()
+    }
+
+    fn config(mode: AccountPolicyMode, accounts: &[u8]) -> AccountPolicyConfig {
+        let accounts =
+            accounts.iter().map(|id| format!("0:{}", UInt256::from([*id; 32]).to_hex_string()));
+        AccountPolicyConfig { mode, accounts: accounts.collect() }
+    }
+
+    #[test]
+    fn test_account_list_policy() {
+        assert!(AccountListPolicy::from_config(&AccountPolicyConfig::default()).unwrap().is_none());
+        assert!(AccountListPolicy::from_config(&AccountPolicyConfig {
+            mode: AccountPolicyMode::Denylist,
+            accounts: vec!["0:xyz".to_string()],
+        })
+        .is_err());
+
+        let denylist =
+            AccountListPolicy::from_config(&config(AccountPolicyMode::Denylist, &[2])).unwrap();
+        let denylist = denylist.unwrap();
+        let mut messages = queue(&[1, 2, 3]);
+        let (record, excluded) = apply_message_policy(&denylist, &mut messages);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(excluded.len(), 1);
+        assert_eq!(record.policy, "denylist");
+        assert_eq!(record.excluded_messages, vec![*message(2).hash().unwrap().as_array()]);
+
+        let allowlist =
+            AccountListPolicy::from_config(&config(AccountPolicyMode::Allowlist, &[2])).unwrap();
+        let allowlist = allowlist.unwrap();
+        let mut messages = queue(&[1, 2, 3]);
+        let (record, excluded) = apply_message_policy(&allowlist, &mut messages);
+        assert_eq!(
+            messages.keys().collect::<Vec<_>>(),
+            vec![&AccountAddress(UInt256::from([2; 32]))]
+        );
+        assert_eq!(excluded.len(), 2);
+        assert_ne!(record.digest, denylist.digest());
+
+        let block = tvm_block::Block::default();
+        let policy: &dyn MessagePolicy = &allowlist;
+        assert!(verify_applied_policy(Some(policy), Some(&record), &block).unwrap());
+        let policy: &dyn MessagePolicy = &denylist;
+        assert!(!verify_applied_policy(Some(policy), Some(&record), &block).unwrap());
+        assert!(verify_applied_policy(None, Some(&record), &block).unwrap());
+    }
+}
@@ -14,8 +14,10 @@ use account_inbox::iter::iterator::MessagesRangeIterator;
 use anyhow::ensure;
 use http_server::ExtMsgFeedback;
 use http_server::ExtMsgFeedbackList;
+use http_server::ExtMsgQuarantine;
 use http_server::FeedbackError;
 use http_server::FeedbackErrorCode;
+use http_server::TxTraceRegistry;
 use indexset::BTreeMap;
 use telemetry_utils::mpsc::instrumented_channel;
 use telemetry_utils::mpsc::InstrumentedReceiver;
@@ -31,6 +33,7 @@ use tvm_block::BlockExtra;
 use tvm_block::BlockInfo;
 use tvm_block::CommonMsgInfo;
 use tvm_block::ComputeSkipReason;
+use tvm_block::CurrencyCollection;
 use tvm_block::Deserializable;
 use tvm_block::EnqueuedMsg;
 use tvm_block::ExtBlkRef;
@@ -48,6 +51,7 @@ use tvm_block::ShardAccount;
 use tvm_block::ShardIdent;
 use tvm_block::ShardStateUnsplit;
 use tvm_block::StateInit;
+use tvm_block::TrBouncePhase;
 use tvm_block::TrComputePhase;
 use tvm_block::TrComputePhaseVm;
 use tvm_block::Transaction;
@@ -60,6 +64,7 @@ use tvm_executor::ExecutorError;
 use tvm_executor::OrdinaryTransactionExecutor;
 use tvm_executor::TransactionExecutor;
 use tvm_types::Cell;
+use tvm_types::ExceptionCode;
 use tvm_types::UInt256;
 use tvm_types::UsageTree;
 use tvm_vm::executor::Engine;
@@ -71,13 +76,20 @@ use super::PreparedBlock;
 use super::ThreadResult;
 use crate::block::postprocessing::postprocess;
 use crate::block::producer::builder::trace::simple_trace_callback;
+use crate::block::producer::builder::EngineTraceInfoData;
+use crate::block::producer::crash_marker::panic_message;
 use crate::block::producer::errors::verify_error;
+use crate::block::producer::errors::ACCOUNT_STATE_LIMIT_EXIT_CODE;
 use crate::block::producer::errors::BP_DID_NOT_PROCESS_ALL_MESSAGES_FROM_PREVIOUS_BLOCK;
+use crate::block::producer::errors::DEFERRED_QUEUE_FULL_EXIT_CODE;
+use crate::block::producer::errors::DEFERRED_VALUE_NOT_COVERED_EXIT_CODE;
 use crate::block::producer::execution_time::ExecutionTimeLimits;
 use crate::block::producer::wasm::WasmNodeCache;
+use crate::block::producer::watchdog;
 use crate::block_keeper_system::epoch::decode_epoch_data;
 use crate::block_keeper_system::epoch::decode_preepoch_data;
 use crate::block_keeper_system::BlockKeeperSetChange;
+use crate::config::SheddableSubsystem;
 use crate::creditconfig::abi::DAPP_CONFIG_TVC;
 use crate::creditconfig::abi::DAPP_ROOT_ADDR;
 use crate::creditconfig::dappconfig::calculate_dapp_config_address;
@@ -95,6 +107,7 @@ use crate::repository::optimistic_state::OptimisticStateImpl;
 use crate::repository::CrossThreadRefData;
 use crate::storage::MessageDurableStorage;
 use crate::types::account::WrappedAccount;
+use crate::types::deferred_messages::scheduled_requests;
 use crate::types::thread_message_queue::account_messages_iterator::AccountMessagesIterator;
 use crate::types::thread_message_queue::ThreadMessageQueueState;
 use crate::types::AccountAddress;
@@ -104,6 +117,10 @@ use crate::types::BlockIdentifier;
 use crate::types::BlockSeqNo;
 use crate::types::DAppIdentifier;
 use crate::types::ThreadIdentifier;
+use crate::utilities::load_controller::LOAD_CONTROLLER;
+
+// Accounts with state above this share of the limit are reported in metrics
+const ACCOUNT_STATE_NEAR_LIMIT_PERCENT: u64 = 80;
 
 impl BlockBuilder {
     /// Initialize BlockBuilder
@@ -113,6 +130,7 @@ impl BlockBuilder {
         initial_optimistic_state: OptimisticStateImpl,
         gen_utime_ms: u64,
         block_gas_limit: u64,
+        max_account_state_cells: u64,
         rand_seed: Option<UInt256>,
         control_rx_stop: Option<InstrumentedReceiver<()>>,
         accounts_repository: AccountsRepository,
@@ -125,6 +143,7 @@ impl BlockBuilder {
         >,
         metrics: Option<BlockProductionMetrics>,
         wasm_cache: WasmNodeCache,
+        tx_traces: Option<TxTraceRegistry>,
     ) -> anyhow::Result<Self> {
         let usage_tree =
             UsageTree::with_params(initial_optimistic_state.get_shard_state_as_cell(), true);
@@ -172,6 +191,11 @@ impl BlockBuilder {
             end_lt: start_lt + 1,
             copyleft_rewards: Default::default(),
             block_gas_limit,
+            system_lane_budget_fraction: 0.0,
+            general_deadline: None,
+            max_account_state_cells,
+            timer_contract: None,
+            deferred_messages_scheduled: 0,
             account_blocks: Default::default(),
             total_gas_used: 0,
             control_rx_stop,
@@ -198,6 +222,8 @@ impl BlockBuilder {
             metrics,
             is_stop_requested: false,
             wasm_cache,
+            tx_traces,
+            ext_msg_quarantine: None,
         };
 
         #[cfg(feature = "monitor-accounts-number")]
@@ -215,6 +241,11 @@ impl BlockBuilder {
             end_lt: start_lt + 1,
             copyleft_rewards: Default::default(),
             block_gas_limit,
+            system_lane_budget_fraction: 0.0,
+            general_deadline: None,
+            max_account_state_cells,
+            timer_contract: None,
+            deferred_messages_scheduled: 0,
             account_blocks: Default::default(),
             total_gas_used: 0,
             control_rx_stop,
@@ -241,6 +272,8 @@ impl BlockBuilder {
             metrics,
             is_stop_requested: false,
             wasm_cache,
+            tx_traces,
+            ext_msg_quarantine: None,
             accounts_number_diff: 0,
         };
         Ok(builder)
@@ -259,6 +292,7 @@ impl BlockBuilder {
         )
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn try_prepare_transaction(
         executor: &OrdinaryTransactionExecutor,
         acc_root: &mut Cell,
@@ -266,6 +300,9 @@ impl BlockBuilder {
         last_trans_hash: UInt256,
         last_trans_lt: u64,
         execute_params: ExecuteParams,
+        max_account_state_cells: u64,
+        timer_contract: Option<AccountAddress>,
+        deferred_messages_capacity: usize,
         //    trace: Arc<lockfree::queue::Queue<EngineTraceInfoData>>,
     ) -> anyhow::Result<(Transaction, u64, /* Option<Vec<EngineTraceInfoData>>, */ i128, bool)>
     {
@@ -273,6 +310,8 @@ impl BlockBuilder {
         let last_lt = lt.load(Ordering::Relaxed);
         let block_unixtime = execute_params.block_unixtime;
         let vm_execution_is_block_related = execute_params.vm_execution_is_block_related.clone();
+        let block_version = execute_params.block_version;
+        let initial_acc_root = acc_root.clone();
 
         #[cfg(feature = "timing")]
         let start = std::time::Instant::now();
@@ -301,6 +340,50 @@ impl BlockBuilder {
                 // } else {
                 // None
                 // };
+                let mut abort_exit_code = None;
+                if let Some(timer_contract) = &timer_contract {
+                    if AccountAddress::from(transaction.account_id()) == *timer_contract {
+                        let (count, value) = scheduled_requests(&transaction, timer_contract)?;
+                        if count > deferred_messages_capacity {
+                            tracing::warn!(target: "builder",
+                                "Deferred messages queue can't take {count} scheduled messages, abort tx"
+                            );
+                            abort_exit_code = Some(DEFERRED_QUEUE_FULL_EXIT_CODE);
+                        } else if !debit_scheduled_value(acc_root, &mut transaction, &value)? {
+                            tracing::warn!(target: "builder",
+                                "Timer contract balance doesn't cover scheduled messages value {value:?}, abort tx"
+                            );
+                            abort_exit_code = Some(DEFERRED_VALUE_NOT_COVERED_EXIT_CODE);
+                        }
+                    }
+                }
+                if abort_exit_code.is_none() && max_account_state_cells != 0 {
+                    let state_cells = account_state_cells(acc_root)?;
+                    if state_cells > max_account_state_cells {
+                        // Transaction result is dropped, so an ever-growing account can't
+                        // make the shard state too large to save and share
+                        tracing::warn!(target: "builder",
+                            "Account state size limit exceeded: {state_cells} > {max_account_state_cells} cells, abort tx"
+                        );
+                        abort_exit_code = Some(ACCOUNT_STATE_LIMIT_EXIT_CODE);
+                    }
+                }
+                if let Some(exit_code) = abort_exit_code {
+                    *acc_root = initial_acc_root;
+                    let (transaction, lt) = Self::charged_aborted_transaction(
+                        executor,
+                        acc_root,
+                        msg,
+                        &transaction,
+                        exit_code,
+                        last_lt,
+                        block_unixtime,
+                        block_version,
+                        last_trans_hash,
+                        last_trans_lt,
+                    )?;
+                    return Ok((transaction, lt, 0, msg.is_inbound_external()));
+                }
                 transaction.set_prev_trans_hash(last_trans_hash);
                 transaction.set_prev_trans_lt(last_trans_lt);
                 Ok((
@@ -315,71 +398,217 @@ impl BlockBuilder {
                 if let Some(ExecutorError::TerminationDeadlineReached) = err.downcast_ref() {
                     anyhow::bail!(ExecutorError::TerminationDeadlineReached);
                 }
-                let old_hash = acc_root.repr_hash();
-                let mut account = Account::construct_from_cell(acc_root.clone())
-                    .map_err(|e| anyhow::format_err!("Failed to construct account: {e}"))?;
-                let lt = std::cmp::max(
-                    account.last_tr_time().unwrap_or(0),
-                    std::cmp::max(last_lt, msg.lt().unwrap_or(0) + 1),
-                );
-                account.set_last_tr_time(lt);
-                *acc_root = account
-                    .serialize()
-                    .map_err(|e| anyhow::format_err!("Failed to serialize account: {e}"))?;
-                let mut transaction = Transaction::with_account_and_message(&account, msg, lt)
-                    .map_err(|e| anyhow::format_err!("Failed to create transaction: {e}"))?;
-                transaction.set_now(block_unixtime);
-                let mut description =
-                    TransactionDescrOrdinary { aborted: true, ..Default::default() };
-                match err.downcast_ref::<ExecutorError>() {
-                    Some(ExecutorError::NoAcceptError(error, arg)) => {
-                        let mut vm_phase = TrComputePhaseVm {
-                            success: false,
-                            exit_code: *error,
-                            ..Default::default()
-                        };
-                        if let Some(item) = arg {
-                            vm_phase.exit_arg = match item
-                                .as_integer()
-                                .and_then(|value| value.into(i32::MIN..=i32::MAX))
-                            {
-                                Err(_) | Ok(0) => None,
-                                Ok(exit_arg) => Some(exit_arg),
+                let (transaction, lt) = Self::aborted_transaction(
+                    acc_root,
+                    msg,
+                    last_lt,
+                    block_unixtime,
+                    last_trans_hash,
+                    last_trans_lt,
+                    |account| match err.downcast_ref::<ExecutorError>() {
+                        Some(ExecutorError::NoAcceptError(error, arg)) => {
+                            let mut vm_phase = TrComputePhaseVm {
+                                success: false,
+                                exit_code: *error,
+                                ..Default::default()
                             };
+                            if let Some(item) = arg {
+                                vm_phase.exit_arg = match item
+                                    .as_integer()
+                                    .and_then(|value| value.into(i32::MIN..=i32::MAX))
+                                {
+                                    Err(_) | Ok(0) => None,
+                                    Ok(exit_arg) => Some(exit_arg),
+                                };
+                            }
+                            Ok(TrComputePhase::Vm(vm_phase))
                         }
-                        description.compute_ph = TrComputePhase::Vm(vm_phase);
-                    }
-                    Some(ExecutorError::NoFundsToImportMsg) => {
-                        description.compute_ph = if account.is_none() {
+                        Some(ExecutorError::NoFundsToImportMsg) => Ok(if account.is_none() {
                             TrComputePhase::skipped(ComputeSkipReason::NoState)
                         } else {
                             TrComputePhase::skipped(ComputeSkipReason::NoGas)
-                        };
-                    }
-                    Some(ExecutorError::ExtMsgComputeSkipped(reason)) => {
-                        description.compute_ph = TrComputePhase::skipped(reason.clone());
-                    }
-                    _ => {
-                        return Err(err).map_err(|e| anyhow::format_err!("Execution error: {e}"))?
-                    }
-                }
-                transaction
-                    .write_description(&TransactionDescr::Ordinary(description))
-                    .map_err(|e| anyhow::format_err!("Failed to write tx description: {e}"))?;
-                let state_update = HashUpdate::with_hashes(old_hash, acc_root.repr_hash());
-                transaction
-                    .write_state_update(&state_update)
-                    .map_err(|e| anyhow::format_err!("Failed to write tx state update: {e}"))?;
+                        }),
+                        Some(ExecutorError::ExtMsgComputeSkipped(reason)) => {
+                            Ok(TrComputePhase::skipped(reason.clone()))
+                        }
+                        _ => Err(anyhow::format_err!("Execution error: {err}")),
+                    },
+                )?;
                 // let trace = Some(trace.pop_iter().collect::<Vec<EngineTraceInfoData>>())
                 //    .filter(|trace| !trace.is_empty());
-                transaction.set_prev_trans_hash(last_trans_hash);
-                transaction.set_prev_trans_lt(last_trans_lt);
 
                 Ok((transaction, lt, /* trace, */ 0, is_ext_message))
             }
         }
     }
 
+    // Creates an aborted transaction of the executed message whose result is
+    // dropped by the node limits. Storage and compute fees of the execution are
+    // charged, the value of the bounceable message is returned to the sender by
+    // the executor bounce phase.
+    #[allow(clippy::too_many_arguments)]
+    fn charged_aborted_transaction(
+        executor: &OrdinaryTransactionExecutor,
+        acc_root: &mut Cell,
+        msg: &Message,
+        executed: &Transaction,
+        exit_code: i32,
+        last_lt: u64,
+        block_unixtime: u32,
+        block_version: u32,
+        last_trans_hash: UInt256,
+        last_trans_lt: u64,
+    ) -> anyhow::Result<(Transaction, u64)> {
+        let TransactionDescr::Ordinary(executed) = executed
+            .read_description()
+            .map_err(|e| anyhow::format_err!("Failed to read tx description: {e}"))?
+        else {
+            anyhow::bail!("Executed transaction is not ordinary");
+        };
+        let mut vm_phase = match executed.compute_ph {
+            TrComputePhase::Vm(vm_phase) => vm_phase,
+            TrComputePhase::Skipped(_) => TrComputePhaseVm::default(),
+        };
+        vm_phase.success = false;
+        vm_phase.exit_code = exit_code;
+        let gas_fees = vm_phase.gas_fees.clone();
+        let storage_fees = executed
+            .storage_ph
+            .as_ref()
+            .map(|storage_ph| storage_ph.storage_fees_collected.clone())
+            .unwrap_or_default();
+
+        let old_hash = acc_root.repr_hash();
+        let mut account = Account::construct_from_cell(acc_root.clone())
+            .map_err(|e| anyhow::format_err!("Failed to construct account: {e}"))?;
+        let mut lt = std::cmp::max(
+            account.last_tr_time().unwrap_or(0),
+            std::cmp::max(last_lt, msg.lt().unwrap_or(0) + 1),
+        );
+        account.set_last_tr_time(lt);
+        let mut transaction = Transaction::with_account_and_message(&account, msg, lt)
+            .map_err(|e| anyhow::format_err!("Failed to create transaction: {e}"))?;
+        transaction.set_now(block_unixtime);
+
+        let mut balance = account.balance().cloned().unwrap_or_default();
+        let msg_balance = executed
+            .credit_ph
+            .as_ref()
+            .map(|credit_ph| credit_ph.credit.clone())
+            .unwrap_or_default();
+        balance.add(&msg_balance).map_err(|e| anyhow::format_err!("Failed to add credit: {e}"))?;
+        let mut total_fees = storage_fees.clone();
+        total_fees.add(&gas_fees).map_err(|e| anyhow::format_err!("Failed to sum fees: {e}"))?;
+        if !balance
+            .grams
+            .sub(&total_fees)
+            .map_err(|e| anyhow::format_err!("Failed to charge fees: {e}"))?
+        {
+            anyhow::bail!("Account balance doesn't cover the execution fees");
+        }
+
+        let mut bounce_ph = None;
+        if let Some(header) = msg.int_header().filter(|header| header.bounce) {
+            let (phase, bounce) = executor
+                .bounce_phase(
+                    msg_balance,
+                    &mut balance,
+                    &gas_fees,
+                    msg,
+                    &mut transaction,
+                    &header.dst,
+                    block_version,
+                )
+                .map_err(|e| anyhow::format_err!("Failed to bounce message: {e}"))?;
+            if let TrBouncePhase::Ok(bounce_ok) = &phase {
+                total_fees
+                    .add(&bounce_ok.msg_fees)
+                    .map_err(|e| anyhow::format_err!("Failed to sum fees: {e}"))?;
+            }
+            if let Some(mut bounce) = bounce {
+                lt += 1;
+                if let Some(bounce_header) = bounce.int_header_mut() {
+                    bounce_header.created_lt = lt;
+                    bounce_header.created_at = block_unixtime.into();
+                }
+                transaction
+                    .add_out_message(&bounce)
+                    .map_err(|e| anyhow::format_err!("Failed to add bounce to tx: {e}"))?;
+            }
+            bounce_ph = Some(phase);
+        }
+
+        account.set_balance(balance);
+        account
+            .update_storage_stat()
+            .map_err(|e| anyhow::format_err!("Failed to update account storage stat: {e}"))?;
+        *acc_root = account
+            .serialize()
+            .map_err(|e| anyhow::format_err!("Failed to serialize account: {e}"))?;
+        transaction.set_total_fees(CurrencyCollection::from_grams(total_fees));
+        let description = TransactionDescrOrdinary {
+            credit_first: executed.credit_first,
+            storage_ph: executed.storage_ph,
+            credit_ph: executed.credit_ph,
+            compute_ph: TrComputePhase::Vm(vm_phase),
+            bounce: bounce_ph,
+            aborted: true,
+            ..Default::default()
+        };
+        transaction
+            .write_description(&TransactionDescr::Ordinary(description))
+            .map_err(|e| anyhow::format_err!("Failed to write tx description: {e}"))?;
+        let state_update = HashUpdate::with_hashes(old_hash, acc_root.repr_hash());
+        transaction
+            .write_state_update(&state_update)
+            .map_err(|e| anyhow::format_err!("Failed to write tx state update: {e}"))?;
+        transaction.set_prev_trans_hash(last_trans_hash);
+        transaction.set_prev_trans_lt(last_trans_lt);
+        Ok((transaction, lt))
+    }
+
+    // Creates an aborted transaction that changes only the account last
+    // transaction time.
+    fn aborted_transaction(
+        acc_root: &mut Cell,
+        msg: &Message,
+        last_lt: u64,
+        block_unixtime: u32,
+        last_trans_hash: UInt256,
+        last_trans_lt: u64,
+        compute_phase: impl FnOnce(&Account) -> anyhow::Result<TrComputePhase>,
+    ) -> anyhow::Result<(Transaction, u64)> {
+        let old_hash = acc_root.repr_hash();
+        let mut account = Account::construct_from_cell(acc_root.clone())
+            .map_err(|e| anyhow::format_err!("Failed to construct account: {e}"))?;
+        let lt = std::cmp::max(
+            account.last_tr_time().unwrap_or(0),
+            std::cmp::max(last_lt, msg.lt().unwrap_or(0) + 1),
+        );
+        account.set_last_tr_time(lt);
+        *acc_root = account
+            .serialize()
+            .map_err(|e| anyhow::format_err!("Failed to serialize account: {e}"))?;
+        let mut transaction = Transaction::with_account_and_message(&account, msg, lt)
+            .map_err(|e| anyhow::format_err!("Failed to create transaction: {e}"))?;
+        transaction.set_now(block_unixtime);
+        let description = TransactionDescrOrdinary {
+            aborted: true,
+            compute_ph: compute_phase(&account)?,
+            ..Default::default()
+        };
+        transaction
+            .write_description(&TransactionDescr::Ordinary(description))
+            .map_err(|e| anyhow::format_err!("Failed to write tx description: {e}"))?;
+        let state_update = HashUpdate::with_hashes(old_hash, acc_root.repr_hash());
+        transaction
+            .write_state_update(&state_update)
+            .map_err(|e| anyhow::format_err!("Failed to write tx state update: {e}"))?;
+        transaction.set_prev_trans_hash(last_trans_hash);
+        transaction.set_prev_trans_lt(last_trans_lt);
+        Ok((transaction, lt))
+    }
+
     pub(super) fn after_transaction(
         &mut self,
         mut thread_result: ThreadResult,
@@ -394,14 +623,23 @@ impl BlockBuilder {
                 }
             }
         }
-        let is_tx_aborted = transaction
+        let tx_description = transaction
             .read_description()
-            .map_err(|e| anyhow::format_err!("Failed to read tx description: {e}"))?
-            .is_aborted();
+            .map_err(|e| anyhow::format_err!("Failed to read tx description: {e}"))?;
+        let is_tx_aborted = tx_description.is_aborted();
 
         if is_tx_aborted {
             // This metric counts ALL aborted transactions.
             self.metrics.as_ref().inspect(|m| m.report_tx_aborted(&self.thread_id));
+            if self.max_account_state_cells != 0 {
+                if let Some(TrComputePhase::Vm(vm)) = tx_description.compute_phase_ref() {
+                    if vm.exit_code == ACCOUNT_STATE_LIMIT_EXIT_CODE {
+                        self.metrics
+                            .as_ref()
+                            .inspect(|m| m.report_account_state_limit_exceeded(&self.thread_id));
+                    }
+                }
+            }
 
             if thread_result.in_msg_is_ext {
                 // This metric counts only external aborted transactions
@@ -417,6 +655,13 @@ impl BlockBuilder {
         let acc_id = thread_result.account_id;
         self.tx_cnt += 1;
 
+        // Requests of the timer contract take the queue capacity left for its
+        // next transactions in the block
+        if self.timer_contract.as_ref() == Some(&acc_id) {
+            let (count, _) = scheduled_requests(&transaction, &acc_id)?;
+            self.deferred_messages_scheduled += count;
+        }
+
         if let Some(gas_used) = transaction.gas_used() {
             self.total_gas_used += gas_used;
         }
@@ -441,8 +686,22 @@ impl BlockBuilder {
             transaction.read_description().map_err(|e| anyhow::format_err!("Failed to read tx description: {e}"))?.is_aborted()
         );
 
-        let acc = Account::construct_from_cell(acc_root.clone())
-            .map_err(|e| anyhow::format_err!("Failed to construct account: {e}"))?;
+        // Account state after the transaction is the state of the next block
+        let acc = self.accounts_repository.hot_account(&acc_id, &acc_root)?;
+
+        if self.max_account_state_cells != 0 {
+            let state_cells = acc.storage_info().map(|info| info.used().cells()).unwrap_or(0);
+            if state_cells * 100 >= self.max_account_state_cells * ACCOUNT_STATE_NEAR_LIMIT_PERCENT
+            {
+                tracing::trace!(target: "builder",
+                    "Account {} state is close to the size limit: {state_cells} cells",
+                    acc_id.to_hex_string()
+                );
+                self.metrics
+                    .as_ref()
+                    .inspect(|m| m.report_account_state_near_limit(&self.thread_id));
+            }
+        }
 
         if let Some(acc_code_hash) = acc.get_code_hash() {
             let code_hash_str = acc_code_hash.as_hex_string();
@@ -704,7 +963,7 @@ impl BlockBuilder {
         {
             #[cfg(feature = "timing")]
             let account_start = std::time::Instant::now();
-            if let Ok(account) = Account::construct_from_cell(acc_root.clone()) {
+            if let Ok(account) = self.accounts_repository.hot_account(&acc_id, &acc_root) {
                 if let Some(code_hash) = account.get_code_hash() {
                     let code_hash_str = code_hash.to_hex_string();
                     tracing::trace!(target: "builder", "Start acc code hash: {}", code_hash_str);
@@ -736,71 +995,90 @@ impl BlockBuilder {
         }
         let termination_deadline = time_limits.block_deadline();
         let execution_timeout = time_limits.get_message_timeout(&message_hash);
-        let execute_params = if cfg!(feature = "tvm_tracing") {
-            // let trace_copy = trace.clone();
-            let callback = move |engine: &Engine, info: &EngineTraceInfo| {
-                // trace_copy.push(EngineTraceInfoData::from(info));
+        // Trace requested by API client for this particular message, trace
+        // collection is stopped under production pressure
+        let requested_trace = self
+            .tx_traces
+            .as_ref()
+            .filter(|_| {
+                !LOAD_CONTROLLER.is_shed_for_thread(&self.thread_id, SheddableSubsystem::TxTraces)
+            })
+            .filter(|tx_traces| tx_traces.take_request(&message_hash.to_hex_string()))
+            .map(|_| Arc::new(Mutex::new(Vec::<EngineTraceInfoData>::new())));
+        let tx_traces = self.tx_traces.clone();
+        // Trace requested for the message is collected with the build-wide
+        // tracing enabled as well
+        let debug = cfg!(feature = "tvm_tracing") || requested_trace.is_some();
+        let trace = requested_trace.clone();
+        let callback = move |engine: &Engine, info: &EngineTraceInfo| {
+            if cfg!(feature = "tvm_tracing") {
                 simple_trace_callback(engine, info);
-            };
-            ExecuteParams {
-                block_unixtime,
-                block_lt,
-                last_tr_lt: Arc::clone(&lt),
-                seed_block: self.rand_seed.clone(),
-                debug: true,
-                signature_id: self.shard_state.global_id(),
-                trace_callback: Some(Arc::new(callback)),
-                vm_execution_is_block_related: vm_execution_is_block_related.clone(),
-                seq_no: self.block_info.seq_no(),
-                dapp_id: shard_acc.get_dapp_id().cloned(),
-                available_credit: available_balance,
-                termination_deadline,
-                execution_timeout,
-                wasm_binary_root_path: self.wasm_cache.wasm_binary_root_path.clone(),
-                wasm_hash_whitelist: self.wasm_cache.wasm_hash_whitelist.clone(),
-                wasm_engine: Some(self.wasm_cache.wasm_engine.clone()),
-                wasm_component_cache: self.wasm_cache.wasm_component_cache.clone(),
-                ..Default::default()
             }
-        } else {
-            ExecuteParams {
-                block_unixtime,
-                block_lt,
-                last_tr_lt: Arc::clone(&lt),
-                seed_block: self.rand_seed.clone(),
-                debug: false,
-                signature_id: self.shard_state.global_id(),
-                trace_callback: None,
-                vm_execution_is_block_related: vm_execution_is_block_related.clone(),
-                seq_no: self.block_info.seq_no(),
-                dapp_id: shard_acc.get_dapp_id().cloned(),
-                available_credit: available_balance,
-                termination_deadline,
-                execution_timeout,
-                wasm_binary_root_path: self.wasm_cache.wasm_binary_root_path.clone(),
-                wasm_hash_whitelist: self.wasm_cache.wasm_hash_whitelist.clone(),
-                wasm_engine: Some(self.wasm_cache.wasm_engine.clone()),
-                wasm_component_cache: self.wasm_cache.wasm_component_cache.clone(),
-                ..Default::default()
+            if let Some(trace) = &trace {
+                trace.lock().unwrap().push(EngineTraceInfoData::from(info));
             }
         };
+        let execute_params = ExecuteParams {
+            block_unixtime,
+            block_lt,
+            last_tr_lt: Arc::clone(&lt),
+            seed_block: self.rand_seed.clone(),
+            debug,
+            signature_id: self.shard_state.global_id(),
+            trace_callback: if debug { Some(Arc::new(callback)) } else { None },
+            vm_execution_is_block_related: vm_execution_is_block_related.clone(),
+            seq_no: self.block_info.seq_no(),
+            dapp_id: shard_acc.get_dapp_id().cloned(),
+            available_credit: available_balance,
+            termination_deadline,
+            execution_timeout,
+            wasm_binary_root_path: self.wasm_cache.wasm_binary_root_path.clone(),
+            wasm_hash_whitelist: self.wasm_cache.wasm_hash_whitelist.clone(),
+            wasm_engine: Some(self.wasm_cache.wasm_engine.clone()),
+            wasm_component_cache: self.wasm_cache.wasm_component_cache.clone(),
+            ..Default::default()
+        };
 
         let message_clone = message.clone();
+        let max_account_state_cells = self.max_account_state_cells;
+        let timer_contract = self.timer_contract.clone();
+        let deferred_messages_capacity = self
+            .initial_optimistic_state
+            .deferred_messages
+            .remaining_capacity()
+            .saturating_sub(self.deferred_messages_scheduled);
         let (result_tx, result_rx) = instrumented_channel::<anyhow::Result<ThreadResult>>(
             self.metrics.clone(),
             crate::helper::metrics::PRODUCE_THREAD_RESULT_CHANNEL,
         );
         rayon::spawn(move || {
             tracing::debug!(target: "builder", "Executing message {} {:?}", message_hash.to_hex_string(), message);
-            let res = Self::try_prepare_transaction(
-                &executor,
-                &mut acc_root,
-                &message,
-                shard_acc.last_trans_hash().clone(),
-                shard_acc.last_trans_lt(),
-                execute_params,
-                // trace,
-            );
+            let execute = || {
+                Self::try_prepare_transaction(
+                    &executor,
+                    &mut acc_root,
+                    &message,
+                    shard_acc.last_trans_hash().clone(),
+                    shard_acc.last_trans_lt(),
+                    execute_params,
+                    max_account_state_cells,
+                    timer_contract,
+                    deferred_messages_capacity,
+                    // trace,
+                )
+            };
+            let res = if message.is_inbound_external() {
+                catch_execution_panic(execute)
+            } else {
+                execute()
+            };
+            if let (Some(tx_traces), Some(trace)) = (tx_traces, requested_trace) {
+                let trace = std::mem::take(&mut *trace.lock().unwrap());
+                match serde_json::to_value(trace) {
+                    Ok(trace) => tx_traces.store(message_hash.to_hex_string(), trace),
+                    Err(e) => tracing::warn!(target: "builder", "Failed to serialize trace: {e}"),
+                }
+            }
             #[cfg(feature = "timing")]
             tracing::trace!(target: "builder", "Execute: total time {} ms, available_balance {}, result with minted {:?}", start.elapsed().as_millis(), available_balance, res);
             let _ = result_tx.send(res.map(
@@ -828,16 +1106,20 @@ impl BlockBuilder {
         })
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn execute_internal_messages(
         &mut self,
         blockchain_config: &BlockchainConfig,
         check_messages_map: &mut Option<HashMap<AccountAddress, BTreeMap<u64, UInt256>>>,
         _white_list_of_slashing_messages_hashes: HashSet<UInt256>, // TODO: check usage
         message_queue: ThreadMessageQueueState,
+        is_high_priority: bool,
         message_db: MessageDurableStorage,
         time_limits: &ExecutionTimeLimits,
     ) -> anyhow::Result<bool> {
         let (block_unixtime, block_lt) = self.at_and_lt();
+
+        self.inject_deferred_messages(block_unixtime)?;
         // let out_queue = self.out_queue_info.out_queue().clone();
         // let msg_count = out_queue
         //     .len()
@@ -1000,6 +1282,7 @@ impl BlockBuilder {
                 tracing::info!(target: "builder", "Internal messages execution start");
                 if first_thread_and_key.is_some() {
                     loop {
+                        watchdog::checkpoint();
                         let mut pause_to_avoid_busy_loop = true;
                         // If active pool is not full add threads
                         let mut message_queue_is_empty = false;
@@ -1108,7 +1391,13 @@ impl BlockBuilder {
                                 i += 1;
                             }
                         }
-                        if check_messages_map.is_none() && self.is_limits_reached() {
+                        // High priority messages are the system lane and may use the reserved budget
+                        let limits_reached = if is_high_priority {
+                            self.is_limits_reached()
+                        } else {
+                            self.is_general_limits_reached()
+                        };
+                        if check_messages_map.is_none() && limits_reached {
                             tracing::debug!(target: "builder", "Internal messages stop was set because block is full");
                             block_full = true;
                         }
@@ -1154,6 +1443,12 @@ impl BlockBuilder {
         tracing::info!(target: "builder", "ext_messages_queue.len={}, active_threads.len={}, check_messages_map.len={:?}", queue_len(&ext_messages_queue), active_threads.len(), check_messages_map.as_ref().map(|map| map.len()));
 
         let (block_unixtime, block_lt) = self.at_and_lt();
+        self.general_deadline = time_limits.block_deadline().map(|deadline| {
+            let now = std::time::Instant::now();
+            now + deadline
+                .saturating_duration_since(now)
+                .mul_f64(1.0 - self.system_lane_budget_fraction)
+        });
 
         // TODO: this flag is unused, fix it
         let verify_block_contains_missing_messages_from_prev_state = false;
@@ -1197,6 +1492,7 @@ impl BlockBuilder {
             if !block_full {
                 let mut active_destinations = HashMap::new();
                 loop {
+                    watchdog::checkpoint();
                     let mut there_are_no_new_messages_for_verify_block = true;
 
                     while active_threads.len() < self.parallelization_level {
@@ -1244,7 +1540,7 @@ impl BlockBuilder {
                     // Check active threads
                     self.process_completed_new_message_threads(&mut active_threads, &mut active_destinations)?;
 
-                    if check_messages_map.is_none() && self.is_limits_reached() {
+                    if check_messages_map.is_none() && self.is_general_limits_reached() {
                         tracing::info!(target: "builder", "New messages stop because block is full");
                         break;
                     }
@@ -1633,6 +1929,11 @@ impl BlockBuilder {
         #[cfg(feature = "monitor-accounts-number")]
         let updated_accounts_number = ((self.initial_optimistic_state.accounts_number as i64)
             + self.accounts_number_diff) as u64;
+        if let Some(timer_contract) = &self.timer_contract {
+            self.initial_optimistic_state
+                .deferred_messages
+                .schedule_timer_messages(&self.out_msg_descr, timer_contract)?;
+        }
         let (new_state, cross_thread_ref_data) = postprocess(
             self.initial_optimistic_state,
             self.consumed_internal_messages.clone(),
@@ -1659,7 +1960,7 @@ impl BlockBuilder {
     }
 
     // TODO: remove Option from tr_cell arg
-    fn add_new_internal_message_to_the_current_thread(
+    pub(super) fn add_new_internal_message_to_the_current_thread(
         &mut self,
         message: Message,
         tr_cell: Option<Cell>,
@@ -1711,6 +2012,7 @@ impl BlockBuilder {
             check_messages_map,
             white_list_of_slashing_messages_hashes.clone(),
             self.initial_optimistic_state.high_priority_messages.clone(),
+            true,
             message_db.clone(),
             time_limits,
         )?;
@@ -1721,6 +2023,7 @@ impl BlockBuilder {
                 check_messages_map,
                 white_list_of_slashing_messages_hashes,
                 self.initial_optimistic_state.messages.clone(),
+                false,
                 message_db,
                 time_limits,
             )?;
@@ -1775,6 +2078,7 @@ impl BlockBuilder {
         // let transaction_traces = std::mem::take(&mut self.transaction_traces);
         let tx_cnt = self.tx_cnt;
         let block_keeper_set_changes = self.block_keeper_set_changes.clone();
+        let dapp_minted_map = self.dapp_minted_map.clone();
 
         #[cfg(feature = "monitor-accounts-number")]
         let accounts_number_diff = self.accounts_number_diff;
@@ -1796,6 +2100,7 @@ impl BlockBuilder {
             block_keeper_set_changes,
             cross_thread_ref_data,
             changed_dapp_ids,
+            dapp_minted_map,
             #[cfg(feature = "monitor-accounts-number")]
             accounts_number_diff,
         };
@@ -1884,7 +2189,9 @@ impl BlockBuilder {
         let span_guard = span.enter();
 
         for (acc_id, _queue) in ext_messages_queue.clone().into_iter() {
-            if self.is_limits_reached() || active_ext_threads.len() >= self.parallelization_level {
+            if self.is_general_limits_reached()
+                || active_ext_threads.len() >= self.parallelization_level
+            {
                 break;
             }
 
@@ -1917,6 +2224,16 @@ impl BlockBuilder {
             if let Some(q) = ext_messages_queue.get_mut(&acc_id) {
                 if let Some((stamp, msg)) = q.pop_front() {
                     anyhow::ensure!(msg.int_header().is_none());
+                    if self.is_quarantined(&msg)? {
+                        tracing::debug!(target: "ext_messages", "quarantined message to <dst:{}>. skipped", acc_id.to_hex_string());
+                        processed_stamps.push(stamp);
+                        ext_message_feedbacks
+                            .push(create_quarantined_feedback(msg, &self.thread_id)?);
+                        if q.is_empty() {
+                            ext_messages_queue.remove(&acc_id);
+                        }
+                        continue;
+                    }
                     tracing::trace!(
                         target: "ext_messages",
                         "Parallel ext message: {:?} to {:?}",
@@ -1953,11 +2270,46 @@ impl BlockBuilder {
         Ok(())
     }
 
+    fn is_quarantined(&self, message: &Message) -> anyhow::Result<bool> {
+        let Some(quarantine) = &self.ext_msg_quarantine else {
+            return Ok(false);
+        };
+        let hash = message.hash().map_err(|e| anyhow::format_err!("{e}"))?;
+        Ok(quarantine.is_quarantined(&hash.to_hex_string()))
+    }
+
+    fn report_ext_msg_failure(
+        &self,
+        quarantine: &ExtMsgQuarantine,
+        message: &Message,
+        error: &anyhow::Error,
+    ) -> anyhow::Result<()> {
+        let hash = message.hash().map_err(|e| anyhow::format_err!("{e}"))?.to_hex_string();
+        let destination = message
+            .int_dst_account_id()
+            .map(|dst| AccountAddress::from(dst).to_hex_string())
+            .unwrap_or_default();
+        let is_quarantined = quarantine.report_failure(
+            &hash,
+            &format!("{:x}", self.thread_id),
+            &destination,
+            &error.to_string(),
+            chrono::Utc::now().timestamp_millis() as u64,
+        );
+        if is_quarantined {
+            tracing::warn!(target: "ext_messages", "External message {hash} to {destination} is quarantined: {error}");
+        } else {
+            tracing::debug!(target: "ext_messages", "External message {hash} failed, retry with the next block: {error}");
+        }
+        Ok(())
+    }
+
     fn process_completed_ext_msg_threads(
         &mut self,
         active_ext_threads: &mut VecDeque<(Stamp, ActiveThread)>,
         active_destinations: &mut HashSet<AccountAddress>,
         ext_message_feedbacks: &mut ExtMsgFeedbackList,
+        processed_stamps: &mut Vec<Stamp>,
     ) -> anyhow::Result<()> {
         let span = tracing::span!(
             tracing::Level::INFO,
@@ -1989,9 +2341,33 @@ impl BlockBuilder {
             // used in tests/ext_messages/process_in_parallel.py
             tracing::trace!(target: "ext_messages", "process completed: active_ext_threads={}", active_ext_threads.len());
 
-            let (_, thread) = active_ext_threads.remove(i).unwrap();
-            let thread_result = thread_result
-                .map_err(|_| anyhow::anyhow!("Failed to execute transaction in parallel"))?;
+            let (stamp, thread) = active_ext_threads.remove(i).unwrap();
+            let thread_result = match (thread_result, &self.ext_msg_quarantine) {
+                (Ok(thread_result), quarantine) => {
+                    if let Some(quarantine) = quarantine {
+                        let hash = thread.message.hash().map_err(|e| anyhow::format_err!("{e}"))?;
+                        quarantine.report_success(&hash.to_hex_string());
+                    }
+                    thread_result
+                }
+                (Err(e), Some(quarantine)) => {
+                    if is_quarantined_failure(&e) {
+                        self.report_ext_msg_failure(quarantine, &thread.message, &e)?;
+                    } else {
+                        tracing::debug!(target: "ext_messages", "External message hit the execution limits, retry with the next block: {e}");
+                    }
+                    // The message is left in the queue, it is dropped once
+                    // quarantined
+                    processed_stamps.retain(|processed| processed != &stamp);
+                    if let Some(dst) = thread.message.int_dst_account_id() {
+                        active_destinations.remove(&AccountAddress::from(dst));
+                    }
+                    continue;
+                }
+                (Err(_), None) => {
+                    anyhow::bail!("Failed to execute transaction in parallel")
+                }
+            };
 
             tracing::trace!(target: "builder", "Thread with dapp_id and minted shell {:?} {:?} {:?}", thread_result.initial_dapp_id, thread_result.minted_shell, thread_result.transaction);
             let acc_id = thread_result.account_id.clone();
@@ -2040,12 +2416,13 @@ impl BlockBuilder {
         let mut active_ext_threads = VecDeque::new();
         let mut block_full = false;
         let mut processed_stamps = vec![];
-        if check_messages_map.is_none() && self.is_limits_reached() {
+        if check_messages_map.is_none() && self.is_general_limits_reached() {
             // Don't even enter prcessing external messages.
             return Ok((ext_message_feedbacks, processed_stamps, true, incoming_queue_len));
         }
 
         loop {
+            watchdog::checkpoint();
             self.fill_ext_msg_threads_pool(
                 &mut ext_messages_queue,
                 &mut active_ext_threads,
@@ -2063,11 +2440,12 @@ impl BlockBuilder {
                 &mut active_ext_threads,
                 &mut active_destinations,
                 &mut ext_message_feedbacks,
+                &mut processed_stamps,
             )?;
 
             let span = tracing::span!(tracing::Level::INFO, "is_limits_reached");
             let span_guard = span.enter();
-            if check_messages_map.is_none() && self.is_limits_reached() {
+            if check_messages_map.is_none() && self.is_general_limits_reached() {
                 block_full = true;
                 tracing::debug!(target: "ext_messages", "Ext messages stop because block is full");
                 break;
@@ -2093,6 +2471,66 @@ impl BlockBuilder {
     }
 }
 
+// Executor panic on an external message is turned into an error, so the
+// message can be quarantined instead of aborting the node
+#[allow(clippy::disallowed_methods)]
+fn catch_execution_panic<T>(execute: impl FnOnce() -> anyhow::Result<T>) -> anyhow::Result<T> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(execute)).unwrap_or_else(|payload| {
+        Err(anyhow::format_err!("Executor panicked: {}", panic_message(payload.as_ref())))
+    })
+}
+
+// Block deadline and execution timeout depend on the node load rather than on
+// the message, so they don't count toward the quarantine
+fn is_quarantined_failure(error: &anyhow::Error) -> bool {
+    !matches!(
+        error.downcast_ref::<ExecutorError>(),
+        Some(ExecutorError::TerminationDeadlineReached)
+            | Some(ExecutorError::TvmExceptionCode(ExceptionCode::ExecutionTimeout))
+    )
+}
+
+// Number of cells in the account state according to its storage stat, which
+// is updated by the executor
+fn account_state_cells(acc_root: &Cell) -> anyhow::Result<u64> {
+    let account = Account::construct_from_cell(acc_root.clone())
+        .map_err(|e| anyhow::format_err!("Failed to construct account: {e}"))?;
+    Ok(account.storage_info().map(|info| info.used().cells()).unwrap_or_default())
+}
+
+// Takes the value of the deferred messages scheduled by the timer contract
+// transaction from the contract balance. Returns false if the balance
+// doesn't cover it.
+fn debit_scheduled_value(
+    acc_root: &mut Cell,
+    transaction: &mut Transaction,
+    value: &CurrencyCollection,
+) -> anyhow::Result<bool> {
+    if value.is_zero().map_err(|e| anyhow::format_err!("Failed to check value: {e}"))? {
+        return Ok(true);
+    }
+    let mut account = Account::construct_from_cell(acc_root.clone())
+        .map_err(|e| anyhow::format_err!("Failed to construct account: {e}"))?;
+    let mut balance = account.balance().cloned().unwrap_or_default();
+    if !balance.sub(value).map_err(|e| anyhow::format_err!("Failed to sub value: {e}"))? {
+        return Ok(false);
+    }
+    account.set_balance(balance);
+    account
+        .update_storage_stat()
+        .map_err(|e| anyhow::format_err!("Failed to update account storage stat: {e}"))?;
+    *acc_root =
+        account.serialize().map_err(|e| anyhow::format_err!("Failed to serialize account: {e}"))?;
+    let state_update = transaction
+        .read_state_update()
+        .map_err(|e| anyhow::format_err!("Failed to read tx state update: {e}"))?;
+    let state_update = HashUpdate::with_hashes(state_update.old_hash, acc_root.repr_hash());
+    transaction
+        .write_state_update(&state_update)
+        .map_err(|e| anyhow::format_err!("Failed to write tx state update: {e}"))?;
+    Ok(true)
+}
+
 fn create_feedback(
     message: Message,
     transaction: Option<Transaction>,
@@ -2202,6 +2640,84 @@ pub fn create_queue_overflow_feedback(
     )
 }
 
+pub fn create_policy_rejection_feedback(
+    msg: Message,
+    thread_id: &ThreadIdentifier,
+) -> anyhow::Result<ExtMsgFeedback> {
+    tracing::info!(
+        target: "builder",
+        "External msg is rejected by the account policy: {:?}",
+        msg
+    );
+
+    create_feedback(
+        msg,
+        None,
+        Some(*thread_id),
+        Some(FeedbackError {
+            code: FeedbackErrorCode::RejectedByPolicy,
+            message: Some("Message is rejected by the block producer account policy.".to_string()),
+        }),
+    )
+}
+
+pub fn create_replayed_message_feedback(
+    msg: Message,
+    thread_id: &ThreadIdentifier,
+) -> anyhow::Result<ExtMsgFeedback> {
+    tracing::info!(
+        target: "builder",
+        "External msg is rejected as already processed: {:?}",
+        msg
+    );
+
+    create_feedback(
+        msg,
+        None,
+        Some(*thread_id),
+        Some(FeedbackError {
+            code: FeedbackErrorCode::DuplicateMessage,
+            message: Some("Message has already been processed.".to_string()),
+        }),
+    )
+}
+
+fn create_quarantined_feedback(
+    msg: Message,
+    thread_id: &ThreadIdentifier,
+) -> anyhow::Result<ExtMsgFeedback> {
+    create_feedback(
+        msg,
+        None,
+        Some(*thread_id),
+        Some(FeedbackError {
+            code: FeedbackErrorCode::Quarantined,
+            message: Some("Message is quarantined after repeated execution failures.".to_string()),
+        }),
+    )
+}
+
+pub fn create_message_expired_feedback(
+    msg: Message,
+    thread_id: &ThreadIdentifier,
+) -> anyhow::Result<ExtMsgFeedback> {
+    tracing::debug!(
+        target: "builder",
+        "External msg is dropped because it expires before inclusion: {:?}",
+        msg
+    );
+
+    create_feedback(
+        msg,
+        None,
+        Some(*thread_id),
+        Some(FeedbackError {
+            code: FeedbackErrorCode::MessageExpired,
+            message: Some("Message expires before it can be included into a block.".to_string()),
+        }),
+    )
+}
+
 fn queue_len(map: &HashMap<AccountAddress, VecDeque<(Stamp, Message)>>) -> usize {
     map.values().map(|queue| queue.len()).sum()
 }
@@ -2363,3 +2879,34 @@ fn get_next_int_message<'a>(
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panicking_ext_message_is_quarantined() {
+        let quarantine = ExtMsgQuarantine::new(2, vec![]);
+        for _ in 0..2 {
+            let error = catch_execution_panic(|| -> anyhow::Result<()> {
+                panic!("unexpected executor state")
+            })
+            .unwrap_err();
+            assert!(error.to_string().contains("unexpected executor state"));
+            assert!(is_quarantined_failure(&error));
+            quarantine.report_failure("msg", "thread", "dst", &error.to_string(), 0);
+        }
+        assert!(quarantine.is_quarantined("msg"));
+    }
+
+    #[test]
+    fn test_limit_failures_are_not_quarantined() {
+        assert!(!is_quarantined_failure(&ExecutorError::TerminationDeadlineReached.into()));
+        assert!(!is_quarantined_failure(
+            &ExecutorError::TvmExceptionCode(ExceptionCode::ExecutionTimeout).into()
+        ));
+        assert!(is_quarantined_failure(
+            &ExecutorError::TvmExceptionCode(ExceptionCode::StackUnderflow).into()
+        ));
+    }
+}
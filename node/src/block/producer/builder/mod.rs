@@ -6,7 +6,10 @@ use std::collections::HashMap;
 use std::collections::HashSet;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Instant;
 
+use http_server::ExtMsgQuarantine;
+use http_server::TxTraceRegistry;
 use serde::Serialize;
 use telemetry_utils::mpsc::InstrumentedReceiver;
 use tvm_block::Block;
@@ -44,6 +47,7 @@ use crate::types::DAppIdentifier;
 use crate::types::ThreadIdentifier;
 
 pub mod build_actions;
+pub mod replay;
 pub mod special_messages;
 pub mod trace;
 
@@ -59,6 +63,7 @@ pub struct PreparedBlock {
     pub block_keeper_set_changes: Vec<BlockKeeperSetChange>,
     pub cross_thread_ref_data: CrossThreadRefData,
     pub changed_dapp_ids: DAppIdTableChangeSet,
+    pub dapp_minted_map: HashMap<DAppIdentifier, i128>,
     #[cfg(feature = "monitor-accounts-number")]
     pub accounts_number_diff: i64,
 }
@@ -113,6 +118,20 @@ pub struct BlockBuilder {
     pub(crate) out_msg_descr: OutMsgDescr,
     // pub(crate) out_queue_info: OutMsgQueueInfo,
     pub(crate) block_gas_limit: u64,
+    // Fraction of the gas and time budget that is left by the general
+    // messages to the dapp config messages executed after them, 0 if nothing
+    // is reserved. Epoch touch and slash messages are executed first and
+    // need no reserve.
+    system_lane_budget_fraction: f64,
+    // Time the general messages stop at, set when the build starts
+    general_deadline: Option<Instant>,
+    // Maximum number of cells in an account state, 0 if not limited
+    pub(crate) max_account_state_cells: u64,
+    // Account that schedules deferred messages, None if they are disabled
+    timer_contract: Option<AccountAddress>,
+    // Number of messages scheduled by the timer contract in the block, they
+    // are added to the deferred messages queue after the block is built
+    deferred_messages_scheduled: usize,
     pub(crate) account_blocks: ShardAccountBlocks,
     pub(crate) total_gas_used: u64,
     pub(crate) start_lt: u64,
@@ -151,6 +170,10 @@ pub struct BlockBuilder {
 
     // cached resources used for wasm execution
     pub(crate) wasm_cache: WasmNodeCache,
+    // Execution traces requested for particular external messages
+    pub(crate) tx_traces: Option<TxTraceRegistry>,
+    // External messages failing to execute repeatedly
+    pub(crate) ext_msg_quarantine: Option<ExtMsgQuarantine>,
 
     #[cfg(feature = "monitor-accounts-number")]
     pub(crate) accounts_number_diff: i64,
@@ -177,4 +200,56 @@ impl BlockBuilder {
             false
         }
     }
+
+    /// Limits of the general messages (internal, external and new ones),
+    /// which stop before the budget reserved for the system lane is used.
+    /// The budget is reserved only while system lane messages are pending.
+    fn is_general_limits_reached(&mut self) -> bool {
+        if self.is_limits_reached() {
+            return true;
+        }
+        if self.system_lane_budget_fraction <= 0.0 || !self.has_pending_system_messages() {
+            return false;
+        }
+        let general_gas_limit =
+            (self.block_gas_limit as f64 * (1.0 - self.system_lane_budget_fraction)) as u64;
+        if self.total_gas_used > general_gas_limit {
+            tracing::info!(target: "builder", "block builder general gas limit reached");
+            return true;
+        }
+        if self.general_deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            tracing::info!(target: "builder", "block builder general time limit reached");
+            return true;
+        }
+        false
+    }
+
+    // Dapp config messages are created after the general messages for the
+    // dapps with limited credit that minted in the block
+    fn has_pending_system_messages(&self) -> bool {
+        self.dapp_minted_map.iter().any(|(dapp_id, minted)| {
+            *minted != 0
+                && self.dapp_credit_map.get(dapp_id).is_some_and(|config| !config.is_unlimit)
+        })
+    }
+
+    /// Reserves the fraction of the block budget for the block keeper system
+    /// messages. Only block production uses it, verification replays the
+    /// messages of the produced block.
+    pub fn set_system_lane_budget_fraction(&mut self, fraction: f64) {
+        self.system_lane_budget_fraction = fraction.clamp(0.0, 1.0);
+    }
+
+    /// Enables deferred messages scheduled by the timer contract. Producer
+    /// and verifiers must use the same contract.
+    pub fn set_timer_contract(&mut self, timer_contract: Option<AccountAddress>) {
+        self.timer_contract = timer_contract;
+    }
+
+    /// Makes failed executions of the external messages to be reported
+    /// instead of failing the block, the quarantined messages are dropped.
+    /// Only block production uses it.
+    pub fn set_ext_msg_quarantine(&mut self, quarantine: Option<ExtMsgQuarantine>) {
+        self.ext_msg_quarantine = quarantine;
+    }
 }
@@ -0,0 +1,115 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::str::FromStr;
+use std::time::Duration;
+use std::time::Instant;
+
+use chrono::Utc;
+use tvm_block::Deserializable;
+use tvm_block::HashmapAugType;
+use tvm_block::Message;
+use tvm_block::Transaction;
+use tvm_executor::BlockchainConfig;
+use tvm_types::HashmapType;
+use tvm_types::SliceData;
+
+use crate::block::producer::builder::BlockBuilder;
+use crate::block::producer::execution_time::ExecutionTimeLimits;
+use crate::block::producer::wasm::WasmNodeCache;
+use crate::config::Config;
+use crate::external_messages::Stamp;
+use crate::repository::accounts::AccountsRepository;
+use crate::repository::optimistic_state::OptimisticState;
+use crate::repository::optimistic_state::OptimisticStateImpl;
+use crate::storage::MessageDurableStorage;
+use crate::types::AccountAddress;
+
+#[derive(Debug, Clone)]
+pub struct ReplayResult {
+    pub tx_cnt: usize,
+    pub gas_used: u64,
+    /// Time of the block build, state loading is not included.
+    pub elapsed: Duration,
+}
+
+/// Executes external messages against the state in a single block with the
+/// production block builder. Execution time is not limited, so all messages
+/// fitting the block gas limit are executed.
+pub fn replay_external_messages(
+    state: OptimisticStateImpl,
+    messages: &[Message],
+    blockchain_config: &BlockchainConfig,
+    accounts_repository: AccountsRepository,
+    config: &Config,
+    parallelization_level: usize,
+) -> anyhow::Result<ReplayResult> {
+    let timestamp = Utc::now();
+    let mut grouped_messages = HashMap::new();
+    for (index, message) in messages.iter().enumerate() {
+        let stamp = Stamp { index: index as u64, timestamp };
+        let account = AccountAddress::from(message.int_dst_account_id().unwrap_or_default());
+        grouped_messages
+            .entry(account)
+            .or_insert_with(VecDeque::new)
+            .push_back((stamp, message.clone()));
+    }
+    let timer_contract = config
+        .global
+        .timer_contract_account
+        .as_deref()
+        .map(AccountAddress::from_str)
+        .transpose()
+        .map_err(|e| anyhow::format_err!("Invalid timer contract account: {e}"))?;
+    let mut builder = BlockBuilder::with_params(
+        *state.get_thread_id(),
+        state,
+        Utc::now().timestamp_millis() as u64,
+        blockchain_config.get_gas_config(false).block_gas_limit,
+        config.global.max_account_state_cells,
+        None,
+        None,
+        accounts_repository,
+        config.global.block_keeper_epoch_code_hash.clone(),
+        config.global.block_keeper_preepoch_code_hash.clone(),
+        parallelization_level,
+        HashMap::new(),
+        None,
+        WasmNodeCache::new()?,
+        None,
+    )?;
+    builder.set_timer_contract(timer_contract);
+    let start = Instant::now();
+    let (prepared_block, _, _) = builder.build_block(
+        grouped_messages,
+        blockchain_config,
+        vec![],
+        None,
+        HashSet::new(),
+        MessageDurableStorage::as_noop(),
+        &ExecutionTimeLimits::NO_LIMITS,
+    )?;
+    let elapsed = start.elapsed();
+
+    let mut gas_used = 0;
+    prepared_block
+        .block
+        .read_extra()
+        .and_then(|extra| extra.read_account_blocks())
+        .and_then(|account_blocks| {
+            account_blocks.iterate_objects(|account_block| {
+                account_block.transactions().iterate_slices(|_, transaction_slice| {
+                    let cell = transaction_slice.reference(0)?;
+                    let transaction =
+                        Transaction::construct_from(&mut SliceData::load_cell(cell)?)?;
+                    gas_used += transaction.gas_used().unwrap_or_default();
+                    Ok(true)
+                })
+            })
+        })
+        .map_err(|e| anyhow::format_err!("Failed to read block transactions: {e}"))?;
+    Ok(ReplayResult { tx_cnt: prepared_block.tx_cnt, gas_used, elapsed })
+}
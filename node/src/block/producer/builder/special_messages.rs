@@ -28,6 +28,67 @@ use crate::repository::optimistic_state::OptimisticState;
 use crate::types::AccountAddress;
 
 impl BlockBuilder {
+    // Note: message is generated by node and has no parent transaction, use default tx instead
+    fn add_out_message_without_transaction(&mut self, message: &Message) -> anyhow::Result<()> {
+        let info = message
+            .int_header()
+            .ok_or_else(|| anyhow::format_err!("Node generated message must be internal"))?;
+        let fwd_fee = info.fwd_fee();
+        let msg_cell = message
+            .serialize()
+            .map_err(|e| anyhow::format_err!("Failed to serialize message: {e}"))?;
+        let env = MsgEnvelope::with_message_and_fee(message, *fwd_fee)
+            .map_err(|e| anyhow::format_err!("Failed to generate message envelope: {e}"))?;
+        let enq = EnqueuedMsg::with_param(info.created_lt, &env)
+            .map_err(|e| anyhow::format_err!("Failed to enqueue message: {e}"))?;
+        let default_tx = tvm_block::Transaction::default()
+            .serialize()
+            .map_err(|e| anyhow::format_err!("Failed to serialize default transaction: {e}"))?;
+        let out_msg = OutMsg::new(enq.out_msg_cell(), default_tx);
+
+        self.out_msg_descr
+            .set(
+                &msg_cell.repr_hash(),
+                &out_msg,
+                &out_msg.aug().map_err(|e| anyhow::format_err!("Failed to get msg aug: {e}"))?,
+            )
+            .map_err(|e| anyhow::format_err!("Failed to add msg to out msg descr: {e}"))?;
+        Ok(())
+    }
+
+    /// Adds messages scheduled by the timer contract that are due at the
+    /// block unixtime. Messages for the current thread are executed in this
+    /// block as new messages.
+    pub(super) fn inject_deferred_messages(&mut self, block_unixtime: u32) -> anyhow::Result<()> {
+        let due_messages = self
+            .initial_optimistic_state
+            .take_due_deferred_messages(block_unixtime, self.timer_contract.as_ref());
+        tracing::trace!(target: "builder", "Due deferred messages: {}", due_messages.len());
+        for wrapped_message in due_messages {
+            let message = wrapped_message.message;
+            let dst_addr: AccountAddress = message
+                .int_dst_account_id()
+                .ok_or_else(|| anyhow::format_err!("Deferred message has no destination"))?
+                .into();
+            self.add_out_message_without_transaction(&message)?;
+            let destination_routing = self
+                .initial_optimistic_state
+                .get_account_routing(&dst_addr, Some(&self.dapp_id_table_change_set));
+            if self.initial_optimistic_state.does_routing_belong_to_the_state(&destination_routing)
+            {
+                self.add_new_internal_message_to_the_current_thread(message, None);
+            } else {
+                let wrapped_message = WrappedMessage { message };
+                let entry = self
+                    .produced_internal_messages_to_other_threads
+                    .entry(destination_routing)
+                    .or_default();
+                entry.push((MessageIdentifier::from(&wrapped_message), Arc::new(wrapped_message)));
+            }
+        }
+        Ok(())
+    }
+
     pub(super) fn execute_dapp_config_messages(
         &mut self,
         blockchain_config: &BlockchainConfig,
@@ -93,32 +154,7 @@ impl BlockBuilder {
                         Arc::new(wrapped_message),
                     ));
                 }
-                let info = message.int_header().unwrap();
-                let fwd_fee = info.fwd_fee();
-                let msg_cell = message.serialize().map_err(|e| {
-                    anyhow::format_err!("Failed to serialize dapp config message: {e}")
-                })?;
-                let env = MsgEnvelope::with_message_and_fee(&message, *fwd_fee).map_err(|e| {
-                    anyhow::format_err!("Failed to generate dapp config message envelope: {e}")
-                })?;
-                let enq = EnqueuedMsg::with_param(info.created_lt, &env).map_err(|e| {
-                    anyhow::format_err!("Failed to enqueue dapp config message: {e}")
-                })?;
-                // Note: this message was generate by node and has no parent transaction, use default tx instead
-                let default_tx = tvm_block::Transaction::default().serialize().map_err(|e| {
-                    anyhow::format_err!("Failed to serialize default transaction: {e}")
-                })?;
-                let out_msg = OutMsg::new(enq.out_msg_cell(), default_tx);
-
-                self.out_msg_descr
-                    .set(
-                        &msg_cell.repr_hash(),
-                        &out_msg,
-                        &out_msg
-                            .aug()
-                            .map_err(|e| anyhow::format_err!("Failed to get msg aug: {e}"))?,
-                    )
-                    .map_err(|e| anyhow::format_err!("Failed to add msg to out msg descr: {e}"))?;
+                self.add_out_message_without_transaction(&message)?;
             }
         }
         let mut active_destinations = HashSet::<AccountAddress>::new();
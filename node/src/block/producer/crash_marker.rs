@@ -0,0 +1,152 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use std::sync::Once;
+use std::thread::ThreadId;
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::types::BlockIdentifier;
+use crate::types::BlockRound;
+use crate::types::BlockSeqNo;
+use crate::types::ThreadIdentifier;
+
+/// Number of consecutive panics after which the production thread is not
+/// restarted anymore and the node stops producing the thread.
+pub const MAX_PRODUCER_RESTARTS: usize = 3;
+
+/// Name prefix of the threads running production iterations. The production
+/// thread joins them, so their panics are reported without unwinding it.
+pub const PRODUCTION_ITERATION_THREAD_PREFIX: &str = "Production iteration";
+
+static INSTALL_BACKTRACE_HOOK: Once = Once::new();
+
+static PANIC_BACKTRACES: LazyLock<Mutex<HashMap<ThreadId, String>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Panic payload doesn't contain the backtrace, so it is captured by the
+/// panic hook for the production iteration threads and kept until the
+/// production thread takes it. The previous hook is still invoked.
+pub fn install_backtrace_hook() {
+    INSTALL_BACKTRACE_HOOK.call_once(|| {
+        let orig_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |panic_info| {
+            let current = std::thread::current();
+            if current
+                .name()
+                .is_some_and(|name| name.starts_with(PRODUCTION_ITERATION_THREAD_PREFIX))
+            {
+                let backtrace = Backtrace::force_capture().to_string();
+                PANIC_BACKTRACES.lock().insert(current.id(), backtrace);
+            }
+            orig_hook(panic_info);
+        }));
+    });
+}
+
+fn take_panic_backtrace(thread_id: ThreadId) -> String {
+    PANIC_BACKTRACES
+        .lock()
+        .remove(&thread_id)
+        .unwrap_or_else(|| "<backtrace was not captured>".to_string())
+}
+
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "<unknown panic payload>".to_string()
+    }
+}
+
+/// Context of the production thread panic, persisted for post-mortem analysis.
+#[derive(Serialize, Debug)]
+pub struct CrashMarker {
+    pub thread_id: String,
+    /// Block the panicked production was started from.
+    pub parent_block_id: String,
+    pub parent_block_seq_no: u32,
+    pub round: BlockRound,
+    /// Number of consecutive panics including this one.
+    pub consecutive_panics: usize,
+    pub message: String,
+    pub backtrace: String,
+    /// Unix time (ms) of the panic.
+    pub timestamp: u64,
+}
+
+impl CrashMarker {
+    pub fn new(
+        thread_id: &ThreadIdentifier,
+        parent_block_id: &BlockIdentifier,
+        parent_block_seq_no: BlockSeqNo,
+        round: BlockRound,
+        consecutive_panics: usize,
+        panicked_thread: ThreadId,
+        payload: &(dyn Any + Send),
+    ) -> Self {
+        Self {
+            thread_id: format!("{thread_id:x}"),
+            parent_block_id: format!("{parent_block_id:x}"),
+            parent_block_seq_no: parent_block_seq_no.into(),
+            round,
+            consecutive_panics,
+            message: panic_message(payload),
+            backtrace: take_panic_backtrace(panicked_thread),
+            timestamp: chrono::Utc::now().timestamp_millis() as u64,
+        }
+    }
+
+    pub fn persist(&self, dir: &Path) -> anyhow::Result<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}-{}.json", self.thread_id, self.timestamp));
+        std::fs::write(&path, serde_json::to_vec_pretty(self)?)?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn panicked(name: &str, f: impl FnOnce() + Send + 'static) -> (ThreadId, Box<dyn Any + Send>) {
+        let handle = std::thread::Builder::new().name(name.to_string()).spawn(f).unwrap();
+        let thread_id = handle.thread().id();
+        (thread_id, handle.join().unwrap_err())
+    }
+
+    #[test]
+    fn test_panic_message() {
+        let (_, payload) = panicked("test", || panic!("static message"));
+        assert_eq!(panic_message(payload.as_ref()), "static message");
+
+        let seq_no = 7;
+        let (_, payload) = panicked("test", move || panic!("formatted {seq_no}"));
+        assert_eq!(panic_message(payload.as_ref()), "formatted 7");
+
+        let (_, payload) = panicked("test", || std::panic::panic_any(1_u8));
+        assert_eq!(panic_message(payload.as_ref()), "<unknown panic payload>");
+    }
+
+    #[test]
+    fn test_panic_backtrace() {
+        install_backtrace_hook();
+        let (thread_id, _) =
+            panicked(&format!("{PRODUCTION_ITERATION_THREAD_PREFIX} 01"), || panic!("iteration"));
+        assert_ne!(take_panic_backtrace(thread_id), "<backtrace was not captured>");
+        // Backtrace is taken once
+        assert_eq!(take_panic_backtrace(thread_id), "<backtrace was not captured>");
+
+        let (thread_id, _) = panicked("other", || panic!("other"));
+        assert_eq!(take_panic_backtrace(thread_id), "<backtrace was not captured>");
+    }
+}
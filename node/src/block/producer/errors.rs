@@ -6,8 +6,25 @@ use std::fmt::Formatter;
 
 use anyhow::anyhow;
 
+use crate::utilities::ErrorCode;
+
 pub(crate) const BP_DID_NOT_PROCESS_ALL_MESSAGES_FROM_PREVIOUS_BLOCK: u8 = 1;
 
+// Compute phase exit code of transactions aborted because the account state
+// would exceed the configured size limit (same as TVM "account state size
+// exceeded limits" result code).
+pub const ACCOUNT_STATE_LIMIT_EXIT_CODE: i32 = 50;
+
+// Compute phase exit code of timer contract transactions aborted because its
+// balance doesn't cover the value of the scheduled deferred messages (same as
+// TVM "not enough funds" result code).
+pub const DEFERRED_VALUE_NOT_COVERED_EXIT_CODE: i32 = 37;
+
+// Compute phase exit code of timer contract transactions aborted because the
+// deferred messages queue can't take all the scheduled messages (same as TVM
+// "too many actions" result code).
+pub const DEFERRED_QUEUE_FULL_EXIT_CODE: i32 = 33;
+
 #[derive(Debug)]
 pub(crate) struct VerifyError {
     pub code: u8,
@@ -36,3 +53,30 @@ impl VerifyError {
 pub(crate) fn verify_error(code: u8) -> anyhow::Error {
     anyhow!(VerifyError::new(code))
 }
+
+#[derive(thiserror::Error, Debug)]
+pub enum BlockProducerError {
+    #[error("Failed to prepare block production: {0}")]
+    Preprocessing(anyhow::Error),
+    #[error("Failed to build block: {0}")]
+    Build(anyhow::Error),
+    #[error("Block production is not allowed: {0}")]
+    ClockSkew(anyhow::Error),
+}
+
+impl ErrorCode for BlockProducerError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Preprocessing(_) => "BLOCK_PRODUCER_PREPROCESSING",
+            Self::Build(_) => "BLOCK_PRODUCER_BUILD",
+            Self::ClockSkew(_) => "BLOCK_PRODUCER_CLOCK_SKEW",
+        }
+    }
+
+    fn is_retryable(&self) -> bool {
+        // Preprocessing depends on cross thread refs and stored messages
+        // that may become available for the next attempt, the clock may be
+        // fixed by the next attempt as well
+        matches!(self, Self::Preprocessing(_) | Self::ClockSkew(_))
+    }
+}
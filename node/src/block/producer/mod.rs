@@ -8,13 +8,17 @@ pub use single_block_producer::TVMBlockProducer;
 pub use single_block_producer::DEFAULT_VERIFY_COMPLEXITY;
 pub use single_block_verifier::BlockVerifier;
 pub use single_block_verifier::TVMBlockVerifier;
+pub mod account_policy;
 pub mod builder;
 pub mod process;
 pub mod wasm;
+pub mod watchdog;
 
+pub mod crash_marker;
 pub mod errors;
 pub(crate) mod execution_time;
 mod producer_service;
 #[cfg(test)]
 pub mod producer_stub;
+mod sealing_stage;
 pub use producer_service::ProducerService;
@@ -9,21 +9,34 @@ use std::thread::JoinHandle;
 use std::time::Duration;
 use std::time::Instant;
 
+use http_server::ExtMsgQuarantine;
+use http_server::TxTraceRegistry;
 use parking_lot::Mutex;
 use telemetry_utils::mpsc::instrumented_channel;
 use telemetry_utils::mpsc::InstrumentedReceiver;
 use telemetry_utils::mpsc::InstrumentedSender;
 use tracing::instrument;
 use tracing::trace_span;
-use tvm_executor::BlockchainConfig;
 use tvm_types::Cell;
 use typed_builder::TypedBuilder;
 
+use crate::block::producer::account_policy::MessagePolicy;
 use crate::block::producer::builder::ActiveThread;
+#[cfg(not(feature = "fail-fast"))]
+use crate::block::producer::crash_marker::install_backtrace_hook;
+#[cfg(not(feature = "fail-fast"))]
+use crate::block::producer::crash_marker::CrashMarker;
+#[cfg(not(feature = "fail-fast"))]
+use crate::block::producer::crash_marker::MAX_PRODUCER_RESTARTS;
+#[cfg(not(feature = "fail-fast"))]
+use crate::block::producer::crash_marker::PRODUCTION_ITERATION_THREAD_PREFIX;
 use crate::block::producer::execution_time::ExecutionTimeLimits;
 use crate::block::producer::execution_time::ProductionTimeoutCorrection;
 use crate::block::producer::producer_service::memento::ProducedBlock;
+use crate::block::producer::sealing_stage::SealingJob;
+use crate::block::producer::sealing_stage::SealingStage;
 use crate::block::producer::wasm::WasmNodeCache;
+use crate::block::producer::watchdog::ProductionWatchdog;
 use crate::block::producer::BlockProducer;
 use crate::block::producer::TVMBlockProducer;
 use crate::block_keeper_system::BlockKeeperData;
@@ -31,6 +44,7 @@ use crate::bls::envelope::BLSSignedEnvelope;
 use crate::bls::envelope::Envelope;
 use crate::bls::BLSSignatureScheme;
 use crate::bls::GoshBLS;
+use crate::config::BlockchainConfigSource;
 use crate::config::Config;
 use crate::external_messages::ExternalMessagesThreadState;
 use crate::helper::block_flow_trace;
@@ -51,18 +65,19 @@ use crate::repository::repository_impl::RepositoryImpl;
 use crate::repository::CrossThreadRefData;
 use crate::repository::Repository;
 use crate::types::next_seq_no;
+use crate::types::notification::Notification;
 use crate::types::BlockIdentifier;
 use crate::types::BlockRound;
 use crate::types::BlockSeqNo;
 use crate::types::ThreadIdentifier;
 use crate::utilities::guarded::Guarded;
-use crate::utilities::guarded::GuardedMut;
+use crate::utilities::load_controller::LOAD_CONTROLLER;
 use crate::utilities::thread_spawn_critical::SpawnCritical;
 
 #[derive(TypedBuilder)]
 pub struct TVMBlockProducerProcess {
     node_config: Config,
-    blockchain_config: Arc<BlockchainConfig>,
+    blockchain_config: BlockchainConfigSource,
     repository: RepositoryImpl,
     #[builder(default)]
     produced_blocks: Arc<Mutex<Vec<ProducedBlock>>>,
@@ -82,6 +97,14 @@ pub struct TVMBlockProducerProcess {
     block_keeper_preepoch_code_hash: String,
     metrics: Option<BlockProductionMetrics>,
     wasm_cache: WasmNodeCache,
+    #[builder(default)]
+    tx_traces: Option<TxTraceRegistry>,
+    #[builder(default)]
+    ext_msg_quarantine: Option<ExtMsgQuarantine>,
+    #[builder(default)]
+    message_policy: Option<Arc<dyn MessagePolicy>>,
+    #[builder(default)]
+    production_watchdog: Option<ProductionWatchdog>,
     share_service: Option<ExternalFileSharesBased>,
     save_optimistic_service_sender: InstrumentedSender<Arc<OptimisticStateImpl>>,
 }
@@ -98,13 +121,13 @@ impl TVMBlockProducerProcess {
     fn produce_next(
         node_config: Config,
         initial_state: &mut OptimisticStateImpl,
-        blockchain_config: Arc<BlockchainConfig>,
+        blockchain_config: BlockchainConfigSource,
         producer_node_id: NodeIdentifier,
         thread_count_soft_limit: usize,
         parallelization_level: usize,
         block_keeper_epoch_code_hash: String,
         block_keeper_preepoch_code_hash: String,
-        produced_blocks: Arc<Mutex<Vec<ProducedBlock>>>,
+        sealing_stage: &mut SealingStage,
         timeout: Arc<Mutex<Duration>>,
         timeout_correction: &mut ProductionTimeoutCorrection,
         thread_id_clone: ThreadIdentifier,
@@ -118,6 +141,9 @@ impl TVMBlockProducerProcess {
         external_control_rx: &InstrumentedReceiver<()>,
         metrics: Option<BlockProductionMetrics>,
         wasm_cache: WasmNodeCache,
+        tx_traces: Option<TxTraceRegistry>,
+        ext_msg_quarantine: Option<ExtMsgQuarantine>,
+        message_policy: Option<Arc<dyn MessagePolicy>>,
         external_messages_queue: &mut ExternalMessagesThreadState,
         repository: &RepositoryImpl,
         is_state_sync_requested: Arc<Mutex<Option<BlockSeqNo>>>,
@@ -128,6 +154,8 @@ impl TVMBlockProducerProcess {
         tracing::trace!("Start block production process iteration");
         let start_time = std::time::SystemTime::now();
         let production_time = Instant::now();
+        let mut arrivals = external_messages_queue.arrivals();
+        let arrivals_stamp = arrivals.stamp();
         let (message_queue, epoch_block_keeper_data, block_nack, aggregated_acks, aggregated_nacks) =
             trace_span!("read messages").in_scope(|| {
                 let message_queue = external_messages_queue.get_remaining_external_messages()?;
@@ -160,15 +188,24 @@ impl TVMBlockProducerProcess {
                 ))
             })?;
 
+        // The idle block is cut short by the external message arrival
+        let on_demand_min_interval = node_config
+            .global
+            .produce_on_demand_min_interval_millis
+            .filter(|_| message_queue.is_empty())
+            .map(Duration::from_millis);
+        let blockchain_config = blockchain_config.for_state(initial_state, &accounts_repo)?;
         let producer = TVMBlockProducer::builder()
             .active_threads(mem::take(active_block_producer_threads))
-            .blockchain_config(blockchain_config.clone())
+            .blockchain_config(blockchain_config)
             .message_queue(message_queue)
             .producer_node_id(producer_node_id.clone())
             .thread_count_soft_limit(thread_count_soft_limit)
             .parallelization_level(parallelization_level)
             .block_keeper_epoch_code_hash(block_keeper_epoch_code_hash)
             .block_keeper_preepoch_code_hash(block_keeper_preepoch_code_hash)
+            .max_account_state_cells(node_config.global.max_account_state_cells)
+            .system_lane_budget_fraction(node_config.global.system_lane_budget_fraction)
             .epoch_block_keeper_data(epoch_block_keeper_data)
             .shared_services(shared_services.clone())
             .block_nack(block_nack.clone())
@@ -176,6 +213,10 @@ impl TVMBlockProducerProcess {
             .block_state_repository(block_state_repo.clone())
             .metrics(metrics.clone())
             .wasm_cache(wasm_cache)
+            .tx_traces(tx_traces)
+            .ext_msg_quarantine(ext_msg_quarantine)
+            .message_policy(message_policy)
+            .verify_complexity(node_config.global.verify_sampling.verify_complexity)
             .build();
 
         let (control_tx, control_rx) =
@@ -187,6 +228,8 @@ impl TVMBlockProducerProcess {
             initial_state_clone.thread_refs_state
         );
 
+        // Ref data of the previously produced block is stored on sealing
+        trace_span!("wait sealing").in_scope(|| sealing_stage.wait_sealed())?;
         let refs = trace_span!("read thread refs").in_scope(|| {
             let initial_buffer: Vec<(ThreadIdentifier, BlockIdentifier)> =
                 trace_span!("guarded list_blocks_sending_messages_to_thread").in_scope(|| {
@@ -292,15 +335,25 @@ impl TVMBlockProducerProcess {
                 *flag == Some(next_seq_no(initial_state.block_seq_no))
             };
             if state_share_was_requested {
+                // Ref states are loaded in background not to stall the production
                 for block_ref in &refs {
-                    let state = repository
-                        .get_full_optimistic_state(
-                            block_ref.block_identifier(),
-                            block_ref.block_thread_identifier(),
-                            None,
-                        )?
-                        .ok_or(anyhow::format_err!("Ref state must be present on BP"))?;
-                    share_service.save_state_for_sharing(state)?;
+                    let block_id = block_ref.block_identifier().clone();
+                    let share_service = share_service.clone();
+                    repository.get_full_optimistic_state_in_background(
+                        block_id.clone(),
+                        *block_ref.block_thread_identifier(),
+                        move |state| match state {
+                            Ok(Some(state)) => {
+                                if let Err(e) = share_service.save_state_for_sharing(state) {
+                                    tracing::error!("Failed to share ref state {block_id:?}: {e}");
+                                }
+                            }
+                            Ok(None) => {
+                                tracing::error!("Ref state must be present on BP: {block_id:?}")
+                            }
+                            Err(e) => tracing::error!("Failed to load ref state {block_id:?}: {e}"),
+                        },
+                    );
                 }
             }
         }
@@ -350,9 +403,21 @@ impl TVMBlockProducerProcess {
         let corrected_timeout = timeout_correction.get_production_timeout(desired_timeout);
         tracing::trace!("Sleep for {corrected_timeout:?}");
 
-        trace_span!("sleep").in_scope(|| {
-            sleep(corrected_timeout);
+        let produced_on_demand = trace_span!("sleep").in_scope(|| match on_demand_min_interval {
+            Some(min_interval) => wait_for_production_timeout(
+                &mut arrivals,
+                arrivals_stamp,
+                corrected_timeout,
+                production_time + min_interval,
+            ),
+            None => {
+                sleep(corrected_timeout);
+                false
+            }
         });
+        if produced_on_demand {
+            tracing::trace!("External message arrived, finish the idle block");
+        }
 
         tracing::trace!("Send signal to stop production");
         let _ = control_tx.send(());
@@ -410,50 +475,23 @@ impl TVMBlockProducerProcess {
 
         *active_block_producer_threads = new_active_block_producer_threads;
         *initial_state = result_state;
-        repository.store_optimistic_in_cache(initial_state.clone())?;
 
-        if let Some(share_service) = &share_service {
-            let state_share_was_requested = {
-                let flag = is_state_sync_requested.lock();
-                *flag == Some(initial_state.block_seq_no)
-            };
-            if state_share_was_requested {
-                let block_id = initial_state.block_id.clone();
-                let thread_id = initial_state.thread_id;
-                let state = repository
-                    .get_full_optimistic_state(
-                        &block_id,
-                        &thread_id,
-                        Some(Arc::new(initial_state.clone())),
-                    )?
-                    .expect("Must be accessible");
-                share_service.save_state_for_sharing(state.clone())?;
-            }
-        }
-
-        let span_save_cross_thread_refs =
-            trace_span!("save cross thread refs", refs_len = cross_thread_ref_data.refs().len());
-        shared_services.exec(|e| {
-            span_save_cross_thread_refs.in_scope(|| {
-                e.cross_thread_ref_data_service.set_cross_thread_ref_data(cross_thread_ref_data)
+        let share_state = share_service.is_some() && {
+            let flag = is_state_sync_requested.lock();
+            *flag == Some(initial_state.block_seq_no)
+        };
+        let block_id = block.identifier();
+        // Sealing of the produced block overlaps with the next production iteration
+        trace_span!("submit to sealing").in_scope(|| {
+            sealing_stage.submit(SealingJob {
+                block,
+                optimistic_state: Arc::new(initial_state.clone()),
+                cross_thread_ref_data,
+                feedbacks: ext_msg_feedbacks,
+                block_state: produced_block_state.clone(),
+                share_state,
             })
         })?;
-        drop(span_save_cross_thread_refs);
-        let block_id = block.identifier();
-        produced_block_state.guarded_mut(|e| e.set_has_cross_thread_ref_data_prepared())?;
-
-        trace_span!("save state").in_scope(|| {
-            tracing::trace!("Save produced block");
-            let mut blocks = produced_blocks.lock();
-            let produced_data = ProducedBlock::builder()
-                .block(block)
-                .optimistic_state(Arc::new(initial_state.clone()))
-                .feedbacks(ext_msg_feedbacks)
-                .block_state(produced_block_state.clone())
-                .metrics_memento_init_time(None)
-                .build();
-            blocks.push(produced_data);
-        });
         tracing::trace!("End block production process iteration");
         let production_time = production_time.elapsed();
 
@@ -465,11 +503,16 @@ impl TVMBlockProducerProcess {
             )
         });
 
-        timeout_correction.report_last_production(production_time);
-        if production_time < desired_timeout {
-            sleep(desired_timeout - production_time);
+        LOAD_CONTROLLER.report_deadline(&thread_id_clone, production_time > desired_timeout);
+        // The block finished on demand must not skew the timeout correction
+        // and the next block is started right away
+        if !produced_on_demand {
+            timeout_correction.report_last_production(production_time);
+            if production_time < desired_timeout {
+                sleep(desired_timeout - production_time);
+            }
         }
-        block_flow_trace("finish production", &block_id, &producer_node_id, []);
+        block_flow_trace("submitted for sealing", &block_id, &producer_node_id, []);
         Ok((ProcudeNextResult::Continues, produced_block_state))
     }
 
@@ -504,11 +547,18 @@ impl TVMBlockProducerProcess {
                         "start_thread_production: producer process contains state cache for this thread: {:?} {prev_block_id:?}",
                         state.block_id
                     );
-                    if &state.block_id == prev_block_id {
+                    if &state.block_id != prev_block_id {
+                        None
+                    } else if let Err(reason) =
+                        self.repository.validate_optimistic_state(state, prev_block_id)
+                    {
+                        tracing::error!(
+                            "start_thread_production: discard corrupted cached state: {reason}"
+                        );
+                        None
+                    } else {
                         tracing::trace!("start_thread_production: use cached state");
                         Some(state.clone())
-                    } else {
-                        None
                     }
                 }
                 _ => None,
@@ -519,7 +569,7 @@ impl TVMBlockProducerProcess {
                     "start_thread_production: cached state block id is not appropriate. Load state from repo"
                 );
                 if let Some(state) =
-                    self.repository.get_optimistic_state(prev_block_id, thread_id, None)?
+                    self.repository.get_validated_optimistic_state(prev_block_id, thread_id)?
                 {
                     state
                 } else if prev_block_id == &BlockIdentifier::default() {
@@ -530,7 +580,7 @@ impl TVMBlockProducerProcess {
             }
         };
 
-        let blockchain_config = Arc::clone(&self.blockchain_config);
+        let blockchain_config = self.blockchain_config.clone();
 
         let repo_clone = self.repository.clone();
         let produced_blocks = self.produced_blocks.clone();
@@ -552,12 +602,21 @@ impl TVMBlockProducerProcess {
         let block_keeper_preepoch_code_hash = self.block_keeper_preepoch_code_hash.clone();
         let metrics = self.repository.get_metrics();
         let wasm_cache = self.wasm_cache.clone();
+        let tx_traces = self.tx_traces.clone();
+        let ext_msg_quarantine = self.ext_msg_quarantine.clone();
+        let message_policy = self.message_policy.clone();
+        let production_watchdog = self.production_watchdog.clone();
         let accounts_repo = self.repository.accounts_repository().clone();
         let node_config = self.node_config.clone();
         let share_service = self.share_service.clone();
         let prev_block_id = prev_block_id.clone();
         let save_state_sender = self.save_optimistic_service_sender.clone();
+        #[cfg(not(feature = "fail-fast"))]
+        install_backtrace_hook();
         let produce = move || {
+            // Receivers are shared with the iteration threads
+            let epoch_block_keeper_data_rx = Mutex::new(epoch_block_keeper_data_rx);
+            let external_control_rx = Mutex::new(external_control_rx);
             let mut active_block_producer_threads = vec![];
             // Note:
             // This loop runs infinitely till the interrupt signal generating new blocks
@@ -572,38 +631,137 @@ impl TVMBlockProducerProcess {
             let mut parent_block_state = block_state_repository
                 .get(&prev_block_id)
                 .expect("Failed to load parent block state");
+            let mut sealing_stage = SealingStage::spawn(
+                thread_id_clone,
+                producer_node_id.clone(),
+                repo_clone.clone(),
+                shared_services.clone(),
+                share_service.clone(),
+                produced_blocks,
+            )
+            .expect("Failed to start sealing stage");
+            #[cfg(not(feature = "fail-fast"))]
+            let mut consecutive_panics = 0;
             loop {
+                #[cfg(not(feature = "fail-fast"))]
+                let (parent_block_id, parent_block_seq_no) =
+                    (initial_state.block_id.clone(), initial_state.block_seq_no);
                 let mut state_in = Arc::unwrap_or_clone(initial_state);
-                let produce_res = Self::produce_next(
-                    node_config.clone(),
-                    &mut state_in,
-                    blockchain_config.clone(),
-                    producer_node_id.clone(),
-                    thread_count_soft_limit,
-                    parallelization_level,
-                    block_keeper_epoch_code_hash.clone(),
-                    block_keeper_preepoch_code_hash.clone(),
-                    produced_blocks.clone(),
-                    timeout.clone(),
-                    &mut timeout_correction,
-                    thread_id_clone,
-                    &epoch_block_keeper_data_rx,
-                    &mut shared_services,
-                    &mut active_block_producer_threads,
-                    received_acks.clone(),
-                    received_nacks.clone(),
-                    block_state_repository.clone(),
-                    accounts_repo.clone(),
-                    &external_control_rx,
-                    metrics.clone(),
-                    wasm_cache.clone(),
-                    &mut external_messages,
-                    &repo_clone,
-                    is_state_sync_requested.clone(),
-                    share_service.clone(),
-                    round,
-                    parent_block_state,
-                );
+                let iteration_guard = production_watchdog.as_ref().map(|watchdog| {
+                    watchdog.start_iteration(
+                        thread_id_clone,
+                        state_in.block_seq_no,
+                        *timeout.lock(),
+                    )
+                });
+                let produce_next = || {
+                    if let Some(guard) = &iteration_guard {
+                        guard.attach_current_thread();
+                    }
+                    Self::produce_next(
+                        node_config.clone(),
+                        &mut state_in,
+                        blockchain_config.clone(),
+                        producer_node_id.clone(),
+                        thread_count_soft_limit,
+                        parallelization_level,
+                        block_keeper_epoch_code_hash.clone(),
+                        block_keeper_preepoch_code_hash.clone(),
+                        &mut sealing_stage,
+                        timeout.clone(),
+                        &mut timeout_correction,
+                        thread_id_clone,
+                        &epoch_block_keeper_data_rx.lock(),
+                        &mut shared_services,
+                        &mut active_block_producer_threads,
+                        received_acks.clone(),
+                        received_nacks.clone(),
+                        block_state_repository.clone(),
+                        accounts_repo.clone(),
+                        &external_control_rx.lock(),
+                        metrics.clone(),
+                        wasm_cache.clone(),
+                        tx_traces.clone(),
+                        ext_msg_quarantine.clone(),
+                        message_policy.clone(),
+                        &mut external_messages,
+                        &repo_clone,
+                        is_state_sync_requested.clone(),
+                        share_service.clone(),
+                        round,
+                        parent_block_state.clone(),
+                    )
+                };
+                // With fail-fast the panic hook exits the process, otherwise the
+                // iteration runs in its own thread, its panic is returned by join
+                // and the production is restarted from the last clean state.
+                #[cfg(feature = "fail-fast")]
+                let produce_res = produce_next();
+                #[cfg(not(feature = "fail-fast"))]
+                let iteration_res = std::thread::scope(|scope| {
+                    let iteration = std::thread::Builder::new()
+                        .name(format!("{PRODUCTION_ITERATION_THREAD_PREFIX} {thread_id_clone}"))
+                        .spawn_scoped(scope, produce_next)
+                        .expect("Failed to spawn production iteration thread");
+                    let iteration_thread = iteration.thread().id();
+                    iteration.join().map_err(|payload| (iteration_thread, payload))
+                });
+                #[cfg(not(feature = "fail-fast"))]
+                let produce_res = match iteration_res {
+                    Ok(produce_res) => {
+                        consecutive_panics = 0;
+                        produce_res
+                    }
+                    Err((iteration_thread, payload)) => {
+                        consecutive_panics += 1;
+                        let marker = CrashMarker::new(
+                            &thread_id_clone,
+                            &parent_block_id,
+                            parent_block_seq_no,
+                            round,
+                            consecutive_panics,
+                            iteration_thread,
+                            payload.as_ref(),
+                        );
+                        tracing::error!(
+                            "Production of thread {thread_id_clone:?} panicked on top of block {parent_block_id:?}: {}",
+                            marker.message
+                        );
+                        match marker.persist(&repo_clone.get_crash_markers_dir_path()) {
+                            Ok(path) => tracing::error!("Crash marker saved: {path:?}"),
+                            Err(err) => tracing::error!("Failed to save crash marker: {err:?}"),
+                        }
+                        // The state could be modified before the panic, so it is
+                        // discarded and the parent state is loaded again.
+                        let clean_state = if consecutive_panics > MAX_PRODUCER_RESTARTS {
+                            tracing::error!(
+                                "Production of thread {thread_id_clone:?} panicked {consecutive_panics} times in a row, stop production"
+                            );
+                            None
+                        } else {
+                            repo_clone
+                                .get_optimistic_state(&parent_block_id, &thread_id_clone, None)
+                                .inspect_err(|err| {
+                                    tracing::error!("Failed to load clean state: {err:?}")
+                                })
+                                .ok()
+                                .flatten()
+                        };
+                        let Some(clean_state) = clean_state else {
+                            if let Err(err) = sealing_stage.finish() {
+                                tracing::error!("Sealing stage failed: {err:?}");
+                            }
+                            std::panic::resume_unwind(payload);
+                        };
+                        metrics.as_ref().inspect(|m| m.report_producer_restart(&thread_id_clone));
+                        tracing::warn!(
+                            "Restart production of thread {thread_id_clone:?} from block {parent_block_id:?}"
+                        );
+                        initial_state = clean_state;
+                        continue;
+                    }
+                };
+                drop(iteration_guard);
                 // Note:
                 // if stopped.is_ok() ... is skipped.
                 // this heavily relies on the produce_next fn and assumes
@@ -616,6 +774,10 @@ impl TVMBlockProducerProcess {
                 if produce_res.is_err()
                     || produce_res.as_ref().map(|e| e.0 == ProcudeNextResult::Stopped).unwrap()
                 {
+                    // Blocks produced before the stop must be sealed before the last state is stored
+                    if let Err(err) = sealing_stage.finish() {
+                        tracing::error!("Sealing stage failed: {err:?}");
+                    }
                     trace_span!("store optimistic").in_scope(|| {
                         repo_clone
                             .store_optimistic_in_cache(initial_state.clone())
@@ -737,6 +899,26 @@ fn aggregate_acks(
     Ok(aggregated_acks.values().cloned().collect())
 }
 
+// Sleeps for the production timeout unless an external message arrives.
+// Returns true if the sleep was cut short by the arrival, the block is not
+// finished earlier than `earliest_finish` anyway.
+fn wait_for_production_timeout(
+    arrivals: &mut Notification,
+    stamp: u32,
+    timeout: Duration,
+    earliest_finish: Instant,
+) -> bool {
+    arrivals.wait_for_updates_timeout(stamp, timeout);
+    if arrivals.stamp() == stamp {
+        return false;
+    }
+    let now = Instant::now();
+    if earliest_finish > now {
+        sleep(earliest_finish - now);
+    }
+    true
+}
+
 // TODO: fix this function nacks can't be aggregated based on block id
 fn aggregate_nacks(
     mut received_nacks: Vec<Envelope<GoshBLS, NackData>>,
@@ -793,6 +975,7 @@ mod tests {
     use telemetry_utils::mpsc::InstrumentedSender;
     use testdir::testdir;
 
+    use crate::block::producer::process::wait_for_production_timeout;
     use crate::block::producer::process::TVMBlockProducerProcess;
     use crate::block::producer::wasm::WasmNodeCache;
     use crate::config::load_blockchain_config;
@@ -809,6 +992,7 @@ mod tests {
     use crate::storage::CrossRefStorage;
     use crate::storage::MessageDurableStorage;
     use crate::tests::project_root;
+    use crate::types::notification::Notification;
     use crate::types::BlockIdentifier;
     use crate::types::ThreadIdentifier;
     use crate::utilities::FixedSizeHashSet;
@@ -846,7 +1030,7 @@ mod tests {
         let repository = RepositoryImpl::new(
             root_dir.clone(),
             Some(config.local.zerostate_path.clone()),
-            1,
+            crate::repository::repository_impl::tests::state_save_policy(),
             SharedServices::start(
                 RoutingService::stub().0,
                 root_dir.clone(),
@@ -861,7 +1045,11 @@ mod tests {
             false,
             block_state_repository.clone(),
             None,
-            AccountsRepository::new(root_dir.clone(), None, 1),
+            AccountsRepository::new(
+                root_dir.clone(),
+                None,
+                crate::repository::repository_impl::tests::state_save_policy(),
+            ),
             message_db.clone(),
             finalized_blocks,
             mock_bk_set_updates_tx(),
@@ -879,9 +1067,7 @@ mod tests {
             .block_keeper_epoch_code_hash(config.global.block_keeper_epoch_code_hash.clone())
             .block_keeper_preepoch_code_hash(config.global.block_keeper_preepoch_code_hash.clone())
             .producer_node_id(config.local.node_id.clone())
-            .blockchain_config(Arc::new(load_blockchain_config(
-                &config.local.blockchain_config_path,
-            )?))
+            .blockchain_config(load_blockchain_config(&config.local.blockchain_config_path)?.into())
             .parallelization_level(config.local.parallelization_level)
             .shared_services(SharedServices::start(
                 router,
@@ -945,4 +1131,38 @@ mod tests {
         assert!(avg_time > 320 && avg_time < 340);
         Ok(())
     }
+
+    #[test]
+    fn test_production_timeout_cut_by_arrival() {
+        let mut arrivals = Notification::new();
+        let stamp = arrivals.stamp();
+        let start = Instant::now();
+        assert!(!wait_for_production_timeout(
+            &mut arrivals,
+            stamp,
+            Duration::from_millis(50),
+            start
+        ));
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        let mut pusher = arrivals.clone();
+        let handle = std::thread::Builder::new()
+            .name("Arrivals pusher".to_string())
+            .spawn(move || {
+                sleep(Duration::from_millis(10));
+                pusher.touch();
+            })
+            .unwrap();
+        let start = Instant::now();
+        // The arrival does not finish the block before the minimal interval
+        assert!(wait_for_production_timeout(
+            &mut arrivals,
+            stamp,
+            Duration::from_secs(10),
+            start + Duration::from_millis(100)
+        ));
+        handle.join().unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
 }
@@ -26,6 +26,7 @@ use crate::bls::gosh_bls::PubKey;
 use crate::bls::gosh_bls::Secret;
 use crate::bls::GoshBLS;
 use crate::config::must_save_state_on_seq_no;
+use crate::config::ProducerSelectionConfig;
 use crate::external_messages::ExternalMessagesThreadState;
 use crate::helper::block_flow_trace;
 use crate::helper::SHUTDOWN_FLAG;
@@ -93,6 +94,7 @@ pub struct BlockProducer {
     node_identifier: NodeIdentifier,
     production_timeout: Duration,
     save_state_frequency: u32,
+    producer_selection: ProducerSelectionConfig,
 
     bp_production_count: Arc<AtomicI32>,
 
@@ -162,6 +164,9 @@ impl BlockProducer {
                     if was_producer {
                         self.bp_production_count.fetch_sub(1, Ordering::Relaxed);
                     }
+                    self.shared_services.node_status.as_ref().inspect(|x| {
+                        x.set_producing(&format!("{:x}", self.thread_id), false);
+                    });
                     self.repository
                         .get_metrics()
                         .inspect(|m| m.report_thread_load(0, &self.thread_id));
@@ -235,6 +240,9 @@ impl BlockProducer {
                     if !was_producer {
                         self.bp_production_count.fetch_add(1, Ordering::Relaxed);
                     }
+                    self.shared_services.node_status.as_ref().inspect(|x| {
+                        x.set_producing(&format!("{:x}", self.thread_id), true);
+                    });
                     producer_tails = Some((block_id_to_continue.clone(), block_seq_no_to_continue));
                 }
                 Err(e) => {
@@ -725,14 +733,19 @@ impl BlockProducer {
         let mut producer_selector = self.get_producer_selector(&candidate_block.parent())?;
 
         // 2 step: update index if we have rotated BP:
-        let find_bp = producer_selector.get_producer_node_id(&bk_set);
+        let find_bp = producer_selector.get_producer_node_id(&bk_set, &self.producer_selection);
         if find_bp.is_err() || find_bp.unwrap() != self.node_identifier {
-            if let Some(bp_distance_for_this_node) =
-                producer_selector.get_distance_from_bp(&bk_set, &self.node_identifier)
-            {
+            if let Some(bp_distance_for_this_node) = producer_selector.get_distance_from_bp(
+                &bk_set,
+                &self.producer_selection,
+                &self.node_identifier,
+            ) {
                 // move index
-                producer_selector =
-                    producer_selector.move_index(bp_distance_for_this_node, bk_set.len());
+                producer_selector = producer_selector.move_index(
+                    bp_distance_for_this_node,
+                    &bk_set,
+                    &self.producer_selection,
+                );
             } else {
                 // If previous selector was invalid (due to bk removal) generate a new one
                 producer_selector = ProducerSelector::builder()
@@ -740,10 +753,13 @@ impl BlockProducer {
                     .index(0)
                     .build();
                 let bp_distance_for_this_node = producer_selector
-                    .get_distance_from_bp(&bk_set, &self.node_identifier)
+                    .get_distance_from_bp(&bk_set, &self.producer_selection, &self.node_identifier)
                     .expect("Must be able to find bp_distance_for_this_node");
-                producer_selector =
-                    producer_selector.move_index(bp_distance_for_this_node, bk_set.len());
+                producer_selector = producer_selector.move_index(
+                    bp_distance_for_this_node,
+                    &bk_set,
+                    &self.producer_selection,
+                );
             }
         }
         common_section.producer_selector = Some(producer_selector);
@@ -19,6 +19,7 @@ use crate::bls::envelope::Envelope;
 use crate::bls::gosh_bls::PubKey;
 use crate::bls::gosh_bls::Secret;
 use crate::bls::GoshBLS;
+use crate::config::ProducerSelectionConfig;
 use crate::external_messages::ExternalMessagesThreadState;
 use crate::node::associated_types::AckData;
 use crate::node::associated_types::NackData;
@@ -65,6 +66,7 @@ impl ProducerService {
         node_identifier: NodeIdentifier,
         production_timeout: Duration,
         save_state_frequency: u32,
+        producer_selection: ProducerSelectionConfig,
         external_messages: ExternalMessagesThreadState,
 
         is_state_sync_requested: Arc<Mutex<Option<BlockSeqNo>>>,
@@ -90,6 +92,7 @@ impl ProducerService {
             .received_acks(received_acks)
             .feedback_sender(feedback_sender)
             .save_state_frequency(save_state_frequency)
+            .producer_selection(producer_selection)
             .external_messages(external_messages)
             .is_state_sync_requested(is_state_sync_requested)
             .bp_production_count(bp_production_count)
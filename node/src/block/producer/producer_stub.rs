@@ -6,6 +6,7 @@ use telemetry_utils::mpsc::InstrumentedReceiver;
 use tvm_types::Cell;
 
 use crate::block::producer::builder::ActiveThread;
+use crate::block::producer::errors::BlockProducerError;
 use crate::block::producer::execution_time::ExecutionTimeLimits;
 use crate::block::producer::BlockProducer;
 use crate::external_messages::Stamp;
@@ -36,15 +37,18 @@ impl BlockProducer for BlockProducerStub {
         _time_limits: &ExecutionTimeLimits,
         _block_round: BlockRound,
         _parent_block_state: BlockState,
-    ) -> anyhow::Result<(
-        AckiNackiBlock,
-        Self::OptimisticState,
-        Vec<(Cell, ActiveThread)>,
-        CrossThreadRefData,
-        Vec<Stamp>,
-        ExtMsgFeedbackList,
-        BlockState,
-    )>
+    ) -> Result<
+        (
+            AckiNackiBlock,
+            Self::OptimisticState,
+            Vec<(Cell, ActiveThread)>,
+            CrossThreadRefData,
+            Vec<Stamp>,
+            ExtMsgFeedbackList,
+            BlockState,
+        ),
+        BlockProducerError,
+    >
     where
         I: std::iter::Iterator<Item = &'a CrossThreadRefData> + Clone,
         CrossThreadRefData: 'a,
@@ -0,0 +1,239 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::sync::mpsc::sync_channel;
+use std::sync::mpsc::SyncSender;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use http_server::ExtMsgFeedbackList;
+use http_server::OutMsgQueueEntry;
+use http_server::OutMsgQueueIndex;
+use http_server::OutMsgQueueRecord;
+use parking_lot::Condvar;
+use parking_lot::Mutex;
+use tracing::trace_span;
+
+use crate::block::producer::producer_service::memento::ProducedBlock;
+use crate::helper::block_flow_trace;
+use crate::node::block_state::repository::BlockState;
+use crate::node::services::sync::ExternalFileSharesBased;
+use crate::node::services::sync::StateSyncService;
+use crate::node::shared_services::SharedServices;
+use crate::node::NodeIdentifier;
+use crate::repository::optimistic_state::OptimisticStateImpl;
+use crate::repository::repository_impl::RepositoryImpl;
+use crate::repository::CrossThreadRefData;
+use crate::repository::Repository;
+use crate::types::AckiNackiBlock;
+use crate::types::ThreadIdentifier;
+use crate::utilities::guarded::GuardedMut;
+
+// Number of produced blocks that can wait for sealing while the next one is
+// being produced. Production blocks on submit when the stage falls behind.
+const SEALING_QUEUE_DEPTH: usize = 1;
+
+// Post-production work for a single produced block. None of it is required
+// to start producing the next block, so it is done on the sealing stage.
+pub(crate) struct SealingJob {
+    pub block: AckiNackiBlock,
+    pub optimistic_state: Arc<OptimisticStateImpl>,
+    pub cross_thread_ref_data: CrossThreadRefData,
+    pub feedbacks: ExtMsgFeedbackList,
+    pub block_state: BlockState,
+    pub share_state: bool,
+}
+
+// Seals produced blocks on a dedicated thread in the order they were submitted:
+// stores the optimistic state in cache, persists cross-thread ref data and
+// hands the block over to the producer service.
+pub(crate) struct SealingStage {
+    jobs_tx: Option<SyncSender<SealingJob>>,
+    handle: Option<JoinHandle<anyhow::Result<()>>>,
+    progress: Arc<(Mutex<SealingProgress>, Condvar)>,
+}
+
+#[derive(Default)]
+struct SealingProgress {
+    // Submitted jobs that are not sealed yet
+    pending: usize,
+    stopped: bool,
+}
+
+// Marks the stage stopped when the sealing thread exits, including on errors,
+// so waiters do not hang.
+struct StoppedOnDrop(Arc<(Mutex<SealingProgress>, Condvar)>);
+
+impl Drop for StoppedOnDrop {
+    fn drop(&mut self) {
+        let (progress, sealed) = &*self.0;
+        progress.lock().stopped = true;
+        sealed.notify_all();
+    }
+}
+
+impl SealingStage {
+    pub fn spawn(
+        thread_id: ThreadIdentifier,
+        producer_node_id: NodeIdentifier,
+        repository: RepositoryImpl,
+        mut shared_services: SharedServices,
+        share_service: Option<ExternalFileSharesBased>,
+        produced_blocks: Arc<Mutex<Vec<ProducedBlock>>>,
+    ) -> anyhow::Result<Self> {
+        let (jobs_tx, jobs_rx) = sync_channel::<SealingJob>(SEALING_QUEUE_DEPTH);
+        let current_span = tracing::Span::current().clone();
+        let progress = Arc::new((Mutex::new(SealingProgress::default()), Condvar::new()));
+        let stage_progress = progress.clone();
+        let handle = std::thread::Builder::new().name(format!("Seal block {}", &thread_id)).spawn(
+            move || {
+                let _current_span_scope = current_span.enter();
+                let _stopped = StoppedOnDrop(stage_progress.clone());
+                while let Ok(job) = jobs_rx.recv() {
+                    seal(
+                        job,
+                        &producer_node_id,
+                        &repository,
+                        &mut shared_services,
+                        share_service.as_ref(),
+                        &produced_blocks,
+                    )?;
+                    let (progress, sealed) = &*stage_progress;
+                    progress.lock().pending -= 1;
+                    sealed.notify_all();
+                }
+                tracing::trace!("Sealing stage finished: {}", &thread_id);
+                Ok(())
+            },
+        )?;
+        Ok(Self { jobs_tx: Some(jobs_tx), handle: Some(handle), progress })
+    }
+
+    pub fn submit(&mut self, job: SealingJob) -> anyhow::Result<()> {
+        let Some(jobs_tx) = self.jobs_tx.as_ref() else {
+            anyhow::bail!("Sealing stage is already stopped");
+        };
+        self.progress.0.lock().pending += 1;
+        if jobs_tx.send(job).is_ok() {
+            return Ok(());
+        }
+        // Receiver is dropped only when the stage thread exits, get the reason
+        self.jobs_tx = None;
+        self.join()?;
+        anyhow::bail!("Sealing stage stopped unexpectedly")
+    }
+
+    // Waits until the submitted blocks are sealed, their cross-thread ref data
+    // is stored, while the stage keeps running.
+    pub fn wait_sealed(&self) -> anyhow::Result<()> {
+        let (progress, sealed) = &*self.progress;
+        let mut progress = progress.lock();
+        while progress.pending > 0 {
+            if progress.stopped {
+                anyhow::bail!("Sealing stage stopped with {} blocks not sealed", progress.pending);
+            }
+            sealed.wait(&mut progress);
+        }
+        Ok(())
+    }
+
+    // Waits until all submitted blocks are sealed.
+    pub fn finish(mut self) -> anyhow::Result<()> {
+        self.jobs_tx = None;
+        self.join()
+    }
+
+    fn join(&mut self) -> anyhow::Result<()> {
+        match self.handle.take() {
+            Some(handle) => {
+                handle.join().map_err(|_| anyhow::format_err!("Failed to join sealing thread"))?
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+fn seal(
+    job: SealingJob,
+    producer_node_id: &NodeIdentifier,
+    repository: &RepositoryImpl,
+    shared_services: &mut SharedServices,
+    share_service: Option<&ExternalFileSharesBased>,
+    produced_blocks: &Arc<Mutex<Vec<ProducedBlock>>>,
+) -> anyhow::Result<()> {
+    let SealingJob {
+        block,
+        optimistic_state,
+        cross_thread_ref_data,
+        feedbacks,
+        block_state,
+        share_state,
+    } = job;
+    trace_span!("store optimistic")
+        .in_scope(|| repository.store_optimistic_in_cache(optimistic_state.clone()))?;
+
+    if let (Some(share_service), true) = (share_service, share_state) {
+        let block_id = optimistic_state.block_id.clone();
+        let thread_id = optimistic_state.thread_id;
+        let state = repository
+            .get_full_optimistic_state(&block_id, &thread_id, Some(optimistic_state.clone()))?
+            .expect("Must be accessible");
+        share_service.save_state_for_sharing(state)?;
+    }
+
+    let span_save_cross_thread_refs =
+        trace_span!("save cross thread refs", refs_len = cross_thread_ref_data.refs().len());
+    shared_services.exec(|e| {
+        span_save_cross_thread_refs.in_scope(|| {
+            e.cross_thread_ref_data_service.set_cross_thread_ref_data(cross_thread_ref_data)
+        })
+    })?;
+    drop(span_save_cross_thread_refs);
+    let block_id = block.identifier();
+    if let Some(index) = shared_services.out_msg_queues.as_ref() {
+        if let Err(e) = index_out_msg_queues(index, &block, &optimistic_state) {
+            tracing::warn!("Failed to index out msg queues of {block_id:?}: {e}");
+        }
+    }
+    block_state.guarded_mut(|e| e.set_has_cross_thread_ref_data_prepared())?;
+
+    trace_span!("save state").in_scope(|| {
+        tracing::trace!("Save produced block");
+        let mut blocks = produced_blocks.lock();
+        let produced_data = ProducedBlock::builder()
+            .block(block)
+            .optimistic_state(optimistic_state)
+            .feedbacks(feedbacks)
+            .block_state(block_state)
+            .metrics_memento_init_time(None)
+            .build();
+        blocks.push(produced_data);
+    });
+    block_flow_trace("finish production", &block_id, producer_node_id, []);
+    Ok(())
+}
+
+fn index_out_msg_queues(
+    index: &OutMsgQueueIndex,
+    block: &AckiNackiBlock,
+    optimistic_state: &OptimisticStateImpl,
+) -> anyhow::Result<()> {
+    let mut queues = optimistic_state
+        .out_msg_queue_summary()?
+        .into_iter()
+        .map(|(dst_thread_id, (count, oldest_lt))| OutMsgQueueEntry {
+            dst_thread_id: format!("{dst_thread_id:x}"),
+            count,
+            oldest_lt,
+        })
+        .collect::<Vec<_>>();
+    queues.sort_by(|a, b| a.dst_thread_id.cmp(&b.dst_thread_id));
+    index.publish(OutMsgQueueRecord {
+        thread_id: format!("{:x}", block.get_common_section().thread_id),
+        block_id: block.identifier().to_string(),
+        block_seq_no: block.seq_no().into(),
+        queues,
+        timestamp: block.time()?,
+    });
+    Ok(())
+}
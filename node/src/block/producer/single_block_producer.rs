@@ -7,8 +7,9 @@ use std::collections::VecDeque;
 use std::sync::Arc;
 
 use http_server::ExtMsgFeedbackList;
+use http_server::ExtMsgQuarantine;
+use http_server::TxTraceRegistry;
 use telemetry_utils::mpsc::InstrumentedReceiver;
-use telemetry_utils::now_ms;
 use tracing::instrument;
 use tracing::trace_span;
 use tvm_block::GetRepresentationHash;
@@ -17,10 +18,15 @@ use tvm_types::Cell;
 use tvm_types::HashmapType;
 use typed_builder::TypedBuilder;
 
+use crate::block::producer::account_policy::apply_message_policy;
+use crate::block::producer::account_policy::MessagePolicy;
+use crate::block::producer::builder::build_actions::create_policy_rejection_feedback;
 use crate::block::producer::builder::ActiveThread;
 use crate::block::producer::builder::BlockBuilder;
+use crate::block::producer::errors::BlockProducerError;
 use crate::block::producer::execution_time::ExecutionTimeLimits;
 use crate::block::producer::wasm::WasmNodeCache;
+use crate::block_keeper_system::slash::SlashType;
 use crate::block_keeper_system::wallet_config::create_wallet_slash_message;
 use crate::block_keeper_system::BlockKeeperData;
 use crate::block_keeper_system::BlockKeeperSlashData;
@@ -44,11 +50,14 @@ use crate::repository::optimistic_state::OptimisticState;
 use crate::repository::optimistic_state::OptimisticStateImpl;
 use crate::repository::CrossThreadRefData;
 use crate::storage::MessageDurableStorage;
+use crate::types::extensions::MintedShell;
+use crate::types::extensions::ProducerBuild;
 use crate::types::AccountAddress;
 use crate::types::AckiNackiBlock;
 use crate::types::BlockIdentifier;
 use crate::types::BlockRound;
 use crate::types::ThreadIdentifier;
+use crate::utilities::clock::CLOCK;
 use crate::utilities::guarded::Guarded;
 use crate::utilities::guarded::GuardedMut;
 
@@ -71,15 +80,18 @@ pub trait BlockProducer {
         time_limits: &ExecutionTimeLimits,
         block_round: BlockRound,
         parent_block_state: BlockState,
-    ) -> anyhow::Result<(
-        AckiNackiBlock,
-        Self::OptimisticState,
-        Vec<(Cell, ActiveThread)>,
-        CrossThreadRefData,
-        Vec<Stamp>,
-        ExtMsgFeedbackList,
-        BlockState,
-    )>
+    ) -> Result<
+        (
+            AckiNackiBlock,
+            Self::OptimisticState,
+            Vec<(Cell, ActiveThread)>,
+            CrossThreadRefData,
+            Vec<Stamp>,
+            ExtMsgFeedbackList,
+            BlockState,
+        ),
+        BlockProducerError,
+    >
     where
         I: std::iter::Iterator<Item = &'a CrossThreadRefData> + Clone,
         CrossThreadRefData: 'a;
@@ -95,6 +107,9 @@ pub struct TVMBlockProducer {
     parallelization_level: usize,
     block_keeper_epoch_code_hash: String,
     block_keeper_preepoch_code_hash: String,
+    max_account_state_cells: u64,
+    #[builder(default)]
+    system_lane_budget_fraction: f64,
     epoch_block_keeper_data: Vec<BlockKeeperData>,
     shared_services: SharedServices,
     block_nack: Vec<Envelope<GoshBLS, NackData>>,
@@ -102,6 +117,14 @@ pub struct TVMBlockProducer {
     block_state_repository: BlockStateRepository,
     metrics: Option<BlockProductionMetrics>,
     wasm_cache: WasmNodeCache,
+    #[builder(default)]
+    tx_traces: Option<TxTraceRegistry>,
+    #[builder(default)]
+    ext_msg_quarantine: Option<ExtMsgQuarantine>,
+    #[builder(default)]
+    message_policy: Option<Arc<dyn MessagePolicy>>,
+    #[builder(default = DEFAULT_VERIFY_COMPLEXITY)]
+    verify_complexity: SignerIndex,
 }
 
 impl TVMBlockProducer {
@@ -132,74 +155,81 @@ impl BlockProducer for TVMBlockProducer {
         time_limits: &ExecutionTimeLimits,
         block_round: BlockRound,
         parent_block_state: BlockState,
-    ) -> anyhow::Result<(
-        AckiNackiBlock,
-        Self::OptimisticState,
-        Vec<(Cell, ActiveThread)>,
-        CrossThreadRefData,
-        Vec<Stamp>,
-        ExtMsgFeedbackList,
-        BlockState,
-    )>
+    ) -> Result<
+        (
+            AckiNackiBlock,
+            Self::OptimisticState,
+            Vec<(Cell, ActiveThread)>,
+            CrossThreadRefData,
+            Vec<Stamp>,
+            ExtMsgFeedbackList,
+            BlockState,
+        ),
+        BlockProducerError,
+    >
     where
         // TODO: remove Clone and change to Into<>
         I: std::iter::Iterator<Item = &'a CrossThreadRefData> + Clone,
         CrossThreadRefData: 'a,
     {
+        CLOCK.ensure_production_allowed().map_err(BlockProducerError::ClockSkew)?;
         let (initial_state, in_table, white_list_of_slashing_messages_hashes, forwarded_messages) =
-            trace_span!("pre processing").in_scope(|| {
-                tracing::trace!("Start production");
-                tracing::trace!(
-                    "Producing block for {}, parent_seq_no: {}, refs: {:?}",
-                    thread_identifier,
-                    parent_state.block_seq_no,
-                    refs.clone()
-                        .map(|e| (*e.block_thread_identifier(), *e.block_seq_no()))
-                        .collect::<Vec<_>>()
-                );
-                let mut wrapped_slash_messages = vec![];
-                let mut white_list_of_slashing_messages_hashes = HashSet::new();
-                trace_span!("nacks").in_scope(|| {
-                    for nack in self.block_nack.iter() {
-                        tracing::trace!("push nack into slash {:?}", nack);
-                        let reason = nack.data().reason.clone();
-                        if let Some((id, bls_key, addr)) =
-                            reason.get_node_data(self.block_state_repository.clone())
-                        {
-                            let epoch_nack_data = BlockKeeperSlashData {
-                                node_id: id,
-                                bls_pubkey: bls_key,
-                                addr,
-                                slash_type: 0,
-                            };
-                            let msg = create_wallet_slash_message(&epoch_nack_data)?;
-                            let wrapped_message = Arc::new(WrappedMessage { message: msg.clone() });
-                            wrapped_slash_messages.push(wrapped_message);
-                            white_list_of_slashing_messages_hashes.insert(msg.hash().unwrap());
+            trace_span!("pre processing")
+                .in_scope(|| {
+                    tracing::trace!("Start production");
+                    tracing::trace!(
+                        "Producing block for {}, parent_seq_no: {}, refs: {:?}",
+                        thread_identifier,
+                        parent_state.block_seq_no,
+                        refs.clone()
+                            .map(|e| (*e.block_thread_identifier(), *e.block_seq_no()))
+                            .collect::<Vec<_>>()
+                    );
+                    let mut wrapped_slash_messages = vec![];
+                    let mut white_list_of_slashing_messages_hashes = HashSet::new();
+                    trace_span!("nacks").in_scope(|| {
+                        for nack in self.block_nack.iter() {
+                            tracing::trace!("push nack into slash {:?}", nack);
+                            let reason = nack.data().reason.clone();
+                            if let Some((id, bls_key, addr)) =
+                                reason.get_node_data(self.block_state_repository.clone())
+                            {
+                                let epoch_nack_data = BlockKeeperSlashData {
+                                    node_id: id,
+                                    bls_pubkey: bls_key,
+                                    addr,
+                                    slash_type: SlashType::FullStake,
+                                };
+                                let msg = create_wallet_slash_message(&epoch_nack_data)?;
+                                let wrapped_message =
+                                    Arc::new(WrappedMessage { message: msg.clone() });
+                                wrapped_slash_messages.push(wrapped_message);
+                                white_list_of_slashing_messages_hashes.insert(msg.hash().unwrap());
+                            }
                         }
-                    }
-                    Ok::<_, anyhow::Error>(())
-                })?;
-                let cross_thread_ref_data_service = self
-                    .shared_services
-                    .exec(|container| container.cross_thread_ref_data_service.clone());
-                let preprocessing_result = crate::block::preprocessing::preprocess(
-                    parent_state,
-                    refs.clone(),
-                    &thread_identifier,
-                    &cross_thread_ref_data_service,
-                    wrapped_slash_messages,
-                    self.epoch_block_keeper_data.clone(),
-                    message_db.clone(),
-                    self.metrics.clone(),
-                )?;
-                Ok::<_, anyhow::Error>((
-                    preprocessing_result.state,
-                    preprocessing_result.threads_table,
-                    white_list_of_slashing_messages_hashes,
-                    preprocessing_result.redirected_messages,
-                ))
-            })?;
+                        Ok::<_, anyhow::Error>(())
+                    })?;
+                    let cross_thread_ref_data_service = self
+                        .shared_services
+                        .exec(|container| container.cross_thread_ref_data_service.clone());
+                    let preprocessing_result = crate::block::preprocessing::preprocess(
+                        parent_state,
+                        refs.clone(),
+                        &thread_identifier,
+                        &cross_thread_ref_data_service,
+                        wrapped_slash_messages,
+                        self.epoch_block_keeper_data.clone(),
+                        message_db.clone(),
+                        self.metrics.clone(),
+                    )?;
+                    Ok::<_, anyhow::Error>((
+                        preprocessing_result.state,
+                        preprocessing_result.threads_table,
+                        white_list_of_slashing_messages_hashes,
+                        preprocessing_result.redirected_messages,
+                    ))
+                })
+                .map_err(BlockProducerError::Preprocessing)?;
 
         let ref_ids: Vec<BlockIdentifier> =
             refs.into_iter().map(|ref_data| ref_data.block_identifier().clone()).collect();
@@ -209,13 +239,14 @@ impl BlockProducer for TVMBlockProducer {
         tracing::debug!(target: "node", "PARENT block: {:?}", initial_state.get_block_info());
         tracing::trace!(target: "node", "ref_ids: {:?}", ref_ids);
 
-        let time = now_ms();
+        let time = CLOCK.now_ms();
 
-        let producer = BlockBuilder::with_params(
+        let mut producer = BlockBuilder::with_params(
             thread_identifier,
             initial_state,
             time,
             block_gas_limit,
+            self.max_account_state_cells,
             None,
             Some(control_rx_stop),
             self.accounts,
@@ -225,17 +256,41 @@ impl BlockProducer for TVMBlockProducer {
             forwarded_messages,
             self.metrics.clone(),
             self.wasm_cache,
+            self.tx_traces,
         )
-        .map_err(|e| anyhow::format_err!("Failed to create block builder: {e}"))?;
-        let (mut prepared_block, processed_stamps, ext_message_feedbacks) = producer.build_block(
-            std::mem::take(&mut self.message_queue),
-            &self.blockchain_config,
-            active_threads,
-            None,
-            white_list_of_slashing_messages_hashes,
-            message_db.clone(),
-            time_limits,
-        )?;
+        .map_err(|e| {
+            BlockProducerError::Build(anyhow::format_err!("Failed to create block builder: {e}"))
+        })?;
+        producer.set_system_lane_budget_fraction(self.system_lane_budget_fraction);
+        producer.set_timer_contract(self.shared_services.timer_contract.clone());
+        producer.set_ext_msg_quarantine(self.ext_msg_quarantine);
+        let (applied_account_policy, policy_excluded) = match &self.message_policy {
+            Some(policy) => {
+                let (record, excluded) =
+                    apply_message_policy(policy.as_ref(), &mut self.message_queue);
+                (Some(record), excluded)
+            }
+            None => (None, vec![]),
+        };
+        let (mut prepared_block, mut processed_stamps, mut ext_message_feedbacks) = producer
+            .build_block(
+                std::mem::take(&mut self.message_queue),
+                &self.blockchain_config,
+                active_threads,
+                None,
+                white_list_of_slashing_messages_hashes,
+                message_db.clone(),
+                time_limits,
+            )
+            .map_err(BlockProducerError::Build)?;
+        // Excluded messages leave the queue with a feedback to the sender
+        for (stamp, message) in policy_excluded {
+            processed_stamps.push(stamp);
+            ext_message_feedbacks.push(
+                create_policy_rejection_feedback(message, &thread_identifier)
+                    .map_err(BlockProducerError::Build)?,
+            );
+        }
         tracing::trace!(target: "node", "block generated successfully");
         Self::print_block_info(&prepared_block.block);
 
@@ -305,22 +360,53 @@ impl BlockProducer for TVMBlockProducer {
                 e.set_block_round(block_round).expect("Failed to set round for the block state")
             });
 
+            let minted_shell = MintedShell(
+                prepared_block
+                    .dapp_minted_map
+                    .iter()
+                    .filter(|(_, minted)| **minted != 0)
+                    .map(|(dapp_id, minted)| (dapp_id.0.to_hex_string(), *minted))
+                    .collect(),
+            );
+            let mut block = AckiNackiBlock::new(
+                thread_identifier,
+                prepared_block.block,
+                self.producer_node_id,
+                prepared_block.tx_cnt,
+                prepared_block.block_keeper_set_changes,
+                self.verify_complexity,
+                ref_ids,
+                forward_table,
+                prepared_block.changed_dapp_ids,
+                block_round,
+                block_height,
+                #[cfg(feature = "monitor-accounts-number")]
+                prepared_block.accounts_number_diff,
+            );
+            let mut common_section = block.get_common_section().clone();
+            if let Some(applied_account_policy) = applied_account_policy {
+                common_section
+                    .extensions
+                    .insert(&applied_account_policy)
+                    .expect("Failed to set account policy extension");
+            }
+            if !minted_shell.0.is_empty() {
+                common_section
+                    .extensions
+                    .insert(&minted_shell)
+                    .expect("Failed to set minted shell extension");
+            }
+            common_section
+                .extensions
+                .insert(&ProducerBuild::current())
+                .expect("Failed to set producer build extension");
+            // Hash is updated when the producer service finalizes the common section
+            block
+                .set_common_section(common_section, false)
+                .expect("Failed to update common section");
+
             let res = (
-                AckiNackiBlock::new(
-                    thread_identifier,
-                    prepared_block.block,
-                    self.producer_node_id,
-                    prepared_block.tx_cnt,
-                    prepared_block.block_keeper_set_changes,
-                    DEFAULT_VERIFY_COMPLEXITY,
-                    ref_ids,
-                    forward_table,
-                    prepared_block.changed_dapp_ids,
-                    block_round,
-                    block_height,
-                    #[cfg(feature = "monitor-accounts-number")]
-                    prepared_block.accounts_number_diff,
-                ),
+                block,
                 new_state,
                 active_threads,
                 cross_thread_ref_data,
@@ -23,6 +23,7 @@ use typed_builder::TypedBuilder;
 use crate::block::producer::builder::BlockBuilder;
 use crate::block::producer::execution_time::ExecutionTimeLimits;
 use crate::block::producer::wasm::WasmNodeCache;
+use crate::block_keeper_system::slash::SlashType;
 use crate::block_keeper_system::wallet_config::create_wallet_slash_message;
 use crate::block_keeper_system::BlockKeeperData;
 use crate::block_keeper_system::BlockKeeperSlashData;
@@ -116,8 +117,12 @@ impl BlockVerifier for TVMBlockVerifier {
             if let Some((id, bls_key, addr)) =
                 reason.get_node_data(self.block_state_repository.clone())
             {
-                let epoch_nack_data =
-                    BlockKeeperSlashData { node_id: id, bls_pubkey: bls_key, addr, slash_type: 0 };
+                let epoch_nack_data = BlockKeeperSlashData {
+                    node_id: id,
+                    bls_pubkey: bls_key,
+                    addr,
+                    slash_type: SlashType::FullStake,
+                };
                 let msg = create_wallet_slash_message(&epoch_nack_data)?;
                 let wrapped_message = WrappedMessage { message: msg.clone() };
                 wrapped_slash_messages.push(Arc::new(wrapped_message));
@@ -214,22 +219,26 @@ impl BlockVerifier for TVMBlockVerifier {
 
         tracing::debug!(target: "node", "PARENT block: {:?}", preprocessing_result.state.get_block_info());
 
-        let producer = BlockBuilder::with_params(
+        let timer_contract = self.shared_services.timer_contract.clone();
+        let mut producer = BlockBuilder::with_params(
             thread_identifier,
             preprocessing_result.state,
             time,
             block_gas_limit,
+            self.node_config.global.max_account_state_cells,
             Some(rand_seed),
             None,
             self.accounts_repository.clone(),
             self.node_config.global.block_keeper_epoch_code_hash.clone(),
             self.node_config.global.block_keeper_preepoch_code_hash.clone(),
-            self.node_config.local.parallelization_level,
+            self.node_config.verification_parallelization_level(),
             preprocessing_result.redirected_messages,
             self.metrics,
             self.wasm_cache,
+            None,
         )
         .map_err(|e| anyhow::format_err!("Failed to create block builder: {e}"))?;
+        producer.set_timer_contract(timer_contract);
         let (verify_block, _, _) = producer.build_block(
             grouped_ext_messages,
             &self.blockchain_config,
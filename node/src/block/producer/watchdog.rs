@@ -0,0 +1,291 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::backtrace::Backtrace;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use http_server::ExtMsgQueueStatus;
+use http_server::ProductionStallFeed;
+use http_server::ProductionStallReport;
+use parking_lot::Mutex;
+
+use crate::config::ProductionWatchdogConfig;
+use crate::helper::metrics::BlockProductionMetrics;
+use crate::helper::SHUTDOWN_FLAG;
+use crate::repository::repository_impl::RepositoryImpl;
+use crate::repository::Repository;
+use crate::types::BlockSeqNo;
+use crate::types::ThreadIdentifier;
+
+const CHECK_INTERVAL: Duration = Duration::from_millis(100);
+// Repository reads that take longer are reported as not finished
+const REPOSITORY_PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+// Stalls are reported without the stack if the producer doesn't reach a
+// checkpoint in time
+const CAPTURE_TIMEOUT: Duration = Duration::from_millis(500);
+
+thread_local! {
+    static CURRENT_CAPTURE: RefCell<Option<Arc<StackCapture>>> = const { RefCell::new(None) };
+}
+
+/// Stack of the producer thread requested by the watchdog. The producer
+/// captures it itself at a checkpoint, interrupting the thread to unwind its
+/// stack isn't async-signal-safe.
+#[derive(Default)]
+struct StackCapture {
+    requested: AtomicBool,
+    stack: Mutex<Option<Vec<String>>>,
+}
+
+impl StackCapture {
+    fn wait(&self, timeout: Duration) -> Option<Vec<String>> {
+        let started = Instant::now();
+        loop {
+            if let Some(stack) = self.stack.lock().take() {
+                return Some(stack);
+            }
+            if started.elapsed() > timeout {
+                return None;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
+
+/// Captures the stack of the producer thread if the watchdog requested it.
+/// Called at the safe points of the production loops.
+pub fn checkpoint() {
+    CURRENT_CAPTURE.with(|capture| {
+        let capture = capture.borrow();
+        let Some(capture) = capture.as_ref() else {
+            return;
+        };
+        if capture.requested.swap(false, Ordering::Relaxed) {
+            let stack =
+                Backtrace::force_capture().to_string().lines().map(str::to_string).collect();
+            *capture.stack.lock() = Some(stack);
+        }
+    });
+}
+
+struct ProductionIteration {
+    started: Instant,
+    timeout: Duration,
+    parent_block_seq_no: BlockSeqNo,
+    capture: Arc<StackCapture>,
+    stalled: bool,
+}
+
+/// Registry of the running block production iterations, shared by the
+/// producer threads and the watchdog service.
+#[derive(Clone, Default)]
+pub struct ProductionWatchdog {
+    iterations: Arc<Mutex<HashMap<ThreadIdentifier, ProductionIteration>>>,
+}
+
+/// Marks the iteration as finished when dropped.
+pub struct IterationGuard {
+    watchdog: ProductionWatchdog,
+    thread_id: ThreadIdentifier,
+    capture: Arc<StackCapture>,
+}
+
+impl IterationGuard {
+    /// Must be called on the thread that runs the iteration, its stack is
+    /// captured at the next checkpoint if the iteration stalls.
+    pub fn attach_current_thread(&self) {
+        CURRENT_CAPTURE.with(|capture| *capture.borrow_mut() = Some(self.capture.clone()));
+    }
+}
+
+impl Drop for IterationGuard {
+    fn drop(&mut self) {
+        CURRENT_CAPTURE.with(|capture| {
+            let mut capture = capture.borrow_mut();
+            if capture.as_ref().is_some_and(|capture| Arc::ptr_eq(capture, &self.capture)) {
+                capture.take();
+            }
+        });
+        let Some(iteration) = self.watchdog.iterations.lock().remove(&self.thread_id) else {
+            return;
+        };
+        if iteration.stalled {
+            tracing::warn!(
+                "Stalled production iteration of thread {:?} finished in {:?}",
+                self.thread_id,
+                iteration.started.elapsed()
+            );
+        }
+    }
+}
+
+impl ProductionWatchdog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn start_iteration(
+        &self,
+        thread_id: ThreadIdentifier,
+        parent_block_seq_no: BlockSeqNo,
+        timeout: Duration,
+    ) -> IterationGuard {
+        let capture = Arc::new(StackCapture::default());
+        let iteration = ProductionIteration {
+            started: Instant::now(),
+            timeout,
+            parent_block_seq_no,
+            capture: capture.clone(),
+            stalled: false,
+        };
+        self.iterations.lock().insert(thread_id, iteration);
+        IterationGuard { watchdog: self.clone(), thread_id, capture }
+    }
+
+    /// Marks the iterations that run longer than `multiplier` timeouts as
+    /// stalled, requests their stacks and returns them. Each iteration is
+    /// returned once.
+    fn take_stalled(
+        &self,
+        multiplier: u32,
+    ) -> Vec<(ThreadIdentifier, ProductionStallReport, Arc<StackCapture>)> {
+        let mut iterations = self.iterations.lock();
+        let mut stalled = vec![];
+        for (thread_id, iteration) in iterations.iter_mut() {
+            let iteration_time = iteration.started.elapsed();
+            if iteration.stalled || iteration_time <= iteration.timeout * multiplier {
+                continue;
+            }
+            iteration.stalled = true;
+            iteration.capture.requested.store(true, Ordering::Relaxed);
+            stalled.push((
+                *thread_id,
+                ProductionStallReport {
+                    thread_id: format!("{thread_id:x}"),
+                    parent_block_seq_no: iteration.parent_block_seq_no.into(),
+                    iteration_time_ms: iteration_time.as_millis() as u64,
+                    production_timeout_ms: iteration.timeout.as_millis() as u64,
+                    stack: vec![],
+                    ext_msg_queue_depth: None,
+                    repository_latency_ms: BTreeMap::new(),
+                    timestamp: chrono::Utc::now().timestamp_millis() as u64,
+                },
+                iteration.capture.clone(),
+            ));
+        }
+        stalled
+    }
+}
+
+/// Measures the repository reads the production depends on. Reads are made
+/// on the repository readers, so a blocked repository doesn't block the
+/// watchdog.
+fn probe_repository(
+    repository: &RepositoryImpl,
+    thread_id: ThreadIdentifier,
+) -> BTreeMap<String, Option<u64>> {
+    let (tx, rx) = mpsc::channel();
+    let repository_clone = repository.clone();
+    repository.read_service().execute(
+        move || {
+            let started = Instant::now();
+            let last_finalized = repository_clone.select_thread_last_finalized_block(&thread_id);
+            let _ = tx.send(("last_finalized_block_id", started.elapsed()));
+            if let Ok(Some((block_id, _))) = last_finalized {
+                let started = Instant::now();
+                let _ = repository_clone.get_block_from_repo_or_archive(&block_id, &thread_id);
+                let _ = tx.send(("load_last_finalized_block", started.elapsed()));
+            }
+        },
+        |_| {},
+    );
+    let mut latencies = BTreeMap::from([
+        ("last_finalized_block_id".to_string(), None),
+        ("load_last_finalized_block".to_string(), None),
+    ]);
+    let deadline = Instant::now() + REPOSITORY_PROBE_TIMEOUT;
+    while let Ok((operation, latency)) =
+        rx.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+    {
+        latencies.insert(operation.to_string(), Some(latency.as_millis() as u64));
+    }
+    latencies
+}
+
+/// Reports production iterations that exceed the configured number of
+/// production timeouts with the producer stack, the external messages queue
+/// depth and the repository latencies.
+pub fn start_production_watchdog(
+    watchdog: ProductionWatchdog,
+    repository: RepositoryImpl,
+    ext_msg_queue: ExtMsgQueueStatus,
+    stalls: ProductionStallFeed,
+    config: ProductionWatchdogConfig,
+    metrics: Option<BlockProductionMetrics>,
+) -> anyhow::Result<()> {
+    if config.stall_timeout_multiplier == 0 {
+        tracing::info!("Production watchdog is disabled");
+        return Ok(());
+    }
+    loop {
+        if SHUTDOWN_FLAG.get() == Some(&true) {
+            return Ok(());
+        }
+        std::thread::sleep(CHECK_INTERVAL);
+        for (thread_id, mut report, capture) in
+            watchdog.take_stalled(config.stall_timeout_multiplier)
+        {
+            report.stack = capture.wait(CAPTURE_TIMEOUT).unwrap_or_else(|| {
+                tracing::warn!("Producer of thread {thread_id:?} didn't reach a stack checkpoint");
+                vec![]
+            });
+            report.ext_msg_queue_depth = ext_msg_queue
+                .snapshot()
+                .into_iter()
+                .find(|stats| stats.thread_id == report.thread_id)
+                .map(|stats| stats.depth);
+            report.repository_latency_ms = probe_repository(&repository, thread_id);
+            tracing::error!("Block production stalled: {report:?}");
+            metrics.as_ref().inspect(|m| m.report_production_stall(&thread_id));
+            stalls.publish(report);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stalled_iteration_is_reported_once() {
+        let watchdog = ProductionWatchdog::new();
+        let thread_id = ThreadIdentifier::default();
+        let guard = watchdog.start_iteration(thread_id, BlockSeqNo::from(7), Duration::ZERO);
+        guard.attach_current_thread();
+        std::thread::sleep(Duration::from_millis(5));
+        // Not requested yet
+        checkpoint();
+        assert!(guard.capture.stack.lock().is_none());
+
+        let stalled = watchdog.take_stalled(10);
+        assert_eq!(stalled.len(), 1);
+        let (stalled_thread_id, report, capture) = &stalled[0];
+        assert_eq!(stalled_thread_id, &thread_id);
+        assert_eq!(report.parent_block_seq_no, 7);
+        assert!(watchdog.take_stalled(10).is_empty());
+
+        checkpoint();
+        assert!(!capture.wait(Duration::ZERO).unwrap().is_empty());
+
+        drop(guard);
+        assert!(watchdog.iterations.lock().is_empty());
+    }
+}
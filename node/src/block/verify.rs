@@ -7,6 +7,9 @@ use tvm_block::HashmapAugType;
 use tvm_executor::BlockchainConfig;
 use tvm_types::UInt256;
 
+use crate::block::producer::account_policy::verify_applied_policy;
+use crate::block::producer::account_policy::AccountListPolicy;
+use crate::block::producer::account_policy::MessagePolicy;
 use crate::block::producer::errors::VerifyError;
 use crate::block::producer::errors::BP_DID_NOT_PROCESS_ALL_MESSAGES_FROM_PREVIOUS_BLOCK;
 use crate::block::producer::wasm::WasmNodeCache;
@@ -23,6 +26,8 @@ use crate::repository::accounts::AccountsRepository;
 use crate::repository::optimistic_state::OptimisticStateImpl;
 use crate::repository::CrossThreadRefData;
 use crate::storage::MessageDurableStorage;
+use crate::types::extensions::AppliedAccountPolicy;
+use crate::types::extensions::EXTENSION_REGISTRY;
 use crate::types::AckiNackiBlock;
 use crate::types::BlockInfo;
 
@@ -49,6 +54,19 @@ pub fn verify_block(
         block_candidate.seq_no()
     );
 
+    EXTENSION_REGISTRY.check(&block_candidate.get_common_section().extensions)?;
+
+    let local_policy = AccountListPolicy::from_config(&node_config.local.account_policy)?;
+    let applied_policy =
+        block_candidate.get_common_section().extensions.get::<AppliedAccountPolicy>()?;
+    if !verify_applied_policy(
+        local_policy.as_ref().map(|policy| policy as &dyn MessagePolicy),
+        applied_policy.as_ref(),
+        block_candidate.tvm_block(),
+    )? {
+        return Ok(false);
+    }
+
     let producer = TVMBlockVerifier::builder()
         .blockchain_config(blockchain_config)
         .node_config(node_config.clone())
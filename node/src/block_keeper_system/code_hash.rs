@@ -0,0 +1,179 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::sync::Arc;
+
+use ext_messages_auth::root_contracts::BK_CONTRACT_ROOT_ABI;
+use ext_messages_auth::root_contracts::BK_CONTRACT_ROOT_ADDR;
+use parking_lot::Mutex;
+use serde::Deserialize;
+use tvm_block::Account;
+use tvm_block::Serializable;
+use tvm_client::abi::Abi;
+use tvm_client::ClientConfig;
+use tvm_client::ClientContext;
+use tvm_types::base64_encode;
+
+use crate::config::GlobalConfig;
+use crate::config::DEFAULT_BLOCK_KEEPER_EPOCH_CODE_HASH;
+use crate::config::DEFAULT_BLOCK_KEEPER_PREEPOCH_CODE_HASH;
+use crate::helper::account_boc_loader::get_account_from_shard_state;
+use crate::repository::optimistic_state::OptimisticState;
+use crate::repository::repository_impl::RepositoryImpl;
+use crate::zerostate::ZeroState;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EpochCodeHash {
+    epoch_code_hash: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PreEpochCodeHash {
+    pre_epoch_code_hash: String,
+}
+
+/// Code hashes of the epoch contracts deployed by the block keeper root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpochCodeHashes {
+    pub epoch: String,
+    pub preepoch: String,
+}
+
+impl EpochCodeHashes {
+    /// Runs the code hash getters of the block keeper root account.
+    pub async fn from_root_account(root: &Account) -> anyhow::Result<Self> {
+        let boc = base64_encode(
+            root.write_to_bytes()
+                .map_err(|e| anyhow::format_err!("Failed to serialize root account: {e}"))?,
+        );
+        let context = Arc::new(ClientContext::new(ClientConfig::default())?);
+        let contract = sdk_wrapper::Account::try_new_with_abi(
+            Abi::Json(BK_CONTRACT_ROOT_ABI.to_string()),
+            None,
+            None,
+            Some(&format!("0:{}", root_address())),
+        )
+        .await?;
+        let epoch: EpochCodeHash = serde_json::from_value(
+            contract.run_local(&context, "getEpochCodeHash", None, Some(boc.clone())).await?,
+        )?;
+        let preepoch: PreEpochCodeHash = serde_json::from_value(
+            contract.run_local(&context, "getPreEpochCodeHash", None, Some(boc)).await?,
+        )?;
+        Ok(Self {
+            epoch: normalize(&epoch.epoch_code_hash)?,
+            preepoch: normalize(&preepoch.pre_epoch_code_hash)?,
+        })
+    }
+}
+
+// Account lookups take the address without the workchain
+fn root_address() -> String {
+    BK_CONTRACT_ROOT_ADDR.to_string()
+}
+
+// Getters return uint256 as a hex string which may be not zero padded
+fn normalize(hash: &str) -> anyhow::Result<String> {
+    let hash = hash.trim_start_matches("0x");
+    anyhow::ensure!(hash.len() <= 64, "Invalid code hash: {hash}");
+    Ok(format!("{hash:0>64}"))
+}
+
+/// Loads the block keeper root account from the last finalized state or from
+/// the zerostate if the node has no finalized state yet.
+fn load_root_account(
+    repository: &RepositoryImpl,
+    zerostate: &ZeroState,
+) -> anyhow::Result<Account> {
+    let address = root_address();
+    match get_account_from_shard_state(Arc::new(Mutex::new(repository.clone())), &address) {
+        Ok((account, _)) => return Ok(account),
+        Err(e) => tracing::debug!("Block keeper root is not found in the finalized state: {e}"),
+    }
+    let account_id = tvm_types::AccountId::from_string(&address)
+        .map_err(|e| anyhow::format_err!("Invalid root address: {e}"))?;
+    for state in zerostate.states().values() {
+        let accounts = state
+            .get_shard_state()
+            .read_accounts()
+            .map_err(|e| anyhow::format_err!("Failed to read zerostate accounts: {e}"))?;
+        let Some(account) = accounts
+            .account(&account_id)
+            .map_err(|e| anyhow::format_err!("Failed to read zerostate account: {e}"))?
+        else {
+            continue;
+        };
+        return account
+            .read_account()
+            .and_then(|account| account.as_struct())
+            .map_err(|e| anyhow::format_err!("Failed to read zerostate account: {e}"));
+    }
+    anyhow::bail!("Block keeper root {address} is not found in the zerostate")
+}
+
+/// Resolves the epoch code hashes from the block keeper root of the state, so
+/// they follow the contract upgrades. Hashes set in the config override the
+/// resolved ones. If the root can't be resolved, unset hashes fall back to
+/// the defaults.
+pub async fn resolve_epoch_code_hashes(
+    config: &mut GlobalConfig,
+    repository: &RepositoryImpl,
+    zerostate: &ZeroState,
+) -> anyhow::Result<()> {
+    let resolved = match load_root_account(repository, zerostate) {
+        Ok(root) => EpochCodeHashes::from_root_account(&root).await,
+        Err(e) => Err(e),
+    };
+    let resolved = match resolved {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            tracing::warn!(
+                "Failed to resolve the epoch code hashes, configured or default ones are used: {e}"
+            );
+            for (configured, default) in [
+                (&mut config.block_keeper_epoch_code_hash, DEFAULT_BLOCK_KEEPER_EPOCH_CODE_HASH),
+                (
+                    &mut config.block_keeper_preepoch_code_hash,
+                    DEFAULT_BLOCK_KEEPER_PREEPOCH_CODE_HASH,
+                ),
+            ] {
+                if configured.is_empty() {
+                    *configured = default.to_string();
+                }
+            }
+            return Ok(());
+        }
+    };
+    tracing::info!("Resolved epoch code hashes: {resolved:?}");
+    for (configured, resolved, name) in [
+        (&mut config.block_keeper_epoch_code_hash, resolved.epoch, "epoch"),
+        (&mut config.block_keeper_preepoch_code_hash, resolved.preepoch, "preepoch"),
+    ] {
+        if configured.is_empty() {
+            *configured = resolved;
+        } else if *configured != resolved {
+            tracing::warn!(
+                "Configured {name} code hash {configured} overrides the resolved one {resolved}"
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_code_hash() {
+        assert_eq!(
+            normalize("0xad2647fa7fe0540f656b9fc137f0bcfc18fc7750c0197e789230f8e28c437df6")
+                .unwrap(),
+            "ad2647fa7fe0540f656b9fc137f0bcfc18fc7750c0197e789230f8e28c437df6"
+        );
+        assert_eq!(normalize("0x1f").unwrap(), format!("{}1f", "0".repeat(62)));
+        assert!(normalize(&"1".repeat(65)).is_err());
+    }
+}
@@ -16,6 +16,7 @@ use serde::Deserializer;
 use serde::Serialize;
 use serde::Serializer;
 
+use crate::block_keeper_system::slash::SlashType;
 use crate::bls::gosh_bls::PubKey;
 use crate::node::NodeIdentifier;
 use crate::node::SignerIndex;
@@ -23,7 +24,9 @@ use crate::types::AccountAddress;
 
 pub mod abi;
 pub mod bk_set;
+pub mod code_hash;
 pub mod epoch;
+pub mod slash;
 pub mod wallet_config;
 
 #[derive(Clone, Serialize, Deserialize, Debug, Eq, PartialEq)]
@@ -211,7 +214,7 @@ pub struct BlockKeeperSlashData {
     pub node_id: NodeIdentifier,
     pub bls_pubkey: PubKey,
     pub addr: AccountAddress,
-    pub slash_type: u8,
+    pub slash_type: SlashType,
 }
 
 impl Debug for BlockKeeperSlashData {
@@ -0,0 +1,177 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::str::FromStr;
+
+use tvm_block::messages::InternalMessageHeader;
+use tvm_block::CurrencyCollection;
+use tvm_block::Grams;
+use tvm_block::Message;
+use tvm_block::MsgAddressInt;
+use tvm_types::SliceData;
+
+use crate::block_keeper_system::abi::BLOCK_KEEPER_WALLET_ABI;
+use crate::bls::gosh_bls::PubKey;
+use crate::types::AccountAddress;
+
+/// Value attached to the slash message, covers the wallet execution fees.
+pub const SLASH_MESSAGE_VALUE: u64 = 100_000_000;
+
+/// Function id of the wallet `slash(uint8,bytes)` method.
+pub const SLASH_FUNCTION_ID: u32 = 0x19b2dfc7;
+
+/// Slash type as it is passed to the block keeper wallet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SlashType {
+    /// Slash the whole stake, encoded as 0.
+    FullStake,
+    /// Slash the percent (1..=99) of the stake.
+    Partial(u8),
+}
+
+impl SlashType {
+    pub fn code(&self) -> u8 {
+        match self {
+            SlashType::FullStake => 0,
+            SlashType::Partial(percent) => *percent,
+        }
+    }
+
+    pub fn from_code(code: u8) -> anyhow::Result<Self> {
+        match code {
+            0 => Ok(SlashType::FullStake),
+            // The wallet rejects slash types greater than 99
+            1..=99 => Ok(SlashType::Partial(code)),
+            _ => anyhow::bail!("Invalid slash type: {code}, expected 0 (full stake) or 1..=99"),
+        }
+    }
+}
+
+impl Display for SlashType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SlashType::FullStake => write!(f, "full"),
+            SlashType::Partial(percent) => write!(f, "{percent}"),
+        }
+    }
+}
+
+/// Parses "full" or a slash percent.
+impl FromStr for SlashType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(SlashType::FullStake),
+            percent => match percent.parse::<u8>()? {
+                0 => anyhow::bail!("Slash percent must be in 1..=99, use \"full\" instead of 0"),
+                percent => Self::from_code(percent),
+            },
+        }
+    }
+}
+
+/// Encodes the body of the wallet `slash` call.
+pub fn slash_message_body(slash_type: SlashType, bls_pubkey: &PubKey) -> anyhow::Result<SliceData> {
+    let parameters = format!(
+        r#"{{"slash_type": {}, "bls_key": "{}"}}"#,
+        slash_type.code(),
+        hex::encode(bls_pubkey.as_ref().to_bytes())
+    );
+    let msg_body = tvm_abi::encode_function_call(
+        BLOCK_KEEPER_WALLET_ABI,
+        "slash",
+        None,
+        &parameters,
+        true,
+        None,
+        None,
+    )
+    .map_err(|e| anyhow::format_err!("Failed to create message body: {e}"))?;
+    SliceData::load_cell(
+        msg_body
+            .into_cell()
+            .map_err(|e| anyhow::format_err!("Failed serialize message body: {e}"))?,
+    )
+    .map_err(|e| anyhow::format_err!("Failed to serialize message body: {e}"))
+}
+
+/// Builds the internal message that slashes the owner of the BLS key. The
+/// message is sent by the wallet to itself, producers add it to the block as
+/// the evidence of the confirmed nack.
+pub fn build_slash_message(
+    wallet: &AccountAddress,
+    slash_type: SlashType,
+    bls_pubkey: &PubKey,
+) -> anyhow::Result<Message> {
+    tracing::trace!("create Slash message: {wallet:?}");
+    let body = slash_message_body(slash_type, bls_pubkey)?;
+    let wallet_addr = MsgAddressInt::with_standart(None, 0, wallet.into())
+        .map_err(|e| anyhow::format_err!("Failed to get addr: {e}"))?;
+    let header = InternalMessageHeader::with_addresses(
+        wallet_addr.clone(),
+        wallet_addr,
+        CurrencyCollection::from_grams(Grams::from(SLASH_MESSAGE_VALUE)),
+    );
+    Ok(Message::with_int_header_and_body(header, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WALLET: &str = "0101010101010101010101010101010101010101010101010101010101010101";
+    // Hex of `PubKey::default()` in tests
+    const BLS_KEY: &str = "a695ad325dfc7e1191fbc9f186f58eff42a634029731b18380ff89bf42c464a42cb8ca55b200f051f57f1e1893c68759";
+
+    #[test]
+    fn test_slash_type_codes() {
+        assert_eq!(SlashType::from_code(0).unwrap(), SlashType::FullStake);
+        assert_eq!(SlashType::from_code(1).unwrap(), SlashType::Partial(1));
+        assert_eq!(SlashType::from_code(99).unwrap(), SlashType::Partial(99));
+        assert!(SlashType::from_code(100).is_err());
+        assert!(SlashType::from_code(255).is_err());
+
+        assert_eq!("full".parse::<SlashType>().unwrap(), SlashType::FullStake);
+        assert_eq!("50".parse::<SlashType>().unwrap(), SlashType::Partial(50));
+        assert!("0".parse::<SlashType>().is_err());
+        assert!("100".parse::<SlashType>().is_err());
+        assert!("half".parse::<SlashType>().is_err());
+        for slash_type in [SlashType::FullStake, SlashType::Partial(1), SlashType::Partial(99)] {
+            assert_eq!(slash_type.to_string().parse::<SlashType>().unwrap(), slash_type);
+        }
+    }
+
+    #[test]
+    fn test_slash_message_vectors() {
+        let wallet = AccountAddress::from_str(WALLET).unwrap();
+        let bls_key = PubKey::from_str(BLS_KEY).unwrap();
+        assert_eq!(hex::encode(bls_key.as_ref().to_bytes()), BLS_KEY);
+
+        for (slash_type, code) in
+            [(SlashType::FullStake, 0u8), (SlashType::Partial(1), 1), (SlashType::Partial(99), 99)]
+        {
+            let message = build_slash_message(&wallet, slash_type, &bls_key).unwrap();
+            assert!(message.is_internal());
+            assert_eq!(
+                message.src().map(|src| AccountAddress::from(src.address())),
+                Some(wallet.clone())
+            );
+            assert_eq!(
+                message.int_dst_account_id().map(AccountAddress::from),
+                Some(wallet.clone())
+            );
+            assert_eq!(message.get_value().unwrap().grams, Grams::from(SLASH_MESSAGE_VALUE));
+
+            // Body layout: function id, slash type, reference to the key bytes
+            let mut body = message.body().unwrap();
+            assert_eq!(body.get_next_u32().unwrap(), SLASH_FUNCTION_ID);
+            assert_eq!(body.get_next_byte().unwrap(), code);
+            assert_eq!(body.remaining_bits(), 0);
+            let key_cell = body.reference(0).unwrap();
+            assert_eq!(hex::encode(key_cell.data()), BLS_KEY);
+        }
+    }
+}
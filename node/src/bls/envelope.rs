@@ -13,6 +13,7 @@ use serde::Deserializer;
 use serde::Serialize;
 use serde::Serializer;
 
+use crate::bls::metrics::report_envelope_verification;
 use crate::bls::BLSSignatureScheme;
 use crate::node::SignerIndex;
 
@@ -108,6 +109,7 @@ where
         &self,
         signers: &HashMap<Self::SignerIndex, BLS::PubKey>,
     ) -> anyhow::Result<bool> {
+        let start = std::time::Instant::now();
         let mut pubkeys_occurrences = Vec::<(BLS::PubKey, usize)>::new();
         let mut is_any_signature_exists = false;
         for signer_index in self.signature_occurrences.keys() {
@@ -124,6 +126,7 @@ where
                         signers.keys(),
                         self.signature_occurrences.keys(),
                     );
+                    report_envelope_verification::<TData>(start.elapsed(), false);
                     return Ok(false);
                 }
                 Some(pubkey) => {
@@ -134,6 +137,7 @@ where
         }
 
         if !is_any_signature_exists {
+            report_envelope_verification::<TData>(start.elapsed(), false);
             return Ok(false);
         }
 
@@ -148,6 +152,7 @@ where
             );
             tracing::trace!("Verify signature: {:?}", pubkeys_occurrences);
         }
+        report_envelope_verification::<TData>(start.elapsed(), is_valid);
         Ok(is_valid)
     }
 
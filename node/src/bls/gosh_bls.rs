@@ -13,6 +13,7 @@ use serde::Deserialize;
 use serde::Serialize;
 use serde_with::serde_as;
 
+use crate::bls::metrics::report_operation;
 use crate::bls::BLSSignatureScheme;
 pub const DST: [u8; 43] = *b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_NUL_";
 
@@ -23,16 +24,20 @@ impl GoshBLS {
     pub fn merge_all(
         signatures: &[<GoshBLS as BLSSignatureScheme>::Signature],
     ) -> anyhow::Result<<GoshBLS as BLSSignatureScheme>::Signature> {
+        let start = std::time::Instant::now();
         let mut sig_refs: Vec<&gosh_blst::min_pk::Signature> = Vec::new();
         for sig in signatures {
             sig_refs.push(&sig.0);
         }
-        match gosh_blst::min_pk::AggregateSignature::aggregate(sig_refs.as_slice(), true) {
-            Ok(agg) => std::result::Result::Ok(Signature(agg.to_signature())),
-            Err(err) => {
-                Err(anyhow::anyhow!("BLS signatures inner merge process has failed: {:?}", err))
-            }
-        }
+        let result =
+            match gosh_blst::min_pk::AggregateSignature::aggregate(sig_refs.as_slice(), true) {
+                Ok(agg) => std::result::Result::Ok(Signature(agg.to_signature())),
+                Err(err) => {
+                    Err(anyhow::anyhow!("BLS signatures inner merge process has failed: {:?}", err))
+                }
+            };
+        report_operation("merge", start.elapsed());
+        result
     }
 }
 
@@ -48,6 +53,10 @@ impl Default for Signature {
 }
 
 impl Signature {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes().to_vec()
+    }
+
     #[cfg(test)]
     pub fn empty() -> Self {
         use gosh_blst::BLS_SIG_LEN;
@@ -183,9 +192,12 @@ impl BLSSignatureScheme for GoshBLS {
         secret: &Self::Secret,
         data: &TData,
     ) -> anyhow::Result<Self::Signature> {
+        let start = std::time::Instant::now();
         let buffer = bincode::serialize(&data)?;
         tracing::trace!("Sign data: {:?}", secret);
-        anyhow::Ok(Signature(secret.0.sign(&buffer, &DST, &[])))
+        let signature = Signature(secret.0.sign(&buffer, &DST, &[]));
+        report_operation("sign", start.elapsed());
+        anyhow::Ok(signature)
     }
 
     fn verify<TData: Serialize>(
@@ -193,7 +205,6 @@ impl BLSSignatureScheme for GoshBLS {
         pubkeys_occurrences: &mut dyn Iterator<Item = &(Self::PubKey, usize)>,
         data: &TData,
     ) -> anyhow::Result<bool> {
-        #[cfg(feature = "timing")]
         let start = std::time::Instant::now();
         #[cfg(feature = "timing")]
         tracing::trace!("signature verification: start");
@@ -227,16 +238,19 @@ impl BLSSignatureScheme for GoshBLS {
         );
         #[cfg(feature = "timing")]
         tracing::trace!("signature verification: finish: {}", start.elapsed().as_millis());
+        report_operation("verify", start.elapsed());
         Ok(is_valid == gosh_blst::BLST_ERROR::BLST_SUCCESS)
     }
 
     fn merge(one: &Self::Signature, another: &Self::Signature) -> anyhow::Result<Self::Signature> {
+        let start = std::time::Instant::now();
         let mut agg_sig = gosh_blst::min_pk::AggregateSignature::from_signature(&one.0);
         gosh_blst::min_pk::AggregateSignature::add_signature(&mut agg_sig, &another.0, false)
             .map_err(|e| -> anyhow::Error {
                 anyhow::anyhow!("BLS signatures inner merge process has failed: {:?}", e)
             })?;
 
+        report_operation("merge", start.elapsed());
         Ok(Signature(agg_sig.to_signature()))
     }
 }
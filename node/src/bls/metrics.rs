@@ -0,0 +1,91 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use opentelemetry::metrics::Counter;
+use opentelemetry::metrics::Histogram;
+use opentelemetry::metrics::Meter;
+use opentelemetry::KeyValue;
+
+// BLS operations are called from static contexts without access to the node
+// metrics, so they report to the global instance set on metrics init.
+static BLS_METRICS: OnceLock<BlsMetrics> = OnceLock::new();
+
+// Latency boundaries in microseconds
+const LATENCY_BOUNDARIES: [f64; 12] =
+    [10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2000.0, 5000.0, 10000.0, 50000.0, 100000.0];
+
+pub struct BlsMetrics {
+    operations: Counter<u64>,
+    operation_time: Histogram<u64>,
+    envelope_verifications: Counter<u64>,
+    envelope_verification_time: Histogram<u64>,
+}
+
+impl BlsMetrics {
+    pub fn init(meter: &Meter) {
+        let _ = BLS_METRICS.set(Self {
+            operations: meter.u64_counter("node_bls_operations").build(),
+            operation_time: meter
+                .u64_histogram("node_bls_operation_time")
+                .with_unit("us")
+                .with_boundaries(LATENCY_BOUNDARIES.to_vec())
+                .build(),
+            envelope_verifications: meter.u64_counter("node_envelope_verifications").build(),
+            envelope_verification_time: meter
+                .u64_histogram("node_envelope_verification_time")
+                .with_unit("us")
+                .with_boundaries(LATENCY_BOUNDARIES.to_vec())
+                .build(),
+        });
+    }
+}
+
+/// Reports `sign`, `verify` or `merge` call of the signature scheme.
+pub(crate) fn report_operation(operation: &'static str, elapsed: Duration) {
+    if let Some(metrics) = BLS_METRICS.get() {
+        let attrs = [KeyValue::new("operation", operation)];
+        metrics.operations.add(1, &attrs);
+        metrics.operation_time.record(elapsed.as_micros() as u64, &attrs);
+    }
+}
+
+pub(crate) fn report_envelope_verification<TData>(elapsed: Duration, is_valid: bool) {
+    if let Some(metrics) = BLS_METRICS.get() {
+        let attrs = [
+            KeyValue::new("envelope_type", envelope_type::<TData>()),
+            KeyValue::new("is_valid", is_valid),
+        ];
+        metrics.envelope_verifications.add(1, &attrs);
+        metrics.envelope_verification_time.record(elapsed.as_micros() as u64, &attrs);
+    }
+}
+
+fn envelope_type<TData>() -> &'static str {
+    let type_name = std::any::type_name::<TData>();
+    match type_name.rsplit("::").next().unwrap_or(type_name) {
+        "AckiNackiBlock" => "block",
+        "AttestationData" => "attestation",
+        "AckData" => "ack",
+        "NackData" => "nack",
+        _ => "other",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::associated_types::AckData;
+    use crate::node::associated_types::NackData;
+    use crate::types::AckiNackiBlock;
+
+    #[test]
+    fn test_envelope_type() {
+        assert_eq!(envelope_type::<AckiNackiBlock>(), "block");
+        assert_eq!(envelope_type::<AckData>(), "ack");
+        assert_eq!(envelope_type::<NackData>(), "nack");
+        assert_eq!(envelope_type::<u32>(), "other");
+    }
+}
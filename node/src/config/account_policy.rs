@@ -0,0 +1,34 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// How the account list of the policy is applied to external messages.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountPolicyMode {
+    /// All messages are included
+    #[default]
+    Disabled,
+    /// Only messages between the listed accounts are included
+    Allowlist,
+    /// Messages to or from the listed accounts are excluded
+    Denylist,
+}
+
+/// Account policy applied by the block producer to external messages.
+/// Blocks produced with an enabled policy record it in the common section
+/// along with the excluded messages.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct AccountPolicyConfig {
+    /// Defaults to disabled
+    #[serde(default)]
+    pub mode: AccountPolicyMode,
+
+    /// Account addresses in the hex form, optionally with the workchain
+    /// prefix (`0:<hex>`).
+    /// Defaults to empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub accounts: Vec<String>,
+}
@@ -1,7 +1,16 @@
 use std::path::PathBuf;
+use std::sync::Arc;
 
+use parking_lot::Mutex;
 use serde_json::Value;
+use tvm_block::ConfigParams;
 use tvm_executor::BlockchainConfig;
+use tvm_types::UInt256;
+
+use crate::repository::accounts::AccountsRepository;
+use crate::repository::optimistic_state::OptimisticState;
+use crate::repository::optimistic_state::OptimisticStateImpl;
+use crate::types::AccountAddress;
 
 pub fn load_blockchain_config(path: &PathBuf) -> anyhow::Result<BlockchainConfig> {
     let json = std::fs::read_to_string(path).unwrap_or_else(|_| {
@@ -14,3 +23,219 @@ pub fn load_blockchain_config(path: &PathBuf) -> anyhow::Result<BlockchainConfig
     BlockchainConfig::with_config(config_params)
         .map_err(|e| anyhow::format_err!("Failed to create blockchain config: {e}"))
 }
+
+/// Selects blockchain config for a block built on top of the given state.
+/// Config is read from the config account of the state starting from the
+/// activation seq_no, so all nodes switch to it on the same block. The file
+/// config is used before that and when the account can't be read.
+#[derive(Clone)]
+pub struct BlockchainConfigSource {
+    file_config: Arc<BlockchainConfig>,
+    config_account: Option<AccountAddress>,
+    activation_seq_no: u32,
+    // Config parsed from the account, keyed by the account last transaction hash
+    account_config: Arc<Mutex<Option<(UInt256, Arc<BlockchainConfig>)>>>,
+}
+
+impl From<BlockchainConfig> for BlockchainConfigSource {
+    fn from(file_config: BlockchainConfig) -> Self {
+        Self::new(file_config, None, 0)
+    }
+}
+
+impl BlockchainConfigSource {
+    pub fn new(
+        file_config: BlockchainConfig,
+        config_account: Option<AccountAddress>,
+        activation_seq_no: u32,
+    ) -> Self {
+        Self {
+            file_config: Arc::new(file_config),
+            config_account,
+            activation_seq_no,
+            account_config: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// The file config is used only without the config account or before the
+    /// activation seq no. After the activation all nodes must execute with the
+    /// account config, so failing to read it is an error.
+    pub fn for_state(
+        &self,
+        state: &OptimisticStateImpl,
+        accounts_repo: &AccountsRepository,
+    ) -> anyhow::Result<Arc<BlockchainConfig>> {
+        let Some(config_account) = &self.config_account else {
+            return Ok(self.file_config.clone());
+        };
+        let seq_no: u32 = (*state.get_block_seq_no()).into();
+        if seq_no < self.activation_seq_no {
+            return Ok(self.file_config.clone());
+        }
+        self.read_account_config(state, accounts_repo, config_account)
+            .map_err(|e| anyhow::format_err!("Failed to read blockchain config from account: {e}"))
+    }
+
+    fn read_account_config(
+        &self,
+        state: &OptimisticStateImpl,
+        accounts_repo: &AccountsRepository,
+        config_account: &AccountAddress,
+    ) -> anyhow::Result<Arc<BlockchainConfig>> {
+        let accounts = state
+            .get_shard_state()
+            .read_accounts()
+            .map_err(|e| anyhow::format_err!("Failed to read shard accounts: {e}"))?;
+        let mut shard_account = accounts
+            .account(&config_account.into())
+            .map_err(|e| anyhow::format_err!("Failed to read config account: {e}"))?
+            .ok_or_else(|| anyhow::format_err!("Config account is not in the state"))?;
+        let last_trans_hash = shard_account.last_trans_hash().clone();
+        if let Some((hash, config)) = self.account_config.lock().as_ref() {
+            if hash == &last_trans_hash {
+                return Ok(config.clone());
+            }
+        }
+        if shard_account.is_external() {
+            let account_root = match state.cached_accounts.get(config_account) {
+                Some((_, account_root)) => account_root.clone(),
+                None => accounts_repo.load_account(
+                    config_account,
+                    &last_trans_hash,
+                    shard_account.last_trans_lt(),
+                )?,
+            };
+            shard_account.set_account_cell(account_root);
+        }
+        let account = shard_account
+            .read_account()
+            .and_then(|account| account.as_struct())
+            .map_err(|e| anyhow::format_err!("Failed to read config account: {e}"))?;
+        // Config contract keeps config params dictionary in the first reference of its data
+        let config_params_root = account
+            .get_data()
+            .and_then(|data| data.reference(0).ok())
+            .ok_or_else(|| anyhow::format_err!("Config account has no config params"))?;
+        let config_params = ConfigParams::with_address_and_params(
+            config_account.0.clone(),
+            Some(config_params_root),
+        );
+        let config = Arc::new(
+            BlockchainConfig::with_config(config_params)
+                .map_err(|e| anyhow::format_err!("Failed to create blockchain config: {e}"))?,
+        );
+        tracing::info!("Blockchain config was reloaded from the config account");
+        *self.account_config.lock() = Some((last_trans_hash, config.clone()));
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tvm_block::Account;
+    use tvm_block::AccountStorage;
+    use tvm_block::ConfigParam1;
+    use tvm_block::ConfigParamEnum;
+    use tvm_block::CurrencyCollection;
+    use tvm_block::MsgAddressInt;
+    use tvm_block::ShardAccount;
+    use tvm_block::ShardStateUnsplit;
+    use tvm_block::StateInit;
+    use tvm_block::StorageInfo;
+    use tvm_types::AccountId;
+    use tvm_types::BuilderData;
+
+    use super::*;
+    use crate::config::StateSavePolicy;
+    use crate::types::BlockSeqNo;
+
+    fn config_params(elector_addr: u8) -> ConfigParams {
+        let mut params = BlockchainConfig::default().raw_config().clone();
+        params
+            .set_config(ConfigParamEnum::ConfigParam1(ConfigParam1 {
+                elector_addr: UInt256::from([elector_addr; 32]),
+            }))
+            .unwrap();
+        params
+    }
+
+    fn params_root(params: &ConfigParams) -> Option<UInt256> {
+        params.config_params.data().map(|cell| cell.repr_hash())
+    }
+
+    fn state_with_config(
+        config_account: &AccountAddress,
+        params: &ConfigParams,
+        last_trans_hash: UInt256,
+        seq_no: u32,
+    ) -> OptimisticStateImpl {
+        let mut data = BuilderData::new();
+        data.checked_append_reference(params.config_params.data().cloned().unwrap()).unwrap();
+        let mut state_init = StateInit::default();
+        state_init.set_data(data.into_cell().unwrap());
+        let address =
+            MsgAddressInt::with_standart(None, 0, AccountId::from(config_account.0.clone()))
+                .unwrap();
+        let storage = AccountStorage::active_by_init_code_hash(
+            0,
+            CurrencyCollection::default(),
+            state_init,
+            false,
+        );
+        let account = Account::with_storage(&address, &StorageInfo::default(), &storage);
+        let shard_account = ShardAccount::with_params(&account, last_trans_hash, 0, None).unwrap();
+        let mut shard_state = ShardStateUnsplit::default();
+        shard_state.insert_account(&config_account.0, &shard_account).unwrap();
+        let mut state = OptimisticStateImpl::zero();
+        state.set_shard_state(Arc::new(shard_state));
+        state.block_seq_no = BlockSeqNo::from(seq_no);
+        state
+    }
+
+    #[test]
+    fn test_config_is_resolved_for_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let accounts_repo =
+            AccountsRepository::new(dir.path().to_path_buf(), None, StateSavePolicy::default());
+        let config_account = AccountAddress(UInt256::from([5; 32]));
+        let source = BlockchainConfigSource::new(
+            BlockchainConfig::default(),
+            Some(config_account.clone()),
+            10,
+        );
+        let resolve = |state: &OptimisticStateImpl| {
+            params_root(source.for_state(state, &accounts_repo).unwrap().raw_config())
+        };
+        let file_root = params_root(BlockchainConfig::default().raw_config());
+        let first = config_params(1);
+        let second = config_params(2);
+        assert_ne!(params_root(&first), params_root(&second));
+
+        // The file config is used before the activation
+        let state = state_with_config(&config_account, &first, UInt256::from([1; 32]), 9);
+        assert_eq!(resolve(&state), file_root);
+        let state = state_with_config(&config_account, &first, UInt256::from([1; 32]), 10);
+        assert_eq!(resolve(&state), params_root(&first));
+
+        // The config changed between the blocks is reloaded, the previous
+        // state still resolves to its own config
+        let state = state_with_config(&config_account, &second, UInt256::from([2; 32]), 11);
+        assert_eq!(resolve(&state), params_root(&second));
+        let state = state_with_config(&config_account, &first, UInt256::from([1; 32]), 10);
+        assert_eq!(resolve(&state), params_root(&first));
+
+        // After the activation the config account is required
+        let mut state = OptimisticStateImpl::zero();
+        state.block_seq_no = BlockSeqNo::from(12);
+        assert!(source.for_state(&state, &accounts_repo).is_err());
+        assert_eq!(
+            params_root(
+                BlockchainConfigSource::from(BlockchainConfig::default())
+                    .for_state(&state, &accounts_repo)
+                    .unwrap()
+                    .raw_config()
+            ),
+            file_root
+        );
+    }
+}
@@ -0,0 +1,59 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Reaction to the local clock skew exceeding the threshold.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClockSkewAction {
+    /// Block production is refused until the clock is fixed
+    #[default]
+    RefuseProduction,
+    /// Warn and correct the local time by the measured offset
+    Compensate,
+}
+
+/// Local clock monitoring against NTP servers.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ClockSkewConfig {
+    /// NTP servers (`host:port`) queried in order until one responds.
+    /// Empty list disables the monitoring.
+    /// Defaults to empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ntp_servers: Vec<String>,
+
+    /// Maximum acceptable offset of the local clock in milliseconds.
+    /// Defaults to 500
+    #[serde(default = "default_max_skew_millis")]
+    pub max_skew_millis: u64,
+
+    /// Interval between offset measurements in seconds.
+    /// Defaults to 60
+    #[serde(default = "default_check_interval_sec")]
+    pub check_interval_sec: u64,
+
+    /// Defaults to `refuse_production`
+    #[serde(default)]
+    pub action: ClockSkewAction,
+}
+
+fn default_max_skew_millis() -> u64 {
+    500
+}
+
+fn default_check_interval_sec() -> u64 {
+    60
+}
+
+impl Default for ClockSkewConfig {
+    fn default() -> Self {
+        Self {
+            ntp_servers: vec![],
+            max_skew_millis: default_max_skew_millis(),
+            check_interval_sec: default_check_interval_sec(),
+            action: ClockSkewAction::default(),
+        }
+    }
+}
@@ -0,0 +1,28 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Quarantine of the external messages whose execution repeatedly panics or
+/// fails. Without it such a message fails every block production
+/// iteration until it expires. The quarantine is persisted, it is listed and
+/// purged at `v2/ext_msg_quarantine`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ExtMsgQuarantineConfig {
+    /// Number of the consecutive failed executions after which the message is
+    /// quarantined. 0 disables the quarantine.
+    /// Defaults to 3
+    #[serde(default = "default_max_failures")]
+    pub max_failures: u32,
+}
+
+fn default_max_failures() -> u32 {
+    3
+}
+
+impl Default for ExtMsgQuarantineConfig {
+    fn default() -> Self {
+        Self { max_failures: default_max_failures() }
+    }
+}
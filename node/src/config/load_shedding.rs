@@ -0,0 +1,80 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Optional node work that can be disabled while block production and
+/// verification miss their deadlines.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SheddableSubsystem {
+    /// Finalized blocks for the block managers are queued instead of sent
+    Archive,
+    /// API queries are rejected with `503 Service Unavailable`
+    Api,
+    /// Requested transaction traces are not collected
+    TxTraces,
+}
+
+/// Load shedding of optional subsystems under production pressure.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LoadSheddingConfig {
+    /// Subsystems in the order they are disabled, they are restored in the
+    /// reverse order. Empty list disables load shedding.
+    /// Defaults to empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub priority: Vec<SheddableSubsystem>,
+
+    /// Number of the last production and verification deadlines of a thread
+    /// the miss ratio of the thread is measured over.
+    /// Defaults to 20
+    #[serde(default = "default_window")]
+    pub window: usize,
+
+    /// Ratio of missed deadlines in the window to disable the next
+    /// subsystem.
+    /// Defaults to 0.3
+    #[serde(default = "default_shed_miss_ratio")]
+    pub shed_miss_ratio: f64,
+
+    /// Ratio of missed deadlines in the window to restore the last disabled
+    /// subsystem.
+    /// Defaults to 0.05
+    #[serde(default = "default_restore_miss_ratio")]
+    pub restore_miss_ratio: f64,
+
+    /// Maximum number of finalized blocks queued while the archive is
+    /// disabled, the oldest blocks are sent anyway over the limit.
+    /// Defaults to 1000
+    #[serde(default = "default_archive_queue_limit")]
+    pub archive_queue_limit: usize,
+}
+
+fn default_window() -> usize {
+    20
+}
+
+fn default_shed_miss_ratio() -> f64 {
+    0.3
+}
+
+fn default_restore_miss_ratio() -> f64 {
+    0.05
+}
+
+fn default_archive_queue_limit() -> usize {
+    1000
+}
+
+impl Default for LoadSheddingConfig {
+    fn default() -> Self {
+        Self {
+            priority: vec![],
+            window: default_window(),
+            shed_miss_ratio: default_shed_miss_ratio(),
+            restore_miss_ratio: default_restore_miss_ratio(),
+            archive_queue_limit: default_archive_queue_limit(),
+        }
+    }
+}
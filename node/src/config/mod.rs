@@ -1,31 +1,75 @@
 // 2022-2024 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
 //
+mod account_policy;
 mod blockchain_config;
+mod clock_skew;
+mod ext_msg_quarantine;
+mod load_shedding;
 mod network_config;
+mod producer_build_policy;
+mod producer_selection;
+mod production_watchdog;
+mod replay_protection;
 mod serde_config;
+mod state_checksum;
+mod state_save;
+mod storage_quota;
 #[cfg(test)]
 mod test;
+mod thread_split_approval;
 mod validations;
+mod verify_sampling;
 
 use std::collections::HashSet;
 use std::path::PathBuf;
 use std::time::Duration;
 
+pub use account_policy::AccountPolicyConfig;
+pub use account_policy::AccountPolicyMode;
 pub use blockchain_config::*;
+pub use clock_skew::ClockSkewAction;
+pub use clock_skew::ClockSkewConfig;
+pub use ext_msg_quarantine::ExtMsgQuarantineConfig;
+use http_server::AdminAuthMode;
+pub use load_shedding::LoadSheddingConfig;
+pub use load_shedding::SheddableSubsystem;
 use network::pub_sub::CertFile;
 use network::pub_sub::CertStore;
 use network::pub_sub::PrivateKeyFile;
 use network::resolver::GossipPeer;
 pub use network_config::NetworkConfig;
+pub use producer_build_policy::ProducerBuildAction;
+pub use producer_build_policy::ProducerBuildPolicyConfig;
+pub use producer_selection::ProducerSelectionConfig;
+pub use production_watchdog::ProductionWatchdogConfig;
+pub use replay_protection::ReplayProtectionConfig;
 use serde::Deserialize;
 use serde::Serialize;
 pub use serde_config::load_config_from_file;
 pub use serde_config::save_config_to_file;
+pub use state_checksum::StateChecksumConfig;
+pub use state_save::StateSaveParams;
+pub use state_save::StateSavePolicy;
+pub use state_save::ThreadStateSaveConfig;
+pub use storage_quota::StorageQuotaConfig;
+use telemetry_config::TelemetryConfig;
+pub use thread_split_approval::ThreadSplitApprovalConfig;
+pub use thread_split_approval::ThreadSplitApprovalMode;
 use transport_layer::TlsCertCache;
 use typed_builder::TypedBuilder;
+pub use verify_sampling::VerifySamplingConfig;
+pub use verify_sampling::VerifySamplingMode;
 
 use crate::node::NodeIdentifier;
 use crate::types::BlockSeqNo;
+use crate::types::ThreadIdentifier;
+
+// Epoch contract code hashes used when they are not set in the config and
+// can't be resolved from the block keeper root contract
+pub const DEFAULT_BLOCK_KEEPER_EPOCH_CODE_HASH: &str =
+    "ad2647fa7fe0540f656b9fc137f0bcfc18fc7750c0197e789230f8e28c437df6";
+pub const DEFAULT_BLOCK_KEEPER_PREEPOCH_CODE_HASH: &str =
+    "aad416360eaf1d667e1470e5d4c9f56b7f55810e43cb5fa239bde4cec3454a72";
 
 // TODO: These settings should be moved onchain.
 /// Global node config, including block producer and synchronization settings.
@@ -35,6 +79,14 @@ pub struct GlobalConfig {
     /// Defaults to 330
     pub time_to_produce_block_millis: u64,
 
+    /// Enables production on demand: the block started with no external
+    /// messages is finished as soon as an external message arrives, but not
+    /// earlier than this time in milliseconds after its start. The next block
+    /// then picks up the message without waiting out the full producing cycle.
+    /// Defaults to None (blocks are produced every producing cycle)
+    #[serde(default)]
+    pub produce_on_demand_min_interval_millis: Option<u64>,
+
     /// Maximum verification duration for one block.
     /// Defaults to 440 (330 * 4 / 3)
     pub time_to_verify_block_millis: u64,
@@ -52,9 +104,18 @@ pub struct GlobalConfig {
     /// Defaults to Some(time_to_produce_transaction_millis * 0.9) is set in ensure_execution_timeouts
     pub time_to_verify_transaction_aborted_with_execution_timeout_millis: Option<u64>,
 
-    /// Timeout between attestation resend.
+    /// Timeout between attestation resend. Used until the RTT to the block
+    /// producer is measured, then by the RTT, at most once per pulse.
     pub attestation_resend_timeout: Duration,
 
+    /// Attestation send is scheduled to arrive at the block producer this
+    /// time before it aggregates attestations. The send time is calculated
+    /// from the measured RTT to the producer, until it is measured
+    /// attestations are sent after the fixed delay.
+    /// Defaults to 50 milliseconds
+    #[serde(default = "default_attestation_send_margin")]
+    pub attestation_send_margin: Duration,
+
     /// Difference between the seq no of the incoming block and the seq no of
     /// the last saved block, which causes the node synchronization process
     /// to start. Defaults to 20
@@ -85,10 +146,16 @@ pub struct GlobalConfig {
     /// Defaults to 200
     pub save_state_frequency: u32,
 
-    /// Block keeper epoch code hash
+    /// Block keeper epoch code hash. Resolved from the block keeper root
+    /// contract of the state at startup, a set value overrides it.
+    /// Defaults to empty (DEFAULT_BLOCK_KEEPER_EPOCH_CODE_HASH if the
+    /// root contract can't be resolved)
     pub block_keeper_epoch_code_hash: String,
 
-    /// Block keeper preepoch code hash
+    /// Block keeper preepoch code hash. Resolved from the block keeper root
+    /// contract of the state at startup, a set value overrides it.
+    /// Defaults to empty (DEFAULT_BLOCK_KEEPER_PREEPOCH_CODE_HASH if the
+    /// root contract can't be resolved)
     pub block_keeper_preepoch_code_hash: String,
 
     /// Expected maximum number of threads.
@@ -108,6 +175,64 @@ pub struct GlobalConfig {
     pub round_min_time_millis: u64,
     pub round_step_millis: u64,
     pub round_max_time_millis: u64,
+
+    /// Number of block times without a block from the round producer after
+    /// which the node votes to skip it. Zero disables skip votes.
+    /// Defaults to 0
+    #[serde(default)]
+    pub producer_skip_vote_after_blocks: u64,
+
+    /// Address of the account that stores blockchain config params (hex).
+    /// If not set, blockchain config is loaded from `blockchain_config_path` only.
+    /// Defaults to None
+    #[serde(default)]
+    pub blockchain_config_account: Option<String>,
+
+    /// Seq no starting from which blockchain config is read from the config account.
+    /// Defaults to 0
+    #[serde(default)]
+    pub blockchain_config_activation_seq_no: u32,
+
+    /// Address of the system contract that schedules deferred messages (hex).
+    /// The value of a scheduled message is taken from its balance when the
+    /// message is scheduled. If not set, messages are not deferred.
+    /// Defaults to None
+    #[serde(default)]
+    pub timer_contract_account: Option<String>,
+
+    /// Maximum number of cells in an account state. Transactions that grow an
+    /// account above it are aborted with exit code 50. Zero disables the limit.
+    /// Defaults to 0
+    #[serde(default)]
+    pub max_account_state_cells: u64,
+
+    /// Fraction of the block gas limit and production time that general
+    /// messages leave to the dapp config messages executed after them, so
+    /// they don't overrun the block limits under full queues. Reserved only
+    /// while such messages are pending.
+    /// Defaults to 0.1
+    #[serde(default = "default_system_lane_budget_fraction")]
+    pub system_lane_budget_fraction: f64,
+
+    /// Stake weighting and cooldown of the block producer selection.
+    /// Defaults to one turn per keeper without cooldown
+    #[serde(default)]
+    pub producer_selection: ProducerSelectionConfig,
+
+    /// Verify complexity of the produced blocks and sampling of the blocks
+    /// verified by the block keepers. All keepers must sample the same way,
+    /// so the setting is network-wide.
+    /// Defaults to the adaptive sampling
+    #[serde(default)]
+    pub verify_sampling: VerifySamplingConfig,
+}
+
+fn default_attestation_send_margin() -> Duration {
+    Duration::from_millis(50)
+}
+
+fn default_system_lane_budget_fraction() -> f64 {
+    0.1
 }
 
 /// Node interaction settings
@@ -140,6 +265,14 @@ pub struct NodeConfig {
     #[builder(default = 20)]
     pub parallelization_level: usize,
 
+    /// Level of block verification parallelization. Blocks are verified with
+    /// the production machinery bounded by `time_to_verify_block_millis`, so
+    /// the level must keep up with the producers of the network.
+    /// Defaults to parallelization_level
+    #[builder(default)]
+    #[serde(default)]
+    pub verification_parallelization_level: Option<usize>,
+
     /// Block cache size in local repository
     #[builder(default = 20)]
     pub block_cache_size: usize,
@@ -160,6 +293,13 @@ pub struct NodeConfig {
     #[builder(default = 1000)]
     pub ext_messages_cache_size: usize,
 
+    /// External messages whose ABI header `expire` is earlier than now plus
+    /// this margin are refused, they can't be included into a block in time.
+    /// Defaults to 1000
+    #[builder(default = 1000)]
+    #[serde(default = "default_ext_message_expiration_margin_ms")]
+    pub ext_message_expiration_margin_ms: u64,
+
     /// BlockKeeper node owner wallet pubkey
     #[builder(default = "".to_string())]
     pub node_wallet_pubkey: String,
@@ -168,6 +308,162 @@ pub struct NodeConfig {
     /// Required for direct sending external messages via node
     #[builder(default = None)]
     pub signing_keys: Option<String>,
+
+    /// Limit of accepted execution trace requests of external messages per minute.
+    /// Zero disables per-message tracing.
+    /// Defaults to 10
+    #[builder(default = 10)]
+    #[serde(default = "default_tx_trace_rate_limit_per_minute")]
+    pub tx_trace_rate_limit_per_minute: u32,
+
+    /// Share of execution trace requests that are accepted (from 0.0 to 1.0).
+    /// Defaults to 1.0
+    #[builder(default = 1.0)]
+    #[serde(default = "default_tx_trace_sample_rate")]
+    pub tx_trace_sample_rate: f64,
+
+    /// Number of collected execution traces kept for retrieval.
+    /// Defaults to 100
+    #[builder(default = 100)]
+    #[serde(default = "default_tx_trace_cache_size")]
+    pub tx_trace_cache_size: usize,
+
+    /// Interval of the background integrity audit of stored states and blocks (in seconds).
+    /// Audit can also be requested via API. None disables the periodic audit.
+    /// Defaults to None
+    #[builder(default = None)]
+    #[serde(default)]
+    pub integrity_audit_interval_sec: Option<u64>,
+
+    /// Number of the latest saved optimistic states kept on disk for each thread.
+    /// Zero keeps all saved states.
+    /// Defaults to 0
+    #[builder(default = 0)]
+    #[serde(default)]
+    pub saved_states_retention: usize,
+
+    /// Per-thread overrides of `save_state_frequency`, `state_cache_size` and
+    /// `saved_states_retention`.
+    /// Defaults to empty
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub thread_state_save: Vec<ThreadStateSaveConfig>,
+
+    /// Threads (hex ids) followed in the archive mode only: blocks are applied
+    /// and indexed, but the node does not verify, attest, vote or produce in
+    /// them. Keeper duties are kept in the rest of the threads.
+    /// Defaults to empty
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub archive_only_threads: Vec<String>,
+
+    /// Local clock monitoring: block production relies on the local time
+    /// for gen_utime, so a skewed clock produces blocks with bad timestamps.
+    /// Defaults to disabled monitoring
+    #[builder(default)]
+    #[serde(default)]
+    pub clock_skew: ClockSkewConfig,
+
+    /// Optional subsystems disabled while block production and verification
+    /// miss their deadlines.
+    /// Defaults to disabled load shedding
+    #[builder(default)]
+    #[serde(default)]
+    pub load_shedding: LoadSheddingConfig,
+
+    /// Account policy applied to external messages in produced blocks.
+    /// Defaults to disabled policy
+    #[builder(default)]
+    #[serde(default)]
+    pub account_policy: AccountPolicyConfig,
+
+    /// Authentication of the API admin endpoints: `token` (`AUTH_TOKEN` env)
+    /// or `wallet_signature` (challenge signed by the node owner wallet key).
+    /// Defaults to `token`
+    #[builder(default)]
+    #[serde(default)]
+    pub api_admin_auth: AdminAuthMode,
+
+    /// Finalized state checksums gossip and divergence detection.
+    /// Defaults to disabled
+    #[builder(default)]
+    #[serde(default)]
+    pub state_checksum: StateChecksumConfig,
+
+    /// Detection and diagnostics of the stalled block production.
+    #[builder(default)]
+    #[serde(default)]
+    pub production_watchdog: ProductionWatchdogConfig,
+
+    /// Operator approval of the thread splits proposed by this node as the
+    /// producer. Local-only: splits of the blocks produced by other nodes are
+    /// not held and are accepted by this node as usual.
+    /// Defaults to immediate splits
+    #[builder(default)]
+    #[serde(default)]
+    pub thread_split_approval: ThreadSplitApprovalConfig,
+
+    /// Allowed versions of the block producers (advertised in the blocks and
+    /// in the gossip).
+    /// Defaults to any version
+    #[builder(default)]
+    #[serde(default)]
+    pub producer_build_policy: ProducerBuildPolicyConfig,
+
+    /// Per-thread disk usage quotas.
+    /// Defaults to disabled
+    #[builder(default)]
+    #[serde(default)]
+    pub storage_quota: StorageQuotaConfig,
+
+    /// Rejection of the recently processed external messages sent again.
+    /// Defaults to 600 seconds or 1000 blocks
+    #[builder(default)]
+    #[serde(default)]
+    pub replay_protection: ReplayProtectionConfig,
+
+    /// Code hashes (hex) of the wallets with the `pubkey` and `time` ABI
+    /// headers. The account nonce API reads the replay protection time stored
+    /// by these wallets only, the data layout of other contracts is unknown.
+    /// Defaults to empty
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub replay_protection_code_hashes: Vec<String>,
+
+    /// Quarantine of the external messages failing to execute repeatedly.
+    /// Defaults to 3 consecutive failures
+    #[builder(default)]
+    #[serde(default)]
+    pub ext_msg_quarantine: ExtMsgQuarantineConfig,
+
+    /// URLs receiving a JSON POST for each block invalidated after it was
+    /// applied optimistically (also streamed at `v2/block_invalidations`).
+    /// Defaults to empty
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub block_invalidation_webhooks: Vec<String>,
+
+    /// OpenTelemetry exporters. Unset fields are taken from the `OTEL_*`
+    /// environment variables. `metrics_enabled` is applied on config reload.
+    #[builder(default)]
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+}
+
+fn default_tx_trace_rate_limit_per_minute() -> u32 {
+    10
+}
+
+fn default_tx_trace_sample_rate() -> f64 {
+    1.0
+}
+
+fn default_tx_trace_cache_size() -> usize {
+    100
+}
+
+fn default_ext_message_expiration_margin_ms() -> u64 {
+    1000
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -187,6 +483,7 @@ impl Default for GlobalConfig {
     fn default() -> Self {
         Self {
             time_to_produce_block_millis: 330,
+            produce_on_demand_min_interval_millis: None,
             time_to_verify_block_millis: 330 * 4 / 3,
             time_to_produce_transaction_millis: None,
             time_to_verify_transaction_millis: None,
@@ -194,15 +491,14 @@ impl Default for GlobalConfig {
             need_synchronization_block_diff: 20,
             min_time_between_state_publish_directives: Duration::from_secs(600),
             attestation_resend_timeout: Duration::from_secs(3),
+            attestation_send_margin: default_attestation_send_margin(),
             producer_change_gap_size: 6,
             node_joining_timeout: Duration::from_secs(300),
             sync_gap: 32,
             sync_delay_milliseconds: 500,
             save_state_frequency: 200,
-            block_keeper_epoch_code_hash:
-                "ad2647fa7fe0540f656b9fc137f0bcfc18fc7750c0197e789230f8e28c437df6".to_string(),
-            block_keeper_preepoch_code_hash:
-                "aad416360eaf1d667e1470e5d4c9f56b7f55810e43cb5fa239bde4cec3454a72".to_string(),
+            block_keeper_epoch_code_hash: String::new(),
+            block_keeper_preepoch_code_hash: String::new(),
             thread_count_soft_limit: 100,
             thread_load_window_size: 100,
             thread_load_threshold: 5000,
@@ -210,20 +506,86 @@ impl Default for GlobalConfig {
             round_min_time_millis: 10000,
             round_step_millis: 1000,
             round_max_time_millis: 30000,
+            producer_skip_vote_after_blocks: 0,
+            blockchain_config_account: None,
+            blockchain_config_activation_seq_no: 0,
+            timer_contract_account: None,
+            max_account_state_cells: 0,
+            system_lane_budget_fraction: default_system_lane_budget_fraction(),
+            producer_selection: ProducerSelectionConfig::default(),
+            verify_sampling: VerifySamplingConfig::default(),
         }
     }
 }
 
 impl Config {
+    pub fn verification_parallelization_level(&self) -> usize {
+        self.local.verification_parallelization_level.unwrap_or(self.local.parallelization_level)
+    }
+
     pub fn gossip_config(&self) -> anyhow::Result<gossip::GossipConfig> {
         Ok(gossip::GossipConfig {
             listen_addr: self.network.gossip_listen_addr,
             advertise_addr: self.network.gossip_advertise_addr,
             seeds: self.network.gossip_seeds.clone(),
             cluster_id: self.network.chitchat_cluster_id.clone(),
+            tuning: self.network.gossip_tuning.clone(),
+            snapshot: self.network.gossip_snapshot.clone(),
         })
     }
 
+    /// Configs of the additional gossip clusters. Clusters must not share the
+    /// cluster id, the listen address or the snapshot file with each other and
+    /// the main cluster.
+    pub fn extra_gossip_configs(&self) -> anyhow::Result<Vec<gossip::GossipConfig>> {
+        let mut cluster_ids = HashSet::from([self.network.chitchat_cluster_id.as_str()]);
+        let mut listen_addrs = HashSet::from([self.network.gossip_listen_addr]);
+        let mut snapshot_paths = self.network.gossip_snapshot.path.iter().collect::<HashSet<_>>();
+        for cluster in &self.network.extra_gossip_clusters {
+            anyhow::ensure!(
+                cluster_ids.insert(cluster.cluster_id.as_str()),
+                "Gossip cluster id {} is used more than once",
+                cluster.cluster_id
+            );
+            anyhow::ensure!(
+                listen_addrs.insert(cluster.listen_addr),
+                "Gossip listen addr {} is used by more than one cluster",
+                cluster.listen_addr
+            );
+            if let Some(path) = &cluster.snapshot.path {
+                anyhow::ensure!(
+                    snapshot_paths.insert(path),
+                    "Gossip snapshot {path:?} is used by more than one cluster"
+                );
+            }
+        }
+        Ok(self.network.extra_gossip_clusters.clone())
+    }
+
+    /// State saving settings of the threads: global `save_state_frequency` and
+    /// local cache and retention settings with per-thread overrides applied.
+    pub fn state_save_policy(&self) -> anyhow::Result<StateSavePolicy> {
+        StateSavePolicy::with_overrides(
+            StateSaveParams {
+                save_state_frequency: self.global.save_state_frequency,
+                state_cache_size: self.local.state_cache_size,
+                saved_states_retention: self.local.saved_states_retention,
+            },
+            &self.local.thread_state_save,
+        )
+    }
+
+    pub fn archive_only_threads(&self) -> anyhow::Result<HashSet<ThreadIdentifier>> {
+        let mut threads = HashSet::new();
+        for thread_id in &self.local.archive_only_threads {
+            let parsed = ThreadIdentifier::try_from(thread_id.clone()).map_err(|e| {
+                anyhow::format_err!("Invalid archive only thread id {thread_id}: {e}")
+            })?;
+            anyhow::ensure!(threads.insert(parsed), "Duplicate archive only thread {thread_id}");
+        }
+        Ok(threads)
+    }
+
     pub fn gossip_peer(&self) -> anyhow::Result<GossipPeer<NodeIdentifier>> {
         GossipPeer::new(
             self.local.node_id.clone(),
@@ -242,7 +604,7 @@ impl Config {
         &self,
         tls_cert_cache: Option<TlsCertCache>,
     ) -> anyhow::Result<network::config::NetworkConfig> {
-        network::config::NetworkConfig::new(
+        let mut config = network::config::NetworkConfig::new(
             self.network.bind,
             CertFile::try_new(&self.network.my_cert)?,
             PrivateKeyFile::try_new(&self.network.my_key)?,
@@ -255,7 +617,25 @@ impl Config {
             self.network.subscribe.clone(),
             self.network.proxies.clone(),
             tls_cert_cache,
-        )
+        )?;
+        config.subscription_silence_timeout =
+            (self.network.subscription_silence_blocks > 0).then(|| {
+                Duration::from_millis(self.global.time_to_produce_block_millis)
+                    * self.network.subscription_silence_blocks
+            });
+        config.subscribe_srv = self.network.subscribe_srv.clone();
+        config.srv_refresh_interval =
+            Duration::from_millis(self.network.srv_refresh_interval_millis);
+        config.connection_migration_check_interval =
+            (self.network.connection_migration_check_interval_millis > 0).then(|| {
+                Duration::from_millis(self.network.connection_migration_check_interval_millis)
+            });
+        config.send_buffer_size = self.network.send_buffer_size;
+        config.send_buffer_tuning = self.network.send_buffer_tuning;
+        config.backpressure = self.network.backpressure;
+        config.enforce_peer_identities = self.network.enforce_peer_identities;
+        config.chunked_transfer = self.network.chunked_transfer;
+        Ok(config)
     }
 }
 
@@ -269,14 +649,36 @@ impl Default for NodeConfig {
             zerostate_path: PathBuf::from("zerostate"),
             external_state_share_local_base_dir: PathBuf::from("/tmp"),
             parallelization_level: 20,
+            verification_parallelization_level: None,
             block_keeper_seed_path: "block_keeper.keys.json".to_string(),
             block_cache_size: 20,
             state_cache_size: 10,
             unload_after: None,
             rate_limit_on_incoming_block_req: u32::MAX,
             ext_messages_cache_size: 200,
+            ext_message_expiration_margin_ms: default_ext_message_expiration_margin_ms(),
             node_wallet_pubkey: "some_public_key".to_string(),
             signing_keys: None,
+            tx_trace_rate_limit_per_minute: 10,
+            tx_trace_sample_rate: 1.0,
+            tx_trace_cache_size: 100,
+            integrity_audit_interval_sec: None,
+            saved_states_retention: 0,
+            thread_state_save: vec![],
+            archive_only_threads: vec![],
+            clock_skew: ClockSkewConfig::default(),
+            load_shedding: LoadSheddingConfig::default(),
+            account_policy: AccountPolicyConfig::default(),
+            api_admin_auth: AdminAuthMode::default(),
+            state_checksum: StateChecksumConfig::default(),
+            production_watchdog: ProductionWatchdogConfig::default(),
+            thread_split_approval: ThreadSplitApprovalConfig::default(),
+            producer_build_policy: ProducerBuildPolicyConfig::default(),
+            storage_quota: StorageQuotaConfig::default(),
+            replay_protection: ReplayProtectionConfig::default(),
+            ext_msg_quarantine: ExtMsgQuarantineConfig::default(),
+            block_invalidation_webhooks: vec![],
+            telemetry: TelemetryConfig::default(),
         }
     }
 }
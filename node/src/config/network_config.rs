@@ -67,6 +67,15 @@ pub struct NetworkConfig {
     #[builder(default)]
     pub subscribe: Vec<Vec<SocketAddr>>,
 
+    /// Subscribe SRV records (e.g. "_publisher._udp.proxies.example.com").
+    ///
+    /// Each record is resolved into a subscribe entry with record targets as
+    /// alternate publisher addresses. Records are re-resolved every
+    /// `srv_refresh_interval_millis`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[builder(default)]
+    pub subscribe_srv: Vec<String>,
+
     /// Proxies.
     ///
     /// Node propagates this proxy list via gossip.
@@ -84,6 +93,14 @@ pub struct NetworkConfig {
     #[serde(default)]
     pub peer_certs: Vec<PathBuf>,
 
+    /// Interval to reload `peer_certs`, so the certificates of the new peers
+    /// put into the directories are trusted without a restart. Zero disables
+    /// the reload.
+    /// Defaults to 60000
+    #[builder(default = 60000)]
+    #[serde(default = "default_peer_certs_refresh_interval_millis")]
+    pub peer_certs_refresh_interval_millis: u64,
+
     /// Files and directories with TLS certificates (*.ca.pem), required to verify
     /// server certificate when node establish client connection to other node or proxy.
     #[builder(default)]
@@ -114,7 +131,9 @@ pub struct NetworkConfig {
     #[serde(default = "default_block_manager_listen_addr")]
     pub block_manager_listen_addr: SocketAddr,
 
-    /// Static storages urls (e.g. <https://example.com/storage/>)
+    /// Static storages urls (e.g. <https://example.com/storage/>).
+    /// Urls with `srv+` scheme prefix (e.g. <srv+https://_storage._tcp.example.com/storage/>)
+    /// are resolved via SRV records and re-resolved every `srv_refresh_interval_millis`.
     #[builder(default)]
     #[serde(default = "Default::default")]
     pub static_storages: Vec<url::Url>,
@@ -131,6 +150,19 @@ pub struct NetworkConfig {
     #[serde(default = "default_send_buffer_size")]
     pub send_buffer_size: usize,
 
+    /// Bounds the send buffer size is adjusted within at runtime from the
+    /// observed buffer occupancy. The size is fixed if not set.
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub send_buffer_tuning: Option<network::adaptive_capacity::CapacityTuningConfig>,
+
+    /// Sustained delivery delays from a publisher are reported to it, so it
+    /// sends only announcements-sized messages until the subscription
+    /// recovers. Advisories are not sent if not set.
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backpressure: Option<network::backpressure::BackpressureConfig>,
+
     /// Public address for Block Manager API of this node
     #[builder(default)]
     pub bm_api_socket: Option<SocketAddr>,
@@ -151,9 +183,79 @@ pub struct NetworkConfig {
     #[serde(default = "default_shared_state_retry_download_timeout_millis")]
     pub shared_state_retry_download_timeout_millis: u64,
 
+    /// Number of block production intervals without incoming messages after
+    /// which a subscription is considered dead and is re-established.
+    /// Zero disables the check.
+    /// Defaults to 30
+    #[builder(default = 30)]
+    #[serde(default = "default_subscription_silence_blocks")]
+    pub subscription_silence_blocks: u32,
+
+    /// Interval to re-resolve SRV records of `subscribe_srv` and `static_storages`.
+    /// Defaults to 60000
+    #[builder(default = 60000)]
+    #[serde(default = "default_srv_refresh_interval_millis")]
+    pub srv_refresh_interval_millis: u64,
+
+    /// Interval to check whether connections are routed through another
+    /// local IP (egress failover). Rerouted outgoing connections are migrated
+    /// to the new IP keeping their sessions, incoming ones are closed so the
+    /// peers reconnect at once. Zero disables the check.
+    /// Defaults to 1000
+    #[builder(default = 1000)]
+    #[serde(default = "default_connection_migration_check_interval_millis")]
+    pub connection_migration_check_interval_millis: u64,
+
+    /// Claim the node id on established connections and reject incoming
+    /// connections of nodes whose claim doesn't match the certificate key.
+    /// Takes effect only with `peer_ed_pubkeys` set. Enable after all nodes
+    /// are upgraded: previous versions drop connections with claims.
+    #[builder(default)]
+    #[serde(default)]
+    pub enforce_peer_identities: bool,
+
+    /// Size limits of the messages received in chunks from the peers.
+    #[builder(default)]
+    #[serde(default)]
+    pub chunked_transfer: network::chunked_transfer::ChunkedTransferConfig,
+
+    /// UPnP or NAT-PMP mapping of the `bind` and `gossip_listen_addr` UDP
+    /// ports on the router for nodes behind a NAT. Ports are mapped to the
+    /// same external ports.
+    #[builder(default)]
+    #[serde(default)]
+    pub port_mapping: network::port_mapping::PortMappingConfig,
+
+    /// Ask a proxy or a peer to dial back to `node_advertise_addr` at startup
+    /// and report whether other nodes can connect to this node.
+    /// Defaults to false
+    #[builder(default)]
+    #[serde(default)]
+    pub reachability_self_test: bool,
+
     /// Chitchat cluster id for gossip
     #[serde(default = "default_chitchat_cluster_id")]
     pub chitchat_cluster_id: String,
+
+    /// Gossip interval, fanout and datagram size of the main gossip cluster.
+    /// Set `adaptive_cluster_size` for networks of 100+ nodes to reduce the
+    /// UDP traffic.
+    #[builder(default)]
+    #[serde(default)]
+    pub gossip_tuning: gossip::GossipTuning,
+
+    /// Saving of the main gossip cluster state, so a restarted node knows
+    /// its peers without waiting for the gossip rounds. Set `path` to enable.
+    #[builder(default)]
+    #[serde(default)]
+    pub gossip_snapshot: gossip::GossipSnapshotConfig,
+
+    /// Additional gossip clusters the node joins (e.g. an operator-private
+    /// cluster for fleet tooling). Each cluster has its own listen address
+    /// and peer list, peers of these clusters are not used by the node.
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_gossip_clusters: Vec<gossip::GossipConfig>,
 }
 
 fn default_bind() -> SocketAddr {
@@ -180,6 +282,22 @@ fn default_shared_state_max_download_tries() -> u8 {
     30
 }
 
+fn default_subscription_silence_blocks() -> u32 {
+    30
+}
+
+fn default_srv_refresh_interval_millis() -> u64 {
+    60000
+}
+
+fn default_peer_certs_refresh_interval_millis() -> u64 {
+    60000
+}
+
+fn default_connection_migration_check_interval_millis() -> u64 {
+    1000
+}
+
 fn default_chitchat_cluster_id() -> String {
     "acki_nacki".to_string()
 }
@@ -0,0 +1,122 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::cmp::Ordering;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::types::extensions::ProducerBuild;
+
+/// Reaction to a block produced by a build outside the allowed range.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProducerBuildAction {
+    /// Warn and attest the block as usual
+    #[default]
+    Warn,
+    /// Warn and don't attest the block
+    RefuseAttestation,
+}
+
+/// Versions of the producers accepted during coordinated upgrades. Blocks
+/// without the build info come from versions predating it and are below any
+/// minimum version.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProducerBuildPolicyConfig {
+    /// Lowest allowed version, e.g. `0.5.0`.
+    /// Defaults to None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_version: Option<String>,
+
+    /// Highest allowed version.
+    /// Defaults to None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_version: Option<String>,
+
+    /// Defaults to `warn`
+    #[serde(default)]
+    pub action: ProducerBuildAction,
+}
+
+impl ProducerBuildPolicyConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.min_version.is_some() || self.max_version.is_some()
+    }
+
+    /// Returns the reason if the build is outside the allowed range.
+    pub fn check(&self, build: Option<&ProducerBuild>) -> Option<String> {
+        let Some(build) = build else {
+            return self
+                .min_version
+                .as_ref()
+                .map(|min| format!("producer build is unknown, expected at least {min}"));
+        };
+        if let Some(min) = &self.min_version {
+            if compare_versions(&build.version, min) == Ordering::Less {
+                return Some(format!(
+                    "producer version {} ({}) is below {min}",
+                    build.version, build.commit
+                ));
+            }
+        }
+        if let Some(max) = &self.max_version {
+            if compare_versions(&build.version, max) == Ordering::Greater {
+                return Some(format!(
+                    "producer version {} ({}) is above {max}",
+                    build.version, build.commit
+                ));
+            }
+        }
+        None
+    }
+}
+
+// Compares dot separated numeric components, a suffix after the numbers
+// (`-rc1`) is ignored and missing components are zeros
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let components = |version: &str| -> Vec<u64> {
+        version
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|x| x.trim().parse().unwrap_or_default())
+            .collect()
+    };
+    let (a, b) = (components(a), components(b));
+    (0..a.len().max(b.len()))
+        .map(|i| a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0)))
+        .find(|x| x.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(version: &str) -> ProducerBuild {
+        ProducerBuild { version: version.to_string(), commit: "abc".to_string() }
+    }
+
+    #[test]
+    fn test_producer_build_policy() {
+        assert_eq!(compare_versions("0.10.0", "0.9.1"), Ordering::Greater);
+        assert_eq!(compare_versions("1.2", "1.2.0-rc1"), Ordering::Equal);
+
+        let policy = ProducerBuildPolicyConfig::default();
+        assert!(!policy.is_enabled());
+        assert_eq!(policy.check(None), None);
+
+        let policy = ProducerBuildPolicyConfig {
+            min_version: Some("0.5.0".to_string()),
+            max_version: Some("0.6".to_string()),
+            action: ProducerBuildAction::RefuseAttestation,
+        };
+        assert!(policy.check(None).is_some());
+        assert!(policy.check(Some(&build("0.4.9"))).is_some());
+        assert_eq!(policy.check(Some(&build("0.5.0"))), None);
+        assert_eq!(policy.check(Some(&build("0.6.0"))), None);
+        assert!(policy.check(Some(&build("0.6.1"))).is_some());
+    }
+}
@@ -0,0 +1,24 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Order of the block producers of a thread. Selection is part of the
+/// consensus and is not written to the blocks: all nodes must share the
+/// config, keepers reject producers that don't match their schedule.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ProducerSelectionConfig {
+    /// Give the keepers turns proportional to their stake instead of one
+    /// turn per keeper.
+    /// Defaults to false
+    #[serde(default)]
+    pub stake_weighted: bool,
+
+    /// Minimum number of turns of other keepers between two turns of the
+    /// same keeper. Applied to the stake weighted selection as far as the
+    /// stake distribution allows.
+    /// Defaults to 0
+    #[serde(default)]
+    pub cooldown: usize,
+}
@@ -0,0 +1,26 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Detection of the stalled block production iterations. Diagnostics of a
+/// stall are served on the `v2/production_stalls` admin endpoint.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ProductionWatchdogConfig {
+    /// Iteration is stalled when it takes longer than the block production
+    /// timeout multiplied by this value. 0 disables the watchdog.
+    /// Defaults to 10
+    #[serde(default = "default_stall_timeout_multiplier")]
+    pub stall_timeout_multiplier: u32,
+}
+
+fn default_stall_timeout_multiplier() -> u32 {
+    10
+}
+
+impl Default for ProductionWatchdogConfig {
+    fn default() -> Self {
+        Self { stall_timeout_multiplier: default_stall_timeout_multiplier() }
+    }
+}
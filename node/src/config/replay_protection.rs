@@ -0,0 +1,37 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// How long the node remembers the external messages of the finalized blocks
+/// and rejects them if they are sent again. The processed messages are
+/// persisted, so the protection survives node restarts.
+/// A message is remembered while it is within either horizon.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReplayProtectionConfig {
+    /// Time since the block with the message was finalized, in seconds.
+    /// Defaults to 600
+    #[serde(default = "default_horizon_sec")]
+    pub horizon_sec: u64,
+
+    /// Number of the thread blocks finalized since the block the message was
+    /// processed in.
+    /// Defaults to 1000
+    #[serde(default = "default_horizon_blocks")]
+    pub horizon_blocks: u32,
+}
+
+fn default_horizon_sec() -> u64 {
+    600
+}
+
+fn default_horizon_blocks() -> u32 {
+    1000
+}
+
+impl Default for ReplayProtectionConfig {
+    fn default() -> Self {
+        Self { horizon_sec: default_horizon_sec(), horizon_blocks: default_horizon_blocks() }
+    }
+}
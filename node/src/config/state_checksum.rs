@@ -0,0 +1,35 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Gossip of the finalized state checksums and detection of the state
+/// divergence among the block keepers.
+///
+/// Nodes that don't know about the checksum gossip keys fail to verify the
+/// node signature, so enable it after all nodes of the cluster are updated.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StateChecksumConfig {
+    /// Interval between checksum publications in seconds. None disables
+    /// publication and divergence detection.
+    /// Defaults to None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval_sec: Option<u64>,
+
+    /// Number of the latest own checksums per thread kept to compare with
+    /// checksums of lagging peers.
+    /// Defaults to 16
+    #[serde(default = "default_history_size")]
+    pub history_size: usize,
+}
+
+fn default_history_size() -> usize {
+    16
+}
+
+impl Default for StateChecksumConfig {
+    fn default() -> Self {
+        Self { interval_sec: None, history_size: default_history_size() }
+    }
+}
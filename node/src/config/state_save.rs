@@ -0,0 +1,140 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::types::ThreadIdentifier;
+
+/// Per-thread override of the optimistic state saving settings. Fields that
+/// are not set are taken from the global and local configs.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ThreadStateSaveConfig {
+    /// Thread identifier (hex)
+    pub thread_id: String,
+
+    /// Save optimistic state frequency (every N'th block)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub save_state_frequency: Option<u32>,
+
+    /// Number of finalized optimistic states kept in memory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_cache_size: Option<usize>,
+
+    /// Number of the latest saved optimistic states kept on disk. Zero keeps all
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub saved_states_retention: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateSaveParams {
+    pub save_state_frequency: u32,
+    pub state_cache_size: usize,
+    pub saved_states_retention: usize,
+}
+
+impl Default for StateSaveParams {
+    fn default() -> Self {
+        Self { save_state_frequency: 200, state_cache_size: 10, saved_states_retention: 0 }
+    }
+}
+
+/// Resolves state saving settings of a thread: busy threads may save states
+/// more often for sync while idle threads save rarely to reduce IO.
+#[derive(Debug, Clone, Default)]
+pub struct StateSavePolicy {
+    default: StateSaveParams,
+    threads: HashMap<ThreadIdentifier, StateSaveParams>,
+}
+
+impl StateSavePolicy {
+    pub fn new(default: StateSaveParams) -> Self {
+        Self { default, threads: HashMap::new() }
+    }
+
+    pub fn with_overrides(
+        default: StateSaveParams,
+        overrides: &[ThreadStateSaveConfig],
+    ) -> anyhow::Result<Self> {
+        anyhow::ensure!(default.save_state_frequency > 0, "save_state_frequency must not be zero");
+        let mut policy = Self::new(default);
+        for thread_override in overrides {
+            let thread_id =
+                ThreadIdentifier::try_from(thread_override.thread_id.clone()).map_err(|e| {
+                    anyhow::format_err!("Invalid thread id {}: {e}", thread_override.thread_id)
+                })?;
+            let params = StateSaveParams {
+                save_state_frequency: thread_override
+                    .save_state_frequency
+                    .unwrap_or(default.save_state_frequency),
+                state_cache_size: thread_override
+                    .state_cache_size
+                    .unwrap_or(default.state_cache_size),
+                saved_states_retention: thread_override
+                    .saved_states_retention
+                    .unwrap_or(default.saved_states_retention),
+            };
+            anyhow::ensure!(
+                params.save_state_frequency > 0,
+                "save_state_frequency of thread {} must not be zero",
+                thread_override.thread_id
+            );
+            anyhow::ensure!(
+                policy.threads.insert(thread_id, params).is_none(),
+                "Duplicate state save config for thread {}",
+                thread_override.thread_id
+            );
+        }
+        Ok(policy)
+    }
+
+    pub fn for_thread(&self, thread_id: &ThreadIdentifier) -> StateSaveParams {
+        self.threads.get(thread_id).copied().unwrap_or(self.default)
+    }
+
+    /// The largest frequency over all threads, buffers shared by the threads
+    /// must fit the longest gap between saved states.
+    pub fn max_save_state_frequency(&self) -> u32 {
+        self.threads
+            .values()
+            .map(|params| params.save_state_frequency)
+            .fold(self.default.save_state_frequency, u32::max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_save_policy() -> anyhow::Result<()> {
+        let default = StateSaveParams { saved_states_retention: 5, ..Default::default() };
+        let busy_thread = ThreadIdentifier::new(&Default::default(), 1);
+        let mut overrides = vec![ThreadStateSaveConfig {
+            thread_id: format!("{busy_thread:x}"),
+            save_state_frequency: Some(20),
+            state_cache_size: None,
+            saved_states_retention: Some(50),
+        }];
+        let policy = StateSavePolicy::with_overrides(default, &overrides)?;
+        assert_eq!(
+            policy.for_thread(&busy_thread),
+            StateSaveParams {
+                save_state_frequency: 20,
+                state_cache_size: 10,
+                saved_states_retention: 50
+            }
+        );
+        assert_eq!(policy.for_thread(&ThreadIdentifier::default()), default);
+        assert_eq!(policy.max_save_state_frequency(), 200);
+
+        overrides.push(overrides[0].clone());
+        assert!(StateSavePolicy::with_overrides(default, &overrides).is_err());
+        overrides.pop();
+        overrides[0].save_state_frequency = Some(0);
+        assert!(StateSavePolicy::with_overrides(default, &overrides).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,41 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Soft quotas of the disk usage of each thread (saved states, stored blocks
+/// and internal messages), so that one runaway thread doesn't fill the disk
+/// shared by all threads.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StorageQuotaConfig {
+    /// Quota of a thread in bytes. None disables the tracking.
+    /// Defaults to None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread_quota_bytes: Option<u64>,
+
+    /// Interval between disk usage measurements in seconds.
+    /// Defaults to 60
+    #[serde(default = "default_check_interval_sec")]
+    pub check_interval_sec: u64,
+
+    /// Number of the latest saved states kept on disk for a thread exceeding
+    /// the quota, until its usage drops below it. None only alerts.
+    /// Defaults to None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tightened_states_retention: Option<usize>,
+}
+
+fn default_check_interval_sec() -> u64 {
+    60
+}
+
+impl Default for StorageQuotaConfig {
+    fn default() -> Self {
+        Self {
+            thread_quota_bytes: None,
+            check_interval_sec: default_check_interval_sec(),
+            tightened_states_retention: None,
+        }
+    }
+}
@@ -7,6 +7,7 @@ mod tests {
     use std::path::PathBuf;
     use std::time::Duration;
 
+    use crate::config::AccountPolicyMode;
     use crate::config::Config;
     use crate::config::NetworkConfig;
     use crate::node::NodeIdentifier;
@@ -27,6 +28,68 @@ mod tests {
         assert!(config.static_storages.is_empty());
         assert_eq!(config.gossip_listen_addr, SocketAddr::from(([127, 0, 0, 1], 10000)));
         assert_eq!(config.send_buffer_size, 1000);
+        assert!(config.send_buffer_tuning.is_none());
+        assert_eq!(config.subscription_silence_blocks, 30);
+        assert!(config.subscribe_srv.is_empty());
+        assert_eq!(config.srv_refresh_interval_millis, 60000);
+        assert_eq!(config.peer_certs_refresh_interval_millis, 60000);
+        assert_eq!(config.connection_migration_check_interval_millis, 1000);
+        assert!(config.extra_gossip_clusters.is_empty());
+        assert!(config.gossip_snapshot.path.is_none());
+        assert_eq!(config.gossip_snapshot.max_age_secs, 600);
+        Ok(())
+    }
+
+    #[test]
+    fn test_extra_gossip_clusters() -> anyhow::Result<()> {
+        let config_str = r#"{
+    "network": {
+        "node_advertise_addr": "0.0.0.0:8500",
+        "api_addr": "127.0.0.1:8600",
+        "api_advertise_addr": "https://node0:8600",
+        "gossip_seeds": [],
+        "extra_gossip_clusters": [{
+            "listen_addr": "0.0.0.0:10001",
+            "advertise_addr": "10.0.0.1:10001",
+            "seeds": ["10.0.0.2:10001"],
+            "cluster_id": "operator"
+        }]
+    },
+    "local": {
+        "node_id": "81a6bea128f5e03843362e55fd574c42a8e457dd553498cbc8ec7e14966d20a3",
+        "blockchain_config_path": "../bc_config.json",
+        "key_path": "key1.json",
+        "zerostate_path": "./zerostate",
+        "external_state_share_local_base_dir": "/tmp",
+        "parallelization_level": 20,
+        "split_state": false,
+        "block_keeper_seed_path": "block_keeper.keys.json",
+        "block_cache_size": 20,
+        "state_cache_size": 10,
+        "message_storage_path": "message_strage",
+        "rate_limit_on_incoming_block_req": 1000,
+        "ext_messages_cache_size": 10,
+        "node_wallet_pubkey": "hex_string"
+    }
+}"#;
+        let mut config: Config = serde_json::from_str(config_str)?;
+        let clusters = config.extra_gossip_configs()?;
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].cluster_id, "operator");
+        assert_eq!(clusters[0].advertise_addr(), SocketAddr::from(([10, 0, 0, 1], 10001)));
+
+        config.network.extra_gossip_clusters[0].cluster_id =
+            config.network.chitchat_cluster_id.clone();
+        assert!(config.extra_gossip_configs().is_err());
+
+        config.network.extra_gossip_clusters[0].cluster_id = "operator".to_string();
+        config.network.extra_gossip_clusters[0].listen_addr = config.network.gossip_listen_addr;
+        assert!(config.extra_gossip_configs().is_err());
+
+        config.network.extra_gossip_clusters[0].listen_addr = "0.0.0.0:10001".parse()?;
+        config.network.gossip_snapshot.path = Some("gossip.json".into());
+        config.network.extra_gossip_clusters[0].snapshot.path = Some("gossip.json".into());
+        assert!(config.extra_gossip_configs().is_err());
         Ok(())
     }
 
@@ -70,8 +133,22 @@ mod tests {
         assert_eq!(config.local.key_path, "key1.json");
         assert_eq!(config.local.zerostate_path, PathBuf::from("./zerostate"));
         assert_eq!(config.local.external_state_share_local_base_dir, PathBuf::from("/tmp"));
+        assert_eq!(config.local.verification_parallelization_level, None);
+        assert_eq!(config.verification_parallelization_level(), 20);
+        assert_eq!(config.local.tx_trace_rate_limit_per_minute, 10);
+        assert_eq!(config.local.tx_trace_cache_size, 100);
+        assert_eq!(config.local.integrity_audit_interval_sec, None);
+        assert_eq!(config.local.saved_states_retention, 0);
+        assert!(config.local.thread_state_save.is_empty());
+        assert!(config.archive_only_threads()?.is_empty());
+        assert!(config.local.clock_skew.ntp_servers.is_empty());
+        assert!(config.local.load_shedding.priority.is_empty());
+        assert_eq!(config.local.account_policy.mode, AccountPolicyMode::Disabled);
+        assert!(config.local.telemetry.endpoint.is_none());
+        assert!(config.local.telemetry.metrics_enabled);
 
         assert_eq!(config.global.time_to_produce_block_millis, 330);
+        assert_eq!(config.global.produce_on_demand_min_interval_millis, None);
         assert_eq!(config.global.need_synchronization_block_diff, 20);
         assert_eq!(
             config.global.min_time_between_state_publish_directives,
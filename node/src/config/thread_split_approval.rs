@@ -0,0 +1,43 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// How the thread splits proposed by the load balancing service are applied.
+/// The gate is local: it only holds the splits of the blocks produced by this
+/// node. Block keepers don't check the approval, so other producers of the
+/// thread split it as their own config says. Operators of all the keepers
+/// must enable it to hold the splits on the network.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ThreadSplitApprovalMode {
+    /// The producer splits the thread as soon as the split is proposed.
+    #[default]
+    Immediate,
+    /// Proposed splits are held and listed at `v2/thread_splits` with a
+    /// preview of the threads table and load, until an operator approves
+    /// them or the auto approve delay passes.
+    Approval,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct ThreadSplitApprovalConfig {
+    /// Defaults to immediate
+    #[serde(default)]
+    pub mode: ThreadSplitApprovalMode,
+
+    /// Seconds after the first proposal a held split is approved without an
+    /// operator. Unset to wait for the operator (dry-run).
+    /// Defaults to None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_approve_delay_secs: Option<u64>,
+}
+
+impl ThreadSplitApprovalConfig {
+    pub fn auto_approve_delay(&self) -> Option<Duration> {
+        self.auto_approve_delay_secs.map(Duration::from_secs)
+    }
+}
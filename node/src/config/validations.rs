@@ -23,6 +23,9 @@ impl Config {
                 self.global.time_to_produce_transaction_millis = Some(time_to_produce_block);
                 time_to_produce_block
             };
+        if let Some(min_interval) = self.global.produce_on_demand_min_interval_millis {
+            assert!(min_interval <= time_to_produce_block);
+        }
         let time_to_verify_block = self.global.time_to_verify_block_millis;
         assert!(time_to_verify_block >= time_to_produce_block);
         if let Some(timeout) = self.global.time_to_verify_transaction_millis {
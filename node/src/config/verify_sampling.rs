@@ -0,0 +1,52 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::block::producer::DEFAULT_VERIFY_COMPLEXITY;
+use crate::node::SignerIndex;
+
+/// How a block keeper decides whether to fully verify a block candidate.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifySamplingMode {
+    /// The share of verified blocks is derived from the BK set size, the
+    /// attestation targets and the chance of a successful attack. The verify
+    /// complexity is not used.
+    #[default]
+    Attestation,
+    /// The keeper verifies the share of blocks set by the block verify
+    /// complexity.
+    Constant,
+    /// The share of verified blocks is derived as in the attestation mode,
+    /// the block verify complexity is the lower bound of the share.
+    Adaptive,
+}
+
+/// Sampling of the blocks verified by the block keepers. Every keeper draws
+/// a number in `[0, 65536)` from its random seed and the block id, so
+/// keepers verify different blocks.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct VerifySamplingConfig {
+    /// Defaults to attestation
+    #[serde(default)]
+    pub mode: VerifySamplingMode,
+
+    /// Share of blocks (out of 65536) the keepers must verify, written by the
+    /// producer to the produced blocks. Keepers in the constant and adaptive
+    /// modes use the larger of the block and the configured values.
+    /// Defaults to 2048
+    #[serde(default = "default_verify_complexity")]
+    pub verify_complexity: SignerIndex,
+}
+
+fn default_verify_complexity() -> SignerIndex {
+    DEFAULT_VERIFY_COMPLEXITY
+}
+
+impl Default for VerifySamplingConfig {
+    fn default() -> Self {
+        Self { mode: VerifySamplingMode::default(), verify_complexity: default_verify_complexity() }
+    }
+}
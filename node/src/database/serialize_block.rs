@@ -15,6 +15,7 @@ use database::serialization::MessageSerializationSet;
 use database::serialization::TransactionSerializationSet;
 use database::sqlite::ArchAccount;
 use database::sqlite::ArchBlock;
+use database::sqlite::ArchBlockFees;
 use database::sqlite::ArchMessage;
 use database::sqlite::ArchTransaction;
 use parking_lot::Mutex;
@@ -45,6 +46,8 @@ use crate::block::producer::builder::EngineTraceInfoData;
 use crate::bls::envelope::BLSSignedEnvelope;
 use crate::bls::envelope::Envelope;
 use crate::bls::GoshBLS;
+use crate::types::extensions::CommonSectionExtensions;
+use crate::types::extensions::MintedShell;
 use crate::types::AccountAddress;
 use crate::types::AckiNackiBlock;
 
@@ -497,6 +500,7 @@ pub(crate) fn prepare_block_archive_struct(
     let common_section = envelope.data().get_common_section();
     set.producer_id = Some(common_section.producer_id.to_string());
     set.thread_id = Some(hex::encode(common_section.thread_id));
+    set.fees = Some(prepare_block_fees(block, &common_section.extensions)?);
 
     let block_info = block.read_info().map_err(|e| anyhow::format_err!("{e}"))?;
     set.flags = Some(block_info.flags() as i64);
@@ -544,6 +548,35 @@ pub(crate) fn prepare_block_archive_struct(
     Ok(set)
 }
 
+fn prepare_block_fees(
+    block: &Block,
+    extensions: &CommonSectionExtensions,
+) -> anyhow::Result<ArchBlockFees> {
+    let value_flow = block
+        .read_value_flow()
+        .map_err(|e| anyhow::format_err!("Failed to read block value flow: {e}"))?;
+    // Blocks of producers without fee accounting have no minted shell extension
+    let minted_shell = extensions
+        .get::<MintedShell>()
+        .inspect_err(|e| tracing::warn!(target: "database", "Minted shell is not archived: {e}"))
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    let minted_by_dapp = if minted_shell.0.is_empty() {
+        None
+    } else {
+        let minted_by_dapp: BTreeMap<&String, String> =
+            minted_shell.0.iter().map(|(dapp_id, minted)| (dapp_id, minted.to_string())).collect();
+        Some(serde_json::to_string(&minted_by_dapp)?)
+    };
+    Ok(ArchBlockFees {
+        fees_collected: value_flow.fees_collected.grams.as_u128().to_string(),
+        minted: minted_shell.minted().to_string(),
+        burned: minted_shell.burned().to_string(),
+        minted_by_dapp,
+    })
+}
+
 pub(crate) fn block_index(block: &Block) -> anyhow::Result<String> {
     let info =
         block.read_info().map_err(|e| anyhow::format_err!("Failed to read block info: {e}"))?;
@@ -0,0 +1,95 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use tvm_block::Message;
+
+// Headers of contracts that don't declare `time` and `expire` can't be told
+// apart from the function call, so the header is only trusted when `expire`
+// is close enough to `time`.
+const MAX_HEADER_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// Standard ABI header of an external inbound message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtMessageHeader {
+    /// Message creation time in milliseconds.
+    pub time: u64,
+    /// Time in seconds after which the contract rejects the message.
+    pub expire: u32,
+}
+
+impl ExtMessageHeader {
+    /// Body of an external message is `maybe(signature)`, `maybe(pubkey)`,
+    /// `time`, `expire` followed by the function call.
+    pub fn read(message: &Message) -> Option<Self> {
+        let mut body = message.body()?;
+        if body.get_next_bit().ok()? {
+            body.move_by(512).ok()?;
+        }
+        if body.get_next_bit().ok()? {
+            body.move_by(256).ok()?;
+        }
+        let time = body.get_next_u64().ok()?;
+        let expire = body.get_next_u32().ok()?;
+        let created_secs = time / 1000;
+        if (expire as u64) < created_secs || expire as u64 - created_secs > MAX_HEADER_TTL_SECS {
+            return None;
+        }
+        Some(Self { time, expire })
+    }
+
+    /// Returns true if the message expires before `deadline_ms`.
+    pub fn expires_before(&self, deadline_ms: u64) -> bool {
+        (self.expire as u64) * 1000 <= deadline_ms
+    }
+}
+
+/// Returns true if the message has the standard header and it expires
+/// before `deadline_ms`. Messages without the header never expire.
+pub fn is_expired_at(message: &Message, deadline_ms: u64) -> bool {
+    ExtMessageHeader::read(message).is_some_and(|header| header.expires_before(deadline_ms))
+}
+
+#[cfg(test)]
+mod tests {
+    use tvm_block::ExternalInboundMessageHeader;
+    use tvm_types::BuilderData;
+    use tvm_types::IBitstring;
+    use tvm_types::SliceData;
+
+    use super::*;
+
+    fn message(time: u64, expire: u32) -> Message {
+        let mut body = BuilderData::new();
+        body.append_bit_one().unwrap();
+        body.append_raw(&[0; 64], 512).unwrap();
+        body.append_bit_zero().unwrap();
+        body.append_u64(time).unwrap();
+        body.append_u32(expire).unwrap();
+        let mut message = Message::with_ext_in_header(ExternalInboundMessageHeader::default());
+        message.set_body(SliceData::load_builder(body).unwrap());
+        message
+    }
+
+    #[test]
+    fn test_read_header() {
+        let header = ExtMessageHeader::read(&message(1_700_000_000_000, 1_700_000_100));
+        assert_eq!(
+            header,
+            Some(ExtMessageHeader { time: 1_700_000_000_000, expire: 1_700_000_100 })
+        );
+
+        let message_without_body =
+            Message::with_ext_in_header(ExternalInboundMessageHeader::default());
+        assert_eq!(ExtMessageHeader::read(&message_without_body), None);
+        // Implausible `expire` means the contract has no such header
+        assert_eq!(ExtMessageHeader::read(&message(1_700_000_000_000, 1_600_000_000)), None);
+        assert_eq!(ExtMessageHeader::read(&message(1_700_000_000_000, u32::MAX)), None);
+    }
+
+    #[test]
+    fn test_expiration() {
+        let message = message(1_700_000_000_000, 1_700_000_100);
+        assert!(!is_expired_at(&message, 1_700_000_099_999));
+        assert!(is_expired_at(&message, 1_700_000_100_000));
+    }
+}
@@ -5,9 +5,17 @@
 // - It is allowed to pushback on incoming external messages.
 // - External messages are stored per blockchain thread.
 
+mod header;
+mod quarantine;
 mod queue;
+mod replay_guard;
 mod stamp;
 mod thread_state;
 
+pub use header::is_expired_at;
+pub use header::ExtMessageHeader;
+pub use quarantine::load_ext_msg_quarantine;
+pub use quarantine::start_ext_msg_quarantine_save_service;
+pub use replay_guard::ReplayGuard;
 pub use stamp::Stamp;
 pub use thread_state::ExternalMessagesThreadState;
@@ -0,0 +1,76 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use http_server::ExtMsgQuarantine;
+use http_server::QuarantinedExtMessage;
+
+use crate::config::ExtMsgQuarantineConfig;
+use crate::helper::SHUTDOWN_FLAG;
+use crate::repository::repository_impl::load_from_file;
+use crate::repository::repository_impl::save_to_file;
+
+const QUARANTINE_SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Loads the messages quarantined by the previous run. None if the quarantine
+/// is disabled.
+pub fn load_ext_msg_quarantine(
+    config: &ExtMsgQuarantineConfig,
+    path: &PathBuf,
+) -> anyhow::Result<Option<ExtMsgQuarantine>> {
+    if config.max_failures == 0 {
+        return Ok(None);
+    }
+    let quarantined: Vec<QuarantinedExtMessage> = load_from_file(path)
+        .map_err(|e| anyhow::format_err!("Failed to load ext messages quarantine {path:?}: {e}"))?
+        .unwrap_or_default();
+    tracing::info!(target: "ext_messages", "Loaded {} quarantined messages", quarantined.len());
+    Ok(Some(ExtMsgQuarantine::new(config.max_failures, quarantined)))
+}
+
+/// Persists the quarantine after the producers quarantine messages or an
+/// operator purges them.
+pub fn start_ext_msg_quarantine_save_service(
+    quarantine: ExtMsgQuarantine,
+    path: PathBuf,
+) -> anyhow::Result<()> {
+    let mut saved_version = quarantine.version();
+    loop {
+        let is_shutdown = SHUTDOWN_FLAG.get() == Some(&true);
+        let version = quarantine.version();
+        if version != saved_version {
+            // The version is read before the list, so a change made in
+            // between is saved with the next check
+            save_to_file(&path, &quarantine.list(None), false)?;
+            saved_version = version;
+        }
+        if is_shutdown {
+            return Ok(());
+        }
+        std::thread::sleep(QUARANTINE_SAVE_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quarantine_is_restored() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("quarantine");
+        let config = ExtMsgQuarantineConfig { max_failures: 1 };
+        assert!(load_ext_msg_quarantine(&ExtMsgQuarantineConfig { max_failures: 0 }, &path)
+            .unwrap()
+            .is_none());
+
+        let quarantine = load_ext_msg_quarantine(&config, &path).unwrap().unwrap();
+        assert!(quarantine.report_failure("aa", "01", "dst", "panic", 1));
+        save_to_file(&path, &quarantine.list(None), false).unwrap();
+
+        let quarantine = load_ext_msg_quarantine(&config, &path).unwrap().unwrap();
+        assert!(quarantine.is_quarantined("aa"));
+    }
+}
@@ -7,6 +7,7 @@ use std::collections::HashMap;
 use std::collections::VecDeque;
 
 use chrono::DateTime;
+use chrono::TimeDelta;
 use chrono::Utc;
 use derive_getters::Getters;
 use tvm_block::Message;
@@ -15,20 +16,50 @@ use crate::external_messages::stamp::Stamp;
 use crate::message::WrappedMessage;
 use crate::types::AccountAddress;
 
+// Window the processing rate is measured over
+const PROCESSING_RATE_WINDOW_SECS: i64 = 60;
+
 #[derive(Getters, Debug)]
 pub struct ExternalMessagesQueue {
     messages: BTreeMap<Stamp, (AccountAddress, WrappedMessage)>,
     last_index: u64,
+    // Number of erased messages by the erase time within the rate window
+    #[getter(skip)]
+    processed: VecDeque<(DateTime<Utc>, usize)>,
 }
 
 impl ExternalMessagesQueue {
     pub fn empty() -> Self {
-        Self { messages: BTreeMap::new(), last_index: 0 }
+        Self { messages: BTreeMap::new(), last_index: 0, processed: VecDeque::new() }
     }
 
-    pub fn erase_processed(&mut self, processed: &[Stamp]) {
+    pub fn erase_processed(&mut self, processed: &[Stamp], timestamp: DateTime<Utc>) {
         let to_remove: BTreeSet<_> = processed.iter().cloned().collect();
+        let len_before = self.messages.len();
         self.messages.retain(|stamp, _| !to_remove.contains(stamp));
+        self.processed.push_back((timestamp, len_before - self.messages.len()));
+        let window_start = timestamp - TimeDelta::seconds(PROCESSING_RATE_WINDOW_SECS);
+        while self.processed.front().is_some_and(|(erased_at, _)| *erased_at <= window_start) {
+            self.processed.pop_front();
+        }
+    }
+
+    /// Time the oldest waiting message was received at.
+    pub fn oldest_message_timestamp(&self) -> Option<DateTime<Utc>> {
+        // Stamps are ordered by the index, it grows with the receive time
+        self.messages.first_key_value().map(|(stamp, _)| stamp.timestamp)
+    }
+
+    /// Messages processed per second over the last minute.
+    pub fn processing_rate(&self, now: DateTime<Utc>) -> f64 {
+        let window_start = now - TimeDelta::seconds(PROCESSING_RATE_WINDOW_SECS);
+        let processed: usize = self
+            .processed
+            .iter()
+            .filter(|(erased_at, _)| *erased_at > window_start)
+            .map(|(_, count)| count)
+            .sum();
+        processed as f64 / PROCESSING_RATE_WINDOW_SECS as f64
     }
 
     pub fn push_external_messages(
@@ -51,6 +82,23 @@ impl ExternalMessagesQueue {
         self.last_index = cursor;
     }
 
+    /// Removes messages that satisfy the predicate, returns removed ones.
+    pub fn remove_messages(
+        &mut self,
+        mut predicate: impl FnMut(&WrappedMessage) -> bool,
+    ) -> Vec<WrappedMessage> {
+        let mut removed = vec![];
+        self.messages.retain(|_, (_, message)| {
+            if predicate(message) {
+                removed.push(message.clone());
+                false
+            } else {
+                true
+            }
+        });
+        removed
+    }
+
     pub fn unprocessed_messages(&self) -> HashMap<AccountAddress, VecDeque<(Stamp, Message)>> {
         let mut grouped_by_acc: HashMap<AccountAddress, VecDeque<(Stamp, Message)>> =
             HashMap::new();
@@ -0,0 +1,237 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::fs;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::config::ReplayProtectionConfig;
+use crate::types::BlockSeqNo;
+
+// The log is compacted when it holds this many times more records than the
+// horizon does
+const COMPACTION_FACTOR: usize = 2;
+const MIN_COMPACTION_RECORDS: usize = 1024;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+struct ProcessedMessage {
+    hash: [u8; 32],
+    processed_at_ms: u64,
+    block_seq_no: u32,
+}
+
+#[derive(Default)]
+struct ProcessedMessages {
+    // Ordered by the processing time
+    messages: VecDeque<ProcessedMessage>,
+    hashes: HashSet<[u8; 32]>,
+}
+
+impl ProcessedMessages {
+    fn push(&mut self, message: ProcessedMessage) -> bool {
+        if self.hashes.insert(message.hash) {
+            self.messages.push_back(message);
+            return true;
+        }
+        false
+    }
+
+    fn prune(&mut self, config: &ReplayProtectionConfig, now_ms: u64) {
+        let Some(last_block_seq_no) = self.messages.back().map(|message| message.block_seq_no)
+        else {
+            return;
+        };
+        let horizon_ms = config.horizon_sec.saturating_mul(1000);
+        while let Some(oldest) = self.messages.front() {
+            let within_time = oldest.processed_at_ms.saturating_add(horizon_ms) > now_ms;
+            let within_blocks =
+                oldest.block_seq_no.saturating_add(config.horizon_blocks) > last_block_seq_no;
+            if within_time || within_blocks {
+                break;
+            }
+            self.hashes.remove(&oldest.hash);
+            self.messages.pop_front();
+        }
+    }
+}
+
+/// Append-only file of the processed messages. Records beyond the horizon
+/// stay in the file until it is compacted.
+struct ProcessedLog {
+    path: PathBuf,
+    file: File,
+    records: usize,
+}
+
+impl ProcessedLog {
+    fn load(path: &PathBuf) -> anyhow::Result<Vec<ProcessedMessage>> {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        let mut reader = data.as_slice();
+        let mut messages = vec![];
+        // A crash may leave the last record truncated, it is skipped
+        while let Ok(message) = bincode::deserialize_from(&mut reader) {
+            messages.push(message);
+        }
+        Ok(messages)
+    }
+
+    /// Replaces the file with the given messages.
+    fn create<'a>(
+        path: PathBuf,
+        messages: impl IntoIterator<Item = &'a ProcessedMessage>,
+    ) -> anyhow::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut buffer = vec![];
+        let mut records = 0;
+        for message in messages {
+            bincode::serialize_into(&mut buffer, message)?;
+            records += 1;
+        }
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, buffer)?;
+        fs::rename(&tmp_path, &path)?;
+        let file = OpenOptions::new().append(true).open(&path)?;
+        Ok(Self { path, file, records })
+    }
+
+    fn append(&mut self, messages: &[ProcessedMessage]) -> anyhow::Result<()> {
+        let mut buffer = vec![];
+        for message in messages {
+            bincode::serialize_into(&mut buffer, message)?;
+        }
+        self.file.write_all(&buffer)?;
+        self.records += messages.len();
+        Ok(())
+    }
+}
+
+/// Remembers the external messages of the finalized blocks within the replay
+/// protection horizon. Without it the only protection is the routing registry
+/// of the messages in flight, which is empty after a restart.
+///
+/// The messages are appended to a log that is not synced, a crash may lose
+/// the latest ones.
+#[derive(Clone)]
+pub struct ReplayGuard {
+    config: ReplayProtectionConfig,
+    processed: Arc<Mutex<ProcessedMessages>>,
+    log: Arc<Mutex<ProcessedLog>>,
+}
+
+impl ReplayGuard {
+    /// Loads the messages persisted by the previous run, the ones beyond the
+    /// horizon are dropped.
+    pub fn load(
+        config: ReplayProtectionConfig,
+        path: PathBuf,
+        now_ms: u64,
+    ) -> anyhow::Result<Self> {
+        let mut processed = ProcessedMessages::default();
+        let persisted = ProcessedLog::load(&path)
+            .map_err(|e| anyhow::format_err!("Failed to load processed messages {path:?}: {e}"))?;
+        for message in persisted {
+            processed.push(message);
+        }
+        processed.prune(&config, now_ms);
+        let log = ProcessedLog::create(path.clone(), &processed.messages).map_err(|e| {
+            anyhow::format_err!("Failed to compact processed messages {path:?}: {e}")
+        })?;
+        tracing::info!(
+            target: "ext_messages",
+            "Loaded {} processed messages within the replay protection horizon",
+            processed.messages.len()
+        );
+        Ok(Self {
+            config,
+            processed: Arc::new(Mutex::new(processed)),
+            log: Arc::new(Mutex::new(log)),
+        })
+    }
+
+    pub fn is_processed(&self, hash: &[u8; 32]) -> bool {
+        self.processed.lock().hashes.contains(hash)
+    }
+
+    /// Records the messages of the finalized block and appends them to the
+    /// log.
+    pub fn record_processed(
+        &self,
+        hashes: impl IntoIterator<Item = [u8; 32]>,
+        block_seq_no: BlockSeqNo,
+        now_ms: u64,
+    ) -> anyhow::Result<()> {
+        let block_seq_no = u32::from(block_seq_no);
+        let mut processed = self.processed.lock();
+        let recorded: Vec<_> = hashes
+            .into_iter()
+            .map(|hash| ProcessedMessage { hash, processed_at_ms: now_ms, block_seq_no })
+            .filter(|message| processed.push(message.clone()))
+            .collect();
+        processed.prune(&self.config, now_ms);
+        // The log is locked before the messages are released, so the records
+        // are appended in the order of the messages
+        let mut log = self.log.lock();
+        let compaction_threshold =
+            (processed.messages.len() * COMPACTION_FACTOR).max(MIN_COMPACTION_RECORDS);
+        if log.records + recorded.len() > compaction_threshold {
+            let path = log.path.clone();
+            *log = ProcessedLog::create(path, &processed.messages)?;
+        } else {
+            drop(processed);
+            log.append(&recorded)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_guard_horizon_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("processed");
+        let config = ReplayProtectionConfig { horizon_sec: 10, horizon_blocks: 3 };
+
+        let guard = ReplayGuard::load(config.clone(), path.clone(), 0).unwrap();
+        guard.record_processed([[1; 32]], BlockSeqNo::from(1), 1_000).unwrap();
+        guard.record_processed([[2; 32]], BlockSeqNo::from(2), 2_000).unwrap();
+        assert!(guard.is_processed(&[1; 32]));
+
+        let guard = ReplayGuard::load(config.clone(), path.clone(), 5_000).unwrap();
+        assert!(guard.is_processed(&[1; 32]));
+        assert!(guard.is_processed(&[2; 32]));
+        assert!(!guard.is_processed(&[3; 32]));
+
+        // Beyond the time horizon, but still within the block horizon
+        guard.record_processed([[3; 32]], BlockSeqNo::from(3), 20_000).unwrap();
+        assert!(guard.is_processed(&[1; 32]));
+        // Beyond both horizons
+        guard.record_processed([[4; 32]], BlockSeqNo::from(4), 20_000).unwrap();
+        assert!(!guard.is_processed(&[1; 32]));
+        assert!(guard.is_processed(&[2; 32]));
+
+        // The pruned message stays in the log until it is compacted on load
+        assert_eq!(ProcessedLog::load(&path).unwrap().len(), 4);
+        let guard = ReplayGuard::load(config, path.clone(), 30_000).unwrap();
+        assert!(!guard.is_processed(&[1; 32]));
+        assert!(guard.is_processed(&[4; 32]));
+        assert_eq!(ProcessedLog::load(&path).unwrap().len(), 3);
+    }
+}
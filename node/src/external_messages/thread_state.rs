@@ -5,20 +5,31 @@ use std::collections::HashMap;
 use std::collections::VecDeque;
 use std::sync::Arc;
 
+use chrono::DateTime;
 use chrono::Utc;
 use http_server::ExtMsgFeedbackList;
+use http_server::ExtMsgQueueStats;
+use http_server::ExtMsgQueueStatus;
 use parking_lot::Mutex;
 use telemetry_utils::mpsc::InstrumentedSender;
+use tvm_block::GetRepresentationHash;
 use tvm_block::Message;
 use typed_builder::TypedBuilder;
 
+use crate::block::producer::builder::build_actions::create_message_expired_feedback;
 use crate::block::producer::builder::build_actions::create_queue_overflow_feedback;
+use crate::block::producer::builder::build_actions::create_replayed_message_feedback;
+use crate::external_messages::is_expired_at;
 use crate::external_messages::queue::ExternalMessagesQueue;
+use crate::external_messages::ReplayGuard;
 use crate::external_messages::Stamp;
 use crate::helper::metrics::BlockProductionMetrics;
 use crate::message::WrappedMessage;
+use crate::types::notification::Notification;
 use crate::types::AccountAddress;
+use crate::types::AckiNackiBlock;
 use crate::types::ThreadIdentifier;
+use crate::utilities::clock::CLOCK;
 use crate::utilities::guarded::AllowGuardedMut;
 use crate::utilities::guarded::Guarded;
 use crate::utilities::guarded::GuardedMut;
@@ -37,6 +48,13 @@ pub struct ExternalMessagesThreadStateConfig {
     thread_id: ThreadIdentifier,
     cache_size: usize,
     feedback_sender: InstrumentedSender<ExtMsgFeedbackList>,
+    #[builder(default, setter(strip_option))]
+    queue_status: Option<ExtMsgQueueStatus>,
+    // Messages expiring within this time are not expected to be included
+    #[builder(default)]
+    expiration_margin_ms: u64,
+    #[builder(default, setter(strip_option))]
+    replay_guard: Option<ReplayGuard>,
 }
 
 impl From<ExternalMessagesThreadStateConfig> for anyhow::Result<ExternalMessagesThreadState> {
@@ -48,6 +66,10 @@ impl From<ExternalMessagesThreadStateConfig> for anyhow::Result<ExternalMessages
             thread_id: config.thread_id,
             cache_size: config.cache_size,
             feedback_sender: config.feedback_sender,
+            queue_status: config.queue_status,
+            expiration_margin_ms: config.expiration_margin_ms,
+            replay_guard: config.replay_guard,
+            arrivals: Notification::new(),
         })
     }
 }
@@ -60,6 +82,11 @@ pub struct ExternalMessagesThreadState {
     thread_id: ThreadIdentifier,
     cache_size: usize,
     feedback_sender: InstrumentedSender<ExtMsgFeedbackList>,
+    queue_status: Option<ExtMsgQueueStatus>,
+    expiration_margin_ms: u64,
+    replay_guard: Option<ReplayGuard>,
+    // Touched on every push of new messages
+    arrivals: Notification,
 }
 
 impl ExternalMessagesThreadState {
@@ -72,14 +99,23 @@ impl ExternalMessagesThreadState {
 
         let now = Utc::now();
 
-        let (report_len, unused) = self.queue.guarded_mut(|q| {
+        let deadline_ms = self.expiration_deadline_ms();
+        let (expired, messages): (Vec<_>, Vec<_>) =
+            messages.iter().cloned().partition(|msg| is_expired_at(&msg.message, deadline_ms));
+        self.send_expired_feedbacks(expired)?;
+        let messages = self.reject_replayed(messages)?;
+
+        let (pushed, unused) = self.queue.guarded_mut(|q| {
             let remaining = self.cache_size.saturating_sub(q.messages().len());
 
             let (to_push, unused) = messages.split_at(remaining.min(messages.len()));
 
             q.push_external_messages(to_push, now);
-            (q.messages().len(), unused.to_vec())
+            (!to_push.is_empty(), unused.to_vec())
         });
+        if pushed {
+            self.arrivals.clone().touch();
+        }
 
         if !unused.is_empty() {
             let overflow_feedbacks: Vec<_> = unused
@@ -90,34 +126,146 @@ impl ExternalMessagesThreadState {
             let _ = self.feedback_sender.send(ExtMsgFeedbackList(overflow_feedbacks));
         }
 
-        if let Some(metrics) = &self.report_metrics {
-            metrics.report_ext_msg_queue_size(report_len, &self.thread_id);
+        self.report_queue_state(now);
+
+        Ok(())
+    }
+
+    fn reject_replayed(
+        &self,
+        messages: Vec<WrappedMessage>,
+    ) -> anyhow::Result<Vec<WrappedMessage>> {
+        let Some(replay_guard) = &self.replay_guard else {
+            return Ok(messages);
+        };
+        let (replayed, messages): (Vec<_>, Vec<_>) = messages.into_iter().partition(|msg| {
+            msg.message.hash().is_ok_and(|hash| replay_guard.is_processed(hash.as_slice()))
+        });
+        if !replayed.is_empty() {
+            let feedbacks: Vec<_> = replayed
+                .into_iter()
+                .map(|msg| create_replayed_message_feedback(msg.message, &self.thread_id))
+                .collect::<Result<_, _>>()?;
+            let _ = self.feedback_sender.send(ExtMsgFeedbackList(feedbacks));
         }
+        Ok(messages)
+    }
 
+    fn expiration_deadline_ms(&self) -> u64 {
+        CLOCK.now_ms() + self.expiration_margin_ms
+    }
+
+    fn send_expired_feedbacks(&self, expired: Vec<WrappedMessage>) -> anyhow::Result<()> {
+        if expired.is_empty() {
+            return Ok(());
+        }
+        self.report_metrics
+            .as_ref()
+            .inspect(|m| m.report_ext_msg_expired(expired.len() as u64, &self.thread_id));
+        let feedbacks: Vec<_> = expired
+            .into_iter()
+            .map(|msg| create_message_expired_feedback(msg.message, &self.thread_id))
+            .collect::<Result<_, _>>()?;
+        let _ = self.feedback_sender.send(ExtMsgFeedbackList(feedbacks));
         Ok(())
     }
 
     pub fn erase_processed(&self, processed: &[Stamp]) -> anyhow::Result<()> {
         tracing::trace!("erase_processed ext messages: {}", processed.len());
 
+        let now = Utc::now();
         let report_len = self.queue.guarded_mut(|q| {
-            q.erase_processed(processed);
+            q.erase_processed(processed, now);
             q.messages().len()
         });
 
         tracing::trace!(target: "ext_messages", "on erase: queue_size={}", report_len);
 
+        self.report_queue_state(now);
+
+        Ok(())
+    }
+
+    /// Records the external messages of the finalized block in the replay
+    /// guard. A produced block may still be invalidated, so its messages are
+    /// not recorded before it is finalized.
+    pub fn on_block_finalized(&self, block: &AckiNackiBlock) -> anyhow::Result<()> {
+        let Some(replay_guard) = &self.replay_guard else {
+            return Ok(());
+        };
+        let mut hashes = vec![];
+        block
+            .tvm_block()
+            .read_extra()
+            .and_then(|extra| extra.read_in_msg_descr())
+            .and_then(|in_msg_descr| {
+                in_msg_descr.iterate_objects(|in_msg| {
+                    let message = in_msg.read_message()?;
+                    if message.is_inbound_external() {
+                        hashes.push(*message.hash()?.as_slice());
+                    }
+                    Ok(true)
+                })
+            })
+            .map_err(|e| anyhow::format_err!("Failed to read block external messages: {e}"))?;
+        replay_guard.record_processed(hashes, block.seq_no(), CLOCK.now_ms())
+    }
+
+    fn report_queue_state(&self, now: DateTime<Utc>) {
+        if self.report_metrics.is_none() && self.queue_status.is_none() {
+            return;
+        }
+        let (depth, oldest_message_timestamp, processing_rate) = self.queue.guarded(|q| {
+            (q.messages().len(), q.oldest_message_timestamp(), q.processing_rate(now))
+        });
         if let Some(metrics) = &self.report_metrics {
-            metrics.report_ext_msg_queue_size(report_len, &self.thread_id);
+            metrics.report_ext_msg_queue_size(depth, &self.thread_id);
+            let oldest_age_ms = oldest_message_timestamp
+                .map(|timestamp| (now - timestamp).num_milliseconds().max(0) as u64)
+                .unwrap_or_default();
+            metrics.report_ext_msg_queue_age(oldest_age_ms, processing_rate, &self.thread_id);
         }
+        if let Some(queue_status) = &self.queue_status {
+            queue_status.publish(ExtMsgQueueStats {
+                thread_id: format!("{:x}", self.thread_id),
+                depth,
+                oldest_message_timestamp: oldest_message_timestamp
+                    .map(|timestamp| timestamp.timestamp_millis() as u64),
+                processing_rate,
+                timestamp: now.timestamp_millis() as u64,
+            });
+        }
+    }
 
-        Ok(())
+    pub fn get_pending_account_messages(&self, account: &AccountAddress) -> Vec<Message> {
+        self.queue.guarded(|q| {
+            q.messages()
+                .values()
+                .filter(|(acc_id, _)| acc_id == account)
+                .map(|(_, msg)| msg.message.clone())
+                .collect()
+        })
+    }
+
+    /// Notification touched when new messages are pushed to the queue.
+    pub fn arrivals(&self) -> Notification {
+        self.arrivals.clone()
     }
 
     pub fn get_remaining_external_messages(
         &self,
     ) -> anyhow::Result<HashMap<AccountAddress, VecDeque<(Stamp, Message)>>> {
         tracing::trace!("get_remaining_externals");
-        self.queue.guarded(|q| Ok(q.unprocessed_messages()))
+        // Messages could expire while waiting in the queue
+        let deadline_ms = self.expiration_deadline_ms();
+        let (expired, messages) = self.queue.guarded_mut(|q| {
+            let expired = q.remove_messages(|msg| is_expired_at(&msg.message, deadline_ms));
+            (expired, q.unprocessed_messages())
+        });
+        if !expired.is_empty() {
+            self.send_expired_feedbacks(expired)?;
+            self.report_queue_state(Utc::now());
+        }
+        Ok(messages)
     }
 }
@@ -9,19 +9,35 @@ use tvm_types::UInt256;
 use crate::repository::optimistic_state::OptimisticState;
 use crate::repository::repository_impl::RepositoryImpl;
 use crate::repository::Repository;
+use crate::repository::RepositoryError;
+use crate::types::BlockSeqNo;
 use crate::types::ThreadIdentifier;
+use crate::utilities::ErrorCode;
 
 pub fn get_account_from_shard_state(
     repository: Arc<Mutex<RepositoryImpl>>,
     account_address: &str,
 ) -> anyhow::Result<(tvm_block::Account, Option<UInt256>)> {
-    tracing::trace!("get_account_from_shard_state: address={account_address}");
+    get_account_from_state(repository, account_address, false)
+        .map(|(account, dapp_id, _)| (account, dapp_id))
+}
+
+/// Loads the account from the latest optimistic state of its thread if
+/// `optimistic` is set, from the last finalized state otherwise. Also returns
+/// seq_no of the state block.
+pub fn get_account_from_state(
+    repository: Arc<Mutex<RepositoryImpl>>,
+    account_address: &str,
+    optimistic: bool,
+) -> anyhow::Result<(tvm_block::Account, Option<UInt256>, BlockSeqNo)> {
+    tracing::trace!("get_account_from_state: address={account_address} optimistic={optimistic}");
 
     let repo = repository.lock();
 
     let state = repo
         .last_finalized_optimistic_state(&ThreadIdentifier::default())
-        .ok_or_else(|| anyhow::anyhow!("Shard state not found"))?;
+        .ok_or_else(|| RepositoryError::StateNotFound("Shard state not found".to_string()))
+        .map_err(|e| e.to_api_error())?;
 
     let acc_id = tvm_types::AccountId::from_string(account_address)
         .map_err(|_| anyhow::anyhow!("Invalid account address"))?
@@ -30,11 +46,14 @@ pub fn get_account_from_shard_state(
     let thread_id =
         state.get_thread_for_account(&acc_id).map_err(|_| anyhow::anyhow!("Account not found"))?;
 
-    let shard_state = repo
-        .last_finalized_optimistic_state(&thread_id)
-        .ok_or_else(|| anyhow::anyhow!("Shard state for thread_id {thread_id} not found"))?
-        .get_shard_state()
-        .clone();
+    let thread_state = if optimistic {
+        repo.last_optimistic_state(&thread_id)
+    } else {
+        repo.last_finalized_optimistic_state(&thread_id)
+    }
+    .ok_or_else(|| anyhow::anyhow!("Shard state for thread_id {thread_id} not found"))?;
+    let block_seq_no = thread_state.block_seq_no;
+    let shard_state = thread_state.get_shard_state().clone();
 
     let accounts = shard_state
         .read_accounts()
@@ -46,10 +65,9 @@ pub fn get_account_from_shard_state(
         .ok_or_else(|| anyhow::anyhow!("Can't find account in shard state"))?;
 
     if acc.is_external() {
-        // TODO:
-        // verify with @vasily. It does seem like a bug
-        // I guess it must be account_thread_state
-        let root = match state.cached_accounts.get(&acc_id) {
+        // Cells changed by the latest blocks are cached by the account thread state
+        let cached = thread_state.cached_accounts.get(&acc_id);
+        let root = match cached.or_else(|| state.cached_accounts.get(&acc_id)) {
             Some((_, acc_root)) => acc_root.clone(),
             None => repo.accounts_repository().load_account(
                 &acc_id,
@@ -65,5 +83,5 @@ pub fn get_account_from_shard_state(
     let dapp_id = acc.get_dapp_id().cloned();
     let account =
         acc.read_account().and_then(|acc| acc.as_struct()).map_err(|e| anyhow::anyhow!("{e}"))?;
-    Ok((account, dapp_id))
+    Ok((account, dapp_id, block_seq_no))
 }
@@ -0,0 +1,57 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use http_server::AccountNonce;
+use parking_lot::Mutex;
+use tvm_block::Account;
+use tvm_types::SliceData;
+
+use crate::external_messages::ExtMessageHeader;
+use crate::external_messages::ExternalMessagesThreadState;
+use crate::helper::account_boc_loader::get_account_from_shard_state;
+use crate::repository::repository_impl::RepositoryImpl;
+use crate::types::AccountAddress;
+use crate::types::ThreadIdentifier;
+
+pub fn get_account_nonce(
+    repository: Arc<Mutex<RepositoryImpl>>,
+    ext_messages: &Mutex<HashMap<ThreadIdentifier, ExternalMessagesThreadState>>,
+    replay_protection_code_hashes: &[String],
+    account_address: &str,
+) -> anyhow::Result<AccountNonce> {
+    let (account, _dapp_id) = get_account_from_shard_state(repository, account_address)?;
+    let address = AccountAddress::from_str(account_address)?;
+    let pending_messages = ext_messages
+        .lock()
+        .values()
+        .flat_map(|state| state.get_pending_account_messages(&address))
+        .collect::<Vec<_>>();
+    let pending_seqnos = pending_messages
+        .iter()
+        .filter_map(|message| ExtMessageHeader::read(message).map(|header| header.time))
+        .collect::<Vec<_>>();
+    Ok(AccountNonce::new(
+        account_address.to_string(),
+        stored_replay_protection_time(&account, replay_protection_code_hashes),
+        &pending_seqnos,
+        pending_messages.len(),
+        chrono::Utc::now().timestamp_millis() as u64,
+    ))
+}
+
+// Contracts with the `pubkey` and `time` headers store the timestamp of the
+// last accepted message right after the public key. The data of other
+// contracts is not read, the same bits mean something else there.
+fn stored_replay_protection_time(account: &Account, code_hashes: &[String]) -> Option<u64> {
+    let code_hash = account.get_code_hash()?.as_hex_string();
+    if !code_hashes.iter().any(|hash| hash.eq_ignore_ascii_case(&code_hash)) {
+        return None;
+    }
+    let mut data = SliceData::load_cell(account.get_data()?).ok()?;
+    data.move_by(256).ok()?;
+    data.get_next_u64().ok()
+}
@@ -0,0 +1,153 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::sync::Arc;
+
+use http_server::AccountsExportFilter;
+use http_server::AccountsExportRecord;
+use http_server::ExportedThreadState;
+use parking_lot::Mutex;
+use tokio::sync::mpsc;
+use tvm_types::base64_encode;
+use tvm_types::write_boc;
+
+use crate::repository::optimistic_state::OptimisticState;
+use crate::repository::repository_impl::RepositoryImpl;
+use crate::repository::Repository;
+use crate::types::AccountAddress;
+
+/// Streams the accounts matching the filter from the last finalized states of
+/// all threads: the manifest with the states, then the accounts and the end
+/// record. Errors are streamed as the error record. Stops when the receiver
+/// is dropped.
+pub fn export_accounts(
+    repository: Arc<Mutex<RepositoryImpl>>,
+    filter: AccountsExportFilter,
+    records: mpsc::Sender<AccountsExportRecord>,
+) {
+    match export_finalized_accounts(repository, &filter, &records) {
+        Ok(Some(count)) => {
+            tracing::trace!("Exported {count} accounts: {filter:?}");
+            let _ = records.blocking_send(AccountsExportRecord::End { count });
+        }
+        Ok(None) => {
+            tracing::trace!("Accounts export cancelled: {filter:?}");
+        }
+        Err(e) => {
+            tracing::warn!("Accounts export failed: {e}");
+            let _ = records.blocking_send(AccountsExportRecord::Error { message: e.to_string() });
+        }
+    }
+}
+
+// Returns the number of the exported accounts, None if the receiver is dropped
+fn export_finalized_accounts(
+    repository: Arc<Mutex<RepositoryImpl>>,
+    filter: &AccountsExportFilter,
+    records: &mpsc::Sender<AccountsExportRecord>,
+) -> anyhow::Result<Option<usize>> {
+    // Do not block the repository while the states are iterated
+    let (mut states, accounts_repository) = {
+        let repo = repository.lock();
+        let states = repo
+            .threads_with_finalized_states()
+            .into_iter()
+            .filter_map(|thread_id| repo.last_finalized_optimistic_state(&thread_id))
+            .collect::<Vec<_>>();
+        (states, repo.accounts_repository().clone())
+    };
+    if states.is_empty() {
+        anyhow::bail!("Finalized state not found");
+    }
+    states.sort_by_cached_key(|state| state.thread_id.to_string());
+
+    let manifest = AccountsExportRecord::Manifest {
+        code_hash: filter.code_hash.clone(),
+        address_prefix: filter.address_prefix.clone(),
+        states: states
+            .iter()
+            .map(|state| ExportedThreadState {
+                thread_id: state.thread_id.to_string(),
+                block_id: state.block_id.to_string(),
+                block_seq_no: state.block_seq_no.into(),
+            })
+            .collect(),
+    };
+    if records.blocking_send(manifest).is_err() {
+        return Ok(None);
+    }
+
+    let mut count = 0;
+    for state in states {
+        let thread_id = state.thread_id;
+        let accounts = state
+            .get_shard_state()
+            .read_accounts()
+            .map_err(|e| anyhow::format_err!("Failed to read shard state accounts: {e}"))?;
+        let mut cancelled = false;
+        accounts
+            .iterate_accounts(|account_id, mut shard_acc, _| {
+                let address = account_id.to_hex_string();
+                if filter
+                    .address_prefix
+                    .as_ref()
+                    .is_some_and(|address_prefix| !address.starts_with(address_prefix))
+                {
+                    return Ok(true);
+                }
+                // Thread states may keep accounts that were moved to other threads
+                let account_id = AccountAddress(account_id.clone());
+                if state
+                    .get_thread_for_account(&account_id)
+                    .map_err(|e| tvm_types::error!("{e}"))?
+                    != thread_id
+                {
+                    return Ok(true);
+                }
+                if shard_acc.is_external() {
+                    let root = match state.cached_accounts.get(&account_id) {
+                        Some((_, acc_root)) => acc_root.clone(),
+                        None => accounts_repository
+                            .load_account(
+                                &account_id,
+                                shard_acc.last_trans_hash(),
+                                shard_acc.last_trans_lt(),
+                            )
+                            .map_err(|e| tvm_types::error!("{e}"))?,
+                    };
+                    if root.repr_hash() != shard_acc.account_cell().repr_hash() {
+                        return Err(tvm_types::error!(
+                            "External account {address} cell hash mismatch"
+                        ));
+                    }
+                    shard_acc.set_account_cell(root);
+                }
+                let code_hash = shard_acc
+                    .read_account()?
+                    .as_struct()?
+                    .get_code_hash()
+                    .map(|h| h.to_hex_string());
+                if filter.code_hash.is_some() && code_hash != filter.code_hash {
+                    return Ok(true);
+                }
+                let record = AccountsExportRecord::Account {
+                    address: format!("0:{address}"),
+                    thread_id: thread_id.to_string(),
+                    code_hash,
+                    last_trans_lt: shard_acc.last_trans_lt(),
+                    boc: base64_encode(&write_boc(&shard_acc.account_cell())?),
+                };
+                if records.blocking_send(record).is_err() {
+                    cancelled = true;
+                    return Ok(false);
+                }
+                count += 1;
+                Ok(true)
+            })
+            .map_err(|e| anyhow::format_err!("Failed to iterate shard state accounts: {e}"))?;
+        if cancelled {
+            return Ok(None);
+        }
+    }
+    Ok(Some(count))
+}
@@ -0,0 +1,22 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::HashSet;
+
+use crate::bls::envelope::BLSSignedEnvelope;
+use crate::bls::envelope::Envelope;
+use crate::bls::GoshBLS;
+use crate::types::AckiNackiBlock;
+
+/// Ids of the accounts with transactions in a finalized block of the archive
+/// feed, used by the block manager subscription filters.
+pub fn block_account_ids(raw_block: &[u8]) -> Option<HashSet<String>> {
+    let envelope: Envelope<GoshBLS, AckiNackiBlock> = bincode::deserialize(raw_block)
+        .inspect_err(|e| tracing::warn!("Failed to parse archive feed block: {e}"))
+        .ok()?;
+    envelope
+        .data()
+        .get_account_ids()
+        .inspect_err(|e| tracing::warn!("Failed to read archive feed block accounts: {e}"))
+        .ok()
+}
@@ -0,0 +1,90 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::time::Duration;
+use std::time::SystemTime;
+
+use http_server::BlockInvalidationEvent;
+use http_server::BlockInvalidationFeed;
+
+use crate::node::BlockState;
+use crate::node::BlockStateRepository;
+use crate::utilities::guarded::Guarded;
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Builds the invalidation event of the block. The winner of the fork is
+/// looked up among the siblings of the invalidated branch root.
+pub fn block_invalidation_event(
+    block_state: &BlockState,
+    block_state_repository: &BlockStateRepository,
+) -> BlockInvalidationEvent {
+    let (block_id, thread_id, block_seq_no, parent_block_id) = block_state.guarded(|e| {
+        (
+            e.block_identifier().clone(),
+            *e.thread_identifier(),
+            *e.block_seq_no(),
+            e.parent_block_identifier().clone(),
+        )
+    });
+    let replaced_by = parent_block_id.as_ref().zip(thread_id.as_ref()).and_then(
+        |(parent_block_id, thread_id)| {
+            let parent = block_state_repository.get(parent_block_id).ok()?;
+            let siblings = parent.guarded(|e| {
+                if e.is_invalidated() {
+                    return None;
+                }
+                e.known_children(thread_id).cloned()
+            })?;
+            siblings.into_iter().filter(|sibling| *sibling != block_id).find(|sibling| {
+                block_state_repository.get(sibling).is_ok_and(|sibling| {
+                    sibling.guarded(|e| {
+                        !e.is_invalidated() && (e.is_finalized() || e.is_block_already_applied())
+                    })
+                })
+            })
+        },
+    );
+    BlockInvalidationEvent {
+        thread_id: thread_id.map(|thread_id| thread_id.to_string()).unwrap_or_default(),
+        block_id: block_id.to_string(),
+        block_seq_no: block_seq_no.map(u32::from).unwrap_or_default(),
+        parent_block_id: parent_block_id.map(|id| id.to_string()),
+        replaced_by: replaced_by.map(|id| id.to_string()),
+        timestamp: SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|duration| duration.as_millis() as u64)
+            .unwrap_or_default(),
+    }
+}
+
+/// Publishes the invalidated blocks to the feed and posts them to the
+/// webhooks. Returns when the block state repository is dropped.
+pub fn run_block_invalidation_notifier(
+    block_state_repository: BlockStateRepository,
+    feed: BlockInvalidationFeed,
+    webhooks: Vec<String>,
+) -> anyhow::Result<()> {
+    let invalidations = block_state_repository.subscribe_invalidations();
+    let client = reqwest::blocking::Client::builder().timeout(WEBHOOK_TIMEOUT).build()?;
+    while let Ok(block_state) = invalidations.recv() {
+        let event = block_invalidation_event(&block_state, &block_state_repository);
+        tracing::trace!("Block invalidated: {event:?}");
+        if !webhooks.is_empty() {
+            let body = serde_json::to_vec(&event)?;
+            for url in webhooks.iter() {
+                let result = client
+                    .post(url)
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .body(body.clone())
+                    .send()
+                    .and_then(|response| response.error_for_status());
+                if let Err(e) = result {
+                    tracing::warn!("Failed to post block invalidation to {url}: {e}");
+                }
+            }
+        }
+        feed.publish(event);
+    }
+    Ok(())
+}
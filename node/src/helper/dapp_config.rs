@@ -0,0 +1,55 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use http_server::ApiError;
+use http_server::DappConfigInfo;
+use parking_lot::Mutex;
+use tvm_block::Deserializable;
+use tvm_block::StateInit;
+
+use crate::creditconfig::abi::DAPP_CONFIG_TVC;
+use crate::creditconfig::dappconfig::calculate_dapp_config_address;
+use crate::creditconfig::dappconfig::decode_dapp_config_data;
+use crate::creditconfig::dappconfig::get_available_balance_from_config;
+use crate::helper::account_boc_loader::get_account_from_state;
+use crate::repository::repository_impl::RepositoryImpl;
+use crate::types::AccountAddress;
+use crate::types::DAppIdentifier;
+
+/// Reads the credit config of the dapp from the last finalized state of its
+/// DappConfig contract.
+pub fn get_dapp_config(
+    repository: Arc<Mutex<RepositoryImpl>>,
+    dapp_id: &str,
+) -> anyhow::Result<DappConfigInfo> {
+    let dapp_id = AccountAddress::from_str(dapp_id)
+        .map_err(|e| ApiError::new("INVALID_DAPP_ID", e.to_string(), false))?;
+    let base_config_stateinit = StateInit::construct_from_bytes(DAPP_CONFIG_TVC)
+        .map_err(|e| anyhow::format_err!("Failed to construct DAPP config tvc: {e}"))?;
+    let address =
+        calculate_dapp_config_address(DAppIdentifier(dapp_id.clone()), base_config_stateinit)?;
+    let not_found = || {
+        ApiError::new(
+            "DAPP_CONFIG_NOT_FOUND",
+            format!("Config of dapp {} is not deployed", dapp_id.0.to_hex_string()),
+            false,
+        )
+    };
+    let (account, _, block_seq_no) =
+        get_account_from_state(repository, &address.to_hex_string(), false)
+            .map_err(|_| not_found())?;
+    let config = decode_dapp_config_data(&account)?.ok_or_else(not_found)?;
+
+    Ok(DappConfigInfo {
+        dapp_id: dapp_id.0.to_hex_string(),
+        address: format!("0:{}", address.to_hex_string()),
+        is_unlimit: config.is_unlimit,
+        available_balance: config.available_balance.to_string(),
+        available_credit: (!config.is_unlimit)
+            .then(|| get_available_balance_from_config(config.clone()).to_string()),
+        block_seq_no: block_seq_no.into(),
+    })
+}
@@ -0,0 +1,82 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use http_server::DurableMessage;
+use http_server::DurableMessagesPage;
+use http_server::DurableMessagesQuery;
+use tvm_block::GetRepresentationHash;
+use tvm_block::Serializable;
+use tvm_types::base64_encode;
+
+use crate::message::WrappedMessage;
+use crate::storage::MessageDurableStorage;
+
+const SCAN_BATCH: usize = 100;
+// Messages scanned per request when few of them are in the lt range
+const MAX_SCANNED: usize = 10_000;
+
+/// Reads the messages from the durable storage for the API, so that the
+/// cross-thread delivery can be traced without access to the node storage.
+pub fn query_durable_messages(
+    message_db: &MessageDurableStorage,
+    query: DurableMessagesQuery,
+) -> anyhow::Result<DurableMessagesPage> {
+    let (dst, after_seq, lt_from, lt_to, limit) = match query {
+        DurableMessagesQuery::ByHash(hash) => {
+            let mut page = DurableMessagesPage::default();
+            if let Some((seq, message)) = message_db.read_message(&hash)? {
+                page.messages.push(durable_message(seq, &message)?);
+                page.last_seq = Some(seq);
+            }
+            return Ok(page);
+        }
+        DurableMessagesQuery::ByDestination { dst, after_seq, lt_from, lt_to, limit } => {
+            (dst, after_seq, lt_from, lt_to, limit)
+        }
+    };
+    let in_range = |lt: Option<u64>| {
+        let lt = lt.unwrap_or_default();
+        lt_from.is_none_or(|from| lt >= from) && lt_to.is_none_or(|to| lt <= to)
+    };
+    let mut page = DurableMessagesPage::default();
+    let mut cursor = after_seq;
+    let mut scanned = 0;
+    'scan: while scanned < MAX_SCANNED {
+        let (messages, last_seq) = message_db.next_simple(&dst, cursor, SCAN_BATCH)?;
+        if last_seq.is_none() {
+            break;
+        }
+        // Returned messages follow the cursor without gaps
+        for (seq, message) in (cursor + 1..).zip(messages) {
+            scanned += 1;
+            page.last_seq = Some(seq);
+            if in_range(message.message.lt()) {
+                page.messages.push(durable_message(seq, &message)?);
+                if page.messages.len() >= limit {
+                    break 'scan;
+                }
+            }
+        }
+        cursor = page.last_seq.unwrap_or(cursor);
+    }
+    Ok(page)
+}
+
+fn durable_message(seq: i64, message: &WrappedMessage) -> anyhow::Result<DurableMessage> {
+    let hash = message
+        .message
+        .hash()
+        .map_err(|e| anyhow::format_err!("Failed to calculate message hash: {e}"))?;
+    let boc = message
+        .message
+        .write_to_bytes()
+        .map_err(|e| anyhow::format_err!("Failed to serialize message: {e}"))?;
+    Ok(DurableMessage {
+        hash: hash.to_hex_string(),
+        seq,
+        src: message.message.src().map(|address| address.to_string()),
+        dst: message.message.dst().map(|address| address.to_string()),
+        created_lt: message.message.lt(),
+        boc: base64_encode(boc),
+    })
+}
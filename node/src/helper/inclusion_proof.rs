@@ -0,0 +1,164 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use http_server::signer_bitmap;
+use http_server::ApiError;
+use http_server::BlockAttestation;
+use http_server::InclusionProof;
+use parking_lot::Mutex;
+use tvm_block::AccountBlock;
+use tvm_block::Deserializable;
+use tvm_block::HashmapAugType;
+use tvm_block::Transaction;
+use tvm_types::base64_encode;
+use tvm_types::write_boc;
+use tvm_types::SliceData;
+use tvm_types::UInt256;
+
+use crate::bls::envelope::BLSSignedEnvelope;
+use crate::node::associated_types::AttestationTargetType;
+use crate::node::block_state::repository::BlockStateRepository;
+use crate::repository::repository_impl::RepositoryImpl;
+use crate::repository::Repository;
+use crate::types::BlockIdentifier;
+use crate::types::ThreadIdentifier;
+use crate::utilities::ErrorCode;
+
+// Attestations of a block are carried by its descendants, usually by the
+// next couple of blocks.
+const ATTESTATION_SEARCH_DEPTH: usize = 8;
+
+/// Builds the Merkle proof of the transaction against the block and finds the
+/// primary attestation of the block in its descendants.
+pub fn get_inclusion_proof(
+    repository: Arc<Mutex<RepositoryImpl>>,
+    block_state_repository: &BlockStateRepository,
+    block_id: &str,
+    transaction_id: &str,
+) -> anyhow::Result<InclusionProof> {
+    let block_id = BlockIdentifier::from_str(block_id)
+        .map_err(|e| ApiError::new("INVALID_BLOCK_ID", e.to_string(), false))?;
+    let transaction_hash = UInt256::from_str(transaction_id)
+        .map_err(|e| ApiError::new("INVALID_TRANSACTION_ID", e.to_string(), false))?;
+    let Some(thread_id) =
+        block_state_repository.get(&block_id)?.guarded(|e| *e.thread_identifier())
+    else {
+        return Err(ApiError::new(
+            "BLOCK_NOT_FOUND",
+            format!("Block not found: {block_id:x}"),
+            false,
+        )
+        .into());
+    };
+    let block = repository
+        .lock()
+        .get_block_from_repo_or_archive(&block_id, &thread_id)
+        .map_err(|e| e.to_api_error())?;
+    let (_, block_cell) = block.data().raw_block_data()?;
+
+    let Some(transaction) = find_transaction(block.data().tvm_block(), &transaction_hash)? else {
+        return Err(ApiError::new(
+            "TRANSACTION_NOT_FOUND",
+            format!("Transaction {transaction_id} is not included in the block {block_id:x}"),
+            false,
+        )
+        .into());
+    };
+    let proof = transaction
+        .prepare_proof(&block_cell)
+        .map_err(|e| anyhow::format_err!("Failed to prepare transaction proof: {e}"))?;
+    let proof =
+        write_boc(&proof).map_err(|e| anyhow::format_err!("Failed to write proof boc: {e}"))?;
+
+    Ok(InclusionProof {
+        block_id: format!("{block_id:x}"),
+        thread_id: format!("{thread_id:x}"),
+        block_seq_no: block.data().seq_no().into(),
+        transaction_id: transaction_hash.to_hex_string(),
+        proof: base64_encode(&proof),
+        attestation: find_attestation(&repository, block_state_repository, &block_id, thread_id)?,
+    })
+}
+
+fn find_transaction(
+    block: &tvm_block::Block,
+    transaction_hash: &UInt256,
+) -> anyhow::Result<Option<Transaction>> {
+    let mut found = None;
+    block
+        .read_extra()
+        .and_then(|extra| extra.read_account_blocks())
+        .and_then(|account_blocks| {
+            account_blocks.iterate_objects(|account_block: AccountBlock| {
+                account_block.transactions().iterate_slices(|_, transaction_slice| {
+                    let cell = transaction_slice.reference(0)?;
+                    if cell.repr_hash() == *transaction_hash {
+                        found =
+                            Some(Transaction::construct_from(&mut SliceData::load_cell(cell)?)?);
+                    }
+                    Ok(found.is_none())
+                })
+            })
+        })
+        .map_err(|e| anyhow::format_err!("Failed to read block transactions: {e}"))?;
+    Ok(found)
+}
+
+fn find_attestation(
+    repository: &Arc<Mutex<RepositoryImpl>>,
+    block_state_repository: &BlockStateRepository,
+    block_id: &BlockIdentifier,
+    thread_id: ThreadIdentifier,
+) -> anyhow::Result<Option<BlockAttestation>> {
+    let mut descendants = VecDeque::from([(block_id.clone(), 0)]);
+    let mut visited = HashSet::new();
+    while let Some((ancestor_id, depth)) = descendants.pop_front() {
+        if depth == ATTESTATION_SEARCH_DEPTH {
+            continue;
+        }
+        let children = block_state_repository
+            .get(&ancestor_id)?
+            .guarded(|e| e.known_children(&thread_id).cloned())
+            .unwrap_or_default();
+        for child_id in children {
+            if !visited.insert(child_id.clone()) {
+                continue;
+            }
+            // Children that are not stored yet don't have the attestations either
+            let Ok(child) = repository.lock().get_block_from_repo_or_archive(&child_id, &thread_id)
+            else {
+                continue;
+            };
+            let attestation =
+                child.data().get_common_section().block_attestations.iter().find(|attestation| {
+                    attestation.data().block_id() == block_id
+                        && *attestation.data().target_type() == AttestationTargetType::Primary
+                });
+            if let Some(attestation) = attestation {
+                let signature_occurrences =
+                    attestation.clone_signature_occurrences().into_iter().collect();
+                return Ok(Some(BlockAttestation {
+                    data: hex::encode(bincode::serialize(attestation.data())?),
+                    aggregated_signature: hex::encode(
+                        attestation.aggregated_signature().to_bytes(),
+                    ),
+                    signer_bitmap: hex::encode(signer_bitmap(
+                        attestation
+                            .signers()
+                            .copied()
+                            .filter(|signer| attestation.has_signer_index(*signer)),
+                    )),
+                    signature_occurrences,
+                    included_in_block_id: format!("{child_id:x}"),
+                }));
+            }
+            descendants.push_back((child_id, depth + 1));
+        }
+    }
+    Ok(None)
+}
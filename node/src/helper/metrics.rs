@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::sync::Arc;
 
 use http_server::metrics::RoutingMetrics;
@@ -8,11 +9,13 @@ use opentelemetry::metrics::Histogram;
 use opentelemetry::metrics::Meter;
 use opentelemetry::metrics::UpDownCounter;
 use opentelemetry::KeyValue;
+use telemetry_config::MetricsExportSwitch;
 use telemetry_utils::instrumented_channel_ext::XInstrumentedChannelMetrics;
 use telemetry_utils::mpsc::InstrumentedChannelMetrics;
 use telemetry_utils::out_of_bounds_guard;
 use telemetry_utils::TokioMetrics;
 
+use crate::node::NodeIdentifier;
 use crate::types::ThreadIdentifier;
 
 #[derive(Clone)]
@@ -22,15 +25,26 @@ struct BlockProductionMetricsInner {
     thread_load: Gauge<u64>,
     block_production_time: Histogram<u64>,
     block_production_time_correction: Gauge<i64>,
+    clock_offset: Gauge<i64>,
     block_apply_time: Histogram<u64>,
     finalization_time: Histogram<u64>,
     last_finalized_seqno: Gauge<u64>,
     ext_msg_queue_size: Gauge<u64>,
+    ext_msg_queue_oldest_age: Gauge<u64>,
+    ext_msg_processing_rate: Gauge<f64>,
+    ext_msg_expired: Counter<u64>,
     int_msg_queue_size: Gauge<u64>,
     block_finalized: Counter<u64>,
+    state_divergence: Counter<u64>,
+    producer_build_violations: Counter<u64>,
+    thread_storage_usage: Gauge<u64>,
+    thread_storage_quota_exceeded: Counter<u64>,
+    production_stall: Counter<u64>,
     tx_finalized: Counter<u64>,
     tx_aborted: Counter<u64>,
     ext_tx_aborted: Counter<u64>,
+    account_state_near_limit: Counter<u64>,
+    account_state_limit_exceeded: Counter<u64>,
     thread_count: UpDownCounter<i64>,
     finalization_gap: Gauge<u64>,
     memento_duration: Histogram<u64>,
@@ -62,6 +76,8 @@ struct BlockProductionMetricsInner {
     aerospike_write: Histogram<f64>,
     aerospike_write_err: Counter<u64>,
     aerospike_read_err: Counter<u64>,
+    int_message_bodies: Counter<u64>,
+    int_message_body_bytes: Counter<u64>,
     accounts_number: Gauge<u64>,
     generate_merkle_update_time: Histogram<u64>,
     outbound_accounts: Counter<u64>,
@@ -71,8 +87,36 @@ struct BlockProductionMetricsInner {
     broadcast_join: Counter<u64>,
     sync_time_spent: Counter<u64>,
     sync_error: Counter<u64>,
+    producer_restarts: Counter<u64>,
+    block_pre_validation_rejected: Counter<u64>,
+    verify_sampling: Counter<u64>,
+    producer_blocks: Counter<u64>,
+    producer_labels: parking_lot::Mutex<HashSet<String>>,
 }
 
+// Producers above the limit are reported under the same label, so that a
+// churning BK set doesn't blow up the series count
+const MAX_PRODUCER_LABELS: usize = 256;
+const OTHER_PRODUCERS_LABEL: &str = "other";
+
+/// Blocks received later than this number of production cycles after the
+/// block time are reported as late.
+pub const LATE_BLOCK_PRODUCTION_CYCLES: u64 = 3;
+
+/// Block of a producer as seen by this node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProducerBlockOutcome {
+    /// Candidate received from the producer
+    Produced,
+    /// Candidate received late, see `LATE_BLOCK_PRODUCTION_CYCLES`
+    Late,
+    /// Acked by this node after the verification
+    Acked,
+    /// Nacked by this node, with the failed check
+    Nacked(&'static str),
+}
+
+pub const ARCHIVE_FEED_CHANNEL: &str = "archive_feed";
 pub const BK_SET_UPDATE_CHANNEL: &str = "bk_set_update";
 pub const BLOCK_STATE_CHANNEL: &str = "block_state";
 pub const BLOB_SYNC_COMMAND_CHANNEL: &str = "block_sync_command";
@@ -81,7 +125,6 @@ pub const EXT_MSG_FEEDBACK_CHANNEL: &str = "ext_msg_feedback";
 pub const INBOUND_EXT_CHANNEL: &str = "inbound_ext";
 pub const PRODUCE_CONTROL_CHANNEL: &str = "produce_control";
 pub const PRODUCE_THREAD_RESULT_CHANNEL: &str = "produce_thread_result";
-pub const RAW_BLOCK_CHANNEL: &str = "raw_block";
 pub const ROUTING_COMMAND_CHANNEL: &str = "routing_command";
 pub const THREAD_RECEIVER_CHANNEL: &str = "thread_receiver";
 pub const AUTHORITY_RECEIVER_CHANNEL: &str = "authority_receiver";
@@ -102,15 +145,18 @@ pub struct Metrics {
     pub node: BlockProductionMetrics,
     pub routing: RoutingMetrics,
     pub tokio: TokioMetrics,
+    /// Applies `metrics_enabled` of the reloaded telemetry config
+    pub export: MetricsExportSwitch,
 }
 
 impl Metrics {
-    pub fn new(meter: &Meter) -> Self {
+    pub fn new(meter: &Meter, export: MetricsExportSwitch) -> Self {
         Self {
             net: NetMetrics::new(meter),
             node: BlockProductionMetrics::new(meter),
             routing: RoutingMetrics::new(meter),
             tokio: TokioMetrics::new(meter),
+            export,
         }
     }
 }
@@ -129,6 +175,7 @@ impl BlockProductionMetrics {
             block_production_time_correction: meter
                 .i64_gauge("node_block_production_time_correction")
                 .build(),
+            clock_offset: meter.i64_gauge("node_clock_offset").build(),
             block_apply_time: meter
                 .u64_histogram("node_block_apply_time")
                 .with_boundaries(vec![
@@ -154,11 +201,25 @@ impl BlockProductionMetrics {
                 .build(),
             last_finalized_seqno: meter.u64_gauge("node_last_finalized_seqno").build(),
             ext_msg_queue_size: meter.u64_gauge("node_ext_msg_queue_size").build(),
+            ext_msg_queue_oldest_age: meter.u64_gauge("node_ext_msg_queue_oldest_age").build(),
+            ext_msg_processing_rate: meter.f64_gauge("node_ext_msg_processing_rate").build(),
+            ext_msg_expired: meter.u64_counter("node_ext_msg_expired").build(),
             int_msg_queue_size: meter.u64_gauge("node_int_msg_queue_size").build(),
             block_finalized: meter.u64_counter("node_block_finalized").build(),
+            state_divergence: meter.u64_counter("node_state_divergence").build(),
+            producer_build_violations: meter.u64_counter("node_producer_build_violations").build(),
+            thread_storage_usage: meter.u64_gauge("node_thread_storage_usage").build(),
+            thread_storage_quota_exceeded: meter
+                .u64_counter("node_thread_storage_quota_exceeded")
+                .build(),
+            production_stall: meter.u64_counter("node_production_stall").build(),
             tx_finalized: meter.u64_counter("node_tx_finalized").build(),
             tx_aborted: meter.u64_counter("node_tx_aborted").build(),
             ext_tx_aborted: meter.u64_counter("node_ext_tx_aborted").build(),
+            account_state_near_limit: meter.u64_counter("node_account_state_near_limit").build(),
+            account_state_limit_exceeded: meter
+                .u64_counter("node_account_state_limit_exceeded")
+                .build(),
             thread_count: meter.i64_up_down_counter("node_thread_count").build(),
             finalization_gap: meter.u64_gauge("node_finalization_gap").build(),
             channel_len: meter.i64_up_down_counter("node_channel_len").build(),
@@ -263,6 +324,8 @@ impl BlockProductionMetrics {
                 .build(),
             aerospike_write_err: meter.u64_counter("node_aerospike_write_err").build(),
             aerospike_read_err: meter.u64_counter("node_aerospike_read_err").build(),
+            int_message_bodies: meter.u64_counter("node_int_message_bodies").build(),
+            int_message_body_bytes: meter.u64_counter("node_int_message_body_bytes").build(),
             outbound_accounts: meter.u64_counter("node_outbound_accounts").build(),
             saved_states_counter: meter.u64_counter("node_saved_states_counter").build(),
             bk_set: meter.u64_gauge("node_bk_set").build(),
@@ -270,6 +333,13 @@ impl BlockProductionMetrics {
             broadcast_join: meter.u64_counter("node_broadcast_join").build(),
             sync_time_spent: meter.u64_counter("node_sync_time_spent").build(),
             sync_error: meter.u64_counter("node_sync_error").build(),
+            producer_restarts: meter.u64_counter("node_producer_restarts").build(),
+            block_pre_validation_rejected: meter
+                .u64_counter("node_block_pre_validation_rejected")
+                .build(),
+            verify_sampling: meter.u64_counter("node_verify_sampling").build(),
+            producer_blocks: meter.u64_counter("node_producer_blocks").build(),
+            producer_labels: parking_lot::Mutex::new(HashSet::new()),
         }))
     }
 
@@ -294,6 +364,10 @@ impl BlockProductionMetrics {
             .record(correction_time, &[thread_id_attr(thread_id)]);
     }
 
+    pub fn report_clock_offset(&self, offset_ms: i64) {
+        self.0.clock_offset.record(offset_ms, &[]);
+    }
+
     pub fn report_block_apply_time(&self, value: u64, thread_id: &ThreadIdentifier) {
         out_of_bounds_guard!(value, "block_apply_time");
         self.0.block_apply_time.record(value, &[thread_id_attr(thread_id)]);
@@ -303,6 +377,33 @@ impl BlockProductionMetrics {
         self.0.generate_merkle_update_time.record(value, &[thread_id_attr(thread_id)]);
     }
 
+    pub fn report_state_divergence(&self, thread_id: &ThreadIdentifier) {
+        self.0.state_divergence.add(1, &[thread_id_attr(thread_id)]);
+    }
+
+    pub fn report_producer_build_violation(&self, thread_id: &ThreadIdentifier) {
+        self.0.producer_build_violations.add(1, &[thread_id_attr(thread_id)]);
+    }
+
+    pub fn report_thread_storage_usage(
+        &self,
+        thread_id: &ThreadIdentifier,
+        kind: &'static str,
+        bytes: u64,
+    ) {
+        self.0
+            .thread_storage_usage
+            .record(bytes, &[thread_id_attr(thread_id), KeyValue::new("kind", kind)]);
+    }
+
+    pub fn report_thread_storage_quota_exceeded(&self, thread_id: &ThreadIdentifier) {
+        self.0.thread_storage_quota_exceeded.add(1, &[thread_id_attr(thread_id)]);
+    }
+
+    pub fn report_production_stall(&self, thread_id: &ThreadIdentifier) {
+        self.0.production_stall.add(1, &[thread_id_attr(thread_id)]);
+    }
+
     pub fn report_finalization(&self, seq_no: u32, tx_count: usize, thread_id: &ThreadIdentifier) {
         self.0.block_finalized.add(1, &[thread_id_attr(thread_id)]);
         self.0.last_finalized_seqno.record(seq_no as u64, &[thread_id_attr(thread_id)]);
@@ -328,12 +429,34 @@ impl BlockProductionMetrics {
         self.0.ext_tx_aborted.add(1, &[thread_id_attr(thread_id)]);
     }
 
+    pub fn report_account_state_near_limit(&self, thread_id: &ThreadIdentifier) {
+        self.0.account_state_near_limit.add(1, &[thread_id_attr(thread_id)]);
+    }
+
+    pub fn report_account_state_limit_exceeded(&self, thread_id: &ThreadIdentifier) {
+        self.0.account_state_limit_exceeded.add(1, &[thread_id_attr(thread_id)]);
+    }
+
     pub fn report_ext_msg_queue_size(&self, value: usize, thread_id: &ThreadIdentifier) {
         self.0
             .ext_msg_queue_size
             .record(value as u64, &[KeyValue::new("thread", Self::thread_label(thread_id))]);
     }
 
+    pub fn report_ext_msg_queue_age(
+        &self,
+        oldest_age_ms: u64,
+        processing_rate: f64,
+        thread_id: &ThreadIdentifier,
+    ) {
+        self.0.ext_msg_queue_oldest_age.record(oldest_age_ms, &[thread_id_attr(thread_id)]);
+        self.0.ext_msg_processing_rate.record(processing_rate, &[thread_id_attr(thread_id)]);
+    }
+
+    pub fn report_ext_msg_expired(&self, count: u64, thread_id: &ThreadIdentifier) {
+        self.0.ext_msg_expired.add(count, &[thread_id_attr(thread_id)]);
+    }
+
     pub fn report_int_msg_queue_size(&self, value: usize, thread_id: &ThreadIdentifier) {
         self.0.int_msg_queue_size.record(
             value as u64,
@@ -497,6 +620,14 @@ impl BlockProductionMetrics {
         self.0.aerospike_read_err.add(1, &[KeyValue::new("object_type", object_type)]);
     }
 
+    // Deduplication ratio is the share of the bodies (or bytes) with
+    // `deduplicated=true`, their content was already stored
+    pub fn report_message_body_write(&self, size: usize, deduplicated: bool) {
+        let attrs = [KeyValue::new("deduplicated", deduplicated)];
+        self.0.int_message_bodies.add(1, &attrs);
+        self.0.int_message_body_bytes.add(size as u64, &attrs);
+    }
+
     pub fn report_outbound_accounts(&self, value: u64, thread_id: &ThreadIdentifier) {
         self.0.outbound_accounts.add(value, &[thread_id_attr(thread_id)]);
     }
@@ -516,6 +647,60 @@ impl BlockProductionMetrics {
     pub fn report_sync_error(&self, thread_id: &ThreadIdentifier) {
         self.0.sync_error.add(1, &[thread_id_attr(thread_id)]);
     }
+
+    pub fn report_producer_restart(&self, thread_id: &ThreadIdentifier) {
+        self.0.producer_restarts.add(1, &[thread_id_attr(thread_id)]);
+    }
+
+    pub fn report_block_pre_validation_rejected(
+        &self,
+        reason: &'static str,
+        thread_id: &ThreadIdentifier,
+    ) {
+        self.0
+            .block_pre_validation_rejected
+            .add(1, &[KeyValue::new("reason", reason), thread_id_attr(thread_id)]);
+    }
+
+    pub fn report_producer_block(
+        &self,
+        producer: &NodeIdentifier,
+        outcome: ProducerBlockOutcome,
+        thread_id: &ThreadIdentifier,
+    ) {
+        let (outcome, reason) = match outcome {
+            ProducerBlockOutcome::Produced => ("produced", ""),
+            ProducerBlockOutcome::Late => ("late", ""),
+            ProducerBlockOutcome::Acked => ("acked", ""),
+            ProducerBlockOutcome::Nacked(reason) => ("nacked", reason),
+        };
+        self.0.producer_blocks.add(
+            1,
+            &[
+                KeyValue::new("producer", self.producer_label(producer)),
+                KeyValue::new("outcome", outcome),
+                KeyValue::new("reason", reason),
+                thread_id_attr(thread_id),
+            ],
+        );
+    }
+
+    fn producer_label(&self, producer: &NodeIdentifier) -> String {
+        let label = producer.to_string();
+        let mut labels = self.0.producer_labels.lock();
+        if labels.contains(&label) || labels.len() < MAX_PRODUCER_LABELS {
+            labels.insert(label.clone());
+            label
+        } else {
+            OTHER_PRODUCERS_LABEL.to_string()
+        }
+    }
+
+    pub fn report_verify_sampling(&self, decision: &'static str, thread_id: &ThreadIdentifier) {
+        self.0
+            .verify_sampling
+            .add(1, &[KeyValue::new("decision", decision), thread_id_attr(thread_id)]);
+    }
 }
 
 impl InstrumentedChannelMetrics for BlockProductionMetrics {
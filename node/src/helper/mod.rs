@@ -2,9 +2,17 @@
 //
 
 pub mod account_boc_loader;
+pub mod account_nonce;
+pub mod accounts_export;
+pub mod archive_feed;
+pub mod block_invalidations;
 pub mod bp_resolver;
+pub mod dapp_config;
+pub mod durable_messages;
+pub mod inclusion_proof;
 pub mod key_handling;
 pub mod metrics;
+pub mod run_get;
 
 use std::path::Path;
 use std::path::PathBuf;
@@ -20,22 +28,22 @@ use opentelemetry::trace::Tracer;
 use opentelemetry::trace::TracerProvider;
 use opentelemetry::KeyValue;
 use opentelemetry_sdk::metrics::SdkMeterProvider;
-use opentelemetry_sdk::runtime::Tokio;
 use opentelemetry_sdk::trace;
-use opentelemetry_sdk::Resource;
-use telemetry_utils::get_metrics_endpoint;
-use telemetry_utils::init_meter_provider;
+use telemetry_config::TelemetryConfig;
 use tracing_appender::non_blocking::WorkerGuard;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::Layer;
 
+use crate::bls::metrics::BlsMetrics;
 use crate::helper::metrics::Metrics;
 use crate::node::NodeIdentifier;
 use crate::types::BlockIdentifier;
 
 pub const TIMING_TARGET: &str = "timing";
 
+const SERVICE_NAME: &str = "acki-nacki-node";
+
 pub static SHUTDOWN_FLAG: OnceLock<bool> = OnceLock::new();
 
 fn default_verbose_filter() -> tracing_subscriber::EnvFilter {
@@ -86,36 +94,31 @@ fn default_filter() -> tracing_subscriber::EnvFilter {
     // }
 }
 
-pub fn init_tracing() -> (Option<Metrics>, WorkerGuard) {
+pub fn init_tracing(telemetry: &TelemetryConfig) -> (Option<Metrics>, WorkerGuard) {
     let filter = default_filter();
     // if std::env::var(tracing_subscriber::EnvFilter::DEFAULT_ENV).is_ok() {
     // tracing_subscriber::EnvFilter::from_default_env()
     // } else {
     //     default_filter()
     // };
-    // According to OpenTelemetry Specification:
-    // The following environment variables configure the OTLP exporter:
-    // `OTEL_EXPORTER_OTLP_ENDPOINT`:
-    // The default endpoint used for all OTLP signal types (traces, metrics, logs) if more specific ones are not provided.
-    //
-    // `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`:
-    // Sets the endpoint just for traces.
-    //
-    // `OTEL_EXPORTER_OTLP_METRICS_ENDPOINT`:
-    // Sets the endpoint just for metrics.
-
-    let traces_endpoint = std::env::var("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT")
-        .or_else(|_| std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT"))
-        .ok();
+    // Exporters are configured by the `telemetry` section of the node config,
+    // unset fields are taken from the standard `OTEL_*` environment variables.
+    let tracer_provider = telemetry.tracer_provider(SERVICE_NAME).unwrap_or_else(|err| {
+        println!("Failed to init OTLP traces exporter: {err}");
+        None
+    });
 
     let (non_blocking, guard) = tracing_appender::non_blocking(std::io::stderr());
     if let Ok(Ok(targets)) =
         std::env::var("TELEMETRY_LOG").map(|x| tracing_subscriber::filter::Targets::from_str(&x))
     {
-        if let Some(endpoint) = traces_endpoint {
-            println!("Using OTLP traces endpoint: {endpoint}");
+        if let Some(tracer_provider) = tracer_provider {
+            println!(
+                "Using OTLP traces endpoint: {}",
+                telemetry.traces_endpoint().unwrap_or_default()
+            );
             let telemetry_layer = tracing_opentelemetry::layer()
-                .with_tracer(init_tracer(endpoint))
+                .with_tracer(init_tracer(tracer_provider))
                 .with_filter(tracing_subscriber::filter::filter_fn(|x| x.is_span()))
                 .with_filter(targets);
             tracing_subscriber::registry()
@@ -147,10 +150,10 @@ pub fn init_tracing() -> (Option<Metrics>, WorkerGuard) {
                 .with(telemetry_layer)
                 .init();
         }
-    } else if let Some(endpoint) = traces_endpoint {
-        println!("Using OTLP traces endpoint: {endpoint}");
+    } else if let Some(tracer_provider) = tracer_provider {
+        println!("Using OTLP traces endpoint: {}", telemetry.traces_endpoint().unwrap_or_default());
         let telemetry_layer = tracing_opentelemetry::layer()
-            .with_tracer(init_tracer(endpoint))
+            .with_tracer(init_tracer(tracer_provider))
             .with_filter(tracing_subscriber::filter::filter_fn(|x| x.is_span()));
         tracing_subscriber::registry()
             .with(
@@ -182,14 +185,26 @@ pub fn init_tracing() -> (Option<Metrics>, WorkerGuard) {
     }
 
     // Init metrics
-    if let Some(endpoint) = get_metrics_endpoint() {
-        tracing::info!("Using OTLP metrics endpoint: {endpoint}");
-        opentelemetry::global::set_meter_provider(init_meter_provider());
-        (Some(Metrics::new(&opentelemetry::global::meter("node"))), guard)
-    } else {
-        tracing::info!("No OTEL exporter endpoint found, metrics not collected.");
-        opentelemetry::global::set_meter_provider(SdkMeterProvider::builder().build());
-        (None, guard)
+    match telemetry.meter_provider(SERVICE_NAME) {
+        Ok(Some((meter_provider, export_switch))) => {
+            tracing::info!(
+                "Using OTLP metrics endpoint: {}",
+                telemetry.metrics_endpoint().unwrap_or_default()
+            );
+            opentelemetry::global::set_meter_provider(meter_provider);
+            let meter = opentelemetry::global::meter("node");
+            BlsMetrics::init(&meter);
+            (Some(Metrics::new(&meter, export_switch)), guard)
+        }
+        result => {
+            if let Err(err) = result {
+                tracing::error!("Failed to init OTLP metrics exporter: {err}");
+            } else {
+                tracing::info!("No OTEL exporter endpoint found, metrics not collected.");
+            }
+            opentelemetry::global::set_meter_provider(SdkMeterProvider::builder().build());
+            (None, guard)
+        }
     }
 }
 
@@ -199,22 +214,7 @@ pub fn shutdown_tracing(tracing_guard: WorkerGuard) {
     drop(tracing_guard);
 }
 
-pub fn init_tracer(endpoint: String) -> opentelemetry_sdk::trace::Tracer {
-    let default_service_name = KeyValue::new("service.name", "acki-nacki-node");
-
-    let resource = Resource::new(vec![default_service_name.clone()]).merge(&Resource::default());
-
-    tracing::info!("Using OTLP traces endpoint: {endpoint}");
-    let otlp_exporter = opentelemetry_otlp::SpanExporter::builder()
-        .with_tonic()
-        .build()
-        .expect("Failed to build OTLP exporter");
-
-    let tracer_provider = trace::TracerProvider::builder()
-        .with_batch_exporter(otlp_exporter, Tokio)
-        .with_resource(resource)
-        .build();
-
+pub fn init_tracer(tracer_provider: trace::TracerProvider) -> opentelemetry_sdk::trace::Tracer {
     opentelemetry::global::set_tracer_provider(tracer_provider.clone());
     tracer_provider.tracer("node")
 }
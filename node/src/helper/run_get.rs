@@ -0,0 +1,54 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::sync::Arc;
+
+use http_server::ApiError;
+use http_server::RunGetParams;
+use http_server::RunGetResult;
+use http_server::RunGetState;
+use parking_lot::Mutex;
+use tvm_block::Serializable;
+use tvm_client::tvm::run_get;
+use tvm_client::tvm::ParamsOfRunGet;
+use tvm_client::ClientContext;
+use tvm_types::base64_encode;
+
+use crate::helper::account_boc_loader::get_account_from_state;
+use crate::repository::repository_impl::RepositoryImpl;
+
+/// Runs a get-method of the account against the latest optimistic or the
+/// last finalized state, so clients don't need to load the account BOC.
+pub async fn run_get_method(
+    repository: Arc<Mutex<RepositoryImpl>>,
+    context: Arc<ClientContext>,
+    params: RunGetParams,
+) -> anyhow::Result<RunGetResult> {
+    let optimistic = params.state == RunGetState::Optimistic;
+    let (account, _dapp_id, block_seq_no) =
+        get_account_from_state(repository, &params.address, optimistic)
+            .map_err(|e| ApiError::new("ACCOUNT_NOT_FOUND", e.to_string(), false))?;
+    let boc = account
+        .write_to_bytes()
+        .map_err(|e| anyhow::format_err!("Failed to serialize account: {e}"))?;
+    let output = run_get(
+        context,
+        ParamsOfRunGet {
+            account: base64_encode(&boc),
+            function_name: params.method.clone(),
+            input: params.input,
+            execution_options: None,
+            tuple_list_as_array: None,
+        },
+    )
+    .await
+    .map_err(|e| ApiError::new("RUN_GET_FAILED", e.to_string(), false))?
+    .output;
+    Ok(RunGetResult {
+        address: params.address,
+        method: params.method,
+        state: params.state,
+        block_seq_no: block_seq_no.into(),
+        output,
+    })
+}
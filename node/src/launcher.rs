@@ -0,0 +1,2014 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+use std::any::Any;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::num::NonZero;
+use std::path::PathBuf;
+use std::process::exit;
+use std::str::FromStr;
+use std::sync::atomic::AtomicI32;
+use std::sync::atomic::AtomicU32;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use ext_messages_auth::auth::AccountRequest;
+use gossip::GossipConfig;
+use http_server::AccountNonceRequest;
+use http_server::AccountOwnershipFeed;
+use http_server::AccountsExportRequest;
+use http_server::AdminAuditLog;
+use http_server::BkHistoryInfo;
+use http_server::BkSetChangeEvent;
+use http_server::BkSetChangeFeed;
+use http_server::BkSetChangeKind;
+use http_server::BkSetHistory;
+use http_server::BkSetHistoryUpdate;
+use http_server::BlockInvalidationFeed;
+use http_server::BlockKeeperSetUpdate;
+use http_server::DappConfigRequest;
+use http_server::DurableMessagesRequest;
+use http_server::ExtMsgQueueStatus;
+use http_server::InclusionProofRequest;
+use http_server::IntegrityAudit;
+use http_server::NodeStatusBoard;
+use http_server::OutMsgQueueIndex;
+use http_server::ProducerRotationFeed;
+use http_server::ProductionStallFeed;
+use http_server::ResolvingResult;
+use http_server::RunGetRequest;
+use http_server::ThreadLoadFeed;
+use http_server::ThreadSplitGate;
+use http_server::TxTraceRegistry;
+use message_router::message_router::MessageRouter;
+use message_router::message_router::MessageRouterConfig;
+use message_router::read_keys_from_file;
+use network::config::NetworkConfig;
+use network::network::BasicNetwork;
+use network::network::PeerData;
+use network::port_mapping::run_port_mapping;
+use network::pub_sub::CertStore;
+use network::reachability::run_reachability_self_test;
+use network::resolver::sign_gossip_node;
+use network::resolver::WatchGossipConfig;
+use parking_lot::Mutex;
+use rand::prelude::SeedableRng;
+use rand::prelude::SmallRng;
+use signal_hook::consts::SIGHUP;
+use signal_hook::consts::SIGINT;
+use signal_hook::consts::SIGTERM;
+use signal_hook::iterator::Signals;
+use telemetry_config::MetricsExportSwitch;
+use telemetry_utils::mpsc::instrumented_channel;
+use telemetry_utils::now_ms;
+use tokio::task::JoinHandle;
+use transport_layer::msquic::MsQuicTransport;
+use transport_layer::CertHash;
+use transport_layer::TlsCertCache;
+use tvm_block::GetRepresentationHash;
+use tvm_block::Serializable;
+use tvm_client::ClientConfig;
+use tvm_client::ClientContext;
+use tvm_types::base64_encode;
+
+use crate::block::producer::account_policy::AccountListPolicy;
+use crate::block::producer::account_policy::MessagePolicy;
+use crate::block::producer::process::TVMBlockProducerProcess;
+use crate::block::producer::wasm::WasmNodeCache;
+use crate::block::producer::watchdog::start_production_watchdog;
+use crate::block::producer::watchdog::ProductionWatchdog;
+use crate::block_keeper_system::code_hash::resolve_epoch_code_hashes;
+use crate::block_keeper_system::BlockKeeperData;
+use crate::block_keeper_system::BlockKeeperSet;
+use crate::block_keeper_system::BlockKeeperSetChange;
+use crate::bls::GoshBLS;
+use crate::config::load_blockchain_config;
+use crate::config::load_config_from_file;
+use crate::config::BlockchainConfigSource;
+use crate::config::SheddableSubsystem;
+use crate::config::ThreadSplitApprovalMode;
+use crate::external_messages::load_ext_msg_quarantine;
+use crate::external_messages::start_ext_msg_quarantine_save_service;
+use crate::external_messages::ExternalMessagesThreadState;
+use crate::external_messages::ReplayGuard;
+use crate::helper::account_boc_loader::get_account_from_shard_state;
+use crate::helper::account_nonce::get_account_nonce;
+use crate::helper::accounts_export::export_accounts;
+use crate::helper::archive_feed::block_account_ids;
+use crate::helper::block_invalidations::run_block_invalidation_notifier;
+use crate::helper::bp_resolver::BPResolverImpl;
+use crate::helper::dapp_config::get_dapp_config;
+use crate::helper::durable_messages::query_durable_messages;
+use crate::helper::inclusion_proof::get_inclusion_proof;
+use crate::helper::init_tracing;
+use crate::helper::key_handling::key_pairs_from_file;
+use crate::helper::metrics::Metrics;
+use crate::helper::metrics::BLOCK_STATE_SAVE_CHANNEL;
+use crate::helper::metrics::OPTIMISTIC_STATE_SAVE_CHANNEL;
+use crate::helper::run_get::run_get_method;
+use crate::helper::shutdown_tracing;
+use crate::helper::SHUTDOWN_FLAG;
+use crate::message::WrappedMessage;
+use crate::multithreading::routing::service::Command;
+use crate::multithreading::routing::service::RoutingService;
+use crate::node::block_request_service::BlockRequestService;
+use crate::node::block_state::attestation_target_checkpoints::AncestorBlocksFinalizationCheckpoints;
+use crate::node::block_state::repository::BlockStateRepository;
+use crate::node::block_state::start_state_save_service;
+use crate::node::block_state::state::AttestationTarget;
+use crate::node::block_state::state::AttestationTargets;
+use crate::node::services::attestations_target::service::AttestationTargetsService;
+use crate::node::services::authority_switch::AuthoritySwitchService;
+use crate::node::services::block_processor::chain_pulse::events::ChainPulseEvent;
+use crate::node::services::block_processor::service::BlockProcessorService;
+use crate::node::services::block_processor::service::SecurityGuarantee;
+use crate::node::services::finalization::event_log::FinalizationEventLog;
+use crate::node::services::send_attestations::AttestationSendService;
+use crate::node::services::send_attestations::AttestationSendServiceHandler;
+use crate::node::services::statistics::median_descendants_chain_length_to_meet_threshold::BlockStatistics;
+use crate::node::services::sync::ExternalFileSharesBased;
+use crate::node::services::sync::FileSavingService;
+use crate::node::services::validation::feedback::AckiNackiSend;
+use crate::node::services::validation::service::ValidationService;
+use crate::node::unprocessed_blocks_collection::UnfinalizedCandidateBlockCollection;
+use crate::node::NetworkMessage;
+use crate::node::Node;
+use crate::node::NodeIdentifier;
+use crate::protocol::authority_switch;
+use crate::protocol::authority_switch::action_lock::Authority;
+use crate::protocol::authority_switch::round_time::RoundTime;
+use crate::repository::accounts::AccountsRepository;
+use crate::repository::load_saved_blocks::SavedBlocksLoader;
+use crate::repository::optimistic_state::OptimisticState;
+use crate::repository::repository_impl::BkSetUpdate;
+use crate::repository::repository_impl::FinalizedBlockStorage;
+use crate::repository::repository_impl::RepositoryImpl;
+use crate::repository::start_account_ownership_service;
+use crate::repository::start_integrity_audit_service;
+use crate::repository::start_optimistic_state_save_service;
+use crate::repository::start_state_checksum_service;
+use crate::repository::start_storage_quota_service;
+use crate::repository::Repository;
+use crate::repository::RepositoryError;
+use crate::services::blob_sync;
+use crate::services::cross_thread_ref_data_availability_synchronization::CrossThreadRefDataAvailabilitySynchronizationService;
+use crate::storage::ActionLockStorage;
+use crate::storage::AerospikeStore;
+use crate::storage::CachedStore;
+use crate::storage::CrossRefStorage;
+use crate::storage::LruSizedCache;
+use crate::storage::MessageDurableStorage;
+use crate::storage::DEFAULT_AEROSPIKE_MESSAGE_CACHE_MAX_ENTRIES;
+use crate::types::bp_selector::ProducerSelector;
+use crate::types::calculate_hash;
+use crate::types::extensions::ProducerBuild;
+use crate::types::thread_message_queue::account_messages_iterator::AccountMessagesIterator;
+use crate::types::AccountAddress;
+use crate::types::BlockHeight;
+use crate::types::BlockIdentifier;
+use crate::types::BlockSeqNo;
+use crate::types::CollectedAttestations;
+use crate::types::ThreadIdentifier;
+use crate::utilities::clock::start_clock_skew_monitor;
+use crate::utilities::guarded::Guarded;
+use crate::utilities::guarded::GuardedMut;
+use crate::utilities::load_controller::forward_archive_feed;
+use crate::utilities::load_controller::LOAD_CONTROLLER;
+use crate::utilities::thread_spawn_critical::SpawnCritical;
+use crate::utilities::ErrorCode;
+use crate::utilities::FixedSizeHashSet;
+use crate::zerostate::ZeroState;
+
+// const ALIVE_NODES_WAIT_TIMEOUT_MILLIS: u64 = 100;
+const MINIMUM_NUMBER_OF_CORES: usize = 8;
+const DEFAULT_NACK_SIZE_CACHE: usize = 1000;
+const GOSSIP_PEERS_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+lazy_static::lazy_static!(
+    /// Version with the build info, printed by `--version` of the node binaries.
+    pub static ref LONG_VERSION: String = format!("{}\nBUILD_GIT_BRANCH={}\nBUILD_GIT_COMMIT={}\nBUILD_GIT_DATE={}\nBUILD_TIME={}",
+        env!("CARGO_PKG_VERSION"),
+        env!("BUILD_GIT_BRANCH"),
+        env!("BUILD_GIT_COMMIT"),
+        env!("BUILD_GIT_DATE"),
+        env!("BUILD_TIME"),
+    );
+);
+
+#[cfg(feature = "rayon_affinity")]
+fn init_rayon() {
+    let cores = core_affinity::get_core_ids().expect("Unable to get core IDs");
+    let custom_stack_size = 16 * 1024 * 1024;
+    // Build Rayon thread pool and set affinity for each thread
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(cores.len())
+        .thread_name(|thread_index| format!("rayon{thread_index}"))
+        .stack_size(custom_stack_size)
+        .start_handler(move |thread_index| {
+            let core_id = cores[thread_index % cores.len()];
+            core_affinity::set_for_current(core_id);
+        })
+        .build_global()
+        .unwrap();
+}
+
+/// Runs the node with the config at `config_path` until it is stopped and
+/// exits the process. It is shared by the `node` and `acki-nacki node run`
+/// binaries.
+pub fn run(config_path: PathBuf) -> ! {
+    eprintln!("Starting Acki-Nacki Node version: {}", *LONG_VERSION);
+    debug_used_features();
+    if cfg!(debug_assertions) {
+        std::env::set_var("RUST_BACKTRACE", "1");
+    }
+
+    #[cfg(feature = "rayon_affinity")]
+    init_rayon();
+
+    // unsafe { backtrace_on_stack_overflow::enable() };
+
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .thread_stack_size(100 * 1024 * 1024)
+        .build()
+        .expect("Failed to create Tokio runtime")
+        .block_on(async { tokio_main(config_path).await });
+
+    exit(0);
+}
+
+async fn tokio_main(config_path: PathBuf) {
+    // Config errors are reported by `execute` after tracing is initialized
+    let telemetry = load_config_from_file(&config_path)
+        .map(|config| config.local.telemetry)
+        .unwrap_or_default()
+        .with_env_defaults();
+    let (metrics, tracing_guard) = init_tracing(&telemetry);
+    tracing::info!("Tracing and metrics initialized");
+
+    #[cfg(feature = "misbehave")]
+    tracing::warn!("Node run with --feature misbehave set");
+
+    let exit_code = match execute(config_path, metrics).await {
+        Ok(_) => 0,
+        Err(err) => {
+            tracing::error!("{err:?}");
+            1
+        }
+    };
+    shutdown_tracing(tracing_guard);
+    exit(exit_code);
+}
+
+fn bk_set_update(
+    seq_no: u32,
+    current: Option<&BlockKeeperSet>,
+    future: Option<&BlockKeeperSet>,
+) -> BlockKeeperSetUpdate {
+    BlockKeeperSetUpdate {
+        seq_no,
+        current: collect_node_id_owner_pk(current),
+        future: collect_node_id_owner_pk(future),
+    }
+}
+
+fn collect_node_id_owner_pk(bk_set: Option<&BlockKeeperSet>) -> Vec<(String, [u8; 32])> {
+    let Some(bk_set) = bk_set else {
+        return vec![];
+    };
+    bk_set
+        .iter_node_ids()
+        .filter_map(|node_id| {
+            bk_set.get_by_node_id(node_id).map(|x| (node_id.to_string(), x.owner_pubkey))
+        })
+        .collect()
+}
+
+fn bk_set_history_update(update: &BkSetUpdate) -> BkSetHistoryUpdate {
+    BkSetHistoryUpdate {
+        thread_id: update.thread_id.to_string(),
+        seq_no: update.seq_no,
+        current: collect_bk_history_info(update.current.as_deref()),
+        future: collect_bk_history_info(update.future.as_deref()),
+    }
+}
+
+fn collect_bk_history_info(bk_set: Option<&BlockKeeperSet>) -> Vec<BkHistoryInfo> {
+    let Some(bk_set) = bk_set else {
+        return vec![];
+    };
+    bk_set
+        .iter_node_ids()
+        .filter_map(|node_id| bk_set.get_by_node_id(node_id).map(|data| (node_id, data)))
+        .map(|(node_id, data)| bk_history_info(node_id, data))
+        .collect()
+}
+
+fn bk_history_info(node_id: &NodeIdentifier, data: &BlockKeeperData) -> BkHistoryInfo {
+    BkHistoryInfo {
+        signer_index: data.signer_index,
+        node_id: node_id.to_string(),
+        pubkey: hex::encode(data.pubkey.as_ref().to_bytes()),
+        node_owner_pk: hex::encode(data.owner_pubkey),
+        stake: data.stake.to_string(),
+        epoch_finish_seq_no: data.epoch_finish_seq_no,
+    }
+}
+
+fn bk_set_change_events(update: &BkSetUpdate) -> Vec<BkSetChangeEvent> {
+    update
+        .changes
+        .iter()
+        .map(|change| {
+            let (change, (_, data)) = match change {
+                BlockKeeperSetChange::BlockKeeperAdded(added) => (BkSetChangeKind::Added, added),
+                BlockKeeperSetChange::BlockKeeperRemoved(removed) => {
+                    (BkSetChangeKind::Removed, removed)
+                }
+                BlockKeeperSetChange::FutureBlockKeeperAdded(added) => {
+                    (BkSetChangeKind::FutureAdded, added)
+                }
+            };
+            BkSetChangeEvent {
+                thread_id: update.thread_id.to_string(),
+                block_id: update.block_id.to_string(),
+                block_seq_no: update.seq_no,
+                // Changes of a block are applied to the bk set of its descendants
+                effective_seq_no: update.seq_no + 1,
+                change,
+                block_keeper: bk_history_info(
+                    &NodeIdentifier::from(data.owner_address.clone()),
+                    data,
+                ),
+            }
+        })
+        .collect()
+}
+
+fn verify_zerostate(zs: &ZeroState, message_db: &MessageDurableStorage) -> anyhow::Result<()> {
+    let mut messages = HashSet::new();
+    for state in zs.states().values() {
+        let messages_queue = state.messages().clone();
+        let acc_iter = messages_queue.iter(message_db);
+        for account_messages in acc_iter {
+            for msg in account_messages {
+                let (message, _) =
+                    msg.map_err(|e| anyhow::format_err!("Failed to unpack message: {e:?}"))?;
+                let tvm_message = message.message.clone();
+                if !messages.insert(
+                    tvm_message
+                        .hash()
+                        .map_err(|e| anyhow::format_err!("Failed to calc message hash: {e}"))?,
+                ) {
+                    let hash = tvm_message.hash().map(|x| x.to_hex_string()).unwrap_or_default();
+                    let src =
+                        tvm_message.src().map(|x| x.address().to_hex_string()).unwrap_or_default();
+                    let dst =
+                        tvm_message.dst().map(|x| x.address().to_hex_string()).unwrap_or_default();
+                    return Err(anyhow::format_err!(
+                        "Duplicated message in zerostate: {hash}, src: {src}, dst: {dst}"
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[allow(clippy::await_holding_lock)]
+async fn execute(config_path: PathBuf, metrics: Option<Metrics>) -> anyhow::Result<()> {
+    tracing::info!("Starting network");
+
+    tracing::info!("Loading config");
+    let tls_cert_cache = TlsCertCache::new()?;
+    let mut config = load_config_from_file(&config_path)?.ensure_min_cpu(MINIMUM_NUMBER_OF_CORES);
+    let network_config = config.network_config(Some(tls_cert_cache.clone()))?;
+    let network_credential = network_config.credential.clone();
+    let gossip_config = config.gossip_config()?;
+    tracing::info!("Loaded config");
+
+    tracing::info!("Node config: {}", serde_json::to_string_pretty(&config)?);
+    tracing::info!("Gossip seeds expanded: {:?}", gossip_config.seeds);
+    tracing::info!("Gossip advertise addr: {:?}", gossip_config.advertise_addr);
+
+    let node_metrics = metrics.as_ref().map(|m| m.node.clone());
+    let socket_address =
+        std::env::var("AEROSPIKE_SOCKET_ADDR").unwrap_or_else(|_| "127.0.0.1:3000".to_string());
+    let set_prefix = std::env::var("AEROSPIKE_SET_PREFIX").unwrap_or_else(|_| "node".to_string());
+
+    let num_cached_entries = std::env::var("AEROSPIKE_CACHE_MESSAGE_MAX_ENTRIES")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_AEROSPIKE_MESSAGE_CACHE_MAX_ENTRIES);
+
+    let aerospike_store = AerospikeStore::new(socket_address, node_metrics.clone())?;
+
+    let cache = LruSizedCache::new(num_cached_entries);
+    let aerospike_cached_store = CachedStore::new(aerospike_store.clone(), cache);
+    let message_db = MessageDurableStorage::new(
+        aerospike_cached_store,
+        &format!("m-{set_prefix}"),
+        node_metrics.clone(),
+    );
+
+    // These two dbs do not need cache (some cache is implemented in the code yet).
+    // Aerospike store can be shared among different store types.
+    let crossref_db = CrossRefStorage::new(aerospike_store.clone(), &format!("c-{set_prefix}"));
+    let action_lock_db = ActionLockStorage::new(aerospike_store, &format!("a-{set_prefix}"));
+
+    let zerostate =
+        ZeroState::load_from_file(&config.local.zerostate_path).expect("Failed to open zerostate");
+    verify_zerostate(&zerostate, &message_db)?;
+    let bk_set = zerostate.get_block_keeper_set()?;
+
+    let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+    let initial_bk_set_update = bk_set_update(0, Some(&bk_set), None);
+    let (bk_set_update_async_tx, bk_set_update_async_rx) =
+        tokio::sync::watch::channel(initial_bk_set_update.clone());
+    let (watch_gossip_config_tx, watch_gossip_config_rx) =
+        tokio::sync::watch::channel(WatchGossipConfig { trusted_pubkeys: HashSet::default() });
+    let (config_tx, config_rx) = tokio::sync::watch::channel(config.clone());
+    let (gossip_config_tx, gossip_config_rx) = tokio::sync::watch::channel(gossip_config);
+    let (network_config_tx, network_config_rx) = tokio::sync::watch::channel(network_config);
+    tokio::spawn(dispatch_hot_reload(
+        tls_cert_cache.clone(),
+        shutdown_rx.clone(),
+        config_rx,
+        bk_set_update_async_rx.clone(),
+        network_config_tx,
+        gossip_config_tx,
+        watch_gossip_config_tx,
+        metrics.as_ref().map(|m| m.export.clone()),
+    ));
+    let (gossip_handle, gossip_rest_handle) =
+        gossip::run(shutdown_rx.clone(), gossip_config_rx, chitchat::transport::UdpTransport)
+            .await?;
+    let gossip_listen_addr_clone = config.network.gossip_listen_addr;
+    let gossip_advertise_addr =
+        config.network.gossip_advertise_addr.unwrap_or(gossip_listen_addr_clone);
+    tracing::info!("Gossip advertise addr: {:?}", gossip_advertise_addr);
+
+    let gossip_node = config.gossip_peer()?;
+    let set_gossip_node_state = |c: &mut chitchat::Chitchat| {
+        gossip_node.set_to(c.self_node_state());
+        c.self_node_state().set(
+            crate::node::services::sync::GOSSIP_API_ADVERTISE_ADDR_KEY,
+            config.network.api_advertise_addr.to_string(),
+        );
+        // Set before signing, so peers can trust the advertised build
+        let build = ProducerBuild::current();
+        c.self_node_state()
+            .set(crate::node::services::sync::GOSSIP_NODE_VERSION_KEY, build.version);
+        c.self_node_state().set(crate::node::services::sync::GOSSIP_NODE_COMMIT_KEY, build.commit);
+        if let Ok(Some(key)) = transport_layer::resolve_signing_key(
+            config.network.my_ed_key_secret.clone(),
+            config.network.my_ed_key_path.clone(),
+        ) {
+            sign_gossip_node(c.self_node_state(), key);
+        }
+    };
+    gossip_handle.with_chitchat(set_gossip_node_state).await;
+
+    // Additional clusters only advertise the node, their peers are not used
+    let mut extra_gossip_handles = vec![];
+    for cluster_config in config.extra_gossip_configs()? {
+        let cluster_id = cluster_config.cluster_id.clone();
+        tracing::info!(
+            "Joining gossip cluster {cluster_id} with advertise addr {:?}",
+            cluster_config.advertise_addr()
+        );
+        let (_, cluster_config_rx) = tokio::sync::watch::channel(cluster_config);
+        let (cluster_handle, cluster_rest_handle) =
+            gossip::run(shutdown_rx.clone(), cluster_config_rx, chitchat::transport::UdpTransport)
+                .await?;
+        cluster_handle.with_chitchat(set_gossip_node_state).await;
+        tokio::spawn(async move {
+            let result = cluster_rest_handle.await;
+            tracing::error!("Gossip cluster {cluster_id} rest server stopped: {result:?}");
+        });
+        extra_gossip_handles.push(cluster_handle);
+    }
+
+    let port_mapping = run_port_mapping(
+        config.network.port_mapping.clone(),
+        vec![config.network.bind, config.network.gossip_listen_addr],
+        shutdown_rx.clone(),
+    );
+    tokio::spawn(async move {
+        if let Err(e) = port_mapping.await {
+            tracing::error!("Port mapping failed: {e}");
+        }
+    });
+
+    let network = BasicNetwork::new(shutdown_tx, network_config_rx, MsQuicTransport::default());
+    let chitchat = gossip_handle.chitchat();
+
+    let wasm_cache = WasmNodeCache::new()?;
+
+    let (ext_messages_sender, ext_messages_receiver) = instrumented_channel(
+        metrics.as_ref().map(|x| x.node.clone()),
+        crate::helper::metrics::INBOUND_EXT_CHANNEL,
+    );
+    let (direct_tx, broadcast_tx, incoming_rx, nodes_rx) = network
+        .start(
+            watch_gossip_config_rx,
+            metrics.as_ref().map(|m| m.net.clone()),
+            metrics.as_ref().map(|m| m.node.clone()),
+            config.local.node_id.clone(),
+            false,
+            chitchat.clone(),
+        )
+        .await?;
+    if config.network.reachability_self_test {
+        tokio::spawn(run_reachability_self_test(
+            MsQuicTransport::default(),
+            network_credential,
+            config.network.node_advertise_addr,
+            config
+                .network
+                .proxies
+                .iter()
+                .chain(config.network.subscribe.iter().flatten())
+                .copied()
+                .collect(),
+            nodes_rx.clone(),
+        ));
+    }
+    let broadcast_tx = broadcast_tx
+        .with_thread_tag(|message: &NetworkMessage| {
+            message.thread_id().map(|thread_id| format!("{thread_id:x}"))
+        })
+        .with_min_protocol_version(NetworkMessage::min_protocol_version);
+
+    let bp_thread_count = Arc::<AtomicI32>::default();
+    LOAD_CONTROLLER.configure(config.local.load_shedding.clone());
+    let (archive_feed_sender, archive_feed_receiver) =
+        instrumented_channel::<(NodeIdentifier, Vec<u8>)>(
+            node_metrics.clone(),
+            crate::helper::metrics::ARCHIVE_FEED_CHANNEL,
+        );
+
+    let block_manager_listen_addr = config.network.block_manager_listen_addr;
+    let nodes_rx_clone = nodes_rx.clone();
+    let block_manager_handle: JoinHandle<anyhow::Result<()>> = tokio::spawn(async move {
+        let bp_resolver = move |node_id: NodeIdentifier| {
+            let node_addr =
+                nodes_rx_clone.borrow().get(&node_id).map(|x| x.peer_addr.ip().to_string());
+
+            node_addr
+        };
+        transport_layer::server::LiteServer::new(block_manager_listen_addr)
+            .start(archive_feed_receiver, bp_resolver, block_account_ids)
+            .await?;
+        Ok(())
+    });
+
+    if cfg!(feature = "fail-fast") {
+        let orig_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |panic_info| {
+            // invoke the default handler and exit the process
+            if let Some(location) = panic_info.location() {
+                eprintln!("panic occurred in file '{}'", location.file());
+            } else {
+                eprintln!("panic occurred but can't get location information...");
+            }
+            eprintln!("{panic_info:?}");
+            orig_hook(panic_info);
+            std::process::exit(100);
+        }));
+    }
+
+    let zerostate_path = Some(config.local.zerostate_path.clone());
+
+    tracing::trace!(
+        "config.global.min_time_between_state_publish_directives={:?}",
+        config.global.min_time_between_state_publish_directives
+    );
+    let keys_map = key_pairs_from_file::<GoshBLS>(&config.local.key_path);
+    let bls_keys_map = Arc::new(Mutex::new(keys_map));
+    let bls_keys_map_clone = bls_keys_map.clone();
+
+    // node should sync with other nodes, but if there are
+    // no alive nodes, node should wait
+    // TODO: fix. single thread implementation
+    // let (block_id, _) = repository.select_thread_last_finalized_block(&ThreadIdentifier::new(0))?;
+    // if block_id == BlockIdentifier::default() {
+    //     loop {
+    //         // TODO: improve this code. Do not check length, check that all vals present.
+    //
+    //         if let Ok(true) =
+    //             network_config.alive_nodes(false).await.map(|v| {
+    //                 tracing::trace!(
+    //                 "[synchronizing] Waiting for sync with other nodes: other_nodes_cnt={nodes_cnt} alive_cnt={}", v.len()
+    //             );
+    //                 v.len() >= nodes_cnt
+    //             })
+    //         {
+    //             break;
+    //         }
+    //         sleep(Duration::from_millis(ALIVE_NODES_WAIT_TIMEOUT_MILLIS)).await;
+    //     }
+    // }
+
+    let seed_map = key_pairs_from_file::<GoshBLS>(&config.local.block_keeper_seed_path);
+    let secret_seed = seed_map.values().last().unwrap().clone().0;
+    let block_keeper_rng = SmallRng::from_seed(secret_seed.take_as_seed());
+
+    let config_clone = config.clone();
+
+    let signals_join_handle = {
+        let mut signals = Signals::new([SIGHUP, SIGINT, SIGTERM])?;
+        let blk_key_path = config_clone.local.key_path.clone();
+        let config_path = config_path.clone();
+        std::thread::Builder::new().name("signal handler".to_string()).spawn(move || {
+            for sig in signals.forever() {
+                tracing::info!("Received signal {:?}", sig);
+                match sig {
+                    SIGHUP => {
+                        let new_key_map = key_pairs_from_file::<GoshBLS>(&blk_key_path);
+                        tracing::trace!(
+                            "Insert key pair, pubkeys: {:?}",
+                            new_key_map.keys().collect::<Vec<_>>()
+                        );
+                        let mut keys_map = bls_keys_map_clone.lock();
+                        *keys_map = new_key_map;
+                        ext_messages_auth::auth::update_ext_message_auth_flag_from_files();
+                        match load_config_from_file(&config_path) {
+                            Ok(config) => {
+                                config_tx.send_replace(config);
+                            }
+                            Err(err) => {
+                                tracing::error!("Failed to load config from file: {err:?}");
+                            }
+                        }
+                    }
+                    SIGTERM | SIGINT => {
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+        })?
+    };
+
+    // let mut node_execute_handlers = JoinSet::new();
+    // TODO: check that inner_service_loop is active
+    let (routing, routing_rx, _inner_service_loop, _inner_ext_messages_loop) = RoutingService::new(
+        incoming_rx,
+        ext_messages_receiver,
+        metrics.as_ref().map(|x| x.node.clone()),
+        metrics.as_ref().map(|x| x.net.clone()),
+    );
+
+    let repo_path = PathBuf::from("./data");
+    let finalization_log = FinalizationEventLog::open(repo_path.join("finalization-log"))?;
+    let archive_feed_consumer = finalization_log.subscribe("archive")?;
+    let _archive_feed = std::thread::Builder::new()
+        .name("Archive feed".to_string())
+        .spawn_critical(move || forward_archive_feed(archive_feed_consumer, archive_feed_sender))?;
+    let node_cross_thread_ref_data_availability_synchronization_service =
+        CrossThreadRefDataAvailabilitySynchronizationService::new(
+            metrics.as_ref().map(|m| m.node.clone()),
+        )
+        .unwrap();
+
+    let mut node_shared_services = crate::node::shared_services::SharedServices::start(
+        routing.clone(),
+        repo_path.clone(),
+        metrics.as_ref().map(|m| m.node.clone()),
+        config.global.thread_load_threshold,
+        config.global.thread_load_window_size,
+        config.local.rate_limit_on_incoming_block_req,
+        config.global.thread_count_soft_limit,
+        crossref_db,
+    );
+    let node_status = NodeStatusBoard::new(config.local.node_id.to_string());
+    node_shared_services.node_status = Some(node_status.clone());
+    let producer_rotations = ProducerRotationFeed::new();
+    node_shared_services.producer_rotations = Some(producer_rotations.clone());
+    let out_msg_queues = OutMsgQueueIndex::new();
+    node_shared_services.out_msg_queues = Some(out_msg_queues.clone());
+    node_shared_services.timer_contract = config
+        .global
+        .timer_contract_account
+        .as_deref()
+        .map(AccountAddress::from_str)
+        .transpose()
+        .map_err(|e| anyhow::format_err!("Invalid timer contract account: {e}"))?;
+    let thread_load = ThreadLoadFeed::new();
+    node_shared_services
+        .exec(|services| services.load_balancing.set_load_feed(thread_load.clone()));
+    let thread_splits = (config.local.thread_split_approval.mode
+        == ThreadSplitApprovalMode::Approval)
+        .then(|| ThreadSplitGate::new(config.local.thread_split_approval.auto_approve_delay()));
+    if let Some(thread_splits) = thread_splits.as_ref() {
+        node_shared_services
+            .exec(|services| services.load_balancing.set_split_gate(thread_splits.clone()));
+    }
+    let blob_sync_service =
+        blob_sync::external_fileshares_based::ExternalFileSharesBased::builder()
+            .local_storage_share_base_path(config.local.external_state_share_local_base_dir.clone())
+            .build()
+            .start(metrics.as_ref().map(|m| m.node.clone()))
+            .expect("Blob sync service start");
+    let nack_set_cache = Arc::new(Mutex::new(FixedSizeHashSet::new(DEFAULT_NACK_SIZE_CACHE)));
+    let (state_save_tx, state_save_rx) =
+        instrumented_channel(node_metrics.clone(), BLOCK_STATE_SAVE_CHANNEL);
+    let state_save_service = std::thread::Builder::new()
+        .name("State save service".to_string())
+        .spawn_critical(move || start_state_save_service(state_save_rx))?;
+    let block_state_repo =
+        BlockStateRepository::new(repo_path.clone().join("blocks-states"), Arc::new(state_save_tx));
+
+    let block_id = BlockIdentifier::default();
+    let state = block_state_repo.get(&block_id)?;
+    state.guarded_mut(|state_in| -> anyhow::Result<()> {
+        if !state_in.is_stored() {
+            state_in.set_thread_identifier(ThreadIdentifier::default())?;
+            let first_node_id = bk_set.iter_node_ids().next().unwrap().clone();
+            state_in.set_producer(first_node_id)?;
+            state_in.set_block_seq_no(BlockSeqNo::default())?;
+            state_in.set_block_time_ms(0)?;
+            state_in.set_common_checks_passed()?;
+            state_in.set_finalized()?;
+            // state_in.set_prefinalized()?;
+            state_in.set_ancestors(vec![])?;
+            state_in.set_applied(
+                Instant::now() - Duration::from_millis(330),
+                Instant::now() - Duration::from_millis(330),
+            )?;
+            state_in.set_signatures_verified()?;
+            state_in.set_stored_zero_state()?;
+            let bk_set = Arc::new(bk_set.clone());
+            state_in.set_bk_set(bk_set.clone())?;
+            state_in.set_descendant_bk_set(bk_set)?;
+            state_in.set_future_bk_set(Arc::new(BlockKeeperSet::new()))?;
+            state_in.set_descendant_future_bk_set(Arc::new(BlockKeeperSet::new()))?;
+            state_in.set_block_stats(BlockStatistics::zero(
+                NonZero::new(15).unwrap(),
+                NonZero::new(3).unwrap(),
+            ))?;
+            state_in.set_attestation_target(
+                AttestationTargets::builder()
+                    .primary(
+                        AttestationTarget::builder()
+                            .generation_deadline(3)
+                            .required_attestation_count(0)
+                            .build(),
+                    )
+                    .fallback(
+                        AttestationTarget::builder()
+                            .generation_deadline(7)
+                            .required_attestation_count(0)
+                            .build(),
+                    )
+                    .build(),
+            )?;
+            state_in.set_producer_selector_data(
+                ProducerSelector::builder()
+                    .rng_seed_block_id(BlockIdentifier::default())
+                    .index(0)
+                    .build(),
+            )?;
+            state_in.set_ancestor_blocks_finalization_checkpoints(
+                AncestorBlocksFinalizationCheckpoints::builder()
+                    .primary(HashMap::new())
+                    .fallback(HashMap::new())
+                    .build(),
+            )?;
+            state_in.set_block_height(
+                BlockHeight::builder()
+                    .thread_identifier(ThreadIdentifier::default())
+                    .height(0)
+                    .build(),
+            )?;
+            state_in.set_block_round(0)?;
+        }
+        Ok(())
+    })?;
+    drop(state);
+    let state_save_policy = config.state_save_policy()?;
+    let archive_only_threads = config.archive_only_threads()?;
+    let accounts_repo = AccountsRepository::new(
+        repo_path.clone(),
+        config.local.unload_after,
+        state_save_policy.clone(),
+    );
+
+    let repository_blocks = Arc::new(Mutex::new(FinalizedBlockStorage::new(
+        1_usize
+            + TryInto::<usize>::try_into(state_save_policy.max_save_state_frequency() * 2).unwrap(),
+    )));
+
+    let (bk_set_update_tx, bk_set_update_rx) =
+        instrumented_channel(node_metrics.clone(), crate::helper::metrics::BK_SET_UPDATE_CHANNEL);
+    let bk_set_history = Arc::new(parking_lot::RwLock::new(BkSetHistory::load(
+        repo_path.join("bk-set-history.json"),
+    )?));
+    bk_set_history.write().record(bk_set_history_update(&BkSetUpdate {
+        thread_id: ThreadIdentifier::default(),
+        block_id: BlockIdentifier::default(),
+        seq_no: 0,
+        current: Some(Arc::new(bk_set.clone())),
+        future: None,
+        changes: vec![],
+    }));
+    let bk_set_changes = BkSetChangeFeed::new();
+    let block_invalidations = BlockInvalidationFeed::new();
+    let tx_traces = TxTraceRegistry::new(
+        config.local.tx_trace_sample_rate,
+        config.local.tx_trace_rate_limit_per_minute,
+        config.local.tx_trace_cache_size,
+    );
+    let message_policy = AccountListPolicy::from_config(&config.local.account_policy)?
+        .map(|policy| Arc::new(policy) as Arc<dyn MessagePolicy>);
+    let ext_msg_quarantine_path = repo_path.join("ext-messages-quarantine");
+    let ext_msg_quarantine =
+        load_ext_msg_quarantine(&config.local.ext_msg_quarantine, &ext_msg_quarantine_path)?;
+    if let Some(quarantine) = ext_msg_quarantine.clone() {
+        let _ext_msg_quarantine_save_service = std::thread::Builder::new()
+            .name("Ext messages quarantine save service".to_string())
+            .spawn_critical(move || {
+                start_ext_msg_quarantine_save_service(quarantine, ext_msg_quarantine_path)
+            })?;
+    }
+    let admin_audit_log = AdminAuditLog::open(&repo_path.join("admin-audit-log"))?;
+
+    let mut repository = RepositoryImpl::new(
+        repo_path.clone(),
+        zerostate_path.clone(),
+        state_save_policy.clone(),
+        node_shared_services.clone(),
+        Arc::clone(&nack_set_cache),
+        config.local.unload_after.is_some(),
+        block_state_repo.clone(),
+        metrics.as_ref().map(|m| m.node.clone()),
+        accounts_repo,
+        message_db.clone(),
+        repository_blocks,
+        bk_set_update_tx.clone(),
+    );
+    resolve_epoch_code_hashes(&mut config.global, &repository, &zerostate).await?;
+
+    let (optimistic_save_tx, optimistic_save_rx) =
+        instrumented_channel(node_metrics.clone(), OPTIMISTIC_STATE_SAVE_CHANNEL);
+    let repository_clone = repository.clone();
+    let optimistic_state_service = std::thread::Builder::new()
+        .name("Optimistic state save service".to_string())
+        .spawn_critical(move || {
+            start_optimistic_state_save_service(repository_clone, optimistic_save_rx)
+        })?;
+
+    let integrity_audit = IntegrityAudit::new();
+    let repository_clone = repository.clone();
+    let integrity_audit_clone = integrity_audit.clone();
+    let integrity_audit_interval =
+        config.local.integrity_audit_interval_sec.map(Duration::from_secs);
+    let _integrity_audit_service = std::thread::Builder::new()
+        .name("Integrity audit service".to_string())
+        .spawn_critical(move || {
+            start_integrity_audit_service(
+                repository_clone,
+                integrity_audit_clone,
+                integrity_audit_interval,
+            )
+        })?;
+
+    let repository_clone = repository.clone();
+    let chitchat_clone = chitchat.clone();
+    let state_checksum_node_id = config.local.node_id.clone();
+    let state_checksum_signing_key = transport_layer::resolve_signing_key(
+        config.network.my_ed_key_secret.clone(),
+        config.network.my_ed_key_path.clone(),
+    )
+    .ok()
+    .flatten();
+    let state_checksum_config = config.local.state_checksum.clone();
+    let state_checksum_metrics = node_metrics.clone();
+    let _state_checksum_service = std::thread::Builder::new()
+        .name("State checksum service".to_string())
+        .spawn_critical(move || {
+            start_state_checksum_service(
+                repository_clone,
+                chitchat_clone,
+                state_checksum_node_id,
+                state_checksum_signing_key,
+                state_checksum_config,
+                state_checksum_metrics,
+            )
+        })?;
+
+    let repository_clone = repository.clone();
+    let storage_quota_config = config.local.storage_quota.clone();
+    let storage_quota_metrics = node_metrics.clone();
+    let storage_quota_node_status = node_status.clone();
+    let _storage_quota_service = std::thread::Builder::new()
+        .name("Storage quota service".to_string())
+        .spawn_critical(move || {
+            start_storage_quota_service(
+                repository_clone,
+                storage_quota_config,
+                storage_quota_metrics,
+                storage_quota_node_status,
+            )
+        })?;
+
+    let account_ownership = AccountOwnershipFeed::new();
+    let repository_clone = repository.clone();
+    let feed = account_ownership.clone();
+    let _account_ownership_service = std::thread::Builder::new()
+        .name("Account ownership service".to_string())
+        .spawn_critical(move || start_account_ownership_service(repository_clone, feed))?;
+
+    let chitchat_clone = chitchat.clone();
+    let gossip_peers_node_status = node_status.clone();
+    let _gossip_peers_monitor = std::thread::Builder::new()
+        .name("Gossip peers monitor".to_string())
+        .spawn_critical(move || {
+            while SHUTDOWN_FLAG.get() != Some(&true) {
+                // Live nodes include the node itself
+                let live_nodes = chitchat_clone.lock().live_nodes().count();
+                gossip_peers_node_status.set_gossip_peers(live_nodes.saturating_sub(1));
+                std::thread::sleep(GOSSIP_PEERS_POLL_INTERVAL);
+            }
+            Ok(())
+        })?;
+
+    let clock_skew_config = config.local.clock_skew.clone();
+    let clock_metrics = node_metrics.clone();
+    let _clock_skew_monitor = std::thread::Builder::new()
+        .name("Clock skew monitor".to_string())
+        .spawn_critical(move || start_clock_skew_monitor(clock_skew_config, clock_metrics))?;
+
+    let unprocessed_blocks = repository
+        .load_saved_blocks(&block_state_repo)
+        .map_err(|e| {
+            tracing::trace!("load_saved_blocks error: {e:?}");
+            e
+        })
+        .expect("Failed to init repository");
+
+    #[cfg(feature = "deadlock-detection")]
+    let deadlock_detection_handle =
+        std::thread::Builder::new().name("Deadlock detection".to_string()).spawn(move || {
+            tracing::trace!("Spawn deadlock detector");
+            loop {
+                std::thread::sleep(Duration::from_secs(10));
+                let deadlocks = ::parking_lot::deadlock::check_deadlock();
+                if deadlocks.is_empty() {
+                    continue;
+                }
+
+                println!("{} deadlocks detected", deadlocks.len());
+                for (i, threads) in deadlocks.iter().enumerate() {
+                    println!("Deadlock #{i}");
+                    for t in threads {
+                        println!("Thread Id {:#?}", t.thread_id());
+                        println!("{:#?}", t.backtrace());
+                    }
+                }
+            }
+        })?;
+
+    let zerostate_threads: Vec<ThreadIdentifier> = zerostate.list_threads().cloned().collect();
+
+    for thread_id in &zerostate_threads {
+        let (last_finalized_id, _) =
+            repository.select_thread_last_finalized_block(thread_id)?.unwrap();
+        tracing::trace!(
+            "init thread: thread_id={:?} last_finalized_id={:?}",
+            thread_id,
+            last_finalized_id
+        );
+        node_shared_services.exec(|services| {
+            // services.dependency_tracking.init_thread(*thread_id, BlockIdentifier::default());
+            // TODO: check if we have to pass all threads in set
+            services.threads_tracking.init_thread(
+                last_finalized_id.clone(),
+                HashSet::from_iter(vec![*thread_id].into_iter()),
+                &mut (&mut services.router, &mut services.load_balancing),
+            );
+            // TODO: the same must happen after a node sync.
+            services.thread_sync.on_block_finalized(&last_finalized_id, thread_id).unwrap();
+        });
+    }
+    let ackinackisender = AckiNackiSend::builder()
+        .node_id(config.local.node_id.clone())
+        .bls_keys_map(bls_keys_map.clone())
+        .ack_network_direct_tx(direct_tx.clone())
+        .nack_network_broadcast_tx(broadcast_tx.clone())
+        .archive_only_threads(archive_only_threads.clone())
+        .build();
+
+    let authority = Arc::new(Mutex::new(
+        Authority::builder()
+        .round_buckets(RoundTime::linear(
+            // min round time
+            Duration::from_millis(config.global.round_min_time_millis),
+            // step
+            Duration::from_millis(config.global.round_step_millis),
+            // max round time: 30 sec
+            Duration::from_millis(config.global.round_max_time_millis),
+        ))
+        .data_dir(repo_path.join("action-locks"))
+        .block_state_repository(block_state_repo.clone())
+        .block_repository(repository.clone())
+        .node_identifier(config.local.node_id.clone())
+        .bls_keys_map(bls_keys_map.clone())
+        // TODO: make it restored from disk
+        // .action_lock(HashMap::new())
+        .network_direct_tx(direct_tx.clone())
+        // .block_producers(HashMap::new())
+        .bp_production_count(bp_thread_count.clone())
+        .network_broadcast_tx(broadcast_tx.clone())
+        .node_joining_timeout(config.global.node_joining_timeout)
+        .action_lock_db(action_lock_db)
+        .archive_only_threads(archive_only_threads.clone())
+        .producer_selection(config.global.producer_selection)
+        .skip_vote_after(match config.global.producer_skip_vote_after_blocks {
+            0 => None,
+            blocks => Some(
+                Duration::from_millis(config.global.time_to_produce_block_millis) * blocks as u32,
+            ),
+        })
+        .build(),
+    ));
+
+    let blockchain_config_account = config
+        .global
+        .blockchain_config_account
+        .as_deref()
+        .map(AccountAddress::from_str)
+        .transpose()
+        .map_err(|e| anyhow::format_err!("Invalid blockchain config account: {e}"))?;
+    let blockchain_config = BlockchainConfigSource::new(
+        load_blockchain_config(&config.local.blockchain_config_path)?,
+        blockchain_config_account,
+        config.global.blockchain_config_activation_seq_no,
+    );
+
+    let validation_service = ValidationService::new(
+        blockchain_config.clone(),
+        repository.clone(),
+        config.clone(),
+        node_shared_services.clone(),
+        block_state_repo.clone(),
+        ackinackisender.clone(),
+        metrics.as_ref().map(|m| m.node.clone()),
+        wasm_cache.clone(),
+        message_db.clone(),
+        authority.clone(),
+    )
+    .expect("Failed to create validation process");
+
+    let repository_clone = repository.clone();
+    let (heartbeat_channel_tx, heartbeat_channel_rx) = std::sync::mpsc::channel();
+    let heartbeat_thread_join_handle = {
+        // This is a simple hack to allow time based triggers to work.
+        // Without this hack it will go stale once it runs out of all
+        // messages in the system (it will wait for repo changes forever).
+        let mut blocks_repo = block_state_repo.clone();
+        let heartbeat_rate = std::time::Duration::from_millis(300);
+        std::thread::Builder::new().name("heartbeat".to_string()).spawn(move || {
+            use std::sync::mpsc::TryRecvError;
+            let mut attestation_notifications: Vec<Arc<Mutex<CollectedAttestations>>> = vec![];
+            'outer: loop {
+                std::thread::sleep(heartbeat_rate);
+                blocks_repo.touch();
+                for attestations in attestation_notifications.iter_mut() {
+                    attestations.guarded_mut(|e| e.touch());
+                }
+                'inner: loop {
+                    match heartbeat_channel_rx.try_recv() {
+                        Ok(notifications) => attestation_notifications.push(notifications),
+                        Err(TryRecvError::Empty) => break 'inner,
+                        Err(TryRecvError::Disconnected) => break 'outer,
+                    }
+                }
+            }
+        })?
+    };
+    let mut chain_pulse_bind = authority_switch::chain_pulse_monitor::bind(authority.clone());
+    let chain_pulse_monitor = chain_pulse_bind.monitor();
+    let stalled_threads = chain_pulse_bind.stalled_threads();
+
+    let node_metrics = metrics.as_ref().map(|m| m.node.clone());
+    let node_metrics_clone = node_metrics.clone();
+    let stop_result_rx_vec = Arc::new(Mutex::new(vec![]));
+    let stop_result_rx_vec_clone = stop_result_rx_vec.clone();
+    // Mempools of the node threads, used to compute nonces of accounts
+    let ext_messages_states = Arc::new(Mutex::new(HashMap::new()));
+    let ext_messages_states_clone = ext_messages_states.clone();
+    let ext_msg_queue = ExtMsgQueueStatus::new();
+    let ext_msg_queue_clone = ext_msg_queue.clone();
+    let node_status_clone = node_status.clone();
+    let replay_protection_dir = repo_path.join("replay-protection");
+    let ext_msg_quarantine_clone = ext_msg_quarantine.clone();
+    let production_watchdog = ProductionWatchdog::new();
+    let production_stalls = ProductionStallFeed::new();
+    {
+        let production_watchdog = production_watchdog.clone();
+        let repository = repository.clone();
+        let ext_msg_queue = ext_msg_queue.clone();
+        let production_stalls = production_stalls.clone();
+        let watchdog_config = config.local.production_watchdog.clone();
+        let watchdog_metrics = node_metrics.clone();
+        std::thread::Builder::new().name("Production watchdog".to_string()).spawn_critical(
+            move || {
+                start_production_watchdog(
+                    production_watchdog,
+                    repository,
+                    ext_msg_queue,
+                    production_stalls,
+                    watchdog_config,
+                    watchdog_metrics,
+                )
+            },
+        )?;
+    }
+    // The closure starting the node threads below takes ownership of these
+    let tx_traces_clone = tx_traces.clone();
+    let block_state_repo_clone_1 = block_state_repo.clone();
+    let message_db_clone_1 = message_db.clone();
+    let (routing, _inner_service_thread) = RoutingService::start(
+        (routing, routing_rx),
+        metrics.as_ref().map(|m| m.node.clone()),
+        move |parent_block_id,
+              thread_id,
+              thread_receiver,
+              thread_authority_receiver,
+              thread_sender,
+              thread_authority_sender,
+              feedback_sender,
+              ext_messages_rx| {
+            tracing::trace!("start node for thread: {thread_id:?}");
+            let archive_only = archive_only_threads.contains(thread_id);
+            if archive_only {
+                tracing::info!("Thread {thread_id:?} is followed in the archive only mode");
+            }
+
+            let block_collection = UnfinalizedCandidateBlockCollection::new(
+                unprocessed_blocks.get(thread_id).cloned().unwrap_or_default().into_iter(),
+            );
+
+            let mut repository = repository_clone.clone();
+            repository
+                .unfinalized_blocks()
+                .guarded_mut(|e| e.insert(*thread_id, block_collection.clone()));
+            // HACK!
+            if parent_block_id.is_some()
+                && parent_block_id.as_ref().unwrap() != &BlockIdentifier::default()
+            {
+                repository.init_thread(thread_id, parent_block_id.as_ref().unwrap())?;
+            }
+            // END OF HACK
+            let producer_election_rng = {
+                // Here is the problem!
+                // It takes the wrong block id.
+                // should take a parent block of the thread instead.
+                // Yet it requires an explanation why it was done like that
+                let last_block_id =
+                    repository.get_latest_block_id_with_producer_group_change(thread_id)?;
+                let mut seed_bytes = last_block_id.as_ref().to_vec();
+                seed_bytes.extend_from_slice(thread_id.as_ref());
+                let seed = calculate_hash(&seed_bytes)?;
+                SmallRng::from_seed(seed)
+            };
+            let replay_guard = ReplayGuard::load(
+                config.local.replay_protection.clone(),
+                replay_protection_dir.join(format!("{thread_id:x}")),
+                now_ms(),
+            )?;
+            let external_messages = ExternalMessagesThreadState::builder()
+                .with_thread_id(*thread_id)
+                .with_report_metrics(node_metrics.clone())
+                .with_cache_size(config.local.ext_messages_cache_size)
+                .with_expiration_margin_ms(config.local.ext_message_expiration_margin_ms)
+                .with_feedback_sender(feedback_sender.clone())
+                .with_queue_status(ext_msg_queue_clone.clone())
+                .with_replay_guard(replay_guard)
+                .build()?;
+            ext_messages_states_clone.lock().insert(*thread_id, external_messages.clone());
+
+            let external_messages_clone = external_messages.clone();
+            let ext_msg_receiver = std::thread::Builder::new()
+                .name("Ext message receiver".to_string())
+                .spawn_critical(move || {
+                    loop {
+                        match ext_messages_rx.recv() {
+                            Ok(message) => {
+                                external_messages_clone.push_external_messages(&[message])?;
+                            }
+                            Err(e) => {
+                                tracing::error!("Ext message receiver received an error: {e:?}");
+                                break;
+                            }
+                        }
+                    }
+                    Ok(())
+                })?;
+
+            let file_saving_service = FileSavingService::builder()
+                .root_path(config.local.external_state_share_local_base_dir.clone())
+                .repository(repository.clone())
+                .block_state_repository(block_state_repo.clone())
+                .shared_services(node_shared_services.clone())
+                .message_db(message_db.clone())
+                .build();
+
+            let mut sync_state_service = ExternalFileSharesBased::new(
+                blob_sync_service.interface(),
+                file_saving_service,
+                chitchat.clone(),
+            );
+            let static_storages = config.network.static_storages.clone();
+            // State sync can start before SRV urls are resolved, the static urls are
+            // available right away
+            let (static_storages_tx, static_storages_rx) =
+                tokio::sync::watch::channel(network::srv_discovery::static_urls(&static_storages));
+            let srv_refresh_interval =
+                Duration::from_millis(config.network.srv_refresh_interval_millis);
+            tokio::spawn(async move {
+                if let Err(err) = network::srv_discovery::run_static_storages_discovery(
+                    static_storages,
+                    srv_refresh_interval,
+                    static_storages_tx,
+                )
+                .await
+                {
+                    tracing::error!("Static storages discovery stopped: {err}");
+                }
+            });
+            sync_state_service.static_storages = static_storages_rx;
+            sync_state_service.max_download_tries = config.network.shared_state_max_download_tries;
+            sync_state_service.retry_download_timeout = std::time::Duration::from_millis(
+                config.network.shared_state_retry_download_timeout_millis,
+            );
+            sync_state_service.download_deadline_timeout = config.global.node_joining_timeout;
+            let production_process = TVMBlockProducerProcess::builder()
+                .metrics(node_metrics.clone())
+                .node_config(config.clone())
+                .repository(repository.clone())
+                .block_keeper_epoch_code_hash(config.global.block_keeper_epoch_code_hash.clone())
+                .block_keeper_preepoch_code_hash(
+                    config.global.block_keeper_preepoch_code_hash.clone(),
+                )
+                .producer_node_id(config.local.node_id.clone())
+                .blockchain_config(blockchain_config.clone())
+                .parallelization_level(config.local.parallelization_level)
+                .shared_services(node_shared_services.clone())
+                .block_produce_timeout(Arc::new(Mutex::new(Duration::from_millis(
+                    config.global.time_to_produce_block_millis,
+                ))))
+                .thread_count_soft_limit(config.global.thread_count_soft_limit)
+                .share_service(Some(sync_state_service.clone()))
+                .wasm_cache(wasm_cache.clone())
+                .tx_traces(Some(tx_traces.clone()))
+                .ext_msg_quarantine(ext_msg_quarantine_clone.clone())
+                .message_policy(message_policy.clone())
+                .production_watchdog(Some(production_watchdog.clone()))
+                .save_optimistic_service_sender(optimistic_save_tx.clone())
+                .build();
+
+            let attestation_sender_service = AttestationSendService::builder()
+                .pulse_timeout(std::time::Duration::from_millis(
+                    config.global.time_to_produce_block_millis,
+                ))
+                .resend_attestation_timeout(config.global.attestation_resend_timeout)
+                .attestation_send_margin(config.global.attestation_send_margin)
+                .node_id(config.local.node_id.clone())
+                .thread_id(*thread_id)
+                .bls_keys_map(bls_keys_map.clone())
+                .block_state_repository(block_state_repo.clone())
+                .network_direct_tx(direct_tx.clone())
+                .metrics(node_metrics.clone())
+                .authority(authority.clone())
+                .producer_build_policy(config.local.producer_build_policy.clone())
+                .producer_selection(config.global.producer_selection)
+                .node_status(Some(node_status_clone.clone()))
+                .archive_only(archive_only)
+                .build();
+            let last_block_attestations = Arc::new(Mutex::new(CollectedAttestations::default()));
+            let _ = heartbeat_channel_tx.send(Arc::clone(&last_block_attestations));
+
+            let skipped_attestation_ids = Arc::new(Mutex::new(HashSet::new()));
+            let block_gap = Arc::new(AtomicU32::new(0));
+            let attestation_send_service = AttestationSendServiceHandler::new(
+                attestation_sender_service,
+                repository.clone(),
+                last_block_attestations.clone(),
+                block_state_repo.clone(),
+                block_collection.clone(),
+            );
+            let chain_pulse_monitor = chain_pulse_monitor.clone();
+            match chain_pulse_monitor
+                .send(ChainPulseEvent::start_thread(*thread_id, block_collection.clone()))
+            {
+                Ok(()) => {}
+                _ => {
+                    if SHUTDOWN_FLAG.get() != Some(&true) {
+                        anyhow::bail!("Failed to send start thread message");
+                    }
+                }
+            }
+            let block_height = if let Some(parent_block_id) = parent_block_id.as_ref() {
+                let parent_state = block_state_repo
+                    .get(parent_block_id)
+                    .expect("Failed to get block state of the block that has started a thread");
+                let block_height = parent_state
+                    .guarded(|e| *e.block_height())
+                    .expect("Block that starts a thread must have a block height set");
+                Some(block_height)
+            } else {
+                None
+            };
+            match chain_pulse_monitor
+                .send(ChainPulseEvent::block_finalized(*thread_id, block_height))
+            {
+                Ok(()) => {}
+                _ => {
+                    if SHUTDOWN_FLAG.get() != Some(&true) {
+                        anyhow::bail!("Failed to send block finalized message");
+                    }
+                }
+            }
+            let block_processing_service = BlockProcessorService::new(
+                SecurityGuarantee::from_chance_of_successful_attack(
+                    config.global.chance_of_successful_attack,
+                ),
+                config.global.verify_sampling,
+                config.global.producer_selection,
+                config.local.node_id.clone(),
+                std::time::Duration::from_millis(config.global.time_to_produce_block_millis),
+                state_save_policy.for_thread(thread_id).save_state_frequency,
+                bls_keys_map.clone(),
+                *thread_id,
+                block_state_repo.clone(),
+                repository.clone(),
+                node_shared_services.clone(),
+                nack_set_cache.clone(),
+                direct_tx.clone(),
+                broadcast_tx.clone(),
+                skipped_attestation_ids.clone(),
+                block_gap.clone(),
+                validation_service.interface(),
+                sync_state_service.clone(),
+                ackinackisender.clone(),
+                chain_pulse_monitor.clone(),
+                block_collection.clone(),
+                node_cross_thread_ref_data_availability_synchronization_service.interface(),
+                optimistic_save_tx.clone(),
+                archive_only,
+            );
+
+            // TODO: save blk_req_join_handle
+            let (blk_req_tx, _blk_req_join_handle) = BlockRequestService::start(
+                config.clone(),
+                node_shared_services.clone(),
+                repository.clone(),
+                block_state_repo.clone(),
+                direct_tx.clone(),
+                node_metrics.clone(),
+                block_collection.clone(),
+            )?;
+
+            let (stop_result_tx, stop_result_rx) = std::sync::mpsc::channel();
+            {
+                stop_result_rx_vec_clone.lock().push(stop_result_rx);
+            }
+
+            let self_node_tx_clone = thread_sender.clone();
+            let direct_tx_clone = direct_tx.clone();
+            let block_collection_clone = block_collection.clone();
+            let thread_authority = authority.guarded_mut(|e| e.get_thread_authority(thread_id));
+            let block_state_repo_clone = block_state_repo.clone();
+            let broadcast_tx_clone = broadcast_tx.clone();
+            let chain_pulse_monitor_clone = chain_pulse_monitor.clone();
+            let thread_id_clone = *thread_id;
+            let authority_handler = std::thread::Builder::new()
+                .name("routing_service_network_messages_forwarding_loop".to_string())
+                .spawn_critical(move || {
+                    let mut authority_service = AuthoritySwitchService::builder()
+                        .rx(thread_authority_receiver)
+                        .self_node_tx(self_node_tx_clone)
+                        .network_direct_tx(direct_tx_clone)
+                        .thread_id(thread_id_clone)
+                        .unprocessed_blocks_cache(block_collection_clone)
+                        .thread_authority(thread_authority)
+                        .network_broadcast_tx(broadcast_tx_clone)
+                        .block_state_repository(block_state_repo_clone)
+                        .chain_pulse_monitor(chain_pulse_monitor_clone)
+                        .sync_timeout_duration(
+                            config.global.min_time_between_state_publish_directives,
+                        )
+                        .build();
+                    authority_service.run()
+                })
+                .unwrap();
+
+            let node = Node::new(
+                node_shared_services.clone(),
+                sync_state_service,
+                production_process,
+                repository.clone(),
+                thread_receiver,
+                broadcast_tx.clone(),
+                direct_tx.clone(),
+                finalization_log.clone(),
+                bls_keys_map.clone(),
+                config.clone(),
+                block_keeper_rng.clone(),
+                producer_election_rng.clone(),
+                *thread_id,
+                feedback_sender,
+                parent_block_id.is_some(),
+                block_state_repo.clone(),
+                block_processing_service,
+                // attestations_target_service:
+                AttestationTargetsService::builder()
+                    .block_state_repository(block_state_repo.clone())
+                    .build(),
+                validation_service.interface(),
+                skipped_attestation_ids,
+                // block_gap,
+                node_metrics_clone.clone(),
+                thread_sender.clone(),
+                external_messages,
+                message_db.clone(),
+                last_block_attestations,
+                bp_thread_count.clone(),
+                // Channel (sender) for block requests
+                blk_req_tx.clone(),
+                attestation_send_service,
+                ext_msg_receiver,
+                authority.clone(),
+                block_collection.clone(),
+                stop_result_tx,
+                stalled_threads.clone(),
+                chain_pulse_monitor.clone(),
+                authority_handler,
+                thread_authority_sender,
+                optimistic_save_tx.clone(),
+            );
+
+            Ok(node)
+            // let thread_id_clone = *thread_id;
+            // node_execute_handlers.spawn_blocking(move || (node.execute(), thread_id_clone));
+        },
+    );
+
+    // TODO: need to start routing execution and track its status
+    //    let router_execute_handler: JoinHandle<anyhow::Result<()>> =
+    //        tokio::task::spawn_blocking(move || network_message_router.execute());
+    for thread_id in zerostate_threads {
+        tracing::trace!("Send start thread message for thread from zs: {thread_id:?}");
+        routing.cmd_sender.send(Command::StartThread((thread_id, BlockIdentifier::default())))?;
+    }
+
+    tracing::info!("Adding routes");
+
+    let repo_clone = repository.clone();
+    let repo = Arc::new(Mutex::new(repo_clone));
+
+    let (account_request_tx, mut account_request_rx) =
+        tokio::sync::mpsc::channel::<AccountRequest>(100);
+
+    let account_request_handle = tokio::spawn(async move {
+        while let Some(AccountRequest { address, response }) = account_request_rx.recv().await {
+            tracing::trace!("incoming account ({address}) request");
+            let result = get_account_from_shard_state(repo.clone(), &address)
+                .map(|(acc, _dapp_id)| Some(acc));
+
+            tracing::trace!("incoming account ({address}) request result: {result:?}");
+
+            let _ = response.send(result);
+        }
+    });
+
+    let (account_nonce_request_tx, mut account_nonce_request_rx) =
+        tokio::sync::mpsc::channel::<AccountNonceRequest>(100);
+    let repo = Arc::new(Mutex::new(repository.clone()));
+    let replay_protection_code_hashes = config.local.replay_protection_code_hashes.clone();
+    let account_nonce_handle = tokio::spawn(async move {
+        while let Some(AccountNonceRequest { address, response }) =
+            account_nonce_request_rx.recv().await
+        {
+            tracing::trace!("incoming account nonce ({address}) request");
+            let result = get_account_nonce(
+                repo.clone(),
+                &ext_messages_states,
+                &replay_protection_code_hashes,
+                &address,
+            );
+            tracing::trace!("incoming account nonce ({address}) request result: {result:?}");
+            let _ = response.send(result);
+        }
+    });
+
+    let (run_get_request_tx, mut run_get_request_rx) =
+        tokio::sync::mpsc::channel::<RunGetRequest>(100);
+    let repo = Arc::new(Mutex::new(repository.clone()));
+    let run_get_handle = tokio::spawn(async move {
+        let context = Arc::new(ClientContext::new(ClientConfig::default())?);
+        while let Some(RunGetRequest { params, response }) = run_get_request_rx.recv().await {
+            tracing::trace!("incoming run get ({} {}) request", params.address, params.method);
+            let result = run_get_method(repo.clone(), context.clone(), params).await;
+            tracing::trace!("incoming run get request result: {result:?}");
+            let _ = response.send(result);
+        }
+        anyhow::Ok(())
+    });
+
+    let (inclusion_proof_request_tx, mut inclusion_proof_request_rx) =
+        tokio::sync::mpsc::channel::<InclusionProofRequest>(100);
+    let repo = Arc::new(Mutex::new(repository.clone()));
+    let block_state_repo_clone = block_state_repo_clone_1.clone();
+    let inclusion_proof_handle = tokio::spawn(async move {
+        while let Some(InclusionProofRequest { block_id, transaction_id, response }) =
+            inclusion_proof_request_rx.recv().await
+        {
+            tracing::trace!("incoming inclusion proof ({block_id} {transaction_id}) request");
+            let result = get_inclusion_proof(
+                repo.clone(),
+                &block_state_repo_clone,
+                &block_id,
+                &transaction_id,
+            );
+            tracing::trace!("incoming inclusion proof request result: {result:?}");
+            let _ = response.send(result);
+        }
+    });
+
+    let (dapp_config_request_tx, mut dapp_config_request_rx) =
+        tokio::sync::mpsc::channel::<DappConfigRequest>(100);
+    let repo = Arc::new(Mutex::new(repository.clone()));
+    let dapp_config_handle = tokio::spawn(async move {
+        while let Some(DappConfigRequest { dapp_id, response }) =
+            dapp_config_request_rx.recv().await
+        {
+            tracing::trace!("incoming dapp config ({dapp_id}) request");
+            let result = get_dapp_config(repo.clone(), &dapp_id);
+            tracing::trace!("incoming dapp config request result: {result:?}");
+            let _ = response.send(result);
+        }
+    });
+
+    let (accounts_export_request_tx, mut accounts_export_request_rx) =
+        tokio::sync::mpsc::channel::<AccountsExportRequest>(100);
+    let repo = Arc::new(Mutex::new(repository.clone()));
+    let accounts_export_handle = tokio::spawn(async move {
+        while let Some(AccountsExportRequest { filter, records }) =
+            accounts_export_request_rx.recv().await
+        {
+            tracing::trace!("incoming accounts export ({filter:?}) request");
+            let repo = repo.clone();
+            // Exports take long, other requests are served meanwhile
+            tokio::task::spawn_blocking(move || export_accounts(repo, filter, records));
+        }
+    });
+
+    let (durable_messages_request_tx, mut durable_messages_request_rx) =
+        tokio::sync::mpsc::channel::<DurableMessagesRequest>(100);
+    let message_db_clone = message_db_clone_1.clone();
+    let durable_messages_handle = tokio::spawn(async move {
+        while let Some(DurableMessagesRequest { query, response }) =
+            durable_messages_request_rx.recv().await
+        {
+            tracing::trace!("incoming durable messages ({query:?}) request");
+            let result = query_durable_messages(&message_db_clone, query);
+            tracing::trace!("incoming durable messages request result: {result:?}");
+            let _ = response.send(result);
+        }
+    });
+
+    let bk_set_history_clone = bk_set_history.clone();
+    let bk_set_changes_clone = bk_set_changes.clone();
+    std::thread::Builder::new()
+        .name("BK set update handler".to_string())
+        .spawn(move || {
+            let bk_set_history = bk_set_history_clone;
+            let bk_set_changes = bk_set_changes_clone;
+            tracing::info!("BK set update handler started");
+            let mut bk_set = initial_bk_set_update;
+            while let Ok(update) = bk_set_update_rx.recv() {
+                bk_set_history.write().record(bk_set_history_update(&update));
+                for event in bk_set_change_events(&update) {
+                    bk_set_changes.publish(event);
+                }
+                if update.thread_id != ThreadIdentifier::default() {
+                    continue;
+                }
+                let new_bk_set = bk_set_update(
+                    update.seq_no,
+                    update.current.as_ref().map(|x| x.as_ref()),
+                    update.future.as_ref().map(|x| x.as_ref()),
+                );
+                if new_bk_set != bk_set {
+                    tracing::trace!("new bk set update: {:?}", new_bk_set);
+                    bk_set = new_bk_set;
+                    bk_set_update_async_tx.send_replace(bk_set.clone());
+                }
+            }
+            tracing::info!("BK set update handler stopped");
+        })
+        .expect("Failed to spawn BK set updates handler");
+
+    let block_state_repo_clone = block_state_repo_clone_1.clone();
+    let block_invalidations_clone = block_invalidations.clone();
+    let block_invalidation_webhooks = config_clone.local.block_invalidation_webhooks.clone();
+    std::thread::Builder::new()
+        .name("Block invalidation notifier".to_string())
+        .spawn(move || {
+            tracing::info!("Block invalidation notifier started");
+            if let Err(e) = run_block_invalidation_notifier(
+                block_state_repo_clone,
+                block_invalidations_clone,
+                block_invalidation_webhooks,
+            ) {
+                tracing::error!("Block invalidation notifier failed: {e}");
+            }
+            tracing::info!("Block invalidation notifier stopped");
+        })
+        .expect("Failed to spawn block invalidation notifier");
+
+    let config = config_clone;
+    let repo_clone = repository.clone();
+
+    let mut nodes_rx_clone = nodes_rx.clone();
+    let http_server_handle: JoinHandle<anyhow::Result<()>> = tokio::spawn(async move {
+        // Sync required by a bound in `salvo::Handler`
+        let repo_clone_0 = Arc::new(Mutex::new(repo_clone));
+        let repo_clone_1 = repo_clone_0.clone();
+        let server = http_server::WebServer::new(
+            config.network.api_addr,
+            config.local.external_state_share_local_base_dir,
+            ext_messages_sender,
+            account_request_tx,
+            account_nonce_request_tx,
+            run_get_request_tx,
+            inclusion_proof_request_tx,
+            dapp_config_request_tx,
+            accounts_export_request_tx,
+            durable_messages_request_tx,
+            bk_set_history,
+            bk_set_changes,
+            block_invalidations,
+            tx_traces_clone,
+            integrity_audit,
+            thread_load,
+            thread_splits,
+            ext_msg_queue,
+            ext_msg_quarantine,
+            production_stalls,
+            producer_rotations,
+            out_msg_queues,
+            node_status,
+            account_ownership,
+            LOAD_CONTROLLER.shed_flag(SheddableSubsystem::Api),
+            |msg: tvm_block::Message, thread: [u8; 34]| into_external_message(msg, thread.into()),
+            {
+                let repo = repo_clone_0.clone();
+                let node_id = config.local.node_id.clone();
+                move |thread_id| resolve_bp(thread_id.into(), &repo, &mut nodes_rx_clone, &node_id)
+            },
+            // This closure resolves account addresses to tuple: (BOC, Option<dapp_id_as_hex_string>)
+            move |address| {
+                let (account, dapp_id) =
+                    get_account_from_shard_state(repo_clone_0.clone(), &address)?;
+                let boc = account.write_to_bytes().map_err(|e| anyhow::anyhow!("{e}"))?;
+                let tuple = (
+                    base64_encode(&boc), //
+                    dapp_id.map(|id| id.as_hex_string()),
+                );
+                Ok(tuple)
+            },
+            // This closure returns last seq_no for default thread
+            move || {
+                let block_seq_no = *(repo_clone_1
+                    .lock()
+                    .last_finalized_optimistic_state(&ThreadIdentifier::default())
+                    .ok_or_else(|| {
+                        RepositoryError::StateNotFound("Shard state not found".to_string())
+                            .to_api_error()
+                    })?
+                    .get_block_seq_no());
+
+                Ok(block_seq_no.into())
+            },
+            Some(config.local.node_wallet_pubkey),
+            config.local.signing_keys,
+            config.local.api_admin_auth,
+            admin_audit_log,
+            config.local.node_id.to_string(),
+            metrics.as_ref().map(|x| x.routing.clone()),
+        );
+        let _ = server.run(bk_set_update_async_rx).await;
+        anyhow::bail!("HTTP server supposed to work forever");
+    });
+
+    if let Ok(bind_to) = std::env::var("MESSAGE_ROUTER") {
+        tracing::trace!("start message router");
+        let bp_resolver =
+            BPResolverImpl::new(nodes_rx.clone(), Arc::new(Mutex::new(repository.clone())));
+        let config = MessageRouterConfig {
+            bp_resolver: Arc::new(Mutex::new(bp_resolver)),
+            owner_wallet_pubkey: None,
+            signing_keys: std::env::var("BM_ISSUER_KEYS_FILE")
+                .ok()
+                .and_then(|path| read_keys_from_file(&path).ok()),
+        };
+        MessageRouter::new(bind_to, config).run();
+    }
+
+    let wrapped_signals_join_handle =
+        tokio::task::spawn_blocking(move || signals_join_handle.join());
+    let wrapped_heartbeat_thread_join_handle =
+        tokio::task::spawn_blocking(move || heartbeat_thread_join_handle.join());
+
+    // let wrapped_blk_req_join_handle =
+    //     tokio::task::spawn_blocking(move || blk_req_join_handle.join());
+
+    let wrapped_deadlock_detector = tokio::task::spawn_blocking(move || {
+        #[cfg(feature = "deadlock-detection")]
+        deadlock_detection_handle.join()?;
+        #[cfg(not(feature = "deadlock-detection"))]
+        std::thread::park();
+        Ok::<(), Box<dyn Any + Send + 'static>>(())
+    });
+
+    let state_save_service_join_handle =
+        tokio::task::spawn_blocking(move || state_save_service.join());
+    let optimistic_save_service_join_handle =
+        tokio::task::spawn_blocking(move || optimistic_state_service.join());
+
+    let result = tokio::select! {
+        res = wrapped_signals_join_handle => {
+             match res {
+                Ok(_) => {
+                    // unreachable!("sigint handler thread never returns")
+
+                    tracing::trace!("Start shutdown");
+                    SHUTDOWN_FLAG.set(true).expect("");
+                    repository.dump_state();
+
+                    // Note: vec of rx can be locked because we don't expect new threads to start
+                    // after shutdown.
+                    let result_rx_vec = stop_result_rx_vec.lock();
+                    for rx in result_rx_vec.iter() {
+                        let _ = rx.recv();
+                    }
+                    tracing::trace!("Shutdown finished");
+                    Ok(())
+                }
+                Err(error) => {
+                    anyhow::bail!("sigint handler thread failed with error: {error}");
+                }
+            }
+        },
+        res = wrapped_heartbeat_thread_join_handle => {
+            match res {
+                Ok(_) =>{ unreachable!("heartbeat never returns") }
+                Err(error) => {
+                    anyhow::bail!("heartbeat handler thread failed with error: {error}");
+                }
+            }
+        },
+        // res = wrapped_blk_req_join_handle => {
+        //     match res {
+        //         Ok(Ok(_)) =>{ unreachable!("Block request service never returns") }
+        //         Ok(Err(error)) =>{
+        //             anyhow::bail!("Block request service failed with error: {:?}", error);
+        //          }
+        //         Err(error) => {
+        //             anyhow::bail!("Block request service thread failed with error: {error}");
+        //         }
+        //     }
+        // },
+        v = http_server_handle => {
+            anyhow::bail!("http_server failed: {v:?}");
+        },
+        v = block_manager_handle => {
+            anyhow::bail!("lite node failed: {v:?}");
+        },
+        v = gossip_rest_handle => {
+            anyhow::bail!("gossip rest failed: {v:?}");
+        },
+        v = wrapped_deadlock_detector => {
+            match v {
+                Ok(_) => {
+                    Ok(())
+                }
+                Err(error) => {
+                    anyhow::bail!("record writer thread failed with error: {error}");
+                }
+            }
+        },
+        v = account_request_handle => {
+            anyhow::bail!("AccountRequest resolver failed: {v:?}");
+        },
+        v = account_nonce_handle => {
+            anyhow::bail!("AccountNonceRequest resolver failed: {v:?}");
+        },
+        v = run_get_handle => {
+            anyhow::bail!("RunGetRequest resolver failed: {v:?}");
+        },
+        v = inclusion_proof_handle => {
+            anyhow::bail!("InclusionProofRequest resolver failed: {v:?}");
+        },
+        v = dapp_config_handle => {
+            anyhow::bail!("DappConfigRequest resolver failed: {v:?}");
+        },
+        v = accounts_export_handle => {
+            anyhow::bail!("AccountsExportRequest resolver failed: {v:?}");
+        },
+        v = durable_messages_handle => {
+            anyhow::bail!("DurableMessagesRequest resolver failed: {v:?}");
+        },
+        v = state_save_service_join_handle => {
+            anyhow::bail!("State saving service failed: {v:?}");
+        },
+        v = optimistic_save_service_join_handle => {
+            anyhow::bail!("Optimistic state saving service failed: {v:?}");
+        }
+    };
+
+    // Note: reachable on SIGTERM
+    drop(chain_pulse_bind);
+    result
+}
+
+fn into_external_message(
+    message: tvm_block::Message,
+    thread_id: ThreadIdentifier,
+) -> anyhow::Result<NetworkMessage> {
+    anyhow::ensure!(!message.is_internal(), "An issue with the Message content");
+    let message = WrappedMessage { message };
+    Ok(NetworkMessage::ExternalMessage((message, thread_id)))
+}
+
+fn resolve_bp(
+    thread_id: ThreadIdentifier,
+    repo: &Mutex<RepositoryImpl>,
+    nodes_rx: &mut tokio::sync::watch::Receiver<HashMap<NodeIdentifier, PeerData>>,
+    node_id: &NodeIdentifier,
+) -> ResolvingResult {
+    let bp_map = repo.lock().get_nodes_by_threads();
+    tracing::debug!(target: "http_server", "bp resolver: map={:?}", bp_map);
+
+    let Some(Some(bp_id)) = bp_map.get(&thread_id) else {
+        return ResolvingResult::new(false, vec![]);
+    };
+
+    tracing::debug!(target: "http_server", "resolver: bp_id={:?}", bp_id);
+
+    let list = nodes_rx
+        .borrow()
+        .get(bp_id)
+        .map(|peer| match &peer.bk_api_socket {
+            Some(socket) => socket.to_string(),
+            None => peer.peer_addr.to_string(),
+        })
+        .map_or_else(Vec::new, |addr| vec![addr]);
+
+    ResolvingResult::new(node_id == bp_id, list)
+}
+
+fn debug_used_features() {
+    eprintln!("Enabled features:");
+    if cfg!(feature = "tvm_tracing") {
+        eprintln!("  tvm_tracing");
+    }
+    if cfg!(feature = "timing") {
+        eprintln!("  timing");
+    }
+    if cfg!(feature = "allow-dappid-thread-split") {
+        eprintln!("  allow-dappid-thread-split");
+    }
+    if cfg!(feature = "allow-threads-merge") {
+        eprintln!("  allow-threads-merge");
+    }
+    if cfg!(feature = "messages_db") {
+        eprintln!("  messages_db");
+    }
+    if std::env::var("NODE_VERBOSE").is_ok() {
+        eprintln!("  NODE_VERBOSE");
+    }
+    if let Ok(otel_endpoint) = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT") {
+        eprintln!("  OTEL_EXPORTER_OTLP_ENDPOINT={otel_endpoint}");
+    }
+    if let Ok(otel_service_name) = std::env::var("OTEL_SERVICE_NAME") {
+        eprintln!("  OTEL_SERVICE_NAME={otel_service_name}");
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn dispatch_hot_reload(
+    tls_cert_cache: TlsCertCache,
+    mut shutdown_rx: tokio::sync::watch::Receiver<bool>,
+    mut config_rx: tokio::sync::watch::Receiver<crate::config::Config>,
+    mut bk_set_rx: tokio::sync::watch::Receiver<BlockKeeperSetUpdate>,
+    network_config_tx: tokio::sync::watch::Sender<NetworkConfig>,
+    gossip_config_tx: tokio::sync::watch::Sender<GossipConfig>,
+    watch_gossip_config_tx: tokio::sync::watch::Sender<WatchGossipConfig>,
+    metrics_export: Option<MetricsExportSwitch>,
+) {
+    let mut bk_set_update = bk_set_rx.borrow().clone();
+    let mut config = config_rx.borrow().clone();
+    let mut trusted_cert_hashes = HashSet::new();
+    tracing::trace!(
+        "Hot reload initial node config: {}",
+        serde_json::to_string(&config).unwrap_or_default()
+    );
+    tracing::trace!(
+        "Hot reload initial bk_set: {}",
+        serde_json::to_string(&bk_set_update).unwrap_or_default()
+    );
+    loop {
+        if let Some(metrics_export) = &metrics_export {
+            metrics_export.set_enabled(config.local.telemetry.metrics_enabled);
+        }
+        match config.network_config(Some(tls_cert_cache.clone())) {
+            Ok(mut network_config) => {
+                network_config.credential.trusted_ed_pubkeys =
+                    HashSet::<transport_layer::VerifyingKey>::from_iter(
+                        bk_set_update
+                            .current
+                            .iter()
+                            .map(|x| &x.1)
+                            .chain(bk_set_update.future.iter().map(|x| &x.1))
+                            .filter_map(|x| transport_layer::VerifyingKey::from_bytes(x).ok())
+                            .chain(network_config.credential.trusted_ed_pubkeys.iter().cloned()),
+                    );
+                watch_gossip_config_tx.send_replace(WatchGossipConfig {
+                    trusted_pubkeys: network_config.credential.trusted_ed_pubkeys.clone(),
+                });
+                trusted_cert_hashes = network_config.credential.trusted_cert_hashes.clone();
+                network_config_tx.send_replace(network_config);
+            }
+            Err(err) => {
+                tracing::error!("Failed to split network config: {err}");
+            }
+        }
+        match config_rx.borrow().gossip_config() {
+            Ok(gossip_config) => {
+                gossip_config_tx.send_replace(gossip_config);
+            }
+            Err(err) => {
+                tracing::error!("Failed to split gossip config: {err}");
+            }
+        }
+        tokio::select! {
+            sender = shutdown_rx.changed() => if sender.is_err() || *shutdown_rx.borrow() {
+                tracing::trace!("Hot reload: shutdown");
+                break;
+            },
+            sender = config_rx.changed() => if sender.is_ok() {
+                config = config_rx.borrow().clone();
+                tracing::trace!(
+                    "Hot reload changed node config: {}",
+                    serde_json::to_string(&config).unwrap_or_default()
+                );
+            } else {
+                break;
+            },
+            sender = bk_set_rx.changed() => if sender.is_ok() {
+                bk_set_update = bk_set_rx.borrow().clone();
+                tracing::trace!(
+                    "Hot reload changed bk_set: {}",
+                    serde_json::to_string(&bk_set_update).unwrap_or_default()
+                );
+            } else {
+                break;
+            },
+            _ = wait_peer_certs_change(
+                &config.network.peer_certs,
+                &trusted_cert_hashes,
+                config.network.peer_certs_refresh_interval_millis,
+            ) => {
+                tracing::info!("Hot reload changed peer certificates");
+            }
+        }
+    }
+}
+
+// Completes once the certificates in the peer cert files and directories
+// differ from the trusted ones
+async fn wait_peer_certs_change(
+    paths: &[PathBuf],
+    trusted_cert_hashes: &HashSet<CertHash>,
+    refresh_interval_millis: u64,
+) {
+    if paths.is_empty() || refresh_interval_millis == 0 {
+        return std::future::pending().await;
+    }
+    loop {
+        tokio::time::sleep(Duration::from_millis(refresh_interval_millis)).await;
+        match CertStore::try_new(paths) {
+            Ok(cert_store) if cert_store.cert_hashes() != *trusted_cert_hashes => return,
+            Ok(_) => {}
+            Err(err) => tracing::error!("Failed to reload peer certificates: {err}"),
+        }
+    }
+}
@@ -8,6 +8,7 @@ pub mod config;
 pub mod database;
 pub mod external_messages;
 pub mod helper;
+pub mod launcher;
 pub mod message;
 pub mod node;
 pub mod protocol;
@@ -26,3 +27,5 @@ mod tests;
 #[cfg(feature = "misbehave")]
 pub mod misbehavior;
 pub mod storage;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
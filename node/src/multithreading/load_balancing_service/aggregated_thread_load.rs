@@ -44,6 +44,14 @@ impl AggregatedLoad {
         self.aggregated_value.0
     }
 
+    pub fn accounts_load(&self) -> i64 {
+        self.aggregated_value.1.transactions_count()
+    }
+
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
     pub fn propose_new_bitmask(
         &self,
         current_bitmask: &Bitmask<AccountRouting>,
@@ -51,6 +59,14 @@ impl AggregatedLoad {
         self.aggregated_value.1.best_split(current_bitmask)
     }
 
+    pub fn split_load(
+        &self,
+        current_bitmask: &Bitmask<AccountRouting>,
+        proposed_bitmask: &Bitmask<AccountRouting>,
+    ) -> (i64, i64) {
+        self.aggregated_value.1.split_load(current_bitmask, proposed_bitmask)
+    }
+
     pub fn append_from<TOptimisticState>(
         &mut self,
         block: &AckiNackiBlock,
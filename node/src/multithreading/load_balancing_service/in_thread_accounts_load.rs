@@ -35,6 +35,10 @@ impl InThreadAccountsLoad {
         result
     }
 
+    pub fn transactions_count(&self) -> i64 {
+        self.total_transactions_count
+    }
+
     #[allow(clippy::needless_range_loop)]
     pub fn best_split(
         &self,
@@ -79,6 +83,32 @@ impl InThreadAccountsLoad {
         )
     }
 
+    /// Transactions that stay in the thread and that move to the thread
+    /// spawned by the split to the proposed bitmask.
+    #[allow(clippy::needless_range_loop)]
+    pub fn split_load(
+        &self,
+        current_bitmask: &Bitmask<AccountRouting>,
+        proposed_bitmask: &Bitmask<AccountRouting>,
+    ) -> (i64, i64) {
+        let current_bits: [[bool; 256]; 2] = current_bitmask.meaningful_mask_bits().clone().into();
+        let proposed_bits: [[bool; 256]; 2] =
+            proposed_bitmask.meaningful_mask_bits().clone().into();
+        for outer in 0..MAX_ROUTE_PART_SPLIT {
+            for inner in 0..256 {
+                if proposed_bits[outer][inner] && !current_bits[outer][inner] {
+                    // Accounts with the split bit set move to the spawned thread
+                    let zero_bits_count_at_this: i64 = self.zero_bits_count[outer][inner].into();
+                    return (
+                        zero_bits_count_at_this,
+                        self.total_transactions_count - zero_bits_count_at_this,
+                    );
+                }
+            }
+        }
+        (self.total_transactions_count, 0)
+    }
+
     #[allow(clippy::needless_range_loop)]
     pub fn append_from<TOptimisticState>(
         &mut self,
@@ -1,5 +1,11 @@
 use std::collections::HashMap;
 
+use http_server::ThreadLoadDecision;
+use http_server::ThreadLoadFeed;
+use http_server::ThreadLoadUpdate;
+use http_server::ThreadSplitGate;
+use telemetry_utils::now_ms;
+
 use crate::helper::metrics::BlockProductionMetrics;
 use crate::types::BlockIdentifier;
 use crate::types::ThreadIdentifier;
@@ -53,6 +59,8 @@ pub struct LoadBalancingService {
     metrics: Option<BlockProductionMetrics>,
     window_size: usize,
     load_threshold: Load,
+    load_feed: Option<ThreadLoadFeed>,
+    split_gate: Option<ThreadSplitGate>,
 }
 
 impl LoadBalancingService {
@@ -61,16 +69,57 @@ impl LoadBalancingService {
         window_size: usize,
         load_threshold: usize,
     ) -> Self {
-        Self { thread_load_map: HashMap::default(), metrics, window_size, load_threshold }
+        Self {
+            thread_load_map: HashMap::default(),
+            metrics,
+            window_size,
+            load_threshold,
+            load_feed: None,
+            split_gate: None,
+        }
+    }
+
+    /// Publishes thread load updates on block finalization and split checks.
+    pub fn set_load_feed(&mut self, load_feed: ThreadLoadFeed) {
+        self.load_feed = Some(load_feed);
+    }
+
+    /// Holds proposed splits until they are approved by an operator.
+    pub fn set_split_gate(&mut self, split_gate: ThreadSplitGate) {
+        self.split_gate = Some(split_gate);
     }
 
-    #[allow(clippy::explicit_counter_loop)]
     pub fn check(
         &mut self,
         block_identifier: &BlockIdentifier,
         thread_identifier: &ThreadIdentifier,
         threads_table: &ThreadsTable,
         max_table_size: usize,
+    ) -> anyhow::Result<ThreadAction, CheckError> {
+        let result = self.check_thread_action(
+            block_identifier,
+            thread_identifier,
+            threads_table,
+            max_table_size,
+        );
+        if let Ok(action) = &result {
+            let decision = match action {
+                ThreadAction::ContinueAsIs => ThreadLoadDecision::ContinueAsIs,
+                ThreadAction::Split(_) => ThreadLoadDecision::Split,
+                ThreadAction::Collapse(_) => ThreadLoadDecision::Collapse,
+            };
+            self.publish_load(block_identifier, thread_identifier, Some(decision));
+        }
+        result
+    }
+
+    #[allow(clippy::explicit_counter_loop)]
+    fn check_thread_action(
+        &mut self,
+        block_identifier: &BlockIdentifier,
+        thread_identifier: &ThreadIdentifier,
+        threads_table: &ThreadsTable,
+        max_table_size: usize,
     ) -> anyhow::Result<ThreadAction, CheckError> {
         assert!(max_table_size > 0, "Empty threads table is not allowed");
         let current_load: Load = self.read_load(thread_identifier)?;
@@ -151,6 +200,21 @@ impl LoadBalancingService {
                 threads_table,
                 max_table_size,
             );
+            if let (Ok(ThreadAction::Split(proposal)), Some(split_gate)) = (&res, &self.split_gate)
+            {
+                let preview = threads_split::split_preview(
+                    block_identifier,
+                    thread_identifier,
+                    load,
+                    &this_thread_bitmask,
+                    proposal,
+                );
+                if !split_gate.review(preview, now_ms()) {
+                    // The load window is kept, so the split is proposed again
+                    // with the next block and applied once it is approved
+                    return Ok(ThreadAction::ContinueAsIs);
+                }
+            }
             load.reset();
             res
         } else {
@@ -189,12 +253,38 @@ impl LoadBalancingService {
         let thread_identifier: ThreadIdentifier = block.get_common_section().thread_id;
         if let Some(e) = self.thread_load_map.get_mut(&thread_identifier) {
             e.append_from(block, block_state, &self.metrics);
+            self.publish_load(&block.identifier(), &thread_identifier, None);
         } else {
             // TODO: This must be a serious error, yet the node can continue in a release build
             panic!("DEBUG: thread must be ready: {:?}", &self.thread_load_map);
         }
     }
 
+    fn publish_load(
+        &self,
+        block_identifier: &BlockIdentifier,
+        thread_identifier: &ThreadIdentifier,
+        decision: Option<ThreadLoadDecision>,
+    ) {
+        let (Some(load_feed), Some(load)) =
+            (self.load_feed.as_ref(), self.thread_load_map.get(thread_identifier))
+        else {
+            return;
+        };
+        load_feed.publish(ThreadLoadUpdate {
+            thread_id: format!("{thread_identifier:x}"),
+            block_id: block_identifier.to_string(),
+            timestamp: now_ms(),
+            queue_length: load.load_value(),
+            accounts_load: load.accounts_load(),
+            window_size: load.window_size(),
+            window_filled: load.is_ready(),
+            load_threshold: self.load_threshold,
+            threshold_proximity: load.load_value() as f64 / self.load_threshold.max(1) as f64,
+            decision,
+        });
+    }
+
     fn read_load(&self, thread_identifier: &ThreadIdentifier) -> anyhow::Result<Load, CheckError> {
         tracing::trace!("read_load: {:?}", thread_identifier);
         let load =
@@ -235,5 +325,11 @@ impl crate::multithreading::threads_tracking_service::Subscriber for LoadBalanci
         thread_identifier: &ThreadIdentifier,
     ) {
         self.thread_load_map.remove(thread_identifier);
+        if let Some(load_feed) = self.load_feed.as_ref() {
+            load_feed.remove_thread(&format!("{thread_identifier:x}"));
+        }
+        if let Some(split_gate) = self.split_gate.as_ref() {
+            split_gate.remove_thread(&format!("{thread_identifier:x}"));
+        }
     }
 }
@@ -1,3 +1,6 @@
+use http_server::ThreadSplitPreview;
+use http_server::ThreadsTableRowPreview;
+
 use super::AggregatedLoad;
 use super::CheckError;
 use super::Load;
@@ -51,3 +54,60 @@ pub fn try_threads_split(
         .unwrap();
     Ok(ThreadAction::Split(Proposal { proposed_threads_table }))
 }
+
+/// Preview of the split for the operator approval.
+pub fn split_preview(
+    produced_block_id: &BlockIdentifier,
+    this_thread_id: &ThreadIdentifier,
+    this_thread_aggregated_load: &AggregatedLoad,
+    this_thread_bitmask: &Bitmask<AccountRouting>,
+    proposal: &Proposal,
+) -> ThreadSplitPreview {
+    let new_thread_id = ThreadIdentifier::new(produced_block_id, 0u16);
+    let proposed_bitmask = proposal
+        .proposed_threads_table
+        .rows()
+        .find(|(_, thread_id)| **thread_id == new_thread_id)
+        .map(|(bitmask, _)| bitmask.clone())
+        .unwrap_or_else(|| this_thread_bitmask.clone());
+    let (expected_kept_load, expected_moved_load) =
+        this_thread_aggregated_load.split_load(this_thread_bitmask, &proposed_bitmask);
+    ThreadSplitPreview {
+        thread_id: format!("{this_thread_id:x}"),
+        block_id: produced_block_id.to_string(),
+        current_bitmask: bitmask_preview(this_thread_bitmask),
+        proposed_bitmask: bitmask_preview(&proposed_bitmask),
+        new_thread_id: format!("{new_thread_id:x}"),
+        proposed_threads_table: proposal
+            .proposed_threads_table
+            .rows()
+            .map(|(bitmask, thread_id)| ThreadsTableRowPreview {
+                bitmask: bitmask_preview(bitmask),
+                thread_id: format!("{thread_id:x}"),
+            })
+            .collect(),
+        queue_length: this_thread_aggregated_load.load_value(),
+        expected_kept_load,
+        expected_moved_load,
+    }
+}
+
+// Meaningful bits of the routing, e.g. `dapp_id[0]=1 account[3]=0`
+fn bitmask_preview(bitmask: &Bitmask<AccountRouting>) -> String {
+    let meaningful_bits: [[bool; 256]; 2] = bitmask.meaningful_mask_bits().clone().into();
+    let mask_bits: [[bool; 256]; 2] = bitmask.mask_bits().clone().into();
+    let bits = ["dapp_id", "account"]
+        .into_iter()
+        .zip(meaningful_bits.iter().zip(mask_bits.iter()))
+        .flat_map(|(part, (meaningful_bits, mask_bits))| {
+            (0..256)
+                .filter(|index| meaningful_bits[*index])
+                .map(move |index| format!("{part}[{index}]={}", mask_bits[index] as u8))
+        })
+        .collect::<Vec<_>>();
+    if bits.is_empty() {
+        "*".to_string()
+    } else {
+        bits.join(" ")
+    }
+}
@@ -3,6 +3,8 @@
 use telemetry_utils::now_ms;
 
 use crate::bls::envelope::BLSSignedEnvelope;
+use crate::helper::metrics::ProducerBlockOutcome;
+use crate::helper::metrics::LATE_BLOCK_PRODUCTION_CYCLES;
 use crate::node::associated_types::NodeAssociatedTypes;
 use crate::node::block_state::tools::connect;
 use crate::node::services::sync::StateSyncService;
@@ -96,6 +98,15 @@ where
             }
             Ok::<(), anyhow::Error>(())
         })?;
+        self.metrics.as_ref().inspect(|m| {
+            let producer = &net_block.producer_id;
+            m.report_producer_block(producer, ProducerBlockOutcome::Produced, &thread_identifier);
+            let late_after_ms =
+                LATE_BLOCK_PRODUCTION_CYCLES * self.config.global.time_to_produce_block_millis;
+            if now_ms().saturating_sub(block_time) > late_after_ms {
+                m.report_producer_block(producer, ProducerBlockOutcome::Late, &thread_identifier);
+            }
+        });
 
         connect!(parent = parent, child = block_state, &self.block_state_repository);
         if let Some(hint) =
@@ -9,16 +9,26 @@ pub use save_service::start_state_save_service;
 
 // TODO: migrate to any embedded db.
 mod private {
+    use std::fs::File;
+    use std::os::fd::AsRawFd;
+    use std::path::Path;
     use std::path::PathBuf;
 
     use super::state::AckiNackiBlockState;
     use crate::repository::repository_impl::load_from_file;
     use crate::repository::repository_impl::save_to_file;
+    use crate::repository::repository_impl::save_to_file_unsynced;
 
     pub fn load_state(file_path: PathBuf) -> anyhow::Result<Option<AckiNackiBlockState>> {
         if let Some(mut state) = load_from_file::<AckiNackiBlockState>(&file_path).map_err(|e| {
             anyhow::format_err!("Failed to load block state from file {file_path:?}: {e}")
         })? {
+            state.fork_resolution =
+                load_from_file(&fork_resolution_path(&file_path)).map_err(|e| {
+                    anyhow::format_err!(
+                        "Failed to load fork resolution of block state {file_path:?}: {e}"
+                    )
+                })?;
             state.file_path = file_path;
             Ok(Some(state))
         } else {
@@ -28,7 +38,106 @@ mod private {
 
     pub fn save(state: &AckiNackiBlockState) -> anyhow::Result<()> {
         let file_path = state.file_path.clone();
+        if let Some(fork_resolution) = &state.fork_resolution {
+            save_to_file(&fork_resolution_path(&file_path), fork_resolution, false)?;
+        }
         save_to_file(&file_path, &state, false)?;
         Ok(())
     }
+
+    pub fn save_unsynced(state: &AckiNackiBlockState) -> anyhow::Result<()> {
+        if let Some(fork_resolution) = &state.fork_resolution {
+            save_to_file_unsynced(&fork_resolution_path(&state.file_path), fork_resolution)?;
+        }
+        save_to_file_unsynced(&state.file_path, &state)
+    }
+
+    // Removes the state file together with the files kept next to it. The
+    // fork resolution goes first, so it never outlives the state file.
+    pub fn remove_state(file_path: &Path) -> anyhow::Result<()> {
+        for path in [fork_resolution_path(file_path), file_path.to_path_buf()] {
+            match std::fs::remove_file(&path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => anyhow::bail!("Failed to remove block state file {path:?}: {e}"),
+            }
+        }
+        Ok(())
+    }
+
+    // Fork resolution is not a part of the block state layout and is kept
+    // next to the state file.
+    pub(super) fn fork_resolution_path(file_path: &Path) -> PathBuf {
+        let mut path = file_path.as_os_str().to_owned();
+        path.push(".fork_resolution");
+        PathBuf::from(path)
+    }
+
+    // Syncs the states saved unsynced with a single syncfs instead of a sync
+    // per file.
+    pub fn sync_saved(data_dir: &Path) -> anyhow::Result<()> {
+        if !cfg!(feature = "sync_files") {
+            return Ok(());
+        }
+        let dir = File::open(data_dir).map_err(|e| {
+            anyhow::format_err!("Failed to open block states dir {data_dir:?}: {e}")
+        })?;
+        if unsafe { libc::syncfs(dir.as_raw_fd()) } != 0 {
+            anyhow::bail!(
+                "Failed to sync block states dir {data_dir:?}: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigUint;
+
+    use super::repository::BlockStateRepository;
+    use crate::protocol::authority_switch::fork_resolution::resolve_fork;
+    use crate::protocol::authority_switch::fork_resolution::ForkCandidate;
+    use crate::types::BlockIdentifier;
+    use crate::utilities::guarded::Guarded;
+    use crate::utilities::guarded::GuardedMut;
+
+    #[test]
+    fn test_fork_resolution_is_saved_and_removed_with_state() {
+        let block_identifier = BlockIdentifier::from([1; 32]);
+        let resolution = resolve_fork(vec![
+            ForkCandidate {
+                block_identifier: block_identifier.clone(),
+                round: 1,
+                attester_stake: BigUint::from(1u32),
+            },
+            ForkCandidate {
+                block_identifier: BlockIdentifier::from([2; 32]),
+                round: 0,
+                attester_stake: BigUint::from(1u32),
+            },
+        ])
+        .unwrap();
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let repository = BlockStateRepository::test(tmp_dir.path().to_owned());
+        let state = repository.get(&block_identifier).unwrap();
+        state.guarded_mut(|e| e.set_fork_resolution(resolution.clone())).unwrap();
+        repository.save_batch(&[state.clone()]).unwrap();
+
+        // Resolution is kept next to the saved state
+        let file_path = tmp_dir.path().join(format!("{block_identifier:x}"));
+        let fork_resolution_path = super::private::fork_resolution_path(&file_path);
+        assert!(fork_resolution_path.exists());
+        let restored =
+            BlockStateRepository::test(tmp_dir.path().to_owned()).get(&block_identifier).unwrap();
+        assert_eq!(restored.guarded(|e| e.fork_resolution().clone()), Some(resolution));
+
+        // State in use is not removed
+        assert!(repository.remove(&block_identifier).is_err());
+        drop(state);
+        repository.remove(&block_identifier).unwrap();
+        assert!(!file_path.exists());
+        assert!(!fork_resolution_path.exists());
+    }
 }
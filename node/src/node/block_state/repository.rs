@@ -2,9 +2,11 @@ use std::fmt::Debug;
 use std::hash::Hash;
 use std::hash::Hasher;
 use std::path::PathBuf;
+use std::sync::mpsc;
 use std::sync::Arc;
 use std::sync::Weak;
 
+use parking_lot::Mutex;
 use parking_lot::RwLock;
 #[cfg(test)]
 use telemetry_utils::mpsc::instrumented_channel;
@@ -19,6 +21,7 @@ use crate::helper::metrics::BlockProductionMetrics;
 use crate::node::block_state::start_state_save_service;
 use crate::types::notification::Notification;
 use crate::types::BlockIdentifier;
+use crate::utilities::guarded::AllowGuardedMut;
 
 #[derive(Clone)]
 pub struct BlockState {
@@ -83,6 +86,7 @@ pub struct BlockStateRepository {
     //    cache: Arc<Mutex<LruCache<BlockIdentifier, BlockState>>>,
     notifications: Notification,
     save_service_sender: Arc<InstrumentedSender<Arc<BlockStateInner>>>,
+    invalidation_subscribers: Arc<Mutex<Vec<mpsc::Sender<BlockState>>>>,
 }
 
 impl PartialEq for BlockState {
@@ -113,6 +117,7 @@ impl BlockStateRepository {
             notifications: Notification::new(),
             //            cache: Arc::new(Mutex::new(LruCache::new(NonZeroUsize::new(100_000).unwrap()))),
             save_service_sender,
+            invalidation_subscribers: Default::default(),
         }
     }
 
@@ -130,9 +135,21 @@ impl BlockStateRepository {
             notifications: Notification::new(),
             // cache: Arc::new(Mutex::new(LruCache::new(NonZeroUsize::new(1).unwrap()))),
             save_service_sender: Arc::new(state_save_tx),
+            invalidation_subscribers: Default::default(),
         }
     }
 
+    /// Returns a receiver of the applied blocks that get invalidated.
+    pub fn subscribe_invalidations(&self) -> mpsc::Receiver<BlockState> {
+        let (tx, rx) = mpsc::channel();
+        self.invalidation_subscribers.lock().push(tx);
+        rx
+    }
+
+    pub(super) fn notify_invalidated(&self, block_state: &BlockState) {
+        self.invalidation_subscribers.lock().retain(|tx| tx.send(block_state.clone()).is_ok());
+    }
+
     pub fn block_state_repo_data_dir(&self) -> &PathBuf {
         &self.block_state_repo_data_dir
     }
@@ -154,6 +171,54 @@ impl BlockStateRepository {
         // Keeping this out of the write-lock.
         // Tradeoff: It takes more time for new item,
         // however it reduces overall time, since most of the time we read.
+        let inner_state = self.load(block_identifier)?;
+
+        {
+            let mut guarded = self.map.write();
+            guarded.remove_expired();
+            let state = self.insert_loaded(&mut guarded, inner_state);
+            drop(guarded);
+            Ok(state)
+        }
+    }
+
+    /// Same as `get` for many blocks, the missing states are loaded and
+    /// inserted under a single lock of the map.
+    pub fn get_batch<'a>(
+        &self,
+        block_identifiers: impl IntoIterator<Item = &'a BlockIdentifier>,
+    ) -> anyhow::Result<Vec<BlockState>> {
+        let block_identifiers = block_identifiers.into_iter().collect::<Vec<_>>();
+        let mut states = {
+            let guarded = self.map.read();
+            block_identifiers
+                .iter()
+                .map(|block_identifier| {
+                    guarded.get(*block_identifier).map(|e| BlockState {
+                        block_identifier: (*block_identifier).clone(),
+                        inner: e,
+                        save_sender: self.save_service_sender.clone(),
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut loaded = vec![];
+        for (i, block_identifier) in block_identifiers.iter().enumerate() {
+            if states[i].is_none() {
+                loaded.push((i, self.load(block_identifier)?));
+            }
+        }
+        if !loaded.is_empty() {
+            let mut guarded = self.map.write();
+            guarded.remove_expired();
+            for (i, inner_state) in loaded {
+                states[i] = Some(self.insert_loaded(&mut guarded, inner_state));
+            }
+        }
+        Ok(states.into_iter().map(|state| state.expect("All states are set")).collect())
+    }
+
+    fn load(&self, block_identifier: &BlockIdentifier) -> anyhow::Result<BlockStateInner> {
         let file_path = self.block_state_repo_data_dir.join(format!("{block_identifier:x}"));
         let mut state = super::private::load_state(file_path.clone())?.unwrap_or_else(|| {
             let mut state = AckiNackiBlockState::new(block_identifier.clone());
@@ -166,33 +231,101 @@ impl BlockStateRepository {
         {
             state.set_parent_block_identifier(BlockIdentifier::default())?;
         }
-        let inner_state = BlockStateInner {
+        Ok(BlockStateInner {
             block_identifier: block_identifier.clone(),
             shared_access: RwLock::new(state),
+        })
+    }
+
+    // The state loaded by another thread in the meantime wins.
+    fn insert_loaded(
+        &self,
+        guarded: &mut WeakValueHashMap<BlockIdentifier, Weak<BlockStateInner>>,
+        inner_state: BlockStateInner,
+    ) -> BlockState {
+        let block_identifier = inner_state.block_identifier.clone();
+        let inner = match guarded.get(&block_identifier) {
+            Some(e) => e,
+            None => {
+                let state = Arc::new(inner_state);
+                guarded.insert(block_identifier.clone(), state.clone());
+                state
+            }
         };
+        BlockState { block_identifier, inner, save_sender: self.save_service_sender.clone() }
+    }
 
-        {
-            let mut guarded = self.map.write();
-            guarded.remove_expired();
-            if let Some(e) = guarded.get(block_identifier) {
-                let state = BlockState {
-                    block_identifier: block_identifier.clone(),
-                    inner: e,
-                    save_sender: self.save_service_sender.clone(),
-                };
-                drop(guarded);
-                return Ok(state);
+    /// Reads the states under their locks held together, so the action sees
+    /// a consistent view of all of them.
+    pub fn guarded_batch<F, T>(&self, states: &[BlockState], action: F) -> anyhow::Result<T>
+    where
+        F: FnOnce(&[&AckiNackiBlockState]) -> T,
+    {
+        let guards = lock_batch(states, |inner| inner.shared_access.read())?;
+        let refs = guards.iter().map(|guard| &**guard).collect::<Vec<_>>();
+        Ok(action(&refs))
+    }
+
+    /// Applies the action to each of the states under their locks held
+    /// together. The batch is transactional: if the action fails for any of
+    /// the states, all of them are rolled back and the error is returned.
+    /// The changes are not saved, see `save_batch`.
+    pub fn guarded_mut_batch<F, T>(
+        &self,
+        states: &[BlockState],
+        mut action: F,
+    ) -> anyhow::Result<Vec<T>>
+    where
+        F: FnMut(&mut AckiNackiBlockState) -> anyhow::Result<T>,
+    {
+        let mut guards = lock_batch(states, |inner| inner.shared_access.write())?;
+        let snapshots =
+            guards.iter().map(|guard| guard.snapshot()).collect::<anyhow::Result<Vec<_>>>()?;
+        let mut results = Vec::with_capacity(states.len());
+        for guard in guards.iter_mut() {
+            match guard.inner_guarded_mut(&mut action) {
+                Ok(result) => results.push(result),
+                Err(e) => {
+                    // Including the failed one, the rest are not changed yet
+                    let changed = results.len() + 1;
+                    for (guard, snapshot) in guards.iter_mut().zip(snapshots).take(changed) {
+                        guard.restore(snapshot)?;
+                    }
+                    return Err(e);
+                }
             }
-            let state = Arc::new(inner_state);
-            guarded.insert(block_identifier.clone(), state.clone());
-            drop(guarded);
-            let state = BlockState {
-                block_identifier: block_identifier.clone(),
-                inner: state,
-                save_sender: self.save_service_sender.clone(),
-            };
-            Ok(state)
         }
+        Ok(results)
+    }
+
+    /// Saves the changed states with a single sync instead of a sync per
+    /// file. If saving fails, the states are saved by the save service once
+    /// they are dropped.
+    pub fn save_batch(&self, states: &[BlockState]) -> anyhow::Result<()> {
+        let mut guards = lock_batch(states, |inner| inner.shared_access.write())?;
+        let mut changed = guards
+            .iter_mut()
+            .filter(|guard| guard.last_saved_object_state_version != guard.object_state_version)
+            .map(|guard| &mut **guard)
+            .collect::<Vec<_>>();
+        if changed.is_empty() {
+            return Ok(());
+        }
+        AckiNackiBlockState::save_batch(&mut changed, &self.block_state_repo_data_dir)
+    }
+
+    /// Removes the saved state of the block together with the files kept next
+    /// to it. The state must not be in use and must not be saved by the save
+    /// service anymore.
+    pub fn remove(&self, block_identifier: &BlockIdentifier) -> anyhow::Result<()> {
+        // Write lock keeps the state from being loaded while it's removed
+        let guarded = self.map.write();
+        anyhow::ensure!(
+            !guarded.contains_key(block_identifier),
+            "Block state {block_identifier:?} is in use"
+        );
+        let file_path = self.block_state_repo_data_dir.join(format!("{block_identifier:x}"));
+        super::private::remove_state(&file_path)
     }
 
     pub fn notifications(&self) -> &Notification {
@@ -204,12 +337,29 @@ impl BlockStateRepository {
     }
 }
 
+// Locks the states in the order of their identifiers, so concurrent batches
+// don't deadlock. The guards are returned in the order of the states.
+fn lock_batch<'a, G>(
+    states: &'a [BlockState],
+    lock: impl Fn(&'a BlockStateInner) -> G,
+) -> anyhow::Result<Vec<G>> {
+    let mut order = (0..states.len()).collect::<Vec<_>>();
+    order.sort_by(|a, b| states[*a].block_identifier.cmp(&states[*b].block_identifier));
+    if order.windows(2).any(|w| states[w[0]].block_identifier == states[w[1]].block_identifier) {
+        anyhow::bail!("Batch contains the same block state twice");
+    }
+    let mut guards = order.iter().map(|i| (*i, lock(&*states[*i].inner))).collect::<Vec<_>>();
+    guards.sort_by_key(|(i, _)| *i);
+    Ok(guards.into_iter().map(|(_, guard)| guard).collect())
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
 
     use super::*;
     use crate::node::NodeIdentifier;
+    use crate::types::ThreadIdentifier;
     use crate::utilities::guarded::Guarded;
     use crate::utilities::guarded::GuardedMut;
 
@@ -277,4 +427,77 @@ mod tests {
             assert!(e.is_finalized());
         })
     }
+
+    #[test]
+    fn ensure_applied_invalidated_blocks_are_notified() {
+        let parent_id = BlockIdentifier::from_str(
+            "1000000000000000000000000000000000000000000000000000000000000000",
+        )
+        .unwrap();
+        let child_id = BlockIdentifier::from_str(
+            "2000000000000000000000000000000000000000000000000000000000000000",
+        )
+        .unwrap();
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let repository = BlockStateRepository::test(tmp_dir.path().to_owned());
+        let invalidations = repository.subscribe_invalidations();
+        let parent = repository.get(&parent_id).unwrap();
+        let child = repository.get(&child_id).unwrap();
+        parent.guarded_mut(|e| e.add_child(ThreadIdentifier::default(), child_id.clone())).unwrap();
+        let now = std::time::Instant::now();
+        child.guarded_mut(|e| e.set_applied(now, now)).unwrap();
+
+        crate::node::block_state::tools::invalidate_branch(parent, &repository);
+        let invalidated = invalidations.try_iter().collect::<Vec<_>>();
+        assert_eq!(invalidated, vec![child]);
+    }
+
+    #[test]
+    fn ensure_batch_is_rolled_back_on_failure_and_saved_at_once() {
+        let first_id = BlockIdentifier::from_str(
+            "1000000000000000000000000000000000000000000000000000000000000000",
+        )
+        .unwrap();
+        let second_id = BlockIdentifier::from_str(
+            "2000000000000000000000000000000000000000000000000000000000000000",
+        )
+        .unwrap();
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let repository = BlockStateRepository::test(tmp_dir.path().to_owned());
+        // Out of the identifiers order to check the results order
+        let states = repository.get_batch([&second_id, &first_id]).unwrap();
+        assert_eq!(states[0].block_identifier(), &second_id);
+        assert!(repository
+            .guarded_mut_batch(&[states[0].clone(), states[0].clone()], |_| Ok(()))
+            .is_err());
+
+        let result = repository.guarded_mut_batch(&states, |e| {
+            e.set_finalized()?;
+            if e.block_identifier() == &first_id {
+                anyhow::bail!("Failed");
+            }
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert!(!repository
+            .guarded_batch(&states, |e| e.iter().any(|e| e.is_finalized()))
+            .unwrap());
+
+        let finalized = repository
+            .guarded_mut_batch(&states, |e| {
+                e.set_finalized()?;
+                Ok(e.block_identifier().clone())
+            })
+            .unwrap();
+        assert_eq!(finalized, vec![second_id.clone(), first_id.clone()]);
+        repository.save_batch(&states).unwrap();
+        for block_identifier in [&first_id, &second_id] {
+            let saved = super::super::private::load_state(
+                tmp_dir.path().join(format!("{block_identifier:x}")),
+            )
+            .unwrap()
+            .unwrap();
+            assert!(saved.is_finalized());
+        }
+    }
 }
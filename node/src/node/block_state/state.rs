@@ -4,6 +4,7 @@
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::num::NonZeroUsize;
+use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
 
@@ -25,6 +26,7 @@ use crate::node::GoshBLS;
 use crate::node::NackData;
 use crate::node::NodeIdentifier;
 use crate::node::SignerIndex;
+use crate::protocol::authority_switch::fork_resolution::ForkResolution;
 use crate::types::bp_selector::ProducerSelector;
 use crate::types::envelope_hash::AckiNackiEnvelopeHash;
 use crate::types::notification::Notification;
@@ -225,6 +227,12 @@ pub struct AckiNackiBlockState {
     #[setters(bool, assert_none = false)]
     has_fallback_attestation_target_met: Option<bool>,
 
+    // Rationale of choosing this block over its siblings locked in the
+    // authority switch. Set only when there was more than one candidate.
+    // It's saved in a separate file, so states saved before stay readable.
+    #[serde(skip)]
+    pub(super) fork_resolution: Option<ForkResolution>,
+
     // Calculated baseline for finalization. Must be calculated based on prev
     // history.
     // The DescendantsChainLength is the exact descendant when it will be checked
@@ -650,6 +658,45 @@ has_cross_thread_ref_data_prepared={:?}\
         Ok(())
     }
 
+    // Saves the states with a single sync of the data dir.
+    pub(super) fn save_batch(states: &mut [&mut Self], data_dir: &Path) -> anyhow::Result<()> {
+        for state in states.iter_mut() {
+            state.object_state_version = state.object_state_version.wrapping_add(1);
+            super::private::save_unsynced(&**state)?;
+        }
+        super::private::sync_saved(data_dir)?;
+        for state in states.iter_mut() {
+            state.last_saved_object_state_version = state.object_state_version;
+            state.touch();
+        }
+        Ok(())
+    }
+
+    pub(super) fn snapshot(&self) -> anyhow::Result<StateSnapshot> {
+        Ok(StateSnapshot {
+            serialized: bincode::serialize(self)?,
+            applied_start_timestamp: self.applied_start_timestamp,
+            own_attestation: self.own_attestation.clone(),
+            own_fallback_attestation: self.own_fallback_attestation.clone(),
+            fork_resolution: self.fork_resolution.clone(),
+        })
+    }
+
+    // Rolls the facts back, the file path and the subscribers are kept.
+    pub(super) fn restore(&mut self, snapshot: StateSnapshot) -> anyhow::Result<()> {
+        let mut restored: Self = bincode::deserialize(&snapshot.serialized)?;
+        restored.applied_start_timestamp = snapshot.applied_start_timestamp;
+        restored.own_attestation = snapshot.own_attestation;
+        restored.own_fallback_attestation = snapshot.own_fallback_attestation;
+        restored.fork_resolution = snapshot.fork_resolution;
+        restored.file_path = std::mem::take(&mut self.file_path);
+        restored.notifications = std::mem::take(&mut self.notifications);
+        restored.object_state_version = self.object_state_version;
+        restored.last_saved_object_state_version = self.last_saved_object_state_version;
+        *self = restored;
+        self.notify_changed()
+    }
+
     pub(super) fn notify_changed(&mut self) -> anyhow::Result<()> {
         self.object_state_version = self.object_state_version.wrapping_add(1);
         self.touch();
@@ -691,6 +738,16 @@ has_cross_thread_ref_data_prepared={:?}\
     }
 }
 
+// Copy of a state taken before a batch mutation, including the fields that
+// are not persisted.
+pub(super) struct StateSnapshot {
+    serialized: Vec<u8>,
+    applied_start_timestamp: Option<std::time::Instant>,
+    own_attestation: Option<Envelope<GoshBLS, AttestationData>>,
+    own_fallback_attestation: Option<Envelope<GoshBLS, AttestationData>>,
+    fork_resolution: Option<ForkResolution>,
+}
+
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, Default)]
 pub struct EventTimestamps {
     pub received_ms: Option<u64>,
@@ -1,33 +1,41 @@
 use std::collections::HashSet;
-use std::collections::VecDeque;
 
 use crate::node::BlockState;
 use crate::node::BlockStateRepository;
-use crate::utilities::guarded::Guarded;
-use crate::utilities::guarded::GuardedMut;
 
 pub fn invalidate_branch(
     branch_root_block_state: BlockState,
     block_state_repository: &BlockStateRepository,
 ) {
-    let mut to_process = VecDeque::from([branch_root_block_state]);
-    while let Some(next) = to_process.pop_front() {
-        assert!(!next.guarded(|e| e.is_finalized()));
-        let children = next.guarded_mut(|e| {
-            let mut children = HashSet::new();
-            if e.is_invalidated() {
-                // We expect this branch to be invalidated already with the same call.
-                return children;
-            }
-            e.set_invalidated().unwrap();
-            for (_key, hashset) in e.known_children.iter() {
-                children = children.union(hashset).cloned().collect();
+    // The branch is invalidated level by level, each level is saved at once
+    let mut level = vec![branch_root_block_state];
+    while !level.is_empty() {
+        let invalidated = block_state_repository
+            .guarded_mut_batch(&level, |e| {
+                assert!(!e.is_finalized());
+                let mut children = HashSet::new();
+                if e.is_invalidated() {
+                    // We expect this branch to be invalidated already with the same call.
+                    return Ok((children, false));
+                }
+                e.set_invalidated()?;
+                for (_key, hashset) in e.known_children.iter() {
+                    children = children.union(hashset).cloned().collect();
+                }
+                Ok((children, e.is_block_already_applied()))
+            })
+            .unwrap();
+        if let Err(e) = block_state_repository.save_batch(&level) {
+            tracing::error!("Failed to save invalidated block states: {e}");
+        }
+        let mut children = HashSet::new();
+        for (next, (next_children, was_applied)) in level.iter().zip(invalidated) {
+            if was_applied {
+                // Consumers of the optimistic data roll back the applied blocks only
+                block_state_repository.notify_invalidated(next);
             }
-            children
-        });
-        for child_id in children.iter() {
-            let child = block_state_repository.get(child_id).unwrap();
-            to_process.push_back(child);
+            children.extend(next_children);
         }
+        level = block_state_repository.get_batch(children.iter()).unwrap();
     }
 }
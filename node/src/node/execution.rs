@@ -60,7 +60,14 @@ where
                 //         // } else {
                 if needs_synchronizing {
                     let start = Instant::now();
+                    let thread = format!("{:x}", self.thread_id);
+                    self.shared_services.node_status.as_ref().inspect(|x| {
+                        x.set_syncing(&thread, true);
+                    });
                     let result = self.execute_synchronizing();
+                    self.shared_services.node_status.as_ref().inspect(|x| {
+                        x.set_syncing(&thread, false);
+                    });
                     if let Some(m) = &self.metrics {
                         let duration_ms = start.elapsed().as_millis() as u64;
                         m.report_sync_time_spent(duration_ms, &self.thread_id);
@@ -75,6 +75,7 @@ pub use crate::node::associated_types::NodeIdentifier;
 use crate::node::block_state::repository::BlockState;
 use crate::node::services::block_processor::chain_pulse::events::ChainPulseEvent;
 use crate::node::services::block_processor::service::BlockProcessorService;
+use crate::node::services::finalization::event_log::FinalizationEventLog;
 use crate::node::services::send_attestations::AttestationSendServiceHandler;
 use crate::node::services::validation::service::ValidationServiceInterface;
 use crate::node::unprocessed_blocks_collection::UnfinalizedCandidateBlockCollection;
@@ -95,7 +96,7 @@ where
     network_rx: XInstrumentedReceiver<NetworkMessage>,
     network_broadcast_tx: NetBroadcastSender<NetworkMessage>,
     network_direct_tx: NetDirectSender<NodeIdentifier, NetworkMessage>,
-    raw_block_tx: InstrumentedSender<(NodeIdentifier, Vec<u8>)>,
+    finalization_log: FinalizationEventLog,
     // bls_keys_map: Arc<Mutex<HashMap<PubKey, (Secret, RndSeed)>>>,
     last_block_attestations: Arc<Mutex<CollectedAttestations>>,
     pub received_acks: Arc<Mutex<Vec<Envelope<GoshBLS, AckData>>>>,
@@ -157,7 +158,7 @@ where
         network_rx: XInstrumentedReceiver<NetworkMessage>,
         network_broadcast_tx: NetBroadcastSender<NetworkMessage>,
         network_direct_tx: NetDirectSender<NodeIdentifier, NetworkMessage>,
-        raw_block_tx: InstrumentedSender<(NodeIdentifier, Vec<u8>)>,
+        finalization_log: FinalizationEventLog,
         bls_keys_map: Arc<Mutex<HashMap<PubKey, (Secret, RndSeed)>>>,
         config: Config,
         block_keeper_rng: TRandomGenerator,
@@ -236,7 +237,7 @@ where
             network_rx,
             network_broadcast_tx: network_broadcast_tx.clone(),
             network_direct_tx: network_direct_tx.clone(),
-            raw_block_tx: raw_block_tx.clone(),
+            finalization_log: finalization_log.clone(),
             // bls_keys_map: bls_keys_map.clone(),
             last_block_attestations: last_block_attestations.clone(),
             config: config.clone(),
@@ -264,23 +265,27 @@ where
                     let block_state_repository_clone = block_state_repository.clone();
                     let shared_services_clone = shared_services.clone();
                     let message_db_clone = message_db.clone();
+                    let external_messages_clone = external_messages.clone();
                     let node_id = config.local.node_id.clone();
                     let authority = authority_state.clone();
+                    let time_to_produce_block_millis = config.global.time_to_produce_block_millis;
                     move || {
                         crate::node::services::finalization::finalization_loop(
                             repository_clone,
                             block_state_repository_clone,
                             shared_services_clone,
-                            raw_block_tx,
+                            finalization_log,
                             state_sync_service,
                             metrics_clone,
                             message_db_clone,
+                            external_messages_clone,
                             &node_id,
                             authority,
                             unprocessed_blocks_cache_clone,
                             last_block_attestations_clone,
                             chain_pulse_monitor_clone,
                             thread_id_clone,
+                            time_to_produce_block_millis,
                         );
                         Ok(())
                     }
@@ -304,7 +309,8 @@ where
                 network_broadcast_tx,
                 config.local.node_id.clone(),
                 Duration::from_millis(config.global.time_to_produce_block_millis),
-                config.global.save_state_frequency,
+                repository.state_save_policy().for_thread(&thread_id).save_state_frequency,
+                config.global.producer_selection,
                 external_messages.clone(),
                 is_state_sync_requested.clone(),
                 bp_production_count,
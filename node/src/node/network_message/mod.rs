@@ -7,6 +7,7 @@ use std::fmt::Debug;
 use std::fmt::Display;
 use std::fmt::Formatter;
 
+use network::protocol_version::SKIP_VOTE_VERSION;
 use serde::Deserialize;
 use serde::Serialize;
 
@@ -131,6 +132,35 @@ impl NetworkMessage {
     ) -> anyhow::Result<Self> {
         Ok(Self::ResentCandidate((NetBlock::with_envelope(envelope)?, node_id)))
     }
+
+    // Authority switch messages are not bound to a thread here and are
+    // delivered to all subscribers.
+    pub fn thread_id(&self) -> Option<&ThreadIdentifier> {
+        use NetworkMessage::*;
+        match self {
+            Candidate(block) | ResentCandidate((block, _)) => Some(&block.thread_id),
+            Ack((_, thread_id))
+            | Nack((_, thread_id))
+            | ExternalMessage((_, thread_id))
+            | NodeJoining((_, thread_id))
+            | BlockAttestation((_, thread_id))
+            | SyncFrom((_, thread_id))
+            | SyncFinalized((_, _, _, thread_id))
+            | BlockRequest { thread_id, .. } => Some(thread_id),
+            AuthoritySwitchProtocol(_) | StartSynchronization => None,
+        }
+    }
+
+    // Messages added after the protocol negotiation are sent only to peers
+    // that decode them, 0 means any peer.
+    pub fn min_protocol_version(&self) -> u16 {
+        match self {
+            NetworkMessage::AuthoritySwitchProtocol(AuthoritySwitch::SkipVote(_)) => {
+                SKIP_VOTE_VERSION
+            }
+            _ => 0,
+        }
+    }
 }
 
 impl Debug for NetworkMessage {
@@ -163,6 +193,9 @@ impl Debug for NetworkMessage {
                 AuthoritySwitchProtocol(AuthoritySwitch::Failed(_)) => {
                     f.write_str("AuthoritySwitch::Failed")
                 }
+                AuthoritySwitchProtocol(AuthoritySwitch::SkipVote(_)) => {
+                    f.write_str("AuthoritySwitch::SkipVote")
+                }
                 StartSynchronization => f.write_str("StartSynchronization"),
             }
         } else {
@@ -189,9 +222,111 @@ impl Debug for NetworkMessage {
                 }
                 AuthoritySwitchProtocol(AuthoritySwitch::Switched(_)) => "AuthoritySwitch::Success",
                 AuthoritySwitchProtocol(AuthoritySwitch::Failed(_)) => "AuthoritySwitch::Failed",
+                AuthoritySwitchProtocol(AuthoritySwitch::SkipVote(_)) => {
+                    "AuthoritySwitch::SkipVote"
+                }
                 StartSynchronization => "StartSynchronization",
             };
             write!(f, "NetworkMessage::{enum_type}")
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bls::gosh_bls::Signature;
+    use crate::node::associated_types::AttestationTargetType;
+    use crate::types::envelope_hash::AckiNackiEnvelopeHash;
+
+    // External clients decode the messages with the ackinacki-types crate
+    fn decode_by_client(message: &NetworkMessage) -> ackinacki_types::NetworkMessage {
+        let data = bincode::serialize(message).unwrap();
+        ackinacki_types::decode_network_message(&data).unwrap()
+    }
+
+    #[test]
+    fn test_client_types_layout() {
+        let thread_id = ThreadIdentifier::new(&BlockIdentifier::from([2; 32]), 3);
+        let client_thread_id = ackinacki_types::ThreadIdentifier(thread_id.into());
+        let attestation = Envelope::<GoshBLS, AttestationData>::create(
+            Signature::empty(),
+            HashMap::from([(5, 1), (2, 3)]),
+            AttestationData::builder()
+                .parent_block_id(BlockIdentifier::from([4; 32]))
+                .block_id(BlockIdentifier::from([1; 32]))
+                .block_seq_no(BlockSeqNo::from(7))
+                .envelope_hash(AckiNackiEnvelopeHash([6; 32]))
+                .target_type(AttestationTargetType::Fallback)
+                .build(),
+        );
+        let ackinacki_types::NetworkMessage::BlockAttestation((envelope, decoded_thread_id)) =
+            decode_by_client(&NetworkMessage::BlockAttestation((attestation, thread_id)))
+        else {
+            panic!("Expected an attestation");
+        };
+        assert!(!envelope.aggregated_signature.is_empty());
+        assert_eq!(envelope.signature_occurrences, vec![(2, 3), (5, 1)]);
+        assert_eq!(
+            envelope.data,
+            ackinacki_types::AttestationData {
+                parent_block_id: ackinacki_types::BlockIdentifier([4; 32]),
+                block_id: ackinacki_types::BlockIdentifier([1; 32]),
+                block_seq_no: ackinacki_types::BlockSeqNo(7),
+                envelope_hash: ackinacki_types::EnvelopeHash([6; 32]),
+                target_type: ackinacki_types::AttestationTargetType::Fallback,
+            }
+        );
+        assert_eq!(decoded_thread_id, client_thread_id);
+
+        let candidate = NetBlock {
+            producer_id: NodeIdentifier::some_id(),
+            producer_selector: Some(
+                ProducerSelector::builder()
+                    .rng_seed_block_id(BlockIdentifier::from([8; 32]))
+                    .index(9)
+                    .build(),
+            ),
+            thread_id,
+            identifier: BlockIdentifier::from([1; 32]),
+            seq_no: BlockSeqNo::from(7),
+            envelope_data: vec![1, 2, 3],
+        };
+        assert_eq!(
+            decode_by_client(&NetworkMessage::Candidate(candidate)),
+            ackinacki_types::NetworkMessage::Candidate(ackinacki_types::NetBlock {
+                producer_id: ackinacki_types::NodeIdentifier(NodeIdentifier::some_id().to_string()),
+                producer_selector: Some(ackinacki_types::ProducerSelector {
+                    rng_seed_block_id: ackinacki_types::BlockIdentifier([8; 32]),
+                    index: 9,
+                    selection: Default::default(),
+                }),
+                thread_id: client_thread_id,
+                identifier: ackinacki_types::BlockIdentifier([1; 32]),
+                seq_no: ackinacki_types::BlockSeqNo(7),
+                envelope_data: vec![1, 2, 3],
+            })
+        );
+
+        assert_eq!(
+            decode_by_client(&NetworkMessage::BlockRequest {
+                inclusive_from: BlockSeqNo::from(1),
+                exclusive_to: BlockSeqNo::from(5),
+                requester: NodeIdentifier::some_id(),
+                thread_id,
+                at_least_n_blocks: Some(2),
+            }),
+            ackinacki_types::NetworkMessage::BlockRequest {
+                inclusive_from: ackinacki_types::BlockSeqNo(1),
+                exclusive_to: ackinacki_types::BlockSeqNo(5),
+                requester: ackinacki_types::NodeIdentifier(NodeIdentifier::some_id().to_string()),
+                thread_id: client_thread_id,
+                at_least_n_blocks: Some(2),
+            }
+        );
+        assert_eq!(
+            decode_by_client(&NetworkMessage::StartSynchronization),
+            ackinacki_types::NetworkMessage::StartSynchronization
+        );
+    }
+}
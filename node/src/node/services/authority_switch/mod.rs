@@ -328,6 +328,23 @@ impl AuthoritySwitchService {
                                     }
                                 });
                             }
+                            AuthoritySwitch::SkipVote(vote) => {
+                                tracing::trace!(
+                                    "Received: AuthoritySwitch::SkipVote: {:?}",
+                                    vote.data()
+                                );
+                                let blocks = self.unprocessed_blocks_cache.clone();
+                                self.thread_authority.guarded_mut(|e| {
+                                    if !e.on_skip_vote(vote) {
+                                        return;
+                                    }
+                                    let result = e.on_block_producer_stalled();
+                                    tracing::trace!("on_block_producer_stalled result: {result:?}");
+                                    if let Some(next_round) = result.next_round().clone() {
+                                        e.on_next_round_incoming_request(next_round, blocks);
+                                    }
+                                });
+                            }
                             AuthoritySwitch::RejectTooOld(_) => {
                                 if last_state_sync_executed.elapsed() > self.sync_timeout_duration {
                                     last_state_sync_executed = std::time::Instant::now();
@@ -4,3 +4,4 @@ pub mod chain_pulse;
 mod chain_tracker;
 pub mod rules;
 pub mod service;
+mod verify_sampling;
@@ -1,2 +1,3 @@
 pub mod bk_set;
 pub mod descendant_bk_set;
+pub mod pre_validation;
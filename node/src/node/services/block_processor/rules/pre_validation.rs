@@ -0,0 +1,87 @@
+use std::collections::HashSet;
+
+use crate::bls::envelope::BLSSignedEnvelope;
+use crate::bls::envelope::Envelope;
+use crate::bls::GoshBLS;
+use crate::node::block_state::repository::BlockState;
+use crate::types::AckiNackiBlock;
+use crate::utilities::guarded::Guarded;
+
+/// Blocks with larger serialized data are rejected without being applied.
+pub const MAX_BLOCK_SIZE_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectionReason {
+    NoSignatures,
+    EmptySignatureOccurrence,
+    ParentMismatch,
+    SeqNoNotIncreasing,
+    BlockTooLarge,
+    NoProducerSelector,
+    DuplicateThreadsTableRows,
+}
+
+impl RejectionReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RejectionReason::NoSignatures => "no_signatures",
+            RejectionReason::EmptySignatureOccurrence => "empty_signature_occurrence",
+            RejectionReason::ParentMismatch => "parent_mismatch",
+            RejectionReason::SeqNoNotIncreasing => "seq_no_not_increasing",
+            RejectionReason::BlockTooLarge => "block_too_large",
+            RejectionReason::NoProducerSelector => "no_producer_selector",
+            RejectionReason::DuplicateThreadsTableRows => "duplicate_threads_table_rows",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PreValidation {
+    Passed,
+    // Parent data is not known yet, the block is checked later
+    Deferred,
+    Rejected(RejectionReason),
+}
+
+/// Light checks of the candidate that need neither the bk set nor the
+/// parent optimistic state. They run before signatures verification and
+/// block apply, so garbage blocks are dropped early.
+pub fn pre_validate(
+    candidate_block: &Envelope<GoshBLS, AckiNackiBlock>,
+    parent_block_state: &BlockState,
+) -> PreValidation {
+    let signature_occurrences = candidate_block.clone_signature_occurrences();
+    if signature_occurrences.is_empty() {
+        return PreValidation::Rejected(RejectionReason::NoSignatures);
+    }
+    if signature_occurrences.values().any(|count| *count == 0) {
+        return PreValidation::Rejected(RejectionReason::EmptySignatureOccurrence);
+    }
+
+    let block = candidate_block.data();
+    if &block.parent() != parent_block_state.block_identifier() {
+        return PreValidation::Rejected(RejectionReason::ParentMismatch);
+    }
+    let Some(parent_seq_no) = parent_block_state.guarded(|e| *e.block_seq_no()) else {
+        return PreValidation::Deferred;
+    };
+    if block.seq_no() <= parent_seq_no {
+        return PreValidation::Rejected(RejectionReason::SeqNoNotIncreasing);
+    }
+
+    if block.raw_data_len().is_some_and(|len| len > MAX_BLOCK_SIZE_BYTES) {
+        return PreValidation::Rejected(RejectionReason::BlockTooLarge);
+    }
+
+    let common_section = block.get_common_section();
+    if common_section.producer_selector.is_none() {
+        return PreValidation::Rejected(RejectionReason::NoProducerSelector);
+    }
+    if let Some(threads_table) = &common_section.threads_table {
+        let mut masks = HashSet::new();
+        if !threads_table.rows().all(|(mask, _)| masks.insert(mask)) {
+            return PreValidation::Rejected(RejectionReason::DuplicateThreadsTableRows);
+        }
+    }
+    PreValidation::Passed
+}
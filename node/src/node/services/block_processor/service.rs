@@ -13,10 +13,13 @@ use parking_lot::Mutex;
 use tvm_types::UInt256;
 
 use super::rules;
+use super::rules::pre_validation::PreValidation;
 use crate::bls::envelope::BLSSignedEnvelope;
 use crate::bls::envelope::Envelope;
 use crate::bls::GoshBLS;
 use crate::config::must_save_state_on_seq_no;
+use crate::config::ProducerSelectionConfig;
+use crate::config::VerifySamplingConfig;
 use crate::node::associated_types::AttestationTargetType;
 use crate::node::block_state::attestation_target_checkpoints::inherit_ancestor_blocks_finalization_distances;
 use crate::node::block_state::attestation_target_checkpoints::AncestorBlocksFinalizationCheckpointsConstructorResults;
@@ -28,6 +31,8 @@ use crate::node::block_state::state::MAX_STATE_ANCESTORS;
 use crate::node::block_state::tools::invalidate_branch;
 use crate::node::services::block_processor::chain_pulse::events::ChainPulseEvent;
 use crate::node::services::block_processor::chain_pulse::ChainPulse;
+use crate::node::services::block_processor::verify_sampling::verify_sampling_decision;
+use crate::node::services::block_processor::verify_sampling::VerifyDecision;
 use crate::node::services::validation::feedback::AckiNackiSend;
 use crate::node::shared_services::SharedServices;
 use crate::node::unprocessed_blocks_collection::UnfinalizedBlocksSnapshot;
@@ -64,6 +69,7 @@ use network::channel::NetDirectSender;
 use telemetry_utils::mpsc::InstrumentedSender;
 use telemetry_utils::now_ms;
 
+use crate::helper::metrics::ProducerBlockOutcome;
 use crate::helper::SHUTDOWN_FLAG;
 use crate::node::services::sync::ExternalFileSharesBased;
 use crate::node::services::sync::StateSyncService;
@@ -92,6 +98,8 @@ impl BlockProcessorService {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         security_guarantee: SecurityGuarantee,
+        verify_sampling: VerifySamplingConfig,
+        producer_selection: ProducerSelectionConfig,
         node_id: NodeIdentifier,
         time_to_produce_block: Duration,
         save_state_frequency: u32,
@@ -112,6 +120,7 @@ impl BlockProcessorService {
         mut unprocessed_blocks_cache: UnfinalizedCandidateBlockCollection,
         mut cross_thread_ref_data_availability_synchronization_service: CrossThreadRefDataAvailabilitySynchronizationServiceInterface,
         save_optimistic_service_sender: InstrumentedSender<Arc<OptimisticStateImpl>>,
+        archive_only: bool,
     ) -> Self {
         let chain_pulse_last_finalized_block_id: BlockIdentifier = repository
             .select_thread_last_finalized_block(&thread_identifier)
@@ -199,6 +208,8 @@ impl BlockProcessorService {
                             }
                             process_candidate_block(
                                 security_guarantee,
+                                verify_sampling,
+                                producer_selection,
                                 node_id.clone(),
                                 save_state_frequency,
                                 bls_keys_map.clone(),
@@ -216,6 +227,7 @@ impl BlockProcessorService {
                                 &chain_pulse_monitor,
                                 &mut cross_thread_ref_data_availability_synchronization_service,
                                 &save_optimistic_service_sender,
+                                archive_only,
                             )?;
                         }
                     }
@@ -253,6 +265,8 @@ fn calculate_v_parameter(
 #[allow(non_snake_case, clippy::too_many_arguments)]
 fn process_candidate_block(
     security_guarantee: SecurityGuarantee,
+    verify_sampling: VerifySamplingConfig,
+    producer_selection: ProducerSelectionConfig,
     node_id: NodeIdentifier,
     save_state_frequency: u32,
     bls_keys_map: Arc<Mutex<HashMap<PubKey, (Secret, RndSeed)>>>,
@@ -270,6 +284,7 @@ fn process_candidate_block(
     chain_pulse_monitor: &Sender<ChainPulseEvent>,
     cross_thread_ref_data_availability_synchronization_service: &mut CrossThreadRefDataAvailabilitySynchronizationServiceInterface,
     save_optimistic_service_sender: &InstrumentedSender<Arc<OptimisticStateImpl>>,
+    archive_only: bool,
 ) -> anyhow::Result<()> {
     // if block_state.guarded(|e| e.is_block_already_applied()) {
     //     // This is the last flag this method sets. Skip this block checks if it is already set.
@@ -298,6 +313,27 @@ fn process_candidate_block(
         tracing::trace!("Unexpected failure: failed to load parent");
         return Ok(());
     };
+    if !block_state.guarded(|e| e.common_checks_passed() == &Some(true)) {
+        match rules::pre_validation::pre_validate(candidate_block, &parent_block_state) {
+            PreValidation::Passed => {}
+            PreValidation::Deferred => {
+                tracing::trace!("Process block candidate: parent data is not set, skip it");
+                return Ok(());
+            }
+            PreValidation::Rejected(reason) => {
+                tracing::trace!(
+                    "Process block candidate: pre-validation failed: {}, invalidate it",
+                    reason.as_str()
+                );
+                let thread_id = candidate_block.data().get_common_section().thread_id;
+                shared_services.metrics.as_ref().inspect(|m| {
+                    m.report_block_pre_validation_rejected(reason.as_str(), &thread_id)
+                });
+                invalidate_branch(block_state.clone(), block_state_repository);
+                return Ok(());
+            }
+        }
+    }
     if block_state.guarded(|e| e.bk_set().is_none()) {
         if parent_block_state.guarded(|e| e.descendant_bk_set().is_some()) {
             let metrics = shared_services.metrics.as_ref();
@@ -341,6 +377,7 @@ fn process_candidate_block(
             &parent_block_state,
             time_to_produce_block,
             block_state,
+            &producer_selection,
         )? {
             let thread_id = block_state.guarded_mut(|e| {
                 e.set_common_checks_passed()?;
@@ -354,6 +391,14 @@ fn process_candidate_block(
                 }
             });
         } else {
+            let common_section = candidate_block.data().get_common_section();
+            shared_services.metrics.as_ref().inspect(|m| {
+                m.report_producer_block(
+                    &common_section.producer_id,
+                    ProducerBlockOutcome::Nacked("common_checks"),
+                    &common_section.thread_id,
+                )
+            });
             invalidate_branch(block_state.clone(), block_state_repository);
             let _ = send.send_nack_bad_block(block_state.clone(), candidate_block.clone());
             return Ok(());
@@ -488,7 +533,6 @@ fn process_candidate_block(
             tracing::trace!("median_descendants_chain_length_to_meet_threshold: {median_descendants_chain_length_to_meet_threshold}");
             // from the spec:
             let bk_set_size = bk_set.as_ref().map(|x| x.len()).unwrap();
-            let N = bk_set_size as f64;
             let v = calculate_v_parameter(
                 primary_attestation_target,
                 security_guarantee.chance_of_successful_attack(),
@@ -499,14 +543,31 @@ fn process_candidate_block(
                 security_guarantee.chance_of_successful_attack(),
                 bk_set_size,
             );
-            if let Some(pubkey) =
-                bk_set.as_ref().and_then(|x| x.get_by_node_id(&node_id)).map(|x| x.pubkey.clone())
+            // Blocks of the archive only threads are not verified by this node
+            if let Some(pubkey) = bk_set
+                .as_ref()
+                .filter(|_| !archive_only)
+                .and_then(|x| x.get_by_node_id(&node_id))
+                .map(|x| x.pubkey.clone())
             {
                 if let Some(rnd) = bls_keys_map.guarded(|e| e.get(&pubkey).map(|x| x.1.clone())) {
                     let rnd = rnd ^ block_id.clone();
 
                     let this_block = rnd.not_a_modulus(1 << 16) as SignerIndex;
-                    if (this_block as f64) * N <= (v * (SignerIndex::MAX as f64)) {
+                    let decision = verify_sampling_decision(
+                        &verify_sampling,
+                        this_block,
+                        candidate_block.data().get_common_section().verify_complexity,
+                        v,
+                        fallback_v,
+                        bk_set_size,
+                    );
+                    shared_services.metrics.as_ref().inspect(|m| {
+                        if let Some(thread_id) = block_state.guarded(|e| *e.thread_identifier()) {
+                            m.report_verify_sampling(decision.as_str(), &thread_id);
+                        }
+                    });
+                    if decision == VerifyDecision::Verify {
                         tracing::trace!("set_must_be_validated");
                         block_state.guarded_mut(|e| {
                             if e.must_be_validated() != &Some(true) {
@@ -515,7 +576,7 @@ fn process_candidate_block(
                                 anyhow::Ok(())
                             }
                         })?;
-                    } else if (this_block as f64) * N <= (fallback_v * (SignerIndex::MAX as f64)) {
+                    } else if decision == VerifyDecision::VerifyInFallbackCase {
                         tracing::trace!("set_must_be_validated_in_fallback_case");
                         block_state.guarded_mut(|e| {
                             if e.must_be_validated_in_fallback_case() != &Some(true) {
@@ -689,6 +750,9 @@ fn process_candidate_block(
 
                 let _ = chain_pulse_monitor
                     .send(ChainPulseEvent::block_applied(thread_id, Some(block_height)));
+                shared_services.node_status.as_ref().inspect(|x| {
+                    x.report_applied(&format!("{thread_id:x}"), block_seq_no.into());
+                });
 
                 return Ok(());
             }
@@ -724,15 +788,14 @@ fn process_candidate_block(
                     panic!("Failed to load optimistic state for parent block");
                 }
                 Err(err) => {
-                    return match err.downcast_ref::<RepositoryError>() {
-                        Some(RepositoryError::BlockNotFound(_)) => Ok(()),
-                        Some(RepositoryError::DepthSearchMinStateLimitReached) => {
+                    return match err {
+                        RepositoryError::BlockNotFound(_) => Ok(()),
+                        RepositoryError::DepthSearchMinStateLimitReached => {
                             tracing::trace!("A block from an abandoned branch");
                             invalidate_branch(block_state.clone(), block_state_repository);
                             Ok(())
                         }
-                        Some(RepositoryError::DepthSearchBlockCountLimitReached) => Err(err),
-                        _ => Err(err),
+                        err => Err(err.into()),
                     }
                 }
             };
@@ -773,12 +836,27 @@ fn process_candidate_block(
             if let Some(share_state) =
                 candidate_block.data().get_common_section().directives.share_state_resources()
             {
+                // States are loaded in background not to stall the block processing
                 for (thread_id, block_id) in share_state {
-                    if let Some(state) =
-                        repository.get_full_optimistic_state(block_id, thread_id, None)?
-                    {
-                        share_service.save_state_for_sharing(state)?;
-                    }
+                    let share_service = share_service.clone();
+                    let shared_block_id = block_id.clone();
+                    repository.get_full_optimistic_state_in_background(
+                        block_id.clone(),
+                        *thread_id,
+                        move |state| match state {
+                            Ok(Some(state)) => {
+                                if let Err(e) = share_service.save_state_for_sharing(state) {
+                                    tracing::error!(
+                                        "Failed to share state {shared_block_id:?}: {e}"
+                                    );
+                                }
+                            }
+                            Ok(None) => {}
+                            Err(e) => {
+                                tracing::error!("Failed to load state {shared_block_id:?}: {e}")
+                            }
+                        },
+                    );
                 }
             }
 
@@ -800,6 +878,9 @@ fn process_candidate_block(
             shared_services.metrics.as_ref().inspect(|m| {
                 m.report_apply_block_total(moment.elapsed().as_millis() as u64, &thread_id);
             });
+            shared_services.node_status.as_ref().inspect(|x| {
+                x.report_applied(&format!("{thread_id:x}"), block_seq_no.into());
+            });
         }
     }
 
@@ -942,6 +1023,7 @@ fn check_common_block_params(
     parent_block_state: &BlockState,
     _time_to_produce_block: &Duration,
     block_state: &BlockState,
+    producer_selection: &ProducerSelectionConfig,
 ) -> anyhow::Result<bool> {
     let (Some(_parent_time), Some(parent_seq_no), Some(parent_height)) =
         parent_block_state.guarded(|e| (*e.block_time_ms(), *e.block_seq_no(), *e.block_height()))
@@ -981,8 +1063,11 @@ fn check_common_block_params(
     else {
         anyhow::bail!("Failed to get selector data to perform common check");
     };
-    let is_producer_correct = producer_selector
-        .is_node_bp(&bk_set, &candidate_block.data().get_common_section().producer_id);
+    let is_producer_correct = producer_selector.is_node_bp(
+        &bk_set,
+        producer_selection,
+        &candidate_block.data().get_common_section().producer_id,
+    );
     if is_producer_correct.is_err() || !is_producer_correct? {
         tracing::trace!("Invalid producer selector");
         return Ok(false);
@@ -0,0 +1,135 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use crate::config::VerifySamplingConfig;
+use crate::config::VerifySamplingMode;
+use crate::node::SignerIndex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyDecision {
+    Verify,
+    VerifyInFallbackCase,
+    Skip,
+}
+
+impl VerifyDecision {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VerifyDecision::Verify => "verify",
+            VerifyDecision::VerifyInFallbackCase => "fallback",
+            VerifyDecision::Skip => "skip",
+        }
+    }
+}
+
+/// Decides whether the keeper must verify the block. `draw` is the keeper
+/// number for the block in `[0, 65536)`, `v` and `fallback_v` are the
+/// expected numbers of verifying keepers for the primary and the fallback
+/// attestation targets.
+pub fn verify_sampling_decision(
+    config: &VerifySamplingConfig,
+    draw: SignerIndex,
+    block_verify_complexity: SignerIndex,
+    v: f64,
+    fallback_v: f64,
+    bk_set_size: usize,
+) -> VerifyDecision {
+    // Shares are scaled by the BK set size, so the attestation sampling is
+    // checked as `draw * N <= v * 65535` without rounding
+    #[allow(non_snake_case)]
+    let N = bk_set_size as f64;
+    let max = SignerIndex::MAX as f64;
+    let draw = draw as f64 * N;
+    let attestation_shares = (v * max, fallback_v * max);
+    let min_share = config.verify_complexity.max(block_verify_complexity) as f64 * N;
+    let (share, fallback_share) = match config.mode {
+        VerifySamplingMode::Attestation => attestation_shares,
+        VerifySamplingMode::Constant => (min_share, min_share),
+        VerifySamplingMode::Adaptive => {
+            (attestation_shares.0.max(min_share), attestation_shares.1.max(min_share))
+        }
+    };
+    if draw <= share {
+        VerifyDecision::Verify
+    } else if draw <= fallback_share {
+        VerifyDecision::VerifyInFallbackCase
+    } else {
+        VerifyDecision::Skip
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(mode: VerifySamplingMode, verify_complexity: SignerIndex) -> VerifySamplingConfig {
+        VerifySamplingConfig { mode, verify_complexity }
+    }
+
+    #[test]
+    fn test_verify_sampling_decision() {
+        let constant = config(VerifySamplingMode::Constant, 2048);
+        assert_eq!(
+            verify_sampling_decision(&constant, 2048, 0, 50.0, 60.0, 100),
+            VerifyDecision::Verify
+        );
+        assert_eq!(
+            verify_sampling_decision(&constant, 2049, 0, 50.0, 60.0, 100),
+            VerifyDecision::Skip
+        );
+        // The block value raises the share
+        assert_eq!(
+            verify_sampling_decision(&constant, 4000, 4096, 0.0, 0.0, 100),
+            VerifyDecision::Verify
+        );
+
+        let adaptive = config(VerifySamplingMode::Adaptive, 2048);
+        // 10 of 100 keepers verify in the primary case and 20 in the fallback one
+        assert_eq!(
+            verify_sampling_decision(&adaptive, 6000, 0, 10.0, 20.0, 100),
+            VerifyDecision::Verify
+        );
+        assert_eq!(
+            verify_sampling_decision(&adaptive, 10000, 0, 10.0, 20.0, 100),
+            VerifyDecision::VerifyInFallbackCase
+        );
+        assert_eq!(
+            verify_sampling_decision(&adaptive, 20000, 0, 10.0, 20.0, 100),
+            VerifyDecision::Skip
+        );
+        // Verify complexity is the lower bound of the share
+        assert_eq!(
+            verify_sampling_decision(&adaptive, 2000, 0, 0.0, 0.0, 100),
+            VerifyDecision::Verify
+        );
+    }
+
+    #[test]
+    fn test_default_verify_sampling_decision() {
+        // Default sampling depends only on the attestation targets: the
+        // keeper verifies if `draw * N <= v * 65535`
+        let default = VerifySamplingConfig::default();
+        assert_eq!(default.mode, VerifySamplingMode::Attestation);
+        assert_eq!(
+            verify_sampling_decision(&default, 6553, 4096, 10.0, 20.0, 100),
+            VerifyDecision::Verify
+        );
+        assert_eq!(
+            verify_sampling_decision(&default, 6554, 4096, 10.0, 20.0, 100),
+            VerifyDecision::VerifyInFallbackCase
+        );
+        assert_eq!(
+            verify_sampling_decision(&default, 13108, 4096, 10.0, 20.0, 100),
+            VerifyDecision::Skip
+        );
+        // Neither the configured nor the block verify complexity raise the share
+        assert_eq!(
+            verify_sampling_decision(&default, 1, 4096, 0.0, 0.0, 100),
+            VerifyDecision::Skip
+        );
+        assert_eq!(
+            verify_sampling_decision(&default, 0, 4096, 0.0, 0.0, 100),
+            VerifyDecision::Verify
+        );
+    }
+}
@@ -0,0 +1,441 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fs;
+use std::fs::File;
+use std::os::fd::AsRawFd;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::node::NodeIdentifier;
+use crate::repository::repository_impl::load_from_file;
+use crate::repository::repository_impl::save_to_file_unsynced;
+use crate::types::notification::Notification;
+use crate::types::BlockIdentifier;
+use crate::types::BlockSeqNo;
+use crate::types::ThreadIdentifier;
+
+const EVENTS_DIR: &str = "events";
+const CURSORS_DIR: &str = "cursors";
+// Writes of events and cursors are synced in batches
+const SYNC_INTERVAL: Duration = Duration::from_millis(200);
+const SYNC_BATCH_SIZE: usize = 64;
+// Consumers that don't subscribe again within this time after the log is
+// opened are considered removed, their cursors stop holding the events
+const STALE_CURSOR_TIMEOUT: Duration = Duration::from_secs(600);
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FinalizationEvent {
+    pub thread_identifier: ThreadIdentifier,
+    /// Position of the event in the thread log, consecutive from 0.
+    pub index: u64,
+    pub block_identifier: BlockIdentifier,
+    pub block_seq_no: BlockSeqNo,
+    pub producer_id: NodeIdentifier,
+    /// Serialized block envelope.
+    pub block: Vec<u8>,
+}
+
+/// Durable log of finalized blocks per thread. Downstream services consume it
+/// through named consumers with persisted cursors instead of channels, so a
+/// restarted consumer continues from the first event it didn't acknowledge.
+///
+/// Events are kept until all registered consumers acknowledge them. A cursor
+/// of a consumer that doesn't subscribe after a restart expires.
+///
+/// Events and cursors are synced in batches, a crash may lose the latest
+/// ones: a lost cursor update only delivers the events again.
+#[derive(Clone)]
+pub struct FinalizationEventLog {
+    root: PathBuf,
+    state: Arc<Mutex<LogState>>,
+    notification: Notification,
+}
+
+#[derive(Default)]
+struct LogState {
+    threads: HashMap<ThreadIdentifier, ThreadLog>,
+    cursors: HashMap<String, HashMap<ThreadIdentifier, u64>>,
+    subscribed: HashSet<String>,
+    opened_at: Option<Instant>,
+    unsynced_writes: usize,
+    last_sync: Option<Instant>,
+}
+
+#[derive(Default, Clone)]
+struct ThreadLog {
+    // Index of the oldest event that is not pruned
+    first: u64,
+    // Index of the next appended event
+    next: u64,
+    last_block: Option<BlockIdentifier>,
+}
+
+impl FinalizationEventLog {
+    pub fn open(root: PathBuf) -> anyhow::Result<Self> {
+        let mut threads = HashMap::new();
+        let events_dir = root.join(EVENTS_DIR);
+        fs::create_dir_all(&events_dir)?;
+        for entry in fs::read_dir(&events_dir)? {
+            let entry = entry?;
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            let thread_identifier = ThreadIdentifier::try_from(name)?;
+            let mut indexes = vec![];
+            for event in fs::read_dir(entry.path())? {
+                if let Some(index) = event?.file_name().to_str().and_then(|name| name.parse().ok())
+                {
+                    indexes.push(index);
+                }
+            }
+            let (Some(first), Some(last)) = (indexes.iter().min(), indexes.iter().max()) else {
+                continue;
+            };
+            let last_event: Option<FinalizationEvent> =
+                load_from_file(&event_path(&root, &thread_identifier, *last))?;
+            threads.insert(
+                thread_identifier,
+                ThreadLog {
+                    first: *first,
+                    next: last + 1,
+                    last_block: last_event.map(|event| event.block_identifier),
+                },
+            );
+        }
+        // Cursors of all consumers are loaded before any of them resumes, so
+        // events are not pruned before the slower consumers read them
+        let mut cursors = HashMap::new();
+        let cursors_dir = root.join(CURSORS_DIR);
+        fs::create_dir_all(&cursors_dir)?;
+        for entry in fs::read_dir(&cursors_dir)? {
+            let entry = entry?;
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            // Skip temporary files of interrupted writes
+            if name.ends_with(".tmp") {
+                continue;
+            }
+            if let Some(cursor) = load_from_file(&entry.path())? {
+                cursors.insert(name, cursor);
+            }
+        }
+        tracing::info!(
+            "Finalization event log opened: {} threads, {} consumers",
+            threads.len(),
+            cursors.len()
+        );
+        Ok(Self {
+            root,
+            state: Arc::new(Mutex::new(LogState {
+                threads,
+                cursors,
+                opened_at: Some(Instant::now()),
+                ..Default::default()
+            })),
+            notification: Notification::new(),
+        })
+    }
+
+    /// Appends the finalized block to the thread log. The block is skipped if
+    /// it's the last one appended: finalization of a block is repeated after
+    /// a restart that interrupted it.
+    pub fn append(
+        &self,
+        thread_identifier: ThreadIdentifier,
+        block_identifier: BlockIdentifier,
+        block_seq_no: BlockSeqNo,
+        producer_id: NodeIdentifier,
+        block: Vec<u8>,
+    ) -> anyhow::Result<Option<u64>> {
+        let mut state = self.state.lock();
+        let thread = state.threads.entry(thread_identifier).or_default();
+        if thread.last_block.as_ref() == Some(&block_identifier) {
+            tracing::trace!("Finalization event is already logged: {block_identifier:?}");
+            return Ok(None);
+        }
+        let index = thread.next;
+        let event = FinalizationEvent {
+            thread_identifier,
+            index,
+            block_identifier: block_identifier.clone(),
+            block_seq_no,
+            producer_id,
+            block,
+        };
+        save_to_file_unsynced(&event_path(&self.root, &thread_identifier, index), &event)?;
+        thread.next = index + 1;
+        thread.last_block = Some(block_identifier);
+        self.sync_if_due(&mut state)?;
+        drop(state);
+        self.notification.clone().touch();
+        Ok(Some(index))
+    }
+
+    /// Registers the consumer or resumes it from the persisted cursor.
+    pub fn subscribe(&self, name: &str) -> anyhow::Result<FinalizationEventConsumer> {
+        let cursor: HashMap<ThreadIdentifier, u64> =
+            load_from_file(&self.root.join(CURSORS_DIR).join(name))?.unwrap_or_default();
+        let mut state = self.state.lock();
+        state.cursors.insert(name.to_string(), cursor);
+        state.subscribed.insert(name.to_string());
+        drop(state);
+        Ok(FinalizationEventConsumer {
+            log: self.clone(),
+            name: name.to_string(),
+            last_thread: None,
+        })
+    }
+
+    /// Syncs the events and cursors written since the last sync.
+    pub fn sync(&self) -> anyhow::Result<()> {
+        self.sync_writes(&mut self.state.lock())
+    }
+
+    fn sync_if_due(&self, state: &mut LogState) -> anyhow::Result<()> {
+        state.unsynced_writes += 1;
+        let interval_passed = state.last_sync.is_none_or(|last| last.elapsed() >= SYNC_INTERVAL);
+        if state.unsynced_writes >= SYNC_BATCH_SIZE || interval_passed {
+            self.sync_writes(state)?;
+        }
+        Ok(())
+    }
+
+    // Syncs all the files written unsynced with a single syncfs instead of a
+    // sync per file.
+    fn sync_writes(&self, state: &mut LogState) -> anyhow::Result<()> {
+        if state.unsynced_writes == 0 {
+            return Ok(());
+        }
+        let dir = File::open(&self.root).map_err(|e| {
+            anyhow::format_err!("Failed to open finalization log dir {:?}: {e}", self.root)
+        })?;
+        if unsafe { libc::syncfs(dir.as_raw_fd()) } != 0 {
+            anyhow::bail!(
+                "Failed to sync finalization log dir {:?}: {}",
+                self.root,
+                std::io::Error::last_os_error()
+            );
+        }
+        state.unsynced_writes = 0;
+        state.last_sync = Some(Instant::now());
+        Ok(())
+    }
+
+    // Drops cursors of consumers that didn't subscribe since the log was
+    // opened
+    fn expire_stale_cursors(&self, state: &mut LogState) {
+        if state.opened_at.is_none_or(|opened_at| opened_at.elapsed() < STALE_CURSOR_TIMEOUT) {
+            return;
+        }
+        state.opened_at = None;
+        let LogState { cursors, subscribed, .. } = state;
+        cursors.retain(|name, _| {
+            if subscribed.contains(name) {
+                return true;
+            }
+            tracing::info!("Finalization event consumer {name} didn't resubscribe, cursor expired");
+            let path = self.root.join(CURSORS_DIR).join(name);
+            if let Err(e) = fs::remove_file(&path) {
+                tracing::warn!("Failed to remove finalization cursor {path:?}: {e}");
+            }
+            false
+        });
+    }
+
+    // Removes events acknowledged by all consumers
+    fn prune(&self, state: &mut LogState, thread_identifier: &ThreadIdentifier) {
+        self.expire_stale_cursors(state);
+        let acknowledged = state
+            .cursors
+            .values()
+            .map(|cursor| cursor.get(thread_identifier).copied().unwrap_or_default())
+            .min()
+            .unwrap_or_default();
+        let Some(thread) = state.threads.get_mut(thread_identifier) else {
+            return;
+        };
+        // The last event is kept to skip repeated appends after a restart
+        let prune_until = acknowledged.min(thread.next.saturating_sub(1));
+        while thread.first < prune_until {
+            let path = event_path(&self.root, thread_identifier, thread.first);
+            if let Err(e) = fs::remove_file(&path) {
+                tracing::warn!("Failed to remove finalization event {path:?}: {e}");
+                return;
+            }
+            thread.first += 1;
+        }
+    }
+}
+
+/// Reads the log from its cursor. An event is returned again until it's
+/// acknowledged, so the consumer acknowledges it after the event is fully
+/// processed.
+pub struct FinalizationEventConsumer {
+    log: FinalizationEventLog,
+    name: String,
+    last_thread: Option<ThreadIdentifier>,
+}
+
+impl FinalizationEventConsumer {
+    /// Waits for the next unacknowledged event. Threads with pending events
+    /// are served in turn.
+    pub fn next_event(&mut self, timeout: Duration) -> anyhow::Result<Option<FinalizationEvent>> {
+        let stamp = self.log.notification.stamp();
+        if let Some(event) = self.pending_event()? {
+            return Ok(Some(event));
+        }
+        self.log.notification.clone().wait_for_updates_timeout(stamp, timeout);
+        self.pending_event()
+    }
+
+    /// Moves the cursor past the event and persists it.
+    pub fn ack(&mut self, event: &FinalizationEvent) -> anyhow::Result<()> {
+        let mut state = self.log.state.lock();
+        let cursor = state.cursors.get_mut(&self.name).expect("Consumer must be registered");
+        let position = cursor.entry(event.thread_identifier).or_default();
+        anyhow::ensure!(
+            *position == event.index,
+            "Finalization event {} of {:?} is acknowledged out of order, expected {}",
+            event.index,
+            event.thread_identifier,
+            position
+        );
+        *position += 1;
+        save_to_file_unsynced(&self.log.root.join(CURSORS_DIR).join(&self.name), cursor)?;
+        self.log.sync_if_due(&mut state)?;
+        self.last_thread = Some(event.thread_identifier);
+        self.log.prune(&mut state, &event.thread_identifier);
+        Ok(())
+    }
+
+    /// Number of events the consumer hasn't acknowledged.
+    pub fn backlog(&self) -> u64 {
+        let state = self.log.state.lock();
+        let cursor = &state.cursors[&self.name];
+        state
+            .threads
+            .iter()
+            .map(|(thread_identifier, thread)| {
+                let position = cursor.get(thread_identifier).copied().unwrap_or_default();
+                thread.next.saturating_sub(position.max(thread.first))
+            })
+            .sum()
+    }
+
+    fn pending_event(&self) -> anyhow::Result<Option<FinalizationEvent>> {
+        let pending = {
+            let state = self.log.state.lock();
+            let cursor = &state.cursors[&self.name];
+            let mut pending = state
+                .threads
+                .iter()
+                .filter_map(|(thread_identifier, thread)| {
+                    let position = cursor.get(thread_identifier).copied().unwrap_or_default();
+                    (position < thread.next).then_some((*thread_identifier, position))
+                })
+                .collect::<Vec<_>>();
+            pending.sort_by(|(a, _), (b, _)| a.as_ref().cmp(b.as_ref()));
+            // Start after the thread served last
+            let start = self
+                .last_thread
+                .and_then(|last| {
+                    pending.iter().position(|(thread, _)| thread.as_ref() > last.as_ref())
+                })
+                .unwrap_or_default();
+            pending.get(start).copied()
+        };
+        let Some((thread_identifier, index)) = pending else {
+            return Ok(None);
+        };
+        let path = event_path(&self.log.root, &thread_identifier, index);
+        load_from_file(&path)?
+            .ok_or_else(|| anyhow::format_err!("Finalization event {path:?} is missing"))
+            .map(Some)
+    }
+}
+
+fn event_path(root: &Path, thread_identifier: &ThreadIdentifier, index: u64) -> PathBuf {
+    root.join(EVENTS_DIR).join(format!("{thread_identifier:x}")).join(format!("{index:020}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn append(log: &FinalizationEventLog, thread: &ThreadIdentifier, id: u8) -> Option<u64> {
+        log.append(
+            *thread,
+            BlockIdentifier::from([id; 32]),
+            BlockSeqNo::from(id as u32),
+            NodeIdentifier::some_id(),
+            vec![id],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_finalization_event_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let thread = ThreadIdentifier::default();
+        let log = FinalizationEventLog::open(dir.path().to_owned()).unwrap();
+        let mut archive = log.subscribe("archive").unwrap();
+        let mut watcher = log.subscribe("watcher").unwrap();
+        assert_eq!(append(&log, &thread, 1), Some(0));
+        assert_eq!(append(&log, &thread, 2), Some(1));
+        assert_eq!(append(&log, &thread, 2), None);
+
+        let event = archive.next_event(Duration::ZERO).unwrap().unwrap();
+        assert_eq!(event.block, vec![1]);
+        // Not acknowledged events are delivered again
+        assert_eq!(archive.next_event(Duration::ZERO).unwrap().unwrap().index, 0);
+        archive.ack(&event).unwrap();
+        assert!(archive.ack(&event).is_err());
+        assert_eq!(archive.backlog(), 1);
+        assert_eq!(watcher.backlog(), 2);
+        drop(archive);
+        drop(log);
+
+        // Restarted log and consumer continue from the persisted state
+        let log = FinalizationEventLog::open(dir.path().to_owned()).unwrap();
+        let mut archive = log.subscribe("archive").unwrap();
+        let mut watcher = log.subscribe("watcher").unwrap();
+        assert_eq!(append(&log, &thread, 2), None);
+        let event = archive.next_event(Duration::ZERO).unwrap().unwrap();
+        assert_eq!(event.index, 1);
+        archive.ack(&event).unwrap();
+        assert!(archive.next_event(Duration::ZERO).unwrap().is_none());
+
+        let event = watcher.next_event(Duration::ZERO).unwrap().unwrap();
+        watcher.ack(&event).unwrap();
+        // Acknowledged by both consumers, the last event is kept
+        assert!(!event_path(dir.path(), &thread, 0).exists());
+        assert!(event_path(dir.path(), &thread, 1).exists());
+        assert_eq!(append(&log, &thread, 3), Some(2));
+        log.sync().unwrap();
+        drop(archive);
+        drop(watcher);
+        drop(log);
+
+        // The watcher is removed, its cursor expires and stops holding events
+        let log = FinalizationEventLog::open(dir.path().to_owned()).unwrap();
+        let mut archive = log.subscribe("archive").unwrap();
+        log.state.lock().opened_at = Instant::now().checked_sub(STALE_CURSOR_TIMEOUT);
+        assert_eq!(append(&log, &thread, 4), Some(3));
+        let event = archive.next_event(Duration::ZERO).unwrap().unwrap();
+        assert_eq!(event.index, 2);
+        archive.ack(&event).unwrap();
+        assert!(!event_path(dir.path(), &thread, 1).exists());
+        assert!(!dir.path().join(CURSORS_DIR).join("watcher").exists());
+    }
+}
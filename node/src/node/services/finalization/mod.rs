@@ -1,22 +1,27 @@
 // 2022-2024 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
 //
 
+pub mod event_log;
+pub mod rotations;
+
 use std::cmp::max;
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
 
 use parking_lot::Mutex;
-use telemetry_utils::mpsc::InstrumentedSender;
 use tracing::trace_span;
 
 use crate::bls::envelope::BLSSignedEnvelope;
 use crate::bls::envelope::Envelope;
 use crate::bls::GoshBLS;
+use crate::external_messages::ExternalMessagesThreadState;
 use crate::helper::block_flow_trace;
 use crate::helper::metrics::BlockProductionMetrics;
 use crate::helper::SHUTDOWN_FLAG;
 use crate::node::block_state::tools::invalidate_branch;
 use crate::node::services::block_processor::chain_pulse::events::ChainPulseEvent;
+use crate::node::services::finalization::event_log::FinalizationEventLog;
+use crate::node::services::finalization::rotations::producer_rotation;
 use crate::node::services::sync::StateSyncService;
 use crate::node::unprocessed_blocks_collection::UnfinalizedCandidateBlockCollection;
 use crate::node::BlockState;
@@ -39,16 +44,18 @@ pub fn finalization_loop(
     mut repository: RepositoryImpl,
     block_state_repository: BlockStateRepository,
     mut shared_services: SharedServices,
-    mut raw_block_tx: InstrumentedSender<(NodeIdentifier, Vec<u8>)>,
+    finalization_log: FinalizationEventLog,
     state_sync_service: impl StateSyncService<Repository = RepositoryImpl>,
     metrics: Option<BlockProductionMetrics>,
     _message_db: MessageDurableStorage,
+    external_messages: ExternalMessagesThreadState,
     node_id: &NodeIdentifier,
     authority: Arc<Mutex<Authority>>,
     unprocessed_blocks_cache: UnfinalizedCandidateBlockCollection,
     last_block_attestations: Arc<Mutex<CollectedAttestations>>,
     chain_pulse_monitor: Sender<ChainPulseEvent>,
     thread_identifier: ThreadIdentifier,
+    time_to_produce_block_millis: u64,
 ) {
     tracing::trace!("try_finalize_blocks start");
     let state_sync_service = Arc::new(state_sync_service);
@@ -99,6 +106,9 @@ pub fn finalization_loop(
         let mut height_cutoff = max_child_deadline + 1 + *last_finalized_block_height.height() + 1;
         for (block_state, _candidate_block) in unprocessed_blocks.blocks().values() {
             if SHUTDOWN_FLAG.get() == Some(&true) {
+                if let Err(e) = finalization_log.sync() {
+                    tracing::error!("Failed to sync finalization event log: {e}");
+                }
                 return;
             }
             if block_state.guarded(|e| *e.block_height()).map(|h| *h.height()).unwrap_or_default()
@@ -111,14 +121,16 @@ pub fn finalization_loop(
                 &mut repository,
                 &block_state_repository,
                 &mut shared_services,
-                &mut raw_block_tx,
+                &finalization_log,
                 &metrics,
+                &external_messages,
                 node_id,
                 authority.clone(),
                 state_sync_service.clone(),
                 last_block_attestations.clone(),
                 &unprocessed_blocks_cache,
                 &chain_pulse_monitor,
+                time_to_produce_block_millis,
             )
             .expect("try_finalize iteration failed")
             {
@@ -138,14 +150,16 @@ fn try_finalize(
     repository: &mut RepositoryImpl,
     block_state_repository: &BlockStateRepository,
     shared_services: &mut SharedServices,
-    raw_block_tx: &mut InstrumentedSender<(NodeIdentifier, Vec<u8>)>,
+    finalization_log: &FinalizationEventLog,
     metrics: &Option<BlockProductionMetrics>,
+    external_messages: &ExternalMessagesThreadState,
     node_id: &NodeIdentifier,
     authority: Arc<Mutex<Authority>>,
     state_sync_service: Arc<impl StateSyncService<Repository = RepositoryImpl>>,
     last_block_attestations: Arc<Mutex<CollectedAttestations>>,
     unprocessed_blocks_cache: &UnfinalizedCandidateBlockCollection,
     chain_pulse_monitor: &Sender<ChainPulseEvent>,
+    time_to_produce_block_millis: u64,
 ) -> anyhow::Result<Option<u64>> {
     tracing::trace!(
         "try_finalize_blocks: process: {:?}",
@@ -215,10 +229,13 @@ fn try_finalize(
                 &candidate_block,
                 repository,
                 block_state_repository,
-                raw_block_tx,
+                finalization_log,
                 state_sync_service.clone(),
                 last_block_attestations.clone(),
             )?;
+            if let Err(e) = external_messages.on_block_finalized(candidate_block.data()) {
+                tracing::error!("Failed to record external messages of the finalized block: {e}");
+            }
             let new_height_border = *block_height.height()
                 + *attestation_target.primary().generation_deadline() as u64 * 2
                 + 2;
@@ -239,6 +256,26 @@ fn try_finalize(
                 let tx_count = candidate_block.data().tx_cnt();
                 x.report_finalization(seq_no, tx_count, &thread_id);
             });
+            shared_services.node_status.as_ref().inspect(|x| {
+                x.report_finalized(&format!("{thread_id:x}"), block_seq_no.into());
+            });
+            if let Some(feed) = shared_services.producer_rotations.as_ref() {
+                let parent_id = candidate_block.data().parent();
+                let rotation = block_state_repository.get(&parent_id).ok().and_then(|parent| {
+                    producer_rotation(&block_state, &parent, time_to_produce_block_millis)
+                });
+                if let Some(rotation) = rotation {
+                    tracing::info!(
+                        "Producer rotation at {:?}: {} -> {} ({:?}, {} blocks missed)",
+                        block_id,
+                        rotation.previous_producer,
+                        rotation.new_producer,
+                        rotation.trigger,
+                        rotation.blocks_missed,
+                    );
+                    feed.publish(rotation);
+                }
+            }
         }
     }
     if max_finalized_seq_no != BlockSeqNo::default() {
@@ -270,7 +307,7 @@ pub fn on_block_finalized(
     block: &Envelope<GoshBLS, AckiNackiBlock>,
     repository: &mut RepositoryImpl,
     block_state_repository: &BlockStateRepository,
-    raw_block_tx: &mut InstrumentedSender<(NodeIdentifier, Vec<u8>)>,
+    finalization_log: &FinalizationEventLog,
     state_sync_service: Arc<impl StateSyncService<Repository = RepositoryImpl>>,
     last_block_attestations: Arc<Mutex<CollectedAttestations>>,
 ) -> anyhow::Result<()> {
@@ -283,13 +320,22 @@ pub fn on_block_finalized(
         }
         let thread_id = block.data().get_common_section().thread_id;
         tracing::info!("on_block_finalized: {:?} {:?}", block_seq_no, block_id.clone());
+        let producer_id = block.data().get_common_section().producer_id.clone();
+        // The event is logged before the block is marked as finalized: if the node
+        // stops in between, the block is finalized again and the log skips it
+        finalization_log.append(
+            thread_id,
+            block_id.clone(),
+            block_seq_no,
+            producer_id.clone(),
+            bincode::serialize(&block)?,
+        )?;
         repository.mark_block_as_finalized(
             block,
             block_state_repository.get(&block_id)?,
             Some(state_sync_service),
         )?;
         tracing::info!("Block marked as finalized: {:?} {:?} {:?}", block_seq_no, block_id, thread_id);
-        let producer_id = block.data().get_common_section().producer_id.clone();
         tracing::info!(
             "Last finalized block data: seq_no: {:?}, block_id: {:?}, producer_id: {}, signatures: {:?}, thread_id: {:?}, tx_cnt: {}, time: {}",
             block.data().seq_no(),
@@ -300,16 +346,6 @@ pub fn on_block_finalized(
             block.data().tx_cnt(),
             block.data().time().unwrap_or(0),
         );
-        let serialized_block = bincode::serialize(&block)?;
-        let bm_bcast_set = (producer_id, serialized_block.clone());
-        match raw_block_tx.send(bm_bcast_set)  {
-            Ok(()) => {},
-            Err(e) => {
-                if SHUTDOWN_FLAG.get() != Some(&true) {
-                    anyhow::bail!("Failed to send block: {e}");
-                }
-            }
-        }
         // Share finalized state, producer of this block has already shared this state after block production
         // if block.data().directives().share_state_resources().is_some() {
         //     let _resource_address = state_sync_service.add_share_state_task(
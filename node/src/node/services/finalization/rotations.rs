@@ -0,0 +1,117 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use http_server::ProducerRotationEvent;
+use http_server::ProducerRotationTrigger;
+
+use crate::node::BlockState;
+use crate::types::bp_selector::ProducerSelector;
+use crate::types::BlockRound;
+use crate::utilities::guarded::Guarded;
+
+/// Rotation of the producer if the block was produced by a producer other
+/// than the producer of its parent. None if the states lack the data.
+pub fn producer_rotation(
+    block_state: &BlockState,
+    parent_state: &BlockState,
+    time_to_produce_block_millis: u64,
+) -> Option<ProducerRotationEvent> {
+    let (previous_producer, parent_time_ms, parent_selector) = parent_state.guarded(|e| {
+        (e.producer().clone(), *e.block_time_ms(), e.producer_selector_data().clone())
+    });
+    let (
+        Some(thread_id),
+        Some(block_seq_no),
+        Some(new_producer),
+        Some(time_ms),
+        Some(round),
+        Some(selector),
+    ) = block_state.guarded(|e| {
+        (
+            *e.thread_identifier(),
+            *e.block_seq_no(),
+            e.producer().clone(),
+            *e.block_time_ms(),
+            *e.block_round(),
+            e.producer_selector_data().clone(),
+        )
+    })
+    else {
+        return None;
+    };
+    let previous_producer = previous_producer?;
+    if previous_producer == new_producer {
+        return None;
+    }
+    let gap_ms = parent_time_ms.map(|parent_time_ms| time_ms.saturating_sub(parent_time_ms))?;
+    Some(ProducerRotationEvent {
+        thread_id: format!("{thread_id:x}"),
+        block_id: block_state.block_identifier().to_string(),
+        block_seq_no: block_seq_no.into(),
+        previous_producer: previous_producer.to_string(),
+        new_producer: new_producer.to_string(),
+        gap_ms,
+        blocks_missed: blocks_missed(gap_ms, time_to_produce_block_millis),
+        trigger: rotation_trigger(round, parent_selector.as_ref(), &selector),
+        round,
+        new_selector_index: *selector.index(),
+        timestamp: time_ms,
+    })
+}
+
+fn rotation_trigger(
+    round: BlockRound,
+    parent_selector: Option<&ProducerSelector>,
+    selector: &ProducerSelector,
+) -> ProducerRotationTrigger {
+    if parent_selector
+        .is_some_and(|parent| parent.rng_seed_block_id() != selector.rng_seed_block_id())
+    {
+        ProducerRotationTrigger::BkSetChange
+    } else if round > 0 {
+        ProducerRotationTrigger::AuthoritySwitch
+    } else {
+        ProducerRotationTrigger::ProducerChange
+    }
+}
+
+// The block itself is expected one block time after its parent
+fn blocks_missed(gap_ms: u64, time_to_produce_block_millis: u64) -> u64 {
+    (gap_ms / time_to_produce_block_millis.max(1)).saturating_sub(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::BlockIdentifier;
+
+    #[test]
+    fn test_rotation_trigger() {
+        let selector = |seed: u8, index: usize| {
+            ProducerSelector::builder()
+                .rng_seed_block_id(BlockIdentifier::from([seed; 32]))
+                .index(index)
+                .build()
+        };
+        assert_eq!(
+            rotation_trigger(0, Some(&selector(1, 0)), &selector(1, 3)),
+            ProducerRotationTrigger::ProducerChange
+        );
+        assert_eq!(
+            rotation_trigger(2, Some(&selector(1, 0)), &selector(1, 2)),
+            ProducerRotationTrigger::AuthoritySwitch
+        );
+        assert_eq!(
+            rotation_trigger(2, Some(&selector(1, 0)), &selector(2, 0)),
+            ProducerRotationTrigger::BkSetChange
+        );
+        assert_eq!(
+            rotation_trigger(0, None, &selector(2, 0)),
+            ProducerRotationTrigger::ProducerChange
+        );
+
+        assert_eq!(blocks_missed(330, 330), 0);
+        assert_eq!(blocks_missed(2000, 330), 5);
+        assert_eq!(blocks_missed(100, 0), 99);
+    }
+}
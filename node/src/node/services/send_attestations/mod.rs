@@ -6,6 +6,7 @@ use std::time::Instant;
 
 use derive_getters::Getters;
 use derive_setters::Setters;
+use http_server::NodeStatusBoard;
 use network::channel::NetDirectSender;
 use parking_lot::Mutex;
 use rand::rngs::SmallRng;
@@ -17,6 +18,9 @@ use crate::bls::envelope::BLSSignedEnvelope;
 use crate::bls::gosh_bls::PubKey;
 use crate::bls::gosh_bls::Secret;
 use crate::bls::BLSSignatureScheme;
+use crate::config::ProducerBuildAction;
+use crate::config::ProducerBuildPolicyConfig;
+use crate::config::ProducerSelectionConfig;
 use crate::helper::block_flow_trace;
 use crate::helper::metrics::BlockProductionMetrics;
 use crate::helper::SHUTDOWN_FLAG;
@@ -36,6 +40,7 @@ use crate::protocol::authority_switch::action_lock::ActionLockResult;
 use crate::protocol::authority_switch::action_lock::BlockRef;
 use crate::repository::repository_impl::RepositoryImpl;
 use crate::repository::Repository;
+use crate::types::extensions::ProducerBuild;
 use crate::types::AckiNackiBlock;
 use crate::types::BlockIdentifier;
 use crate::types::CollectedAttestations;
@@ -68,6 +73,9 @@ struct TrackedState {
     last_send_timestamp: Option<std::time::Instant>,
     #[builder(default)]
     last_send_destinations: Option<HashSet<(NodeIdentifier, AttestationTargetType)>>,
+    // Producer build is outside the allowed range and the block is not attested
+    #[builder(default)]
+    refused_by_build_policy: bool,
 }
 
 #[derive(TypedBuilder)]
@@ -76,6 +84,10 @@ pub struct AttestationSendService {
 
     resend_attestation_timeout: std::time::Duration,
 
+    // Reserved before the producer aggregation deadline when the send is
+    // scheduled by the RTT to the producer
+    attestation_send_margin: std::time::Duration,
+
     node_id: NodeIdentifier,
 
     thread_id: ThreadIdentifier,
@@ -105,6 +117,19 @@ pub struct AttestationSendService {
 
     metrics: Option<BlockProductionMetrics>,
     authority: Arc<Mutex<Authority>>,
+
+    #[builder(default)]
+    producer_build_policy: ProducerBuildPolicyConfig,
+
+    // Order of the producers to find the distance to the block producer
+    producer_selection: ProducerSelectionConfig,
+
+    #[builder(default)]
+    node_status: Option<NodeStatusBoard>,
+
+    // The thread is followed without keeper duties, no attestations are sent.
+    #[builder(default)]
+    archive_only: bool,
 }
 
 impl AttestationSendService {
@@ -116,6 +141,9 @@ impl AttestationSendService {
         candidate_block_repository: &impl Repository<CandidateBlock = Envelope<GoshBLS, AckiNackiBlock>>,
         deadline: std::time::Instant,
     ) -> std::time::Instant {
+        if self.archive_only {
+            return std::time::Instant::now() + PULSE_IDLE_TIMEOUT * 2;
+        }
         let last_modified_state: u32 = self.block_state_repository.notifications().stamp();
         let last_modified_set: u32 = *candidates.notifications_stamp();
         if last_modified_state == self.block_state_repository_last_modified
@@ -216,6 +244,10 @@ impl AttestationSendService {
             //     trace_skip("does not have attestation to send");
             //     continue;
             // };
+            if *state.refused_by_build_policy() {
+                trace_skip("producer build is outside the allowed range");
+                continue;
+            }
             let Ok(attestation) = self.try_get_attestation(state) else {
                 trace_skip("does not have attestation to send");
                 continue;
@@ -275,8 +307,8 @@ impl AttestationSendService {
             {
                 0
             } else {
-                let Some(distance_to_producer) =
-                    parent_block_producer_selector.get_distance_from_bp(&bk_set, &producer)
+                let Some(distance_to_producer) = parent_block_producer_selector
+                    .get_distance_from_bp(&bk_set, &self.producer_selection, &producer)
                 else {
                     trace_skip("missing distance to bp");
                     continue;
@@ -284,9 +316,20 @@ impl AttestationSendService {
                 distance_to_producer
             };
 
+            let producer_rtt = self.network_direct_tx.peer_rtt(&producer);
             let earliest_to_send_attestation = {
-                let first_pulse_multiplier = 0.8_f32 + distance_to_producer as f32;
-                let delay = self.pulse_timeout.mul_f32(first_pulse_multiplier);
+                let delay = match producer_rtt {
+                    // Producer aggregates attestations one pulse after its turn: schedule
+                    // the send to arrive right before it
+                    Some(rtt) => self
+                        .pulse_timeout
+                        .mul_f32(1.0_f32 + distance_to_producer as f32)
+                        .saturating_sub(rtt / 2 + self.attestation_send_margin),
+                    None => {
+                        let first_pulse_multiplier = 0.8_f32 + distance_to_producer as f32;
+                        self.pulse_timeout.mul_f32(first_pulse_multiplier)
+                    }
+                };
                 let parent_sent_first_attestation: Option<std::time::Instant> =
                     first_sent.get(parent_block_state.block_identifier()).copied();
 
@@ -371,8 +414,15 @@ impl AttestationSendService {
                     })
                     .collect();
             let received = state.interested_parties_received_blocks().clone().unwrap_or_default();
+            // With the RTT to the producer measured, the attestation is resent once it
+            // should have reached the producer for the next aggregation, but no more
+            // often than once per pulse
+            let resend_timeout = match producer_rtt {
+                Some(rtt) => (rtt + self.attestation_send_margin).max(self.pulse_timeout),
+                None => self.resend_attestation_timeout,
+            };
             let awaiting_destinations = {
-                if last_sent_time.elapsed() > self.resend_attestation_timeout {
+                if last_sent_time.elapsed() > resend_timeout {
                     attestation_interested_parties.clone()
                 } else if attestation_interested_parties != last_destinations {
                     attestation_interested_parties
@@ -672,9 +722,13 @@ impl AttestationSendService {
 
     #[allow(clippy::mutable_key_type)]
     fn append_for_tracking(&mut self, candidates: &UnfinalizedBlocksSnapshot) {
-        for (_, (candidate, _)) in candidates.blocks().iter() {
+        for (_, (candidate, envelope)) in candidates.blocks().iter() {
             if !self.tracking.contains_key(candidate.block_identifier()) {
-                let state = TrackedState::builder().block_state(candidate.clone()).build();
+                let refused_by_build_policy = self.check_producer_build(candidate, envelope);
+                let state = TrackedState::builder()
+                    .block_state(candidate.clone())
+                    .refused_by_build_policy(refused_by_build_policy)
+                    .build();
                 tracing::trace!(
                     "AttestationSendService: append_for_tracking {:?}",
                     candidate.block_identifier()
@@ -685,6 +739,35 @@ impl AttestationSendService {
         self.tracking.retain(|block_id, _state| candidates.block_id_set().contains(block_id));
     }
 
+    // Warns once per block about a producer build outside the allowed range,
+    // returns true if the block must not be attested
+    fn check_producer_build(
+        &self,
+        candidate: &BlockState,
+        envelope: &Envelope<GoshBLS, AckiNackiBlock>,
+    ) -> bool {
+        if !self.producer_build_policy.is_enabled() {
+            return false;
+        }
+        let build = match envelope.data().get_common_section().extensions.get::<ProducerBuild>() {
+            Ok(build) => build,
+            Err(e) => {
+                tracing::warn!("Failed to read producer build: {e}");
+                None
+            }
+        };
+        let Some(reason) = self.producer_build_policy.check(build.as_ref()) else {
+            return false;
+        };
+        let producer = candidate.guarded(|e| e.producer().clone());
+        tracing::warn!(
+            "Block {:?} of producer {producer:?}: {reason}",
+            candidate.block_identifier()
+        );
+        self.metrics.as_ref().inspect(|m| m.report_producer_build_violation(&self.thread_id));
+        self.producer_build_policy.action == ProducerBuildAction::RefuseAttestation
+    }
+
     #[allow(clippy::mutable_key_type)]
     fn update_interested_parties_received_blocks(
         &mut self,
@@ -756,6 +839,7 @@ impl AttestationSendService {
 
     fn handle_attestation_metrics_inner(&self, block_id: &BlockIdentifier) -> anyhow::Result<()> {
         let mut parent_block_identifier = None;
+        let mut block_seq_no = None;
         // Send metrics if only we can obtain a lock
         let current_millis = now_ms();
         let (
@@ -765,6 +849,7 @@ impl AttestationSendService {
             block_applied_timestamp_ms,
         ) = self.block_state_repository.get(block_id)?.guarded_mut(|e| {
             parent_block_identifier = e.parent_block_identifier().clone();
+            block_seq_no = *e.block_seq_no();
             if e.event_timestamps.attestation_sent_ms.is_none() {
                 e.event_timestamps.attestation_sent_ms = Some(current_millis);
                 (
@@ -786,6 +871,9 @@ impl AttestationSendService {
         }
 
         if did_update {
+            if let (Some(node_status), Some(seq_no)) = (self.node_status.as_ref(), block_seq_no) {
+                node_status.report_attested(&format!("{:x}", self.thread_id), seq_no.into());
+            }
             if let Some(metrics) = self.metrics.as_ref() {
                 // Report the duration from the moment the block is received until the attestation is sent
                 if let Some(received_ms) = received_ms {
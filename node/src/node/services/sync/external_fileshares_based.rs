@@ -3,7 +3,6 @@
 
 use std::collections::HashMap;
 use std::collections::HashSet;
-use std::path::PathBuf;
 use std::sync::Arc;
 use std::thread::JoinHandle;
 use std::time::Duration;
@@ -15,6 +14,7 @@ use url::Url;
 
 use crate::helper::SHUTDOWN_FLAG;
 use crate::node::services::sync::FileSavingService;
+use crate::node::services::sync::StateSyncError;
 use crate::node::services::sync::StateSyncService;
 use crate::node::services::sync::GOSSIP_API_ADVERTISE_ADDR_KEY;
 use crate::repository::optimistic_state::OptimisticStateImpl;
@@ -28,7 +28,8 @@ use crate::utilities::thread_spawn_critical::SpawnCritical;
 
 #[derive(Clone)]
 pub struct ExternalFileSharesBased {
-    pub static_storages: Vec<url::Url>,
+    /// Static storages with SRV urls already resolved.
+    pub static_storages: tokio::sync::watch::Receiver<Vec<url::Url>>,
     pub max_download_tries: u8,
     pub retry_download_timeout: std::time::Duration,
     pub download_deadline_timeout: std::time::Duration,
@@ -46,7 +47,7 @@ impl ExternalFileSharesBased {
     ) -> Self {
         // TODO: move to config
         Self {
-            static_storages: vec![],
+            static_storages: tokio::sync::watch::channel(vec![]).1,
             max_download_tries: 3,
             retry_download_timeout: Duration::from_secs(2),
             download_deadline_timeout: Duration::from_secs(120),
@@ -61,11 +62,15 @@ impl ExternalFileSharesBased {
 impl StateSyncService for ExternalFileSharesBased {
     type Repository = RepositoryImpl;
 
-    fn save_state_for_sharing(&self, state: Arc<OptimisticStateImpl>) -> anyhow::Result<()> {
+    fn save_state_for_sharing(
+        &self,
+        state: Arc<OptimisticStateImpl>,
+    ) -> Result<(), StateSyncError> {
         let block_id = state.block_id.clone();
         tracing::trace!("save_state_for_sharing: {:?}", block_id);
-        let file_name = PathBuf::from(block_id.to_string());
-        self.file_saving_service.save_object(state, file_name.clone())
+        self.file_saving_service
+            .save_object(state, block_id.to_string())
+            .map_err(StateSyncError::SaveState)
     }
 
     fn reset_sync(&self) {
@@ -77,7 +82,7 @@ impl StateSyncService for ExternalFileSharesBased {
         resource_address: HashMap<ThreadIdentifier, BlockIdentifier>,
         repository: RepositoryImpl,
         output: InstrumentedSender<anyhow::Result<()>>,
-    ) -> anyhow::Result<()> {
+    ) -> Result<(), StateSyncError> {
         let mut thread = self.state_load_thread.lock();
         if let Some(thread) = thread.as_ref() {
             if !thread.is_finished() {
@@ -93,7 +98,8 @@ impl StateSyncService for ExternalFileSharesBased {
             let checker_clone = checker.clone();
             let repo_clone = repo.clone();
             let external_blob_share_services = {
-                let mut services = HashSet::<Url>::from_iter(self.static_storages.iter().cloned());
+                let mut services =
+                    HashSet::<Url>::from_iter(self.static_storages.borrow().iter().cloned());
                 Extend::extend(
                     &mut services,
                     self.chitchat
@@ -108,43 +114,45 @@ impl StateSyncService for ExternalFileSharesBased {
                 );
                 services.drain().collect()
             };
-            self.blob_sync.load_blob(
-                block_id.to_string(),
-                external_blob_share_services,
-                self.max_download_tries,
-                Some(self.retry_download_timeout),
-                Some(std::time::Instant::now() + self.download_deadline_timeout),
-                {
-                    move |e| {
-                        let mut buffer: Vec<u8> = vec![];
-                        match e.read_to_end(&mut buffer) {
-                            Ok(_size) => {
-                                let res = repo_clone.lock().set_state_from_snapshot(
-                                    buffer,
-                                    &ThreadIdentifier::default(),
-                                    Arc::new(Mutex::new(HashSet::new())),
-                                );
-                                tracing::trace!(
-                                    "add_load_state_task: for {thread_id:?} res={res:?}"
-                                );
-                                res.expect("Failed to set state from snapshot");
-                                tracing::trace!("add_load_state_task: done for {thread_id:?}");
-                                checker_clone.lock().remove(&thread_id);
-                            }
-                            Err(e) => {
-                                let _ = output_clone.send(Err(e.into()));
+            self.blob_sync
+                .load_blob(
+                    block_id.to_string(),
+                    external_blob_share_services,
+                    self.max_download_tries,
+                    Some(self.retry_download_timeout),
+                    Some(std::time::Instant::now() + self.download_deadline_timeout),
+                    {
+                        move |e| {
+                            let mut buffer: Vec<u8> = vec![];
+                            match e.read_to_end(&mut buffer) {
+                                Ok(_size) => {
+                                    let res = repo_clone.lock().set_state_from_snapshot(
+                                        buffer,
+                                        &ThreadIdentifier::default(),
+                                        Arc::new(Mutex::new(HashSet::new())),
+                                    );
+                                    tracing::trace!(
+                                        "add_load_state_task: for {thread_id:?} res={res:?}"
+                                    );
+                                    res.expect("Failed to set state from snapshot");
+                                    tracing::trace!("add_load_state_task: done for {thread_id:?}");
+                                    checker_clone.lock().remove(&thread_id);
+                                }
+                                Err(e) => {
+                                    let _ = output_clone.send(Err(e.into()));
+                                }
                             }
                         }
-                    }
-                },
-                {
-                    let output_clone = output.clone();
-                    move |e| {
-                        // Handle error
-                        let _ = output_clone.send(Err(e));
-                    }
-                },
-            )?;
+                    },
+                    {
+                        let output_clone = output.clone();
+                        move |e| {
+                            // Handle error
+                            let _ = output_clone.send(Err(e));
+                        }
+                    },
+                )
+                .map_err(StateSyncError::LoadState)?;
         }
         let spawned = std::thread::Builder::new().name("State load".to_string()).spawn_critical(
             move || loop {
@@ -6,7 +6,6 @@ use std::thread::JoinHandle;
 use parking_lot::Mutex;
 use typed_builder::TypedBuilder;
 
-use crate::helper::get_temp_file_path;
 use crate::node::block_state::repository::BlockStateRepository;
 use crate::node::shared_services::SharedServices;
 use crate::repository::cross_thread_ref_repository::CrossThreadRefDataHistory;
@@ -15,6 +14,7 @@ use crate::repository::repository_impl::RepositoryImpl;
 use crate::repository::repository_impl::ThreadSnapshot;
 use crate::repository::CrossThreadRefData;
 use crate::repository::Repository;
+use crate::services::blob_sync::external_fileshares_based::share_blob::share_blob;
 use crate::storage::MessageDurableStorage;
 use crate::types::thread_message_queue::account_messages_iterator::AccountMessagesIterator;
 use crate::utilities::guarded::AllowGuardedMut;
@@ -38,16 +38,15 @@ impl FileSavingService {
     pub fn save_object(
         &self,
         state: Arc<OptimisticStateImpl>,
-        path: PathBuf,
+        resource_id: String,
     ) -> anyhow::Result<()> {
-        let path = self.root_path.join(path);
-        let parent_dir = self.root_path.clone();
+        let share_dir = self.root_path.clone();
         let message_db = self.message_db.clone();
         let mut shared_services = self.shared_services.clone();
         let block_state_repository = self.block_state_repository.clone();
         let repository = self.repository.clone();
         let thread = std::thread::Builder::new()
-            .name(format!("Saving state: {}", path.display()))
+            .name(format!("Saving state: {resource_id}"))
             .spawn(move || {
                 let block_id = state.block_id.clone();
                 let db_messages = state
@@ -55,7 +54,7 @@ impl FileSavingService {
                     .iter(&message_db)
                     .map(|range| range.remaining_messages_from_db().unwrap_or_default())
                     .collect();
-                let serialized_state = bincode::serialize(&state)?;
+                let serialized_state = state.serialize_extended()?;
                 let cross_thread_ref_data_history =
                     shared_services.exec(|e| -> anyhow::Result<Vec<CrossThreadRefData>> {
                         e.cross_thread_ref_data_service.get_history_tail(&block_id)
@@ -100,10 +99,7 @@ impl FileSavingService {
                     .build();
 
                 let bytes = bincode::serialize(&shared_thread_state)?;
-                let tmp_file_path = get_temp_file_path(&parent_dir);
-                std::fs::write(tmp_file_path.clone(), bytes)?;
-                std::fs::rename(tmp_file_path, path)?;
-                Ok(())
+                share_blob(&share_dir, &resource_id, &mut bytes.as_slice())
             })?;
         self.threads.guarded_mut(|threads| {
             threads.retain(|thread| !thread.is_finished());
@@ -1,6 +1,7 @@
 // 2022-2024 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
 //
 mod state_sync_service_trait;
+pub use state_sync_service_trait::StateSyncError;
 pub use state_sync_service_trait::StateSyncService;
 
 mod external_fileshares_based;
@@ -13,3 +14,5 @@ mod file_saving_service;
 pub use file_saving_service::FileSavingService;
 
 pub const GOSSIP_API_ADVERTISE_ADDR_KEY: &str = "api_advertise_addr";
+pub const GOSSIP_NODE_VERSION_KEY: &str = "node_version";
+pub const GOSSIP_NODE_COMMIT_KEY: &str = "node_commit";
@@ -11,6 +11,32 @@ use crate::repository::repository_impl::RepositoryImpl;
 use crate::repository::Repository;
 use crate::types::BlockIdentifier;
 use crate::types::ThreadIdentifier;
+use crate::utilities::ErrorCode;
+
+#[derive(thiserror::Error, Debug)]
+pub enum StateSyncError {
+    #[error("Failed to save state for sharing: {0}")]
+    SaveState(anyhow::Error),
+    #[error("Failed to start state download: {0}")]
+    LoadState(anyhow::Error),
+    #[error("Failed to spawn state load thread: {0}")]
+    ThreadSpawn(#[from] std::io::Error),
+}
+
+impl ErrorCode for StateSyncError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::SaveState(_) => "STATE_SYNC_SAVE_STATE",
+            Self::LoadState(_) => "STATE_SYNC_LOAD_STATE",
+            Self::ThreadSpawn(_) => "STATE_SYNC_THREAD_SPAWN",
+        }
+    }
+
+    fn is_retryable(&self) -> bool {
+        // State is shared and requested again on the next sync round
+        matches!(self, Self::SaveState(_) | Self::LoadState(_))
+    }
+}
 
 pub trait StateSyncService {
     type Repository: Repository;
@@ -23,14 +49,15 @@ pub trait StateSyncService {
     // - accept a data storage as a parameters to be able to take state directly
     //   from there.
     // - use data storage to snapshot state and then publish it on ipfs.
-    fn save_state_for_sharing(&self, state: Arc<OptimisticStateImpl>) -> anyhow::Result<()>;
+    fn save_state_for_sharing(&self, state: Arc<OptimisticStateImpl>)
+        -> Result<(), StateSyncError>;
 
     fn add_load_state_task(
         &mut self,
         resource_address: HashMap<ThreadIdentifier, BlockIdentifier>,
         repository: RepositoryImpl,
         output: InstrumentedSender<anyhow::Result<()>>,
-    ) -> anyhow::Result<()>;
+    ) -> Result<(), StateSyncError>;
 
     fn reset_sync(&self);
 }
@@ -6,6 +6,7 @@ use std::sync::Arc;
 
 use telemetry_utils::mpsc::InstrumentedSender;
 
+use crate::node::services::sync::StateSyncError;
 use crate::node::services::sync::StateSyncService;
 use crate::repository::optimistic_state::OptimisticStateImpl;
 use crate::repository::repository_impl::RepositoryImpl;
@@ -21,7 +22,10 @@ impl StateSyncService for StateSyncServiceStub {
         todo!()
     }
 
-    fn save_state_for_sharing(&self, _state: Arc<OptimisticStateImpl>) -> anyhow::Result<()> {
+    fn save_state_for_sharing(
+        &self,
+        _state: Arc<OptimisticStateImpl>,
+    ) -> Result<(), StateSyncError> {
         todo!()
     }
 
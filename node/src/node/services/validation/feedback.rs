@@ -2,6 +2,7 @@
 //
 
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::sync::Arc;
 
 use network::channel::NetBroadcastSender;
@@ -25,6 +26,7 @@ use crate::node::Secret;
 use crate::node::SignerIndex;
 use crate::types::AckiNackiBlock;
 use crate::types::RndSeed;
+use crate::types::ThreadIdentifier;
 use crate::utilities::guarded::Guarded;
 
 #[derive(TypedBuilder, Clone)]
@@ -33,6 +35,9 @@ pub struct AckiNackiSend {
     bls_keys_map: Arc<Mutex<HashMap<PubKey, (Secret, RndSeed)>>>,
     ack_network_direct_tx: NetDirectSender<NodeIdentifier, NetworkMessage>,
     nack_network_broadcast_tx: NetBroadcastSender<NetworkMessage>,
+    // Threads this node follows without keeper duties.
+    #[builder(default)]
+    archive_only_threads: HashSet<ThreadIdentifier>,
 }
 
 impl AckiNackiSend {
@@ -53,6 +58,9 @@ impl AckiNackiSend {
         else {
             anyhow::bail!("block state does not have valid data set")
         };
+        if self.archive_only_threads.contains(&thread_identifier) {
+            return Ok(());
+        }
         let destinations = {
             known_attestation_interested_parties.remove(&self.node_id);
             known_attestation_interested_parties
@@ -101,6 +109,9 @@ impl AckiNackiSend {
         else {
             anyhow::bail!("block state does not have valid data set")
         };
+        if self.archive_only_threads.contains(&thread_id) {
+            return Ok(());
+        }
         let Some((node_epoch_signer_index, node_epoch_secret)) = self.get_signer_data(&block_state)
         else {
             tracing::warn!("Node is not in BK set for given block");
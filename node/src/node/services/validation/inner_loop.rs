@@ -5,18 +5,21 @@ use std::collections::VecDeque;
 use std::sync::mpsc::RecvError;
 use std::sync::mpsc::TryRecvError;
 use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
 
 use parking_lot::Mutex;
 use telemetry_utils::mpsc::InstrumentedReceiver;
-use tvm_executor::BlockchainConfig;
 
 use crate::block::producer::wasm::WasmNodeCache;
 use crate::block::verify::verify_block;
 use crate::bls::envelope::BLSSignedEnvelope;
 use crate::bls::envelope::Envelope;
 use crate::bls::GoshBLS;
+use crate::config::BlockchainConfigSource;
 use crate::config::Config;
 use crate::helper::metrics::BlockProductionMetrics;
+use crate::helper::metrics::ProducerBlockOutcome;
 use crate::helper::SHUTDOWN_FLAG;
 use crate::node::block_state::repository::BlockStateRepository;
 use crate::node::block_state::tools::invalidate_branch;
@@ -32,6 +35,7 @@ use crate::storage::MessageDurableStorage;
 use crate::types::AckiNackiBlock;
 use crate::utilities::guarded::Guarded;
 use crate::utilities::guarded::GuardedMut;
+use crate::utilities::load_controller::LOAD_CONTROLLER;
 
 fn read_into_buffer(
     rx: &mut InstrumentedReceiver<(BlockState, Envelope<GoshBLS, AckiNackiBlock>)>,
@@ -63,7 +67,7 @@ pub(super) fn inner_loop(
     mut rx: InstrumentedReceiver<(BlockState, Envelope<GoshBLS, AckiNackiBlock>)>,
     block_state_repo: BlockStateRepository,
     repository: RepositoryImpl,
-    blockchain_config: Arc<BlockchainConfig>,
+    blockchain_config: BlockchainConfigSource,
     node_config: Config,
     mut shared_services: SharedServices,
     send: AckiNackiSend,
@@ -73,6 +77,7 @@ pub(super) fn inner_loop(
     authority: Arc<Mutex<Authority>>,
 ) {
     let mut buffer = VecDeque::<(BlockState, Envelope<GoshBLS, AckiNackiBlock>)>::new();
+    let verify_timeout = Duration::from_millis(node_config.global.time_to_verify_block_millis);
     loop {
         if SHUTDOWN_FLAG.get() == Some(&true) {
             return;
@@ -150,10 +155,24 @@ pub(super) fn inner_loop(
                 refs
             });
 
+            // The block stays in the buffer and is verified again on the next
+            // iteration, other blocks are not held up by it
+            let block_blockchain_config =
+                match blockchain_config.for_state(&prev_state, repository.accounts_repository()) {
+                    Ok(config) => config,
+                    Err(e) => {
+                        tracing::error!(
+                            "Failed to resolve blockchain config for block {:?}: {e}",
+                            block_identifier
+                        );
+                        continue;
+                    }
+                };
             let block_nack = next_block.get_common_section().nacks.clone();
+            let verify_started = Instant::now();
             let verify_res = verify_block(
                 &next_block,
-                blockchain_config.clone(),
+                block_blockchain_config,
                 &mut prev_state,
                 node_config.clone(),
                 refs,
@@ -166,6 +185,10 @@ pub(super) fn inner_loop(
                 message_db.clone(),
             )
             .expect("Failed to verify block");
+            LOAD_CONTROLLER.report_deadline(
+                &next_block.get_common_section().thread_id,
+                verify_started.elapsed() > verify_timeout,
+            );
             if !verify_res {
                 tracing::warn!("Block verification failed: {:?}", block_identifier);
             }
@@ -180,10 +203,19 @@ pub(super) fn inner_loop(
             if SHUTDOWN_FLAG.get() == Some(&true) {
                 return;
             }
+            let producer = &next_block.get_common_section().producer_id;
+            let thread_id = next_block.get_common_section().thread_id;
+            metrics.as_ref().inspect(|m| {
+                let outcome = if verify_res {
+                    ProducerBlockOutcome::Acked
+                } else {
+                    ProducerBlockOutcome::Nacked("verification")
+                };
+                m.report_producer_block(producer, outcome, &thread_id);
+            });
             if verify_res {
                 let _ = send.send_ack(state.clone());
             } else {
-                let thread_id = next_block.get_common_section().thread_id;
                 authority
                     .guarded_mut(|e| e.get_thread_authority(&thread_id))
                     .guarded_mut(|e| e.on_bad_block_nack_confirmed(state.clone()));
@@ -1,7 +1,6 @@
 // 2022-2024 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
 //
 
-use std::path::Path;
 use std::sync::Arc;
 
 use parking_lot::Mutex;
@@ -14,7 +13,7 @@ use super::inner_loop;
 use crate::block::producer::wasm::WasmNodeCache;
 use crate::bls::envelope::Envelope;
 use crate::bls::GoshBLS;
-use crate::config::load_blockchain_config;
+use crate::config::BlockchainConfigSource;
 use crate::config::Config;
 use crate::helper::metrics::BlockProductionMetrics;
 use crate::helper::SHUTDOWN_FLAG;
@@ -56,8 +55,8 @@ impl ValidationService {
     }
 
     #[allow(clippy::too_many_arguments)]
-    pub fn new<P: AsRef<Path>>(
-        blockchain_config_path: P,
+    pub fn new(
+        blockchain_config: BlockchainConfigSource,
         repository: RepositoryImpl,
         node_config: Config,
         shared_services: SharedServices,
@@ -71,7 +70,6 @@ impl ValidationService {
         let (tx, rx) =
             instrumented_channel(metrics.clone(), crate::helper::metrics::BLOCK_STATE_CHANNEL);
         let interface = ValidationServiceInterface { send_tx: tx };
-        let blockchain_config = load_blockchain_config(&blockchain_config_path.as_ref().into())?;
 
         let handler: std::thread::JoinHandle<()> = std::thread::Builder::new()
             .name("Block validation service".to_string())
@@ -80,7 +78,7 @@ impl ValidationService {
                     rx,
                     block_state_repo,
                     repository,
-                    blockchain_config.into(),
+                    blockchain_config,
                     node_config,
                     shared_services,
                     send,
@@ -6,6 +6,9 @@ use std::sync::Mutex;
 use governor::DefaultKeyedRateLimiter;
 use governor::Quota;
 use governor::RateLimiter;
+use http_server::NodeStatusBoard;
+use http_server::OutMsgQueueIndex;
+use http_server::ProducerRotationFeed;
 
 use super::NodeIdentifier;
 use crate::helper::metrics::BlockProductionMetrics;
@@ -16,6 +19,7 @@ use crate::multithreading::threads_tracking_service::ThreadsTrackingService;
 use crate::repository::optimistic_state::OptimisticState;
 use crate::repository::CrossThreadRefDataRepository;
 use crate::storage::CrossRefStorage;
+use crate::types::AccountAddress;
 use crate::types::AckiNackiBlock;
 use crate::types::BlockIdentifier;
 use crate::types::ThreadIdentifier;
@@ -27,6 +31,11 @@ const DIRTY_HACK_CACHE_SIZE: usize = 10000; // 100 blocks for 100 threads.
 pub struct SharedServices {
     container: Arc<Mutex<Container>>,
     pub metrics: Option<BlockProductionMetrics>,
+    pub node_status: Option<NodeStatusBoard>,
+    pub producer_rotations: Option<ProducerRotationFeed>,
+    pub out_msg_queues: Option<OutMsgQueueIndex>,
+    // Account that schedules deferred messages, None disables them
+    pub timer_contract: Option<AccountAddress>,
     limiter: Arc<DefaultKeyedRateLimiter<NodeIdentifier>>,
 }
 
@@ -100,6 +109,10 @@ impl SharedServices {
                 dirty_hack__invalidated_blocks: FixedSizeHashSet::new(DIRTY_HACK_CACHE_SIZE),
             })),
             metrics,
+            node_status: None,
+            producer_rotations: None,
+            out_msg_queues: None,
+            timer_contract: None,
             // Arc is enough for the rate limiter, since its state lives in AtomicU64
             // https://docs.rs/governor/latest/governor/_guide/index.html#wrapping-the-limiter-in-an-arc
             limiter: Arc::new(RateLimiter::keyed(Quota::per_second(
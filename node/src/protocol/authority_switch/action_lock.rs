@@ -6,8 +6,6 @@ use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::Duration;
 use std::time::Instant;
-use std::time::SystemTime;
-use std::time::UNIX_EPOCH;
 
 use derive_getters::Getters;
 use derive_setters::Setters;
@@ -20,7 +18,11 @@ use typed_builder::TypedBuilder;
 
 use super::find_last_prefinalized::find_last_prefinalized;
 use super::find_last_prefinalized::find_next_prefinalized;
+use super::fork_resolution::resolve_fork;
+use super::fork_resolution::ForkCandidate;
 use super::round_time::RoundTime;
+use super::skip_votes::SkipVotes;
+use crate::block_keeper_system::BlockKeeperSet;
 use crate::bls::create_signed::CreateSealed;
 use crate::bls::envelope::BLSSignedEnvelope;
 use crate::bls::envelope::Envelope;
@@ -28,6 +30,7 @@ use crate::bls::gosh_bls::PubKey;
 use crate::bls::gosh_bls::Secret;
 use crate::bls::BLSSignatureScheme;
 use crate::bls::GoshBLS;
+use crate::config::ProducerSelectionConfig;
 use crate::helper::SHUTDOWN_FLAG;
 use crate::node::associated_types::AttestationData;
 use crate::node::associated_types::AttestationTargetType;
@@ -44,6 +47,7 @@ use crate::protocol::authority_switch::network_message::Lock;
 use crate::protocol::authority_switch::network_message::NextRound;
 use crate::protocol::authority_switch::network_message::NextRoundReject;
 use crate::protocol::authority_switch::network_message::NextRoundSuccess;
+use crate::protocol::authority_switch::network_message::SkipVote;
 use crate::protocol::authority_switch::round_time::CalculateRoundResult;
 use crate::repository::repository_impl::RepositoryImpl;
 use crate::repository::Repository;
@@ -56,8 +60,10 @@ use crate::types::BlockRound;
 use crate::types::BlockSeqNo;
 use crate::types::RndSeed;
 use crate::types::ThreadIdentifier;
+use crate::utilities::clock::CLOCK;
 use crate::utilities::guarded::AllowGuardedMut;
 use crate::utilities::guarded::Guarded;
+use crate::utilities::guarded::GuardedMut;
 
 // Note: std::time::Instant is not serializable
 pub type Timeout = std::time::SystemTime;
@@ -159,6 +165,12 @@ pub struct Authority {
     network_broadcast_tx: NetBroadcastSender<NetworkMessage>,
     node_joining_timeout: Duration,
     action_lock_db: ActionLockStorage,
+    // None disables producer skip votes.
+    skip_vote_after: Option<Duration>,
+    producer_selection: ProducerSelectionConfig,
+    // Threads this node follows without keeper duties.
+    #[builder(default)]
+    archive_only_threads: HashSet<ThreadIdentifier>,
 }
 
 impl Authority {
@@ -183,6 +195,9 @@ impl Authority {
                     .bp_production_count(self.bp_production_count.clone())
                     .network_broadcast_tx(self.network_broadcast_tx.clone())
                     .node_joining_timeout(self.node_joining_timeout)
+                    .skip_vote_after(self.skip_vote_after)
+                    .producer_selection(self.producer_selection)
+                    .archive_only(self.archive_only_threads.contains(thread_id))
                     .build(),
             ))),
         )
@@ -340,6 +355,25 @@ pub struct ThreadAuthority {
     #[builder(default = None)]
     block_producers: Option<std::sync::mpsc::Sender<BlockProducerCommand>>,
 
+    // Time without a block in a round after which this node votes to skip the round producer.
+    skip_vote_after: Option<Duration>,
+
+    // Order of the round producers.
+    producer_selection: ProducerSelectionConfig,
+
+    // The thread is followed without keeper duties: this node neither votes
+    // in the rounds nor produces blocks.
+    #[builder(default)]
+    archive_only: bool,
+
+    #[builder(setter(skip))]
+    #[builder(default)]
+    skip_votes: SkipVotes<SiblingsBlockHeightKey>,
+
+    #[builder(setter(skip))]
+    #[builder(default = HashSet::new())]
+    sent_skip_votes: HashSet<(SiblingsBlockHeightKey, BlockRound)>,
+
     block_repository: RepositoryImpl,
     block_state_repository: BlockStateRepository,
     network_direct_tx: NetDirectSender<NodeIdentifier, NetworkMessage>,
@@ -446,11 +480,7 @@ impl ThreadAuthority {
             tracing::trace!("try_lock_send_attestation_action: parent block time is not set");
             return ActionLockResult::Rejected;
         };
-        let now = {
-            let start = SystemTime::now();
-            let since_the_epoch = start.duration_since(UNIX_EPOCH).expect("time should go forward");
-            TryInto::<u64>::try_into(since_the_epoch.as_millis()).unwrap()
-        };
+        let now = CLOCK.now_ms();
         if parent_block_time > now {
             tracing::warn!(
                 "try_lock_send_attestation_action: local clock and BP clock is out of sync"
@@ -560,6 +590,9 @@ impl ThreadAuthority {
 
     pub fn on_block_producer_stalled(&mut self) -> OnBlockProducerStalledResult {
         let thread_identifier = self.thread_id;
+        if self.archive_only {
+            return OnBlockProducerStalledResult::retry_later(None);
+        }
         tracing::trace!("on_block_producer_stalled: start {thread_identifier:?}");
         let Ok(last_prefinalized) = find_last_prefinalized(
             &thread_identifier,
@@ -585,6 +618,9 @@ impl ThreadAuthority {
         else {
             panic!("Somehow we got a block confirmed to be malicious yet some of the mandatory state fields missing.")
         };
+        if self.archive_only {
+            return;
+        }
         let parent = self.block_state_repository.get(&parent_block_identifier).unwrap();
         let _ = self.start_next_round(parent, block_height);
     }
@@ -636,19 +672,37 @@ impl ThreadAuthority {
             tracing::trace!("start_next_round: parent block time is not set");
             return OnBlockProducerStalledResult::retry_later(Some(block_height));
         };
-        let now = {
-            let start = SystemTime::now();
-            let since_the_epoch = start.duration_since(UNIX_EPOCH).expect("time should go forward");
-            TryInto::<u64>::try_into(since_the_epoch.as_millis()).unwrap()
-        };
+        let now = CLOCK.now_ms();
         if parent_block_time > now {
             tracing::warn!("start_next_round: local clock and BP clock is out of sync");
             return OnBlockProducerStalledResult::retry_later(Some(block_height));
         }
         let duration_from_parent_block = Duration::from_millis(now - parent_block_time);
-        let CalculateRoundResult { round: local_round, round_remaining_time } = self
+        let CalculateRoundResult { round: local_round, mut round_remaining_time } = self
             .round_buckets
             .calculate_round(duration_from_parent_block, bk_set.len().try_into().unwrap());
+        let siblings_key = SiblingsBlockHeightKey::builder()
+            .parent_block_identifier(parent_block.block_identifier().clone())
+            .height(block_height)
+            .build();
+        // Time spent in the round is known only for the first round and for rounds
+        // reached by skip votes. Other rounds are left to the round timeouts.
+        let (local_round, time_in_round) = match self.skip_votes.skipped_round(&siblings_key) {
+            Some((skipped_round, skipped_at)) if *skipped_round >= local_round => {
+                (*skipped_round, Some(skipped_at.elapsed()))
+            }
+            _ if local_round == 0 => (local_round, Some(duration_from_parent_block)),
+            _ => (local_round, None),
+        };
+        if let (Some(skip_vote_after), Some(time_in_round)) = (self.skip_vote_after, time_in_round)
+        {
+            match skip_vote_after.checked_sub(time_in_round) {
+                Some(remaining) if !remaining.is_zero() => {
+                    round_remaining_time = round_remaining_time.min(remaining);
+                }
+                _ => self.send_skip_vote(&siblings_key, local_round, &bk_set),
+            }
+        }
         if local_round == 0 {
             tracing::warn!("start_next_round: (failed) still in the round zero.");
             return OnBlockProducerStalledResult {
@@ -712,14 +766,17 @@ impl ThreadAuthority {
             current_lock_snapshot.parent_block_producer_selector_data().clone().move_index(
                 TryInto::<usize>::try_into((local_round as i64) - 1)
                     .expect("local round must be greater or equal to 1"),
-                bk_set.len(),
+                &bk_set,
+                &self.producer_selection,
             );
-        let cur_producer_node_id = cur_producer_selector.get_producer_node_id(&bk_set).unwrap();
+        let cur_producer_node_id =
+            cur_producer_selector.get_producer_node_id(&bk_set, &self.producer_selection).unwrap();
         let next_producer_selector = current_lock_snapshot
             .parent_block_producer_selector_data()
             .clone()
-            .move_index(local_round as usize, bk_set.len());
-        let next_producer_node_id = next_producer_selector.get_producer_node_id(&bk_set).unwrap();
+            .move_index(local_round as usize, &bk_set, &self.producer_selection);
+        let next_producer_node_id =
+            next_producer_selector.get_producer_node_id(&bk_set, &self.producer_selection).unwrap();
         let next_candidate_ref = current_lock_snapshot.locked_block().clone().map(|e| e.1);
         let mut has_all_attestations_locked = true;
         let (block, attestations) = match next_candidate_ref {
@@ -878,6 +935,87 @@ impl ThreadAuthority {
         }
     }
 
+    fn send_skip_vote(
+        &mut self,
+        siblings_key: &SiblingsBlockHeightKey,
+        round: BlockRound,
+        bk_set: &BlockKeeperSet,
+    ) {
+        if self.archive_only || !self.sent_skip_votes.insert((siblings_key.clone(), round)) {
+            return;
+        }
+        let vote = SkipVote::builder()
+            .parent_block(siblings_key.parent_block_identifier.clone())
+            .height(siblings_key.height)
+            .round(round)
+            .build();
+        let secrets = self.bls_keys_map.guarded(|map| map.clone());
+        let Ok(vote) = Envelope::sealed(&self.node_identifier, bk_set, &secrets, vote) else {
+            tracing::trace!("send_skip_vote: failed to sign a skip vote");
+            return;
+        };
+        tracing::trace!("send_skip_vote: skip producer of round {round} on {:?}", vote.data());
+        for signer_index in vote.clone_signature_occurrences().into_keys() {
+            self.skip_votes.add(siblings_key, round, signer_index, bk_set);
+        }
+        let _ = self
+            .network_broadcast_tx
+            .send(NetworkMessage::AuthoritySwitchProtocol(AuthoritySwitch::SkipVote(vote)));
+    }
+
+    /// Returns true if the vote has completed a quorum to skip the round producer.
+    /// In that case the caller is expected to start the next round.
+    pub fn on_skip_vote(&mut self, vote: Envelope<GoshBLS, SkipVote>) -> bool {
+        let data = vote.data();
+        let signers = vote.clone_signature_occurrences().into_keys().collect::<Vec<_>>();
+        if signers.len() != 1 {
+            tracing::trace!(
+                "on_skip_vote: vote must be signed by exactly one bk, got {} signers, skip it.",
+                signers.len()
+            );
+            return false;
+        }
+        let Ok(parent_state) = self.block_state_repository.get(data.parent_block()) else {
+            return false;
+        };
+        if parent_state.guarded(|e| !e.is_prefinalized() && !e.is_finalized()) {
+            tracing::trace!("on_skip_vote: parent is not prefinalized");
+            return false;
+        }
+        let Some(bk_set) = parent_state.guarded(|e| e.descendant_bk_set().clone()) else {
+            tracing::trace!("on_skip_vote: parent block has no descendant bk set");
+            return false;
+        };
+        match vote.verify_signatures(bk_set.get_pubkeys_by_signers()) {
+            Ok(true) => {}
+            Ok(false) => {
+                tracing::trace!("on_skip_vote: invalid signature");
+                return false;
+            }
+            Err(e) => {
+                tracing::trace!("on_skip_vote: signature verification error: {e}");
+                return false;
+            }
+        }
+        let thread_identifier = *data.height().thread_identifier();
+        if find_next_prefinalized(&parent_state, &thread_identifier, &self.block_state_repository)
+            .is_some()
+        {
+            tracing::trace!("on_skip_vote: there is a prefinalized block on this height already");
+            return false;
+        }
+        let siblings_key = SiblingsBlockHeightKey::builder()
+            .parent_block_identifier(data.parent_block().clone())
+            .height(*data.height())
+            .build();
+        let round = *data.round();
+        let is_skipped = self.skip_votes.add(&siblings_key, round, signers[0], &bk_set);
+        if is_skipped {
+            tracing::trace!("on_skip_vote: quorum to skip round {round} is reached");
+        }
+        is_skipped
+    }
+
     fn get_block(
         &self,
         block_identifier: &BlockIdentifier,
@@ -899,6 +1037,9 @@ impl ThreadAuthority {
         tracing::trace!(
             "on_next_round_incoming_request: next_round_message = {next_round_message:?}"
         );
+        if self.archive_only {
+            return OnNextRoundIncomingRequestResult::DoNothing;
+        }
         let lock = next_round_message.lock().data().clone();
         let signer_index = {
             let signers = next_round_message
@@ -1113,30 +1254,73 @@ impl ThreadAuthority {
             );
             return OnNextRoundIncomingRequestResult::DoNothing;
         }
-        let mut max_locked_block: Option<Arc<Envelope<GoshBLS, AckiNackiBlock>>> = None;
-        let mut max_locked_block_round: Option<BlockRound> = None;
+        // Attesters of every locked block. Their stake breaks ties between
+        // blocks locked in the same round.
+        let mut locked_block_attesters: HashMap<BlockIdentifier, HashSet<SignerIndex>> =
+            HashMap::new();
+        for e in collected_requests.iter() {
+            let Some(attestation) = e.locked_block_attestation() else {
+                continue;
+            };
+            if !matches!(attestation.verify_signatures(bk_set.get_pubkeys_by_signers()), Ok(true)) {
+                tracing::trace!(
+                    "on_next_round_incoming_request: skip invalid locked block attestation"
+                );
+                continue;
+            }
+            locked_block_attesters
+                .entry(attestation.data().block_id().clone())
+                .or_default()
+                .extend(attestation.clone_signature_occurrences().keys().cloned());
+        }
+        let mut locked_blocks: HashMap<BlockIdentifier, Arc<Envelope<GoshBLS, AckiNackiBlock>>> =
+            HashMap::new();
         let mut locked_none_count = 0;
         for e in collected_requests.iter() {
             if let Some(block_id) = e.lock().data().locked_block() {
+                if locked_blocks.contains_key(block_id) {
+                    continue;
+                }
                 let Some(block) = self.get_block(block_id, &unprocessed_blocks_cache) else {
                     // An unknown block id. Skip it.
                     continue;
                 };
-
-                let block_round = block.data().get_common_section().round;
-                if let Some(current_max_round) = &max_locked_block_round {
-                    if block_round > *current_max_round {
-                        max_locked_block = Some(block);
-                        max_locked_block_round = Some(block_round);
-                    }
-                } else {
-                    max_locked_block = Some(block);
-                    max_locked_block_round = Some(block_round);
-                }
+                locked_blocks.insert(block_id.clone(), block);
             } else {
                 locked_none_count += 1;
             }
         }
+        let fork_candidates = locked_blocks
+            .iter()
+            .map(|(block_id, block)| ForkCandidate {
+                block_identifier: block_id.clone(),
+                round: block.data().get_common_section().round,
+                attester_stake: locked_block_attesters
+                    .get(block_id)
+                    .map(|attesters| {
+                        attesters
+                            .iter()
+                            .filter_map(|signer_index| bk_set.get_by_signer(signer_index))
+                            .map(|data| data.stake.clone())
+                            .sum()
+                    })
+                    .unwrap_or_default(),
+            })
+            .collect();
+        let fork_resolution = resolve_fork(fork_candidates);
+        let max_locked_block = fork_resolution
+            .as_ref()
+            .and_then(|resolution| locked_blocks.remove(resolution.chosen()));
+        if let Some(resolution) = fork_resolution.filter(|e| e.candidates().len() > 1) {
+            tracing::trace!("on_next_round_incoming_request: fork resolved: {resolution:?}");
+            if let Ok(chosen_state) = self.block_state_repository.get(resolution.chosen()) {
+                chosen_state.guarded_mut(|e| {
+                    if e.fork_resolution().is_none() {
+                        let _ = e.set_fork_resolution(resolution);
+                    }
+                });
+            }
+        }
         if max_locked_block.is_none() && locked_none_count < votes_target {
             tracing::trace!(
                     "on_next_round_incoming_request: there is a quorum already. However this node has no block to share. (block identifiers only). Not enough votes to produce a new block",
@@ -1248,11 +1432,7 @@ impl ThreadAuthority {
             tracing::trace!("on_next_round_incoming_request: parent block time is not set");
             return OnNextRoundIncomingRequestResult::DoNothing;
         };
-        let now = {
-            let start = SystemTime::now();
-            let since_the_epoch = start.duration_since(UNIX_EPOCH).expect("time should go forward");
-            TryInto::<u64>::try_into(since_the_epoch.as_millis()).unwrap()
-        };
+        let now = CLOCK.now_ms();
         if parent_block_time > now {
             tracing::warn!(
                 "on_next_round_incoming_request: local clock and prev BP clock is out of sync"
@@ -1314,6 +1494,12 @@ impl ThreadAuthority {
 
     pub fn on_block_finalized(&mut self, block_height: &BlockHeight) {
         self.action_lock.drop_old_locks(block_height);
+        let is_actual = |key: &SiblingsBlockHeightKey| {
+            key.height.thread_identifier() != block_height.thread_identifier()
+                || key.height.height() >= block_height.height()
+        };
+        self.skip_votes.retain(is_actual);
+        self.sent_skip_votes.retain(|(key, _)| is_actual(key));
     }
 
     pub fn on_next_round_failed(
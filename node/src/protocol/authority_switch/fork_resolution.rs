@@ -0,0 +1,100 @@
+use std::cmp::Ordering;
+
+use derive_getters::Getters;
+use num_bigint::BigUint;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::types::BlockIdentifier;
+use crate::types::BlockRound;
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum ForkResolutionReason {
+    // There was no other block to choose from
+    Uncontested,
+    // Chosen block was locked in a higher round
+    HigherRound,
+    // Rounds are equal, chosen block has more attester stake
+    AttesterStake,
+    // Rounds and attester stakes are equal, chosen block has the lowest identifier
+    BlockIdentifierOrder,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct ForkCandidate {
+    pub block_identifier: BlockIdentifier,
+    pub round: BlockRound,
+    // Cumulative stake of block keepers that sent verified attestations for the block
+    pub attester_stake: BigUint,
+}
+
+// Rationale of choosing one of the sibling blocks locked by different block keepers.
+// It is stored in the chosen block state to make the choice auditable.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Getters)]
+pub struct ForkResolution {
+    chosen: BlockIdentifier,
+    reason: ForkResolutionReason,
+    candidates: Vec<ForkCandidate>,
+}
+
+// Note: the order must not depend on the order candidates were received in,
+// otherwise nodes could choose different blocks for the same set of locks.
+fn compare_candidates(a: &ForkCandidate, b: &ForkCandidate) -> Ordering {
+    a.round
+        .cmp(&b.round)
+        .then_with(|| a.attester_stake.cmp(&b.attester_stake))
+        .then_with(|| BlockIdentifier::compare(&b.block_identifier, &a.block_identifier))
+}
+
+pub fn resolve_fork(mut candidates: Vec<ForkCandidate>) -> Option<ForkResolution> {
+    candidates.sort_by(|a, b| compare_candidates(b, a));
+    let chosen = candidates.first()?;
+    let reason = match candidates.get(1) {
+        None => ForkResolutionReason::Uncontested,
+        Some(runner_up) if chosen.round != runner_up.round => ForkResolutionReason::HigherRound,
+        Some(runner_up) if chosen.attester_stake != runner_up.attester_stake => {
+            ForkResolutionReason::AttesterStake
+        }
+        Some(_) => ForkResolutionReason::BlockIdentifierOrder,
+    };
+    Some(ForkResolution { chosen: chosen.block_identifier.clone(), reason, candidates })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(id: u8, round: BlockRound, attester_stake: u32) -> ForkCandidate {
+        ForkCandidate {
+            block_identifier: BlockIdentifier::from([id; 32]),
+            round,
+            attester_stake: BigUint::from(attester_stake),
+        }
+    }
+
+    #[test]
+    fn test_resolve_fork_order() {
+        assert_eq!(resolve_fork(vec![]), None);
+
+        let resolution = resolve_fork(vec![candidate(1, 1, 100)]).unwrap();
+        assert_eq!(resolution.reason, ForkResolutionReason::Uncontested);
+
+        let resolution = resolve_fork(vec![candidate(1, 1, 100), candidate(2, 2, 10)]).unwrap();
+        assert_eq!(resolution.chosen, BlockIdentifier::from([2; 32]));
+        assert_eq!(resolution.reason, ForkResolutionReason::HigherRound);
+
+        let resolution = resolve_fork(vec![candidate(1, 2, 10), candidate(2, 2, 100)]).unwrap();
+        assert_eq!(resolution.chosen, BlockIdentifier::from([2; 32]));
+        assert_eq!(resolution.reason, ForkResolutionReason::AttesterStake);
+
+        // Does not depend on the order of candidates
+        for candidates in [
+            vec![candidate(3, 2, 100), candidate(2, 2, 100)],
+            vec![candidate(2, 2, 100), candidate(3, 2, 100)],
+        ] {
+            let resolution = resolve_fork(candidates).unwrap();
+            assert_eq!(resolution.chosen, BlockIdentifier::from([2; 32]));
+            assert_eq!(resolution.reason, ForkResolutionReason::BlockIdentifierOrder);
+        }
+    }
+}
@@ -1,6 +1,8 @@
 pub mod action_lock;
 pub mod chain_pulse_monitor;
 mod find_last_prefinalized;
+pub mod fork_resolution;
 pub mod network_message;
 pub mod round_time;
 pub mod routing;
+mod skip_votes;
@@ -85,6 +85,16 @@ pub struct NextRoundReject {
     proof_of_prefinalization: Envelope<GoshBLS, AttestationData>,
 }
 
+// Vote to skip a producer that has not produced a block on the height
+// for several block times. Once 50%+1 of the bk set voted for the round
+// nodes move to the next round without waiting for the round timeout.
+#[derive(Clone, Serialize, Deserialize, Getters, TypedBuilder, Debug, PartialEq, Eq)]
+pub struct SkipVote {
+    parent_block: BlockIdentifier,
+    height: BlockHeight,
+    round: BlockRound,
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub enum AuthoritySwitch {
     /// This message is send when the current Block Producer is stalled.
@@ -128,4 +138,9 @@ pub enum AuthoritySwitch {
     /// and sends whatever block it is aware of.
     /// It is possible that the node had some requests with an unknown block locked
     Failed(Envelope<GoshBLS, NextRoundFailed>),
+
+    /// This message is broadcasted when a node has not received a block
+    /// from the producer of the round in time.
+    /// Note: it is signed by a node from the descendant bk set of the parent block.
+    SkipVote(Envelope<GoshBLS, SkipVote>),
 }
@@ -11,5 +11,6 @@ pub fn route(message: &AuthoritySwitch) -> ThreadIdentifier {
         AuthoritySwitch::RejectTooOld(e) => *e,
         AuthoritySwitch::Switched(e) => *e.data().block_height().thread_identifier(),
         AuthoritySwitch::Failed(e) => *e.data().block_height().thread_identifier(),
+        AuthoritySwitch::SkipVote(e) => *e.data().height().thread_identifier(),
     }
 }
@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::time::Instant;
+
+use crate::block_keeper_system::BlockKeeperSet;
+use crate::node::SignerIndex;
+use crate::types::BlockRound;
+
+// Votes to skip the round producers collected per height (the key) and round,
+// and the rounds reached by the quorum of the votes.
+pub struct SkipVotes<Key> {
+    collected: HashMap<(Key, BlockRound), HashSet<SignerIndex>>,
+    // The lowest round allowed by skip votes and the time it was reached.
+    skipped_rounds: HashMap<Key, (BlockRound, Instant)>,
+}
+
+impl<Key> Default for SkipVotes<Key> {
+    fn default() -> Self {
+        Self { collected: HashMap::new(), skipped_rounds: HashMap::new() }
+    }
+}
+
+impl<Key: Hash + Eq + Clone> SkipVotes<Key> {
+    /// Returns true if the vote has completed a quorum of 50%+1 of the bk set
+    /// to skip the round. Duplicate votes, votes of signers outside of the
+    /// bk set and votes for the rounds already skipped are ignored.
+    pub fn add(
+        &mut self,
+        key: &Key,
+        round: BlockRound,
+        signer_index: SignerIndex,
+        bk_set: &BlockKeeperSet,
+    ) -> bool {
+        if !bk_set.contains_signer(&signer_index) {
+            return false;
+        }
+        if self.skipped_rounds.get(key).is_some_and(|(skipped, _)| *skipped > round) {
+            return false;
+        }
+        let voters = self.collected.entry((key.clone(), round)).or_default();
+        if !voters.insert(signer_index) {
+            return false;
+        }
+        let votes_target = (bk_set.len() + 1).div_ceil(2);
+        if voters.len() < votes_target {
+            return false;
+        }
+        self.skipped_rounds.insert(key.clone(), (round + 1, Instant::now()));
+        true
+    }
+
+    pub fn skipped_round(&self, key: &Key) -> Option<&(BlockRound, Instant)> {
+        self.skipped_rounds.get(key)
+    }
+
+    pub fn retain(&mut self, is_actual: impl Fn(&Key) -> bool) {
+        self.collected.retain(|(key, _), _| is_actual(key));
+        self.skipped_rounds.retain(|key, _| is_actual(key));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_keeper_system::BlockKeeperData;
+
+    fn bk_set(size: SignerIndex) -> BlockKeeperSet {
+        let mut bk_set = BlockKeeperSet::new();
+        for signer_index in 0..size {
+            bk_set.insert(signer_index, BlockKeeperData { signer_index, ..Default::default() });
+        }
+        bk_set
+    }
+
+    #[test]
+    fn test_quorum_skips_the_round() {
+        let bk_set = bk_set(4);
+        let mut votes = SkipVotes::default();
+        assert!(!votes.add(&1, 0, 0, &bk_set));
+        assert!(!votes.add(&1, 0, 1, &bk_set));
+        // Duplicate votes are not counted
+        assert!(!votes.add(&1, 0, 1, &bk_set));
+        // Votes of other heights are counted separately
+        assert!(!votes.add(&2, 0, 2, &bk_set));
+        assert_eq!(votes.skipped_round(&1), None);
+        assert!(votes.add(&1, 0, 2, &bk_set));
+        assert_eq!(votes.skipped_round(&1).map(|(round, _)| *round), Some(1));
+        // Quorum is completed once
+        assert!(!votes.add(&1, 0, 3, &bk_set));
+        assert_eq!(votes.skipped_round(&2), None);
+    }
+
+    #[test]
+    fn test_votes_outside_of_bk_set_are_ignored() {
+        let bk_set = bk_set(3);
+        let mut votes = SkipVotes::default();
+        assert!(!votes.add(&1, 0, 0, &bk_set));
+        assert!(!votes.add(&1, 0, 3, &bk_set));
+        assert!(!votes.add(&1, 0, 4, &bk_set));
+        assert_eq!(votes.skipped_round(&1), None);
+        assert!(votes.add(&1, 0, 1, &bk_set));
+    }
+
+    #[test]
+    fn test_stale_round_votes_are_ignored() {
+        let bk_set = bk_set(3);
+        let mut votes = SkipVotes::default();
+        assert!(!votes.add(&1, 2, 0, &bk_set));
+        assert!(votes.add(&1, 2, 1, &bk_set));
+        assert_eq!(votes.skipped_round(&1).map(|(round, _)| *round), Some(3));
+        // Quorum of an older round doesn't move the skipped round back
+        assert!(!votes.add(&1, 1, 0, &bk_set));
+        assert!(!votes.add(&1, 1, 1, &bk_set));
+        assert_eq!(votes.skipped_round(&1).map(|(round, _)| *round), Some(3));
+        // Round that is skipped to is voted as usual
+        assert!(!votes.add(&1, 3, 0, &bk_set));
+        assert!(votes.add(&1, 3, 2, &bk_set));
+        assert_eq!(votes.skipped_round(&1).map(|(round, _)| *round), Some(4));
+
+        votes.retain(|key| *key != 1);
+        assert_eq!(votes.skipped_round(&1), None);
+    }
+}
@@ -0,0 +1,219 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::HashMap;
+use std::time::Duration;
+use std::time::Instant;
+
+use http_server::AccountOwnership;
+use http_server::AccountOwnershipFeed;
+use http_server::AccountOwnershipFilter;
+
+use crate::helper::SHUTDOWN_FLAG;
+use crate::repository::dapp_id_table::DAppIdTable;
+use crate::repository::optimistic_state::OptimisticStateImpl;
+use crate::repository::repository_impl::RepositoryImpl;
+use crate::repository::Repository;
+use crate::types::AccountAddress;
+use crate::types::AccountRouting;
+use crate::types::ThreadIdentifier;
+use crate::types::ThreadsTable;
+use crate::utilities::FixedSizeHashMap;
+
+// Number of the accounts with memoized owners per state
+const OWNERS_CACHE_SIZE: usize = 10_000;
+const OWNERSHIP_FILTER_FALSE_POSITIVE_RATE: f64 = 0.01;
+const OWNERSHIP_PUBLISH_INTERVAL: Duration = Duration::from_secs(60);
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone)]
+struct OwnersMemo {
+    threads_table: ThreadsTable,
+    dapp_id_table: DAppIdTable,
+    owners: FixedSizeHashMap<AccountAddress, ThreadIdentifier>,
+}
+
+/// Memoized owner threads of the accounts. The owners are resolved with the
+/// dapp id table and the threads table, the memo is dropped once any of the
+/// tables changes.
+#[derive(Clone, Default)]
+pub struct AccountOwnershipIndex {
+    // Allocated on the first lookup, most of the states are never queried
+    memo: Option<OwnersMemo>,
+}
+
+impl AccountOwnershipIndex {
+    pub fn thread_of(
+        &mut self,
+        account: &AccountAddress,
+        threads_table: &ThreadsTable,
+        dapp_id_table: &DAppIdTable,
+    ) -> ThreadIdentifier {
+        let memo = match &mut self.memo {
+            Some(memo)
+                if memo.dapp_id_table.ptr_eq(dapp_id_table)
+                    && memo.threads_table == *threads_table =>
+            {
+                memo
+            }
+            memo => memo.insert(OwnersMemo {
+                threads_table: threads_table.clone(),
+                dapp_id_table: dapp_id_table.clone(),
+                owners: FixedSizeHashMap::new(OWNERS_CACHE_SIZE),
+            }),
+        };
+        if let Some(thread_id) = memo.owners.get(account) {
+            return *thread_id;
+        }
+        let thread_id = threads_table.find_match(&account_routing(account, dapp_id_table));
+        memo.owners.insert(account.clone(), thread_id);
+        thread_id
+    }
+}
+
+fn account_routing(account: &AccountAddress, dapp_id_table: &DAppIdTable) -> AccountRouting {
+    let dapp_id = dapp_id_table.get(account).and_then(|(dapp_id, _)| dapp_id.clone());
+    AccountRouting::from((dapp_id, account.clone()))
+}
+
+/// Builds the filter of the dapp id table accounts owned by the state thread.
+pub fn build_account_ownership(state: &OptimisticStateImpl) -> AccountOwnership {
+    let owned: Vec<_> = state
+        .dapp_id_table
+        .iter()
+        .map(|(account, _)| account)
+        .filter(|account| {
+            state
+                .threads_table
+                .is_match(&account_routing(account, &state.dapp_id_table), state.thread_id)
+        })
+        .collect();
+    let mut filter = AccountOwnershipFilter::new(owned.len(), OWNERSHIP_FILTER_FALSE_POSITIVE_RATE);
+    for account in &owned {
+        filter.insert(account.0.as_array());
+    }
+    AccountOwnership {
+        thread_id: format!("{:x}", state.thread_id),
+        block_seq_no: state.block_seq_no.into(),
+        accounts: owned.len(),
+        filter,
+    }
+}
+
+/// Publishes the account ownership of the last finalized states of the
+/// threads. Filters are rebuilt only for the states with changed tables.
+pub fn start_account_ownership_service(
+    repository: RepositoryImpl,
+    feed: AccountOwnershipFeed,
+) -> anyhow::Result<()> {
+    let mut published =
+        HashMap::<ThreadIdentifier, (ThreadsTable, DAppIdTable, AccountOwnership)>::new();
+    let mut last_check: Option<Instant> = None;
+    loop {
+        if SHUTDOWN_FLAG.get() == Some(&true) {
+            return Ok(());
+        }
+        if last_check.is_some_and(|last_check| last_check.elapsed() < OWNERSHIP_PUBLISH_INTERVAL) {
+            std::thread::sleep(SHUTDOWN_POLL_INTERVAL);
+            continue;
+        }
+        last_check = Some(Instant::now());
+
+        for thread_id in repository.threads_with_saved_states() {
+            let Some(state) = repository.last_finalized_optimistic_state(&thread_id) else {
+                continue;
+            };
+            let mut ownership = match published.get(&thread_id) {
+                Some((threads, dapps, ownership))
+                    if dapps.ptr_eq(&state.dapp_id_table) && *threads == state.threads_table =>
+                {
+                    ownership.clone()
+                }
+                _ => build_account_ownership(&state),
+            };
+            ownership.block_seq_no = state.block_seq_no.into();
+            tracing::trace!(
+                "Publish account ownership of {thread_id:?}: {} accounts",
+                ownership.accounts
+            );
+            feed.publish(ownership.clone());
+            published.insert(
+                thread_id,
+                (state.threads_table.clone(), state.dapp_id_table.clone(), ownership),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitmask::mask::Bitmask;
+    use crate::repository::dapp_id_table::DAppIdTableChangeSet;
+    use crate::types::account_address::direct_bit_access_operations::DirectBitAccess;
+    use crate::types::BlockEndLT;
+    use crate::types::BlockIdentifier;
+    use crate::types::DAppIdentifier;
+
+    #[test]
+    fn test_account_ownership() {
+        // Routings with the highest dapp id bit set go to the other thread
+        let other_thread = ThreadIdentifier::new(&BlockIdentifier::default(), 1);
+        let mut routing_bits = AccountRouting::default();
+        routing_bits.set_bit_value(0, true);
+        let mut threads_table = ThreadsTable::new();
+        threads_table
+            .insert_above(
+                0,
+                Bitmask::<AccountRouting>::builder()
+                    .meaningful_mask_bits(routing_bits.clone())
+                    .mask_bits(routing_bits)
+                    .build(),
+                other_thread,
+            )
+            .unwrap();
+
+        let account = |first_byte: u8| {
+            let mut bytes = [1; 32];
+            bytes[0] = first_byte;
+            AccountAddress(bytes.into())
+        };
+        let own = account(0x01);
+        let other = account(0x81);
+        let own_in_other_dapp = account(0x02);
+        let mut change_set = DAppIdTableChangeSet::default();
+        change_set.insert(
+            own_in_other_dapp.clone(),
+            Some(DAppIdentifier(other.clone())),
+            BlockEndLT::default(),
+        );
+        change_set.insert(own.clone(), None, BlockEndLT::default());
+        let dapp_id_table = DAppIdTable::default().apply_change_set(&change_set);
+
+        let mut index = AccountOwnershipIndex::default();
+        assert_eq!(
+            index.thread_of(&own, &threads_table, &dapp_id_table),
+            ThreadIdentifier::default()
+        );
+        assert_eq!(index.thread_of(&other, &threads_table, &dapp_id_table), other_thread);
+        assert_eq!(
+            index.thread_of(&own_in_other_dapp, &threads_table, &dapp_id_table),
+            other_thread
+        );
+        // The memo is dropped for the other table
+        let dapp_id_table = DAppIdTable::default();
+        assert_eq!(
+            index.thread_of(&own_in_other_dapp, &threads_table, &dapp_id_table),
+            ThreadIdentifier::default()
+        );
+
+        let state = OptimisticStateImpl {
+            threads_table,
+            dapp_id_table: DAppIdTable::default().apply_change_set(&change_set),
+            ..Default::default()
+        };
+        let ownership = build_account_ownership(&state);
+        assert_eq!(ownership.accounts, 1);
+        assert!(ownership.filter.may_contain(own.0.as_array()));
+    }
+}
@@ -1,31 +1,135 @@
 use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::io::Write;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::Mutex;
 
+use tvm_block::Account;
+use tvm_block::Deserializable;
 use tvm_block::ShardAccounts;
+use tvm_types::Cell;
+use tvm_types::UInt256;
 
+use crate::config::StateSavePolicy;
 use crate::helper::get_temp_file_path;
 use crate::types::AccountAddress;
 use crate::types::ThreadIdentifier;
 
+// Number of deserialized accounts kept between blocks
+const HOT_ACCOUNTS_CACHE_SIZE: usize = 1000;
+
+// Touch counters are halved each time the cache is touched this many times
+// its size, so accounts that were hot long ago are evicted in the end
+const HOT_ACCOUNTS_DECAY_PERIOD: usize = 10;
+
+#[derive(Debug)]
+struct HotAccount {
+    // Account object is valid while the account cell is unchanged
+    cell_hash: UInt256,
+    account: Arc<Account>,
+    touches: u64,
+}
+
+/// LFU cache of the deserialized accounts with decaying touch counters.
+#[derive(Debug)]
+struct HotAccounts {
+    capacity: usize,
+    entries: HashMap<AccountAddress, HotAccount>,
+    // Cached accounts ordered by touches, the first one is evicted
+    by_touches: BTreeSet<(u64, AccountAddress)>,
+    touches_since_decay: usize,
+}
+
+impl HotAccounts {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            by_touches: BTreeSet::new(),
+            touches_since_decay: 0,
+        }
+    }
+
+    /// Counts the touch and returns the cached account if its cell is
+    /// unchanged.
+    fn touch(&mut self, account_id: &AccountAddress, cell_hash: &UInt256) -> Option<Arc<Account>> {
+        self.touches_since_decay += 1;
+        if self.touches_since_decay >= self.capacity * HOT_ACCOUNTS_DECAY_PERIOD {
+            self.decay();
+        }
+        let hot = self.entries.get_mut(account_id)?;
+        self.by_touches.remove(&(hot.touches, account_id.clone()));
+        hot.touches += 1;
+        self.by_touches.insert((hot.touches, account_id.clone()));
+        (&hot.cell_hash == cell_hash).then(|| hot.account.clone())
+    }
+
+    fn insert(&mut self, account_id: &AccountAddress, cell_hash: UInt256, account: Arc<Account>) {
+        let touches = match self.entries.remove(account_id) {
+            Some(hot) => {
+                self.by_touches.remove(&(hot.touches, account_id.clone()));
+                hot.touches
+            }
+            None => {
+                if self.entries.len() >= self.capacity {
+                    if let Some((_, coldest)) = self.by_touches.pop_first() {
+                        self.entries.remove(&coldest);
+                    }
+                }
+                1
+            }
+        };
+        self.by_touches.insert((touches, account_id.clone()));
+        self.entries.insert(account_id.clone(), HotAccount { cell_hash, account, touches });
+    }
+
+    fn remove(&mut self, account_id: &AccountAddress) {
+        if let Some(hot) = self.entries.remove(account_id) {
+            self.by_touches.remove(&(hot.touches, account_id.clone()));
+        }
+    }
+
+    fn decay(&mut self) {
+        self.touches_since_decay = 0;
+        self.by_touches.clear();
+        for (account_id, hot) in self.entries.iter_mut() {
+            hot.touches /= 2;
+            self.by_touches.insert((hot.touches, account_id.clone()));
+        }
+    }
+}
+
+impl Default for HotAccounts {
+    fn default() -> Self {
+        Self::new(HOT_ACCOUNTS_CACHE_SIZE)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AccountsRepository {
     data_dir: PathBuf,
     unload_after: Option<u32>,
-    store_after: u32,
+    // Cached accounts are stored with the next saved state of the thread
+    state_save_policy: StateSavePolicy,
     deleted_accounts: Arc<Mutex<HashMap<ThreadIdentifier, BTreeMap<u64, Vec<AccountAddress>>>>>,
+    hot_accounts: Arc<Mutex<HotAccounts>>,
 }
 
 impl AccountsRepository {
-    pub fn new(data_dir: PathBuf, unload_after: Option<u32>, store_after: u32) -> Self {
+    pub fn new(
+        data_dir: PathBuf,
+        unload_after: Option<u32>,
+        state_save_policy: StateSavePolicy,
+    ) -> Self {
         Self {
             data_dir: data_dir.join("accounts"),
             unload_after,
-            store_after,
+            state_save_policy,
             deleted_accounts: Default::default(),
+            hot_accounts: Default::default(),
         }
     }
 
@@ -169,11 +273,99 @@ impl AccountsRepository {
         thread_deleted.insert(lt, accounts);
     }
 
+    /// Returns the deserialized account of the cell. Objects of frequently
+    /// touched accounts are kept between production iterations, so the same
+    /// wallets are not deserialized in every block.
+    pub fn hot_account(
+        &self,
+        account_id: &AccountAddress,
+        acc_root: &Cell,
+    ) -> anyhow::Result<Arc<Account>> {
+        let cell_hash = acc_root.repr_hash();
+        if let Some(account) = self.hot_accounts.lock().unwrap().touch(account_id, &cell_hash) {
+            return Ok(account);
+        }
+        // Deserialize without the lock: accounts are executed in parallel
+        let account = Arc::new(
+            Account::construct_from_cell(acc_root.clone())
+                .map_err(|e| anyhow::format_err!("Failed to construct account: {e}"))?,
+        );
+        self.hot_accounts.lock().unwrap().insert(account_id, cell_hash, account.clone());
+        Ok(account)
+    }
+
+    /// Drops cached objects of the accounts changed by an applied block.
+    pub fn invalidate_hot_accounts(&self, accounts: &HashSet<AccountAddress>) {
+        let mut hot_accounts = self.hot_accounts.lock().unwrap();
+        for account_id in accounts {
+            hot_accounts.remove(account_id);
+        }
+    }
+
     pub fn get_unload_after(&self) -> Option<u32> {
         self.unload_after
     }
 
-    pub fn get_store_after(&self) -> u32 {
-        self.store_after
+    pub fn get_store_after(&self, thread_id: &ThreadIdentifier) -> u32 {
+        self.state_save_policy.for_thread(thread_id).save_state_frequency
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tvm_block::Serializable;
+
+    use super::*;
+
+    #[test]
+    fn test_hot_account_is_reused_while_cell_is_unchanged() -> anyhow::Result<()> {
+        let repository =
+            AccountsRepository::new(PathBuf::from("/tmp"), None, StateSavePolicy::default());
+        let account_id = AccountAddress(UInt256::default());
+        let acc_root = Account::default().serialize().map_err(|e| anyhow::format_err!("{e}"))?;
+
+        let first = repository.hot_account(&account_id, &acc_root)?;
+        let second = repository.hot_account(&account_id, &acc_root)?;
+        assert!(Arc::ptr_eq(&first, &second));
+
+        repository.invalidate_hot_accounts(&HashSet::from([account_id.clone()]));
+        let third = repository.hot_account(&account_id, &acc_root)?;
+        assert!(!Arc::ptr_eq(&first, &third));
+        Ok(())
+    }
+
+    #[test]
+    fn test_hot_accounts_evict_decayed_accounts() {
+        let account_id = |n: u8| AccountAddress(UInt256::from([n; 32]));
+        let cell_hash = UInt256::default();
+        let mut hot_accounts = HotAccounts::new(2);
+        for n in 0..2 {
+            hot_accounts.insert(&account_id(n), cell_hash.clone(), Arc::new(Account::default()));
+        }
+        // The second account was touched more, the first one is evicted
+        for _ in 0..5 {
+            assert!(hot_accounts.touch(&account_id(1), &cell_hash).is_some());
+        }
+        hot_accounts.insert(&account_id(2), cell_hash.clone(), Arc::new(Account::default()));
+        assert!(hot_accounts.touch(&account_id(0), &cell_hash).is_none());
+        assert!(hot_accounts.entries.contains_key(&account_id(1)));
+
+        // The first account was touched more in total, but the touches of the
+        // second one are recent, so the first one is evicted after the decay
+        let mut hot_accounts = HotAccounts::new(2);
+        for n in 1..3 {
+            hot_accounts.insert(&account_id(n), cell_hash.clone(), Arc::new(Account::default()));
+        }
+        for _ in 0..15 {
+            hot_accounts.touch(&account_id(1), &cell_hash);
+        }
+        for _ in 0..14 {
+            hot_accounts.touch(&account_id(2), &cell_hash);
+        }
+        assert!(hot_accounts.entries[&account_id(1)].touches < 15);
+        hot_accounts.insert(&account_id(3), cell_hash.clone(), Arc::new(Account::default()));
+        assert!(hot_accounts.entries.contains_key(&account_id(2)));
+        assert!(!hot_accounts.entries.contains_key(&account_id(1)));
+        assert_eq!(hot_accounts.by_touches.len(), hot_accounts.entries.len());
     }
 }
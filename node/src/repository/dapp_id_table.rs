@@ -62,6 +62,18 @@ impl DAppIdTable {
         self.inner.get(address)
     }
 
+    pub fn iter(
+        &self,
+    ) -> impl Iterator<Item = (&AccountAddress, &(Option<DAppIdentifier>, BlockEndLT))> {
+        self.inner.iter()
+    }
+
+    /// Tables are immutable once built, so the same allocation means the same
+    /// content.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+
     pub fn apply_change_set(self, change_set: &DAppIdTableChangeSet) -> Self {
         if !change_set.is_empty() {
             let mut table = Arc::unwrap_or_clone(self.inner);
@@ -0,0 +1,310 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::HashMap;
+
+use tempfile::TempDir;
+
+use super::repository_impl::RepositoryImpl;
+use crate::node::block_state::repository::BlockState;
+use crate::node::block_state::repository::BlockStateRepository;
+use crate::node::block_state::state::MAX_STATE_ANCESTORS;
+use crate::node::block_state::tools::connect;
+use crate::types::next_seq_no;
+use crate::types::BlockHeight;
+use crate::types::BlockIdentifier;
+use crate::types::BlockRound;
+use crate::types::BlockSeqNo;
+use crate::types::ThreadIdentifier;
+use crate::utilities::guarded::Guarded;
+use crate::utilities::guarded::GuardedMut;
+
+/// Repository with a scripted chain of block states, for testing services
+/// against forks, thread splits and a finalized prefix without a producer.
+///
+/// Each line of the script is a chain of blocks linked with `->`. The first
+/// block of the script is the zerostate, the first block of any other line
+/// must be declared before. Suffixes of a block name:
+/// - `*` the block is finalized, its parent must be finalized too;
+/// - `:R` the block round;
+/// - `@N` the block spawns thread `N` from its parent.
+///
+/// ```text
+/// zero -> a* -> b* -> c -> d
+/// b -> e:2 -> f
+/// c -> t@1 -> u
+/// ```
+pub struct RepositoryFixture {
+    repository: RepositoryImpl,
+    block_state_repository: BlockStateRepository,
+    blocks: HashMap<String, FixtureBlock>,
+    // Block states are saved here when the last reference is dropped
+    _data_dir: TempDir,
+}
+
+struct FixtureBlock {
+    block_identifier: BlockIdentifier,
+    parent: Option<String>,
+    thread_identifier: ThreadIdentifier,
+    block_seq_no: BlockSeqNo,
+    finalized: bool,
+}
+
+struct BlockToken<'a> {
+    name: &'a str,
+    finalized: bool,
+    round: BlockRound,
+    spawned_thread: Option<u16>,
+}
+
+impl RepositoryFixture {
+    pub fn from_script(script: &str) -> anyhow::Result<Self> {
+        let data_dir = tempfile::tempdir()?;
+        let block_state_repository = BlockStateRepository::test(data_dir.path().to_owned());
+        let mut blocks: HashMap<String, FixtureBlock> = HashMap::new();
+        for line in script.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let mut parent: Option<String> = None;
+            for token in line.split("->").map(parse_block_token) {
+                let token = token?;
+                let parent_name = parent.replace(token.name.to_string());
+                if parent_name.is_none() && !blocks.is_empty() {
+                    anyhow::ensure!(
+                        blocks.contains_key(token.name),
+                        "Chain starts with an undeclared block {}",
+                        token.name
+                    );
+                    continue;
+                }
+                anyhow::ensure!(!blocks.contains_key(token.name), "Duplicate block {}", token.name);
+                let block = add_block(
+                    &block_state_repository,
+                    &blocks,
+                    &token,
+                    parent_name.as_deref(),
+                    blocks.len() as u32,
+                )?;
+                blocks.insert(token.name.to_string(), block);
+            }
+        }
+        anyhow::ensure!(!blocks.is_empty(), "Script has no blocks");
+
+        let mut last_finalized_blocks: HashMap<ThreadIdentifier, (BlockIdentifier, BlockSeqNo)> =
+            HashMap::new();
+        for block in blocks.values().filter(|block| block.finalized) {
+            let last_finalized = last_finalized_blocks
+                .entry(block.thread_identifier)
+                .or_insert((block.block_identifier.clone(), block.block_seq_no));
+            if last_finalized.1 < block.block_seq_no {
+                *last_finalized = (block.block_identifier.clone(), block.block_seq_no);
+            }
+        }
+        let repository = RepositoryImpl::stub_with_threads(
+            last_finalized_blocks,
+            block_state_repository.clone(),
+        );
+        Ok(Self { repository, block_state_repository, blocks, _data_dir: data_dir })
+    }
+
+    pub fn repository(&self) -> &RepositoryImpl {
+        &self.repository
+    }
+
+    pub fn repository_mut(&mut self) -> &mut RepositoryImpl {
+        &mut self.repository
+    }
+
+    pub fn block_state_repository(&self) -> &BlockStateRepository {
+        &self.block_state_repository
+    }
+
+    pub fn block_identifier(&self, name: &str) -> BlockIdentifier {
+        self.block(name).block_identifier.clone()
+    }
+
+    pub fn block_state(&self, name: &str) -> BlockState {
+        self.block_state_repository.get(&self.block_identifier(name)).unwrap()
+    }
+
+    pub fn thread_identifier(&self, name: &str) -> ThreadIdentifier {
+        self.block(name).thread_identifier
+    }
+
+    /// Children of the block in the declaration order.
+    pub fn children(&self, name: &str) -> Vec<BlockIdentifier> {
+        let mut children: Vec<BlockIdentifier> = self
+            .blocks
+            .values()
+            .filter(|block| block.parent.as_deref() == Some(name))
+            .map(|block| block.block_identifier.clone())
+            .collect();
+        children.sort_by(BlockIdentifier::compare);
+        children
+    }
+
+    fn block(&self, name: &str) -> &FixtureBlock {
+        self.blocks.get(name).unwrap_or_else(|| panic!("Block {name} is not in the script"))
+    }
+}
+
+fn parse_block_token(token: &str) -> anyhow::Result<BlockToken<'_>> {
+    let token = token.trim();
+    let end = token.find(['*', ':', '@']).unwrap_or(token.len());
+    let (name, mut suffixes) = token.split_at(end);
+    anyhow::ensure!(!name.is_empty(), "Block name is missing in {token:?}");
+    let mut block = BlockToken { name, finalized: false, round: 0, spawned_thread: None };
+    while let Some(kind) = suffixes.chars().next() {
+        let rest = &suffixes[1..];
+        let value_end = rest.find(['*', ':', '@']).unwrap_or(rest.len());
+        let (value, tail) = rest.split_at(value_end);
+        match kind {
+            '*' if value.is_empty() => block.finalized = true,
+            ':' => block.round = value.parse()?,
+            '@' => block.spawned_thread = Some(value.parse()?),
+            _ => anyhow::bail!("Invalid block suffix in {token:?}"),
+        }
+        suffixes = tail;
+    }
+    Ok(block)
+}
+
+fn add_block(
+    block_state_repository: &BlockStateRepository,
+    blocks: &HashMap<String, FixtureBlock>,
+    token: &BlockToken,
+    parent_name: Option<&str>,
+    index: u32,
+) -> anyhow::Result<FixtureBlock> {
+    // The zerostate gets the default identifier as in the node
+    let mut id = [0u8; 32];
+    id[28..].copy_from_slice(&index.to_be_bytes());
+    let block_identifier = BlockIdentifier::from(id);
+    let state = block_state_repository.get(&block_identifier)?;
+
+    let Some(parent_name) = parent_name else {
+        anyhow::ensure!(token.spawned_thread.is_none(), "Zerostate can't spawn a thread");
+        let thread_identifier = ThreadIdentifier::default();
+        state.guarded_mut(|e| -> anyhow::Result<()> {
+            e.set_thread_identifier(thread_identifier)?;
+            e.set_block_seq_no(BlockSeqNo::default())?;
+            e.set_block_height(
+                BlockHeight::builder().thread_identifier(thread_identifier).height(0).build(),
+            )?;
+            e.set_block_round(token.round)?;
+            e.set_ancestors(vec![])?;
+            e.set_stored_zero_state()?;
+            e.set_finalized()
+        })?;
+        return Ok(FixtureBlock {
+            block_identifier,
+            parent: None,
+            thread_identifier,
+            block_seq_no: BlockSeqNo::default(),
+            finalized: true,
+        });
+    };
+
+    let parent_block = &blocks[parent_name];
+    anyhow::ensure!(
+        !token.finalized || parent_block.finalized,
+        "Finalized block {} has a non finalized parent {parent_name}",
+        token.name
+    );
+    let parent = block_state_repository.get(&parent_block.block_identifier)?;
+    let (parent_height, parent_ancestors) = parent.guarded(|e| {
+        ((*e.block_height()).expect("Fixture block has height"), e.ancestors().clone())
+    });
+    let thread_identifier = match token.spawned_thread {
+        Some(thread) => ThreadIdentifier::new(&parent_block.block_identifier, thread),
+        None => parent_block.thread_identifier,
+    };
+    let block_seq_no = next_seq_no(parent_block.block_seq_no);
+    let mut ancestors = vec![parent_block.block_identifier.clone()];
+    ancestors.extend(parent_ancestors.unwrap_or_default());
+    ancestors.truncate(MAX_STATE_ANCESTORS.get());
+    state.guarded_mut(|e| -> anyhow::Result<()> {
+        e.set_thread_identifier(thread_identifier)?;
+        e.set_block_seq_no(block_seq_no)?;
+        e.set_block_height(parent_height.next(&thread_identifier))?;
+        e.set_block_round(token.round)?;
+        e.set_ancestors(ancestors)
+    })?;
+    connect!(parent = parent, child = state, block_state_repository);
+    if token.finalized {
+        state.guarded_mut(|e| e.set_finalized())?;
+    }
+    Ok(FixtureBlock {
+        block_identifier,
+        parent: Some(parent_name.to_string()),
+        thread_identifier,
+        block_seq_no,
+        finalized: token.finalized,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigUint;
+
+    use super::*;
+    use crate::node::block_state::unfinalized_ancestor_blocks::UnfinalizedAncestorBlocks;
+    use crate::protocol::authority_switch::fork_resolution::resolve_fork;
+    use crate::protocol::authority_switch::fork_resolution::ForkCandidate;
+    use crate::protocol::authority_switch::fork_resolution::ForkResolutionReason;
+    use crate::repository::Repository;
+
+    const SCRIPT: &str = "
+        zero -> a* -> b* -> c -> d
+        b -> e:2 -> f
+        c -> t@1 -> u
+    ";
+
+    #[test]
+    fn test_fixture_chain() {
+        let fixture = RepositoryFixture::from_script(SCRIPT).unwrap();
+        let zero_thread = ThreadIdentifier::default();
+        assert_eq!(fixture.block_identifier("zero"), BlockIdentifier::default());
+        assert_eq!(
+            fixture.repository().select_thread_last_finalized_block(&zero_thread).unwrap(),
+            Some((fixture.block_identifier("b"), BlockSeqNo::from(2))),
+        );
+
+        let spawned_thread = ThreadIdentifier::new(&fixture.block_identifier("c"), 1);
+        assert_eq!(fixture.thread_identifier("u"), spawned_thread);
+        let height = fixture.block_state("u").guarded(|e| (*e.block_height()).unwrap());
+        assert_eq!(
+            height,
+            BlockHeight::builder().thread_identifier(spawned_thread).height(1).build()
+        );
+
+        let tail = fixture.block_state("f");
+        let chain = fixture
+            .block_state_repository()
+            .select_unfinalized_ancestor_blocks(&tail, BlockSeqNo::default())
+            .unwrap();
+        let chain: Vec<_> = chain.iter().map(|e| e.block_identifier().clone()).collect();
+        assert_eq!(chain, vec![fixture.block_identifier("e"), fixture.block_identifier("f")]);
+
+        assert!(RepositoryFixture::from_script("zero -> a -> b*").is_err());
+        assert!(RepositoryFixture::from_script("zero -> a\nx -> b").is_err());
+    }
+
+    #[test]
+    fn test_fixture_fork_resolution() {
+        let fixture = RepositoryFixture::from_script(SCRIPT).unwrap();
+        let candidates = fixture
+            .children("b")
+            .into_iter()
+            .map(|block_identifier| {
+                let state = fixture.block_state_repository().get(&block_identifier).unwrap();
+                ForkCandidate {
+                    round: state.guarded(|e| (*e.block_round()).unwrap()),
+                    block_identifier,
+                    attester_stake: BigUint::from(1u32),
+                }
+            })
+            .collect();
+        let resolution = resolve_fork(candidates).unwrap();
+        assert_eq!(resolution.chosen(), &fixture.block_identifier("e"));
+        assert_eq!(resolution.reason(), &ForkResolutionReason::HigherRound);
+    }
+}
@@ -0,0 +1,198 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use http_server::CorruptedEntry;
+use http_server::IntegrityAudit;
+use http_server::IntegrityAuditSummary;
+use tvm_block::BlkPrevInfo;
+use tvm_types::UInt256;
+
+use crate::bls::envelope::BLSSignedEnvelope;
+use crate::helper::SHUTDOWN_FLAG;
+use crate::repository::optimistic_state::OptimisticStateImpl;
+use crate::repository::repository_impl::RepositoryImpl;
+use crate::repository::Repository;
+use crate::types::BlockIdentifier;
+
+const OPTIMISTIC_STATE_KIND: &str = "optimistic_state";
+const BLOCK_KIND: &str = "block";
+// Period of checking for on-demand audit requests
+const REQUEST_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+impl RepositoryImpl {
+    /// Re-reads stored optimistic states and block envelopes and checks that
+    /// their content still hashes to the identifiers they were saved under.
+    pub fn audit_integrity(&self) -> IntegrityAuditSummary {
+        let mut summary = IntegrityAuditSummary { started_at: now_ms(), ..Default::default() };
+
+        let states_dir = self.get_optimistic_state_dir_path();
+        for (path, block_id) in list_stored_entries(&states_dir) {
+            if is_shutting_down() {
+                return summary;
+            }
+            summary.optimistic_states_checked += 1;
+            if let Err(reason) = self.check_optimistic_state(&path, &block_id) {
+                report(&mut summary, OPTIMISTIC_STATE_KIND, &path, reason);
+            }
+        }
+
+        let blocks_dir = self.get_blocks_dir_path();
+        for (path, block_id) in list_stored_entries(&blocks_dir) {
+            if is_shutting_down() {
+                return summary;
+            }
+            summary.blocks_checked += 1;
+            if let Err(reason) = check_block(&blocks_dir, &block_id) {
+                report(&mut summary, BLOCK_KIND, &path, reason);
+            }
+        }
+
+        summary.finished_at = now_ms();
+        summary
+    }
+
+    fn check_optimistic_state(
+        &self,
+        path: &Path,
+        block_id: &BlockIdentifier,
+    ) -> Result<(), String> {
+        let state = OptimisticStateImpl::load_from_file(path)
+            .map_err(|e| format!("Failed to load state: {e}"))?;
+        self.validate_optimistic_state(&state, block_id)
+    }
+
+    /// Checks that the state was built for the block: its identifiers refer to
+    /// the block and its root hash matches the block state update (if the
+    /// block is available).
+    pub(crate) fn validate_optimistic_state(
+        &self,
+        state: &OptimisticStateImpl,
+        block_id: &BlockIdentifier,
+    ) -> Result<(), String> {
+        if &state.block_id != block_id {
+            return Err(format!("State belongs to block {:?}", state.block_id));
+        }
+        if let BlkPrevInfo::Block { prev } = &*state.block_info {
+            if BlockIdentifier::from(prev.root_hash.clone()) != *block_id {
+                return Err(format!(
+                    "Block info refers to block {}",
+                    prev.root_hash.to_hex_string()
+                ));
+            }
+        }
+        // Cropped and split states are modified after the block was applied,
+        // so their root hash can't be compared with the block state update.
+        if self.is_split_state() || state.cropped.is_some() {
+            return Ok(());
+        }
+        let Ok(block) = self.get_block_from_repo_or_archive(block_id, &state.thread_id) else {
+            return Ok(());
+        };
+        let expected_hash = block
+            .data()
+            .tvm_block()
+            .read_state_update()
+            .map_err(|e| format!("Failed to read block state update: {e}"))?
+            .new_hash;
+        let actual_hash: UInt256 = state.shard_state.into_cell().repr_hash();
+        if actual_hash != expected_hash {
+            return Err(format!(
+                "Shard state hash mismatch: expected {}, actual {}",
+                expected_hash.to_hex_string(),
+                actual_hash.to_hex_string()
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn check_block(blocks_dir: &Path, block_id: &BlockIdentifier) -> Result<(), String> {
+    let block = RepositoryImpl::load_block(blocks_dir, block_id)
+        .map_err(|e| format!("Failed to load block: {e}"))?
+        .ok_or_else(|| "Block file disappeared".to_string())?;
+    let identifier = block.data().identifier();
+    if &identifier != block_id {
+        return Err(format!("Block data hashes to {identifier:?}"));
+    }
+    match block.data().check_hash() {
+        Ok(true) => Ok(()),
+        Ok(false) => Err("Envelope hash mismatch".to_string()),
+        Err(e) => Err(format!("Failed to calculate envelope hash: {e}")),
+    }
+}
+
+// Temporary files left by interrupted writes are skipped, they are not
+// referenced by identifiers.
+pub(super) fn list_stored_entries(dir: &Path) -> Vec<(std::path::PathBuf, BlockIdentifier)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let block_id =
+                BlockIdentifier::from_str(entry.file_name().to_str().unwrap_or_default()).ok()?;
+            Some((entry.path(), block_id))
+        })
+        .collect()
+}
+
+fn report(summary: &mut IntegrityAuditSummary, kind: &str, path: &Path, reason: String) {
+    tracing::error!("Integrity audit: corrupted {kind} {}: {reason}", path.display());
+    summary.corrupted.push(CorruptedEntry {
+        kind: kind.to_string(),
+        path: path.display().to_string(),
+        reason,
+    });
+}
+
+fn is_shutting_down() -> bool {
+    SHUTDOWN_FLAG.get() == Some(&true)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or_default()
+}
+
+/// Runs the audit every `interval` (if set) and whenever it was requested
+/// through the web server.
+pub fn start_integrity_audit_service(
+    repository: RepositoryImpl,
+    audit: IntegrityAudit,
+    interval: Option<Duration>,
+) -> anyhow::Result<()> {
+    let mut last_run = Instant::now();
+    loop {
+        if is_shutting_down() {
+            return Ok(());
+        }
+        std::thread::sleep(REQUEST_POLL_INTERVAL);
+        let is_scheduled = interval.is_some_and(|interval| last_run.elapsed() >= interval);
+        if !audit.take_request() && !is_scheduled {
+            continue;
+        }
+        audit.set_in_progress(true);
+        let summary = repository.audit_integrity();
+        if is_shutting_down() {
+            // The audit was interrupted, the summary is incomplete
+            return Ok(());
+        }
+        tracing::info!(
+            "Integrity audit finished: states: {}, blocks: {}, corrupted: {}",
+            summary.optimistic_states_checked,
+            summary.blocks_checked,
+            summary.corrupted.len()
+        );
+        audit.publish(summary);
+        audit.set_in_progress(false);
+        last_run = Instant::now();
+    }
+}
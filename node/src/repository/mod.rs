@@ -20,14 +20,18 @@ use crate::types::AckiNackiBlock;
 use crate::types::BlockIdentifier;
 use crate::types::BlockSeqNo;
 use crate::types::ThreadIdentifier;
+use crate::utilities::ErrorCode;
 
+pub mod account_ownership;
 pub mod accounts;
 mod cross_thread_ref_data;
 // pub mod thread_state;
 pub mod cross_thread_ref_repository;
 pub mod optimistic_shard_state;
 pub mod optimistic_state;
+pub mod read_service;
 pub mod repository_impl;
+pub mod snapshot_import;
 mod tvm_cell_serde;
 pub use cross_thread_ref_data::CrossThreadRefData;
 pub use cross_thread_ref_repository::CrossThreadRefDataRead;
@@ -41,11 +45,22 @@ use crate::repository::repository_impl::RepositoryImpl;
 use crate::repository::repository_impl::RepositoryMetadata;
 
 pub mod dapp_id_table;
+#[cfg(test)]
+pub mod fixture;
+mod integrity_audit;
 pub mod load_saved_blocks;
 mod optimistic_state_save_service;
+pub mod state_checksum;
+pub mod storage_usage;
 #[cfg(test)]
 pub mod stub_repository;
+pub use account_ownership::start_account_ownership_service;
+pub use integrity_audit::start_integrity_audit_service;
 pub use optimistic_state_save_service::start_optimistic_state_save_service;
+pub use state_checksum::start_state_checksum_service;
+pub use storage_usage::start_storage_quota_service;
+
+pub type RepositoryResult<T> = Result<T, RepositoryError>;
 
 #[derive(thiserror::Error, Debug)]
 pub enum RepositoryError {
@@ -55,6 +70,38 @@ pub enum RepositoryError {
     DepthSearchMinStateLimitReached,
     #[error("{0}")]
     BlockNotFound(String),
+    #[error("{0}")]
+    StateNotFound(String),
+    #[error(transparent)]
+    Internal(anyhow::Error),
+}
+
+// Internal helpers still return anyhow errors. Typed errors raised by them
+// are unwrapped, so callers can match on them.
+impl From<anyhow::Error> for RepositoryError {
+    fn from(err: anyhow::Error) -> Self {
+        match err.downcast::<RepositoryError>() {
+            Ok(err) => err,
+            Err(err) => Self::Internal(err),
+        }
+    }
+}
+
+impl ErrorCode for RepositoryError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::DepthSearchBlockCountLimitReached => "REPOSITORY_DEPTH_SEARCH_BLOCK_COUNT_LIMIT",
+            Self::DepthSearchMinStateLimitReached => "REPOSITORY_DEPTH_SEARCH_MIN_STATE_LIMIT",
+            Self::BlockNotFound(_) => "REPOSITORY_BLOCK_NOT_FOUND",
+            Self::StateNotFound(_) => "REPOSITORY_STATE_NOT_FOUND",
+            Self::Internal(_) => "REPOSITORY_INTERNAL",
+        }
+    }
+
+    fn is_retryable(&self) -> bool {
+        // Missing blocks and states may be received or synced later
+        matches!(self, Self::BlockNotFound(_) | Self::StateNotFound(_))
+    }
 }
 
 pub trait Repository {
@@ -77,13 +124,13 @@ pub trait Repository {
     fn get_finalized_block(
         &self,
         identifier: &BlockIdentifier,
-    ) -> anyhow::Result<Option<Arc<Self::CandidateBlock>>>;
+    ) -> RepositoryResult<Option<Arc<Self::CandidateBlock>>>;
 
     fn get_block_from_repo_or_archive(
         &self,
         block_id: &BlockIdentifier,
         thread_id: &ThreadIdentifier,
-    ) -> anyhow::Result<Arc<<Self as Repository>::CandidateBlock>>;
+    ) -> RepositoryResult<Arc<<Self as Repository>::CandidateBlock>>;
 
     fn last_finalized_optimistic_state(
         &self,
@@ -94,7 +141,7 @@ pub trait Repository {
         &self,
         starting_block_id: &BlockSeqNo,
         thread_id: &ThreadIdentifier,
-    ) -> anyhow::Result<()>;
+    ) -> RepositoryResult<()>;
 
     fn has_thread_metadata(&self, thread_id: &ThreadIdentifier) -> bool;
 
@@ -102,21 +149,21 @@ pub trait Repository {
         &mut self,
         thread_id: &ThreadIdentifier,
         parent_block_id: &BlockIdentifier,
-    ) -> anyhow::Result<()>;
+    ) -> RepositoryResult<()>;
 
     fn select_thread_last_finalized_block(
         &self,
         thread_id: &ThreadIdentifier,
-    ) -> anyhow::Result<Option<(BlockIdentifier, BlockSeqNo)>>;
+    ) -> RepositoryResult<Option<(BlockIdentifier, BlockSeqNo)>>;
 
-    fn is_block_suspicious(&self, block_id: &BlockIdentifier) -> anyhow::Result<Option<bool>>;
+    fn is_block_suspicious(&self, block_id: &BlockIdentifier) -> RepositoryResult<Option<bool>>;
 
     fn mark_block_as_finalized(
         &mut self,
         block: impl Borrow<<Self as Repository>::CandidateBlock>,
         block_state: BlockState,
         state_sync_service: Option<Arc<impl StateSyncService<Repository = RepositoryImpl>>>,
-    ) -> anyhow::Result<()>;
+    ) -> RepositoryResult<()>;
 
     //    fn is_block_finalized(&self, block_id: &BlockIdentifier) -> anyhow::Result<Option<bool>>;
 
@@ -125,62 +172,64 @@ pub trait Repository {
         block_id: &BlockIdentifier,
         thread_id: &ThreadIdentifier,
         min_state: Option<Arc<Self::OptimisticState>>,
-    ) -> anyhow::Result<Option<Arc<Self::OptimisticState>>>;
+    ) -> RepositoryResult<Option<Arc<Self::OptimisticState>>>;
 
     fn get_full_optimistic_state(
         &self,
         block_id: &BlockIdentifier,
         thread_id: &ThreadIdentifier,
         min_state: Option<Arc<Self::OptimisticState>>,
-    ) -> anyhow::Result<Option<Arc<Self::OptimisticState>>>;
+    ) -> RepositoryResult<Option<Arc<Self::OptimisticState>>>;
 
     fn erase_block(
         &self,
         block_id: &BlockIdentifier,
         thread_id: &ThreadIdentifier,
-    ) -> anyhow::Result<()>;
+    ) -> RepositoryResult<()>;
 
-    fn is_block_already_applied(&self, block_id: &BlockIdentifier) -> anyhow::Result<bool>;
+    fn is_block_already_applied(&self, block_id: &BlockIdentifier) -> RepositoryResult<bool>;
 
     fn set_state_from_snapshot(
         &mut self,
         snapshot: Self::StateSnapshot,
         thread_id: &ThreadIdentifier,
         skipped_attestation_ids: Arc<Mutex<HashSet<BlockIdentifier>>>,
-    ) -> anyhow::Result<()>;
+    ) -> RepositoryResult<()>;
 
     fn sync_accounts_from_state(
         &mut self,
         shard_state: Arc<ShardStateUnsplit>,
-    ) -> anyhow::Result<()>;
+    ) -> RepositoryResult<()>;
 
     fn save_account_diffs(
         &self,
         block_id: BlockIdentifier,
         accounts: HashMap<String, SerializedItem>,
-    ) -> anyhow::Result<()>;
+    ) -> RepositoryResult<()>;
 
-    fn store_optimistic<T: Into<Arc<Self::OptimisticState>>>(&self, state: T)
-        -> anyhow::Result<()>;
+    fn store_optimistic<T: Into<Arc<Self::OptimisticState>>>(
+        &self,
+        state: T,
+    ) -> RepositoryResult<()>;
 
     fn store_optimistic_in_cache<T: Into<Arc<Self::OptimisticState>>>(
         &self,
         state: T,
-    ) -> anyhow::Result<()>;
+    ) -> RepositoryResult<()>;
 
     fn get_latest_block_id_with_producer_group_change(
         &self,
         thread_id: &ThreadIdentifier,
-    ) -> anyhow::Result<BlockIdentifier>;
+    ) -> RepositoryResult<BlockIdentifier>;
 
     fn load_sent_attestations(
         &self,
-    ) -> anyhow::Result<HashMap<ThreadIdentifier, Vec<(BlockSeqNo, Self::Attestation)>>>;
+    ) -> RepositoryResult<HashMap<ThreadIdentifier, Vec<(BlockSeqNo, Self::Attestation)>>>;
 
     fn get_zero_state_for_thread(
         &self,
         thread_id: &ThreadIdentifier,
-    ) -> anyhow::Result<Arc<Self::OptimisticState>>;
+    ) -> RepositoryResult<Arc<Self::OptimisticState>>;
 
     fn get_all_metadata(&self) -> RepositoryMetadata;
 
@@ -191,3 +240,21 @@ pub trait Repository {
     ) -> Arc<Mutex<HashMap<ThreadIdentifier, UnfinalizedCandidateBlockCollection>>>;
     fn get_message_storage_service(&self) -> &MessageDBWriterService;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repository_error_from_anyhow() {
+        let err = RepositoryError::from(anyhow::Error::new(
+            RepositoryError::DepthSearchMinStateLimitReached,
+        ));
+        assert!(matches!(err, RepositoryError::DepthSearchMinStateLimitReached));
+        assert!(!err.is_retryable());
+
+        let err = RepositoryError::from(anyhow::format_err!("Failed to read metadata"));
+        assert_eq!(err.code(), "REPOSITORY_INTERNAL");
+        assert_eq!(err.to_api_error().message, "Failed to read metadata");
+    }
+}
@@ -26,6 +26,7 @@ use tvm_block::EnqueuedMsg;
 use tvm_block::GetRepresentationHash;
 use tvm_block::HashmapAugType;
 use tvm_block::MsgEnvelope;
+use tvm_block::OutMsgQueue;
 use tvm_block::OutMsgQueueKey;
 use tvm_block::Serializable;
 use tvm_block::ShardStateUnsplit;
@@ -38,6 +39,7 @@ use super::accounts::AccountsRepository;
 use super::optimistic_shard_state::OptimisticShardState;
 use crate::block::postprocessing::postprocess;
 use crate::block::verify::prepare_prev_block_info;
+use crate::block_keeper_system::slash::SlashType;
 use crate::block_keeper_system::wallet_config::create_wallet_slash_message;
 use crate::block_keeper_system::BlockKeeperSlashData;
 use crate::bls::envelope::BLSSignedEnvelope;
@@ -49,11 +51,13 @@ use crate::multithreading::cross_thread_messaging::thread_references_state::Thre
 use crate::multithreading::shard_state_operations::crop_shard_state_based_on_threads_table;
 use crate::node::block_state::repository::BlockStateRepository;
 use crate::node::shared_services::SharedServices;
+use crate::repository::account_ownership::AccountOwnershipIndex;
 use crate::repository::dapp_id_table::DAppIdTable;
 use crate::repository::dapp_id_table::DAppIdTableChangeSet;
 use crate::repository::CrossThreadRefData;
 use crate::repository::CrossThreadRefDataRead;
 use crate::storage::MessageDurableStorage;
+use crate::types::deferred_messages::DeferredMessageQueue;
 use crate::types::thread_message_queue::ThreadMessageQueueState;
 use crate::types::AccountAddress;
 use crate::types::AccountRouting;
@@ -158,10 +162,18 @@ pub struct OptimisticStateImpl {
 
     pub cropped: Option<(ThreadIdentifier, ThreadsTable)>,
 
+    // Messages scheduled by the timer contract that are not due yet. Stored
+    // in the trailing extension, so the layout read by the older nodes is kept
+    #[serde(skip)]
+    pub(crate) deferred_messages: DeferredMessageQueue,
+
     #[serde(skip)]
     pub changed_accounts: HashMap<AccountAddress, BlockSeqNo>,
     #[serde(skip)]
     pub cached_accounts: HashMap<AccountAddress, (BlockSeqNo, Cell)>,
+    #[serde(skip)]
+    #[builder(default)]
+    pub(crate) account_ownership: AccountOwnershipIndex,
     #[cfg(feature = "monitor-accounts-number")]
     pub accounts_number: u64,
 }
@@ -197,11 +209,98 @@ impl OptimisticStateImpl {
         &self.messages
     }
 
+    /// Takes deferred messages that are due at the block unixtime. Only the
+    /// thread that owns the timer contract delivers them, the queue is moved
+    /// to this thread on crop. Without the configured timer contract the
+    /// queue is kept until it is configured again.
+    pub(crate) fn take_due_deferred_messages(
+        &mut self,
+        unixtime: u32,
+        timer_contract: Option<&AccountAddress>,
+    ) -> Vec<WrappedMessage> {
+        let Some(timer_contract) = timer_contract else {
+            return vec![];
+        };
+        let timer_routing = self.get_account_routing(timer_contract, None);
+        if !self.does_routing_belong_to_the_state(&timer_routing) {
+            return vec![];
+        }
+        self.deferred_messages.take_due(unixtime)
+    }
+
+    /// Number of the out queue messages and the creation lt of the oldest one
+    /// per destination thread.
+    pub fn out_msg_queue_summary(&self) -> anyhow::Result<HashMap<ThreadIdentifier, (usize, u64)>> {
+        let out_msg_queue_info = self
+            .shard_state
+            .into_shard_state()
+            .read_out_msg_queue_info()
+            .map_err(|e| anyhow::format_err!("Failed to read out msg queue: {e}"))?;
+        let mut summary: HashMap<ThreadIdentifier, (usize, u64)> = HashMap::new();
+        let mut unrouted = 0;
+        out_msg_queue_info
+            .out_queue()
+            .iterate_slices(|_key, message_slice| {
+                let (enqueued_message, created_lt) =
+                    OutMsgQueue::value_aug(&mut message_slice.clone())?;
+                let message = enqueued_message.read_out_msg()?.read_message()?;
+                let Some(dest_account_id) = message.int_dst_account_id().map(From::from) else {
+                    unrouted += 1;
+                    return Ok(true);
+                };
+                match self.get_thread_for_account(&dest_account_id) {
+                    Ok(thread_id) => {
+                        let (count, oldest_lt) = summary.entry(thread_id).or_insert((0, u64::MAX));
+                        *count += 1;
+                        *oldest_lt = (*oldest_lt).min(created_lt);
+                    }
+                    Err(_) => unrouted += 1,
+                }
+                Ok(true)
+            })
+            .map_err(|e| anyhow::format_err!("Failed to iterate state out messages: {e}"))?;
+        if unrouted > 0 {
+            tracing::warn!("Out msg queue has {unrouted} messages without a destination thread");
+        }
+        Ok(summary)
+    }
+
     pub fn deserialize_from_buf(data: &[u8]) -> anyhow::Result<Self> {
-        let state: Self = bincode::deserialize(data)?;
+        let (state, _) = Self::deserialize_extended(data)?;
         Ok(state)
     }
 
+    /// Deserializes the state, the flag is false for the states written by
+    /// the node versions without the extension.
+    pub(crate) fn deserialize_extended(data: &[u8]) -> anyhow::Result<(Self, bool)> {
+        let mut reader = data;
+        let mut state: Self = bincode::deserialize_from(&mut reader)?;
+        let extended = state.read_extension(reader)?;
+        Ok((state, extended))
+    }
+
+    /// Serializes the state followed by the extension.
+    pub fn serialize_extended(&self) -> anyhow::Result<Vec<u8>> {
+        let mut buffer: Vec<u8> = bincode::serialize(self)?;
+        bincode::serialize_into(&mut buffer, &self.extension())?;
+        Ok(buffer)
+    }
+
+    fn extension(&self) -> OptimisticStateExtension {
+        OptimisticStateExtension { deferred_messages: self.deferred_messages.clone() }
+    }
+
+    // Reads the extension that follows the serialized state, if any
+    fn read_extension(&mut self, data: &[u8]) -> anyhow::Result<bool> {
+        if data.is_empty() {
+            return Ok(false);
+        }
+        let extension: OptimisticStateExtension = bincode::deserialize(data)
+            .map_err(|e| anyhow::format_err!("Failed to read optimistic state extension: {e}"))?;
+        self.deferred_messages = extension.deferred_messages;
+        Ok(true)
+    }
+
     pub fn zero() -> Self {
         Self {
             block_seq_no: BlockSeqNo::default(),
@@ -223,10 +322,12 @@ impl OptimisticStateImpl {
                 )]))
                 .build(),
             cropped: None,
+            deferred_messages: DeferredMessageQueue::default(),
             messages: ThreadMessageQueueState::empty(),
             high_priority_messages: ThreadMessageQueueState::empty(),
             changed_accounts: Default::default(),
             cached_accounts: Default::default(),
+            account_ownership: Default::default(),
             #[cfg(feature = "monitor-accounts-number")]
             accounts_number: 0,
         }
@@ -302,9 +403,11 @@ impl OptimisticStateImpl {
         let tmp_file_path = get_temp_file_path(&parent_dir);
 
         let shard_state = self.shard_state.into_cell();
+        let extension = self.extension();
         let trimmed_state: TrimmedOptimisticStateImpl = self.into();
         let file = File::create(&tmp_file_path)?;
-        let metadata = bincode::serialize(&trimmed_state)?;
+        let mut metadata = bincode::serialize(&trimmed_state)?;
+        bincode::serialize_into(&mut metadata, &extension)?;
         let metadata_len = metadata.len() as u64;
         let len_bytes = metadata_len.to_be_bytes();
         let mut buf_file = BufWriter::new(file);
@@ -327,11 +430,14 @@ impl OptimisticStateImpl {
         let metadata_len = file.read_be_u64()?;
         let mut data = vec![];
         file.read_to_end(&mut data)?;
-        let (metadata_bytes, shard_state_bytes) = data.split_at(metadata_len as usize);
-        let trimmed_state: TrimmedOptimisticStateImpl = bincode::deserialize(metadata_bytes)?;
+        let (mut metadata_bytes, shard_state_bytes) = data.split_at(metadata_len as usize);
+        let trimmed_state: TrimmedOptimisticStateImpl =
+            bincode::deserialize_from(&mut metadata_bytes)?;
         let shard_state_cell = tvm_types::read_single_root_boc(shard_state_bytes)
             .map_err(|e| anyhow::format_err!("Failed to deser shard state cell: {e}"))?;
-        Ok(state_from_trimmed(trimmed_state, shard_state_cell))
+        let mut state = state_from_trimmed(trimmed_state, shard_state_cell);
+        state.read_extension(metadata_bytes)?;
+        Ok(state)
     }
 }
 
@@ -380,8 +486,7 @@ impl OptimisticState for OptimisticStateImpl {
     }
 
     fn serialize_into_buf(self) -> anyhow::Result<Vec<u8>> {
-        let buffer: Vec<u8> = bincode::serialize(&self)?;
-        Ok(buffer)
+        self.serialize_extended()
     }
 
     fn apply_block(
@@ -416,8 +521,12 @@ impl OptimisticState for OptimisticStateImpl {
             tracing::trace!("push nack into slash {:?}", nack);
             let reason = nack.data().reason.clone();
             if let Some((id, bls_key, addr)) = reason.get_node_data(block_state_repo.clone()) {
-                let epoch_nack_data =
-                    BlockKeeperSlashData { node_id: id, bls_pubkey: bls_key, addr, slash_type: 0 };
+                let epoch_nack_data = BlockKeeperSlashData {
+                    node_id: id,
+                    bls_pubkey: bls_key,
+                    addr,
+                    slash_type: SlashType::FullStake,
+                };
                 let msg = create_wallet_slash_message(&epoch_nack_data)?;
                 let wrapped_message = WrappedMessage { message: msg.clone() };
                 wrapped_slash_messages.push(Arc::new(wrapped_message));
@@ -481,6 +590,7 @@ impl OptimisticState for OptimisticStateImpl {
             block_candidate.tvm_block(),
             accounts_repo.clone(),
         )?;
+        accounts_repo.invalidate_hot_accounts(&changed);
         tracing::trace!("Start state serialization");
         let prev_state = prev_state
             .serialize()
@@ -514,6 +624,25 @@ impl OptimisticState for OptimisticStateImpl {
             produced_internal_messages_to_other_threads,
         ) = block_candidate.get_data_for_postprocessing(self, shard_state.clone())?;
 
+        // Repeat deferred messages queue changes made by the block producer,
+        // due messages are already in the block out messages
+        let block_unixtime = block_candidate
+            .tvm_block()
+            .read_info()
+            .map_err(|e| anyhow::format_err!("Failed to read block info: {e}"))?
+            .gen_utime()
+            .as_u32();
+        let timer_contract = shared_services.timer_contract.as_ref();
+        self.take_due_deferred_messages(block_unixtime, timer_contract);
+        if let Some(timer_contract) = timer_contract {
+            let out_msg_descr = block_candidate
+                .tvm_block()
+                .read_extra()
+                .and_then(|extra| extra.read_out_msg_descr())
+                .map_err(|e| anyhow::format_err!("Failed to read block out msg descr: {e}"))?;
+            self.deferred_messages.schedule_timer_messages(&out_msg_descr, timer_contract)?;
+        }
+
         let old_dapp_id_table = self.dapp_id_table.clone();
 
         let mut all_added_messages = preprocessing_result.settled_messages;
@@ -653,6 +782,16 @@ impl OptimisticState for OptimisticStateImpl {
             .with_db(message_db.clone())
             .build()?;
 
+        // Deferred messages queue stays in the thread of the timer contract
+        // that scheduled them, so the value of the messages is not lost on
+        // split
+        if let Some(owner) = self.deferred_messages.owner() {
+            let owner_routing = self.get_account_routing(&owner, None);
+            if !threads_table.is_match(&owner_routing, *thread_identifier) {
+                self.deferred_messages = DeferredMessageQueue::default();
+            }
+        }
+
         self.cropped = crop_state;
         self.threads_table = threads_table.clone();
         self.thread_id = *thread_identifier;
@@ -710,8 +849,12 @@ impl OptimisticState for OptimisticStateImpl {
         account_id: &AccountAddress,
         change_set: Option<&DAppIdTableChangeSet>,
     ) -> bool {
-        let account_routing = self.get_account_routing(account_id, change_set);
-        self.threads_table.is_match(&account_routing, self.thread_id)
+        if change_set.is_some_and(|change_set| change_set.get_value(account_id).is_some()) {
+            let account_routing = self.get_account_routing(account_id, change_set);
+            return self.threads_table.is_match(&account_routing, self.thread_id);
+        }
+        self.account_ownership.thread_of(account_id, &self.threads_table, &self.dapp_id_table)
+            == self.thread_id
     }
 
     fn does_state_has_messages_to_other_threads(&mut self) -> anyhow::Result<bool> {
@@ -814,6 +957,13 @@ struct TrimmedOptimisticStateImpl {
     accounts_number: u64,
 }
 
+/// Fields added to the state after its layout was shared between node
+/// versions. Written after the state, older nodes ignore the trailing bytes.
+#[derive(Serialize, Deserialize)]
+struct OptimisticStateExtension {
+    deferred_messages: DeferredMessageQueue,
+}
+
 impl From<OptimisticStateImpl> for TrimmedOptimisticStateImpl {
     fn from(value: OptimisticStateImpl) -> Self {
         Self {
@@ -850,6 +1000,7 @@ fn state_from_trimmed(
         .dapp_id_table(trimmed.dapp_id_table)
         .thread_refs_state(trimmed.thread_refs_state)
         .cropped(trimmed.cropped)
+        .deferred_messages(DeferredMessageQueue::default())
         .changed_accounts(HashMap::new())
         .cached_accounts(HashMap::new())
         .accounts_number(trimmed.accounts_number)
@@ -867,6 +1018,7 @@ fn state_from_trimmed(
         .dapp_id_table(trimmed.dapp_id_table)
         .thread_refs_state(trimmed.thread_refs_state)
         .cropped(trimmed.cropped)
+        .deferred_messages(DeferredMessageQueue::default())
         .changed_accounts(HashMap::new())
         .cached_accounts(HashMap::new())
         .build();
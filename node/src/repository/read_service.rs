@@ -0,0 +1,136 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::sync::mpsc;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+use crate::repository::optimistic_state::OptimisticStateImpl;
+use crate::repository::repository_impl::RepositoryImpl;
+use crate::repository::Repository;
+use crate::repository::RepositoryError;
+use crate::repository::RepositoryResult;
+use crate::types::BlockIdentifier;
+use crate::types::ThreadIdentifier;
+
+const READER_THREADS: usize = 2;
+
+type ReadJob = Box<dyn FnOnce() + Send>;
+
+/// Reader threads for the repository loads that can stall on disk
+/// (optimistic states, archived blocks). Services that must not block pass
+/// such loads here, the completion callback is called on a reader thread.
+#[derive(Clone)]
+pub struct RepositoryReadService {
+    sender: mpsc::Sender<ReadJob>,
+}
+
+impl RepositoryReadService {
+    pub fn new() -> anyhow::Result<Self> {
+        let (sender, receiver) = mpsc::channel::<ReadJob>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for i in 0..READER_THREADS {
+            let receiver = receiver.clone();
+            std::thread::Builder::new().name(format!("RepositoryReader_{i}")).spawn(move || {
+                loop {
+                    // Readers stop when all the service clones are dropped
+                    let Ok(job) = receiver.lock().recv() else {
+                        return;
+                    };
+                    job();
+                }
+            })?;
+        }
+        Ok(Self { sender })
+    }
+
+    /// Runs `read` on a reader thread and passes the result to `on_complete`.
+    /// If the read fails to complete (panics), `on_complete` gets the error.
+    pub fn execute<T: Send>(
+        &self,
+        read: impl FnOnce() -> T + Send + 'static,
+        on_complete: impl FnOnce(anyhow::Result<T>) + Send + 'static,
+    ) {
+        let job = move || on_complete(run_read(read));
+        if let Err(mpsc::SendError(job)) = self.sender.send(Box::new(job)) {
+            tracing::error!("Repository readers are stopped, read in the caller thread");
+            job();
+        }
+    }
+}
+
+// The read runs on its own scoped thread, so a panic is returned as an error
+// and the caller waiting for the completion doesn't hang
+fn run_read<T: Send>(read: impl FnOnce() -> T + Send) -> anyhow::Result<T> {
+    std::thread::scope(|scope| {
+        std::thread::Builder::new()
+            .name("RepositoryRead".to_string())
+            .spawn_scoped(scope, read)
+            .map_err(|e| anyhow::format_err!("Failed to start repository read: {e}"))?
+            .join()
+            .map_err(|_| anyhow::format_err!("Repository read panicked"))
+    })
+}
+
+impl RepositoryImpl {
+    /// Like `get_full_optimistic_state`, but the state is loaded on a reader
+    /// thread and passed to `on_complete`.
+    pub fn get_full_optimistic_state_in_background(
+        &self,
+        block_id: BlockIdentifier,
+        thread_id: ThreadIdentifier,
+        on_complete: impl FnOnce(RepositoryResult<Option<Arc<OptimisticStateImpl>>>) + Send + 'static,
+    ) {
+        let repository = self.clone();
+        self.read_service().execute(
+            move || repository.get_full_optimistic_state(&block_id, &thread_id, None),
+            |result| on_complete(result.unwrap_or_else(|e| Err(RepositoryError::Internal(e)))),
+        );
+    }
+
+    /// Like `get_block_from_repo_or_archive`, but the block is loaded on a
+    /// reader thread and passed to `on_complete`.
+    pub fn get_block_from_repo_or_archive_in_background(
+        &self,
+        block_id: BlockIdentifier,
+        thread_id: ThreadIdentifier,
+        on_complete: impl FnOnce(RepositoryResult<Arc<<Self as Repository>::CandidateBlock>>)
+            + Send
+            + 'static,
+    ) {
+        let repository = self.clone();
+        self.read_service().execute(
+            move || repository.get_block_from_repo_or_archive(&block_id, &thread_id),
+            |result| on_complete(result.unwrap_or_else(|e| Err(RepositoryError::Internal(e)))),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_completion_is_called() {
+        let service = RepositoryReadService::new().unwrap();
+        let (tx, rx) = mpsc::channel();
+        for i in 0..10 {
+            let tx = tx.clone();
+            service.execute(move || i * 2, move |value| tx.send(value.unwrap()).unwrap());
+        }
+        let mut values = (0..10).map(|_| rx.recv().unwrap()).collect::<Vec<_>>();
+        values.sort();
+        assert_eq!(values, (0..10).map(|i| i * 2).collect::<Vec<_>>());
+
+        // A panicked read completes with an error and doesn't stop the readers
+        let (error_tx, error_rx) = mpsc::channel();
+        service.execute(
+            || panic!("read failed"),
+            move |value: anyhow::Result<()>| error_tx.send(value.is_err()).unwrap(),
+        );
+        assert!(error_rx.recv().unwrap());
+        service.execute(|| 1, move |value| tx.send(value.unwrap()).unwrap());
+        assert_eq!(rx.recv().unwrap(), 1);
+    }
+}
@@ -18,7 +18,6 @@ use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Instant;
 
-use anyhow::ensure;
 use chrono::DateTime;
 use chrono::TimeDelta;
 use chrono::Utc;
@@ -37,9 +36,11 @@ use typed_builder::TypedBuilder;
 
 use super::accounts::AccountsRepository;
 use crate::block_keeper_system::BlockKeeperSet;
+use crate::block_keeper_system::BlockKeeperSetChange;
 use crate::bls::envelope::BLSSignedEnvelope;
 use crate::bls::envelope::Envelope;
 use crate::bls::GoshBLS;
+use crate::config::StateSavePolicy;
 use crate::database::serialize_block::prepare_account_archive_struct;
 use crate::helper::get_temp_file_path;
 use crate::helper::metrics::BlockProductionMetrics;
@@ -62,9 +63,12 @@ use crate::node::NodeIdentifier;
 use crate::node::SignerIndex;
 use crate::repository::optimistic_state::OptimisticState;
 use crate::repository::optimistic_state::OptimisticStateImpl;
+use crate::repository::read_service::RepositoryReadService;
+use crate::repository::storage_usage::StorageUsage;
 use crate::repository::CrossThreadRefData;
 use crate::repository::Repository;
 use crate::repository::RepositoryError;
+use crate::repository::RepositoryResult;
 use crate::storage::MessageDBWriterService;
 use crate::storage::MessageDurableStorage;
 use crate::types::bp_selector::ProducerSelector;
@@ -183,9 +187,13 @@ impl FinalizedBlockStorage {
 }
 
 pub struct BkSetUpdate {
+    pub thread_id: ThreadIdentifier,
+    pub block_id: BlockIdentifier,
     pub seq_no: u32,
     pub current: Option<Arc<BlockKeeperSet>>,
     pub future: Option<Arc<BlockKeeperSet>>,
+    // Changes from the common section of the finalized block
+    pub changes: Vec<BlockKeeperSetChange>,
 }
 
 // TODO: divide repository into 2 entities: one for blocks, one for states with weak refs (for not
@@ -207,11 +215,13 @@ pub struct RepositoryImpl {
     metrics: Option<BlockProductionMetrics>,
     message_db: MessageDurableStorage,
     message_storage_service: MessageDBWriterService,
-    states_cache_size: usize,
+    state_save_policy: StateSavePolicy,
     finalized_blocks: Arc<Mutex<FinalizedBlockStorage>>,
     bk_set_update_tx: InstrumentedSender<BkSetUpdate>,
     unfinalized_blocks: Arc<Mutex<HashMap<ThreadIdentifier, UnfinalizedCandidateBlockCollection>>>,
     last_message_for_acc: Arc<Mutex<HashMap<AccountAddress, MessageIdentifier>>>,
+    read_service: RepositoryReadService,
+    storage_usage: StorageUsage,
 }
 
 #[allow(dead_code)]
@@ -230,6 +240,16 @@ pub struct Metadata<TBlockIdentifier: Hash + Eq, TBlockSeqNo> {
     pub last_finalized_producer_id: Option<NodeIdentifier>,
 }
 
+impl<TBlockIdentifier: Hash + Eq, TBlockSeqNo> Metadata<TBlockIdentifier, TBlockSeqNo> {
+    pub fn last_finalized_block_id(&self) -> &TBlockIdentifier {
+        &self.last_finalized_block_id
+    }
+
+    pub fn last_finalized_block_seq_no(&self) -> &TBlockSeqNo {
+        &self.last_finalized_block_seq_no
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct WrappedExtMessage<TMessage: Clone> {
     pub index: u32,
@@ -266,6 +286,12 @@ pub struct ThreadSnapshot {
     prefinalization_proof: Envelope<GoshBLS, AttestationData>,
 }
 
+impl ThreadSnapshot {
+    pub(crate) fn set_optimistic_state(&mut self, optimistic_state: Vec<u8>) {
+        self.optimistic_state = optimistic_state;
+    }
+}
+
 impl<TMessage> Default for ExtMessages<TMessage>
 where
     TMessage: Clone,
@@ -293,6 +319,15 @@ pub fn save_to_file<T: Serialize>(
     data: &T,
     force_sync: bool,
 ) -> anyhow::Result<()> {
+    write_file(file_path, data, cfg!(feature = "sync_files") || force_sync)
+}
+
+/// Saves the file without the sync, the caller syncs a batch of files at once.
+pub fn save_to_file_unsynced<T: Serialize>(file_path: &PathBuf, data: &T) -> anyhow::Result<()> {
+    write_file(file_path, data, false)
+}
+
+fn write_file<T: Serialize>(file_path: &PathBuf, data: &T, sync: bool) -> anyhow::Result<()> {
     let buffer = bincode::serialize(&data)?;
     let parent_dir = if let Some(path) = file_path.parent() {
         fs::create_dir_all(path)?;
@@ -304,7 +339,7 @@ pub fn save_to_file<T: Serialize>(
     let tmp_file_path = get_temp_file_path(&parent_dir);
     let mut file = File::create(&tmp_file_path)?;
     file.write_all(&buffer)?;
-    if cfg!(feature = "sync_files") || force_sync {
+    if sync {
         file.sync_all()?;
     }
     drop(file);
@@ -331,11 +366,13 @@ impl Clone for RepositoryImpl {
             metrics: self.metrics.clone(),
             message_storage_service: self.message_storage_service.clone(),
             message_db: self.message_db.clone(),
-            states_cache_size: self.states_cache_size,
+            state_save_policy: self.state_save_policy.clone(),
             finalized_blocks: Arc::clone(&self.finalized_blocks),
             bk_set_update_tx: self.bk_set_update_tx.clone(),
             unfinalized_blocks: self.unfinalized_blocks.clone(),
             last_message_for_acc: self.last_message_for_acc.clone(),
+            read_service: self.read_service.clone(),
+            storage_usage: self.storage_usage.clone(),
         }
     }
 }
@@ -346,7 +383,7 @@ impl RepositoryImpl {
     pub fn new(
         data_dir: PathBuf,
         zerostate_path: Option<PathBuf>,
-        states_cache_size: usize,
+        state_save_policy: StateSavePolicy,
         shared_services: SharedServices,
         nack_set_cache: Arc<Mutex<FixedSizeHashSet<UInt256>>>,
         split_state: bool,
@@ -364,6 +401,8 @@ impl RepositoryImpl {
         let message_storage_service =
             MessageDBWriterService::new(message_db.clone(), metrics.clone())
                 .expect("Failed to init message storage service");
+        let read_service =
+            RepositoryReadService::new().expect("Failed to init repository read service");
 
         let metadata = Arc::new(Mutex::new({
             let metadata = Self::load_metadata(&data_dir)
@@ -412,11 +451,13 @@ impl RepositoryImpl {
             metrics,
             message_db: message_db.clone(),
             message_storage_service,
-            states_cache_size,
+            state_save_policy,
             finalized_blocks,
             bk_set_update_tx,
             unfinalized_blocks: Arc::new(Mutex::new(HashMap::new())),
             last_message_for_acc: Arc::new(Mutex::new(HashMap::new())),
+            read_service,
+            storage_usage: StorageUsage::default(),
         };
 
         let optimistic_dir = format!(
@@ -432,15 +473,32 @@ impl RepositoryImpl {
                             tracing::trace!(
                                 "RepositoryImpl::new reading optimistic state: {block_id:?}"
                             );
-                            if let Ok(state) = OptimisticStateImpl::load_from_file(&path) {
-                                let seq_no = state.get_block_info().prev1().unwrap().seq_no;
-                                {
+                            match OptimisticStateImpl::load_from_file(&path) {
+                                Ok(state) if state.block_id == block_id => {
+                                    let seq_no = state.get_block_info().prev1().unwrap().seq_no;
                                     let mut all_states = repo_impl.saved_states.lock();
                                     let saved_states =
                                         all_states.entry(state.thread_id).or_default();
                                     saved_states.insert(BlockSeqNo::from(seq_no), block_id);
                                 }
-                            };
+                                // Torn writes are discarded, the state is rebuilt
+                                // from an ancestor when it's needed
+                                Ok(state) => {
+                                    tracing::error!(
+                                        "Discard optimistic state {}: it belongs to block {:?}",
+                                        path.display(),
+                                        state.block_id
+                                    );
+                                    let _ = std::fs::remove_file(&path);
+                                }
+                                Err(e) => {
+                                    tracing::error!(
+                                        "Discard optimistic state {}: {e}",
+                                        path.display()
+                                    );
+                                    let _ = std::fs::remove_file(&path);
+                                }
+                            }
                         }
                     }
                 }
@@ -668,6 +726,200 @@ impl RepositoryImpl {
         path
     }
 
+    pub(crate) fn get_optimistic_state_dir_path(&self) -> PathBuf {
+        let mut path = self.data_dir.clone();
+        path.push(self.get_optimistic_state_path());
+        path
+    }
+
+    pub fn get_crash_markers_dir_path(&self) -> PathBuf {
+        self.data_dir.join("crash-markers")
+    }
+
+    pub fn state_save_policy(&self) -> &StateSavePolicy {
+        &self.state_save_policy
+    }
+
+    /// The latest state of the thread applied by this node, it may be not
+    /// finalized yet.
+    pub fn last_optimistic_state(
+        &self,
+        thread_id: &ThreadIdentifier,
+    ) -> Option<Arc<OptimisticStateImpl>> {
+        let cached = self.optimistic_state.guarded(|e| {
+            e.values()
+                .filter(|state| state.thread_id == *thread_id)
+                .max_by_key(|state| state.block_seq_no)
+                .cloned()
+        });
+        let finalized = self.last_finalized_optimistic_state(thread_id);
+        match (cached, finalized) {
+            (Some(cached), Some(finalized)) if finalized.block_seq_no > cached.block_seq_no => {
+                Some(finalized)
+            }
+            (cached, finalized) => cached.or(finalized),
+        }
+    }
+
+    pub fn storage_usage(&self) -> &StorageUsage {
+        &self.storage_usage
+    }
+
+    // Removes saved states exceeding the thread retention. The latest saved
+    // state that is not newer than the last finalized block is always kept:
+    // the finalized state is restored from it.
+    pub(crate) fn remove_expired_saved_states(
+        &self,
+        thread_id: &ThreadIdentifier,
+    ) -> anyhow::Result<()> {
+        let retention = self.storage_usage.effective_retention(
+            thread_id,
+            self.state_save_policy.for_thread(thread_id).saved_states_retention,
+        );
+        if retention == 0 {
+            return Ok(());
+        }
+        let Some((_, last_finalized_seq_no)) =
+            self.select_thread_last_finalized_block(thread_id)?
+        else {
+            return Ok(());
+        };
+        let expired = self.saved_states.guarded_mut(|all_states| {
+            let Some(saved_states) = all_states.get_mut(thread_id) else {
+                return vec![];
+            };
+            let Some(anchor_seq_no) =
+                saved_states.range(..=last_finalized_seq_no).next_back().map(|(seq_no, _)| *seq_no)
+            else {
+                return vec![];
+            };
+            let excess = saved_states.len().saturating_sub(retention);
+            let expired_seq_nos = saved_states
+                .keys()
+                .take(excess)
+                .filter(|seq_no| **seq_no < anchor_seq_no)
+                .copied()
+                .collect::<Vec<_>>();
+            expired_seq_nos
+                .into_iter()
+                .filter_map(|seq_no| saved_states.remove(&seq_no))
+                .collect::<Vec<_>>()
+        });
+        for block_id in expired {
+            let path = self.get_path(self.get_optimistic_state_path(), block_id.to_string());
+            tracing::trace!("Remove expired saved state: {}", path.display());
+            if let Err(e) = fs::remove_file(&path) {
+                tracing::warn!("Failed to remove expired saved state {}: {e}", path.display());
+            }
+        }
+        Ok(())
+    }
+
+    /// Threads that have saved optimistic states.
+    pub fn threads_with_saved_states(&self) -> Vec<ThreadIdentifier> {
+        self.saved_states.guarded(|all_states| all_states.keys().copied().collect())
+    }
+
+    /// Blocks of the saved optimistic states by thread.
+    pub(crate) fn saved_state_ids(&self) -> HashMap<ThreadIdentifier, Vec<BlockIdentifier>> {
+        self.saved_states.guarded(|all_states| {
+            all_states
+                .iter()
+                .map(|(thread_id, states)| (*thread_id, states.values().cloned().collect()))
+                .collect()
+        })
+    }
+
+    fn write_messages_to_db(
+        &self,
+        thread_id: &ThreadIdentifier,
+        messages: HashMap<AccountAddress, Vec<(MessageIdentifier, Arc<WrappedMessage>)>>,
+    ) -> anyhow::Result<()> {
+        if cfg!(feature = "messages_db") {
+            self.storage_usage.report_messages_written(thread_id, &messages);
+        }
+        self.message_storage_service.write(messages)
+    }
+
+    /// Threads that have finalized optimistic states.
+    pub fn threads_with_finalized_states(&self) -> Vec<ThreadIdentifier> {
+        self.thread_last_finalized_state.guarded(|e| e.keys().copied().collect())
+    }
+
+    /// The latest saved state that is not newer than the last finalized block
+    /// of the thread. Saved states are taken on the same seq nos on all nodes,
+    /// so they are comparable among nodes.
+    pub fn last_finalized_saved_state(
+        &self,
+        thread_id: &ThreadIdentifier,
+    ) -> anyhow::Result<Option<(BlockSeqNo, BlockIdentifier)>> {
+        let Some((_, last_finalized_seq_no)) =
+            self.select_thread_last_finalized_block(thread_id)?
+        else {
+            return Ok(None);
+        };
+        Ok(self.saved_states.guarded(|all_states| {
+            all_states.get(thread_id).and_then(|saved_states| {
+                saved_states
+                    .range(..=last_finalized_seq_no)
+                    .next_back()
+                    .map(|(seq_no, block_id)| (*seq_no, block_id.clone()))
+            })
+        }))
+    }
+
+    /// Like `get_optimistic_state`, but a state that doesn't match its block
+    /// is discarded and rebuilt from the closest valid ancestor state.
+    pub fn get_validated_optimistic_state(
+        &self,
+        block_id: &BlockIdentifier,
+        thread_id: &ThreadIdentifier,
+    ) -> anyhow::Result<Option<Arc<OptimisticStateImpl>>> {
+        let Some(state) = self.get_optimistic_state(block_id, thread_id, None)? else {
+            return Ok(None);
+        };
+        let Err(reason) = self.validate_optimistic_state(&state, block_id) else {
+            return Ok(Some(state));
+        };
+        tracing::error!("Optimistic state of block {block_id:?} is corrupted: {reason}");
+        self.discard_optimistic_state(block_id, thread_id);
+        let Some(state) = self.try_load_state_from_archive(
+            block_id,
+            Arc::clone(&self.nack_set_cache),
+            None,
+            thread_id,
+        )?
+        else {
+            return Ok(None);
+        };
+        self.validate_optimistic_state(&state, block_id)
+            .map_err(|e| anyhow::format_err!("Rebuilt optimistic state is corrupted: {e}"))?;
+        tracing::info!("Optimistic state of block {block_id:?} is rebuilt");
+        let state = Arc::new(state);
+        self.optimistic_state.guarded_mut(|e| e.insert(block_id.clone(), Arc::clone(&state)));
+        Ok(Some(state))
+    }
+
+    // Removes the state from the cache and from the disk
+    fn discard_optimistic_state(&self, block_id: &BlockIdentifier, thread_id: &ThreadIdentifier) {
+        self.optimistic_state.guarded_mut(|e| e.remove(block_id));
+        self.saved_states.guarded_mut(|all_states| {
+            if let Some(saved_states) = all_states.get_mut(thread_id) {
+                saved_states.retain(|_, saved_block_id| saved_block_id != block_id);
+            }
+        });
+        let path = self.get_path(self.get_optimistic_state_path(), block_id.to_string());
+        if path.exists() {
+            if let Err(e) = fs::remove_file(&path) {
+                tracing::warn!("Failed to remove optimistic state {}: {e}", path.display());
+            }
+        }
+    }
+
+    pub(crate) fn is_split_state(&self) -> bool {
+        self.split_state
+    }
+
     fn save_block(
         data_dir: &Path,
         block: &<RepositoryImpl as Repository>::CandidateBlock,
@@ -728,7 +980,25 @@ impl RepositoryImpl {
         };
         const RETAIN: bool = true;
         const REMOVE: bool = false;
+        let state_cache_size = self.state_save_policy.for_thread(thread_id).state_cache_size;
         self.optimistic_state.guarded_mut(|states| {
+            // The latest finalized states are kept up to the thread cache size
+            let mut finalized_seq_nos = states
+                .values()
+                .filter(|e| {
+                    e.get_thread_id() == thread_id && e.get_block_seq_no() < &last_finalized_seq_no
+                })
+                .map(|e| *e.get_block_seq_no())
+                .collect::<Vec<_>>();
+            finalized_seq_nos.sort();
+            let cached_from_seq_no = match state_cache_size {
+                0 => None,
+                size => finalized_seq_nos
+                    .len()
+                    .checked_sub(size)
+                    .map(|skip| finalized_seq_nos[skip])
+                    .or(finalized_seq_nos.first().copied()),
+            };
             states.retain(|_, e| {
                 if e.get_thread_id() != thread_id {
                     return RETAIN;
@@ -736,6 +1006,9 @@ impl RepositoryImpl {
                 if e.get_block_seq_no() >= &last_finalized_seq_no {
                     return RETAIN;
                 }
+                if cached_from_seq_no.is_some_and(|seq_no| e.get_block_seq_no() >= &seq_no) {
+                    return RETAIN;
+                }
                 REMOVE
             })
         });
@@ -849,45 +1122,62 @@ impl RepositoryImpl {
         let mut blocks = vec![];
         let mut block_id = block_id.clone();
 
-        let state_id = loop {
-            let block = self.get_block_from_repo_or_archive(&block_id, thread_id)?;
-            tracing::trace!(
-                "try_load_state_from_archive: loaded block: {:?} {block_id:?}",
-                block.data().seq_no()
-            );
-            if let Some(min_state) = min_state.as_ref() {
-                if block.data().seq_no() < min_state.block_seq_no {
-                    anyhow::bail!(RepositoryError::DepthSearchMinStateLimitReached);
+        let state = loop {
+            let state_id = loop {
+                let block = self.get_block_from_repo_or_archive(&block_id, thread_id)?;
+                tracing::trace!(
+                    "try_load_state_from_archive: loaded block: {:?} {block_id:?}",
+                    block.data().seq_no()
+                );
+                if let Some(min_state) = min_state.as_ref() {
+                    if block.data().seq_no() < min_state.block_seq_no {
+                        anyhow::bail!(RepositoryError::DepthSearchMinStateLimitReached);
+                    }
                 }
+                blocks.push(block.data().clone());
+                if blocks.len() > MAX_BLOCK_CNT_THAT_CAN_BE_LOADED_TO_PREPARE_STATE {
+                    anyhow::bail!(RepositoryError::DepthSearchBlockCountLimitReached);
+                }
+                let parent_id = block.data().parent();
+                if available_states.contains(&parent_id) {
+                    break parent_id;
+                }
+                block_id = parent_id;
+            };
+
+            if state_id == BlockIdentifier::default() {
+                tracing::trace!("Load state from zerostate");
+                let mut zero_block = <Self as Repository>::OptimisticState::zero();
+                if let Some(path) = &self.zerostate_path {
+                    let zerostate = ZeroState::load_from_file(path)?;
+                    zero_block = zerostate.state(thread_id)?.clone();
+                };
+                break Arc::new(zero_block);
+            } else if let Some(state) = finalized_state.as_ref().filter(|s| s.block_id == state_id)
+            {
+                break state.clone();
+            } else if let Some(state) = min_state.as_ref().filter(|s| s.block_id == state_id) {
+                break state.clone();
             }
-            blocks.push(block.data().clone());
-            if blocks.len() > MAX_BLOCK_CNT_THAT_CAN_BE_LOADED_TO_PREPARE_STATE {
-                anyhow::bail!(RepositoryError::DepthSearchBlockCountLimitReached);
-            }
-            let parent_id = block.data().parent();
-            if available_states.contains(&parent_id) {
-                break parent_id;
+            let state = self
+                .get_optimistic_state(&state_id, thread_id, None)?
+                .ok_or(anyhow::format_err!("Optimistic state must be present"))?;
+            // A corrupted state is skipped, the search continues to the
+            // closest valid ancestor state
+            match self.validate_optimistic_state(&state, &state_id) {
+                Ok(()) => break state,
+                Err(reason) => {
+                    tracing::error!(
+                        "try_load_state_from_archive: corrupted state {state_id:?}: {reason}"
+                    );
+                    self.discard_optimistic_state(&state_id, thread_id);
+                    available_states.remove(&state_id);
+                    block_id = state_id;
+                }
             }
-            block_id = parent_id;
         };
         blocks.reverse();
 
-        let state = if state_id == BlockIdentifier::default() {
-            tracing::trace!("Load state from zerostate");
-            let mut zero_block = <Self as Repository>::OptimisticState::zero();
-            if let Some(path) = &self.zerostate_path {
-                let zerostate = ZeroState::load_from_file(path)?;
-                zero_block = zerostate.state(thread_id)?.clone();
-            };
-            Arc::new(zero_block)
-        } else if let Some(state) = finalized_state.filter(|s| s.block_id == state_id) {
-            state
-        } else if let Some(state) = min_state.filter(|s| s.block_id == state_id) {
-            state
-        } else {
-            self.get_optimistic_state(&state_id, thread_id, None)?
-                .ok_or(anyhow::format_err!("Optimistic state must be present"))?
-        };
         let mut state = Arc::unwrap_or_clone(state);
         tracing::trace!("try_load_state_from_archive start applying blocks");
         for block in blocks {
@@ -927,12 +1217,27 @@ impl RepositoryImpl {
         &self.accounts
     }
 
+    pub fn read_service(&self) -> &RepositoryReadService {
+        &self.read_service
+    }
+
     #[cfg(test)]
     pub fn stub(
         last_finalized_block_id: BlockIdentifier,
         last_finalized_block_seq_no: BlockSeqNo,
         thread_id: ThreadIdentifier,
         block_state_repository: BlockStateRepository,
+    ) -> Self {
+        Self::stub_with_threads(
+            HashMap::from([(thread_id, (last_finalized_block_id, last_finalized_block_seq_no))]),
+            block_state_repository,
+        )
+    }
+
+    #[cfg(test)]
+    pub fn stub_with_threads(
+        last_finalized_blocks: HashMap<ThreadIdentifier, (BlockIdentifier, BlockSeqNo)>,
+        block_state_repository: BlockStateRepository,
     ) -> Self {
         use telemetry_utils::mpsc::instrumented_channel;
 
@@ -940,10 +1245,17 @@ impl RepositoryImpl {
         use crate::multithreading::routing::service::RoutingService;
         use crate::storage::MessageDurableStorage;
 
-        let metadata =
-            Metadata { last_finalized_block_id, last_finalized_block_seq_no, ..Default::default() };
-        let mut metadatas = HashMap::new();
-        metadatas.insert(thread_id, Arc::new(Mutex::new(metadata)));
+        let metadatas = last_finalized_blocks
+            .into_iter()
+            .map(|(thread_id, (last_finalized_block_id, last_finalized_block_seq_no))| {
+                let metadata = Metadata {
+                    last_finalized_block_id,
+                    last_finalized_block_seq_no,
+                    ..Default::default()
+                };
+                (thread_id, Arc::new(Mutex::new(metadata)))
+            })
+            .collect();
         let tmp_dir = tempfile::tempdir().unwrap();
         let mut tmp_path = tmp_dir.path().to_owned();
         tmp_path.push("archive_db");
@@ -962,7 +1274,11 @@ impl RepositoryImpl {
             metrics::BK_SET_UPDATE_CHANNEL,
         );
         Self {
-            accounts: AccountsRepository::new(data_dir.clone(), None, 1),
+            accounts: AccountsRepository::new(
+                data_dir.clone(),
+                None,
+                crate::repository::repository_impl::tests::state_save_policy(),
+            ),
             data_dir,
             zerostate_path: None,
             metadatas: Arc::new(Mutex::new(metadatas)),
@@ -976,11 +1292,14 @@ impl RepositoryImpl {
             metrics: None,
             message_db,
             message_storage_service: message_service,
-            states_cache_size: 1,
+            state_save_policy: crate::repository::repository_impl::tests::state_save_policy(),
             finalized_blocks,
             bk_set_update_tx,
             unfinalized_blocks: Arc::new(Mutex::new(HashMap::new())),
             last_message_for_acc: Arc::new(Mutex::new(HashMap::new())),
+            read_service: RepositoryReadService::new()
+                .expect("Failed to init repository read service"),
+            storage_usage: StorageUsage::default(),
         }
     }
 
@@ -1092,7 +1411,7 @@ impl Repository for RepositoryImpl {
 
     fn load_sent_attestations(
         &self,
-    ) -> anyhow::Result<HashMap<ThreadIdentifier, Vec<(BlockSeqNo, Self::Attestation)>>> {
+    ) -> RepositoryResult<HashMap<ThreadIdentifier, Vec<(BlockSeqNo, Self::Attestation)>>> {
         let path = self.get_attestations_path();
         let path = self.get_path(path, DEFAULT_OID.to_owned());
         let res = load_from_file(&path)?.unwrap_or_default();
@@ -1103,7 +1422,7 @@ impl Repository for RepositoryImpl {
         &mut self,
         thread_id: &ThreadIdentifier,
         parent_block_id: &BlockIdentifier,
-    ) -> anyhow::Result<()> {
+    ) -> RepositoryResult<()> {
         let mut guarded = self.metadatas.lock();
         if guarded.contains_key(thread_id) {
             let metadata = guarded.get(thread_id).unwrap().lock();
@@ -1111,15 +1430,15 @@ impl Repository for RepositoryImpl {
             return Ok(());
         }
 
-        ensure!(self.is_block_finalized(parent_block_id)? == Some(true));
-        let optimistic_state = {
-            let optimistic_state = self.get_optimistic_state(parent_block_id, thread_id, None)?;
-            ensure!(
-                optimistic_state.is_some(),
-                "thread parent block must have an optimistic state stored"
-            );
-            optimistic_state.unwrap()
-        };
+        if self.is_block_finalized(parent_block_id)? != Some(true) {
+            return Err(anyhow::format_err!("thread parent block must be finalized").into());
+        }
+        let optimistic_state =
+            self.get_optimistic_state(parent_block_id, thread_id, None)?.ok_or_else(|| {
+                RepositoryError::StateNotFound(
+                    "thread parent block must have an optimistic state stored".to_string(),
+                )
+            })?;
         let parent_block_seq_no = { *optimistic_state.get_block_seq_no() };
         self.thread_last_finalized_state.guarded_mut(|e| {
             if let Some(cur_state_state_seq_no) = e.get(thread_id).map(|state| state.block_seq_no) {
@@ -1144,15 +1463,15 @@ impl Repository for RepositoryImpl {
     fn get_finalized_block(
         &self,
         identifier: &BlockIdentifier,
-    ) -> anyhow::Result<Option<Arc<Self::CandidateBlock>>> {
-        self.load_finalized_candidate_block(identifier)
+    ) -> RepositoryResult<Option<Arc<Self::CandidateBlock>>> {
+        Ok(self.load_finalized_candidate_block(identifier)?)
     }
 
     fn get_block_from_repo_or_archive(
         &self,
         block_id: &BlockIdentifier,
         thread_id: &ThreadIdentifier,
-    ) -> anyhow::Result<Arc<<Self as Repository>::CandidateBlock>> {
+    ) -> RepositoryResult<Arc<<Self as Repository>::CandidateBlock>> {
         let unfinalized_block = self
             .unfinalized_blocks
             .guarded(|e| e.get(thread_id).cloned())
@@ -1162,7 +1481,7 @@ impl Repository for RepositoryImpl {
         } else if let Some(block) = self.get_finalized_block(block_id)? {
             block
         } else {
-            anyhow::bail!(RepositoryError::BlockNotFound(format!(
+            return Err(RepositoryError::BlockNotFound(format!(
                 "get_block_from_repo_or_archive: failed to load block: {block_id:?}"
             )));
         })
@@ -1178,7 +1497,7 @@ impl Repository for RepositoryImpl {
     fn select_thread_last_finalized_block(
         &self,
         thread_id: &ThreadIdentifier,
-    ) -> anyhow::Result<Option<(BlockIdentifier, BlockSeqNo)>> {
+    ) -> RepositoryResult<Option<(BlockIdentifier, BlockSeqNo)>> {
         // tracing::trace!("select_thread_last_finalized_block: start");
         // TODO: Critical: This "fast-forward" is already broken.
         // It will not survive thread merge operations.
@@ -1201,7 +1520,7 @@ impl Repository for RepositoryImpl {
         block: impl Borrow<<Self as Repository>::CandidateBlock>,
         _block_state: BlockState,
         state_sync_service: Option<Arc<impl StateSyncService<Repository = RepositoryImpl>>>,
-    ) -> anyhow::Result<()> {
+    ) -> RepositoryResult<()> {
         if SHUTDOWN_FLAG.get() == Some(&true) {
             // NOTE: to prevent unexpected block finalization during shutdown
             return Ok(());
@@ -1232,12 +1551,15 @@ impl Repository for RepositoryImpl {
                     metrics.report_bk_set(bk_set.len(), future_bk_set.len(), &thread_id)
                 }
             }
-            let _ = self.bk_set_update_tx.send(BkSetUpdate {
-                seq_no: block_seq_no.into(),
-                current: bk_set,
-                future: future_bk_set,
-            });
         }
+        let _ = self.bk_set_update_tx.send(BkSetUpdate {
+            thread_id,
+            block_id: block_id.clone(),
+            seq_no: block_seq_no.into(),
+            current: bk_set,
+            future: future_bk_set,
+            changes: block.borrow().data().get_common_section().block_keeper_set_changes.clone(),
+        });
         let metadata = self.get_metadata_for_thread(&thread_id)?;
         let mut metadata = metadata.lock();
 
@@ -1257,7 +1579,7 @@ impl Repository for RepositoryImpl {
 
         if let Err(e) = Self::save_metadata(&self.data_dir, self.metadatas.clone()) {
             tracing::error!("Failed to save metadata: {}", e);
-            return Err(e);
+            return Err(e.into());
         }
         // After sync we mark the incoming block as finalized and this check works
         let state = self
@@ -1282,7 +1604,7 @@ impl Repository for RepositoryImpl {
                     let mut last_message_guard = self.last_message_for_acc.lock();
                     let btree = &saved_state.messages.messages;
                     let new_messages = extract_new_messages(btree, &last_message_guard)?;
-                    self.message_storage_service.write(new_messages.clone())?;
+                    self.write_messages_to_db(&thread_id, new_messages.clone())?;
                     for (address, vector) in new_messages {
                         if let Some((m_identifier, _)) = vector.last() {
                             last_message_guard.insert(address, m_identifier.clone());
@@ -1330,7 +1652,9 @@ impl Repository for RepositoryImpl {
                     let full_state = self
                         .get_full_optimistic_state(&block_id, &thread_id, Some(new_state.clone()))?
                         .expect("Must be accessible");
-                    service.save_state_for_sharing(full_state)?;
+                    service.save_state_for_sharing(full_state).map_err(|e| {
+                        anyhow::format_err!("Failed to save state for sharing: {e}")
+                    })?;
                 }
             }
 
@@ -1356,7 +1680,7 @@ impl Repository for RepositoryImpl {
     fn get_zero_state_for_thread(
         &self,
         thread_id: &ThreadIdentifier,
-    ) -> anyhow::Result<Arc<Self::OptimisticState>> {
+    ) -> RepositoryResult<Arc<Self::OptimisticState>> {
         let mut state = Self::OptimisticState::zero();
         if let Some(path) = &self.zerostate_path {
             let zerostate = ZeroState::load_from_file(path)?;
@@ -1370,7 +1694,7 @@ impl Repository for RepositoryImpl {
         block_id: &BlockIdentifier,
         thread_id: &ThreadIdentifier,
         min_state: Option<Arc<Self::OptimisticState>>,
-    ) -> anyhow::Result<Option<Arc<Self::OptimisticState>>> {
+    ) -> RepositoryResult<Option<Arc<Self::OptimisticState>>> {
         log::info!("RepositoryImpl: get_optimistic_state: {block_id:?}");
         if let Some(cached) = self.optimistic_state.guarded_mut(|e| e.get(block_id).map(Arc::clone))
         {
@@ -1412,7 +1736,7 @@ impl Repository for RepositoryImpl {
         block_id: &BlockIdentifier,
         thread_id: &ThreadIdentifier,
         min_state: Option<Arc<Self::OptimisticState>>,
-    ) -> anyhow::Result<Option<Arc<Self::OptimisticState>>> {
+    ) -> RepositoryResult<Option<Arc<Self::OptimisticState>>> {
         let state = self.get_optimistic_state(block_id, thread_id, min_state)?;
         let Some(state) = state else {
             return Ok(None);
@@ -1462,7 +1786,7 @@ impl Repository for RepositoryImpl {
         &self,
         block_id: &BlockIdentifier,
         thread_id: &ThreadIdentifier,
-    ) -> anyhow::Result<()> {
+    ) -> RepositoryResult<()> {
         tracing::trace!("erase_block: {:?}", block_id);
         let metadata = self.get_metadata_for_thread(thread_id)?;
         let metadata = metadata.lock(); // guarded
@@ -1481,13 +1805,13 @@ impl Repository for RepositoryImpl {
         &self,
         _excluded_starting_seq_no: &BlockSeqNo,
         thread_id: &ThreadIdentifier,
-    ) -> anyhow::Result<()> {
+    ) -> RepositoryResult<()> {
         let _metadata = self.get_metadata_for_thread(thread_id)?;
         // TODO: remove calls
         Ok(())
     }
 
-    fn is_block_already_applied(&self, block_id: &BlockIdentifier) -> anyhow::Result<bool> {
+    fn is_block_already_applied(&self, block_id: &BlockIdentifier) -> RepositoryResult<bool> {
         let result =
             self.block_state_repository.get(block_id)?.guarded(|e| e.is_block_already_applied());
         tracing::trace!(?block_id, "is_block_already_applied: {result}");
@@ -1499,7 +1823,7 @@ impl Repository for RepositoryImpl {
         snapshot: Self::StateSnapshot,
         cur_thread_id: &ThreadIdentifier,
         _skipped_attestation_ids: Arc<Mutex<HashSet<BlockIdentifier>>>,
-    ) -> anyhow::Result<()> {
+    ) -> RepositoryResult<()> {
         tracing::debug!("set_state_from_snapshot");
         let thread_snapshot: ThreadSnapshot = bincode::deserialize(&snapshot)
             .map_err(|e| anyhow::format_err!("Failed to deserialize snapshot: {e}"))?;
@@ -1639,7 +1963,7 @@ impl Repository for RepositoryImpl {
                 }
             })
             .collect();
-        self.message_storage_service.write(db_messages)?;
+        self.write_messages_to_db(&thread_id, db_messages)?;
 
         let mut blocks_with_cross_thread_ref_data_set = vec![];
         self.shared_services.exec(|services| {
@@ -1664,7 +1988,7 @@ impl Repository for RepositoryImpl {
         Ok(())
     }
 
-    fn is_block_suspicious(&self, block_id: &BlockIdentifier) -> anyhow::Result<Option<bool>> {
+    fn is_block_suspicious(&self, block_id: &BlockIdentifier) -> RepositoryResult<Option<bool>> {
         let guarded = self.block_state_repository.get(block_id)?;
         let result = !guarded.guarded(|e| e.has_all_nacks_resolved());
         Ok(Some(result))
@@ -1673,7 +1997,7 @@ impl Repository for RepositoryImpl {
     fn sync_accounts_from_state(
         &mut self,
         shard_state: Arc<ShardStateUnsplit>,
-    ) -> anyhow::Result<()> {
+    ) -> RepositoryResult<()> {
         tracing::debug!("syncing accounts from state...");
         let mut arch_accounts: Vec<ArchAccount> = vec![];
         let accounts = shard_state.read_accounts().map_err(|e| anyhow::format_err!("{e}"))?;
@@ -1723,7 +2047,7 @@ impl Repository for RepositoryImpl {
         &self,
         block_id: BlockIdentifier,
         accounts: HashMap<String, SerializedItem>,
-    ) -> anyhow::Result<()> {
+    ) -> RepositoryResult<()> {
         // TODO split according to the sqlite limits
         self.save_accounts(block_id, accounts)?;
         Ok(())
@@ -1732,7 +2056,7 @@ impl Repository for RepositoryImpl {
     fn store_optimistic_in_cache<T: Into<Arc<Self::OptimisticState>>>(
         &self,
         state: T,
-    ) -> anyhow::Result<()> {
+    ) -> RepositoryResult<()> {
         let optimistic = state.into();
         let block_id = optimistic.get_block_id().clone();
         let thread_id = *optimistic.get_thread_id();
@@ -1747,7 +2071,7 @@ impl Repository for RepositoryImpl {
     fn store_optimistic<T: Into<Arc<Self::OptimisticState>>>(
         &self,
         state: T,
-    ) -> anyhow::Result<()> {
+    ) -> RepositoryResult<()> {
         let optimistic = state.into();
         self.store_optimistic_in_cache(optimistic.clone())?;
         let block_id = optimistic.get_block_id().clone();
@@ -1825,13 +2149,14 @@ impl Repository for RepositoryImpl {
             saved_states.entry(thread_id).or_default().insert(block_seq_no, block_id.clone());
             tracing::trace!("repo saved_states={:?}", saved_states);
         }
+        self.remove_expired_saved_states(&thread_id)?;
         Ok(())
     }
 
     fn get_latest_block_id_with_producer_group_change(
         &self,
         _thread_id: &ThreadIdentifier,
-    ) -> anyhow::Result<BlockIdentifier> {
+    ) -> RepositoryResult<BlockIdentifier> {
         Ok(BlockIdentifier::default())
     }
 
@@ -1901,6 +2226,8 @@ pub mod tests {
     use super::BkSetUpdate;
     use super::RepositoryImpl;
     use super::OID;
+    use crate::config::StateSaveParams;
+    use crate::config::StateSavePolicy;
     use crate::helper::metrics;
     use crate::multithreading::routing::service::RoutingService;
     use crate::node::shared_services::SharedServices;
@@ -1939,6 +2266,14 @@ pub mod tests {
         Arc::new(Mutex::new(FinalizedBlockStorage::new(100)))
     }
 
+    pub fn state_save_policy() -> StateSavePolicy {
+        StateSavePolicy::new(StateSaveParams {
+            save_state_frequency: 1,
+            state_cache_size: 1,
+            saved_states_retention: 0,
+        })
+    }
+
     fn mock_bk_set_updates_tx() -> InstrumentedSender<BkSetUpdate> {
         let (bk_set_updates_tx, _bk_set_updates_rx) = instrumented_channel::<BkSetUpdate>(
             None::<metrics::BlockProductionMetrics>,
@@ -1953,14 +2288,17 @@ pub mod tests {
 
         let block_state_repository =
             BlockStateRepository::test(PathBuf::from("./tests-data/test_save_load/block-state"));
-        let accounts_repository =
-            AccountsRepository::new(PathBuf::from("./tests-data/test_save_load"), Some(0), 1);
+        let accounts_repository = AccountsRepository::new(
+            PathBuf::from("./tests-data/test_save_load"),
+            Some(0),
+            state_save_policy(),
+        );
         let message_db = MessageDurableStorage::as_noop();
         let finalized_blocks = finalized_blocks_storage();
         let repository = RepositoryImpl::new(
             PathBuf::from("./tests-data/test_save_load"),
             Some(PathBuf::from(ZEROSTATE)),
-            1,
+            state_save_policy(),
             SharedServices::test_start(RoutingService::stub().0, u32::MAX),
             Arc::new(Mutex::new(FixedSizeHashSet::new(10))),
             false,
@@ -1991,14 +2329,14 @@ pub mod tests {
         let accounts_repository = AccountsRepository::new(
             PathBuf::from("/home/user/GOSH/acki-nacki/server_data/node1/"),
             Some(0),
-            1,
+            state_save_policy(),
         );
         let message_db = MessageDurableStorage::as_noop();
         let finalized_blocks = finalized_blocks_storage();
         let _repository = RepositoryImpl::new(
             PathBuf::from("/home/user/GOSH/acki-nacki/server_data/node1/"),
             Some(PathBuf::from(ZEROSTATE)),
-            1,
+            state_save_policy(),
             SharedServices::test_start(RoutingService::stub().0, u32::MAX),
             Arc::new(Mutex::new(FixedSizeHashSet::new(10))),
             false,
@@ -2018,15 +2356,18 @@ pub mod tests {
         START.call_once(init_tests);
         let block_state_repository =
             BlockStateRepository::test(PathBuf::from("./tests-data/test_exists/block-state"));
-        let accounts_repository =
-            AccountsRepository::new(PathBuf::from("./tests-data/test_exists"), Some(0), 1);
+        let accounts_repository = AccountsRepository::new(
+            PathBuf::from("./tests-data/test_exists"),
+            Some(0),
+            state_save_policy(),
+        );
         let message_db = MessageDurableStorage::as_noop();
         let finalized_blocks = finalized_blocks_storage();
 
         let repository = RepositoryImpl::new(
             PathBuf::from("./tests-data/test_exists"),
             Some(PathBuf::from(ZEROSTATE)),
-            1,
+            state_save_policy(),
             SharedServices::test_start(RoutingService::stub().0, u32::MAX),
             Arc::new(Mutex::new(FixedSizeHashSet::new(10))),
             false,
@@ -2053,15 +2394,18 @@ pub mod tests {
         START.call_once(init_tests);
         let block_state_repository =
             BlockStateRepository::test(PathBuf::from("./tests-data/test_remove/block-state"));
-        let accounts_repository =
-            AccountsRepository::new(PathBuf::from("./tests-data/test_remove"), Some(0), 1);
+        let accounts_repository = AccountsRepository::new(
+            PathBuf::from("./tests-data/test_remove"),
+            Some(0),
+            state_save_policy(),
+        );
         let message_db = MessageDurableStorage::as_noop();
         let finalized_blocks = finalized_blocks_storage();
 
         let repository = RepositoryImpl::new(
             PathBuf::from("./tests-data/test_remove"),
             Some(PathBuf::from(ZEROSTATE)),
-            1,
+            state_save_policy(),
             SharedServices::test_start(RoutingService::stub().0, u32::MAX),
             Arc::new(Mutex::new(FixedSizeHashSet::new(10))),
             false,
@@ -0,0 +1,108 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::path::Path;
+
+use bincode::Options;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::optimistic_state::OptimisticStateImpl;
+use super::repository_impl::ThreadSnapshot;
+use crate::bls::envelope::BLSSignedEnvelope;
+use crate::services::blob_sync::external_fileshares_based::share_blob::share_blob;
+use crate::types::BlockIdentifier;
+use crate::types::BlockSeqNo;
+use crate::types::ThreadIdentifier;
+
+/// Serialization of the optimistic state found in an imported snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotFormat {
+    /// Written by this node version
+    Current,
+    /// Written by the node versions without the deferred messages queue
+    NoDeferredMessages,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportedSnapshot {
+    pub format: SnapshotFormat,
+    pub thread_id: ThreadIdentifier,
+    pub block_id: BlockIdentifier,
+    pub block_seq_no: BlockSeqNo,
+}
+
+// Same encoding as `bincode::serialize`, but a layout that doesn't consume
+// the whole buffer is rejected.
+fn decode_exact<T: DeserializeOwned>(data: &[u8]) -> bincode::Result<T> {
+    bincode::DefaultOptions::new().with_fixint_encoding().reject_trailing_bytes().deserialize(data)
+}
+
+/// Decodes the optimistic state of any known format.
+fn decode_optimistic_state(data: &[u8]) -> anyhow::Result<(SnapshotFormat, OptimisticStateImpl)> {
+    let (state, extended) = OptimisticStateImpl::deserialize_extended(data)
+        .map_err(|e| anyhow::format_err!("Unknown optimistic state format: {e}"))?;
+    let format =
+        if extended { SnapshotFormat::Current } else { SnapshotFormat::NoDeferredMessages };
+    Ok((format, state))
+}
+
+/// Converts a thread snapshot written by another node version (or fetched by
+/// the block manager, which keeps the snapshots as served by the nodes) into
+/// the current layout.
+pub fn convert_snapshot(data: &[u8]) -> anyhow::Result<(ImportedSnapshot, Vec<u8>)> {
+    let mut snapshot: ThreadSnapshot = decode_exact(data)
+        .map_err(|e| anyhow::format_err!("Failed to deserialize snapshot: {e}"))?;
+    let (format, state) = decode_optimistic_state(snapshot.optimistic_state())?;
+    let finalized_block_id = snapshot.finalized_block().data().identifier();
+    anyhow::ensure!(
+        finalized_block_id == state.block_id,
+        "Snapshot state {:?} doesn't match its finalized block {finalized_block_id:?}",
+        state.block_id
+    );
+    let imported = ImportedSnapshot {
+        format,
+        thread_id: state.thread_id,
+        block_id: state.block_id.clone(),
+        block_seq_no: state.block_seq_no,
+    };
+    if format == SnapshotFormat::Current {
+        return Ok((imported, data.to_vec()));
+    }
+    snapshot.set_optimistic_state(state.serialize_extended()?);
+    Ok((imported, bincode::serialize(&snapshot)?))
+}
+
+/// Converts the snapshot file and shares it in `share_dir` the same way the
+/// node shares its own states, so the directory can be served as a static
+/// storage to seed new nodes.
+pub fn import_snapshot_file(input: &Path, share_dir: &Path) -> anyhow::Result<ImportedSnapshot> {
+    let data = std::fs::read(input)
+        .map_err(|e| anyhow::format_err!("Failed to read snapshot {}: {e}", input.display()))?;
+    let (imported, converted) = convert_snapshot(&data)?;
+    share_blob(share_dir, &imported.block_id.to_string(), &mut converted.as_slice())?;
+    tracing::info!("Imported snapshot {imported:?} into {}", share_dir.display());
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_deferred_messages_state_is_converted() {
+        let state = OptimisticStateImpl::zero();
+
+        // Layout written without the extension
+        let (format, _) = decode_optimistic_state(&bincode::serialize(&state).unwrap()).unwrap();
+        assert_eq!(format, SnapshotFormat::NoDeferredMessages);
+
+        let (format, converted) =
+            decode_optimistic_state(&state.serialize_extended().unwrap()).unwrap();
+        assert_eq!(format, SnapshotFormat::Current);
+        assert_eq!(converted.block_id, state.block_id);
+
+        assert!(decode_optimistic_state(&[1, 2, 3]).is_err());
+    }
+}
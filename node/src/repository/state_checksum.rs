@@ -0,0 +1,324 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::str::FromStr;
+use std::time::Duration;
+use std::time::Instant;
+
+use chitchat::ChitchatRef;
+use network::resolver::sign_gossip_node;
+use network::resolver::GossipPeer;
+use network::resolver::VOLATILE_KEY_PREFIX;
+use sha2::Digest;
+use sha2::Sha256;
+use tvm_types::UInt256;
+
+use crate::config::StateChecksumConfig;
+use crate::helper::metrics::BlockProductionMetrics;
+use crate::helper::SHUTDOWN_FLAG;
+use crate::node::NodeIdentifier;
+use crate::repository::optimistic_state::OptimisticState;
+use crate::repository::optimistic_state::OptimisticStateImpl;
+use crate::repository::repository_impl::RepositoryImpl;
+use crate::types::BlockIdentifier;
+use crate::types::BlockSeqNo;
+use crate::types::ThreadIdentifier;
+
+const STATE_CHECKSUM_KEY_PREFIX: &str = "state_checksum:";
+// Period of checking for the shutdown flag
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Checksum of the thread state at the block.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StateChecksum {
+    pub thread_id: ThreadIdentifier,
+    pub block_seq_no: BlockSeqNo,
+    pub block_id: BlockIdentifier,
+    pub checksum: UInt256,
+}
+
+impl StateChecksum {
+    fn gossip_key(thread_id: &ThreadIdentifier) -> String {
+        format!("{VOLATILE_KEY_PREFIX}{STATE_CHECKSUM_KEY_PREFIX}{thread_id:x}")
+    }
+
+    fn gossip_value(&self) -> String {
+        format!("{}:{}:{}", self.block_seq_no, self.block_id, self.checksum.to_hex_string())
+    }
+
+    fn try_from_gossip(key: &str, value: &str) -> Option<Self> {
+        let thread_id = key
+            .strip_prefix(VOLATILE_KEY_PREFIX)?
+            .strip_prefix(STATE_CHECKSUM_KEY_PREFIX)?
+            .to_string()
+            .try_into()
+            .ok()?;
+        let mut parts = value.split(':');
+        let block_seq_no = BlockSeqNo::from(parts.next()?.parse::<u32>().ok()?);
+        let block_id = BlockIdentifier::from_str(parts.next()?).ok()?;
+        let checksum = UInt256::from_str(parts.next()?).ok()?;
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Self { thread_id, block_seq_no, block_id, checksum })
+    }
+}
+
+/// Checksums of the same block state that don't match.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StateDivergence {
+    pub thread_id: ThreadIdentifier,
+    pub block_seq_no: BlockSeqNo,
+    pub block_id: BlockIdentifier,
+    // node id -> checksum
+    pub checksums: Vec<(String, UInt256)>,
+}
+
+/// Compares own checksums with the checksums published by peers. Own
+/// checksums are kept for `history_size` latest blocks per thread, so
+/// lagging peers are compared with older own states.
+pub struct StateDivergenceDetector {
+    node_id: String,
+    history_size: usize,
+    own_checksums: HashMap<ThreadIdentifier, VecDeque<StateChecksum>>,
+    reported: HashSet<BlockIdentifier>,
+}
+
+impl StateDivergenceDetector {
+    pub fn new(node_id: String, history_size: usize) -> Self {
+        Self {
+            node_id,
+            history_size: history_size.max(1),
+            own_checksums: HashMap::new(),
+            reported: HashSet::new(),
+        }
+    }
+
+    pub fn add_own(&mut self, checksum: StateChecksum) {
+        let history = self.own_checksums.entry(checksum.thread_id).or_default();
+        if history.back().is_some_and(|last| last.block_id == checksum.block_id) {
+            return;
+        }
+        history.push_back(checksum);
+        while history.len() > self.history_size {
+            history.pop_front();
+        }
+    }
+
+    /// Returns divergences that were not reported before. Peer checksums of
+    /// blocks that are unknown to this node are compared among themselves.
+    pub fn check(&mut self, peer_checksums: &[(String, StateChecksum)]) -> Vec<StateDivergence> {
+        let mut by_block = BTreeMap::<(BlockSeqNo, BlockIdentifier), Vec<_>>::new();
+        let own = self.own_checksums.values().flatten().map(|checksum| (&self.node_id, checksum));
+        let peers = peer_checksums.iter().map(|(node_id, checksum)| (node_id, checksum));
+        for (node_id, checksum) in own.chain(peers) {
+            by_block
+                .entry((checksum.block_seq_no, checksum.block_id.clone()))
+                .or_default()
+                .push((node_id, checksum));
+        }
+        // Forget blocks that are not published anymore
+        self.reported.retain(|block_id| by_block.keys().any(|(_, id)| id == block_id));
+
+        let mut divergences = vec![];
+        for ((block_seq_no, block_id), checksums) in by_block {
+            let distinct = checksums.iter().map(|(_, c)| &c.checksum).collect::<HashSet<_>>();
+            if distinct.len() < 2 || !self.reported.insert(block_id.clone()) {
+                continue;
+            }
+            divergences.push(StateDivergence {
+                thread_id: checksums[0].1.thread_id,
+                block_seq_no,
+                block_id,
+                checksums: checksums
+                    .into_iter()
+                    .map(|(node_id, c)| (node_id.clone(), c.checksum.clone()))
+                    .collect(),
+            });
+        }
+        divergences
+    }
+}
+
+/// Hash of the thread accounts (id, last transaction hash and lt) in the
+/// dictionary order. Unlike the shard state root hash it doesn't depend on
+/// whether the accounts are stored in the state or externally.
+pub fn accounts_checksum(state: &OptimisticStateImpl) -> anyhow::Result<UInt256> {
+    let mut hasher = Sha256::new();
+    state
+        .get_shard_state()
+        .read_accounts()
+        .map_err(|e| anyhow::format_err!("Failed to read shard state accounts: {e}"))?
+        .iterate_accounts(|account_id, account, _| {
+            hasher.update(account_id.as_slice());
+            hasher.update(account.last_trans_hash().as_slice());
+            hasher.update(account.last_trans_lt().to_be_bytes());
+            Ok(true)
+        })
+        .map_err(|e| anyhow::format_err!("Failed to iterate shard state accounts: {e}"))?;
+    Ok(UInt256::from_slice(&hasher.finalize()))
+}
+
+impl RepositoryImpl {
+    /// Checksum of the latest saved state that is finalized.
+    pub fn last_finalized_state_checksum(
+        &self,
+        thread_id: &ThreadIdentifier,
+        known: Option<&StateChecksum>,
+    ) -> anyhow::Result<Option<StateChecksum>> {
+        let Some((block_seq_no, block_id)) = self.last_finalized_saved_state(thread_id)? else {
+            return Ok(None);
+        };
+        if let Some(known) = known.filter(|known| known.block_id == block_id) {
+            return Ok(Some(known.clone()));
+        }
+        let path = self.get_optimistic_state_dir_path().join(block_id.to_string());
+        let state = OptimisticStateImpl::load_from_file(&path)?;
+        let checksum = accounts_checksum(&state)?;
+        Ok(Some(StateChecksum { thread_id: *thread_id, block_seq_no, block_id, checksum }))
+    }
+}
+
+/// Publishes checksums of the finalized states via gossip and reports
+/// divergences with the checksums of other nodes. The node state is signed
+/// again after a checksum is published, so peers verify the checksums as
+/// any other node state key.
+pub fn start_state_checksum_service(
+    repository: RepositoryImpl,
+    chitchat: ChitchatRef,
+    node_id: NodeIdentifier,
+    signing_key: Option<transport_layer::SigningKey>,
+    config: StateChecksumConfig,
+    metrics: Option<BlockProductionMetrics>,
+) -> anyhow::Result<()> {
+    let Some(interval) = config.interval_sec.map(Duration::from_secs) else {
+        tracing::info!("State checksum service is disabled");
+        return Ok(());
+    };
+    let node_id = node_id.to_string();
+    let mut detector = StateDivergenceDetector::new(node_id.clone(), config.history_size);
+    let mut published = HashMap::<ThreadIdentifier, StateChecksum>::new();
+    let mut last_check: Option<Instant> = None;
+    loop {
+        if SHUTDOWN_FLAG.get() == Some(&true) {
+            return Ok(());
+        }
+        if last_check.is_some_and(|last_check| last_check.elapsed() < interval) {
+            std::thread::sleep(SHUTDOWN_POLL_INTERVAL);
+            continue;
+        }
+        last_check = Some(Instant::now());
+
+        for thread_id in repository.threads_with_saved_states() {
+            match repository.last_finalized_state_checksum(&thread_id, published.get(&thread_id)) {
+                Ok(Some(checksum)) => {
+                    if published.get(&thread_id) != Some(&checksum) {
+                        tracing::trace!("Publish state checksum: {checksum:?}");
+                        let mut gossip = chitchat.lock();
+                        let node_state = gossip.self_node_state();
+                        node_state
+                            .set(StateChecksum::gossip_key(&thread_id), checksum.gossip_value());
+                        if let Some(key) = &signing_key {
+                            sign_gossip_node(node_state, key.clone());
+                        }
+                        drop(gossip);
+                        detector.add_own(checksum.clone());
+                        published.insert(thread_id, checksum);
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::warn!("Failed to calculate state checksum of {thread_id:?}: {e}")
+                }
+            }
+        }
+
+        let peer_checksums = collect_peer_checksums(&chitchat, &node_id);
+        for divergence in detector.check(&peer_checksums) {
+            tracing::error!(
+                "State divergence detected: thread {:?}, block {:?} {:?}, checksums: {:?}",
+                divergence.thread_id,
+                divergence.block_seq_no,
+                divergence.block_id,
+                divergence.checksums,
+            );
+            metrics.as_ref().inspect(|m| m.report_state_divergence(&divergence.thread_id));
+        }
+    }
+}
+
+fn collect_peer_checksums(chitchat: &ChitchatRef, node_id: &str) -> Vec<(String, StateChecksum)> {
+    let snapshot = chitchat.lock().state_snapshot();
+    let mut checksums = vec![];
+    for node_state in &snapshot.node_states {
+        let Some(peer) = GossipPeer::<NodeIdentifier>::try_get_from(node_state) else {
+            continue;
+        };
+        let peer_id = peer.id.to_string();
+        if peer_id == node_id {
+            continue;
+        }
+        for (key, value) in node_state.key_values() {
+            if let Some(checksum) = StateChecksum::try_from_gossip(key, value) {
+                checksums.push((peer_id.clone(), checksum));
+            }
+        }
+    }
+    checksums
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checksum(seq_no: u32, block: u8, value: u8) -> StateChecksum {
+        StateChecksum {
+            thread_id: ThreadIdentifier::default(),
+            block_seq_no: BlockSeqNo::from(seq_no),
+            block_id: BlockIdentifier::from([block; 32]),
+            checksum: UInt256::from([value; 32]),
+        }
+    }
+
+    #[test]
+    fn test_gossip_encoding() {
+        let expected = checksum(10, 1, 2);
+        let key = StateChecksum::gossip_key(&expected.thread_id);
+        assert!(key.starts_with(VOLATILE_KEY_PREFIX));
+        let actual = StateChecksum::try_from_gossip(&key, &expected.gossip_value());
+        assert_eq!(actual, Some(expected));
+        assert_eq!(StateChecksum::try_from_gossip("node_id", "10"), None);
+    }
+
+    #[test]
+    fn test_divergence_detection() {
+        let mut detector = StateDivergenceDetector::new("own".to_string(), 2);
+        detector.add_own(checksum(10, 1, 1));
+        detector.add_own(checksum(20, 2, 2));
+
+        let peers = vec![
+            ("a".to_string(), checksum(10, 1, 1)),
+            ("b".to_string(), checksum(20, 2, 2)),
+            // Block is unknown to this node, peers agree
+            ("c".to_string(), checksum(30, 3, 3)),
+            ("d".to_string(), checksum(30, 3, 3)),
+        ];
+        assert!(detector.check(&peers).is_empty());
+
+        let peers = vec![("a".to_string(), checksum(10, 1, 5))];
+        let divergences = detector.check(&peers);
+        assert_eq!(divergences.len(), 1);
+        assert_eq!(divergences[0].block_id, BlockIdentifier::from([1; 32]));
+        assert_eq!(divergences[0].checksums.len(), 2);
+        // Reported once
+        assert!(detector.check(&peers).is_empty());
+
+        // Own checksum of the block is evicted from the history
+        detector.add_own(checksum(30, 3, 3));
+        assert!(detector.check(&peers).is_empty());
+    }
+}
@@ -0,0 +1,217 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use http_server::NodeStatusBoard;
+use parking_lot::Mutex;
+
+use crate::bls::envelope::BLSSignedEnvelope;
+use crate::config::StorageQuotaConfig;
+use crate::helper::metrics::BlockProductionMetrics;
+use crate::helper::SHUTDOWN_FLAG;
+use crate::message::identifier::MessageIdentifier;
+use crate::message::WrappedMessage;
+use crate::repository::integrity_audit::list_stored_entries;
+use crate::repository::repository_impl::RepositoryImpl;
+use crate::types::AccountAddress;
+use crate::types::BlockIdentifier;
+use crate::types::ThreadIdentifier;
+
+// Period of checking for the shutdown flag
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ThreadStorageUsage {
+    /// Saved optimistic states
+    pub states: u64,
+    /// Stored block envelopes
+    pub blocks: u64,
+    /// Internal messages written to the durable storage since the node start
+    pub messages: u64,
+}
+
+impl ThreadStorageUsage {
+    pub fn total(&self) -> u64 {
+        self.states.saturating_add(self.blocks).saturating_add(self.messages)
+    }
+}
+
+/// Disk usage attributed to the threads and the saved states retention of the
+/// threads exceeding the quota.
+#[derive(Clone, Default)]
+pub struct StorageUsage {
+    inner: Arc<Mutex<StorageUsageInner>>,
+}
+
+#[derive(Default)]
+struct StorageUsageInner {
+    messages: HashMap<ThreadIdentifier, u64>,
+    // Block files are attributed to the threads once, they are never modified
+    block_files: HashMap<BlockIdentifier, (ThreadIdentifier, u64)>,
+    tightened_retention: HashMap<ThreadIdentifier, usize>,
+}
+
+impl StorageUsage {
+    pub fn report_messages_written(
+        &self,
+        thread_id: &ThreadIdentifier,
+        messages: &HashMap<AccountAddress, Vec<(MessageIdentifier, Arc<WrappedMessage>)>>,
+    ) {
+        let size = messages
+            .values()
+            .flatten()
+            .map(|(_, message)| bincode::serialized_size(message.as_ref()).unwrap_or_default())
+            .sum::<u64>();
+        *self.inner.lock().messages.entry(*thread_id).or_default() += size;
+    }
+
+    /// Retention of the thread saved states, zero keeps all.
+    pub fn effective_retention(&self, thread_id: &ThreadIdentifier, retention: usize) -> usize {
+        match self.inner.lock().tightened_retention.get(thread_id) {
+            Some(tightened) if retention == 0 => *tightened,
+            Some(tightened) => retention.min(*tightened),
+            None => retention,
+        }
+    }
+
+    /// Returns false if the retention was already tightened.
+    pub fn tighten_retention(&self, thread_id: &ThreadIdentifier, retention: usize) -> bool {
+        self.inner.lock().tightened_retention.insert(*thread_id, retention.max(1)).is_none()
+    }
+
+    /// Returns false if the retention was not tightened.
+    pub fn restore_retention(&self, thread_id: &ThreadIdentifier) -> bool {
+        self.inner.lock().tightened_retention.remove(thread_id).is_some()
+    }
+}
+
+impl RepositoryImpl {
+    pub fn measure_storage_usage(&self) -> HashMap<ThreadIdentifier, ThreadStorageUsage> {
+        let mut usage = HashMap::<ThreadIdentifier, ThreadStorageUsage>::new();
+
+        let states_dir = self.get_optimistic_state_dir_path();
+        for (thread_id, block_ids) in self.saved_state_ids() {
+            usage.entry(thread_id).or_default().states = block_ids
+                .iter()
+                .map(|block_id| file_size(&states_dir.join(block_id.to_string())))
+                .sum();
+        }
+
+        let blocks_dir = self.get_blocks_dir_path();
+        let cached = std::mem::take(&mut self.storage_usage().inner.lock().block_files);
+        let mut block_files = HashMap::new();
+        for (path, block_id) in list_stored_entries(&blocks_dir) {
+            let entry = cached.get(&block_id).copied().or_else(|| {
+                let block = RepositoryImpl::load_block(&blocks_dir, &block_id).ok().flatten()?;
+                Some((block.data().get_common_section().thread_id, file_size(&path)))
+            });
+            if let Some((thread_id, size)) = entry {
+                usage.entry(thread_id).or_default().blocks += size;
+                block_files.insert(block_id, (thread_id, size));
+            }
+        }
+
+        let mut inner = self.storage_usage().inner.lock();
+        // Removed files are dropped from the cache
+        inner.block_files = block_files;
+        for (thread_id, size) in &inner.messages {
+            usage.entry(*thread_id).or_default().messages = *size;
+        }
+        usage
+    }
+}
+
+fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|metadata| metadata.len()).unwrap_or_default()
+}
+
+/// Measures the disk usage of the threads, alerts about threads exceeding the
+/// quota and tightens their saved states retention (if configured).
+pub fn start_storage_quota_service(
+    repository: RepositoryImpl,
+    config: StorageQuotaConfig,
+    metrics: Option<BlockProductionMetrics>,
+    node_status: NodeStatusBoard,
+) -> anyhow::Result<()> {
+    let Some(quota) = config.thread_quota_bytes else {
+        tracing::info!("Storage quota service is disabled");
+        return Ok(());
+    };
+    let interval = Duration::from_secs(config.check_interval_sec);
+    let mut last_check: Option<Instant> = None;
+    loop {
+        if SHUTDOWN_FLAG.get() == Some(&true) {
+            return Ok(());
+        }
+        if last_check.is_some_and(|last_check| last_check.elapsed() < interval) {
+            std::thread::sleep(SHUTDOWN_POLL_INTERVAL);
+            continue;
+        }
+        last_check = Some(Instant::now());
+
+        for (thread_id, usage) in repository.measure_storage_usage() {
+            metrics.as_ref().inspect(|m| {
+                m.report_thread_storage_usage(&thread_id, "states", usage.states);
+                m.report_thread_storage_usage(&thread_id, "blocks", usage.blocks);
+                m.report_thread_storage_usage(&thread_id, "messages", usage.messages);
+            });
+            node_status
+                .set_storage_quota_exceeded(&format!("{thread_id:x}"), usage.total() > quota);
+            if usage.total() <= quota {
+                if repository.storage_usage().restore_retention(&thread_id) {
+                    tracing::info!(
+                        "Thread {thread_id:?} storage usage is below the quota, saved states retention is restored"
+                    );
+                }
+                continue;
+            }
+            tracing::warn!(
+                "Thread {thread_id:?} storage usage {} bytes exceeds the quota {quota}: states {}, blocks {}, messages {}",
+                usage.total(),
+                usage.states,
+                usage.blocks,
+                usage.messages
+            );
+            metrics.as_ref().inspect(|m| m.report_thread_storage_quota_exceeded(&thread_id));
+            let Some(retention) = config.tightened_states_retention else {
+                continue;
+            };
+            if repository.storage_usage().tighten_retention(&thread_id, retention) {
+                tracing::warn!(
+                    "Saved states retention of thread {thread_id:?} is tightened to {retention}"
+                );
+            }
+            if let Err(e) = repository.remove_expired_saved_states(&thread_id) {
+                tracing::warn!("Failed to remove expired saved states of {thread_id:?}: {e}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tightened_retention() {
+        let usage = StorageUsage::default();
+        let thread_id = ThreadIdentifier::default();
+        assert_eq!(usage.effective_retention(&thread_id, 0), 0);
+        assert_eq!(usage.effective_retention(&thread_id, 10), 10);
+
+        assert!(usage.tighten_retention(&thread_id, 3));
+        assert!(!usage.tighten_retention(&thread_id, 3));
+        assert_eq!(usage.effective_retention(&thread_id, 0), 3);
+        assert_eq!(usage.effective_retention(&thread_id, 10), 3);
+        assert_eq!(usage.effective_retention(&thread_id, 2), 2);
+
+        assert!(usage.restore_retention(&thread_id));
+        assert!(!usage.restore_retention(&thread_id));
+        assert_eq!(usage.effective_retention(&thread_id, 0), 0);
+    }
+}
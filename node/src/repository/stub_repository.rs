@@ -33,6 +33,7 @@ use crate::repository::dapp_id_table::DAppIdTableChangeSet;
 use crate::repository::optimistic_state::OptimisticState;
 use crate::repository::CrossThreadRefData;
 use crate::repository::Repository;
+use crate::repository::RepositoryResult;
 #[cfg(test)]
 use crate::storage::MessageDBWriterService;
 use crate::storage::MessageDurableStorage;
@@ -245,14 +246,14 @@ impl Repository for RepositoryStub {
 
     fn load_sent_attestations(
         &self,
-    ) -> anyhow::Result<HashMap<ThreadIdentifier, Vec<(BlockSeqNo, Self::Attestation)>>> {
+    ) -> RepositoryResult<HashMap<ThreadIdentifier, Vec<(BlockSeqNo, Self::Attestation)>>> {
         todo!()
     }
 
     fn get_finalized_block(
         &self,
         _identifier: &BlockIdentifier,
-    ) -> anyhow::Result<Option<Arc<Self::CandidateBlock>>> {
+    ) -> RepositoryResult<Option<Arc<Self::CandidateBlock>>> {
         todo!();
     }
 
@@ -260,7 +261,7 @@ impl Repository for RepositoryStub {
         &self,
         _block_id: &BlockIdentifier,
         _thread_id: &ThreadIdentifier,
-    ) -> anyhow::Result<Arc<<Self as Repository>::CandidateBlock>> {
+    ) -> RepositoryResult<Arc<<Self as Repository>::CandidateBlock>> {
         todo!()
     }
 
@@ -268,18 +269,18 @@ impl Repository for RepositoryStub {
         &mut self,
         _thread_id: &ThreadIdentifier,
         _parent_block_id: &BlockIdentifier,
-    ) -> anyhow::Result<()> {
+    ) -> RepositoryResult<()> {
         todo!();
     }
 
     fn select_thread_last_finalized_block(
         &self,
         _thread_id: &ThreadIdentifier,
-    ) -> anyhow::Result<Option<(BlockIdentifier, BlockSeqNo)>> {
+    ) -> RepositoryResult<Option<(BlockIdentifier, BlockSeqNo)>> {
         todo!();
     }
 
-    fn is_block_suspicious(&self, _block_id: &BlockIdentifier) -> anyhow::Result<Option<bool>> {
+    fn is_block_suspicious(&self, _block_id: &BlockIdentifier) -> RepositoryResult<Option<bool>> {
         todo!()
     }
 
@@ -288,7 +289,7 @@ impl Repository for RepositoryStub {
         _block: impl Borrow<Self::CandidateBlock>,
         _block_state: BlockState,
         _state_sync_service: Option<Arc<impl StateSyncService<Repository = RepositoryImpl>>>,
-    ) -> anyhow::Result<()> {
+    ) -> RepositoryResult<()> {
         Ok(())
     }
 
@@ -301,7 +302,7 @@ impl Repository for RepositoryStub {
         block_id: &BlockIdentifier,
         _thread_id: &ThreadIdentifier,
         _min_seq_no: Option<Arc<OptimisticStateStub>>,
-    ) -> anyhow::Result<Option<Arc<OptimisticStateStub>>> {
+    ) -> RepositoryResult<Option<Arc<OptimisticStateStub>>> {
         Ok(self.optimistic_state.get(block_id).map(|s| Arc::new(s.to_owned())))
     }
 
@@ -310,7 +311,7 @@ impl Repository for RepositoryStub {
         block_id: &BlockIdentifier,
         _thread_id: &ThreadIdentifier,
         _min_state: Option<Arc<Self::OptimisticState>>,
-    ) -> anyhow::Result<Option<Arc<Self::OptimisticState>>> {
+    ) -> RepositoryResult<Option<Arc<Self::OptimisticState>>> {
         Ok(self.optimistic_state.get(block_id).map(|s| Arc::new(s.to_owned())))
     }
 
@@ -318,7 +319,7 @@ impl Repository for RepositoryStub {
         &self,
         _block_id: &BlockIdentifier,
         _thread_id: &ThreadIdentifier,
-    ) -> anyhow::Result<()> {
+    ) -> RepositoryResult<()> {
         todo!()
     }
 
@@ -326,7 +327,7 @@ impl Repository for RepositoryStub {
     // anyhow::Result<()> { todo!()
     // }
 
-    fn is_block_already_applied(&self, _block_id: &BlockIdentifier) -> anyhow::Result<bool> {
+    fn is_block_already_applied(&self, _block_id: &BlockIdentifier) -> RepositoryResult<bool> {
         todo!()
     }
 
@@ -335,14 +336,14 @@ impl Repository for RepositoryStub {
         _snapshot: Self::StateSnapshot,
         _thread_id: &ThreadIdentifier,
         _skipped_attestation_ids: Arc<Mutex<HashSet<BlockIdentifier>>>,
-    ) -> anyhow::Result<()> {
+    ) -> RepositoryResult<()> {
         todo!()
     }
 
     fn sync_accounts_from_state(
         &mut self,
         _shard_state: Arc<ShardStateUnsplit>,
-    ) -> anyhow::Result<()> {
+    ) -> RepositoryResult<()> {
         todo!()
     }
 
@@ -350,28 +351,28 @@ impl Repository for RepositoryStub {
         &self,
         _block_id: BlockIdentifier,
         _accounts: HashMap<String, SerializedItem>,
-    ) -> anyhow::Result<()> {
+    ) -> RepositoryResult<()> {
         todo!()
     }
 
     fn store_optimistic<T: Into<Arc<Self::OptimisticState>>>(
         &self,
         _state: T,
-    ) -> anyhow::Result<()> {
+    ) -> RepositoryResult<()> {
         todo!()
     }
 
     fn store_optimistic_in_cache<T: Into<Arc<Self::OptimisticState>>>(
         &self,
         _state: T,
-    ) -> anyhow::Result<()> {
+    ) -> RepositoryResult<()> {
         todo!()
     }
 
     fn get_latest_block_id_with_producer_group_change(
         &self,
         _thread_id: &ThreadIdentifier,
-    ) -> anyhow::Result<BlockIdentifier> {
+    ) -> RepositoryResult<BlockIdentifier> {
         todo!()
     }
 
@@ -379,14 +380,14 @@ impl Repository for RepositoryStub {
         &self,
         _starting_block_id: &BlockSeqNo,
         _thread_id: &ThreadIdentifier,
-    ) -> anyhow::Result<()> {
+    ) -> RepositoryResult<()> {
         todo!()
     }
 
     fn get_zero_state_for_thread(
         &self,
         _thread_id: &ThreadIdentifier,
-    ) -> anyhow::Result<Arc<Self::OptimisticState>> {
+    ) -> RepositoryResult<Arc<Self::OptimisticState>> {
         todo!()
     }
 
@@ -2,35 +2,41 @@
 //
 
 use std::path::Path;
-use std::path::PathBuf;
 
+use sha2::Digest;
+use sha2::Sha256;
+
+use super::share_blob::chunk_file_name;
+use super::share_blob::commit_blob;
+use super::share_blob::is_shared;
+use super::share_blob::manifest_file_name;
+use super::share_blob::read_staged_chunk;
+use super::share_blob::sha256_hex;
+use super::share_blob::share_blob;
+use super::share_blob::stage_chunk;
+use super::share_blob::staging_dir;
+use super::share_blob::BlobManifest;
 use crate::helper::get_temp_file_path;
 
 const CONNECT_TIMEOUT: Option<std::time::Duration> = Some(std::time::Duration::from_secs(3));
 
+/// Downloads the blob from one of the external shares and shares it locally.
+/// Chunks are verified against the manifest and kept between attempts, so a
+/// retry downloads only the missing ones.
 pub fn download_blob(
-    share_full_path: &PathBuf,
-    tmp_dir_path: &Path,
+    share_dir: &Path,
+    resource_id: &str,
     urls: &[url::Url],
     max_tries: u8,
     retry_timeout: Option<std::time::Duration>,
     deadline: Option<std::time::Instant>,
 ) -> anyhow::Result<()> {
-    if share_full_path.exists() {
+    if is_shared(share_dir, resource_id) {
         return Ok(());
     }
-    // TODO: must be guarded code here and in the share blob fn.
-
-    let tmp_file_path = get_temp_file_path(tmp_dir_path);
-    tracing::trace!("download_blob: trying to create file: {tmp_file_path:?}");
-    if let Some(parent) = tmp_file_path.parent() {
-        if !parent.exists() {
-            tracing::trace!("download_blob: trying create to parent dir: {parent:?}");
-            std::fs::create_dir_all(parent).expect("Failed to create dir for shared state");
-        }
-    }
-    let mut file = std::fs::File::create(tmp_file_path.clone())?;
-    let mut is_downloaded = false;
+    let staging_dir = staging_dir(share_dir, resource_id);
+    tracing::trace!("download_blob: trying to create staging dir: {staging_dir:?}");
+    std::fs::create_dir_all(&staging_dir)?;
     for _ in 0..max_tries {
         for url in urls.iter() {
             if let Some(deadline) = deadline {
@@ -38,54 +44,104 @@ pub fn download_blob(
                     anyhow::bail!("Failed to download a blob: deadline.");
                 }
             }
-            match download_file(url, &mut file, deadline) {
-                Ok(()) => {
-                    is_downloaded = true;
-                    break;
-                }
+            match download_from_share(url, share_dir, resource_id, deadline) {
+                Ok(()) => return Ok(()),
                 Err(e) => {
                     tracing::error!("Download failed: {}", e);
-                    file.set_len(0)?;
-                    file.sync_all()?;
                 }
             }
         }
-        if is_downloaded {
-            break;
-        }
         if let Some(retry_timeout) = retry_timeout {
             std::thread::sleep(retry_timeout);
         }
     }
-    if !is_downloaded {
-        anyhow::bail!("Failed to download a blob: max tries");
+    anyhow::bail!("Failed to download a blob: max tries")
+}
+
+fn download_from_share(
+    share_url: &url::Url,
+    share_dir: &Path,
+    resource_id: &str,
+    deadline: Option<std::time::Instant>,
+) -> anyhow::Result<()> {
+    let client = client(deadline)?;
+    let Some(manifest) = fetch(&client, &share_url.join(&manifest_file_name(resource_id))?)? else {
+        return download_whole_blob(&client, share_url, share_dir, resource_id);
+    };
+    let manifest: BlobManifest = serde_json::from_slice(&manifest)?;
+    let staging_dir = staging_dir(share_dir, resource_id);
+    let mut hasher = Sha256::new();
+    let mut size = 0;
+    for (index, hash) in manifest.chunks.iter().enumerate() {
+        let chunk = match read_staged_chunk(&staging_dir, index, hash) {
+            Some(chunk) => chunk,
+            None => {
+                let chunk = fetch(&client, &share_url.join(&chunk_file_name(resource_id, index))?)?
+                    .ok_or_else(|| anyhow::format_err!("download blob: chunk {index} not found"))?;
+                if sha256_hex(&chunk) != *hash {
+                    anyhow::bail!("download blob: chunk {index} hash mismatch");
+                }
+                stage_chunk(&staging_dir, index, &chunk)?;
+                chunk
+            }
+        };
+        hasher.update(&chunk);
+        size += chunk.len() as u64;
+    }
+    if size != manifest.size || hex::encode(hasher.finalize()) != manifest.sha256 {
+        anyhow::bail!("download blob: blob does not match the manifest");
     }
-    tracing::trace!("download_blob: rename file: {tmp_file_path:?} -> {share_full_path:?}");
-    std::fs::rename(tmp_file_path, share_full_path)?;
+    commit_blob(share_dir, resource_id, &manifest)?;
+    tracing::trace!("Downloaded {resource_id} from {share_url}");
     Ok(())
 }
 
-fn download_file(
-    url: &url::Url,
-    file: &mut std::fs::File,
-    deadline: Option<std::time::Instant>,
+// Shares of the nodes that don't split blobs into chunks serve the whole blob
+fn download_whole_blob(
+    client: &reqwest::blocking::Client,
+    share_url: &url::Url,
+    share_dir: &Path,
+    resource_id: &str,
 ) -> anyhow::Result<()> {
+    let url = share_url.join(resource_id)?;
     tracing::trace!("Downloading {} ...", url);
-    let client: reqwest::blocking::Client = reqwest::blocking::Client::builder()
+    let mut response = client.get(url.clone()).send()?;
+    if !response.status().is_success() {
+        anyhow::bail!("download blob: Some error happened. Status: {:?}", response.status());
+    }
+    let tmp_file_path = get_temp_file_path(share_dir);
+    let result = (|| -> anyhow::Result<()> {
+        let mut file = std::fs::File::create(&tmp_file_path)?;
+        response.copy_to(&mut file)?;
+        file.sync_all()?;
+        share_blob(share_dir, resource_id, &mut std::fs::File::open(&tmp_file_path)?)
+    })();
+    let _ = std::fs::remove_file(tmp_file_path);
+    tracing::trace!("Downloaded {}", url);
+    result
+}
+
+fn client(deadline: Option<std::time::Instant>) -> anyhow::Result<reqwest::blocking::Client> {
+    Ok(reqwest::blocking::Client::builder()
         .timeout(
             deadline.map(|deadline| deadline.saturating_duration_since(std::time::Instant::now())),
         )
         .connect_timeout(CONNECT_TIMEOUT)
-        .build()?;
-    let mut response = client.get(url.clone()).send()?;
+        .build()?)
+}
+
+// Returns None if the share doesn't have the file
+fn fetch(client: &reqwest::blocking::Client, url: &url::Url) -> anyhow::Result<Option<Vec<u8>>> {
+    tracing::trace!("Downloading {} ...", url);
+    let response = client.get(url.clone()).send()?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
     if response.status().is_server_error() {
         anyhow::bail!("download blob: server error!");
     }
     if !response.status().is_success() {
         anyhow::bail!("download blob: Some error happened. Status: {:?}", response.status());
     }
-    response.copy_to(file)?;
-    file.sync_all()?;
-    tracing::trace!("Downloaded {}", url);
-    Ok(())
+    Ok(Some(response.bytes()?.to_vec()))
 }
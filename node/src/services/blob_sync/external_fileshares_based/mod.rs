@@ -14,7 +14,7 @@ use crate::helper::metrics::BlockProductionMetrics;
 
 mod download_blob;
 mod service_inner_loop;
-mod share_blob;
+pub(crate) mod share_blob;
 
 #[derive(TypedBuilder)]
 pub struct ExternalFileSharesBased {
@@ -8,6 +8,7 @@ use rand::thread_rng;
 use telemetry_utils::mpsc::InstrumentedReceiver;
 
 use super::download_blob::download_blob;
+use super::share_blob::open_blob;
 use super::share_blob::share_blob;
 use super::ResourceId;
 
@@ -49,10 +50,9 @@ pub(super) fn service_inner_loop(
                     .name(format!("share-{}", &resource_id))
                     .spawn_scoped(s, move || {
                         tracing::trace!("share_blob: sharing {:?}", &resource_id);
-                        let share_full_path = local_storage_share_base_path.join(resource_id);
                         let share_result = {
                             let stream = Box::leak(stream);
-                            share_blob(share_full_path, local_storage_share_base_path, stream)
+                            share_blob(local_storage_share_base_path, &resource_id, stream)
                         };
                         on_complete(share_result);
                     })
@@ -69,24 +69,21 @@ pub(super) fn service_inner_loop(
                     .name(format!("load-{}", &resource_id))
                     .spawn_scoped(s, move || {
                         tracing::trace!("share_blob: loading {:?}", &resource_id);
-                        let mut urls: Vec<url::Url> = known_external_file_shares
-                            .into_iter()
-                            .map(|e| e.join(&resource_id))
-                            .collect::<Result<Vec<_>, _>>()
-                            .expect("resource id must be usable as a url path");
+                        let mut urls = known_external_file_shares;
                         urls.shuffle(&mut thread_rng());
-                        let local_share_full_path = local_storage_share_base_path.join(resource_id);
                         match download_blob(
-                            &local_share_full_path,
                             local_storage_share_base_path,
+                            &resource_id,
                             &urls,
                             options.max_tries,
                             options.retry_timeout,
                             options.deadline,
                         ) {
                             Ok(()) => {
-                                if let Ok(mut file) = std::fs::File::open(local_share_full_path) {
-                                    on_success(&mut file);
+                                if let Ok(mut blob) =
+                                    open_blob(local_storage_share_base_path, &resource_id)
+                                {
+                                    on_success(&mut blob);
                                 } else {
                                     on_error(anyhow::Error::msg(
                                         "Failed to open a downloaded blob",
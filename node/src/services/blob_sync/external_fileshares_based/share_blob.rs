@@ -1,31 +1,204 @@
 // 2022-2024 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
 //
 
+use std::fs::File;
+use std::io::Read;
+use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
 
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
+
 use crate::helper::get_temp_file_path;
 
-pub fn share_blob(
-    share_full_path: PathBuf,
-    tmp_dir_path: &Path,
-    data: &mut dyn std::io::Read,
+/// Size of the chunks blobs are shared and downloaded by.
+pub const BLOB_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Shared blob: `<resource_id>.chunks/<index>` files listed in the
+/// `<resource_id>.manifest`, and the whole blob in `<resource_id>` for the
+/// nodes that download blobs without chunks. The manifest is written after
+/// the chunks and the whole blob are stored, so a blob without it is not
+/// shared yet. Blobs shared before chunks were introduced have no manifest.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BlobManifest {
+    pub size: u64,
+    pub chunk_size: u64,
+    /// Hex sha256 of the chunks
+    pub chunks: Vec<String>,
+    /// Hex sha256 of the whole blob
+    pub sha256: String,
+}
+
+pub fn manifest_file_name(resource_id: &str) -> String {
+    format!("{resource_id}.manifest")
+}
+
+pub fn chunk_file_name(resource_id: &str, index: usize) -> String {
+    format!("{resource_id}.chunks/{index}")
+}
+
+fn chunks_dir(share_dir: &Path, resource_id: &str) -> PathBuf {
+    share_dir.join(format!("{resource_id}.chunks"))
+}
+
+// Chunks are kept here until all of them are stored. Interrupted shares and
+// downloads resume from the chunks found here.
+pub(super) fn staging_dir(share_dir: &Path, resource_id: &str) -> PathBuf {
+    share_dir.join(format!("_{resource_id}.staging"))
+}
+
+pub fn is_shared(share_dir: &Path, resource_id: &str) -> bool {
+    share_dir.join(manifest_file_name(resource_id)).exists() || share_dir.join(resource_id).exists()
+}
+
+pub(super) fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+// Returns the staged chunk if it is stored and not corrupted
+pub(super) fn read_staged_chunk(staging_dir: &Path, index: usize, hash: &str) -> Option<Vec<u8>> {
+    std::fs::read(staging_dir.join(index.to_string())).ok().filter(|data| sha256_hex(data) == hash)
+}
+
+pub(super) fn stage_chunk(staging_dir: &Path, index: usize, data: &[u8]) -> anyhow::Result<()> {
+    let tmp_file_path = get_temp_file_path(staging_dir);
+    let mut file = File::create(&tmp_file_path)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    std::fs::rename(tmp_file_path, staging_dir.join(index.to_string()))?;
+    Ok(())
+}
+
+/// Moves the staged chunks to the share dir, writes the whole blob and then
+/// the manifest.
+pub(super) fn commit_blob(
+    share_dir: &Path,
+    resource_id: &str,
+    manifest: &BlobManifest,
 ) -> anyhow::Result<()> {
-    if share_full_path.exists() {
-        return Ok(());
+    let chunks_dir = chunks_dir(share_dir, resource_id);
+    // Left by an attempt interrupted before the manifest was written
+    if chunks_dir.exists() {
+        std::fs::remove_dir_all(&chunks_dir)?;
     }
-    let tmp_file_path = get_temp_file_path(tmp_dir_path);
-    tracing::trace!("share_blob: trying to create file: {tmp_file_path:?}");
-    if let Some(parent) = tmp_file_path.parent() {
-        if !parent.exists() {
-            tracing::trace!("share_blob: trying create to parent dir: {parent:?}");
-            std::fs::create_dir_all(parent).expect("Failed to create dir for shared state");
-        }
+    std::fs::rename(staging_dir(share_dir, resource_id), &chunks_dir)?;
+
+    let tmp_file_path = get_temp_file_path(share_dir);
+    let mut file = File::create(&tmp_file_path)?;
+    for index in 0..manifest.chunks.len() {
+        std::io::copy(&mut File::open(chunks_dir.join(index.to_string()))?, &mut file)?;
     }
-    let mut file = std::fs::File::create(tmp_file_path.clone())?;
-    std::io::copy(data, &mut file)?;
     file.sync_all()?;
-    tracing::trace!("share_blob: rename file: {tmp_file_path:?} -> {share_full_path:?}");
-    std::fs::rename(tmp_file_path, share_full_path)?;
+    tracing::trace!("share_blob: publish {resource_id}");
+    std::fs::rename(tmp_file_path, share_dir.join(resource_id))?;
+
+    let tmp_file_path = get_temp_file_path(share_dir);
+    let mut file = File::create(&tmp_file_path)?;
+    file.write_all(&serde_json::to_vec(manifest)?)?;
+    file.sync_all()?;
+    tracing::trace!("share_blob: publish manifest of {resource_id}");
+    std::fs::rename(tmp_file_path, share_dir.join(manifest_file_name(resource_id)))?;
     Ok(())
 }
+
+/// Shares the blob in two phases: stores the chunks in the staging dir, then
+/// moves them to the share dir and writes the manifest. Downloaders never see
+/// a partially shared blob and a repeated share of the same blob skips the
+/// chunks already staged.
+pub fn share_blob(share_dir: &Path, resource_id: &str, data: &mut dyn Read) -> anyhow::Result<()> {
+    share_blob_by_chunks(share_dir, resource_id, data, BLOB_CHUNK_SIZE)
+}
+
+fn share_blob_by_chunks(
+    share_dir: &Path,
+    resource_id: &str,
+    data: &mut dyn Read,
+    chunk_size: usize,
+) -> anyhow::Result<()> {
+    if is_shared(share_dir, resource_id) {
+        return Ok(());
+    }
+    let staging_dir = staging_dir(share_dir, resource_id);
+    tracing::trace!("share_blob: trying to create staging dir: {staging_dir:?}");
+    std::fs::create_dir_all(&staging_dir)?;
+    let mut hasher = Sha256::new();
+    let mut chunks = vec![];
+    let mut size = 0;
+    let mut buffer = Vec::with_capacity(chunk_size);
+    loop {
+        buffer.clear();
+        Read::take(&mut *data, chunk_size as u64).read_to_end(&mut buffer)?;
+        if buffer.is_empty() {
+            break;
+        }
+        hasher.update(&buffer);
+        let hash = sha256_hex(&buffer);
+        if read_staged_chunk(&staging_dir, chunks.len(), &hash).is_none() {
+            stage_chunk(&staging_dir, chunks.len(), &buffer)?;
+        }
+        size += buffer.len() as u64;
+        chunks.push(hash);
+        if buffer.len() < chunk_size {
+            break;
+        }
+    }
+    let manifest = BlobManifest {
+        size,
+        chunk_size: chunk_size as u64,
+        chunks,
+        sha256: hex::encode(hasher.finalize()),
+    };
+    commit_blob(share_dir, resource_id, &manifest)
+}
+
+/// Reads the shared blob.
+pub fn open_blob(share_dir: &Path, resource_id: &str) -> anyhow::Result<impl Read> {
+    Ok(File::open(share_dir.join(resource_id))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_share_blob_by_chunks() {
+        let share_dir = tempfile::tempdir().unwrap();
+        let share_dir = share_dir.path();
+        let data = (0..=255u8).cycle().take(1000).collect::<Vec<_>>();
+
+        // Interrupted attempt: one valid and one corrupted chunk are staged
+        let staging_dir = staging_dir(share_dir, "blob");
+        std::fs::create_dir_all(&staging_dir).unwrap();
+        stage_chunk(&staging_dir, 0, &data[..400]).unwrap();
+        stage_chunk(&staging_dir, 1, b"corrupted").unwrap();
+        assert!(!is_shared(share_dir, "blob"));
+
+        share_blob_by_chunks(share_dir, "blob", &mut data.as_slice(), 400).unwrap();
+        assert!(is_shared(share_dir, "blob"));
+        assert!(!staging_dir.exists());
+        assert_eq!(std::fs::read(share_dir.join("blob")).unwrap(), data);
+        let manifest: BlobManifest = serde_json::from_slice(
+            &std::fs::read(share_dir.join(manifest_file_name("blob"))).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(manifest.size, 1000);
+        assert_eq!(manifest.chunks.len(), 3);
+        assert_eq!(manifest.chunks[1], sha256_hex(&data[400..800]));
+        assert_eq!(manifest.sha256, sha256_hex(&data));
+        assert_eq!(
+            std::fs::read(share_dir.join(chunk_file_name("blob", 2))).unwrap(),
+            &data[800..]
+        );
+
+        let mut shared = vec![];
+        open_blob(share_dir, "blob").unwrap().read_to_end(&mut shared).unwrap();
+        assert_eq!(shared, data);
+
+        // Shared before the blobs were split into chunks
+        std::fs::write(share_dir.join("whole"), &data).unwrap();
+        assert!(is_shared(share_dir, "whole"));
+    }
+}
@@ -4,12 +4,14 @@ use std::time::Duration;
 use std::time::Instant;
 
 use aerospike::errors::ErrorKind;
+use aerospike::operations;
 use aerospike::BatchPolicy;
 use aerospike::BatchRead;
 use aerospike::Bin;
 use aerospike::Client;
 use aerospike::ClientPolicy;
 use aerospike::Error;
+use aerospike::GenerationPolicy;
 use aerospike::Key;
 use aerospike::ReadPolicy;
 use aerospike::ResultCode;
@@ -24,6 +26,8 @@ pub const DEFAULT_AEROSPIKE_MESSAGE_CACHE_MAX_ENTRIES: usize = 10_000;
 pub const BIN_HASH: &str = "hash";
 pub const BIN_BLOB: &str = "blob";
 pub const BIN_SEQ: &str = "seq";
+pub const BIN_BODY: &str = "body";
+pub const BIN_REFS: &str = "refs";
 pub const NAMESPACE: &str = "node";
 
 const WRITE_RETRY_MILLIS: u64 = 5;
@@ -40,6 +44,25 @@ pub trait KeyValueStore: Send + Sync {
         label: &'static str,
     ) -> anyhow::Result<()>;
     fn batch_get(&self, reads: Vec<BatchRead>) -> anyhow::Result<Vec<Option<BinMap>>>;
+    // Atomically adds `delta` to the integer bin `counter` (missing record
+    // and bin count as zero) and puts `bins` to the same record. Returns the
+    // new value of the counter and the generation of the record.
+    fn increment(
+        &self,
+        key: &Key,
+        counter: &str,
+        delta: i64,
+        bins: &[Bin],
+        label: &'static str,
+    ) -> anyhow::Result<(i64, u32)>;
+    // With the generation set the record is deleted only if it was not
+    // modified since. Returns false if nothing was deleted.
+    fn delete(
+        &self,
+        key: &Key,
+        generation: Option<u32>,
+        label: &'static str,
+    ) -> anyhow::Result<bool>;
 }
 
 #[derive(Clone)]
@@ -67,6 +90,26 @@ impl KeyValueStore for NoopStore {
     fn batch_get(&self, _reads: Vec<BatchRead>) -> anyhow::Result<Vec<Option<BinMap>>> {
         Ok(vec![None])
     }
+
+    fn increment(
+        &self,
+        _key: &Key,
+        _counter: &str,
+        delta: i64,
+        _bins: &[Bin],
+        _label: &'static str,
+    ) -> anyhow::Result<(i64, u32)> {
+        Ok((delta, 1))
+    }
+
+    fn delete(
+        &self,
+        _key: &Key,
+        _generation: Option<u32>,
+        _label: &'static str,
+    ) -> anyhow::Result<bool> {
+        Ok(false)
+    }
 }
 
 // ============================
@@ -177,4 +220,55 @@ impl KeyValueStore for AerospikeStore {
             .map_err(|e| anyhow::anyhow!("Batch get failed: {e}"))
             .map(|results| results.into_iter().map(|r| r.record.map(|rec| rec.bins)).collect())
     }
+
+    fn increment(
+        &self,
+        key: &Key,
+        counter: &str,
+        delta: i64,
+        bins: &[Bin],
+        label: &'static str,
+    ) -> anyhow::Result<(i64, u32)> {
+        let moment = Instant::now();
+        let counter_bin = Bin::new(counter, Value::Int(delta));
+        let mut ops = bins.iter().map(operations::put).collect::<Vec<_>>();
+        ops.push(operations::add(&counter_bin));
+        ops.push(operations::get_bin(counter));
+        let record = self.client.operate(&self.wpolicy, key, &ops).map_err(|e| {
+            if let Some(m) = &self.metrics {
+                m.report_aerospike_write_err(label);
+            }
+            anyhow::anyhow!("Aerospike operate failed: {e}")
+        })?;
+        if let Some(m) = &self.metrics {
+            m.report_aerospike_write(moment.elapsed().as_micros() as f64, label);
+        }
+        match record.bins.get(counter) {
+            Some(Value::Int(value)) => Ok((*value, record.generation)),
+            _ => Err(anyhow::anyhow!("Aerospike operate: missing counter {counter}")),
+        }
+    }
+
+    fn delete(
+        &self,
+        key: &Key,
+        generation: Option<u32>,
+        label: &'static str,
+    ) -> anyhow::Result<bool> {
+        let mut wpolicy = self.wpolicy.clone();
+        if let Some(generation) = generation {
+            wpolicy.generation_policy = GenerationPolicy::ExpectGenEqual;
+            wpolicy.generation = generation;
+        }
+        match self.client.delete(&wpolicy, key) {
+            Ok(deleted) => Ok(deleted),
+            Err(Error(ErrorKind::ServerError(ResultCode::GenerationError), _)) => Ok(false),
+            Err(err) => {
+                if let Some(m) = &self.metrics {
+                    m.report_aerospike_write_err(label);
+                }
+                Err(anyhow::anyhow!("Aerospike delete failed: {err}"))
+            }
+        }
+    }
 }
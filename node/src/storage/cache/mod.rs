@@ -118,4 +118,29 @@ impl<B: KeyValueStore, C: Cache> KeyValueStore for CachedStore<B, C> {
         }
         Ok(out)
     }
+
+    fn increment(
+        &self,
+        key: &Key,
+        counter: &str,
+        delta: i64,
+        bins: &[Bin],
+        label: &'static str,
+    ) -> anyhow::Result<(i64, u32)> {
+        let value = self.db.increment(key, counter, delta, bins, label)?;
+        // The cached bins don't have the new counter value
+        self.cache.invalidate(key);
+        Ok(value)
+    }
+
+    fn delete(
+        &self,
+        key: &Key,
+        generation: Option<u32>,
+        label: &'static str,
+    ) -> anyhow::Result<bool> {
+        let deleted = self.db.delete(key, generation, label)?;
+        self.cache.invalidate(key);
+        Ok(deleted)
+    }
 }
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::sync::Arc;
 
 use aerospike::as_bin;
@@ -9,8 +10,14 @@ use aerospike::Key;
 use aerospike::Value;
 use parking_lot::Mutex;
 use telemetry_utils::now_ms;
+use tvm_block::Deserializable;
 use tvm_block::GetRepresentationHash;
+use tvm_block::Serializable;
+use tvm_types::read_single_root_boc;
+use tvm_types::write_boc;
+use tvm_types::SliceData;
 
+use crate::helper::metrics::BlockProductionMetrics;
 use crate::helper::metrics::AEROSPIKE_OBJECT_TYPE_INT_MESSAGES;
 use crate::message::identifier::MessageIdentifier;
 use crate::message::WrappedMessage;
@@ -19,7 +26,9 @@ use crate::storage::CachedStore;
 use crate::storage::KeyValueStore;
 use crate::storage::LruSizedCache;
 use crate::storage::BIN_BLOB;
+use crate::storage::BIN_BODY;
 use crate::storage::BIN_HASH;
+use crate::storage::BIN_REFS;
 use crate::storage::BIN_SEQ;
 use crate::storage::NAMESPACE;
 use crate::types::AccountAddress;
@@ -33,14 +42,20 @@ pub struct MessageDurableStorage {
     store: Option<Arc<CachedStore<AerospikeStore, LruSizedCache>>>,
     set_prefix: String,
     seq: Arc<Mutex<HashMap<String, i64>>>,
+    metrics: Option<BlockProductionMetrics>,
 }
 
 impl MessageDurableStorage {
-    pub fn new(store: CachedStore<AerospikeStore, LruSizedCache>, set_prefix: &str) -> Self {
+    pub fn new(
+        store: CachedStore<AerospikeStore, LruSizedCache>,
+        set_prefix: &str,
+        metrics: Option<BlockProductionMetrics>,
+    ) -> Self {
         Self {
             store: Some(Arc::new(store)),
             set_prefix: set_prefix.to_string(),
             seq: Arc::new(Mutex::new(HashMap::new())),
+            metrics,
         }
     }
 
@@ -49,7 +64,12 @@ impl MessageDurableStorage {
     }
 
     pub fn as_noop() -> Self {
-        Self { store: None, set_prefix: "".to_string(), seq: Arc::new(Mutex::new(HashMap::new())) }
+        Self {
+            store: None,
+            set_prefix: "".to_string(),
+            seq: Arc::new(Mutex::new(HashMap::new())),
+            metrics: None,
+        }
     }
 
     fn message_key(&self, hash: &str) -> Key {
@@ -60,9 +80,91 @@ impl MessageDurableStorage {
         as_key!(NAMESPACE, &self.set_prefix, format!("{account}-{seq}"))
     }
 
+    fn body_key(&self, body_hash: &str) -> Key {
+        as_key!(NAMESPACE, &self.set_prefix, format!("body-{body_hash}"))
+    }
+
+    // Stores the body once per content and counts the messages referencing it.
+    // A message already referencing the body (e.g. a retry) is not counted
+    // again. Messages of different accounts are written in parallel, so the
+    // counter is updated atomically in the body record.
+    fn write_body(
+        &self,
+        store: &CachedStore<AerospikeStore, LruSizedCache>,
+        message_key: &Key,
+        body_hash: &str,
+        body: Vec<u8>,
+    ) -> anyhow::Result<()> {
+        let is_referenced =
+            store.get(message_key, &[BIN_BODY], AEROSPIKE_OBJECT_TYPE_INT_MESSAGES)?.is_some_and(
+                |bins| matches!(bins.get(BIN_BODY), Some(Value::String(hash)) if hash == body_hash),
+            );
+        if is_referenced {
+            return Ok(());
+        }
+        let size = body.len();
+        let (refs, _) = store.increment(
+            &self.body_key(body_hash),
+            BIN_REFS,
+            1,
+            &[as_bin!(BIN_BLOB, body)],
+            AEROSPIKE_OBJECT_TYPE_INT_MESSAGES,
+        )?;
+        if let Some(metrics) = &self.metrics {
+            metrics.report_message_body_write(size, refs > 1);
+        }
+        Ok(())
+    }
+
+    // Releases the body of a removed message, the body record is removed
+    // with the last message referencing it
+    fn release_body(
+        &self,
+        store: &CachedStore<AerospikeStore, LruSizedCache>,
+        body_hash: &str,
+    ) -> anyhow::Result<()> {
+        let key = self.body_key(body_hash);
+        let (refs, generation) =
+            store.increment(&key, BIN_REFS, -1, &[], AEROSPIKE_OBJECT_TYPE_INT_MESSAGES)?;
+        if refs <= 0 {
+            // Not removed if a message referenced the body again meanwhile
+            store.delete(&key, Some(generation), AEROSPIKE_OBJECT_TYPE_INT_MESSAGES)?;
+        }
+        Ok(())
+    }
+
+    fn read_bodies(
+        &self,
+        store: &CachedStore<AerospikeStore, LruSizedCache>,
+        body_hashes: HashSet<String>,
+    ) -> anyhow::Result<HashMap<String, SliceData>> {
+        if body_hashes.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let bins = Bins::from([BIN_BLOB]);
+        let body_hashes = body_hashes.into_iter().collect::<Vec<_>>();
+        let batch_reads = body_hashes
+            .iter()
+            .map(|hash| BatchRead::new(self.body_key(hash), &bins))
+            .collect::<Vec<_>>();
+        let batch_results = store
+            .batch_get(batch_reads)
+            .map_err(|e| anyhow::anyhow!("Error executing batch request: {}", e))?;
+        let mut bodies = HashMap::new();
+        for (hash, record) in body_hashes.into_iter().zip(batch_results) {
+            let Some(Value::Blob(body_blob)) = record.as_ref().and_then(|r| r.get(BIN_BLOB)) else {
+                return Err(anyhow::anyhow!("Failed to read message body {hash}"));
+            };
+            bodies.insert(hash, decode_body(body_blob)?);
+        }
+        Ok(bodies)
+    }
+
     // Writes records:
-    // 1.  {PKey: message_hash, values: message, seq}
+    // 1.  {PKey: message_hash, values: message without body, body_hash, seq}
     // 2.  {PKey: address+seqno, value: message_hash}  - kind of reverse index
+    // 3.  {PKey: body-body_hash, values: body, refs}  - shared by the messages
+    //     with the same body
     pub fn write_messages(
         &self,
         messages: HashMap<AccountAddress, Vec<(MessageIdentifier, Arc<WrappedMessage>)>>,
@@ -84,7 +186,14 @@ impl MessageDurableStorage {
             for message in messages {
                 let hash =
                     message.1.message.hash().expect("message must have hash").to_hex_string();
-                let blob = bincode::serialize(&message.1)?;
+                let key = self.message_key(&hash);
+                let (blob, body_hash) = match split_body(&message.1)? {
+                    Some((stripped, body_hash, body)) => {
+                        self.write_body(store, &key, &body_hash, body)?;
+                        (bincode::serialize(&stripped)?, Some(body_hash))
+                    }
+                    None => (bincode::serialize(&message.1)?, None),
+                };
 
                 let last_seq = {
                     let seq = self.seq.lock();
@@ -95,13 +204,11 @@ impl MessageDurableStorage {
                 let next_seq = last_seq + 1;
 
                 // write message
-                let key = self.message_key(&hash);
-                store.put(
-                    &key,
-                    &[as_bin!(BIN_BLOB, blob), as_bin!(BIN_SEQ, &next_seq)],
-                    true,
-                    AEROSPIKE_OBJECT_TYPE_INT_MESSAGES,
-                )?;
+                let mut bins = vec![as_bin!(BIN_BLOB, blob), as_bin!(BIN_SEQ, &next_seq)];
+                if let Some(body_hash) = &body_hash {
+                    bins.push(as_bin!(BIN_BODY, body_hash));
+                }
+                store.put(&key, &bins, true, AEROSPIKE_OBJECT_TYPE_INT_MESSAGES)?;
 
                 // write index
                 let idx_key = self.index_key(&dest, next_seq);
@@ -128,7 +235,7 @@ impl MessageDurableStorage {
 
         let key = self.message_key(hash);
         if let Some(bins) =
-            store.get(&key, &[BIN_SEQ, BIN_BLOB], AEROSPIKE_OBJECT_TYPE_INT_MESSAGES)?
+            store.get(&key, &[BIN_SEQ, BIN_BLOB, BIN_BODY], AEROSPIKE_OBJECT_TYPE_INT_MESSAGES)?
         {
             let seq = match bins.get(BIN_SEQ) {
                 Some(Value::Int(s)) => *s,
@@ -138,13 +245,40 @@ impl MessageDurableStorage {
                 Some(Value::Blob(b)) => b.clone(),
                 _ => return Err(anyhow::anyhow!("Missing blob")),
             };
-            let msg: WrappedMessage = bincode::deserialize(&blob)?;
+            let mut msg: WrappedMessage = bincode::deserialize(&blob)?;
+            if let Some(Value::String(body_hash)) = bins.get(BIN_BODY) {
+                let mut bodies = self.read_bodies(store, HashSet::from([body_hash.clone()]))?;
+                msg.message.set_body(bodies.remove(body_hash).expect("body must be read"));
+            }
             Ok(Some((seq, msg)))
         } else {
             Ok(None)
         }
     }
 
+    // Removes the message record and releases its body. Index records of the
+    // removed messages are left, reads skip the missing messages.
+    pub fn remove_message(&self, hash: &str) -> anyhow::Result<()> {
+        if !cfg!(feature = "messages_db") {
+            return Ok(());
+        }
+        let Some(store) = self.get_store() else {
+            return Ok(());
+        };
+        let key = self.message_key(hash);
+        let Some(bins) = store.get(&key, &[BIN_BODY], AEROSPIKE_OBJECT_TYPE_INT_MESSAGES)? else {
+            return Ok(());
+        };
+        // Only the call that removed the record releases the body
+        if !store.delete(&key, None, AEROSPIKE_OBJECT_TYPE_INT_MESSAGES)? {
+            return Ok(());
+        }
+        if let Some(Value::String(body_hash)) = bins.get(BIN_BODY) {
+            self.release_body(store, body_hash)?;
+        }
+        Ok(())
+    }
+
     pub fn get_rowid_by_hash(&self, hash: &str) -> anyhow::Result<Option<i64>> {
         // TODO: we can repeat code from read_message to exclude field WrappedMessage
         self.read_message(hash).map(|opt| opt.map(|(id, _msg)| id))
@@ -165,7 +299,7 @@ impl MessageDurableStorage {
 
         let num_messages = hashes.len();
 
-        let bins = Bins::from([BIN_SEQ, BIN_BLOB]);
+        let bins = Bins::from([BIN_SEQ, BIN_BLOB, BIN_BODY]);
         let mut batch_reads: Vec<BatchRead<'_>> = vec![];
 
         for hash in hashes {
@@ -177,6 +311,7 @@ impl MessageDurableStorage {
             .batch_get(batch_reads)
             .map_err(|e| anyhow::anyhow!("Error executing batch request: {}", e))?;
 
+        let mut body_hashes = vec![];
         for record in batch_results.into_iter().flatten() {
             let Some(Value::Int(seq)) = record.get(BIN_SEQ) else {
                 return Err(anyhow::anyhow!("Failed to read message, missing seq"));
@@ -185,8 +320,18 @@ impl MessageDurableStorage {
                 return Err(anyhow::anyhow!("Failed to read message, missing blob"));
             };
             let wrapped_message = bincode::deserialize(message_blob)?;
+            body_hashes.push(match record.get(BIN_BODY) {
+                Some(Value::String(body_hash)) => Some(body_hash.clone()),
+                _ => None,
+            });
             ret_val.push((*seq, wrapped_message));
         }
+        let bodies = self.read_bodies(store, body_hashes.iter().flatten().cloned().collect())?;
+        for ((_, wrapped_message), body_hash) in ret_val.iter_mut().zip(body_hashes) {
+            if let Some(body_hash) = body_hash {
+                wrapped_message.message.set_body(bodies[&body_hash].clone());
+            }
+        }
 
         // This is not an error, but this interesting, and may be we need to tune BatchPolicy
         if ret_val.len() != num_messages {
@@ -258,6 +403,43 @@ impl MessageDurableStorage {
     }
 }
 
+// Splits the message into the message without body, the body hash and the
+// serialized body. Messages without body or not restored exactly from the
+// parts are stored whole.
+fn split_body(
+    message: &WrappedMessage,
+) -> anyhow::Result<Option<(WrappedMessage, String, Vec<u8>)>> {
+    let Some(body) = message.message.body() else {
+        return Ok(None);
+    };
+    let body_cell = body.clone().into_cell();
+    let mut stripped = message.message.clone();
+    stripped.set_body(SliceData::default());
+    let stripped_bytes = stripped
+        .write_to_bytes()
+        .map_err(|e| anyhow::format_err!("Failed to serialize message: {e}"))?;
+    let mut restored = tvm_block::Message::construct_from_bytes(&stripped_bytes)
+        .map_err(|e| anyhow::format_err!("Failed to deserialize message: {e}"))?;
+    restored.set_body(body);
+    let is_restored = match (restored.hash(), message.message.hash()) {
+        (Ok(restored), Ok(original)) => restored == original,
+        _ => false,
+    };
+    if !is_restored {
+        return Ok(None);
+    }
+    let body_bytes = write_boc(&body_cell)
+        .map_err(|e| anyhow::format_err!("Failed to serialize message body: {e}"))?;
+    Ok(Some((stripped, body_cell.repr_hash().to_hex_string(), body_bytes)))
+}
+
+fn decode_body(body_bytes: &[u8]) -> anyhow::Result<SliceData> {
+    let body_cell = read_single_root_boc(body_bytes)
+        .map_err(|e| anyhow::format_err!("Failed to deserialize message body: {e}"))?;
+    SliceData::load_cell(body_cell)
+        .map_err(|e| anyhow::format_err!("Failed to load message body: {e}"))
+}
+
 fn monotonic_prefix_len(xs: &[i64]) -> usize {
     if xs.is_empty() {
         return 0;
@@ -274,8 +456,51 @@ fn monotonic_prefix_len(xs: &[i64]) -> usize {
 }
 #[cfg(test)]
 mod tests {
+    use tvm_block::CurrencyCollection;
+    use tvm_block::InternalMessageHeader;
+    use tvm_block::MsgAddressInt;
+    use tvm_types::AccountId;
+    use tvm_types::BuilderData;
+    use tvm_types::UInt256;
+
     use super::*;
 
+    fn message(dst: u8, body: u32) -> WrappedMessage {
+        let address = |id: u8| {
+            MsgAddressInt::with_standart(None, 0, AccountId::from(UInt256::from([id; 32]))).unwrap()
+        };
+        let header = InternalMessageHeader::with_addresses(
+            address(1),
+            address(dst),
+            CurrencyCollection::default(),
+        );
+        let mut builder = BuilderData::new();
+        builder.append_u32(body).unwrap();
+        let body = SliceData::load_builder(builder).unwrap();
+        WrappedMessage { message: tvm_block::Message::with_int_header_and_body(header, body) }
+    }
+
+    #[test]
+    fn test_split_body() {
+        let (stripped_2, body_hash_2, body_2) = split_body(&message(2, 42)).unwrap().unwrap();
+        let (stripped_3, body_hash_3, body_3) = split_body(&message(3, 42)).unwrap().unwrap();
+        let (_, body_hash_other, _) = split_body(&message(2, 7)).unwrap().unwrap();
+        assert_eq!(body_hash_2, body_hash_3);
+        assert_eq!(body_2, body_3);
+        assert_ne!(body_hash_2, body_hash_other);
+        assert_ne!(stripped_2, stripped_3);
+
+        // The message is restored from the stored parts
+        let mut restored: WrappedMessage =
+            bincode::deserialize(&bincode::serialize(&stripped_3).unwrap()).unwrap();
+        restored.message.set_body(decode_body(&body_3).unwrap());
+        assert_eq!(restored.message.hash().unwrap(), message(3, 42).message.hash().unwrap());
+
+        let header = InternalMessageHeader::default();
+        let without_body = WrappedMessage { message: tvm_block::Message::with_int_header(header) };
+        assert!(split_body(&without_body).unwrap().is_none());
+    }
+
     #[test]
     fn test_find_monotonicity_border() {
         let xs = vec![1, 2, 3, 4, 5];
@@ -0,0 +1,331 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+//! Generators of protocol structures and serialization round-trip checks.
+//! Downstream crates enable the `testing` feature to fuzz their decoders
+//! against the same definitions the node uses.
+
+use std::collections::HashMap;
+
+use num_bigint::BigUint;
+use rand::rngs::SmallRng;
+use rand::Rng;
+use rand::SeedableRng;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Serialize;
+use tvm_block::BlkPrevInfo;
+use tvm_block::BlockExtra;
+use tvm_block::BlockInfo;
+use tvm_block::ExtBlkRef;
+use tvm_block::MerkleUpdate;
+use tvm_block::ValueFlow;
+use tvm_types::UInt256;
+
+use crate::block_keeper_system::BlockKeeperData;
+use crate::block_keeper_system::BlockKeeperSetChange;
+use crate::block_keeper_system::BlockKeeperStatus;
+use crate::bls::envelope::BLSSignedEnvelope;
+use crate::bls::envelope::Envelope;
+use crate::bls::gosh_bls::PubKey;
+use crate::bls::gosh_bls::Secret;
+use crate::bls::BLSSignatureScheme;
+use crate::bls::GoshBLS;
+use crate::node::associated_types::AckData;
+use crate::node::associated_types::AttestationData;
+use crate::node::associated_types::AttestationTargetType;
+use crate::node::NodeIdentifier;
+use crate::repository::dapp_id_table::DAppIdTableChangeSet;
+use crate::types::bp_selector::ProducerSelector;
+use crate::types::common_section::CommonSection;
+use crate::types::envelope_hash::AckiNackiEnvelopeHash;
+use crate::types::AccountAddress;
+use crate::types::AckiNackiBlock;
+use crate::types::BlockHeight;
+use crate::types::BlockIdentifier;
+use crate::types::BlockSeqNo;
+use crate::types::ThreadIdentifier;
+use crate::types::ThreadsTable;
+
+const MAX_SIGNERS: u16 = 4;
+const MAX_ITEMS: usize = 4;
+
+/// Deterministic generator: the same seed produces the same structures, so
+/// a failing case is reproduced by its seed.
+pub struct Generator {
+    rng: SmallRng,
+    secret: Secret,
+    pubkey: PubKey,
+}
+
+impl Generator {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let key_material: [u8; 32] = rng.gen();
+        let secret_key = gosh_blst::min_pk::SecretKey::key_gen(&key_material, &[])
+            .expect("Key material is 32 bytes long");
+        let pubkey = PubKey::from(secret_key.sk_to_pk());
+        let secret = Secret::from(secret_key.to_bytes());
+        Self { rng, secret, pubkey }
+    }
+
+    pub fn rng(&mut self) -> &mut SmallRng {
+        &mut self.rng
+    }
+
+    pub fn block_identifier(&mut self) -> BlockIdentifier {
+        BlockIdentifier::from(self.rng.gen::<[u8; 32]>())
+    }
+
+    pub fn thread_identifier(&mut self) -> ThreadIdentifier {
+        let block_id = self.block_identifier();
+        ThreadIdentifier::new(&block_id, self.rng.gen())
+    }
+
+    pub fn node_identifier(&mut self) -> NodeIdentifier {
+        NodeIdentifier::from(self.account_address())
+    }
+
+    pub fn account_address(&mut self) -> AccountAddress {
+        AccountAddress(UInt256::from(self.rng.gen::<[u8; 32]>()))
+    }
+
+    pub fn attestation(&mut self) -> AttestationData {
+        let target_type = if self.rng.gen() {
+            AttestationTargetType::Primary
+        } else {
+            AttestationTargetType::Fallback
+        };
+        AttestationData::builder()
+            .parent_block_id(self.block_identifier())
+            .block_id(self.block_identifier())
+            .block_seq_no(BlockSeqNo::from(self.rng.gen::<u32>()))
+            .envelope_hash(AckiNackiEnvelopeHash(self.rng.gen()))
+            .target_type(target_type)
+            .build()
+    }
+
+    /// Envelope with valid signatures of random signers.
+    pub fn envelope<TData>(&mut self, data: TData) -> Envelope<GoshBLS, TData>
+    where
+        TData: Serialize + for<'b> Deserialize<'b> + Clone + Send + Sync + 'static,
+    {
+        let signature = GoshBLS::sign(&self.secret, &data).expect("Failed to sign generated data");
+        let signer_index = self.rng.gen_range(0..MAX_SIGNERS);
+        let mut envelope = Envelope::create(signature, HashMap::from([(signer_index, 1)]), data);
+        for _ in 0..self.rng.gen_range(0..MAX_SIGNERS) {
+            let signer_index = self.rng.gen_range(0..MAX_SIGNERS);
+            envelope
+                .add_signature(&signer_index, &self.secret)
+                .expect("Failed to sign generated data");
+        }
+        envelope
+    }
+
+    /// All generated data is signed with the same key.
+    pub fn pubkey(&self) -> &PubKey {
+        &self.pubkey
+    }
+
+    pub fn block_keeper_set_change(&mut self) -> BlockKeeperSetChange {
+        let signer_index = self.rng.gen_range(0..MAX_SIGNERS);
+        let data = BlockKeeperData {
+            pubkey: self.pubkey.clone(),
+            epoch_finish_seq_no: self.rng.gen(),
+            status: BlockKeeperStatus::Active,
+            address: self.account_address().to_hex_string(),
+            stake: BigUint::from(self.rng.gen::<u128>()),
+            owner_address: self.account_address(),
+            signer_index,
+            owner_pubkey: self.rng.gen(),
+        };
+        match self.rng.gen_range(0..3) {
+            0 => BlockKeeperSetChange::BlockKeeperAdded((signer_index, data)),
+            1 => BlockKeeperSetChange::BlockKeeperRemoved((signer_index, data)),
+            _ => BlockKeeperSetChange::FutureBlockKeeperAdded((signer_index, data)),
+        }
+    }
+
+    pub fn common_section(&mut self, thread_id: ThreadIdentifier) -> CommonSection {
+        let block_keeper_set_changes =
+            (0..self.rng.gen_range(0..MAX_ITEMS)).map(|_| self.block_keeper_set_change()).collect();
+        let refs = (0..self.rng.gen_range(0..MAX_ITEMS)).map(|_| self.block_identifier()).collect();
+        let threads_table = self.rng.gen::<bool>().then(ThreadsTable::default);
+        let block_height =
+            BlockHeight::builder().thread_identifier(thread_id).height(self.rng.gen()).build();
+        let mut common_section = CommonSection::new(
+            thread_id,
+            self.rng.gen(),
+            self.node_identifier(),
+            block_keeper_set_changes,
+            self.rng.gen(),
+            refs,
+            threads_table,
+            DAppIdTableChangeSet::default(),
+            block_height,
+            #[cfg(feature = "monitor-accounts-number")]
+            self.rng.gen(),
+        );
+        common_section.block_attestations = (0..self.rng.gen_range(0..MAX_ITEMS))
+            .map(|_| {
+                let attestation = self.attestation();
+                self.envelope(attestation)
+            })
+            .collect();
+        common_section.acks = (0..self.rng.gen_range(0..MAX_ITEMS))
+            .map(|_| {
+                let ack = AckData {
+                    block_id: self.block_identifier(),
+                    block_seq_no: BlockSeqNo::from(self.rng.gen::<u32>()),
+                };
+                self.envelope(ack)
+            })
+            .collect();
+        // Common section can't be serialized without the producer selector
+        common_section.producer_selector = Some(
+            ProducerSelector::builder()
+                .rng_seed_block_id(self.block_identifier())
+                .index(self.rng.gen_range(0..MAX_SIGNERS as usize))
+                .build(),
+        );
+        common_section
+    }
+
+    /// Block with an empty TVM block and a random common section.
+    pub fn block(&mut self) -> AckiNackiBlock {
+        let seq_no = self.rng.gen_range(1..u32::MAX);
+        let mut info = BlockInfo::default();
+        info.set_seq_no(seq_no).expect("Generated seq_no is not zero");
+        info.set_gen_utime_ms(self.rng.gen_range(0..u32::MAX as u64) * 1000);
+        let prev = ExtBlkRef {
+            end_lt: 0,
+            seq_no: seq_no - 1,
+            root_hash: UInt256::from(self.rng.gen::<[u8; 32]>()),
+            file_hash: UInt256::default(),
+        };
+        info.set_prev_stuff(false, &BlkPrevInfo::Block { prev })
+            .expect("Failed to set generated block parent");
+        let tvm_block = tvm_block::Block::with_params(
+            0,
+            info,
+            ValueFlow::default(),
+            MerkleUpdate::default(),
+            BlockExtra::default(),
+        )
+        .expect("Failed to construct generated TVM block");
+
+        let thread_id = self.thread_identifier();
+        let common_section = self.common_section(thread_id);
+        let mut block = AckiNackiBlock::new(
+            thread_id,
+            tvm_block,
+            common_section.producer_id.clone(),
+            0,
+            vec![],
+            common_section.verify_complexity,
+            vec![],
+            None,
+            DAppIdTableChangeSet::default(),
+            common_section.round,
+            common_section.block_height,
+            #[cfg(feature = "monitor-accounts-number")]
+            common_section.accounts_number_diff,
+        );
+        block.set_common_section(common_section, true).expect("Failed to set block hash");
+        block
+    }
+
+    pub fn block_envelope(&mut self) -> Envelope<GoshBLS, AckiNackiBlock> {
+        let block = self.block();
+        self.envelope(block)
+    }
+}
+
+/// Serializes the value, decodes it back and checks the encoding of the
+/// decoded value is the same byte to byte. Returns the encoding.
+pub fn check_round_trip<T>(value: &T) -> anyhow::Result<Vec<u8>>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let data = bincode::serialize(value)?;
+    let decoded: T = bincode::deserialize(&data)?;
+    let encoded_again = bincode::serialize(&decoded)?;
+    anyhow::ensure!(
+        data == encoded_again,
+        "Round trip changed the encoding: {} bytes before, {} bytes after",
+        data.len(),
+        encoded_again.len()
+    );
+    Ok(data)
+}
+
+/// Corrupted copies of the encoded data: truncations and single bit flips.
+pub fn mutations(data: &[u8], rng: &mut impl Rng, count: usize) -> Vec<Vec<u8>> {
+    if data.is_empty() {
+        return vec![];
+    }
+    (0..count)
+        .map(|_| {
+            if rng.gen() {
+                data[..rng.gen_range(0..data.len())].to_vec()
+            } else {
+                let mut mutated = data.to_vec();
+                let bit = rng.gen_range(0..data.len() * 8);
+                mutated[bit / 8] ^= 1 << (bit % 8);
+                mutated
+            }
+        })
+        .collect()
+}
+
+/// Decoders may reject corrupted data with an error but must not panic on
+/// it. Each input is decoded on its own thread, so a panic fails the join
+/// and the check returns the input the decoder panicked on. Returns the
+/// number of rejected inputs.
+pub fn check_decoder<F, T>(inputs: &[Vec<u8>], decode: F) -> anyhow::Result<usize>
+where
+    F: Fn(&[u8]) -> anyhow::Result<T> + Sync,
+{
+    let mut rejected = 0;
+    for input in inputs {
+        let decoded = std::thread::scope(|scope| {
+            std::thread::Builder::new()
+                .name("Decoder check".to_string())
+                .spawn_scoped(scope, || decode(input).is_ok())
+                .map_err(|e| anyhow::format_err!("Failed to spawn decoder thread: {e}"))?
+                .join()
+                .map_err(|_| {
+                    anyhow::format_err!("Decoder panicked on input: {}", hex::encode(input))
+                })
+        })?;
+        if !decoded {
+            rejected += 1;
+        }
+    }
+    Ok(rejected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_envelope_round_trip() -> anyhow::Result<()> {
+        for seed in 0..10 {
+            let mut generator = Generator::new(seed);
+            let envelope = generator.block_envelope();
+            let data = check_round_trip(&envelope)?;
+            let decoded: Envelope<GoshBLS, AckiNackiBlock> = bincode::deserialize(&data)?;
+            assert_eq!(decoded, envelope);
+            assert!(decoded.data().check_hash()?);
+            check_round_trip(decoded.data().get_common_section())?;
+
+            let raw_block = bincode::serialize(envelope.data())?;
+            let inputs = mutations(&raw_block, generator.rng(), 100);
+            let rejected =
+                check_decoder(&inputs, |input| Ok(bincode::deserialize::<AckiNackiBlock>(input)?))?;
+            assert!(rejected > 0);
+        }
+        Ok(())
+    }
+}
@@ -7,6 +7,7 @@ use std::fmt::Debug;
 use std::fmt::Formatter;
 
 use derive_getters::Getters;
+use serde::de::Error as DeserError;
 use serde::Deserialize;
 use serde::Deserializer;
 use serde::Serialize;
@@ -22,6 +23,7 @@ use crate::node::associated_types::NackData;
 use crate::node::NodeIdentifier;
 use crate::node::SignerIndex;
 use crate::repository::dapp_id_table::DAppIdTableChangeSet;
+use crate::types::ackinacki_block::extensions::CommonSectionExtensions;
 use crate::types::bp_selector::ProducerSelector;
 use crate::types::BlockHeight;
 use crate::types::BlockIdentifier;
@@ -67,6 +69,9 @@ pub struct CommonSection {
     pub changed_dapp_ids: DAppIdTableChangeSet,
     #[cfg(feature = "monitor-accounts-number")]
     pub accounts_number_diff: i64,
+    /// Serialized by the block after the other fields, see
+    /// [`CommonSectionExtensions`].
+    pub extensions: CommonSectionExtensions,
 }
 
 impl CommonSection {
@@ -100,6 +105,7 @@ impl CommonSection {
             changed_dapp_ids,
             #[cfg(feature = "monitor-accounts-number")]
             accounts_number_diff,
+            extensions: CommonSectionExtensions::default(),
         }
     }
 
@@ -158,18 +164,15 @@ impl CommonSection {
         }
     }
 
-    fn wrap_deserialize(data: WrappedCommonSection) -> Self {
+    fn wrap_deserialize(data: WrappedCommonSection) -> bincode::Result<Self> {
         let block_attestations: Vec<Envelope<GoshBLS, AttestationData>> =
-            bincode::deserialize(&data.block_attestations)
-                .expect("Failed to deserialize block attestations");
-        let acks: Vec<Envelope<GoshBLS, AckData>> =
-            bincode::deserialize(&data.acks).expect("Failed to deserialize acks");
-        let nacks: Vec<Envelope<GoshBLS, NackData>> =
-            bincode::deserialize(&data.nacks).expect("Failed to deserialize nacks");
+            bincode::deserialize(&data.block_attestations)?;
+        let acks: Vec<Envelope<GoshBLS, AckData>> = bincode::deserialize(&data.acks)?;
+        let nacks: Vec<Envelope<GoshBLS, NackData>> = bincode::deserialize(&data.nacks)?;
 
         #[cfg(not(feature = "monitor-accounts-number"))]
         {
-            Self {
+            Ok(Self {
                 round: data.round,
                 directives: data.directives,
                 block_attestations,
@@ -184,11 +187,12 @@ impl CommonSection {
                 threads_table: data.threads_table,
                 changed_dapp_ids: data.changed_dapp_ids,
                 block_height: data.block_height,
-            }
+                extensions: CommonSectionExtensions::default(),
+            })
         }
         #[cfg(feature = "monitor-accounts-number")]
         {
-            Self {
+            Ok(Self {
                 round: data.round,
                 directives: data.directives,
                 block_attestations,
@@ -204,7 +208,8 @@ impl CommonSection {
                 changed_dapp_ids: data.changed_dapp_ids,
                 block_height: data.block_height,
                 accounts_number_diff: data.accounts_number_diff,
-            }
+                extensions: CommonSectionExtensions::default(),
+            })
         }
     }
 }
@@ -244,7 +249,8 @@ impl<'de> Deserialize<'de> for CommonSection {
         D: Deserializer<'de>,
     {
         let wrapped_data = WrappedCommonSection::deserialize(deserializer)?;
-        Ok(CommonSection::wrap_deserialize(wrapped_data))
+        CommonSection::wrap_deserialize(wrapped_data)
+            .map_err(|e| D::Error::custom(format!("Failed to deserialize common section: {e}")))
     }
 }
 
@@ -266,6 +272,7 @@ impl Debug for CommonSection {
                 .field("refs", &self.refs)
                 .field("threads_table", &self.threads_table)
                 .field("changed_dapp_ids.len", &self.changed_dapp_ids.len())
+                .field("extensions", &self.extensions)
                 .finish()
         }
         #[cfg(feature = "monitor-accounts-number")]
@@ -285,6 +292,7 @@ impl Debug for CommonSection {
                 .field("threads_table", &self.threads_table)
                 .field("changed_dapp_ids.len", &self.changed_dapp_ids.len())
                 .field("accounts_number_diff", &self.accounts_number_diff)
+                .field("extensions", &self.extensions)
                 .finish()
         }
     }
@@ -0,0 +1,260 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Serialize;
+
+pub type ExtensionId = u16;
+
+const EXTENSIONS_FORMAT: u8 = 1;
+
+/// Extensions known to this node. A new extension gets an unused id and is
+/// registered here, ids of removed extensions are never reused.
+pub static EXTENSION_REGISTRY: LazyLock<ExtensionRegistry> = LazyLock::new(|| {
+    ExtensionRegistry::default()
+        .register::<MintedShell>()
+        .register::<ProducerBuild>()
+        .register::<AppliedAccountPolicy>()
+});
+
+/// Typed value stored in the common section extensions.
+pub trait CommonSectionExtension: Serialize + DeserializeOwned {
+    const ID: ExtensionId;
+    const NAME: &'static str;
+    /// Payload format version, incremented on incompatible changes.
+    const VERSION: u16;
+    /// A block with a critical extension can't be verified by a node that
+    /// doesn't know it (or knows only an older version of it). Non critical
+    /// extensions are skipped by such nodes.
+    const CRITICAL: bool;
+}
+
+/// Extensions are serialized after the fixed fields of the common section.
+/// Nodes built before extensions existed ignore these trailing bytes, and the
+/// section of a block without extensions is byte-identical to the old format.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct CommonSectionExtensions {
+    format: u8,
+    entries: BTreeMap<ExtensionId, ExtensionEntry>,
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct ExtensionEntry {
+    pub version: u16,
+    pub critical: bool,
+    pub payload: Vec<u8>,
+}
+
+impl Default for CommonSectionExtensions {
+    fn default() -> Self {
+        Self { format: EXTENSIONS_FORMAT, entries: BTreeMap::new() }
+    }
+}
+
+impl CommonSectionExtensions {
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&ExtensionId, &ExtensionEntry)> {
+        self.entries.iter()
+    }
+
+    pub fn insert<E: CommonSectionExtension>(&mut self, value: &E) -> anyhow::Result<()> {
+        let payload = bincode::serialize(value)
+            .map_err(|e| anyhow::format_err!("Failed to serialize extension {}: {e}", E::NAME))?;
+        self.entries
+            .insert(E::ID, ExtensionEntry { version: E::VERSION, critical: E::CRITICAL, payload });
+        Ok(())
+    }
+
+    pub fn remove<E: CommonSectionExtension>(&mut self) {
+        self.entries.remove(&E::ID);
+    }
+
+    /// Returns an error if the stored version of the extension differs from
+    /// the supported one.
+    pub fn get<E: CommonSectionExtension>(&self) -> anyhow::Result<Option<E>> {
+        let Some(entry) = self.entries.get(&E::ID) else {
+            return Ok(None);
+        };
+        anyhow::ensure!(
+            entry.version == E::VERSION,
+            "Unsupported version {} of extension {}, expected {}",
+            entry.version,
+            E::NAME,
+            E::VERSION
+        );
+        bincode::deserialize(&entry.payload)
+            .map(Some)
+            .map_err(|e| anyhow::format_err!("Failed to deserialize extension {}: {e}", E::NAME))
+    }
+
+    pub(crate) fn deserialize_trailing(data: &[u8]) -> anyhow::Result<Self> {
+        let extensions: Self = bincode::deserialize(data)
+            .map_err(|e| anyhow::format_err!("Failed to deserialize extensions: {e}"))?;
+        anyhow::ensure!(
+            extensions.format == EXTENSIONS_FORMAT,
+            "Unsupported common section extensions format: {}",
+            extensions.format
+        );
+        Ok(extensions)
+    }
+}
+
+/// Shell minted by the dapps in the block, keyed by the dapp id hex. A
+/// negative amount is shell burned by the dapp (gas paid from its credit
+/// exceeds the minted amount). Used only for fee accounting.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, Default)]
+pub struct MintedShell(pub BTreeMap<String, i128>);
+
+impl CommonSectionExtension for MintedShell {
+    const CRITICAL: bool = false;
+    const ID: ExtensionId = 1;
+    const NAME: &'static str = "minted_shell";
+    const VERSION: u16 = 1;
+}
+
+impl MintedShell {
+    pub fn minted(&self) -> u128 {
+        self.0
+            .values()
+            .filter(|x| **x > 0)
+            .fold(0u128, |sum, x| sum.saturating_add(x.unsigned_abs()))
+    }
+
+    pub fn burned(&self) -> u128 {
+        self.0
+            .values()
+            .filter(|x| **x < 0)
+            .fold(0u128, |sum, x| sum.saturating_add(x.unsigned_abs()))
+    }
+}
+
+/// Build of the node that produced the block. Lets the block keepers see which
+/// versions the producers run during coordinated upgrades.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug, Default)]
+pub struct ProducerBuild {
+    pub version: String,
+    pub commit: String,
+}
+
+impl CommonSectionExtension for ProducerBuild {
+    const CRITICAL: bool = false;
+    const ID: ExtensionId = 2;
+    const NAME: &'static str = "producer_build";
+    const VERSION: u16 = 1;
+}
+
+impl ProducerBuild {
+    /// Build of this node.
+    pub fn current() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            commit: env!("BUILD_GIT_COMMIT").trim().to_string(),
+        }
+    }
+}
+
+/// Account policy the producer applied to the external messages of the block.
+/// Verifiers with the same policy check the block against it.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub struct AppliedAccountPolicy {
+    /// Policy name: `allowlist`, `denylist` or the name of a custom policy.
+    pub policy: String,
+    /// SHA-256 of the policy definition, equal for the same account lists.
+    pub digest: [u8; 32],
+    /// Hashes of the external messages excluded from the block.
+    pub excluded_messages: Vec<[u8; 32]>,
+}
+
+impl CommonSectionExtension for AppliedAccountPolicy {
+    const CRITICAL: bool = false;
+    const ID: ExtensionId = 3;
+    const NAME: &'static str = "account_policy";
+    const VERSION: u16 = 1;
+}
+
+#[derive(Clone, Debug)]
+pub struct ExtensionInfo {
+    pub name: &'static str,
+    pub version: u16,
+}
+
+#[derive(Default, Debug)]
+pub struct ExtensionRegistry {
+    extensions: BTreeMap<ExtensionId, ExtensionInfo>,
+}
+
+impl ExtensionRegistry {
+    pub fn register<E: CommonSectionExtension>(mut self) -> Self {
+        let previous =
+            self.extensions.insert(E::ID, ExtensionInfo { name: E::NAME, version: E::VERSION });
+        assert!(previous.is_none(), "Extension id {} is registered twice", E::ID);
+        self
+    }
+
+    pub fn get(&self, id: ExtensionId) -> Option<&ExtensionInfo> {
+        self.extensions.get(&id)
+    }
+
+    /// Fails if the node can't verify a block with these extensions.
+    pub fn check(&self, extensions: &CommonSectionExtensions) -> anyhow::Result<()> {
+        for (id, entry) in extensions.entries() {
+            let supported = self.extensions.get(id).is_some_and(|x| x.version == entry.version);
+            if supported {
+                continue;
+            }
+            anyhow::ensure!(
+                !entry.critical,
+                "Unsupported critical common section extension {id} version {}",
+                entry.version
+            );
+            tracing::trace!("Skip unsupported common section extension {id} v{}", entry.version);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Marker(u32);
+
+    impl CommonSectionExtension for Marker {
+        const CRITICAL: bool = true;
+        const ID: ExtensionId = 0xFFFF;
+        const NAME: &'static str = "marker";
+        const VERSION: u16 = 1;
+    }
+
+    #[test]
+    fn test_common_section_extensions() {
+        let mut extensions = CommonSectionExtensions::default();
+        assert_eq!(extensions.get::<Marker>().unwrap(), None);
+        extensions.insert(&Marker(7)).unwrap();
+        assert_eq!(extensions.get::<Marker>().unwrap(), Some(Marker(7)));
+
+        // Extensions trail the fixed fields, old parsers stop before them
+        let mut data = bincode::serialize(&42u64).unwrap();
+        data.extend(bincode::serialize(&extensions).unwrap());
+        assert_eq!(bincode::deserialize::<u64>(&data).unwrap(), 42);
+        let mut reader = data.as_slice();
+        let _: u64 = bincode::deserialize_from(&mut reader).unwrap();
+        assert_eq!(CommonSectionExtensions::deserialize_trailing(reader).unwrap(), extensions);
+
+        assert!(ExtensionRegistry::default().check(&extensions).is_err());
+        assert!(ExtensionRegistry::default().register::<Marker>().check(&extensions).is_ok());
+
+        extensions.entries.get_mut(&Marker::ID).unwrap().version = 2;
+        assert!(extensions.get::<Marker>().is_err());
+        extensions.entries.get_mut(&Marker::ID).unwrap().critical = false;
+        assert!(ExtensionRegistry::default().register::<Marker>().check(&extensions).is_ok());
+    }
+}
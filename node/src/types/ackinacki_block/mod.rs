@@ -29,6 +29,7 @@ use crate::types::ThreadsTable;
 pub mod as_signatures_map;
 pub mod common_section;
 pub mod envelope_hash;
+pub mod extensions;
 pub mod hash;
 mod parse_block_accounts_and_messages;
 mod serialize;
@@ -148,6 +149,12 @@ impl AckiNackiBlock {
         }
     }
 
+    /// Size of the serialized block. None if the block was not deserialized
+    /// from the network or storage.
+    pub fn raw_data_len(&self) -> Option<usize> {
+        self.raw_data.as_ref().map(|raw_data| raw_data.len())
+    }
+
     pub fn parent(&self) -> BlockIdentifier {
         BlockIdentifier::from(
             self.block
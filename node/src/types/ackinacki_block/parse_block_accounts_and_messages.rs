@@ -128,4 +128,20 @@ impl AckiNackiBlock {
             produced_internal_messages_to_other_threads,
         ))
     }
+
+    /// Hex ids of the accounts with transactions in the block.
+    pub fn get_account_ids(&self) -> anyhow::Result<HashSet<String>> {
+        let mut account_ids = HashSet::new();
+        self.tvm_block()
+            .read_extra()
+            .and_then(|extra| extra.read_account_blocks())
+            .and_then(|account_blocks| {
+                account_blocks.iterate_objects(|account_block| {
+                    account_ids.insert(account_block.account_id().to_hex_string());
+                    Ok(true)
+                })
+            })
+            .map_err(|e| anyhow::format_err!("Failed to read block account blocks: {e}"))?;
+        Ok(account_ids)
+    }
 }
@@ -13,12 +13,17 @@ use tvm_types::read_single_root_boc;
 use tvm_types::write_boc;
 
 use crate::types::ackinacki_block::common_section::CommonSection;
+use crate::types::ackinacki_block::extensions::CommonSectionExtensions;
 use crate::types::AckiNackiBlock;
 
 impl AckiNackiBlock {
     pub fn get_raw_data_without_hash(&self) -> anyhow::Result<Vec<u8>> {
         tracing::trace!("full serialize block data");
-        let common_section = bincode::serialize(&self.common_section)?;
+        let mut common_section = bincode::serialize(&self.common_section)?;
+        // Blocks without extensions keep the format of the older nodes
+        if !self.common_section.extensions.is_empty() {
+            common_section.extend(bincode::serialize(&self.common_section.extensions)?);
+        }
         let mut data = vec![];
         data.extend_from_slice(&common_section.len().to_be_bytes()); // 8 bytes of common section len
         data.extend_from_slice(&common_section);
@@ -59,35 +64,51 @@ impl<'de> Deserialize<'de> for AckiNackiBlock {
         D: Deserializer<'de>,
     {
         let raw_data = Vec::<u8>::deserialize(deserializer)?;
-        let (common_section_len_data, rest) = raw_data.split_at(8);
+        let (common_section_len_data, rest) = raw_data
+            .split_at_checked(8)
+            .ok_or_else(|| D::Error::custom("Failed to deserialize common section len"))?;
         let common_section_len = usize::from_be_bytes(
             common_section_len_data
                 .try_into()
                 .map_err(|_| D::Error::custom("Failed to deserialize common section len"))?,
         );
-        let (common_section_data, rest) = rest.split_at(common_section_len);
-        let common_section: CommonSection = bincode::deserialize(common_section_data)
-            .map_err(|_| D::Error::custom("Failed to deserialize common section"))?;
+        let (common_section_data, rest) = rest
+            .split_at_checked(common_section_len)
+            .ok_or_else(|| D::Error::custom("Failed to deserialize common section"))?;
+        let mut common_section_reader = common_section_data;
+        let mut common_section: CommonSection =
+            bincode::deserialize_from(&mut common_section_reader)
+                .map_err(|_| D::Error::custom("Failed to deserialize common section"))?;
+        if !common_section_reader.is_empty() {
+            common_section.extensions =
+                CommonSectionExtensions::deserialize_trailing(common_section_reader)
+                    .map_err(|e| D::Error::custom(format!("{e}")))?;
+        }
 
-        let (block_len_data, rest) = rest.split_at(8);
+        let (block_len_data, rest) = rest
+            .split_at_checked(8)
+            .ok_or_else(|| D::Error::custom("Failed to decode block len"))?;
         let block_len = usize::from_be_bytes(
             block_len_data
                 .try_into()
                 .map_err(|_| D::Error::custom("Failed to decode block len"))?,
         );
-        let (block_data, rest) = rest.split_at(block_len);
+        let (block_data, rest) = rest
+            .split_at_checked(block_len)
+            .ok_or_else(|| D::Error::custom("Failed to deserialize tvm block cell"))?;
         let block_cell = read_single_root_boc(block_data)
             .map_err(|_| D::Error::custom("Failed to deserialize tvm block cell"))?;
         let block = tvm_block::Block::construct_from_cell(block_cell.clone())
             .map_err(|_| D::Error::custom("Failed to deserialize tvm block"))?;
 
-        let (tx_cnt_data, rest) = rest.split_at(8);
+        let (tx_cnt_data, rest) = rest
+            .split_at_checked(8)
+            .ok_or_else(|| D::Error::custom("Failed to decode block field: tx_cnt"))?;
         let tx_cnt = usize::from_be_bytes(
             tx_cnt_data
                 .try_into()
                 .map_err(|_| D::Error::custom("Failed to decode block field: tx_cnt"))?,
         );
-        assert_eq!(rest.len(), 32);
         let hash =
             rest.try_into().map_err(|_| D::Error::custom("Failed to deserialize block hash"))?;
         Ok(Self {
@@ -1,8 +1,12 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
 use std::sync::atomic::AtomicU32;
 use std::sync::Arc;
 
 use derive_getters::Getters;
-use itertools::Itertools;
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
+use num_traits::Zero;
 use rand::rngs::SmallRng;
 use rand::seq::SliceRandom;
 use rand::SeedableRng;
@@ -11,83 +15,182 @@ use serde::Serialize;
 use typed_builder::TypedBuilder;
 
 use crate::block_keeper_system::BlockKeeperSet;
+use crate::config::ProducerSelectionConfig;
 use crate::node::NodeIdentifier;
 use crate::types::BlockIdentifier;
 
 pub type BlockGap = Arc<AtomicU32>;
 
+// Average number of turns of a keeper in the stake weighted schedule
+const WEIGHTED_TURNS_PER_KEEPER: usize = 4;
+
 #[derive(Debug, Clone, Serialize, Deserialize, TypedBuilder, Eq, PartialEq, Getters)]
 pub struct ProducerSelector {
     // Block id with last BK set change
     rng_seed_block_id: BlockIdentifier,
-    // Shuffled BK set offset to find BP
+    // Producer schedule offset to find BP
     index: usize,
 }
 
 impl ProducerSelector {
-    pub fn get_producer_node_id(&self, bk_set: &BlockKeeperSet) -> anyhow::Result<NodeIdentifier> {
-        let mut sorted_node_id_list = bk_set.iter_node_ids().collect::<Vec<_>>();
+    /// Order of the producer turns: the shuffled BK set, or the shuffled
+    /// turns allocated to the keepers by stake for the stake weighted
+    /// selection. Every keeper has at least one turn. Deterministic for the
+    /// seed block, the BK set and the selection config, which is the same
+    /// on all nodes (global config).
+    pub fn producer_schedule(
+        &self,
+        bk_set: &BlockKeeperSet,
+        selection: &ProducerSelectionConfig,
+    ) -> Vec<NodeIdentifier> {
         let mut rng = SmallRng::from_seed(self.rng_seed_block_id.clone().as_rng_seed());
-        sorted_node_id_list.shuffle(&mut rng);
-        anyhow::ensure!(
-            self.index < sorted_node_id_list.len(),
-            "Producer selector index out of bounds"
-        );
-        Ok(sorted_node_id_list
-            .get(self.index)
-            .expect("Producer index out of bounds")
-            .to_owned()
-            .clone())
+        if !selection.stake_weighted {
+            let mut sorted_node_id_list = bk_set.iter_node_ids().cloned().collect::<Vec<_>>();
+            sorted_node_id_list.shuffle(&mut rng);
+            return sorted_node_id_list;
+        }
+        let mut turns = stake_weighted_turns(bk_set);
+        turns.shuffle(&mut rng);
+        apply_cooldown(turns, selection.cooldown)
+    }
+
+    fn schedule_len(&self, bk_set: &BlockKeeperSet, selection: &ProducerSelectionConfig) -> usize {
+        if selection.stake_weighted {
+            stake_weighted_turns(bk_set).len()
+        } else {
+            bk_set.len()
+        }
+    }
+
+    pub fn get_producer_node_id(
+        &self,
+        bk_set: &BlockKeeperSet,
+        selection: &ProducerSelectionConfig,
+    ) -> anyhow::Result<NodeIdentifier> {
+        let schedule = self.producer_schedule(bk_set, selection);
+        anyhow::ensure!(self.index < schedule.len(), "Producer selector index out of bounds");
+        Ok(schedule.get(self.index).expect("Producer index out of bounds").clone())
     }
 
     pub fn is_node_bp(
         &self,
         bk_set: &BlockKeeperSet,
+        selection: &ProducerSelectionConfig,
         node_id: &NodeIdentifier,
     ) -> anyhow::Result<bool> {
-        Ok(&self.get_producer_node_id(bk_set)? == node_id)
+        Ok(&self.get_producer_node_id(bk_set, selection)? == node_id)
     }
 
     pub fn check_whether_this_node_is_bp_based_on_bk_set_and_index_offset(
         &self,
         bk_set: &BlockKeeperSet,
+        selection: &ProducerSelectionConfig,
         node_id: &NodeIdentifier,
         offset: usize,
     ) -> bool {
-        match self.get_distance_from_bp(bk_set, node_id) {
-            Some(distance) => distance == (offset % bk_set.len()),
+        let schedule = self.producer_schedule(bk_set, selection);
+        match self.distance_in_schedule(&schedule, node_id) {
+            Some(distance) => distance == (offset % schedule.len()),
             None => false,
         }
     }
 
+    /// Number of turns before the next turn of the node, zero if the node is
+    /// the current producer.
     pub fn get_distance_from_bp(
         &self,
         bk_set: &BlockKeeperSet,
+        selection: &ProducerSelectionConfig,
         node_id: &NodeIdentifier,
     ) -> Option<usize> {
-        let mut sorted_node_id_list = bk_set.iter_node_ids().collect::<Vec<_>>();
-        let total_bk_cnt = sorted_node_id_list.len();
-        if self.index >= total_bk_cnt {
+        self.distance_in_schedule(&self.producer_schedule(bk_set, selection), node_id)
+    }
+
+    fn distance_in_schedule(
+        &self,
+        schedule: &[NodeIdentifier],
+        node_id: &NodeIdentifier,
+    ) -> Option<usize> {
+        let total_turns = schedule.len();
+        if self.index >= total_turns {
             return None;
         }
-        let mut rng = SmallRng::from_seed(self.rng_seed_block_id.clone().as_rng_seed());
-        sorted_node_id_list.shuffle(&mut rng);
-        if let Some((position, _)) =
-            sorted_node_id_list.into_iter().find_position(|id| *id == node_id)
-        {
-            Some(if position >= self.index {
-                position - self.index
+        (0..total_turns)
+            .find(|distance| schedule[(self.index + distance) % total_turns] == *node_id)
+    }
+
+    pub fn move_index(
+        self,
+        diff: usize,
+        bk_set: &BlockKeeperSet,
+        selection: &ProducerSelectionConfig,
+    ) -> Self {
+        let schedule_len = self.schedule_len(bk_set, selection);
+        Self { index: (self.index + diff) % schedule_len, ..self }
+    }
+}
+
+// Turns of the keepers in the order of node ids, proportional to the stake
+fn stake_weighted_turns(bk_set: &BlockKeeperSet) -> Vec<NodeIdentifier> {
+    let keepers = bk_set
+        .iter_node_ids()
+        .filter_map(|node_id| bk_set.get_by_node_id(node_id).map(|data| (node_id, &data.stake)))
+        .collect::<Vec<_>>();
+    let total_stake = keepers.iter().fold(BigUint::zero(), |total, (_, stake)| total + *stake);
+    let total_turns = BigUint::from(keepers.len() * WEIGHTED_TURNS_PER_KEEPER);
+    keepers
+        .into_iter()
+        .flat_map(|(node_id, stake)| {
+            let turns = if total_stake.is_zero() {
+                1
             } else {
-                total_bk_cnt - self.index + position
+                (stake * &total_turns / &total_stake).to_usize().unwrap_or(1).max(1)
+            };
+            std::iter::repeat_n(node_id.clone(), turns)
+        })
+        .collect()
+}
+
+// Reorders the shuffled turns so that at least `cooldown` other turns
+// separate two turns of a keeper, including the wrap to the next cycle of
+// the schedule. Takes the turn of the keeper with the most remaining turns
+// among the ones satisfying the cooldown (the shuffled order breaks ties),
+// or the first remaining turn if the stake distribution leaves no choice.
+fn apply_cooldown(mut remaining: Vec<NodeIdentifier>, cooldown: usize) -> Vec<NodeIdentifier> {
+    if cooldown == 0 {
+        return remaining;
+    }
+    let total_turns = remaining.len();
+    let mut schedule = Vec::with_capacity(total_turns);
+    let mut remaining_turns: HashMap<NodeIdentifier, usize> = HashMap::new();
+    for node_id in remaining.iter() {
+        *remaining_turns.entry(node_id.clone()).or_default() += 1;
+    }
+    let mut first_turns: HashMap<NodeIdentifier, usize> = HashMap::new();
+    let mut last_turns: HashMap<NodeIdentifier, usize> = HashMap::new();
+    while !remaining.is_empty() {
+        let position = schedule.len();
+        let next = remaining
+            .iter()
+            .enumerate()
+            .filter(|(_, node_id)| {
+                last_turns.get(*node_id).is_none_or(|last| position - last > cooldown)
+                    && first_turns
+                        .get(*node_id)
+                        .is_none_or(|first| total_turns - position + first > cooldown)
             })
-        } else {
-            None
+            .max_by_key(|(index, node_id)| (remaining_turns[*node_id], Reverse(*index)))
+            .map(|(index, _)| index)
+            .unwrap_or(0);
+        let node_id = remaining.remove(next);
+        if let Some(turns) = remaining_turns.get_mut(&node_id) {
+            *turns -= 1;
         }
+        first_turns.entry(node_id.clone()).or_insert(position);
+        last_turns.insert(node_id.clone(), position);
+        schedule.push(node_id);
     }
-
-    pub fn move_index(self, diff: usize, bk_set_size: usize) -> Self {
-        Self { rng_seed_block_id: self.rng_seed_block_id, index: (self.index + diff) % bk_set_size }
-    }
+    schedule
 }
 
 #[cfg(test)]
@@ -95,10 +198,12 @@ mod tests {
     use std::collections::HashSet;
     use std::str::FromStr;
 
+    use num_bigint::BigUint;
     use tvm_types::UInt256;
 
     use crate::block_keeper_system::BlockKeeperData;
     use crate::block_keeper_system::BlockKeeperSet;
+    use crate::config::ProducerSelectionConfig;
     use crate::node::NodeIdentifier;
     use crate::node::SignerIndex;
     use crate::types::bp_selector::ProducerSelector;
@@ -119,15 +224,16 @@ mod tests {
             )
         }
 
+        let selection = ProducerSelectionConfig::default();
         let producer_selector =
             ProducerSelector { rng_seed_block_id: BlockIdentifier::default(), index: 0 };
         let producer_node_id = producer_selector
-            .get_producer_node_id(&bk_set)
+            .get_producer_node_id(&bk_set, &selection)
             .expect("Producer node id out of bounds");
         println!("Producer node ID: {producer_node_id}");
         let mut distances_set: HashSet<usize> = HashSet::from_iter(0..100usize);
         for node_id in bk_set.iter_node_ids() {
-            let dist = producer_selector.get_distance_from_bp(&bk_set, node_id);
+            let dist = producer_selector.get_distance_from_bp(&bk_set, &selection, node_id);
             assert!(dist.is_some());
             assert!(distances_set.remove(&dist.unwrap()));
         }
@@ -148,15 +254,16 @@ mod tests {
                 },
             )
         }
+        let selection = ProducerSelectionConfig::default();
         let mut producer_selector =
             ProducerSelector { rng_seed_block_id: BlockIdentifier::default(), index: 0 };
         let mut producer_node_id = producer_selector
-            .get_producer_node_id(&bk_set)
+            .get_producer_node_id(&bk_set, &selection)
             .expect("Producer node id out of bounds");
         for _i in 0..1000 {
-            producer_selector = producer_selector.move_index(1, 100);
+            producer_selector = producer_selector.move_index(1, &bk_set, &selection);
             let new_producer_node_id = producer_selector
-                .get_producer_node_id(&bk_set)
+                .get_producer_node_id(&bk_set, &selection)
                 .expect("Producer node id out of bounds");
             assert_ne!(new_producer_node_id, producer_node_id);
             producer_node_id = new_producer_node_id;
@@ -177,6 +284,7 @@ mod tests {
                 },
             )
         }
+        let selection = ProducerSelectionConfig::default();
         let producer_selector =
             ProducerSelector { rng_seed_block_id: BlockIdentifier::default(), index: 0 };
         let nodes_set = bk_set.iter_node_ids().cloned().collect::<Vec<_>>();
@@ -184,7 +292,7 @@ mod tests {
             for i in 0..=100 {
                 assert_ne!(i, 100);
                 if producer_selector.check_whether_this_node_is_bp_based_on_bk_set_and_index_offset(
-                    &bk_set, &node_id, i,
+                    &bk_set, &selection, &node_id, i,
                 ) {
                     break;
                 }
@@ -206,9 +314,10 @@ mod tests {
                 },
             )
         }
+        let selection = ProducerSelectionConfig::default();
         let producer_selector =
             ProducerSelector { rng_seed_block_id: BlockIdentifier::default(), index: 11 };
-        let res = producer_selector.get_producer_node_id(&bk_set);
+        let res = producer_selector.get_producer_node_id(&bk_set, &selection);
         assert!(res.is_err());
         let producer_selector_clone = producer_selector.clone();
         let test_acc_id_str =
@@ -216,7 +325,54 @@ mod tests {
         let test_node_id =
             NodeIdentifier::from(AccountAddress::from_str(&test_acc_id_str).unwrap());
         let bp_distance_for_this_node =
-            producer_selector_clone.get_distance_from_bp(&bk_set, &test_node_id);
+            producer_selector_clone.get_distance_from_bp(&bk_set, &selection, &test_node_id);
         assert!(bp_distance_for_this_node.is_none());
     }
+
+    #[test]
+    fn test_stake_weighted_selection() {
+        let mut bk_set = BlockKeeperSet::new();
+        for i in 0..10 {
+            let acc_id_str =
+                format!("00000000000000000000000000000000000000000000000000000000{i:08x}");
+            bk_set.insert(
+                i as SignerIndex,
+                BlockKeeperData {
+                    owner_address: AccountAddress::from_str(&acc_id_str).unwrap(),
+                    // One keeper holds a third of the stake
+                    stake: BigUint::from(if i == 0 { 9u32 } else { 2u32 }),
+                    ..Default::default()
+                },
+            )
+        }
+        let selection = ProducerSelectionConfig { stake_weighted: true, cooldown: 1 };
+        let producer_selector =
+            ProducerSelector { rng_seed_block_id: BlockIdentifier::default(), index: 0 };
+        let schedule = producer_selector.producer_schedule(&bk_set, &selection);
+        assert_eq!(schedule, producer_selector.producer_schedule(&bk_set, &selection));
+        // Out of 40 turns: 9 * 40 / 27 for the large keeper and 2 * 40 / 27
+        // for each of the rest, rounded down
+        assert_eq!(schedule.len(), 13 + 9 * 2);
+        let heavy_node_id =
+            NodeIdentifier::from(AccountAddress::from_str(&format!("{:064x}", 0)).unwrap());
+        assert_eq!(schedule.iter().filter(|node_id| **node_id == heavy_node_id).count(), 13);
+        // No immediate repeat, including the wrap to the next cycle
+        for (a, b) in schedule.iter().zip(schedule.iter().cycle().skip(1)) {
+            assert_ne!(a, b);
+        }
+
+        let mut moved_selector = producer_selector.clone();
+        for _ in 0..schedule.len() {
+            let producer = moved_selector.get_producer_node_id(&bk_set, &selection).unwrap();
+            assert_eq!(
+                moved_selector.get_distance_from_bp(&bk_set, &selection, &producer),
+                Some(0)
+            );
+            moved_selector = moved_selector.move_index(1, &bk_set, &selection);
+        }
+        assert_eq!(moved_selector, producer_selector);
+        for node_id in bk_set.iter_node_ids() {
+            assert!(producer_selector.get_distance_from_bp(&bk_set, &selection, node_id).is_some());
+        }
+    }
 }
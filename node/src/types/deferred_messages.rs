@@ -0,0 +1,258 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde::Serialize;
+use tvm_block::AddSub;
+use tvm_block::CurrencyCollection;
+use tvm_block::Deserializable;
+use tvm_block::HashmapAugType;
+use tvm_block::Message;
+use tvm_block::OutMsgDescr;
+use tvm_block::Transaction;
+
+use crate::message::WrappedMessage;
+use crate::types::AccountAddress;
+
+// Protects the state from a timer contract flooding the queue. A timer
+// contract transaction with more requests than the queue can take is
+// aborted before its value is taken.
+const MAX_DEFERRED_MESSAGES: usize = 100_000;
+
+/// Internal messages scheduled by the timer contract, ordered by the
+/// unixtime they become due at. The queue is a part of the thread state,
+/// so the producer and verifiers inject the same messages into a block.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeferredMessageQueue {
+    messages: BTreeMap<u32, Vec<WrappedMessage>>,
+    len: usize,
+}
+
+impl DeferredMessageQueue {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of messages that can be scheduled before the queue is full.
+    pub fn remaining_capacity(&self) -> usize {
+        MAX_DEFERRED_MESSAGES.saturating_sub(self.len)
+    }
+
+    /// Account that scheduled the queued messages, all of them are sent on
+    /// behalf of the timer contract. None if the queue is empty.
+    pub fn owner(&self) -> Option<AccountAddress> {
+        self.messages
+            .values()
+            .flatten()
+            .next()
+            .and_then(|wrapped| wrapped.message.src())
+            .map(|src| AccountAddress::from(src.address()))
+    }
+
+    pub fn schedule(&mut self, due_at: u32, message: WrappedMessage) -> anyhow::Result<()> {
+        if self.len >= MAX_DEFERRED_MESSAGES {
+            anyhow::bail!("Deferred messages queue is full");
+        }
+        self.messages.entry(due_at).or_default().push(message);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Schedules messages sent by the timer contract in the block. Producer
+    /// and verifiers call it with the same out messages descr, so the queue
+    /// stays the same on all nodes. Invalid requests are skipped.
+    pub fn schedule_timer_messages(
+        &mut self,
+        out_msg_descr: &OutMsgDescr,
+        timer_contract: &AccountAddress,
+    ) -> anyhow::Result<()> {
+        out_msg_descr
+            .iterate_objects(|out_msg| {
+                let Some(message) = out_msg.read_message()? else {
+                    return Ok(true);
+                };
+                let Some(request) = timer_request(&message, timer_contract) else {
+                    return Ok(true);
+                };
+                match request.and_then(|(due_at, message)| self.schedule(due_at, message)) {
+                    Ok(()) => {}
+                    Err(e) => tracing::warn!(target: "builder", "Skip timer message: {e}"),
+                }
+                Ok(true)
+            })
+            .map_err(|e| anyhow::format_err!("Failed to iterate out msgs: {e}"))?;
+        Ok(())
+    }
+
+    /// Removes and returns messages that are due at the given unixtime
+    /// in the order they were scheduled.
+    pub fn take_due(&mut self, unixtime: u32) -> Vec<WrappedMessage> {
+        let not_due = match unixtime.checked_add(1) {
+            Some(next) => self.messages.split_off(&next),
+            None => BTreeMap::new(),
+        };
+        let due = std::mem::replace(&mut self.messages, not_due);
+        let due = due.into_values().flatten().collect::<Vec<_>>();
+        self.len -= due.len();
+        due
+    }
+}
+
+/// Number and sum of the values of the messages scheduled by the transaction
+/// of the timer contract. The value is taken from the timer contract balance
+/// when the request is sent, so the due messages don't create value.
+pub fn scheduled_requests(
+    transaction: &Transaction,
+    timer_contract: &AccountAddress,
+) -> anyhow::Result<(usize, CurrencyCollection)> {
+    let mut count = 0;
+    let mut value = CurrencyCollection::default();
+    transaction
+        .iterate_out_msgs(|message| {
+            if let Some(Ok((_, scheduled))) = timer_request(&message, timer_contract) {
+                count += 1;
+                if let Some(header) = scheduled.message.int_header() {
+                    value.add(&header.value)?;
+                }
+            }
+            Ok(true)
+        })
+        .map_err(|e| anyhow::format_err!("Failed to sum scheduled messages value: {e}"))?;
+    Ok((count, value))
+}
+
+// Returns None if the message is not a timer contract request
+fn timer_request(
+    message: &Message,
+    timer_contract: &AccountAddress,
+) -> Option<anyhow::Result<(u32, WrappedMessage)>> {
+    if message.ext_out_header().is_none() || !is_sent_by(message, timer_contract) {
+        return None;
+    }
+    Some(decode_timer_message(message, timer_contract))
+}
+
+/// Timer contract schedules a message by sending an external outbound
+/// message with body `due_at: uint32` and the internal message to deliver
+/// in the first reference. The scheduled message must be sent on behalf
+/// of the timer contract.
+fn decode_timer_message(
+    out_message: &Message,
+    timer_contract: &AccountAddress,
+) -> anyhow::Result<(u32, WrappedMessage)> {
+    let mut body =
+        out_message.body().ok_or_else(|| anyhow::format_err!("Timer message has no body"))?;
+    let due_at = body
+        .get_next_u32()
+        .map_err(|e| anyhow::format_err!("Failed to read timer message due time: {e}"))?;
+    let message_cell = body
+        .reference(0)
+        .map_err(|e| anyhow::format_err!("Timer message has no scheduled message: {e}"))?;
+    let message = Message::construct_from_cell(message_cell)
+        .map_err(|e| anyhow::format_err!("Failed to construct scheduled message: {e}"))?;
+    if message.int_header().is_none() {
+        anyhow::bail!("Scheduled message is not internal");
+    }
+    if !is_sent_by(&message, timer_contract) {
+        anyhow::bail!("Scheduled message is not sent by the timer contract");
+    }
+    if message.int_dst_account_id().is_none() {
+        anyhow::bail!("Scheduled message has invalid destination");
+    }
+    Ok((due_at, WrappedMessage { message }))
+}
+
+fn is_sent_by(message: &Message, account: &AccountAddress) -> bool {
+    message.src().is_some_and(|src| AccountAddress::from(src.address()) == *account)
+}
+
+#[cfg(test)]
+mod tests {
+    use tvm_block::ExtOutMessageHeader;
+    use tvm_block::InternalMessageHeader;
+    use tvm_block::MsgAddressExt;
+    use tvm_block::MsgAddressInt;
+    use tvm_block::Serializable;
+    use tvm_types::AccountId;
+    use tvm_types::BuilderData;
+    use tvm_types::SliceData;
+    use tvm_types::UInt256;
+
+    use super::*;
+
+    fn address(id: u8) -> MsgAddressInt {
+        MsgAddressInt::with_standart(None, 0, AccountId::from(UInt256::from([id; 32]))).unwrap()
+    }
+
+    fn request_message(src: u8, due_at: u32, value: u64) -> Message {
+        let header = InternalMessageHeader::with_addresses(
+            address(src),
+            address(2),
+            CurrencyCollection::with_grams(value),
+        );
+        let mut body = BuilderData::new();
+        body.append_u32(due_at).unwrap();
+        body.checked_append_reference(Message::with_int_header(header).serialize().unwrap())
+            .unwrap();
+        Message::with_ext_out_header_and_body(
+            ExtOutMessageHeader::with_addresses(address(src), MsgAddressExt::default()),
+            SliceData::load_builder(body).unwrap(),
+        )
+    }
+
+    fn message(dst: u8) -> WrappedMessage {
+        let header = InternalMessageHeader::with_addresses(
+            address(1),
+            address(dst),
+            CurrencyCollection::default(),
+        );
+        WrappedMessage { message: Message::with_int_header(header) }
+    }
+
+    #[test]
+    fn test_take_due_messages() {
+        let mut queue = DeferredMessageQueue::default();
+        queue.schedule(20, message(1)).unwrap();
+        queue.schedule(10, message(2)).unwrap();
+        queue.schedule(10, message(3)).unwrap();
+        assert_eq!(queue.len(), 3);
+
+        assert!(queue.take_due(9).is_empty());
+        assert_eq!(queue.take_due(10), vec![message(2), message(3)]);
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue.take_due(u32::MAX), vec![message(1)]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_queue_capacity_and_owner() {
+        let mut queue = DeferredMessageQueue::default();
+        assert_eq!(queue.owner(), None);
+        assert_eq!(queue.remaining_capacity(), MAX_DEFERRED_MESSAGES);
+        queue.schedule(10, message(2)).unwrap();
+        assert_eq!(queue.owner(), Some(AccountAddress::from(address(1).address())));
+        assert_eq!(queue.remaining_capacity(), MAX_DEFERRED_MESSAGES - 1);
+        queue.take_due(10);
+        assert_eq!(queue.owner(), None);
+    }
+
+    #[test]
+    fn test_scheduled_requests() {
+        let timer_contract = AccountAddress::from(address(1).address());
+        let mut transaction = Transaction::default();
+        transaction.add_out_message(&request_message(1, 10, 3)).unwrap();
+        transaction.add_out_message(&request_message(1, 20, 4)).unwrap();
+        // Requests of other accounts are not scheduled
+        transaction.add_out_message(&request_message(3, 10, 5)).unwrap();
+        assert_eq!(
+            scheduled_requests(&transaction, &timer_contract).unwrap(),
+            (2, CurrencyCollection::with_grams(7))
+        );
+    }
+}
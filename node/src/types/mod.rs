@@ -13,6 +13,7 @@ mod block_info;
 mod block_seq_no;
 pub mod bp_selector;
 mod dapp_identifier;
+pub mod deferred_messages;
 mod message_storage;
 mod rnd_seed;
 mod thread_identifier;
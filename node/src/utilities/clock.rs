@@ -0,0 +1,216 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::net::UdpSocket;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::config::ClockSkewAction;
+use crate::config::ClockSkewConfig;
+use crate::helper::metrics::BlockProductionMetrics;
+use crate::helper::SHUTDOWN_FLAG;
+
+const NTP_PACKET_SIZE: usize = 48;
+// Seconds between the NTP epoch (1900) and the Unix epoch (1970)
+const NTP_UNIX_EPOCH_DELTA_SECS: i64 = 2_208_988_800;
+const NTP_QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Node time source. Block production, epoch touching and sync use it
+/// instead of the system time, so the measured NTP offset is applied in one
+/// place.
+pub struct Clock {
+    offset_ms: AtomicI64,
+    compensate: AtomicBool,
+    // Latest returned time, the time never goes back when the compensation
+    // is switched off or the offset decreases
+    last_ms: AtomicU64,
+    production_refused: AtomicBool,
+}
+
+pub static CLOCK: Clock = Clock::new();
+
+impl Clock {
+    const fn new() -> Self {
+        Self {
+            offset_ms: AtomicI64::new(0),
+            compensate: AtomicBool::new(false),
+            last_ms: AtomicU64::new(0),
+            production_refused: AtomicBool::new(false),
+        }
+    }
+
+    /// Unix time in milliseconds, corrected by the measured offset when
+    /// compensation is enabled. The returned time never decreases.
+    pub fn now_ms(&self) -> u64 {
+        let local = telemetry_utils::now_ms();
+        let now = if self.compensate.load(Ordering::Relaxed) {
+            local.saturating_add_signed(self.offset_ms.load(Ordering::Relaxed))
+        } else {
+            local
+        };
+        self.last_ms.fetch_max(now, Ordering::Relaxed).max(now)
+    }
+
+    pub fn now_secs(&self) -> u32 {
+        (self.now_ms() / 1000) as u32
+    }
+
+    /// Last measured offset of the NTP time from the local time.
+    pub fn offset_ms(&self) -> i64 {
+        self.offset_ms.load(Ordering::Relaxed)
+    }
+
+    pub fn ensure_production_allowed(&self) -> anyhow::Result<()> {
+        if self.production_refused.load(Ordering::Relaxed) {
+            anyhow::bail!(
+                "Local clock is skewed by {}ms from NTP time, block production is refused",
+                self.offset_ms()
+            );
+        }
+        Ok(())
+    }
+
+    fn update(&self, offset_ms: i64, config: &ClockSkewConfig) {
+        let exceeded = offset_ms.unsigned_abs() > config.max_skew_millis;
+        self.offset_ms.store(offset_ms, Ordering::Relaxed);
+        let (compensate, refuse) = match config.action {
+            ClockSkewAction::RefuseProduction => (false, exceeded),
+            // Compensation is switched off only when the offset drops well below
+            // the threshold, so it doesn't flap while the offset hovers around it
+            ClockSkewAction::Compensate => {
+                let compensating = self.compensate.load(Ordering::Relaxed)
+                    && offset_ms.unsigned_abs() > config.max_skew_millis / 2;
+                (exceeded || compensating, false)
+            }
+        };
+        self.compensate.store(compensate, Ordering::Relaxed);
+        self.production_refused.store(refuse, Ordering::Relaxed);
+        if exceeded {
+            tracing::warn!(
+                "Local clock is skewed by {offset_ms}ms from NTP time (max {}ms), action: {:?}",
+                config.max_skew_millis,
+                config.action
+            );
+        }
+    }
+}
+
+/// Measures the local clock offset every `check_interval_sec`. When no
+/// server responds the last measurement is kept.
+pub fn start_clock_skew_monitor(
+    config: ClockSkewConfig,
+    metrics: Option<BlockProductionMetrics>,
+) -> anyhow::Result<()> {
+    if config.ntp_servers.is_empty() {
+        tracing::info!("Clock skew monitor is disabled: no NTP servers configured");
+        return Ok(());
+    }
+    let check_interval = Duration::from_secs(config.check_interval_sec);
+    let mut last_check: Option<Instant> = None;
+    loop {
+        if SHUTDOWN_FLAG.get() == Some(&true) {
+            return Ok(());
+        }
+        if last_check.is_some_and(|last_check| last_check.elapsed() < check_interval) {
+            std::thread::sleep(SHUTDOWN_POLL_INTERVAL);
+            continue;
+        }
+        last_check = Some(Instant::now());
+        match query_ntp_servers(&config.ntp_servers) {
+            Ok(offset_ms) => {
+                tracing::trace!("Local clock offset from NTP time: {offset_ms}ms");
+                CLOCK.update(offset_ms, &config);
+                metrics.as_ref().inspect(|m| m.report_clock_offset(offset_ms));
+            }
+            Err(err) => tracing::warn!("Failed to measure local clock offset: {err}"),
+        }
+    }
+}
+
+fn query_ntp_servers(servers: &[String]) -> anyhow::Result<i64> {
+    let mut last_error = anyhow::format_err!("No NTP servers");
+    for server in servers {
+        match query_ntp_offset_ms(server) {
+            Ok(offset_ms) => return Ok(offset_ms),
+            Err(err) => last_error = anyhow::format_err!("{server}: {err}"),
+        }
+    }
+    Err(last_error)
+}
+
+/// SNTP (RFC 4330) request, returns the offset of the server time from the
+/// local time.
+fn query_ntp_offset_ms(server: &str) -> anyhow::Result<i64> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.set_read_timeout(Some(NTP_QUERY_TIMEOUT))?;
+    socket.connect(server)?;
+    let mut request = [0u8; NTP_PACKET_SIZE];
+    // LI = 0, VN = 4, Mode = 3 (client)
+    request[0] = 0x23;
+    let originate_ms = telemetry_utils::now_ms() as i64;
+    socket.send(&request)?;
+    let mut response = [0u8; NTP_PACKET_SIZE];
+    let len = socket.recv(&mut response)?;
+    let destination_ms = telemetry_utils::now_ms() as i64;
+    anyhow::ensure!(len == NTP_PACKET_SIZE, "Invalid NTP response size {len}");
+    anyhow::ensure!(response[0] & 0x7 == 4, "NTP response is not from a server");
+    // Stratum 0 is a kiss-o'-death response
+    anyhow::ensure!(response[1] != 0, "NTP server refused the request");
+    let receive_ms = ntp_timestamp_ms(&response[32..40]);
+    let transmit_ms = ntp_timestamp_ms(&response[40..48]);
+    Ok(ntp_offset_ms(originate_ms, receive_ms, transmit_ms, destination_ms))
+}
+
+fn ntp_timestamp_ms(bytes: &[u8]) -> i64 {
+    let secs = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as i64;
+    let fraction = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as i64;
+    (secs - NTP_UNIX_EPOCH_DELTA_SECS) * 1000 + ((fraction * 1000) >> 32)
+}
+
+fn ntp_offset_ms(originate: i64, receive: i64, transmit: i64, destination: i64) -> i64 {
+    ((receive - originate) + (transmit - destination)) / 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ntp_offset() {
+        // Server is 1s ahead, 100ms round trip
+        assert_eq!(ntp_offset_ms(10_000, 11_050, 11_050, 10_100), 1000);
+        // Unix epoch plus 1.5s
+        let mut timestamp = [0u8; 8];
+        timestamp[..4].copy_from_slice(&((NTP_UNIX_EPOCH_DELTA_SECS + 1) as u32).to_be_bytes());
+        timestamp[4..].copy_from_slice(&(u32::MAX / 2 + 1).to_be_bytes());
+        assert_eq!(ntp_timestamp_ms(&timestamp), 1500);
+    }
+
+    #[test]
+    fn test_clock_update() {
+        let clock = Clock::new();
+        let config = ClockSkewConfig::default();
+        clock.update(100, &config);
+        assert!(clock.ensure_production_allowed().is_ok());
+        clock.update(-1000, &config);
+        assert!(clock.ensure_production_allowed().is_err());
+
+        let config = ClockSkewConfig { action: ClockSkewAction::Compensate, ..config };
+        clock.update(60_000, &config);
+        assert!(clock.ensure_production_allowed().is_ok());
+        let compensated = clock.now_ms();
+        assert!(compensated >= telemetry_utils::now_ms() + 59_000);
+
+        // Compensation stays on near the threshold
+        clock.update(400, &config);
+        assert!(clock.compensate.load(Ordering::Relaxed));
+        clock.update(100, &config);
+        assert!(!clock.compensate.load(Ordering::Relaxed));
+        assert!(clock.now_ms() >= compensated);
+    }
+}
@@ -0,0 +1,17 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use http_server::ApiError;
+
+/// Typed errors of the node public APIs. Codes are stable and may be used
+/// by downstream services, changing them is a breaking change.
+pub trait ErrorCode: std::fmt::Display {
+    fn code(&self) -> &'static str;
+
+    /// The same call may succeed later without any changes on the caller side.
+    fn is_retryable(&self) -> bool;
+
+    fn to_api_error(&self) -> ApiError {
+        ApiError::new(self.code(), self.to_string(), self.is_retryable())
+    }
+}
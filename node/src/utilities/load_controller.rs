@@ -0,0 +1,213 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use telemetry_utils::mpsc::InstrumentedSender;
+
+use crate::config::LoadSheddingConfig;
+use crate::config::SheddableSubsystem;
+use crate::helper::SHUTDOWN_FLAG;
+use crate::node::services::finalization::event_log::FinalizationEventConsumer;
+use crate::node::NodeIdentifier;
+use crate::types::ThreadIdentifier;
+
+const ARCHIVE_QUEUE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Disables optional subsystems one by one in the configured priority order
+/// while production and verification deadlines of a thread are missed, and
+/// restores them in the reverse order when the pressure drops. Deadlines are
+/// measured per thread: transaction traces are shed only for the threads
+/// under pressure, node-wide subsystems while any thread sheds them.
+pub struct LoadController {
+    state: Mutex<LoadState>,
+    shed: HashMap<SheddableSubsystem, Arc<AtomicBool>>,
+}
+
+#[derive(Default)]
+struct LoadState {
+    config: LoadSheddingConfig,
+    threads: HashMap<ThreadIdentifier, ThreadLoad>,
+}
+
+#[derive(Default)]
+struct ThreadLoad {
+    // Last deadline outcomes, true for a missed one
+    outcomes: VecDeque<bool>,
+    shed_count: usize,
+}
+
+pub static LOAD_CONTROLLER: LazyLock<LoadController> = LazyLock::new(LoadController::new);
+
+impl LoadController {
+    fn new() -> Self {
+        let shed =
+            [SheddableSubsystem::Archive, SheddableSubsystem::Api, SheddableSubsystem::TxTraces]
+                .into_iter()
+                .map(|subsystem| (subsystem, Arc::new(AtomicBool::new(false))))
+                .collect();
+        Self { state: Mutex::new(LoadState::default()), shed }
+    }
+
+    /// Replaces the config and restores all subsystems.
+    pub fn configure(&self, config: LoadSheddingConfig) {
+        let mut state = self.state.lock();
+        *state = LoadState { config, ..Default::default() };
+        for flag in self.shed.values() {
+            flag.store(false, Ordering::Relaxed);
+        }
+    }
+
+    /// Subsystem is shed for any thread.
+    pub fn is_shed(&self, subsystem: SheddableSubsystem) -> bool {
+        self.shed[&subsystem].load(Ordering::Relaxed)
+    }
+
+    pub fn is_shed_for_thread(
+        &self,
+        thread_id: &ThreadIdentifier,
+        subsystem: SheddableSubsystem,
+    ) -> bool {
+        if !self.is_shed(subsystem) {
+            return false;
+        }
+        let state = self.state.lock();
+        let shed_count = state.threads.get(thread_id).map_or(0, |load| load.shed_count);
+        state.config.priority[..shed_count].contains(&subsystem)
+    }
+
+    /// Flag for the components outside the node, it's set while the
+    /// subsystem is disabled.
+    pub fn shed_flag(&self, subsystem: SheddableSubsystem) -> Arc<AtomicBool> {
+        self.shed[&subsystem].clone()
+    }
+
+    /// Reports a block production or verification of the thread finished in
+    /// time or not.
+    pub fn report_deadline(&self, thread_id: &ThreadIdentifier, missed: bool) {
+        let mut state = self.state.lock();
+        let LoadState { config, threads } = &mut *state;
+        if config.priority.is_empty() || config.window == 0 {
+            return;
+        }
+        let load = threads.entry(*thread_id).or_default();
+        load.outcomes.push_back(missed);
+        if load.outcomes.len() < config.window {
+            return;
+        }
+        let misses = load.outcomes.iter().filter(|missed| **missed).count();
+        let miss_ratio = misses as f64 / load.outcomes.len() as f64;
+        load.outcomes.pop_front();
+        // The window is cleared on each change, so the effect of the change is
+        // measured over a full window before the next one
+        if miss_ratio >= config.shed_miss_ratio && load.shed_count < config.priority.len() {
+            let subsystem = config.priority[load.shed_count];
+            load.shed_count += 1;
+            load.outcomes.clear();
+            tracing::warn!(
+                "Load shedding: {subsystem:?} is disabled for thread {thread_id:?}, {misses} of {} deadlines missed",
+                config.window
+            );
+        } else if miss_ratio <= config.restore_miss_ratio && load.shed_count > 0 {
+            load.shed_count -= 1;
+            let subsystem = config.priority[load.shed_count];
+            load.outcomes.clear();
+            tracing::info!("Load shedding: {subsystem:?} is restored for thread {thread_id:?}");
+        } else {
+            return;
+        }
+        let max_shed_count = threads.values().map(|load| load.shed_count).max().unwrap_or(0);
+        for (i, subsystem) in config.priority.iter().enumerate() {
+            self.shed[subsystem].store(i < max_shed_count, Ordering::Relaxed);
+        }
+    }
+
+    fn archive_queue_limit(&self) -> usize {
+        self.state.lock().config.archive_queue_limit
+    }
+}
+
+/// Forwards finalized blocks from the event log to the block managers feed.
+/// Blocks stay in the log while the archive is shed, except the ones over
+/// the queue limit, and are sent in order once it's restored.
+pub fn forward_archive_feed(
+    mut consumer: FinalizationEventConsumer,
+    tx: InstrumentedSender<(NodeIdentifier, Vec<u8>)>,
+) -> anyhow::Result<()> {
+    loop {
+        if SHUTDOWN_FLAG.get() == Some(&true) {
+            return Ok(());
+        }
+        if LOAD_CONTROLLER.is_shed(SheddableSubsystem::Archive)
+            && consumer.backlog() <= LOAD_CONTROLLER.archive_queue_limit() as u64
+        {
+            std::thread::sleep(ARCHIVE_QUEUE_POLL_INTERVAL);
+            continue;
+        }
+        let Some(event) = consumer.next_event(ARCHIVE_QUEUE_POLL_INTERVAL)? else {
+            continue;
+        };
+        if tx.send((event.producer_id.clone(), event.block.clone())).is_err() {
+            // The feed is closed on shutdown, the event is delivered after restart
+            return Ok(());
+        }
+        consumer.ack(&event)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::BlockIdentifier;
+
+    #[test]
+    fn test_load_shedding() {
+        let thread_id = ThreadIdentifier::default();
+        let controller = LoadController::new();
+        controller.configure(LoadSheddingConfig {
+            priority: vec![SheddableSubsystem::TxTraces, SheddableSubsystem::Api],
+            window: 4,
+            shed_miss_ratio: 0.5,
+            restore_miss_ratio: 0.0,
+            ..Default::default()
+        });
+        for missed in [true, false, true, false] {
+            controller.report_deadline(&thread_id, missed);
+        }
+        assert!(controller.is_shed(SheddableSubsystem::TxTraces));
+        assert!(!controller.is_shed(SheddableSubsystem::Api));
+
+        // Deadlines of other threads don't affect the thread under pressure
+        let other_thread_id = ThreadIdentifier::new(&BlockIdentifier::default(), 1);
+        for _ in 0..4 {
+            controller.report_deadline(&other_thread_id, false);
+        }
+        assert!(controller.is_shed_for_thread(&thread_id, SheddableSubsystem::TxTraces));
+        assert!(!controller.is_shed_for_thread(&other_thread_id, SheddableSubsystem::TxTraces));
+
+        for _ in 0..4 {
+            controller.report_deadline(&thread_id, true);
+        }
+        assert!(controller.is_shed(SheddableSubsystem::Api));
+        assert!(controller.shed_flag(SheddableSubsystem::Api).load(Ordering::Relaxed));
+
+        for _ in 0..4 {
+            controller.report_deadline(&thread_id, false);
+        }
+        assert!(!controller.is_shed(SheddableSubsystem::Api));
+        assert!(controller.is_shed(SheddableSubsystem::TxTraces));
+
+        for _ in 0..4 {
+            controller.report_deadline(&thread_id, false);
+        }
+        assert!(!controller.is_shed(SheddableSubsystem::TxTraces));
+        assert!(!controller.is_shed(SheddableSubsystem::Archive));
+    }
+}
@@ -1,7 +1,11 @@
+mod error_code;
 mod fixed_size_hash_map;
 mod fixed_size_hash_set;
 
+pub use error_code::ErrorCode;
 pub use fixed_size_hash_map::FixedSizeHashMap;
 pub use fixed_size_hash_set::FixedSizeHashSet;
+pub mod clock;
 pub mod guarded;
+pub mod load_controller;
 pub mod thread_spawn_critical;
@@ -12,10 +12,13 @@ use network::pub_sub::CertStore;
 use network::pub_sub::PrivateKeyFile;
 use serde::Deserialize;
 use serde::Serialize;
+use telemetry_config::TelemetryConfig;
 use tokio::signal::unix::signal;
 use tokio::signal::unix::SignalKind;
 use transport_layer::TlsCertCache;
 
+const THREAD_ID_LEN: usize = 34;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ProxyConfig {
     pub bind: SocketAddr,
@@ -36,11 +39,31 @@ pub struct ProxyConfig {
     #[serde(default, with = "transport_layer::hex_verifying_keys")]
     pub peer_ed_pubkeys: Vec<transport_layer::VerifyingKey>,
     pub bk_addrs: Vec<SocketAddr>,
+    /// Reject peers that are neither in the current BK set nor listed in
+    /// `peer_certs`/`peer_ed_pubkeys`, including while the BK set is not loaded yet
+    #[serde(default = "default_authenticate_peers")]
+    pub authenticate_peers: bool,
     #[serde(
         serialize_with = "network::serialize_subscribe",
         deserialize_with = "network::deserialize_subscribe"
     )]
     pub subscribe: Vec<Vec<SocketAddr>>,
+    /// Hex encoded thread identifiers requested from publishers.
+    /// Empty list means all threads
+    #[serde(default)]
+    pub subscribe_threads: Vec<String>,
+    /// Sustained delivery delays from a publisher are reported to it, so it
+    /// sends only small messages until the subscription recovers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backpressure: Option<network::backpressure::BackpressureConfig>,
+    /// OpenTelemetry exporters. Unset fields are taken from the `OTEL_*`
+    /// environment variables. `metrics_enabled` is applied on config reload
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+}
+
+fn default_authenticate_peers() -> bool {
+    true
 }
 
 impl ProxyConfig {
@@ -61,7 +84,7 @@ impl ProxyConfig {
         &self,
         tls_cert_cache: Option<TlsCertCache>,
     ) -> anyhow::Result<NetworkConfig> {
-        NetworkConfig::new(
+        let mut config = NetworkConfig::new(
             self.bind,
             self.my_cert.clone(),
             self.my_key.clone(),
@@ -71,7 +94,18 @@ impl ProxyConfig {
             self.subscribe.clone(),
             vec![],
             tls_cert_cache,
-        )
+        )?;
+        config.credential.reject_unknown_peers = self.authenticate_peers;
+        for thread_id in &self.subscribe_threads {
+            let is_valid = hex::decode(thread_id).is_ok_and(|bytes| bytes.len() == THREAD_ID_LEN);
+            if !is_valid {
+                anyhow::bail!("Invalid thread identifier in subscribe_threads: {thread_id}");
+            }
+        }
+        config.subscribe_threads =
+            self.subscribe_threads.iter().map(|thread_id| thread_id.to_lowercase()).collect();
+        config.backpressure = self.backpressure;
+        Ok(config)
     }
 }
 
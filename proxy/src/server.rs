@@ -15,6 +15,7 @@ use ed25519_dalek::VerifyingKey;
 use gossip::GossipConfig;
 use network::config::NetworkConfig;
 use network::metrics::NetMetrics;
+use network::peer_identity::PeerIdentities;
 use network::pub_sub::connection::IncomingMessage;
 use network::pub_sub::connection::MessageDelivery;
 use network::pub_sub::connection::OutgoingMessage;
@@ -26,6 +27,8 @@ use network::resolver::WatchGossipConfig;
 use network::DeliveryPhase;
 use network::SendMode;
 use opentelemetry::global;
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+use telemetry_config::MetricsExportSwitch;
 use telemetry_utils::TokioMetrics;
 use tokio::task::JoinHandle;
 use transport_layer::msquic::MsQuicTransport;
@@ -90,15 +93,27 @@ async fn tokio_main() -> anyhow::Result<()> {
     let args = CliArgs::parse();
     tracing::info!("Config path: {}", args.config.as_path().display());
 
+    // Config errors are reported by `run`
+    let telemetry = ProxyConfig::from_file(&args.config)
+        .map(|config| config.telemetry)
+        .unwrap_or_default()
+        .with_env_defaults();
+
     // Initialize the meter provider for OpenTelemetry
-    let meter_provider = telemetry_utils::init_meter_provider();
+    let (meter_provider, metrics_export) = match telemetry.meter_provider("acki-nacki-proxy")? {
+        Some((meter_provider, metrics_export)) => (meter_provider, Some(metrics_export)),
+        None => {
+            tracing::info!("No OTEL exporter endpoint found, metrics not exported.");
+            (SdkMeterProvider::builder().build(), None)
+        }
+    };
     global::set_meter_provider(meter_provider.clone());
 
     // Create a NetMetrics instance using the meter provider
     let net_metrics = Some(NetMetrics::new(&global::meter("node")));
     let _tokio_metrics = TokioMetrics::new(&global::meter("node"));
 
-    let result = args.run(net_metrics).await;
+    let result = args.run(net_metrics, metrics_export).await;
 
     // Shutdown the meter provider gracefully
     meter_provider.shutdown().ok();
@@ -123,7 +138,11 @@ impl FromStr for NodeId {
 }
 
 impl CliArgs {
-    async fn run(self, net_metrics: Option<NetMetrics>) -> anyhow::Result<()> {
+    async fn run(
+        self,
+        net_metrics: Option<NetMetrics>,
+        metrics_export: Option<MetricsExportSwitch>,
+    ) -> anyhow::Result<()> {
         let tls_cert_cache = TlsCertCache::new()?;
         let config = ProxyConfig::from_file(&self.config)?;
         tracing::info!("Loaded configuration: {}", serde_json::to_string_pretty(&config)?);
@@ -140,6 +159,7 @@ impl CliArgs {
             tokio::sync::watch::channel(WatchGossipConfig { trusted_pubkeys: HashSet::new() });
         let (subscribe_tx, subscribe_rx) = tokio::sync::watch::channel(Vec::new());
         let (peers_tx, _) = tokio::sync::watch::channel(HashMap::new());
+        let (identities_tx, identities_rx) = tokio::sync::watch::channel(PeerIdentities::new());
 
         let (gossip_handle, gossip_rest_handle) =
             gossip::run(shutdown_tx.subscribe(), gossip_config_rx, UdpTransport).await?;
@@ -154,6 +174,7 @@ impl CliArgs {
                 gossip_handle.chitchat(),
                 subscribe_tx.clone(),
                 peers_tx,
+                identities_tx,
                 None,
             ),
         );
@@ -164,6 +185,9 @@ impl CliArgs {
 
         let config_reload_handle: JoinHandle<anyhow::Result<()>> =
             tokio::spawn(config_reload_handler(config_tx, self.config.clone()));
+        if let Some(metrics_export) = metrics_export {
+            tokio::spawn(watch_metrics_export(config_rx.clone(), metrics_export));
+        }
 
         let multiplexer_handle = tokio::spawn(message_multiplexor(
             net_metrics.clone(),
@@ -181,6 +205,8 @@ impl CliArgs {
             subscribe_rx,
             outgoing_messages_tx,
             IncomingSender::AsyncUnbounded(incoming_messages_tx),
+            None,
+            identities_rx,
         ));
 
         let client: reqwest::Client = reqwest::Client::builder()
@@ -244,6 +270,19 @@ impl CliArgs {
     }
 }
 
+// Applies `telemetry.metrics_enabled` of the reloaded config
+async fn watch_metrics_export(
+    mut config_rx: tokio::sync::watch::Receiver<ProxyConfig>,
+    metrics_export: MetricsExportSwitch,
+) {
+    loop {
+        metrics_export.set_enabled(config_rx.borrow().telemetry.metrics_enabled);
+        if config_rx.changed().await.is_err() {
+            return;
+        }
+    }
+}
+
 async fn dispatch_hot_reload(
     tls_cert_cache: Option<TlsCertCache>,
     mut shutdown_rx: tokio::sync::watch::Receiver<bool>,
@@ -307,6 +346,9 @@ fn dispatch_configs(
     bk_set.extend(network_config.credential.trusted_ed_pubkeys.iter().cloned());
     let trusted_ed_pubkeys = bk_set.into_iter().collect::<HashSet<_>>();
     network_config.credential.trusted_ed_pubkeys = trusted_ed_pubkeys.clone();
+    if network_config.credential.no_cert_are_trusted() {
+        tracing::warn!("BK set is not loaded yet, incoming peers will be rejected");
+    }
     Some((
         network_config,
         config.gossip.clone(),
@@ -0,0 +1,41 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::hex_bytes;
+use crate::BlockIdentifier;
+use crate::BlockSeqNo;
+
+/// Hash of the signed block envelope.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct EnvelopeHash(
+    #[serde(
+        serialize_with = "hex_bytes::serialize_hash",
+        deserialize_with = "hex_bytes::deserialize_hash"
+    )]
+    pub [u8; 32],
+);
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum AttestationTargetType {
+    Primary,
+    Fallback,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct AttestationData {
+    pub parent_block_id: BlockIdentifier,
+    pub block_id: BlockIdentifier,
+    pub block_seq_no: BlockSeqNo,
+    pub envelope_hash: EnvelopeHash,
+    pub target_type: AttestationTargetType,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct AckData {
+    pub block_id: BlockIdentifier,
+    pub block_seq_no: BlockSeqNo,
+}
@@ -0,0 +1,24 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use alloc::vec::Vec;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::hex_bytes;
+
+/// Data signed by the aggregated BLS signature of the signers.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Envelope<TData> {
+    /// Aggregated BLS (min_pk) signature
+    #[serde(
+        serialize_with = "hex_bytes::serialize",
+        deserialize_with = "hex_bytes::deserialize_vec"
+    )]
+    pub aggregated_signature: Vec<u8>,
+    /// Signer index in the block keeper set and the number of its signatures
+    /// aggregated, sorted by the signer index
+    pub signature_occurrences: Vec<(u16, u16)>,
+    pub data: TData,
+}
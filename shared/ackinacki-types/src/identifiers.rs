@@ -0,0 +1,89 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use alloc::string::String;
+use core::fmt;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::hex_bytes;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct BlockIdentifier(
+    #[serde(
+        serialize_with = "hex_bytes::serialize",
+        deserialize_with = "hex_bytes::deserialize_array"
+    )]
+    pub [u8; 32],
+);
+
+/// Thread identifier: the thread index (big endian) followed by the
+/// identifier of the block the thread was spawned in.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ThreadIdentifier(
+    #[serde(
+        serialize_with = "hex_bytes::serialize",
+        deserialize_with = "hex_bytes::deserialize_array"
+    )]
+    pub [u8; 34],
+);
+
+#[derive(
+    Clone, Copy, PartialEq, Eq, Hash, Default, PartialOrd, Ord, Debug, Serialize, Deserialize,
+)]
+pub struct BlockSeqNo(pub u32);
+
+/// Node identifier, serialized as the hex of the node account address.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub struct NodeIdentifier(pub String);
+
+impl ThreadIdentifier {
+    pub fn spawning_block_id(&self) -> BlockIdentifier {
+        let mut block_id = [0; 32];
+        block_id.copy_from_slice(&self.0[2..]);
+        BlockIdentifier(block_id)
+    }
+}
+
+impl Default for ThreadIdentifier {
+    fn default() -> Self {
+        Self([0; 34])
+    }
+}
+
+impl fmt::Display for BlockIdentifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self.0))
+    }
+}
+
+impl fmt::Debug for BlockIdentifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Display for ThreadIdentifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self.0))
+    }
+}
+
+impl fmt::Debug for ThreadIdentifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Display for BlockSeqNo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for NodeIdentifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
@@ -0,0 +1,67 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+//! Wire types of the node network messages for the external clients
+//! (explorers, wallets), buildable for `wasm32-unknown-unknown`.
+//!
+//! The types mirror the serde layouts of the node types, so the bincode
+//! encoded messages are decoded without the node crate. The node tests check
+//! that the layouts match. Network frames carry the messages zstd compressed
+//! above a size threshold, the data is expected to be decompressed.
+//!
+//! Identifiers, hashes and signatures are hex strings in the human-readable
+//! formats (JSON, JS values).
+
+#![cfg_attr(not(feature = "wasm"), no_std)]
+
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::string::ToString;
+use core::fmt;
+
+use serde::de::DeserializeOwned;
+
+mod attestation;
+mod envelope;
+mod hex_bytes;
+mod identifiers;
+mod network_message;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+pub use attestation::AckData;
+pub use attestation::AttestationData;
+pub use attestation::AttestationTargetType;
+pub use attestation::EnvelopeHash;
+pub use envelope::Envelope;
+pub use identifiers::BlockIdentifier;
+pub use identifiers::BlockSeqNo;
+pub use identifiers::NodeIdentifier;
+pub use identifiers::ThreadIdentifier;
+pub use network_message::NetBlock;
+pub use network_message::NetworkMessage;
+pub use network_message::ProducerSelectionConfig;
+pub use network_message::ProducerSelector;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError(String);
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to decode: {}", self.0)
+    }
+}
+
+impl core::error::Error for DecodeError {}
+
+/// Decodes a value encoded by the node (bincode with the default options).
+pub fn decode<T: DeserializeOwned>(data: &[u8]) -> Result<T, DecodeError> {
+    bincode::serde::decode_from_slice(data, bincode::config::legacy())
+        .map(|(value, _)| value)
+        .map_err(|e| DecodeError(e.to_string()))
+}
+
+pub fn decode_network_message(data: &[u8]) -> Result<NetworkMessage, DecodeError> {
+    decode(data)
+}
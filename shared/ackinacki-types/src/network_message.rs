@@ -0,0 +1,171 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::fmt;
+
+use serde::de;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::hex_bytes;
+use crate::AckData;
+use crate::AttestationData;
+use crate::BlockIdentifier;
+use crate::BlockSeqNo;
+use crate::Envelope;
+use crate::NodeIdentifier;
+use crate::ThreadIdentifier;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct ProducerSelectionConfig {
+    pub stake_weighted: bool,
+    pub cooldown: u64,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct ProducerSelector {
+    pub rng_seed_block_id: BlockIdentifier,
+    pub index: u64,
+    pub selection: ProducerSelectionConfig,
+}
+
+/// Candidate block broadcasted by its producer.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct NetBlock {
+    pub producer_id: NodeIdentifier,
+    pub producer_selector: Option<ProducerSelector>,
+    pub thread_id: ThreadIdentifier,
+    pub identifier: BlockIdentifier,
+    pub seq_no: BlockSeqNo,
+    /// Signed block envelope, encoded by the node
+    #[serde(
+        serialize_with = "hex_bytes::serialize",
+        deserialize_with = "hex_bytes::deserialize_vec"
+    )]
+    pub envelope_data: Vec<u8>,
+}
+
+/// Messages the nodes exchange. Variants are declared in the order of the
+/// node variant indexes.
+///
+/// Payloads of `Nack`, `ExternalMessage` and `AuthoritySwitchProtocol`
+/// depend on the block and TVM message layouts and are not decoded, so the
+/// encoding of these variants is not the node one. Serialization is meant
+/// for the human-readable formats.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub enum NetworkMessage {
+    Candidate(NetBlock),
+    Ack((Envelope<AckData>, ThreadIdentifier)),
+    Nack,
+    ExternalMessage,
+    NodeJoining((NodeIdentifier, ThreadIdentifier)),
+    BlockAttestation((Envelope<AttestationData>, ThreadIdentifier)),
+    BlockRequest {
+        inclusive_from: BlockSeqNo,
+        exclusive_to: BlockSeqNo,
+        requester: NodeIdentifier,
+        thread_id: ThreadIdentifier,
+        at_least_n_blocks: Option<u64>,
+    },
+    SyncFrom((BlockSeqNo, ThreadIdentifier)),
+    SyncFinalized(
+        (
+            BlockIdentifier,
+            BlockSeqNo,
+            BTreeMap<ThreadIdentifier, BlockIdentifier>,
+            ThreadIdentifier,
+        ),
+    ),
+    ResentCandidate((NetBlock, NodeIdentifier)),
+    AuthoritySwitchProtocol,
+    StartSynchronization,
+}
+
+impl NetworkMessage {
+    pub fn thread_id(&self) -> Option<&ThreadIdentifier> {
+        use NetworkMessage::*;
+        match self {
+            Candidate(net_block) | ResentCandidate((net_block, _)) => Some(&net_block.thread_id),
+            Ack((_, thread_id))
+            | NodeJoining((_, thread_id))
+            | BlockAttestation((_, thread_id))
+            | BlockRequest { thread_id, .. }
+            | SyncFrom((_, thread_id))
+            | SyncFinalized((_, _, _, thread_id)) => Some(thread_id),
+            Nack | ExternalMessage | AuthoritySwitchProtocol | StartSynchronization => None,
+        }
+    }
+}
+
+const VARIANTS: &[&str] = &[
+    "Candidate",
+    "Ack",
+    "Nack",
+    "ExternalMessage",
+    "NodeJoining",
+    "BlockAttestation",
+    "BlockRequest",
+    "SyncFrom",
+    "SyncFinalized",
+    "ResentCandidate",
+    "AuthoritySwitchProtocol",
+    "StartSynchronization",
+];
+
+impl<'de> Deserialize<'de> for NetworkMessage {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_enum("NetworkMessage", VARIANTS, NetworkMessageVisitor)
+    }
+}
+
+struct NetworkMessageVisitor;
+
+impl<'de> de::Visitor<'de> for NetworkMessageVisitor {
+    type Value = NetworkMessage;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a NetworkMessage type.")
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::EnumAccess<'de>,
+    {
+        use de::VariantAccess;
+        use NetworkMessage::*;
+        // Payloads that are not decoded are the last in the data and are
+        // left unread
+        match data.variant::<u32>()? {
+            (0, v) => v.newtype_variant().map(Candidate),
+            (1, v) => v.newtype_variant().map(Ack),
+            (2, _) => Ok(Nack),
+            (3, _) => Ok(ExternalMessage),
+            (4, v) => v.newtype_variant().map(NodeJoining),
+            (5, v) => v.newtype_variant().map(BlockAttestation),
+            (6, v) => v.newtype_variant().map(|e| {
+                let (inclusive_from, exclusive_to, requester, thread_id, at_least_n_blocks) = e;
+                BlockRequest {
+                    inclusive_from,
+                    exclusive_to,
+                    requester,
+                    thread_id,
+                    at_least_n_blocks,
+                }
+            }),
+            (7, v) => v.newtype_variant().map(SyncFrom),
+            (8, v) => v.newtype_variant().map(SyncFinalized),
+            (9, v) => v.newtype_variant().map(ResentCandidate),
+            (10, _) => Ok(AuthoritySwitchProtocol),
+            (11, v) => v.newtype_variant::<()>().map(|_| StartSynchronization),
+            (index, _) => Err(de::Error::invalid_value(
+                de::Unexpected::Unsigned(index.into()),
+                &"a NetworkMessage variant index",
+            )),
+        }
+    }
+}
@@ -0,0 +1,14 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// Decodes a network message into a plain JS object.
+#[wasm_bindgen(js_name = decodeNetworkMessage)]
+pub fn decode_network_message(data: &[u8]) -> Result<JsValue, JsError> {
+    let message = crate::decode_network_message(data).map_err(|e| JsError::new(&e.to_string()))?;
+    message
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| JsError::new(&e.to_string()))
+}
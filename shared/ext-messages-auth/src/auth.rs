@@ -26,6 +26,8 @@ use tvm_block::Serializable;
 use crate::normalize_address;
 use crate::now_plus_n_secs;
 use crate::owner_wallet::decode_signing_pubkey;
+use crate::owner_wallet::decode_wallet_signing_pubkey;
+use crate::owner_wallet::BK_OWNER_WALLET_ABI;
 use crate::root_contracts::BK_CONTRACT_ROOT_ABI;
 use crate::root_contracts::BK_CONTRACT_ROOT_ADDR;
 use crate::root_contracts::BM_CONTRACT_ROOT_ABI;
@@ -246,6 +248,21 @@ async fn get_signing_pubkey(
     signing_key
 }
 
+/// Returns the signing pubkey stored in the Block Keeper node wallet deployed
+/// at `wallet_address` (node id).
+pub async fn get_bk_wallet_signing_pubkey(
+    wallet_address: &str,
+    account_request_tx: mpsc::Sender<AccountRequest>,
+) -> anyhow::Result<Option<String>> {
+    let wallet_addr = normalize_address(wallet_address)
+        .ok_or_else(|| anyhow::format_err!("Invalid wallet address: {wallet_address}"))?;
+    let Some(wallet_account) = request_account(&wallet_addr, account_request_tx).await? else {
+        tracing::trace!("Failed to get BOC of the wallet at {wallet_addr}");
+        return Ok(None);
+    };
+    decode_wallet_signing_pubkey(&wallet_account, BK_OWNER_WALLET_ABI)
+}
+
 pub fn is_auth_required() -> bool {
     EXT_MESSAGE_AUTH_REQUIRED.load(Ordering::Relaxed)
 }
@@ -18,6 +18,17 @@ pub static BM_OWNER_WALLET_ABI: &str =
 pub fn decode_signing_pubkey(
     account: &Account,
     issuer: &TokenIssuer,
+) -> anyhow::Result<Option<String>> {
+    let abi_str = match issuer {
+        TokenIssuer::Bk(_) => BK_OWNER_WALLET_ABI,
+        TokenIssuer::Bm(_) => BM_OWNER_WALLET_ABI,
+    };
+    decode_wallet_signing_pubkey(account, abi_str)
+}
+
+pub(crate) fn decode_wallet_signing_pubkey(
+    account: &Account,
+    abi_str: &str,
 ) -> anyhow::Result<Option<String>> {
     let Some(data) = account.get_data() else {
         return Ok(None);
@@ -26,11 +37,7 @@ pub fn decode_signing_pubkey(
     let slice = slice_from_cell(data)
         .map_err(|e| anyhow::format_err!("Failed to decode Node Wallet data cell: {e}"))?;
 
-    let abi_str = match issuer {
-        TokenIssuer::Bk(_) => BK_OWNER_WALLET_ABI.to_string(),
-        TokenIssuer::Bm(_) => BM_OWNER_WALLET_ABI.to_string(),
-    };
-    let abi = tvm_client::abi::Abi::Json(abi_str).abi()?;
+    let abi = tvm_client::abi::Abi::Json(abi_str.to_string()).abi()?;
 
     let decoded = abi
         .decode_storage_fields(slice, true)
@@ -0,0 +1,66 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use opentelemetry_sdk::metrics::data::ResourceMetrics;
+use opentelemetry_sdk::metrics::exporter::PushMetricExporter;
+use opentelemetry_sdk::metrics::MetricResult;
+use opentelemetry_sdk::metrics::Temporality;
+
+/// Turns the metrics export of a meter provider off and on at runtime.
+#[derive(Clone, Debug)]
+pub struct MetricsExportSwitch(Arc<AtomicBool>);
+
+impl MetricsExportSwitch {
+    pub fn new(enabled: bool) -> Self {
+        Self(Arc::new(AtomicBool::new(enabled)))
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        if self.0.swap(enabled, Ordering::Relaxed) != enabled {
+            tracing::info!("Metrics export {}", if enabled { "enabled" } else { "disabled" });
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+// Drops collected metrics instead of exporting them while the switch is off
+pub(crate) struct SwitchableExporter<E> {
+    inner: E,
+    switch: MetricsExportSwitch,
+}
+
+impl<E> SwitchableExporter<E> {
+    pub(crate) fn new(inner: E, switch: MetricsExportSwitch) -> Self {
+        Self { inner, switch }
+    }
+}
+
+#[async_trait]
+impl<E: PushMetricExporter> PushMetricExporter for SwitchableExporter<E> {
+    async fn export(&self, metrics: &mut ResourceMetrics) -> MetricResult<()> {
+        if !self.switch.is_enabled() {
+            return Ok(());
+        }
+        self.inner.export(metrics).await
+    }
+
+    async fn force_flush(&self) -> MetricResult<()> {
+        self.inner.force_flush().await
+    }
+
+    fn shutdown(&self) -> MetricResult<()> {
+        self.inner.shutdown()
+    }
+
+    fn temporality(&self) -> Temporality {
+        self.inner.temporality()
+    }
+}
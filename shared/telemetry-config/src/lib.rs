@@ -0,0 +1,316 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+mod export;
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Context;
+pub use export::MetricsExportSwitch;
+use export::SwitchableExporter;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_otlp::WithHttpConfig;
+use opentelemetry_otlp::WithTonicConfig;
+use opentelemetry_sdk::metrics::PeriodicReader;
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+use opentelemetry_sdk::runtime::Tokio;
+use opentelemetry_sdk::trace::Sampler;
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::Resource;
+use serde::Deserialize;
+use serde::Serialize;
+
+const DEFAULT_SAMPLING_RATIO: f64 = 1.0;
+const DEFAULT_METRICS_EXPORT_INTERVAL_MILLIS: u64 = 60_000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OtlpProtocol {
+    #[serde(rename = "grpc")]
+    Grpc,
+    #[serde(rename = "http/protobuf")]
+    HttpProtobuf,
+}
+
+/// OpenTelemetry exporters configuration shared by all binaries.
+///
+/// Fields that are not set are taken from the standard `OTEL_*` environment
+/// variables (see [`TelemetryConfig::with_env_defaults`]), so deployments
+/// configured with the environment keep working.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TelemetryConfig {
+    /// Collector endpoint used for all signals if a specific one is not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub traces_endpoint: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics_endpoint: Option<String>,
+
+    /// Defaults to `grpc`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<OtlpProtocol>,
+
+    /// Headers sent with every export request (e.g. collector auth).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+
+    /// Ratio of sampled root traces, child spans follow their parent.
+    /// Defaults to 1.0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sampling_ratio: Option<f64>,
+
+    /// Overrides the default service name of the binary.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_name: Option<String>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub resource_attributes: BTreeMap<String, String>,
+
+    /// Defaults to 60000
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics_export_interval_millis: Option<u64>,
+
+    /// Metrics export can be switched off and on at runtime by the config
+    /// reload, metrics are still collected while it's off.
+    #[serde(default = "default_metrics_enabled")]
+    pub metrics_enabled: bool,
+}
+
+fn default_metrics_enabled() -> bool {
+    true
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: None,
+            traces_endpoint: None,
+            metrics_endpoint: None,
+            protocol: None,
+            headers: BTreeMap::new(),
+            sampling_ratio: None,
+            service_name: None,
+            resource_attributes: BTreeMap::new(),
+            metrics_export_interval_millis: None,
+            metrics_enabled: true,
+        }
+    }
+}
+
+impl TelemetryConfig {
+    /// Loads the config from a YAML (or JSON) file.
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let file = std::fs::File::open(path.as_ref())
+            .with_context(|| format!("Failed to open {}", path.as_ref().display()))?;
+        serde_yaml::from_reader(file).context("Failed to load telemetry config")
+    }
+
+    /// Fills fields that are not set from the `OTEL_*` environment variables.
+    pub fn with_env_defaults(self) -> Self {
+        self.with_defaults_from(|name| std::env::var(name).ok())
+    }
+
+    fn with_defaults_from(mut self, var: impl Fn(&str) -> Option<String>) -> Self {
+        self.endpoint = self.endpoint.or_else(|| var("OTEL_EXPORTER_OTLP_ENDPOINT"));
+        self.traces_endpoint =
+            self.traces_endpoint.or_else(|| var("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT"));
+        self.metrics_endpoint =
+            self.metrics_endpoint.or_else(|| var("OTEL_EXPORTER_OTLP_METRICS_ENDPOINT"));
+        self.protocol = self.protocol.or_else(|| {
+            var("OTEL_EXPORTER_OTLP_PROTOCOL").and_then(|value| match value.trim() {
+                "grpc" => Some(OtlpProtocol::Grpc),
+                "http/protobuf" => Some(OtlpProtocol::HttpProtobuf),
+                other => {
+                    tracing::warn!("Unsupported OTEL_EXPORTER_OTLP_PROTOCOL: {other}");
+                    None
+                }
+            })
+        });
+        let headers = var("OTEL_EXPORTER_OTLP_HEADERS").unwrap_or_default();
+        for (key, value) in parse_key_values(&headers) {
+            self.headers.entry(key).or_insert(value);
+        }
+        self.sampling_ratio = self
+            .sampling_ratio
+            .or_else(|| var("OTEL_TRACES_SAMPLER_ARG").and_then(|value| value.parse().ok()));
+        self.service_name = self.service_name.or_else(|| var("OTEL_SERVICE_NAME"));
+        let resource_attributes = var("OTEL_RESOURCE_ATTRIBUTES").unwrap_or_default();
+        for (key, value) in parse_key_values(&resource_attributes) {
+            self.resource_attributes.entry(key).or_insert(value);
+        }
+        self.metrics_export_interval_millis = self
+            .metrics_export_interval_millis
+            .or_else(|| var("OTEL_METRIC_EXPORT_INTERVAL").and_then(|value| value.parse().ok()));
+        self
+    }
+
+    pub fn traces_endpoint(&self) -> Option<&str> {
+        self.traces_endpoint.as_deref().or(self.endpoint.as_deref())
+    }
+
+    pub fn metrics_endpoint(&self) -> Option<&str> {
+        self.metrics_endpoint.as_deref().or(self.endpoint.as_deref())
+    }
+
+    pub fn protocol(&self) -> OtlpProtocol {
+        self.protocol.unwrap_or(OtlpProtocol::Grpc)
+    }
+
+    pub fn sampling_ratio(&self) -> f64 {
+        self.sampling_ratio.unwrap_or(DEFAULT_SAMPLING_RATIO).clamp(0.0, 1.0)
+    }
+
+    pub fn metrics_export_interval(&self) -> Duration {
+        Duration::from_millis(
+            self.metrics_export_interval_millis.unwrap_or(DEFAULT_METRICS_EXPORT_INTERVAL_MILLIS),
+        )
+    }
+
+    pub fn resource(&self, default_service_name: &str) -> Resource {
+        let service_name = self.service_name.as_deref().unwrap_or(default_service_name);
+        let attributes = self
+            .resource_attributes
+            .iter()
+            .map(|(key, value)| KeyValue::new(key.clone(), value.clone()))
+            .chain([KeyValue::new("service.name", service_name.to_string())]);
+        // Configured attributes take precedence over the detected ones
+        Resource::default().merge(&Resource::new(attributes))
+    }
+
+    /// Returns `None` if traces endpoint is not configured.
+    pub fn tracer_provider(
+        &self,
+        default_service_name: &str,
+    ) -> anyhow::Result<Option<TracerProvider>> {
+        let Some(endpoint) = self.traces_endpoint() else {
+            return Ok(None);
+        };
+        let exporter = match self.protocol() {
+            OtlpProtocol::Grpc => opentelemetry_otlp::SpanExporter::builder()
+                .with_tonic()
+                .with_endpoint(endpoint)
+                .with_metadata(self.metadata()?)
+                .build()?,
+            OtlpProtocol::HttpProtobuf => opentelemetry_otlp::SpanExporter::builder()
+                .with_http()
+                .with_endpoint(endpoint)
+                .with_headers(self.headers.clone().into_iter().collect())
+                .build()?,
+        };
+        let sampler =
+            Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(self.sampling_ratio())));
+        Ok(Some(
+            TracerProvider::builder()
+                .with_batch_exporter(exporter, Tokio)
+                .with_sampler(sampler)
+                .with_resource(self.resource(default_service_name))
+                .build(),
+        ))
+    }
+
+    /// Returns `None` if metrics endpoint is not configured. The switch
+    /// controls the export of the returned provider.
+    pub fn meter_provider(
+        &self,
+        default_service_name: &str,
+    ) -> anyhow::Result<Option<(SdkMeterProvider, MetricsExportSwitch)>> {
+        let Some(endpoint) = self.metrics_endpoint() else {
+            return Ok(None);
+        };
+        let exporter = match self.protocol() {
+            OtlpProtocol::Grpc => opentelemetry_otlp::MetricExporter::builder()
+                .with_tonic()
+                .with_endpoint(endpoint)
+                .with_metadata(self.metadata()?)
+                .build()?,
+            OtlpProtocol::HttpProtobuf => opentelemetry_otlp::MetricExporter::builder()
+                .with_http()
+                .with_endpoint(endpoint)
+                .with_headers(self.headers.clone().into_iter().collect())
+                .build()?,
+        };
+        let switch = MetricsExportSwitch::new(self.metrics_enabled);
+        let reader =
+            PeriodicReader::builder(SwitchableExporter::new(exporter, switch.clone()), Tokio)
+                .with_interval(self.metrics_export_interval())
+                .build();
+        let provider = SdkMeterProvider::builder()
+            .with_reader(reader)
+            .with_resource(self.resource(default_service_name))
+            .build();
+        Ok(Some((provider, switch)))
+    }
+
+    fn metadata(&self) -> anyhow::Result<tonic::metadata::MetadataMap> {
+        let mut metadata = tonic::metadata::MetadataMap::new();
+        for (key, value) in &self.headers {
+            let key = tonic::metadata::MetadataKey::from_bytes(key.as_bytes())
+                .with_context(|| format!("Invalid OTLP header name: {key}"))?;
+            let value =
+                value.parse().with_context(|| format!("Invalid OTLP header value of {key}"))?;
+            metadata.insert(key, value);
+        }
+        Ok(metadata)
+    }
+}
+
+// Parses `key1=value1,key2=value2` lists of the OTEL environment variables
+fn parse_key_values(value: &str) -> BTreeMap<String, String> {
+    value
+        .split(',')
+        .filter_map(|pair| {
+            let (key, value) = pair.split_once('=')?;
+            let key = key.trim();
+            (!key.is_empty()).then(|| (key.to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_key_values() {
+        let parsed = parse_key_values("a=1, b = x=y ,,c,=2");
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed["a"], "1");
+        assert_eq!(parsed["b"], "x=y");
+        assert!(parse_key_values("").is_empty());
+    }
+
+    #[test]
+    fn test_env_defaults() {
+        let env = HashMap::from([
+            ("OTEL_EXPORTER_OTLP_ENDPOINT", "http://collector:4317"),
+            ("OTEL_EXPORTER_OTLP_METRICS_ENDPOINT", "http://metrics:4317"),
+            ("OTEL_EXPORTER_OTLP_PROTOCOL", "http/protobuf"),
+            ("OTEL_EXPORTER_OTLP_HEADERS", "authorization=env,x-team=node"),
+            ("OTEL_TRACES_SAMPLER_ARG", "0.25"),
+        ]);
+        let config: TelemetryConfig = serde_yaml::from_str(
+            r#"
+            traces_endpoint: "http://traces:4317"
+            headers:
+              authorization: file
+            "#,
+        )
+        .unwrap();
+        assert!(config.metrics_enabled);
+        let config = config.with_defaults_from(|name| env.get(name).map(|value| value.to_string()));
+        assert_eq!(config.traces_endpoint(), Some("http://traces:4317"));
+        assert_eq!(config.metrics_endpoint(), Some("http://metrics:4317"));
+        assert_eq!(config.protocol(), OtlpProtocol::HttpProtobuf);
+        assert_eq!(config.headers["authorization"], "file");
+        assert_eq!(config.headers["x-team"], "node");
+        assert_eq!(config.sampling_ratio(), 0.25);
+        assert_eq!(config.metrics_export_interval(), Duration::from_secs(60));
+    }
+}
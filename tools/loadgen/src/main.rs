@@ -0,0 +1,243 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use clap::Parser;
+use parking_lot::Mutex;
+use serde_json::json;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tracing_subscriber::EnvFilter;
+use tvm_client::abi::encode_message;
+use tvm_client::abi::Abi;
+use tvm_client::abi::CallSet;
+use tvm_client::abi::ParamsOfEncodeMessage;
+use tvm_client::abi::Signer;
+use tvm_client::ClientConfig;
+use tvm_client::ClientContext;
+
+use crate::stats::Stats;
+use crate::wallets::WalletSet;
+
+mod stats;
+mod wallets;
+
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Generates signed external messages at a target rate and reports how fast
+/// they are accepted and included into blocks
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// External messages endpoint of the message router or the node API,
+    /// e.g. `http://127.0.0.1:11000/bm/v2/messages`
+    #[arg(long, env)]
+    endpoint: url::Url,
+
+    /// JSON file with the wallets, their contract ABI and the called function
+    #[arg(long, env)]
+    wallets: PathBuf,
+
+    /// Messages per second
+    #[arg(long, env, default_value_t = 10.0)]
+    rate: f64,
+
+    #[arg(long, env, default_value_t = 60)]
+    duration_secs: u64,
+
+    /// Messages waiting for the response or inclusion, new messages are
+    /// skipped above it
+    #[arg(long, env, default_value_t = 1000)]
+    max_in_flight: usize,
+
+    /// Thread of the wallets (hex), the default thread if unset
+    #[arg(long, env)]
+    thread_id: Option<String>,
+
+    /// GraphQL endpoint of the block manager. If set, messages are polled
+    /// until they are found in the archive to measure the inclusion latency
+    #[arg(long, env)]
+    graphql_url: Option<url::Url>,
+
+    #[arg(long, env, default_value_t = 120)]
+    inclusion_timeout_secs: u64,
+
+    #[arg(long, env, default_value_t = 500)]
+    poll_interval_ms: u64,
+
+    /// Report file, the report is printed if unset
+    #[arg(long, env)]
+    report: Option<PathBuf>,
+}
+
+struct Generator {
+    args: Args,
+    wallet_set: WalletSet,
+    abi: Abi,
+    context: Arc<ClientContext>,
+    client: reqwest::Client,
+    stats: Mutex<Stats>,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()))
+        .init();
+    let args = Args::parse();
+    anyhow::ensure!(args.rate > 0.0, "Rate must be positive");
+
+    let wallet_set = WalletSet::load(&args.wallets)?;
+    let abi =
+        Abi::Json(std::fs::read_to_string(&wallet_set.abi).map_err(|e| {
+            anyhow::format_err!("Failed to read {}: {e}", wallet_set.abi.display())
+        })?);
+    tracing::info!(
+        "Sending {} messages per second from {} wallets to {} for {}s",
+        args.rate,
+        wallet_set.wallets.len(),
+        args.endpoint,
+        args.duration_secs
+    );
+    let generator = Arc::new(Generator {
+        wallet_set,
+        abi,
+        context: Arc::new(ClientContext::new(ClientConfig::default())?),
+        client: reqwest::Client::builder().timeout(Duration::from_secs(60)).build()?,
+        stats: Mutex::new(Stats::default()),
+        args,
+    });
+
+    let start = Instant::now();
+    let deadline = start + Duration::from_secs(generator.args.duration_secs);
+    let in_flight = Arc::new(Semaphore::new(generator.args.max_in_flight));
+    // Missed ticks are sent in a burst to keep the target rate
+    let mut interval = tokio::time::interval(Duration::from_secs_f64(1.0 / generator.args.rate));
+    let mut tasks = JoinSet::new();
+    let mut last_progress = start;
+    let mut number = 0;
+    while Instant::now() < deadline {
+        interval.tick().await;
+        while tasks.try_join_next().is_some() {}
+        if last_progress.elapsed() >= PROGRESS_INTERVAL {
+            last_progress = Instant::now();
+            let stats = generator.stats.lock();
+            tracing::info!(
+                "Sent {}, accepted {}, included {}, in flight {}",
+                stats.sent,
+                stats.acceptance.len(),
+                stats.inclusion.len(),
+                generator.args.max_in_flight - in_flight.available_permits()
+            );
+        }
+        let Ok(permit) = in_flight.clone().try_acquire_owned() else {
+            generator.stats.lock().skipped += 1;
+            continue;
+        };
+        let generator = generator.clone();
+        tasks.spawn(async move {
+            if let Err(e) = generator.send_message(number).await {
+                tracing::warn!("Message {number} failed: {e}");
+                generator.stats.lock().failed += 1;
+            }
+            drop(permit);
+        });
+        number += 1;
+    }
+    tracing::info!("Waiting for {} messages in flight", tasks.len());
+    while tasks.join_next().await.is_some() {}
+
+    let report = generator.stats.lock().report(
+        start.elapsed(),
+        generator.args.rate,
+        generator.args.graphql_url.is_some(),
+    );
+    let report = serde_json::to_string_pretty(&report)?;
+    match &generator.args.report {
+        Some(path) => std::fs::write(path, report)
+            .map_err(|e| anyhow::format_err!("Failed to write {}: {e}", path.display()))?,
+        None => println!("{report}"),
+    }
+    Ok(())
+}
+
+impl Generator {
+    async fn send_message(&self, number: u64) -> anyhow::Result<()> {
+        let (wallet, input) = self.wallet_set.message(number);
+        let encoded = encode_message(
+            self.context.clone(),
+            ParamsOfEncodeMessage {
+                abi: self.abi.clone(),
+                address: Some(wallet.address.clone()),
+                call_set: CallSet::some_with_function_and_input(&self.wallet_set.function, input),
+                signer: Signer::Keys { keys: wallet.keys.clone() },
+                deploy_set: None,
+                processing_try_index: None,
+                signature_id: None,
+            },
+        )
+        .await
+        .map_err(|e| anyhow::format_err!("Failed to encode message: {e}"))?;
+
+        let mut request = json!({
+            "id": tvm_types::base64_encode(hex::decode(&encoded.message_id)?),
+            "body": encoded.message,
+        });
+        if let Some(thread_id) = &self.args.thread_id {
+            request["thread_id"] = thread_id.clone().into();
+        }
+        let sent = Instant::now();
+        self.stats.lock().sent += 1;
+        let response: serde_json::Value = self
+            .client
+            .post(self.args.endpoint.clone())
+            .json(&json!([request]))
+            .send()
+            .await?
+            .json()
+            .await?;
+        if let Some(error) = response.get("error").filter(|error| !error.is_null()) {
+            let code = error["code"].as_str().unwrap_or("UNKNOWN").to_string();
+            tracing::debug!("Message {} rejected: {error}", encoded.message_id);
+            *self.stats.lock().rejected.entry(code).or_default() += 1;
+            return Ok(());
+        }
+        self.stats.lock().acceptance.record(sent.elapsed());
+
+        let Some(graphql_url) = &self.args.graphql_url else {
+            return Ok(());
+        };
+        let timeout = Duration::from_secs(self.args.inclusion_timeout_secs);
+        while sent.elapsed() < timeout {
+            if self.is_archived(graphql_url, &encoded.message_id).await.unwrap_or_else(|e| {
+                tracing::debug!("Failed to query message {}: {e}", encoded.message_id);
+                false
+            }) {
+                self.stats.lock().inclusion.record(sent.elapsed());
+                return Ok(());
+            }
+            tokio::time::sleep(Duration::from_millis(self.args.poll_interval_ms)).await;
+        }
+        self.stats.lock().not_included += 1;
+        Ok(())
+    }
+
+    async fn is_archived(&self, graphql_url: &url::Url, message_id: &str) -> anyhow::Result<bool> {
+        let response: serde_json::Value = self
+            .client
+            .post(graphql_url.clone())
+            .json(&json!({
+                "query": "query($hash: String!) { blockchain { message(hash: $hash) { id } } }",
+                "variables": { "hash": message_id },
+            }))
+            .send()
+            .await?
+            .json()
+            .await?;
+        Ok(!response["data"]["blockchain"]["message"].is_null())
+    }
+}
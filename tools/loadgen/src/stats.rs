@@ -0,0 +1,145 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// Upper bounds of the histogram buckets, the last bucket is unbounded.
+const BUCKET_BOUNDS_MS: [u64; 13] =
+    [5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10000, 30000, 60000];
+
+#[derive(Default, Debug)]
+pub struct LatencyHistogram {
+    samples_ms: Vec<u64>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct HistogramReport {
+    pub count: usize,
+    pub p50_ms: u64,
+    pub p90_ms: u64,
+    pub p99_ms: u64,
+    pub max_ms: u64,
+    pub buckets: Vec<Bucket>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Bucket {
+    /// Unset for the last bucket
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub le_ms: Option<u64>,
+    pub count: usize,
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, latency: Duration) {
+        self.samples_ms.push(latency.as_millis() as u64);
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples_ms.len()
+    }
+
+    pub fn report(&self) -> HistogramReport {
+        let mut samples = self.samples_ms.clone();
+        samples.sort_unstable();
+        let percentile = |p: f64| {
+            samples.get(((samples.len().max(1) - 1) as f64 * p) as usize).copied().unwrap_or(0)
+        };
+        let mut buckets = BUCKET_BOUNDS_MS
+            .iter()
+            .map(|bound| Bucket { le_ms: Some(*bound), count: 0 })
+            .chain(std::iter::once(Bucket { le_ms: None, count: 0 }))
+            .collect::<Vec<_>>();
+        for sample in &samples {
+            let index = BUCKET_BOUNDS_MS.partition_point(|bound| bound < sample);
+            buckets[index].count += 1;
+        }
+        HistogramReport {
+            count: samples.len(),
+            p50_ms: percentile(0.5),
+            p90_ms: percentile(0.9),
+            p99_ms: percentile(0.99),
+            max_ms: samples.last().copied().unwrap_or(0),
+            buckets,
+        }
+    }
+}
+
+/// Outcomes of the generated messages.
+#[derive(Default, Debug)]
+pub struct Stats {
+    pub sent: u64,
+    /// Not generated because too many messages were in flight
+    pub skipped: u64,
+    /// Failed to encode or submit
+    pub failed: u64,
+    /// Rejected by the node, by the error code
+    pub rejected: BTreeMap<String, u64>,
+    /// From the submission to the response of the accepted message
+    pub acceptance: LatencyHistogram,
+    /// From the submission to the message found in the archive
+    pub inclusion: LatencyHistogram,
+    pub not_included: u64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Report {
+    pub elapsed_secs: f64,
+    pub target_rate: f64,
+    pub achieved_rate: f64,
+    pub sent: u64,
+    pub accepted: usize,
+    pub skipped: u64,
+    pub failed: u64,
+    pub rejected: BTreeMap<String, u64>,
+    pub acceptance_latency: HistogramReport,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inclusion_latency: Option<HistogramReport>,
+    pub not_included: u64,
+}
+
+impl Stats {
+    pub fn report(&self, elapsed: Duration, target_rate: f64, track_inclusion: bool) -> Report {
+        Report {
+            elapsed_secs: elapsed.as_secs_f64(),
+            target_rate,
+            achieved_rate: self.sent as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+            sent: self.sent,
+            accepted: self.acceptance.len(),
+            skipped: self.skipped,
+            failed: self.failed,
+            rejected: self.rejected.clone(),
+            acceptance_latency: self.acceptance.report(),
+            inclusion_latency: track_inclusion.then(|| self.inclusion.report()),
+            not_included: self.not_included,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_histogram() {
+        let mut histogram = LatencyHistogram::default();
+        assert_eq!(histogram.report().count, 0);
+        assert_eq!(histogram.report().p99_ms, 0);
+
+        for ms in 1..=100 {
+            histogram.record(Duration::from_millis(ms));
+        }
+        histogram.record(Duration::from_secs(120));
+        let report = histogram.report();
+        assert_eq!(report.count, 101);
+        assert_eq!(report.p50_ms, 51);
+        assert_eq!(report.p90_ms, 91);
+        assert_eq!(report.max_ms, 120000);
+        assert_eq!(report.buckets[0], Bucket { le_ms: Some(5), count: 5 });
+        assert_eq!(report.buckets[4], Bucket { le_ms: Some(100), count: 50 });
+        assert_eq!(report.buckets[13], Bucket { le_ms: None, count: 1 });
+    }
+}
@@ -0,0 +1,95 @@
+// 2022-2025 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
+//
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use tvm_client::crypto::KeyPair;
+
+const DEST_PLACEHOLDER: &str = "$dest";
+const NUMBER_PLACEHOLDER: &str = "$n";
+
+/// Wallets of the same contract the messages are sent from.
+#[derive(Deserialize, Debug)]
+pub struct WalletSet {
+    /// Contract ABI, relative to the wallet set file
+    pub abi: PathBuf,
+    /// Function called by each message
+    pub function: String,
+    /// Function input. String values `$dest` are replaced with the address
+    /// of the next wallet of the set and `$n` with the message number.
+    #[serde(default)]
+    pub input: serde_json::Value,
+    pub wallets: Vec<Wallet>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Wallet {
+    pub address: String,
+    pub keys: KeyPair,
+}
+
+impl WalletSet {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::format_err!("Failed to read {}: {e}", path.display()))?;
+        let mut wallet_set: Self = serde_json::from_str(&data)
+            .map_err(|e| anyhow::format_err!("Failed to parse {}: {e}", path.display()))?;
+        anyhow::ensure!(!wallet_set.wallets.is_empty(), "Wallet set is empty");
+        if let Some(dir) = path.parent() {
+            wallet_set.abi = dir.join(&wallet_set.abi);
+        }
+        Ok(wallet_set)
+    }
+
+    /// Wallet sending the message and the function input.
+    pub fn message(&self, number: u64) -> (&Wallet, serde_json::Value) {
+        let index = (number % self.wallets.len() as u64) as usize;
+        let dest = &self.wallets[(index + 1) % self.wallets.len()].address;
+        (&self.wallets[index], substitute(&self.input, dest, number))
+    }
+}
+
+fn substitute(value: &serde_json::Value, dest: &str, number: u64) -> serde_json::Value {
+    match value {
+        serde_json::Value::String(s) if s == DEST_PLACEHOLDER => dest.into(),
+        serde_json::Value::String(s) if s == NUMBER_PLACEHOLDER => number.to_string().into(),
+        serde_json::Value::Array(values) => {
+            values.iter().map(|value| substitute(value, dest, number)).collect()
+        }
+        serde_json::Value::Object(fields) => fields
+            .iter()
+            .map(|(key, value)| (key.clone(), substitute(value, dest, number)))
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+        value => value.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_wallet_set_messages() {
+        let wallet = |address: &str| Wallet {
+            address: address.to_string(),
+            keys: KeyPair::new(String::new(), String::new()),
+        };
+        let wallet_set = WalletSet {
+            abi: PathBuf::new(),
+            function: "sendTransaction".to_string(),
+            input: json!({ "dest": "$dest", "value": 1, "payload": ["$n", "x"] }),
+            wallets: vec![wallet("0:01"), wallet("0:02")],
+        };
+        let (sender, input) = wallet_set.message(0);
+        assert_eq!(sender.address, "0:01");
+        assert_eq!(input, json!({ "dest": "0:02", "value": 1, "payload": ["0", "x"] }));
+        let (sender, input) = wallet_set.message(3);
+        assert_eq!(sender.address, "0:02");
+        assert_eq!(input, json!({ "dest": "0:01", "value": 1, "payload": ["3", "x"] }));
+    }
+}
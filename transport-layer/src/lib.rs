@@ -3,6 +3,9 @@
 
 use std::collections::HashSet;
 use std::fmt::Display;
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+use std::net::Ipv6Addr;
 use std::net::SocketAddr;
 use std::time::Duration;
 
@@ -34,6 +37,19 @@ mod tls;
 mod utils;
 pub mod wtransport;
 
+/// Returns the local IP the OS currently routes packets to the remote through.
+/// No packets are sent.
+pub fn route_local_ip(remote: SocketAddr) -> std::io::Result<IpAddr> {
+    let bind: SocketAddr = if remote.is_ipv4() {
+        (Ipv4Addr::UNSPECIFIED, 0).into()
+    } else {
+        (Ipv6Addr::UNSPECIFIED, 0).into()
+    };
+    let socket = std::net::UdpSocket::bind(bind)?;
+    socket.connect(remote)?;
+    Ok(socket.local_addr()?.ip())
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Hash, Eq)]
 pub struct CertHash(pub [u8; 32]);
 
@@ -55,6 +71,8 @@ pub struct NetCredential {
     pub my_certs: Vec<CertificateDer<'static>>,
     pub trusted_cert_hashes: HashSet<CertHash>,
     pub trusted_ed_pubkeys: HashSet<VerifyingKey>,
+    /// When set, an empty trust list rejects every peer instead of accepting any.
+    pub reject_unknown_peers: bool,
 }
 
 impl NetCredential {
@@ -68,6 +86,7 @@ impl NetCredential {
             my_certs: vec![my_cert],
             trusted_cert_hashes: HashSet::new(),
             trusted_ed_pubkeys: HashSet::new(),
+            reject_unknown_peers: false,
         })
     }
 
@@ -87,16 +106,31 @@ impl NetCredential {
         }
     }
 
-    pub fn any_cert_are_trusted(&self) -> bool {
+    fn trust_list_is_empty(&self) -> bool {
         self.trusted_cert_hashes.is_empty() && self.trusted_ed_pubkeys.is_empty()
     }
 
+    pub fn any_cert_are_trusted(&self) -> bool {
+        !self.reject_unknown_peers && self.trust_list_is_empty()
+    }
+
+    pub fn no_cert_are_trusted(&self) -> bool {
+        self.reject_unknown_peers && self.trust_list_is_empty()
+    }
+
     pub fn verify_is_valid_cert(&self, cert: &CertificateDer<'static>) -> bool {
+        if self.no_cert_are_trusted() {
+            tracing::warn!("TLS certificate rejected: trust list is empty");
+            return false;
+        }
         self.any_cert_are_trusted()
             || verify_is_valid_cert(cert, &self.trusted_cert_hashes, &self.trusted_ed_pubkeys)
     }
 
     pub fn is_trusted(&self, cert_hash: &CertHash, ed_pubkey: &Option<VerifyingKey>) -> bool {
+        if self.no_cert_are_trusted() {
+            return false;
+        }
         self.any_cert_are_trusted()
             || verify_cert_or_ed_pubkey_is_trusted(
                 cert_hash,
@@ -114,6 +148,7 @@ impl Clone for NetCredential {
             my_certs: self.my_certs.clone(),
             trusted_cert_hashes: self.trusted_cert_hashes.clone(),
             trusted_ed_pubkeys: self.trusted_ed_pubkeys.clone(),
+            reject_unknown_peers: self.reject_unknown_peers,
         }
     }
 }
@@ -162,6 +197,13 @@ pub trait NetConnection: Clone + Send + Sync {
     fn alpn_negotiated_is(&self, protocol: &str) -> bool {
         self.alpn_negotiated().as_ref().map(|x| x == protocol).unwrap_or_default()
     }
+    // Returns smoothed round trip time measured by the transport
+    fn rtt(&self) -> Option<Duration>;
+    // Moves the connection to the new local address keeping its streams
+    // (QUIC connection migration). Port 0 means any free port.
+    fn migrate(&self, _local_addr: SocketAddr) -> anyhow::Result<()> {
+        anyhow::bail!("Connection migration is not supported by the transport")
+    }
     async fn send(&self, data: &[u8]) -> anyhow::Result<()>;
     async fn recv(&self) -> anyhow::Result<(Vec<u8>, Duration)>;
     async fn close(&self, code: usize);
@@ -214,6 +214,15 @@ impl NetConnection for MsQuicNetConnection {
         self.inner.get_negotiated_alpn()
     }
 
+    fn rtt(&self) -> Option<Duration> {
+        self.inner.get_rtt()
+    }
+
+    fn migrate(&self, local_addr: SocketAddr) -> anyhow::Result<()> {
+        self.inner.set_local_addr(local_addr)?;
+        Ok(())
+    }
+
     async fn send(&self, bytes: &[u8]) -> anyhow::Result<()> {
         let mut stream = self.stream_pool.acquire_send(self).await?;
         let result = if let Some(stream) = stream.as_mut() {
@@ -8,6 +8,7 @@ use std::sync::Mutex;
 use std::task::Context;
 use std::task::Poll;
 use std::task::Waker;
+use std::time::Duration;
 
 use bytes::Bytes;
 use libc::c_void;
@@ -438,6 +439,16 @@ impl Connection {
             .map_err(ConnectionError::OtherError)
     }
 
+    /// Set the local address of the connection. For a client connection
+    /// msquic migrates the connection to the new path: the path is validated
+    /// and the peer continues the connection on it.
+    pub fn set_local_addr(&self, addr: SocketAddr) -> Result<(), ConnectionError> {
+        self.0
+            .msquic_conn
+            .set_local_addr(&msquic::Addr::from(addr))
+            .map_err(ConnectionError::OtherError)
+    }
+
     /// Get the remote address of the connection.
     pub fn get_remote_addr(&self) -> Result<SocketAddr, ConnectionError> {
         self.0
@@ -456,6 +467,11 @@ impl Connection {
     pub fn get_remote_certificate(&self) -> Option<CertificateDer<'static>> {
         self.0.exclusive.lock().unwrap().certificate.clone()
     }
+
+    /// Get the smoothed round trip time of the connection.
+    pub fn get_rtt(&self) -> Option<Duration> {
+        self.0.msquic_conn.get_stats_v2().ok().map(|stats| Duration::from_micros(stats.Rtt as u64))
+    }
 }
 
 struct ConnectionInstance {
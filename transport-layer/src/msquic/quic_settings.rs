@@ -45,6 +45,9 @@ impl ConfigFactory {
             .set_StreamRecvWindowDefault(268_435_456)
             .set_ConnFlowControlWindow(2_147_483_648)
             .set_SendBufferingEnabled()
+            // Peers changing their address (e.g. producer failover link) keep
+            // the connection instead of reconnecting
+            .set_MigrationEnabled()
     }
 
     pub(crate) fn build_credential(
@@ -359,6 +359,7 @@ mod unit_tests {
             my_certs: vec![cert],
             trusted_cert_hashes: HashSet::new(),
             trusted_ed_pubkeys: HashSet::new(),
+            reject_unknown_peers: false,
         };
         let reg = Registration::new(&RegistrationConfig::default()).unwrap();
         let alpn = ["qtest"];
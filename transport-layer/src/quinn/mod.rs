@@ -157,6 +157,10 @@ impl NetConnection for QuinnConnection {
             .map(|p| String::from_utf8_lossy(p).into_owned())
     }
 
+    fn rtt(&self) -> Option<Duration> {
+        Some(self.inner.rtt())
+    }
+
     async fn send(&self, data: &[u8]) -> anyhow::Result<()> {
         let mut stream = self.stream_pool.acquire_send(self).await?;
         let result = if let Some(stream) = stream.as_mut() {
@@ -1,9 +1,14 @@
 // 2022-2024 (c) Copyright Contributors to the GOSH DAO. All rights reserved.
 //
 
+use std::collections::HashSet;
 use std::net::SocketAddr;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
+use serde::Deserialize;
+use serde::Serialize;
 use telemetry_utils::mpsc::InstrumentedReceiver;
 
 use crate::msquic::MsQuicNetIncomingRequest;
@@ -16,6 +21,43 @@ use crate::NetTransport;
 
 const DEFAULT_BROADCAST_CAPACITY: usize = 10;
 
+/// Sent by a client after the connection is established, and again at any
+/// time to replace the previous filter. The server then sends only the blocks
+/// with transactions of the listed accounts. An empty list means all blocks.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubscriptionFilter {
+    pub accounts: HashSet<String>,
+}
+
+impl SubscriptionFilter {
+    pub fn new(accounts: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        Self { accounts: accounts.into_iter().map(|x| normalize_account(x.as_ref())).collect() }
+    }
+
+    fn matches(&self, block_accounts: Option<&HashSet<String>>) -> bool {
+        match block_accounts {
+            _ if self.accounts.is_empty() => true,
+            Some(block_accounts) => !self.accounts.is_disjoint(block_accounts),
+            // Blocks that couldn't be parsed are sent to everyone
+            None => true,
+        }
+    }
+}
+
+/// Account id as lowercase hex without the workchain prefix.
+pub fn normalize_account(account: &str) -> String {
+    let account = account.trim();
+    let account = account.rsplit_once(':').map_or(account, |(_, id)| id);
+    account.to_lowercase()
+}
+
+#[derive(Debug)]
+struct OutgoingBlock {
+    data: Vec<u8>,
+    // Resolved only while there are filtered subscriptions
+    accounts: Option<HashSet<String>>,
+}
+
 #[derive(Debug, Clone)]
 pub struct LiteServer {
     pub bind: SocketAddr,
@@ -26,13 +68,17 @@ impl LiteServer {
         Self { bind }
     }
 
-    pub async fn start<TBPResolver, A>(
+    /// `accounts_resolver` returns normalized ids of the accounts with
+    /// transactions in a raw block, it's used for the subscription filters.
+    pub async fn start<TBPResolver, TAccountsResolver, A>(
         self,
         raw_block_receiver: InstrumentedReceiver<(A, Vec<u8>)>,
         bp_resolver: TBPResolver,
+        accounts_resolver: TAccountsResolver,
     ) -> anyhow::Result<()>
     where
         TBPResolver: Send + Sync + Clone + 'static + FnMut(A) -> Option<String>,
+        TAccountsResolver: Send + 'static + FnMut(&[u8]) -> Option<HashSet<String>>,
         A: Send + 'static,
     {
         let (incoming_request_tx, incoming_request_rx) =
@@ -40,11 +86,14 @@ impl LiteServer {
         let (outgoing_message_tx, _ /* we will subscribe() later */) =
             tokio::sync::broadcast::channel(DEFAULT_BROADCAST_CAPACITY);
 
+        let filtered_connections = Arc::new(AtomicUsize::new(0));
+
         let listener_task = tokio::spawn(listener_handler(self.bind, incoming_request_tx));
 
         let incoming_requests_task = tokio::spawn(incoming_requests_handler(
             incoming_request_rx,
             outgoing_message_tx.clone(),
+            filtered_connections.clone(),
         ));
 
         let multiplexer_task = tokio::task::spawn_blocking(move || {
@@ -52,6 +101,8 @@ impl LiteServer {
                 raw_block_receiver,
                 outgoing_message_tx.clone(),
                 bp_resolver,
+                accounts_resolver,
+                filtered_connections,
             )
         });
 
@@ -90,7 +141,8 @@ async fn listener_handler(
 }
 async fn incoming_requests_handler(
     mut incoming_request_rx: tokio::sync::mpsc::UnboundedReceiver<MsQuicNetIncomingRequest>,
-    outgoing_message_tx: tokio::sync::broadcast::Sender<Arc<Vec<u8>>>,
+    outgoing_message_tx: tokio::sync::broadcast::Sender<Arc<OutgoingBlock>>,
+    filtered_connections: Arc<AtomicUsize>,
 ) -> anyhow::Result<()> {
     loop {
         match incoming_request_rx.recv().await {
@@ -98,6 +150,7 @@ async fn incoming_requests_handler(
                 tokio::spawn(connection_supervisor(
                     incoming_request,
                     outgoing_message_tx.subscribe(),
+                    filtered_connections.clone(),
                 ));
             }
             None => {
@@ -109,26 +162,96 @@ async fn incoming_requests_handler(
 
 async fn connection_supervisor(
     incoming_request: MsQuicNetIncomingRequest,
-    outgoing_message_rx: tokio::sync::broadcast::Receiver<Arc<Vec<u8>>>,
+    outgoing_message_rx: tokio::sync::broadcast::Receiver<Arc<OutgoingBlock>>,
+    filtered_connections: Arc<AtomicUsize>,
+) {
+    let result =
+        connection_handler(incoming_request, outgoing_message_rx, filtered_connections).await;
+    if let Err(err) = result {
+        tracing::error!("Connection handler failed: {err}");
+    }
+}
+
+// Counts the connection as filtered while its filter is not empty
+struct FilteredConnectionGuard {
+    filtered_connections: Arc<AtomicUsize>,
+    is_filtered: bool,
+}
+
+impl FilteredConnectionGuard {
+    fn set(&mut self, is_filtered: bool) {
+        if self.is_filtered == is_filtered {
+            return;
+        }
+        if is_filtered {
+            self.filtered_connections.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.filtered_connections.fetch_sub(1, Ordering::Relaxed);
+        }
+        self.is_filtered = is_filtered;
+    }
+}
+
+impl Drop for FilteredConnectionGuard {
+    fn drop(&mut self) {
+        self.set(false);
+    }
+}
+
+async fn filter_receiver<C: NetConnection>(
+    connection: C,
+    filter_tx: tokio::sync::watch::Sender<SubscriptionFilter>,
+    filtered_connections: Arc<AtomicUsize>,
 ) {
-    match connection_handler(incoming_request, outgoing_message_rx).await {
-        Ok(_) => {}
-        Err(err) => {
-            tracing::error!("Connection handler failed: {err}");
+    let peer = connection.remote_addr().to_string();
+    let mut filtered = FilteredConnectionGuard { filtered_connections, is_filtered: false };
+    loop {
+        let data = match connection.recv().await {
+            Ok((data, _)) => data,
+            Err(err) => {
+                tracing::debug!("Stop receiving subscription filters from {peer}: {err}");
+                return;
+            }
+        };
+        match bincode::deserialize::<SubscriptionFilter>(&data) {
+            Ok(filter) => {
+                tracing::info!("Subscription filter of {peer}: {} accounts", filter.accounts.len());
+                let filter = SubscriptionFilter::new(filter.accounts);
+                filtered.set(!filter.accounts.is_empty());
+                if filter_tx.send(filter).is_err() {
+                    return;
+                }
+            }
+            Err(err) => tracing::warn!("Invalid subscription filter from {peer}: {err}"),
         }
     }
 }
 
 async fn connection_handler(
     incoming_request: MsQuicNetIncomingRequest,
-    mut outgoing_message_rx: tokio::sync::broadcast::Receiver<Arc<Vec<u8>>>,
+    outgoing_message_rx: tokio::sync::broadcast::Receiver<Arc<OutgoingBlock>>,
+    filtered_connections: Arc<AtomicUsize>,
 ) -> anyhow::Result<()> {
     tracing::info!("Establishing connection");
     let connection = incoming_request.accept().await?;
     tracing::info!(remote_addr = connection.remote_addr().to_string(), "Connection established");
+    let (filter_tx, filter_rx) = tokio::sync::watch::channel(SubscriptionFilter::default());
+    // Filters are read in a separate task: recv is not cancel safe
+    let filter_task =
+        tokio::spawn(filter_receiver(connection.clone(), filter_tx, filtered_connections));
+    let result = send_blocks(&connection, outgoing_message_rx, filter_rx).await;
+    filter_task.abort();
+    result
+}
+
+async fn send_blocks<C: NetConnection>(
+    connection: &C,
+    mut outgoing_message_rx: tokio::sync::broadcast::Receiver<Arc<OutgoingBlock>>,
+    mut filter_rx: tokio::sync::watch::Receiver<SubscriptionFilter>,
+) -> anyhow::Result<()> {
     loop {
-        let data = match outgoing_message_rx.recv().await {
-            Ok(data) => data,
+        let block = match outgoing_message_rx.recv().await {
+            Ok(block) => block,
             Err(tokio::sync::broadcast::error::RecvError::Lagged(lagged)) => {
                 anyhow::bail!(
                     "Connection handler failed: outgoing message receiver lagged by {} messages",
@@ -139,9 +262,13 @@ async fn connection_handler(
                 anyhow::bail!("Connection handler failed: outgoing message receiver was closed");
             }
         };
+        if !filter_rx.borrow_and_update().matches(block.accounts.as_ref()) {
+            continue;
+        }
+        let data = &block.data;
         let peer = connection.remote_addr().to_string();
         tracing::trace!("Received {} bytes for {peer}", data.len());
-        match connection.send(&data).await {
+        match connection.send(data).await {
             Ok(_) => {
                 tracing::info!("Sent {} bytes to {peer}", data.len())
             }
@@ -153,13 +280,16 @@ async fn connection_handler(
     }
 }
 
-fn message_multiplexor_handler<TBKAddrResolver, A>(
+fn message_multiplexor_handler<TBKAddrResolver, TAccountsResolver, A>(
     incoming_message_rx: InstrumentedReceiver<(A, Vec<u8>)>,
-    outgoing_message_tx: tokio::sync::broadcast::Sender<Arc<Vec<u8>>>,
+    outgoing_message_tx: tokio::sync::broadcast::Sender<Arc<OutgoingBlock>>,
     mut bp_resolver: TBKAddrResolver,
+    mut accounts_resolver: TAccountsResolver,
+    filtered_connections: Arc<AtomicUsize>,
 ) -> anyhow::Result<()>
 where
     TBKAddrResolver: Send + Sync + Clone + 'static + FnMut(A) -> Option<String>,
+    TAccountsResolver: FnMut(&[u8]) -> Option<HashSet<String>>,
     A: Send,
 {
     tracing::info!("Message multiplexor started");
@@ -173,7 +303,14 @@ where
             "Received message for broadcast"
         );
         let node_addr = bp_resolver(node_id);
-        match outgoing_message_tx.send(Arc::new(bincode::serialize(&(node_addr, message))?)) {
+        // Parsing the block is skipped while nobody filters
+        let accounts = if filtered_connections.load(Ordering::Relaxed) > 0 {
+            accounts_resolver(&message)
+        } else {
+            None
+        };
+        let data = bincode::serialize(&(node_addr, message))?;
+        match outgoing_message_tx.send(Arc::new(OutgoingBlock { data, accounts })) {
             Ok(number_subscribers) => {
                 tracing::info!("Message forwarded to {} broadcast senders", number_subscribers);
             }
@@ -183,3 +320,20 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subscription_filter() {
+        assert_eq!(normalize_account("0:ABcd"), "abcd");
+        assert_eq!(normalize_account(" abcd "), "abcd");
+
+        let block_accounts = HashSet::from(["abcd".to_string(), "ef01".to_string()]);
+        assert!(SubscriptionFilter::default().matches(Some(&block_accounts)));
+        assert!(SubscriptionFilter::new(["0:EF01"]).matches(Some(&block_accounts)));
+        assert!(!SubscriptionFilter::new(["0:1234"]).matches(Some(&block_accounts)));
+        assert!(SubscriptionFilter::new(["0:1234"]).matches(None));
+    }
+}
@@ -498,3 +498,19 @@ fn test_cert_validation() {
         &HashSet::from_iter([ed_key.verifying_key()].into_iter())
     ));
 }
+
+#[test]
+fn test_cert_validation_with_reject_unknown_peers() {
+    let ed_key = ed25519_dalek::SigningKey::generate(&mut rand::thread_rng());
+    let mut credential = NetCredential::generate_self_signed(None, None).unwrap();
+    let (_key, cert) = generate_self_signed_cert(None, Some(ed_key.clone())).unwrap();
+
+    assert!(credential.verify_is_valid_cert(&cert));
+
+    credential.reject_unknown_peers = true;
+    assert!(!credential.verify_is_valid_cert(&cert));
+    assert!(!credential.is_trusted(&CertHash::from(&cert), &Some(ed_key.verifying_key())));
+
+    credential.trusted_ed_pubkeys.insert(ed_key.verifying_key());
+    assert!(credential.verify_is_valid_cert(&cert));
+}
@@ -213,6 +213,10 @@ impl NetConnection for WTransportConnection {
         self.alpn_negotiated.clone()
     }
 
+    fn rtt(&self) -> Option<Duration> {
+        Some(self.connection.rtt())
+    }
+
     async fn send(&self, data: &[u8]) -> anyhow::Result<()> {
         let mut stream = self.connection.open_uni().await?.await?;
         stream.write_all(data).await?;